@@ -0,0 +1,89 @@
+// compares the two ways of splitting a bsp tree, SplitStrategy::Median and SplitStrategy::Sah: how long each takes to
+// build, how long ray queries against it take, and its TreeQuality; run with `cargo bench --features test-util`
+//
+// it's a plain main rather than a harness, timing with Instant, since the crate doesn't pull in a benchmarking crate
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use pof::collision_trees::{SplitStrategy, TreeQuality};
+use pof::samples::{sample_capital, sample_fighter};
+use pof::test_util::Rng;
+use pof::tolerance::TolerancePolicy;
+use pof::{BspData, Model, SubObject};
+
+const BUILDS: usize = 5;
+const RAYS: usize = 20_000;
+
+struct Timing {
+    build: Duration,
+    rays: Duration,
+    hits: usize,
+    sah_cost: f32,
+    mean_leaf_depth: f32,
+}
+
+fn run(model: &Model, strategy: SplitStrategy) -> Timing {
+    let policy = TolerancePolicy::default();
+    let polygons = |subobj: &SubObject| subobj.bsp_data.collision_tree.leaves().map(|(_, poly)| poly.clone()).collect::<Vec<_>>();
+    let inputs = model
+        .sub_objects
+        .iter()
+        .map(|subobj| (&subobj.bsp_data.verts, polygons(subobj)))
+        .collect::<Vec<_>>();
+
+    let start = Instant::now();
+    let mut trees = vec![];
+    for _ in 0..BUILDS {
+        trees = (inputs.iter())
+            .map(|(verts, polygons)| BspData::recalculate_with_strategy(verts, polygons.iter().cloned(), strategy, &policy))
+            .collect();
+        black_box(&trees);
+    }
+    let build = start.elapsed() / BUILDS as u32;
+
+    // the same rays for both strategies, from around each subobject towards somewhere near its middle
+    let mut rng = Rng::new(0);
+    let start = Instant::now();
+    let mut hits = 0;
+    for (tree, (verts, _)) in trees.iter().zip(&inputs) {
+        let radius = verts.iter().map(|vert| vert.magnitude()).fold(0.0, f32::max).max(1.0);
+        for _ in 0..RAYS / inputs.len().max(1) {
+            let origin = rng.vec3d(radius * 2.0);
+            let direction = rng.vec3d(radius * 0.25) - origin;
+            hits += black_box(tree.ray_intersect(origin, direction, verts)).is_some() as usize;
+        }
+    }
+    let rays = start.elapsed();
+
+    let qualities = trees.iter().map(|tree| tree.quality()).collect::<Vec<_>>();
+    let mean = |value: fn(&TreeQuality) -> f32| qualities.iter().map(value).sum::<f32>() / qualities.len().max(1) as f32;
+    Timing {
+        build,
+        rays,
+        hits,
+        sah_cost: mean(|quality| quality.sah_cost),
+        mean_leaf_depth: mean(|quality| quality.mean_leaf_depth),
+    }
+}
+
+fn main() {
+    let models = [
+        ("fighter", sample_fighter(0)),
+        ("capital 4x2000", sample_capital(4, 2000, 0)),
+        ("capital 40x200", sample_capital(40, 200, 0)),
+    ];
+    println!("{:<16} {:<8} {:>12} {:>12} {:>8} {:>10} {:>10}", "model", "split", "build", "rays", "hits", "sah cost", "leaf depth");
+    for (name, model) in &models {
+        let mut hits = vec![];
+        for (label, strategy) in [("median", SplitStrategy::Median), ("sah", SplitStrategy::Sah)] {
+            let timing = run(model, strategy);
+            println!(
+                "{:<16} {:<8} {:>12.2?} {:>12.2?} {:>8} {:>10.2} {:>10.2}",
+                name, label, timing.build, timing.rays, timing.hits, timing.sah_cost, timing.mean_leaf_depth
+            );
+            hits.push(timing.hits);
+        }
+        // both trees hold the same polygons, so the same rays hit, or one of them is broken and its timings mean nothing
+        assert!(hits.windows(2).all(|pair| pair[0] == pair[1]), "{}: {:?}", name, hits);
+    }
+}
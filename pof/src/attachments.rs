@@ -0,0 +1,175 @@
+// named points with a full orientation, for scripts and engine features which need to attach things to a model
+// special points have no orientation and empty subobjects are heavyweight, so these are kept separately
+//
+// the engine doesn't know about them yet, so they're saved in the tool metadata chunk, see crate::tool_metadata;
+// WriteOptions::attachments_as_special_points also writes them as special points, with their orientation in "$fvec" and
+// "$uvec" properties, for engines which read those
+use std::io::{self, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+use glm::TMat4;
+use nalgebra_glm as glm;
+
+use crate::tool_metadata::{read_string, write_string};
+use crate::{Dock, Model, NormalVec3, ObjectId, SpecialPoint, Vec3d};
+
+#[derive(Debug, Clone, Default)]
+pub struct AttachmentPoint {
+    pub name: String,
+    // like eye points, the position is relative to the parent subobject if there is one
+    pub parent: Option<ObjectId>,
+    pub position: Vec3d,
+    pub fvec: NormalVec3,
+    pub uvec: NormalVec3,
+}
+impl AttachmentPoint {
+    pub fn apply_transform(&mut self, matrix: &TMat4<f32>) {
+        self.position = matrix * self.position;
+        self.fvec.apply_rotation(matrix);
+        self.uvec.apply_rotation(matrix);
+        // a non-uniform scale can skew them
+        self.uvec = Dock::orthonormalize(&self.uvec.0.into(), &self.fvec.0.into());
+    }
+
+    // the frame can't be used if the vectors are parallel, or not normalized
+    pub fn has_degenerate_frame(&self) -> bool {
+        let unit = |vec: Vec3d| (vec.magnitude() - 1.0).abs() <= 0.001;
+        !unit(self.fvec.0) || !unit(self.uvec.0) || self.fvec.0.dot(&self.uvec.0).abs() > 0.001
+    }
+}
+
+fn format_vec(vec: Vec3d) -> String {
+    format!("{},{},{}", vec.x, vec.y, vec.z)
+}
+
+impl Model {
+    // the attachments as special points in model space, with their orientation in "$fvec" and "$uvec" properties
+    pub fn attachments_as_special_points(&self) -> Vec<SpecialPoint> {
+        (self.attachments.iter())
+            .map(|attachment| SpecialPoint {
+                name: attachment.name.clone(),
+                properties: format!("$attachment\n$fvec={}\n$uvec={}", format_vec(attachment.fvec.0), format_vec(attachment.uvec.0)),
+                position: attachment.position + attachment.parent.map_or(Vec3d::ZERO, |id| self.get_total_subobj_offset(id)),
+                radius: 1.0,
+            })
+            .collect()
+    }
+}
+
+pub(crate) fn write_attachments(w: &mut impl Write, attachments: &[AttachmentPoint]) -> io::Result<()> {
+    w.write_u32::<LE>(attachments.len() as u32)?;
+    for attachment in attachments {
+        write_string(w, &attachment.name)?;
+        w.write_u32::<LE>(attachment.parent.map_or(u32::MAX, |id| id.0))?;
+        for vec in [attachment.position, attachment.fvec.0, attachment.uvec.0] {
+            for val in [vec.x, vec.y, vec.z] {
+                w.write_f32::<LE>(val)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_attachments(mut buf: &[u8]) -> io::Result<Vec<AttachmentPoint>> {
+    let buf = &mut buf;
+    let count = buf.read_u32::<LE>()?;
+    let mut attachments = vec![];
+    for _ in 0..count {
+        let name = read_string(buf)?;
+        let parent = Some(buf.read_u32::<LE>()?).filter(|&id| id != u32::MAX).map(ObjectId);
+        let mut read_vec = || -> io::Result<Vec3d> { Ok(Vec3d::new(buf.read_f32::<LE>()?, buf.read_f32::<LE>()?, buf.read_f32::<LE>()?)) };
+        attachments.push(AttachmentPoint {
+            name,
+            parent,
+            position: read_vec()?,
+            fvec: NormalVec3(read_vec()?),
+            uvec: NormalVec3(read_vec()?),
+        });
+    }
+    Ok(attachments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::write::WriteOptions;
+    use crate::{Set, Warning};
+
+    fn fighter_with_attachments() -> Model {
+        let mut fighter = sample_fighter(0);
+        let (up, forward) = (NormalVec3(Vec3d::new(0.0, 1.0, 0.0)), NormalVec3(Vec3d::new(0.0, 0.0, 1.0)));
+        let attachment = |name: &str, parent: Option<ObjectId>, position: Vec3d, fvec: NormalVec3, uvec: NormalVec3| AttachmentPoint {
+            name: name.to_string(),
+            parent,
+            position,
+            fvec,
+            uvec,
+        };
+        fighter.attachments = vec![
+            attachment("muzzle", Some(ObjectId(2)), Vec3d::new(0.0, 0.0, 1.0), forward, up),
+            attachment("antenna", None, Vec3d::new(1.0, 2.0, 3.0), up, forward),
+        ];
+        fighter.recheck_warnings(Set::All);
+        fighter
+    }
+
+    fn frames(model: &Model) -> Vec<(String, Option<ObjectId>, [Vec3d; 3])> {
+        (model.attachments.iter())
+            .map(|attachment| (attachment.name.clone(), attachment.parent, [attachment.position, attachment.fvec.0, attachment.uvec.0]))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let fighter = fighter_with_attachments();
+        let parsed = Model::from_bytes(&fighter.write_to_vec().unwrap()).unwrap();
+        assert_eq!(frames(&parsed), frames(&fighter));
+        assert_eq!(parsed.special_points.len(), fighter.special_points.len());
+
+        // and as special points, for engines which read the orientation from properties
+        let mut buf = vec![];
+        let options = WriteOptions { attachments_as_special_points: true, ..Default::default() };
+        fighter.write_with(&mut buf, &options).unwrap();
+        let parsed = Model::from_bytes(&buf).unwrap();
+        assert_eq!(frames(&parsed), frames(&fighter));
+        let muzzle = parsed.special_points.iter().find(|point| point.name == "$muzzle").unwrap();
+        assert_eq!(muzzle.properties, "$attachment\n$fvec=0,0,1\n$uvec=0,1,0");
+        assert_eq!(muzzle.position, fighter.get_total_subobj_offset(ObjectId(2)) + Vec3d::new(0.0, 0.0, 1.0));
+
+        // a truncated section is dropped, not misread
+        let mut section = vec![];
+        write_attachments(&mut section, &fighter.attachments).unwrap();
+        assert_eq!(read_attachments(&section).unwrap().len(), 2);
+        assert!(read_attachments(&section[..section.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn transforms_keep_frames_orthonormal() {
+        let mut fighter = fighter_with_attachments();
+        fighter
+            .apply_transform(&glm::rotation(std::f32::consts::FRAC_PI_2, &glm::vec3(0.0, 1.0, 0.0)))
+            .unwrap();
+        let antenna = &fighter.attachments[1];
+        assert!((antenna.position - Vec3d::new(3.0, 2.0, -1.0)).magnitude() < 1e-5);
+        assert!((antenna.uvec.0 - Vec3d::new(1.0, 0.0, 0.0)).magnitude() < 1e-5);
+
+        // a skewing scale is straightened out
+        fighter.attachments[0].uvec = NormalVec3(Vec3d::new(1.0, 1.0, 0.0).normalize());
+        fighter.attachments[0].apply_transform(&glm::scaling(&glm::vec3(1.0, 3.0, 0.5)));
+        assert!(!fighter.attachments[0].has_degenerate_frame());
+    }
+
+    #[test]
+    fn validation() {
+        let mut fighter = fighter_with_attachments();
+        assert!(fighter.warnings.is_empty(), "{:?}", fighter.warnings);
+
+        fighter.attachments[1].name = "muzzle".to_string();
+        fighter.attachments[1].uvec = fighter.attachments[1].fvec;
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::DuplicateAttachmentName("muzzle".to_string())));
+        assert!(fighter.warnings.contains(&Warning::DegenerateAttachmentFrame(1)));
+        assert!(!fighter.warnings.contains(&Warning::DegenerateAttachmentFrame(0)));
+    }
+}
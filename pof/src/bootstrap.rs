@@ -0,0 +1,306 @@
+// fills in the bare minimum a geometry-only import needs to work in game: an eye point, an attack path,
+// and for big ships a docking bay
+// everything here is guessed from the hull's shape, so all of it is listed in the report for the user to review
+use crate::spatial::SpatialHash;
+use crate::{properties_update_field, BoundingBox, Dock, EyePoint, Model, NormalVec3, Path, PathId, PathPoint, Vec3d};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShipClassHint {
+    Fighter,
+    Bomber,
+    Capital,
+    Installation,
+}
+
+// something created by bootstrap_minimum_metadata
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bootstrapped {
+    EyePoint(usize),
+    Path(PathId),
+    DockingBay(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BootstrapSkip {
+    // there's no detail0 geometry to guess from
+    NoGeometry,
+    EyePointsExist,
+    PathsExist,
+    DockingBaysExist,
+    // nothing on the top or bottom of the hull is flat enough to dock to
+    NoFlatRegion,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BootstrapReport {
+    // everything here was auto-generated and should be reviewed
+    pub generated: Vec<Bootstrapped>,
+    pub skipped: Vec<BootstrapSkip>,
+}
+
+// polygons within about 10 degrees of straight up or down count as part of a flat region
+const FLAT_REGION_COS: f32 = 0.985;
+
+// a flat, level area of the hull, found by grouping upward or downward facing polygons by height
+struct FlatRegion {
+    center: Vec3d,
+    dorsal: bool,
+}
+
+impl Model {
+    pub fn bootstrap_minimum_metadata(&mut self, ship_class_hint: ShipClassHint) -> BootstrapReport {
+        let mut report = BootstrapReport::default();
+
+        let Some(detail0) = self.detail0_id() else {
+            report.skipped.push(BootstrapSkip::NoGeometry);
+            return report;
+        };
+        let hull = SpatialHash::from_detail0(self);
+        if hull.triangles().is_empty() {
+            report.skipped.push(BootstrapSkip::NoGeometry);
+            return report;
+        }
+        let bbox = BoundingBox::from_vectors(hull.triangles().iter().flatten().copied());
+        let radius = self.header.max_radius.max(bbox.size_on_axis(bbox.greatest_dimension()) / 2.0);
+
+        if self.eye_points.is_empty() {
+            let position = self.bootstrap_eye_position(ship_class_hint, &bbox, &hull);
+            self.eye_points.push(EyePoint {
+                attached_subobj: None,
+                position,
+                normal: NormalVec3(Vec3d::new(0., 0., 1.)),
+            });
+            report.generated.push(Bootstrapped::EyePoint(self.eye_points.len() - 1));
+        } else {
+            report.skipped.push(BootstrapSkip::EyePointsExist);
+        }
+
+        if self.paths.is_empty() {
+            // straight in from the front, then pulling up over the hull and away
+            let center = bbox.center();
+            let points = [(0.0, 3.0), (0.25, 1.5), (1.0, 0.0), (0.5, -1.5)].map(|(up, forward)| PathPoint {
+                position: center + Vec3d::new(0., up, forward) * radius,
+                radius: radius * 0.25,
+                turrets: vec![],
+            });
+            let path = Path {
+                name: self.unique_path_name("$path_attack"),
+                parent: String::new(),
+                points: points.into(),
+            };
+            report.generated.push(Bootstrapped::Path(self.add_path(path)));
+        } else {
+            report.skipped.push(BootstrapSkip::PathsExist);
+        }
+
+        if matches!(ship_class_hint, ShipClassHint::Capital | ShipClassHint::Installation) {
+            if !self.docking_bays.is_empty() {
+                report.skipped.push(BootstrapSkip::DockingBaysExist);
+            } else if let Some(region) = self.largest_flat_region(detail0) {
+                let fvec = Vec3d::new(0., if region.dorsal { 1. } else { -1. }, 0.);
+                let approach = [2.0, 1.0, 0.2].map(|dist| PathPoint {
+                    position: region.center + fvec * radius * dist,
+                    radius: radius * 0.1,
+                    turrets: vec![],
+                });
+                let path = Path {
+                    name: self.unique_path_name("$path_dock01"),
+                    parent: String::new(),
+                    points: approach.into(),
+                };
+                let path_id = self.add_path(path);
+                report.generated.push(Bootstrapped::Path(path_id));
+
+                let mut dock = Dock {
+                    path: Some(path_id),
+                    position: region.center,
+                    fvec: NormalVec3(fvec),
+                    uvec: NormalVec3(Vec3d::new(0., 0., 1.)),
+                    ..Default::default()
+                };
+                properties_update_field(&mut dock.properties, "$name", "Docking bay 1");
+                self.docking_bays.push(dock);
+                report.generated.push(Bootstrapped::DockingBay(self.docking_bays.len() - 1));
+            } else {
+                report.skipped.push(BootstrapSkip::NoFlatRegion);
+            }
+        }
+
+        self.recheck_warnings(crate::Set::All);
+        self.recheck_errors(crate::Set::All);
+        report
+    }
+
+    fn bootstrap_eye_position(&self, ship_class_hint: ShipClassHint, bbox: &BoundingBox, hull: &SpatialHash) -> Vec3d {
+        let center = bbox.center();
+        match ship_class_hint {
+            // the cockpit is usually near the front, just under the top of the hull there
+            ShipClassHint::Fighter | ShipClassHint::Bomber => {
+                let forward = if ship_class_hint == ShipClassHint::Fighter { 0.7 } else { 0.65 };
+                let z = bbox.min.z + bbox.z_length() * forward;
+                let above = Vec3d::new(center.x, bbox.max.y + bbox.y_height(), z);
+                let top = hull.nearest(above).map_or(bbox.max.y, |point| point.y);
+                Vec3d::new(center.x, (top - bbox.y_height() * 0.1).max(center.y), z)
+            }
+            // the bridge is usually the highest point of the rear half
+            ShipClassHint::Capital => {
+                let rear = hull.triangles().iter().flatten().filter(|vert| vert.z <= center.z);
+                let top = rear
+                    .max_by(|a, b| a.y.total_cmp(&b.y))
+                    .copied()
+                    .unwrap_or(Vec3d::new(center.x, bbox.max.y, center.z));
+                top + Vec3d::new(0., bbox.y_height() * 0.02, 0.)
+            }
+            ShipClassHint::Installation => {
+                let above = Vec3d::new(center.x, bbox.max.y + bbox.y_height(), center.z);
+                let top = hull.nearest(above).map_or(bbox.max.y, |point| point.y);
+                Vec3d::new(center.x, top + bbox.y_height() * 0.02, center.z)
+            }
+        }
+    }
+
+    fn largest_flat_region(&self, detail0: crate::ObjectId) -> Option<FlatRegion> {
+        let mut regions: Vec<(bool, f32, f32, Vec3d)> = vec![];
+        let tolerance = (self.header.bbox.y_height() * 0.01).max(0.01);
+
+        for subobj in &self.sub_objects {
+            if !self.is_obj_id_ancestor(subobj.obj_id, detail0) {
+                continue;
+            }
+            let offset = self.get_total_subobj_offset(subobj.obj_id);
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+                if poly.normal.y.abs() < FLAT_REGION_COS {
+                    continue;
+                }
+                let dorsal = poly.normal.y > 0.0;
+                let verts = poly
+                    .verts
+                    .iter()
+                    .map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize] + offset)
+                    .collect::<Vec<_>>();
+                let Some((&first, rest)) = verts.split_first() else { continue };
+                for pair in rest.windows(2) {
+                    let area = (pair[0] - first).cross(&(pair[1] - first)).magnitude() / 2.0;
+                    let centroid = (first + pair[0] + pair[1]) / 3.0;
+                    match regions
+                        .iter_mut()
+                        .find(|(d, height, _, _)| *d == dorsal && (height - centroid.y).abs() <= tolerance)
+                    {
+                        Some((_, _, total, weighted)) => {
+                            *total += area;
+                            *weighted += centroid * area;
+                        }
+                        None => regions.push((dorsal, centroid.y, area, centroid * area)),
+                    }
+                }
+            }
+        }
+
+        let (dorsal, _, area, weighted) = regions.into_iter().filter(|region| region.2 > 0.0).max_by(|a, b| a.2.total_cmp(&b.2))?;
+        Some(FlatRegion { center: weighted / area, dorsal })
+    }
+
+    // the name, or the name with a number appended if a path already has it
+    fn unique_path_name(&self, name: &str) -> String {
+        let taken = |name: &str| self.paths.iter().any(|path| path.name == name);
+        let mut out = name.to_string();
+        let mut i = 2;
+        while taken(&out) {
+            out = format!("{}_{}", name, i);
+            i += 1;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+
+    // just the fighter's hull, as it would come from a geometry-only import
+    fn bare_fighter() -> Model {
+        let mut model = sample_fighter(0);
+        model.eye_points.clear();
+        model.paths.clear();
+        model.docking_bays.clear();
+        model
+    }
+
+    // the hull is a box, so the bay's on its top or bottom, which are equally flat, with its approach path leading away
+    fn assert_dock_on_a_face(model: &Model) {
+        let dock = &model.docking_bays[0];
+        let bbox = model.sub_objects[model.detail0_id().unwrap()].bbox;
+        let face = if dock.fvec.0.y > 0.0 { bbox.max.y } else { bbox.min.y };
+        assert!((dock.position.y - face).abs() < 1e-3, "{:?}", dock.position);
+        assert_eq!(dock.fvec.0.y.abs(), 1.0);
+
+        let path = &model.paths[dock.path.unwrap().0 as usize];
+        assert_eq!(path.name, "$path_dock01");
+        assert!(path.points.iter().all(|point| (point.position.y - face) * dock.fvec.0.y > 0.0));
+    }
+
+    #[test]
+    fn fighter_and_bomber() {
+        let mut fighter = bare_fighter();
+        let report = fighter.bootstrap_minimum_metadata(ShipClassHint::Fighter);
+        assert_eq!(report.generated, [Bootstrapped::EyePoint(0), Bootstrapped::Path(PathId(0))]);
+        assert!(report.skipped.is_empty());
+        // a cockpit in the front half, near the top
+        let bbox = fighter.header.bbox;
+        let eye = fighter.eye_points[0].position;
+        assert!(bbox.contains(eye) && eye.z > bbox.center().z && eye.y > bbox.center().y, "{:?}", eye);
+        assert_eq!(fighter.paths[0].name, "$path_attack");
+        assert!(fighter.docking_bays.is_empty());
+
+        let mut bomber = bare_fighter();
+        bomber.bootstrap_minimum_metadata(ShipClassHint::Bomber);
+        assert!(bomber.eye_points[0].position.z < eye.z);
+
+        // nothing is added to a model which already has them
+        let report = fighter.bootstrap_minimum_metadata(ShipClassHint::Fighter);
+        assert!(report.generated.is_empty());
+        assert_eq!(report.skipped, [BootstrapSkip::EyePointsExist, BootstrapSkip::PathsExist]);
+    }
+
+    #[test]
+    fn capital() {
+        let mut capital = bare_fighter();
+        let report = capital.bootstrap_minimum_metadata(ShipClassHint::Capital);
+        let generated = [
+            Bootstrapped::EyePoint(0),
+            Bootstrapped::Path(PathId(0)),
+            Bootstrapped::Path(PathId(1)),
+            Bootstrapped::DockingBay(0),
+        ];
+        assert_eq!(report.generated, generated);
+        // the bridge is just above the highest point of the rear half
+        let bbox = capital.header.bbox;
+        let eye = capital.eye_points[0].position;
+        assert!(eye.z <= bbox.center().z && eye.y >= bbox.max.y, "{:?}", eye);
+        assert_dock_on_a_face(&capital);
+
+        let report = capital.bootstrap_minimum_metadata(ShipClassHint::Capital);
+        assert_eq!(report.skipped.last(), Some(&BootstrapSkip::DockingBaysExist));
+    }
+
+    #[test]
+    fn installation() {
+        let mut installation = bare_fighter();
+        let report = installation.bootstrap_minimum_metadata(ShipClassHint::Installation);
+        assert_eq!(report.generated.len(), 4);
+        // the eye is just above the middle of the top
+        let bbox = installation.header.bbox;
+        let eye = installation.eye_points[0].position;
+        assert!(eye.y > bbox.center().y && (eye.x - bbox.center().x).abs() < 1e-3 && (eye.z - bbox.center().z).abs() < 1e-3, "{:?}", eye);
+        assert_dock_on_a_face(&installation);
+    }
+
+    #[test]
+    fn nothing_to_go_on() {
+        let mut empty = Model::default();
+        let report = empty.bootstrap_minimum_metadata(ShipClassHint::Capital);
+        assert!(report.generated.is_empty());
+        assert_eq!(report.skipped, [BootstrapSkip::NoGeometry]);
+    }
+}
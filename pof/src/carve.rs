@@ -0,0 +1,286 @@
+// turning a legacy model which is one subobject with everything baked into it into a proper hierarchy
+//
+// bootstrap_detail_hierarchy makes the monolith detail0, and extract_region_to_child then carves parts like turrets and
+// engines out of it one at a time, into child subobjects of their own
+// a polygon is carved out if all of its vertices are in the region, so one which straddles the boundary stays behind
+use crate::components::extract_polygons;
+use crate::{BoundingBox, BspData, Model, ObjectId, Set, SubObject, Vec3d};
+
+// in the space of the subobject being carved, i.e. the coordinates of its vertices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SelectionVolume {
+    Box(BoundingBox),
+    Sphere { center: Vec3d, radius: f32 },
+}
+impl SelectionVolume {
+    pub fn contains(&self, point: Vec3d) -> bool {
+        match self {
+            SelectionVolume::Box(bbox) => {
+                (bbox.min.x..=bbox.max.x).contains(&point.x)
+                    && (bbox.min.y..=bbox.max.y).contains(&point.y)
+                    && (bbox.min.z..=bbox.max.z).contains(&point.z)
+            }
+            SelectionVolume::Sphere { center, radius } => (point - *center).magnitude() <= *radius,
+        }
+    }
+
+    pub fn center(&self) -> Vec3d {
+        match self {
+            SelectionVolume::Box(bbox) => bbox.center(),
+            SelectionVolume::Sphere { center, .. } => *center,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BootstrapHierarchyReport {
+    // none if the model already had detail levels, or has no subobjects
+    pub detail0: Option<ObjectId>,
+    // the name detail0 had before it was renamed to "detail0", none if it was already called that, or the name was taken
+    pub renamed_from: Option<String>,
+    // subobjects whose obj_id didn't match their position, and were renumbered
+    pub renumbered: Vec<ObjectId>,
+    // subobjects whose parent didn't exist, and were made top level
+    pub orphaned: Vec<ObjectId>,
+    // whether any subobject's children list had to be rebuilt from the parents
+    pub children_rebuilt: bool,
+}
+
+impl Model {
+    // for a model with no detail levels, makes the top level subobject with the largest radius detail0, renaming it to
+    // "detail0" unless another subobject already has that name, after repairing the subobject ids and children lists
+    // a model which already has detail levels is left alone
+    pub fn bootstrap_detail_hierarchy(&mut self) -> BootstrapHierarchyReport {
+        let mut report = BootstrapHierarchyReport::default();
+        if !self.header.detail_levels.is_empty() {
+            return report;
+        }
+
+        let num_subobjects = self.sub_objects.len();
+        for (i, subobj) in self.sub_objects.iter_mut().enumerate() {
+            if subobj.obj_id.0 as usize != i {
+                subobj.obj_id = ObjectId(i as u32);
+                report.renumbered.push(subobj.obj_id);
+            }
+            if matches!(subobj.parent, Some(parent) if parent.0 as usize >= num_subobjects || parent == subobj.obj_id) {
+                subobj.parent = None;
+                report.orphaned.push(subobj.obj_id);
+            }
+        }
+        let old_children = self.sub_objects.iter().map(|subobj| subobj.children.clone()).collect::<Vec<_>>();
+        self.recalc_all_children_ids();
+        report.children_rebuilt = (self.sub_objects.iter().zip(&old_children)).any(|(subobj, old)| subobj.children != *old);
+        self.header.num_subobjects = num_subobjects as u32;
+
+        let largest = (self.sub_objects.iter())
+            .filter(|subobj| subobj.parent.is_none())
+            .max_by(|a, b| a.radius.total_cmp(&b.radius))
+            .map(|subobj| subobj.obj_id);
+        if let Some(id) = largest {
+            self.header.detail_levels = vec![id];
+            report.detail0 = Some(id);
+            if self.sub_objects[id].name != "detail0" && self.get_obj_id_by_name("detail0").is_none() {
+                report.renamed_from = self.rename_subobject(id, "detail0".to_string());
+            }
+        }
+
+        self.recalc_semantic_name_links();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        report
+    }
+
+    // moves the source's polygons which are entirely within the region, and the vertices and normals they use, into a new
+    // child subobject of the source, whose offset is the center of the moved vertices so it can rotate about it
+    // nothing moves in model space, vertices the source no longer uses are dropped, and both bsp trees are rebuilt
+    // with no polygons in the region the child is created empty, at the region's center
+    // returns the child, or None if there's no such source subobject
+    pub fn extract_region_to_child(&mut self, source: ObjectId, region: SelectionVolume, name: String) -> Option<ObjectId> {
+        let source_subobj = self.sub_objects.get(source.0 as usize)?;
+        let (carved, kept): (Vec<_>, Vec<_>) = (source_subobj.bsp_data.collision_tree.leaves().map(|(_, poly)| poly))
+            .partition(|poly| (poly.verts.iter()).all(|vert| region.contains(source_subobj.bsp_data.verts[vert.vertex_id.0 as usize])));
+        let (mut carved_verts, carved_norms, carved_polys) = extract_polygons(&source_subobj.bsp_data, &carved);
+        let (kept_verts, kept_norms, kept_polys) = extract_polygons(&source_subobj.bsp_data, &kept);
+
+        let offset = if carved_verts.is_empty() {
+            region.center()
+        } else {
+            Vec3d::average(carved_verts.iter().copied())
+        };
+        for vert in &mut carved_verts {
+            *vert -= offset;
+        }
+
+        let policy = self.tolerance_policy;
+        if !carved_polys.is_empty() {
+            let source_subobj = &mut self.sub_objects[source];
+            source_subobj.bsp_data = BspData {
+                collision_tree: BspData::recalculate_with(&kept_verts, kept_polys.into_iter(), &policy),
+                norms: kept_norms,
+                verts: kept_verts,
+            };
+            source_subobj.recalc_bbox_with(&policy);
+            source_subobj.recalc_radius_with(&policy);
+        }
+
+        let new_id = ObjectId(self.sub_objects.len() as u32);
+        let mut child = SubObject {
+            obj_id: new_id,
+            parent: Some(source),
+            offset,
+            geo_center: offset,
+            name,
+            bsp_data: BspData {
+                collision_tree: BspData::recalculate_with(&carved_verts, carved_polys.into_iter(), &policy),
+                norms: carved_norms,
+                verts: carved_verts,
+            },
+            ..Default::default()
+        };
+        child.recalc_bbox_with(&policy);
+        child.recalc_radius_with(&policy);
+        self.sub_objects.push(child);
+        self.sub_objects[source].children.push(new_id);
+        self.header.num_subobjects = self.sub_objects.len() as u32;
+
+        self.recalc_semantic_name_links();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        Some(new_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NormalId, ObjVec, PolyVertex, Polygon, TextureId, VertexId};
+
+    const TURRET: Vec3d = Vec3d { x: 0.0, y: 6.0, z: 2.0 };
+
+    // cubes of the given centers and half sizes, each with its own 8 corners and a normal per face
+    fn cubes(cubes: &[(Vec3d, f32)]) -> BspData {
+        let (mut verts, mut norms, mut polygons) = (vec![], vec![], vec![]);
+        for &(center, half) in cubes {
+            let first = verts.len() as u32;
+            for corner in 0..8 {
+                let pick = |bit: usize| if corner & bit != 0 { half } else { -half };
+                verts.push(center + Vec3d::new(pick(1), pick(2), pick(4)));
+            }
+            for face in [[0, 2, 6, 4], [1, 5, 7, 3], [0, 4, 5, 1], [2, 3, 7, 6], [0, 1, 3, 2], [4, 6, 7, 5]] {
+                let face_center = Vec3d::average(face.iter().map(|&corner| verts[(first + corner) as usize]));
+                let normal_id = NormalId(norms.len() as u32);
+                norms.push((face_center - center).normalize());
+                let verts = (face.into_iter())
+                    .map(|corner| PolyVertex {
+                        vertex_id: VertexId(first + corner),
+                        normal_id,
+                        uv: (0.0, 0.0),
+                    })
+                    .collect();
+                polygons.push(Polygon { normal: Default::default(), texture: TextureId(0), verts });
+            }
+        }
+        BspData {
+            collision_tree: BspData::recalculate(&verts, polygons.into_iter()),
+            norms,
+            verts,
+        }
+    }
+
+    // a legacy model: a hull with a turret baked into it on top, and a loose scrap of debris, with no detail levels
+    fn monolith() -> Model {
+        let mut hull = SubObject {
+            obj_id: ObjectId(0),
+            name: "hull".to_string(),
+            bsp_data: cubes(&[(Vec3d::ZERO, 5.0), (TURRET, 0.5)]),
+            ..Default::default()
+        };
+        let mut scrap = SubObject {
+            obj_id: ObjectId(1),
+            name: "scrap".to_string(),
+            bsp_data: cubes(&[(Vec3d::ZERO, 1.0)]),
+            ..Default::default()
+        };
+        for subobj in [&mut hull, &mut scrap] {
+            subobj.recalc_bbox();
+            subobj.recalc_radius();
+        }
+        Model {
+            sub_objects: ObjVec(vec![hull, scrap]),
+            textures: vec!["hull".to_string()],
+            ..Default::default()
+        }
+    }
+
+    // each polygon's vertex normals are the normals of the faces they came from
+    fn assert_face_normals(subobj: &SubObject) {
+        for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+            let center = Vec3d::average(poly.verts.iter().map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize]));
+            for vert in &poly.verts {
+                let normal = subobj.bsp_data.norms[vert.normal_id.0 as usize];
+                assert!(normal.dot(&poly.normal) > 0.99 || normal.dot(&-poly.normal) > 0.99, "{} {:?}", subobj.name, center);
+            }
+        }
+    }
+
+    #[test]
+    fn bootstrapping_a_monolith() {
+        let mut model = monolith();
+        model.sub_objects[ObjectId(1)].obj_id = ObjectId(7);
+        model.sub_objects[ObjectId(1)].parent = Some(ObjectId(9));
+
+        let report = model.bootstrap_detail_hierarchy();
+        assert_eq!(report.detail0, Some(ObjectId(0)));
+        assert_eq!(report.renamed_from.as_deref(), Some("hull"));
+        assert_eq!((report.renumbered, report.orphaned), (vec![ObjectId(1)], vec![ObjectId(1)]));
+        assert_eq!(model.header.detail_levels, [ObjectId(0)]);
+        assert_eq!(model.sub_objects[ObjectId(0)].name, "detail0");
+        assert!(model.check_invariants().is_empty());
+
+        // once there are detail levels it's left alone
+        let report = model.bootstrap_detail_hierarchy();
+        assert_eq!(report.detail0, None);
+        assert!(report.renumbered.is_empty() && !report.children_rebuilt);
+    }
+
+    #[test]
+    fn carving_out_a_turret() {
+        let mut model = monolith();
+        model.bootstrap_detail_hierarchy();
+        let world_verts = |model: &Model, id: ObjectId| {
+            let offset = model.get_total_subobj_offset(id);
+            model.sub_objects[id].bsp_data.verts.iter().map(|&vert| vert + offset).collect::<Vec<_>>()
+        };
+        let turret_verts = cubes(&[(TURRET, 0.5)]).verts;
+
+        let region = SelectionVolume::Box(BoundingBox {
+            min: Vec3d::new(-1.0, 5.2, 1.0),
+            max: Vec3d::new(1.0, 7.0, 3.0),
+        });
+        let id = model.extract_region_to_child(ObjectId(0), region, "turret01".to_string()).unwrap();
+        assert_eq!(id, ObjectId(2));
+        let (hull, turret) = (&model.sub_objects[ObjectId(0)], &model.sub_objects[id]);
+        assert_eq!((turret.parent, hull.children().copied().collect::<Vec<_>>()), (Some(ObjectId(0)), vec![id]));
+        assert_eq!(model.header.num_subobjects, 3);
+
+        // the turret's 6 faces and 8 corners move, nothing else, and nothing moves in model space
+        assert_eq!((hull.bsp_data.collision_tree.leaves().count(), hull.bsp_data.verts.len(), hull.bsp_data.norms.len()), (6, 8, 6));
+        assert_eq!((turret.bsp_data.collision_tree.leaves().count(), turret.bsp_data.verts.len(), turret.bsp_data.norms.len()), (6, 8, 6));
+        assert!((turret.offset - TURRET).magnitude() < 1e-5);
+        for vert in world_verts(&model, id) {
+            assert!(turret_verts.iter().any(|&expected| (expected - vert).magnitude() < 1e-5), "{:?}", vert);
+        }
+        assert_face_normals(hull);
+        assert_face_normals(turret);
+        assert!(turret.radius < 1.0 && turret.bbox.max.y < 1.0);
+        assert_eq!(hull.bbox.max.y, 5.0);
+        assert!(model.check_invariants().is_empty());
+
+        // a region around a corner of the hull, which takes in no whole polygon, carves out nothing
+        let region = SelectionVolume::Sphere { center: Vec3d::new(5.0, 5.0, 5.0), radius: 2.0 };
+        let id = model.extract_region_to_child(ObjectId(0), region, "empty".to_string()).unwrap();
+        let empty = &model.sub_objects[id];
+        assert_eq!((empty.bsp_data.collision_tree.leaves().count(), empty.offset), (0, Vec3d::new(5.0, 5.0, 5.0)));
+        assert_eq!(model.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves().count(), 6);
+    }
+}
@@ -0,0 +1,291 @@
+// checking that a ship following a path stays clear of the hull, mostly for fighterbay paths, where one running through
+// the hangar wall blows fighters up as they arrive
+//
+// the ship is a sphere of the path points' radius, interpolated along each segment, swept against the collision geometry
+// of the detail0 hierarchy at rest; the segment where a bay path enters its fighterbay is expected to get close to the
+// hangar and isn't held against it
+use crate::spatial::{closest_point_on_triangle, push_subobj_triangles, SpatialHash};
+use crate::{ray_triangle_distance, BoundingBox, Model, ObjectId, Path, Vec3d};
+
+#[derive(Debug, Clone)]
+pub struct SegmentClearance {
+    // how far the swept sphere stays from the hull over the segment, negative where it penetrates
+    // infinite if there is no hull
+    pub min_clearance: f32,
+    // where along the segment min_clearance is, from 0 at its first point to 1 at its second
+    pub worst_t: f32,
+    pub nearest_subobj: Option<ObjectId>,
+    // the segment into the fighterbay, which isn't counted as a violation
+    pub bay_entry: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PathClearanceReport {
+    // segment i runs from point i to point i + 1
+    pub segments: Vec<SegmentClearance>,
+    // what the sphere runs into first, going from the path's first point to its last
+    pub first_blocking_subobj: Option<ObjectId>,
+    // the deepest penetration, as the segment index plus the fraction along it
+    pub worst_violation: Option<f32>,
+}
+impl PathClearanceReport {
+    pub fn clips_geometry(&self) -> bool {
+        self.worst_violation.is_some()
+    }
+}
+
+// the collision geometry of the detail0 hierarchy in model space, along with which subobject each triangle came from
+pub(crate) struct HullTriangles {
+    pub(crate) hash: SpatialHash,
+    owners: Vec<ObjectId>,
+}
+impl HullTriangles {
+    pub(crate) fn new(model: &Model) -> HullTriangles {
+        let mut triangles = vec![];
+        let mut owners = vec![];
+        if let Some(detail0) = model.detail0_id() {
+            for subobj in &model.sub_objects {
+                if model.is_obj_id_ancestor(subobj.obj_id, detail0) && model.subobj_collides(subobj.obj_id) {
+                    push_subobj_triangles(model, subobj.obj_id, &mut triangles);
+                    owners.resize(triangles.len(), subobj.obj_id);
+                }
+            }
+        }
+        HullTriangles { hash: SpatialHash::from_triangles(triangles), owners }
+    }
+}
+
+fn is_fighterbay(name: &str) -> bool {
+    name.to_lowercase().starts_with("fighterbay")
+}
+
+// the parameters along each segment of their closest points, from Real-Time Collision Detection, 5.1.9
+fn closest_between_segments((p1, q1): (Vec3d, Vec3d), (p2, q2): (Vec3d, Vec3d)) -> (f32, f32) {
+    let (d1, d2, r) = (q1 - p1, q2 - p2, p1 - p2);
+    let (a, e, f) = (d1.dot(&d1), d2.dot(&d2), d2.dot(&r));
+    if a <= f32::EPSILON && e <= f32::EPSILON {
+        return (0.0, 0.0);
+    }
+    if a <= f32::EPSILON {
+        return (0.0, (f / e).clamp(0.0, 1.0));
+    }
+    let c = d1.dot(&r);
+    if e <= f32::EPSILON {
+        return ((-c / a).clamp(0.0, 1.0), 0.0);
+    }
+
+    let b = d1.dot(&d2);
+    let denom = a * e - b * b;
+    // parallel segments, any point will do
+    let s = if denom != 0.0 { ((b * f - c * e) / denom).clamp(0.0, 1.0) } else { 0.0 };
+    let t = (b * s + f) / e;
+    if t < 0.0 {
+        ((-c / a).clamp(0.0, 1.0), 0.0)
+    } else if t > 1.0 {
+        (((b - c) / a).clamp(0.0, 1.0), 1.0)
+    } else {
+        (s, t)
+    }
+}
+
+// the closest approach of the segment to the triangle, as the distance and the parameter along the segment
+fn segment_triangle_distance((p, q): (Vec3d, Vec3d), tri: [Vec3d; 3]) -> (f32, f32) {
+    if let Some(t) = ray_triangle_distance(p, q - p, tri) {
+        if t <= 1.0 {
+            return (0.0, t);
+        }
+    }
+
+    // otherwise it's at one of the segment's ends, or between the segment and one of the triangle's edges
+    let mut best = (f32::INFINITY, 0.0);
+    for (t, point) in [(0.0, p), (1.0, q)] {
+        let distance = (closest_point_on_triangle(point, tri) - point).magnitude();
+        if distance < best.0 {
+            best = (distance, t);
+        }
+    }
+    for i in 0..3 {
+        let (a, b) = (tri[i], tri[(i + 1) % 3]);
+        let (s, t) = closest_between_segments((p, q), (a, b));
+        let distance = ((p + (q - p) * s) - (a + (b - a) * t)).magnitude();
+        if distance < best.0 {
+            best = (distance, s);
+        }
+    }
+    best
+}
+
+impl Model {
+    // a bay path either belongs to a fighterbay subobject or follows FSO's $bay naming
+    pub fn is_bay_path(&self, path: &Path) -> bool {
+        is_fighterbay(&path.parent) || path.name.to_lowercase().starts_with("$bay")
+    }
+
+    // the index of the segment which enters the fighterbay, if the path has one
+    // that's whichever end segment ends inside a fighterbay subobject's bbox, or failing that, for a path belonging to a
+    // fighterbay, whichever end is closer to it
+    fn bay_entry_segment(&self, path: &Path) -> Option<usize> {
+        if path.points.len() < 2 {
+            return None;
+        }
+        let ends = [
+            (0, path.points[0].position),
+            (path.points.len() - 2, path.points[path.points.len() - 1].position),
+        ];
+
+        let bay_boxes = (self.sub_objects.iter())
+            .filter(|subobj| is_fighterbay(&subobj.name))
+            .map(|subobj| {
+                let offset = self.get_total_subobj_offset(subobj.obj_id);
+                BoundingBox { min: subobj.bbox.min + offset, max: subobj.bbox.max + offset }
+            })
+            .collect::<Vec<_>>();
+        let inside = |point: Vec3d| bay_boxes.iter().any(|bbox| bbox.contains(point));
+        if let Some(&(segment, _)) = ends.iter().find(|(_, point)| inside(*point)) {
+            return Some(segment);
+        }
+
+        let parent = self.get_obj_id_by_name(&path.parent).filter(|_| is_fighterbay(&path.parent))?;
+        let bay_center = self.get_total_subobj_offset(parent) + self.sub_objects[parent].bbox.center();
+        (ends.into_iter())
+            .min_by(|(_, point1), (_, point2)| (*point1 - bay_center).magnitude().total_cmp(&(*point2 - bay_center).magnitude()))
+            .map(|(segment, _)| segment)
+    }
+
+    // sweeps a sphere along the path against the detail0 hierarchy, the path points' radii or probe_radius if given
+    pub fn path_clearance_report(&self, path_idx: usize, probe_radius: Option<f32>) -> PathClearanceReport {
+        self.path_clearance_report_with(path_idx, probe_radius, &HullTriangles::new(self))
+    }
+
+    pub(crate) fn path_clearance_report_with(&self, path_idx: usize, probe_radius: Option<f32>, hull: &HullTriangles) -> PathClearanceReport {
+        let mut report = PathClearanceReport::default();
+        let Some(path) = self.paths.get(path_idx) else {
+            return report;
+        };
+        let bay_entry = self.bay_entry_segment(path);
+
+        for (i, pair) in path.points.windows(2).enumerate() {
+            let (p, q) = (pair[0].position, pair[1].position);
+            let (r0, r1) = match probe_radius {
+                Some(radius) => (radius, radius),
+                None => (pair[0].radius, pair[1].radius),
+            };
+
+            let mut reach = BoundingBox::from_vectors([p, q].into_iter());
+            let pad = Vec3d::new(1.0, 1.0, 1.0) * r0.max(r1);
+            reach.min -= pad;
+            reach.max += pad;
+            let mut candidates = hull.hash.triangles_in(&reach);
+            // nothing within reach, but the clearance is still worth knowing
+            if candidates.is_empty() {
+                candidates = (0..hull.hash.triangles().len()).collect();
+            }
+
+            let mut segment = SegmentClearance {
+                min_clearance: f32::INFINITY,
+                worst_t: 0.0,
+                nearest_subobj: None,
+                bay_entry: bay_entry == Some(i),
+            };
+            for tri_idx in candidates {
+                let (distance, t) = segment_triangle_distance((p, q), hull.hash.triangles()[tri_idx]);
+                let clearance = distance - (r0 + (r1 - r0) * t);
+                if clearance < segment.min_clearance {
+                    segment.min_clearance = clearance;
+                    segment.worst_t = t;
+                    segment.nearest_subobj = Some(hull.owners[tri_idx]);
+                }
+            }
+            report.segments.push(segment);
+        }
+
+        let mut worst: Option<(f32, f32)> = None;
+        for (i, segment) in report.segments.iter().enumerate() {
+            if segment.bay_entry || segment.min_clearance >= 0.0 {
+                continue;
+            }
+            if report.first_blocking_subobj.is_none() {
+                report.first_blocking_subobj = segment.nearest_subobj;
+            }
+            if !matches!(worst, Some((clearance, _)) if clearance <= segment.min_clearance) {
+                worst = Some((segment.min_clearance, i as f32 + segment.worst_t));
+            }
+        }
+        report.worst_violation = worst.map(|(_, position)| position);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighterbay;
+    use crate::{Set, Warning};
+
+    #[test]
+    fn segments_and_triangles() {
+        let tri = [Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(4.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, 4.0)];
+        let (distance, t) = segment_triangle_distance((Vec3d::new(1.0, 2.0, 1.0), Vec3d::new(1.0, -2.0, 1.0)), tri);
+        assert!(distance.abs() < 1e-5 && (t - 0.5).abs() < 1e-5);
+        let (distance, _) = segment_triangle_distance((Vec3d::new(1.0, 2.0, 1.0), Vec3d::new(2.0, 2.0, 1.0)), tri);
+        assert!((distance - 2.0).abs() < 1e-5);
+        // past the triangle's edge, closest to its corner at the origin
+        let (distance, t) = segment_triangle_distance((Vec3d::new(-3.0, 0.0, -1.0), Vec3d::new(-1.0, 0.0, -1.0)), tri);
+        assert!((distance - 2f32.sqrt()).abs() < 1e-5 && (t - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn clear_and_clipping_bay_paths() {
+        for seed in 0..10 {
+            let clear = sample_fighterbay(seed, false);
+            let report = clear.path_clearance_report(1, None);
+            assert_eq!(report.segments.len(), 3);
+            // the path starts inside the bay, so its first segment gets close, but that's fine
+            assert!(report.segments[0].bay_entry && report.segments[0].min_clearance < 0.0);
+            let clear_of_the_bay = |segment: &SegmentClearance| !segment.bay_entry && segment.min_clearance > 0.0;
+            assert!(report.segments[1..].iter().all(clear_of_the_bay));
+            assert!(!report.clips_geometry() && report.first_blocking_subobj.is_none());
+            assert!(!clear.warnings.contains(&Warning::PathClipsGeometry(1)));
+
+            let clipping = sample_fighterbay(seed, true);
+            let report = clipping.path_clearance_report(1, None);
+            assert!(report.segments[2].min_clearance <= -0.5);
+            assert_eq!(report.first_blocking_subobj, Some(ObjectId(0)));
+            assert!(matches!(report.worst_violation, Some(position) if (2.0..=3.0).contains(&position)));
+            assert!(clipping.warnings.contains(&Warning::PathClipsGeometry(1)));
+            // the fighter's own path isn't a bay path
+            assert!(!clipping.warnings.contains(&Warning::PathClipsGeometry(0)));
+        }
+    }
+
+    #[test]
+    fn probe_radius_overrides_the_path() {
+        let model = sample_fighterbay(0, false);
+        assert!(!model.path_clearance_report(1, Some(0.1)).clips_geometry());
+        let report = model.path_clearance_report(1, Some(100.0));
+        assert!(report.segments.iter().all(|segment| segment.min_clearance < 0.0));
+        assert!(matches!(report.worst_violation, Some(position) if position >= 1.0));
+        assert!(model.path_clearance_report(5, None).segments.is_empty());
+    }
+
+    #[test]
+    fn bay_entry_needs_a_bay() {
+        // without its fighterbay the path is just a path starting inside the hull
+        let mut model = sample_fighterbay(0, false);
+        model.sub_objects[ObjectId(9)].name = "hangar01".to_string();
+        let report = model.path_clearance_report(1, None);
+        assert!(report.segments.iter().all(|segment| !segment.bay_entry));
+        assert_eq!(report.worst_violation, Some(report.segments[0].worst_t));
+        assert_eq!(report.first_blocking_subobj, Some(ObjectId(9)));
+
+        // it's still a bay path though, by its parent, and a bay path by its name alone is checked too
+        model.recheck_warnings(Set::All);
+        assert!(model.warnings.contains(&Warning::PathClipsGeometry(1)));
+        model.paths[1].parent = "detail0".to_string();
+        model.recheck_warnings(Set::All);
+        assert!(model.warnings.contains(&Warning::PathClipsGeometry(1)));
+        model.paths[1].name = "$path02".to_string();
+        model.recheck_warnings(Set::All);
+        assert!(!model.warnings.contains(&Warning::PathClipsGeometry(1)));
+    }
+}
@@ -0,0 +1,620 @@
+// swapping collision trees in and out without touching the polygons in them, for comparing ways of building them
+//
+// a snapshot holds just the shape of each tree: the bbox of every node, and which polygons each leaf holds, by index
+// a subobject's polygons are indexed in a canonical order which doesn't depend on the tree they're in, so a snapshot of
+// one tree can be put back after the subobject has been given another; shield polygons are indexed as they are already
+//
+// with the `serde` feature, snapshots can be saved as json
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+use crate::tolerance::TolerancePolicy;
+use crate::{Axis, BoundingBox, BspData, BspNode, Model, ObjectId, Polygon, PolygonId, ShieldNode, Vec3d};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TreeShape {
+    Split {
+        bbox: BoundingBox,
+        front: Box<TreeShape>,
+        back: Box<TreeShape>,
+    },
+    Leaf {
+        bbox: BoundingBox,
+        polygons: Vec<u32>,
+    },
+    Empty,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubObjectTree {
+    // the vertex ids of every polygon in canonical order, to check the polygons haven't changed since
+    pub polygon_verts: Vec<Vec<u32>>,
+    pub shape: TreeShape,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CollisionTreeSnapshot {
+    // by subobject id
+    pub subobjects: Vec<SubObjectTree>,
+    pub shield: Option<TreeShape>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TreeMismatchError {
+    SubObjectCount { snapshot: usize, model: usize },
+    // the subobject's polygons aren't the ones the snapshot was taken of
+    PolygonsChanged(ObjectId),
+    // the leaves don't hold every polygon exactly once, or a leaf holds other than one polygon
+    BadLeaves(ObjectId),
+    // a leaf's bbox doesn't contain its polygon, so the vertices have moved since
+    StaleBBox(ObjectId),
+    // the snapshot has a shield tree and the model no shield, or the other way round
+    ShieldPresence,
+    BadShieldLeaves,
+}
+impl Display for TreeMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeMismatchError::SubObjectCount { snapshot, model } => {
+                write!(f, "the snapshot has {} subobjects, but the model has {}", snapshot, model)
+            }
+            TreeMismatchError::PolygonsChanged(id) => write!(f, "the polygons of subobject {} have changed since the snapshot", id.0),
+            TreeMismatchError::BadLeaves(id) => write!(f, "the tree for subobject {} doesn't hold each of its polygons exactly once", id.0),
+            TreeMismatchError::StaleBBox(id) => write!(f, "the tree for subobject {} has bboxes which don't fit its polygons", id.0),
+            TreeMismatchError::ShieldPresence => write!(f, "the snapshot and the model don't agree on whether there is a shield tree"),
+            TreeMismatchError::BadShieldLeaves => write!(f, "the shield tree doesn't hold each shield polygon exactly once"),
+        }
+    }
+}
+
+// how good a tree is for collision queries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeQuality {
+    pub depth: u32,
+    pub mean_leaf_depth: f32,
+    // the expected cost of a random ray query, counting a node visit and a polygon test as 1 each, with the chance of
+    // visiting a node taken to be its surface area relative to the root's
+    pub sah_cost: f32,
+}
+
+fn surface_area(bbox: &BoundingBox) -> f32 {
+    if bbox.is_inverted() {
+        return 0.0;
+    }
+    let (x, y, z) = (bbox.x_width(), bbox.y_height(), bbox.z_length());
+    2.0 * (x * y + y * z + z * x)
+}
+
+impl BspNode {
+    pub fn quality(&self) -> TreeQuality {
+        let root_area = surface_area(self.bbox()).max(f32::EPSILON);
+        let (mut sah_cost, mut leaves, mut depth_sum) = (0.0, 0, 0);
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            match node {
+                BspNode::Split { bbox, front, back, .. } => {
+                    sah_cost += surface_area(bbox) / root_area;
+                    stack.push((front, depth + 1));
+                    stack.push((back, depth + 1));
+                }
+                BspNode::Leaf { bbox, .. } => {
+                    sah_cost += surface_area(bbox) / root_area;
+                    leaves += 1;
+                    depth_sum += depth;
+                }
+                BspNode::Empty => {}
+            }
+        }
+        TreeQuality {
+            depth: self.depth(),
+            mean_leaf_depth: if leaves == 0 { 0.0 } else { depth_sum as f32 / leaves as f32 },
+            sah_cost,
+        }
+    }
+}
+
+// how a bsp tree's nodes are split, see BspData::recalculate_with_strategy
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitStrategy {
+    // at the median polygon along the longest axis, as BspData::recalculate does
+    #[default]
+    Median,
+    // where the surface area heuristic says, as BspData::recalculate_sah does
+    Sah,
+}
+
+impl BspData {
+    pub fn recalculate_with_strategy(
+        verts: &[Vec3d], polygons: impl Iterator<Item = Polygon>, strategy: SplitStrategy, policy: &TolerancePolicy,
+    ) -> BspNode {
+        match strategy {
+            SplitStrategy::Median => Self::recalculate_with(verts, polygons, policy),
+            SplitStrategy::Sah => Self::recalculate_sah_with(verts, polygons, policy),
+        }
+    }
+
+    // like recalculate, but splits each node where the two halves' surface areas, weighted by how many polygons they hold,
+    // are the least, rather than at the median along the longest axis; slower to build, but usually cheaper to query
+    pub fn recalculate_sah(verts: &[Vec3d], polygons: impl Iterator<Item = Polygon>) -> BspNode {
+        Self::recalculate_sah_with(verts, polygons, &TolerancePolicy::default())
+    }
+
+    pub fn recalculate_sah_with(verts: &[Vec3d], polygons: impl Iterator<Item = Polygon>, policy: &TolerancePolicy) -> BspNode {
+        let padding = policy.tree_padding;
+        let polygons = Self::prepare_polygons(verts, polygons, padding);
+
+        fn recalc_recurse(polygons: &mut [&(Vec3d, BoundingBox, Polygon)], padding: f32) -> BspNode {
+            if let [&(_, bbox, ref polygon)] = *polygons {
+                return BspNode::Leaf { bbox, poly: polygon.clone() };
+            }
+            let bbox = BoundingBox::from_bboxes(polygons.iter().map(|(_, bbox, _)| bbox)).pad(padding);
+
+            // the cheapest split along each axis, found by sweeping the polygons in order of their centers
+            let mut best: Option<(f32, Axis, usize)> = None;
+            let mut back_areas = vec![0.0; polygons.len()];
+            for axis in [Axis::X, Axis::Y, Axis::Z] {
+                polygons.sort_by(|a, b| a.0[axis].total_cmp(&b.0[axis]));
+                let mut back = BoundingBox::EMPTY;
+                for i in (1..polygons.len()).rev() {
+                    back.expand_bbox(&polygons[i].1);
+                    back_areas[i] = surface_area(&back);
+                }
+                let mut front = BoundingBox::EMPTY;
+                for i in 1..polygons.len() {
+                    front.expand_bbox(&polygons[i - 1].1);
+                    let cost = surface_area(&front) * i as f32 + back_areas[i] * (polygons.len() - i) as f32;
+                    if best.map_or(true, |(best_cost, _, _)| cost < best_cost) {
+                        best = Some((cost, axis, i));
+                    }
+                }
+            }
+
+            let (_, axis, split) = best.unwrap();
+            polygons.sort_by(|a, b| a.0[axis].total_cmp(&b.0[axis]));
+            let (front, back) = polygons.split_at_mut(split);
+            BspNode::split(bbox, Box::new(recalc_recurse(front, padding)), Box::new(recalc_recurse(back, padding)))
+        }
+
+        if polygons.is_empty() {
+            BspNode::Empty
+        } else {
+            recalc_recurse(&mut polygons.iter().collect::<Vec<_>>(), padding)
+        }
+    }
+}
+
+fn polygon_order(poly1: &Polygon, poly2: &Polygon) -> Ordering {
+    let key = |poly: &Polygon| {
+        let verts = poly
+            .verts
+            .iter()
+            .map(|vert| (vert.vertex_id.0, vert.normal_id.0, vert.uv.0.to_bits(), vert.uv.1.to_bits()));
+        (verts.collect::<Vec<_>>(), poly.texture.0)
+    };
+    key(poly1).cmp(&key(poly2))
+}
+
+// the subobject's polygons in canonical order, polygons which compare equal are identical, so it doesn't matter which is which
+fn canonical_polygons(bsp_data: &BspData) -> Vec<&Polygon> {
+    let mut polygons = bsp_data.collision_tree.leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+    polygons.sort_by(|poly1, poly2| polygon_order(poly1, poly2));
+    polygons
+}
+
+fn bsp_shape(node: &BspNode, index_of: &dyn Fn(&Polygon) -> u32) -> TreeShape {
+    match node {
+        BspNode::Split { bbox, front, back, .. } => TreeShape::Split {
+            bbox: *bbox,
+            front: Box::new(bsp_shape(front, index_of)),
+            back: Box::new(bsp_shape(back, index_of)),
+        },
+        BspNode::Leaf { bbox, poly } => TreeShape::Leaf { bbox: *bbox, polygons: vec![index_of(poly)] },
+        BspNode::Empty => TreeShape::Empty,
+    }
+}
+
+fn shield_shape(node: &ShieldNode) -> TreeShape {
+    match node {
+        ShieldNode::Split { bbox, front, back } => TreeShape::Split {
+            bbox: *bbox,
+            front: Box::new(shield_shape(front)),
+            back: Box::new(shield_shape(back)),
+        },
+        ShieldNode::Leaf { bbox, poly_list } => TreeShape::Leaf {
+            bbox: *bbox,
+            polygons: poly_list.iter().map(|id| id.0).collect(),
+        },
+    }
+}
+
+// whether every polygon index below num_polygons is in exactly one leaf; with single_polygon_leaves, as a bsp tree needs
+fn leaves_cover(shape: &TreeShape, num_polygons: usize, single_polygon_leaves: bool) -> bool {
+    let mut seen = vec![false; num_polygons];
+    let mut stack = vec![shape];
+    while let Some(node) = stack.pop() {
+        match node {
+            TreeShape::Split { front, back, .. } => {
+                stack.push(front);
+                stack.push(back);
+            }
+            TreeShape::Leaf { polygons, .. } => {
+                if single_polygon_leaves && polygons.len() != 1 {
+                    return false;
+                }
+                for &i in polygons {
+                    match seen.get_mut(i as usize) {
+                        Some(seen @ false) => *seen = true,
+                        _ => return false,
+                    }
+                }
+            }
+            // only a tree with nothing in it at all
+            TreeShape::Empty => {
+                if num_polygons != 0 {
+                    return false;
+                }
+            }
+        }
+    }
+    seen.into_iter().all(|seen| seen)
+}
+
+// assumes leaves_cover
+fn build_bsp(shape: &TreeShape, polygons: &[&Polygon]) -> BspNode {
+    match shape {
+        TreeShape::Split { bbox, front, back } => BspNode::split(*bbox, Box::new(build_bsp(front, polygons)), Box::new(build_bsp(back, polygons))),
+        TreeShape::Leaf { bbox, polygons: ids } => BspNode::Leaf { bbox: *bbox, poly: polygons[ids[0] as usize].clone() },
+        TreeShape::Empty => BspNode::Empty,
+    }
+}
+
+// assumes leaves_cover, and that it has no Empty nodes, which a shield tree can't hold
+fn build_shield(shape: &TreeShape) -> ShieldNode {
+    match shape {
+        TreeShape::Split { bbox, front, back } => ShieldNode::Split {
+            bbox: *bbox,
+            front: Box::new(build_shield(front)),
+            back: Box::new(build_shield(back)),
+        },
+        TreeShape::Leaf { bbox, polygons } => ShieldNode::Leaf {
+            bbox: *bbox,
+            poly_list: polygons.iter().map(|&id| PolygonId(id)).collect(),
+        },
+        TreeShape::Empty => ShieldNode::Leaf { bbox: BoundingBox::default(), poly_list: vec![] },
+    }
+}
+
+impl Model {
+    pub fn export_collision_trees(&self) -> CollisionTreeSnapshot {
+        let subobjects = (self.sub_objects.iter())
+            .map(|subobj| {
+                let polygons = canonical_polygons(&subobj.bsp_data);
+                // by address, so identical polygons still get an index each
+                let indices = (polygons.iter().enumerate())
+                    .map(|(i, &poly)| (poly as *const Polygon, i as u32))
+                    .collect::<HashMap<_, _>>();
+                let index_of = |poly: &Polygon| indices[&(poly as *const Polygon)];
+                SubObjectTree {
+                    polygon_verts: polygons
+                        .iter()
+                        .map(|poly| poly.verts.iter().map(|vert| vert.vertex_id.0).collect())
+                        .collect(),
+                    shape: bsp_shape(&subobj.bsp_data.collision_tree, &index_of),
+                }
+            })
+            .collect();
+        let shield = self
+            .shield_data
+            .as_ref()
+            .and_then(|shield| shield.collision_tree.as_ref())
+            .map(shield_shape);
+        CollisionTreeSnapshot { subobjects, shield }
+    }
+
+    // replaces every subobject's tree, and the shield's, with the snapshot's, or nothing at all if any of them doesn't fit
+    pub fn import_collision_trees(&mut self, snapshot: &CollisionTreeSnapshot) -> Result<(), TreeMismatchError> {
+        if snapshot.subobjects.len() != self.sub_objects.len() {
+            return Err(TreeMismatchError::SubObjectCount {
+                snapshot: snapshot.subobjects.len(),
+                model: self.sub_objects.len(),
+            });
+        }
+
+        let mut trees = vec![];
+        for (subobj, tree) in self.sub_objects.iter().zip(&snapshot.subobjects) {
+            let polygons = canonical_polygons(&subobj.bsp_data);
+            let polygon_verts = polygons
+                .iter()
+                .map(|poly| poly.verts.iter().map(|vert| vert.vertex_id.0).collect::<Vec<_>>());
+            if !polygon_verts.eq(tree.polygon_verts.iter().cloned()) {
+                return Err(TreeMismatchError::PolygonsChanged(subobj.obj_id));
+            }
+            if !leaves_cover(&tree.shape, polygons.len(), true) {
+                return Err(TreeMismatchError::BadLeaves(subobj.obj_id));
+            }
+
+            let new_tree = build_bsp(&tree.shape, &polygons);
+            let verts = &subobj.bsp_data.verts;
+            let fits = |bbox: &BoundingBox, poly: &Polygon| poly.verts.iter().all(|vert| bbox.contains(verts[vert.vertex_id.0 as usize]));
+            if !new_tree.leaves().all(|(bbox, poly)| fits(bbox, poly)) {
+                return Err(TreeMismatchError::StaleBBox(subobj.obj_id));
+            }
+            trees.push(new_tree);
+        }
+
+        let shield_tree = match (&self.shield_data, &snapshot.shield) {
+            (Some(shield), Some(shape)) => {
+                if matches!(shape, TreeShape::Empty) || !leaves_cover(shape, shield.polygons.len(), false) {
+                    return Err(TreeMismatchError::BadShieldLeaves);
+                }
+                Some(build_shield(shape))
+            }
+            (Some(shield), None) if shield.collision_tree.is_none() => None,
+            (None, None) => None,
+            _ => return Err(TreeMismatchError::ShieldPresence),
+        };
+
+        for (subobj, tree) in self.sub_objects.iter_mut().zip(trees) {
+            subobj.bsp_data.collision_tree = tree;
+        }
+        if let Some(shield) = &mut self.shield_data {
+            shield.collision_tree = shield_tree;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use serde_json::{json, Value};
+
+    use super::{CollisionTreeSnapshot, SubObjectTree, TreeShape};
+    use crate::{BoundingBox, Vec3d};
+
+    fn bbox_to_json(bbox: &BoundingBox) -> Value {
+        json!([bbox.min.x, bbox.min.y, bbox.min.z, bbox.max.x, bbox.max.y, bbox.max.z])
+    }
+
+    fn bbox_from_json(value: &Value) -> Option<BoundingBox> {
+        let values = value
+            .as_array()?
+            .iter()
+            .map(|value| Some(value.as_f64()? as f32))
+            .collect::<Option<Vec<_>>>()?;
+        let [min_x, min_y, min_z, max_x, max_y, max_z] = values[..] else {
+            return None;
+        };
+        Some(BoundingBox {
+            min: Vec3d::new(min_x, min_y, min_z),
+            max: Vec3d::new(max_x, max_y, max_z),
+        })
+    }
+
+    fn shape_to_json(shape: &TreeShape) -> Value {
+        match shape {
+            TreeShape::Split { bbox, front, back } => {
+                json!({ "bbox": bbox_to_json(bbox), "front": shape_to_json(front), "back": shape_to_json(back) })
+            }
+            TreeShape::Leaf { bbox, polygons } => json!({ "bbox": bbox_to_json(bbox), "polygons": polygons }),
+            TreeShape::Empty => Value::Null,
+        }
+    }
+
+    fn shape_from_json(value: &Value) -> Option<TreeShape> {
+        if value.is_null() {
+            return Some(TreeShape::Empty);
+        }
+        let bbox = bbox_from_json(value.get("bbox")?)?;
+        if let Some(polygons) = value.get("polygons") {
+            let polygons = polygons.as_array()?.iter().map(|i| Some(i.as_u64()? as u32)).collect::<Option<_>>()?;
+            Some(TreeShape::Leaf { bbox, polygons })
+        } else {
+            let front = Box::new(shape_from_json(value.get("front")?)?);
+            let back = Box::new(shape_from_json(value.get("back")?)?);
+            Some(TreeShape::Split { bbox, front, back })
+        }
+    }
+
+    impl CollisionTreeSnapshot {
+        pub fn to_json(&self) -> Value {
+            json!({
+                "subobjects": self.subobjects.iter().map(|tree| json!({
+                    "polygon_verts": tree.polygon_verts,
+                    "tree": shape_to_json(&tree.shape),
+                })).collect::<Vec<_>>(),
+                "shield": self.shield.as_ref().map(shape_to_json),
+            })
+        }
+
+        // None if it isn't a snapshot written by to_json
+        pub fn from_json(value: &Value) -> Option<CollisionTreeSnapshot> {
+            let subobjects = (value.get("subobjects")?.as_array()?.iter())
+                .map(|tree| {
+                    let polygon_verts = (tree.get("polygon_verts")?.as_array()?.iter())
+                        .map(|verts| verts.as_array()?.iter().map(|i| Some(i.as_u64()? as u32)).collect())
+                        .collect::<Option<_>>()?;
+                    Some(SubObjectTree { polygon_verts, shape: shape_from_json(tree.get("tree")?)? })
+                })
+                .collect::<Option<_>>()?;
+            let shield = match value.get("shield")? {
+                Value::Null => None,
+                shape => Some(shape_from_json(shape)?),
+            };
+            Some(CollisionTreeSnapshot { subobjects, shield })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{sample_capital, sample_fighter};
+    use crate::{NormalId, PolyVertex, TextureId, VertexId};
+
+    fn rebuild(model: &mut Model, strategy: SplitStrategy) {
+        for subobj in &mut model.sub_objects.0 {
+            let polygons = subobj.bsp_data.collision_tree.leaves().map(|(_, poly)| poly.clone()).collect::<Vec<_>>();
+            let policy = TolerancePolicy::default();
+            subobj.bsp_data.collision_tree = BspData::recalculate_with_strategy(&subobj.bsp_data.verts, polygons.into_iter(), strategy, &policy);
+        }
+    }
+
+    fn first_leaf(mut shape: &mut TreeShape) -> &mut Vec<u32> {
+        loop {
+            match shape {
+                TreeShape::Split { front, .. } => shape = front,
+                TreeShape::Leaf { polygons, .. } => return polygons,
+                TreeShape::Empty => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn snapshots_swap_trees_back_in() {
+        let mut model = sample_fighter(0);
+        let median = model.export_collision_trees();
+        assert_eq!(median.subobjects.len(), model.sub_objects.len());
+        assert!(median.shield.is_some());
+
+        rebuild(&mut model, SplitStrategy::Sah);
+        let sah = model.export_collision_trees();
+        assert_ne!(sah, median);
+        // the polygons are indexed the same whichever tree they're in
+        for (tree1, tree2) in sah.subobjects.iter().zip(&median.subobjects) {
+            assert_eq!(tree1.polygon_verts, tree2.polygon_verts);
+        }
+
+        model.import_collision_trees(&median).unwrap();
+        assert_eq!(model.export_collision_trees(), median);
+        model.import_collision_trees(&sah).unwrap();
+        assert_eq!(model.export_collision_trees(), sah);
+    }
+
+    #[test]
+    fn mismatched_snapshots_change_nothing() {
+        let model = sample_fighter(0);
+        let snapshot = model.export_collision_trees();
+        let check = |edit: &dyn Fn(&mut Model, &mut CollisionTreeSnapshot), expected: TreeMismatchError| {
+            let (mut model, mut snapshot) = (model.clone(), snapshot.clone());
+            edit(&mut model, &mut snapshot);
+            let before = model.export_collision_trees();
+            assert_eq!(model.import_collision_trees(&snapshot), Err(expected));
+            assert_eq!(model.export_collision_trees(), before);
+        };
+
+        check(&|_, snapshot| drop(snapshot.subobjects.pop()), TreeMismatchError::SubObjectCount { snapshot: 8, model: 9 });
+        check(
+            &|model, _| {
+                let (_, poly) = model.sub_objects[ObjectId(2)].bsp_data.collision_tree.leaves_mut().next().unwrap();
+                poly.verts.reverse();
+            },
+            TreeMismatchError::PolygonsChanged(ObjectId(2)),
+        );
+        check(
+            &|_, snapshot| {
+                let TreeShape::Split { front, back, .. } = &mut snapshot.subobjects[1].shape else {
+                    unreachable!()
+                };
+                let id = first_leaf(front)[0];
+                first_leaf(back)[0] = id;
+            },
+            TreeMismatchError::BadLeaves(ObjectId(1)),
+        );
+        check(
+            &|model, _| {
+                for vert in &mut model.sub_objects[ObjectId(0)].bsp_data.verts {
+                    *vert = *vert * 2.0;
+                }
+            },
+            TreeMismatchError::StaleBBox(ObjectId(0)),
+        );
+        check(&|model, _| model.shield_data = None, TreeMismatchError::ShieldPresence);
+        check(
+            &|_, snapshot| {
+                let TreeShape::Split { front, .. } = snapshot.shield.as_mut().unwrap() else {
+                    unreachable!()
+                };
+                **front = TreeShape::Leaf { bbox: BoundingBox::default(), polygons: vec![0] };
+            },
+            TreeMismatchError::BadShieldLeaves,
+        );
+    }
+
+    #[test]
+    fn sah_trees_are_cheaper() {
+        for mut model in [sample_fighter(0), sample_capital(4, 200, 0)] {
+            let median = (model.sub_objects.iter())
+                .map(|subobj| subobj.bsp_data.collision_tree.quality())
+                .collect::<Vec<_>>();
+            rebuild(&mut model, SplitStrategy::Sah);
+            for (subobj, median) in model.sub_objects.iter().zip(median) {
+                let tree = &subobj.bsp_data.collision_tree;
+                let sah = tree.quality();
+                assert!(sah.sah_cost <= median.sah_cost * 1.001, "{:?} vs {:?}", sah, median);
+                // still a valid tree, a polygon per leaf and each inside its leaf's bbox
+                let verts = &subobj.bsp_data.verts;
+                let contained =
+                    |(bbox, poly): (&BoundingBox, &Polygon)| poly.verts.iter().all(|vert| bbox.contains(verts[vert.vertex_id.0 as usize]));
+                assert!(tree.leaves().all(contained));
+            }
+        }
+        assert_eq!(BspData::recalculate_sah(&[], std::iter::empty()).quality().depth, 0);
+    }
+
+    // a bumpy square grid of cells, two triangles each, with a tight cluster of tiny ones in a corner, like greebling
+    fn triangle_grid(cells: usize, greebles: usize, seed: u64) -> (Vec<Vec3d>, Vec<Polygon>) {
+        let mut rng = crate::test_util::Rng::new(seed);
+        let (mut verts, mut polygons) = (vec![], vec![]);
+        let mut triangle = |verts: &mut Vec<Vec3d>, corners: [Vec3d; 3]| {
+            let first = verts.len() as u32;
+            verts.extend(corners);
+            let verts = (first..first + 3)
+                .map(|i| PolyVertex {
+                    vertex_id: VertexId(i),
+                    normal_id: NormalId(0),
+                    uv: (0.0, 0.0),
+                })
+                .collect();
+            polygons.push(Polygon { normal: Default::default(), texture: TextureId(0), verts });
+        };
+        let heights = (0..(cells + 1) * (cells + 1)).map(|_| rng.float(0.0, 1.0)).collect::<Vec<_>>();
+        let corner = |x: usize, z: usize| Vec3d::new(x as f32, heights[x * (cells + 1) + z], z as f32);
+        for x in 0..cells {
+            for z in 0..cells {
+                triangle(&mut verts, [corner(x, z), corner(x + 1, z), corner(x + 1, z + 1)]);
+                triangle(&mut verts, [corner(x, z), corner(x + 1, z + 1), corner(x, z + 1)]);
+            }
+        }
+        for _ in 0..greebles {
+            let center = Vec3d::new(1.0, 1.5, 1.0) + rng.vec3d(0.5);
+            triangle(&mut verts, [0, 1, 2].map(|_| center + rng.vec3d(0.05)));
+        }
+        (verts, polygons)
+    }
+
+    #[test]
+    fn sah_grids_have_smaller_bboxes() {
+        let policy = TolerancePolicy::default();
+        for (cells, greebles, seed) in [(16, 0, 0), (16, 200, 1), (32, 500, 2)] {
+            let (verts, polygons) = triangle_grid(cells, greebles, seed);
+            let build = |strategy| BspData::recalculate_with_strategy(&verts, polygons.clone().into_iter(), strategy, &policy);
+            let (median, sah) = (build(SplitStrategy::Median), build(SplitStrategy::Sah));
+            assert_eq!(sah.leaves().count(), polygons.len());
+            // on an even grid there's little to choose between them, but with a cluster the heuristic does clearly better
+            let (median_sum, sah_sum) = (median.sum_of_bboxes(), sah.sum_of_bboxes());
+            let expected = if greebles == 0 { median_sum } else { median_sum * 0.9 };
+            assert!(sah_sum <= expected, "{} cells, {} greebles: {} vs {}", cells, greebles, sah_sum, median_sum);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trip() {
+        let snapshot = sample_fighter(0).export_collision_trees();
+        let json = serde_json::to_string(&snapshot.to_json()).unwrap();
+        let value = serde_json::from_str(&json).unwrap();
+        assert_eq!(CollisionTreeSnapshot::from_json(&value), Some(snapshot));
+        assert_eq!(CollisionTreeSnapshot::from_json(&serde_json::json!({ "subobjects": 5 })), None);
+    }
+}
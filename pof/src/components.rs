@@ -0,0 +1,235 @@
+// finding the separate shells within a subobject, usually parts which were merged by accident, and splitting them apart
+//
+// polygons are connected when they share an edge, by vertex id, so shells which only touch at a point, or whose seams were
+// exported with duplicate vertices, count as separate
+// polygon indices here are in leaf order, the same as BspNode::leaves and BspData::to_indexed_mesh
+use std::collections::HashMap;
+
+use crate::{BspData, Model, NormalId, ObjectId, PolyVertex, Polygon, PolygonId, Set, SubObject, Vec3d, VertexId};
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+impl SubObject {
+    // each set of polygons joined by shared edges, ordered by their lowest id, with the ids in each ascending
+    pub fn connected_components(&self) -> Vec<Vec<PolygonId>> {
+        let polys = self.bsp_data.collision_tree.leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+        let mut parents = (0..polys.len()).collect::<Vec<_>>();
+        let mut edges: HashMap<(VertexId, VertexId), usize> = HashMap::new();
+        for (i, poly) in polys.iter().enumerate() {
+            for j in 0..poly.verts.len() {
+                let (a, b) = (poly.verts[j].vertex_id, poly.verts[(j + 1) % poly.verts.len()].vertex_id);
+                let edge = if a <= b { (a, b) } else { (b, a) };
+                let other = *edges.entry(edge).or_insert(i);
+                let (root_i, root_other) = (find_root(&mut parents, i), find_root(&mut parents, other));
+                parents[root_other] = root_i;
+            }
+        }
+
+        let mut component_of_root = HashMap::new();
+        let mut components: Vec<Vec<PolygonId>> = vec![];
+        for i in 0..polys.len() {
+            let root = find_root(&mut parents, i);
+            let idx = *component_of_root.entry(root).or_insert_with(|| {
+                components.push(vec![]);
+                components.len() - 1
+            });
+            components[idx].push(PolygonId(i as u32));
+        }
+        components
+    }
+}
+
+// the polygons with only the vertices and normals they use, renumbered to match
+pub(crate) fn extract_polygons(bsp_data: &BspData, polys: &[&Polygon]) -> (Vec<Vec3d>, Vec<Vec3d>, Vec<Polygon>) {
+    let (mut verts, mut norms) = (vec![], vec![]);
+    let (mut vert_map, mut norm_map) = (HashMap::new(), HashMap::new());
+    let polygons = (polys.iter())
+        .map(|poly| Polygon {
+            verts: (poly.verts.iter())
+                .map(|vert| PolyVertex {
+                    vertex_id: *vert_map.entry(vert.vertex_id).or_insert_with(|| {
+                        verts.push(bsp_data.verts[vert.vertex_id.0 as usize]);
+                        VertexId(verts.len() as u32 - 1)
+                    }),
+                    normal_id: *norm_map.entry(vert.normal_id).or_insert_with(|| {
+                        norms.push(bsp_data.norms[vert.normal_id.0 as usize]);
+                        NormalId(norms.len() as u32 - 1)
+                    }),
+                    uv: vert.uv,
+                })
+                .collect(),
+            ..(*poly).clone()
+        })
+        .collect();
+    (verts, norms, polygons)
+}
+
+impl Model {
+    // moves every connected component of the subobject but the largest into its own new subobject named "<name>-part2" and
+    // so on, parented alongside it, or under it for a detail level, which can't share a parent; nothing moves in model space
+    // the original keeps its children, properties and everything pointing at it, and any vertices no longer used are dropped
+    // returns the new subobjects, none if it was already in one piece or doesn't exist
+    pub fn split_disconnected(&mut self, id: ObjectId) -> Vec<ObjectId> {
+        let Some(subobj) = self.sub_objects.get(id.0 as usize) else {
+            return vec![];
+        };
+        let mut components = subobj.connected_components();
+        if components.len() < 2 {
+            return vec![];
+        }
+        // stable, so ties keep the first
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+
+        let source = &self.sub_objects[id];
+        let polys = source.bsp_data.collision_tree.leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+        let pieces = (components.iter())
+            .map(|component| extract_polygons(&source.bsp_data, &component.iter().map(|poly_id| polys[poly_id.0 as usize]).collect::<Vec<_>>()))
+            .collect::<Vec<_>>();
+        let (parent, offset) = match source.parent {
+            Some(parent) => (parent, source.offset),
+            None => (id, Vec3d::ZERO),
+        };
+        let base_name = source.name.clone();
+
+        let policy = self.tolerance_policy;
+        let mut new_ids = vec![];
+        for (i, (verts, norms, polygons)) in pieces.into_iter().enumerate() {
+            let collision_tree = BspData::recalculate_with(&verts, polygons.into_iter(), &policy);
+            let bsp_data = BspData { collision_tree, norms, verts };
+            if i == 0 {
+                let subobj = &mut self.sub_objects[id];
+                subobj.bsp_data = bsp_data;
+                subobj.recalc_bbox_with(&policy);
+                subobj.recalc_radius_with(&policy);
+                continue;
+            }
+
+            let mut name = format!("{}-part{}", base_name, i + 1);
+            for j in 2.. {
+                if self.get_obj_id_by_name(&name).is_none() {
+                    break;
+                }
+                name = format!("{}-part{}-{}", base_name, i + 1, j);
+            }
+
+            let new_id = ObjectId(self.sub_objects.len() as u32);
+            let mut piece = SubObject {
+                obj_id: new_id,
+                parent: Some(parent),
+                offset,
+                geo_center: offset,
+                name,
+                bsp_data,
+                ..Default::default()
+            };
+            piece.recalc_bbox_with(&policy);
+            piece.recalc_radius_with(&policy);
+            self.sub_objects.push(piece);
+            self.sub_objects[parent].children.push(new_id);
+            new_ids.push(new_id);
+        }
+        self.header.num_subobjects = self.sub_objects.len() as u32;
+
+        self.recalc_semantic_name_links();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        new_ids
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::TextureId;
+
+    // unit cubes around the centers, each sharing its corners between its faces, and with the given vertex ids of the
+    // first cube reused as the same corners of the next, if any
+    fn cubes(centers: &[Vec3d], shared_corners: &[usize]) -> BspData {
+        let mut verts = vec![];
+        let mut polygons = vec![];
+        for (i, &center) in centers.iter().enumerate() {
+            let first = verts.len();
+            let ids = (0..8)
+                .map(|corner| match i {
+                    1 if shared_corners.contains(&corner) => corner,
+                    _ => first + corner,
+                })
+                .collect::<Vec<_>>();
+            for corner in 0..8 {
+                let pick = |bit: usize| if corner & bit != 0 { 0.5 } else { -0.5 };
+                verts.push(center + Vec3d::new(pick(1), pick(2), pick(4)));
+            }
+            for face in [[0, 2, 6, 4], [1, 5, 7, 3], [0, 4, 5, 1], [2, 3, 7, 6], [0, 1, 3, 2], [4, 6, 7, 5]] {
+                let verts = (face.into_iter())
+                    .map(|corner| PolyVertex {
+                        vertex_id: VertexId(ids[corner] as u32),
+                        normal_id: NormalId(0),
+                        uv: (0.0, 0.0),
+                    })
+                    .collect();
+                polygons.push(Polygon { normal: Default::default(), texture: TextureId(0), verts });
+            }
+        }
+        let norms = vec![Vec3d::new(0.0, 1.0, 0.0)];
+        BspData {
+            collision_tree: BspData::recalculate(&verts, polygons.into_iter()),
+            norms,
+            verts,
+        }
+    }
+
+    fn with_geometry(bsp_data: BspData) -> SubObject {
+        SubObject { bsp_data, ..Default::default() }
+    }
+
+    #[test]
+    fn two_cubes() {
+        let centers = [Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(5.0, 0.0, 0.0)];
+        let components = with_geometry(cubes(&centers, &[])).connected_components();
+        assert_eq!(components.len(), 2);
+        assert!(components.iter().all(|component| component.len() == 6));
+        assert!(components.iter().all(|component| component.windows(2).all(|pair| pair[0] < pair[1])));
+        assert!(components[0][0] < components[1][0]);
+
+        // touching at a corner isn't enough, but sharing an edge is
+        let touching = [Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(1.0, 1.0, 1.0)];
+        assert_eq!(with_geometry(cubes(&touching, &[7])).connected_components().len(), 2);
+        let joined = [Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(1.0, 1.0, 0.0)];
+        assert_eq!(with_geometry(cubes(&joined, &[3, 7])).connected_components().len(), 1);
+
+        assert_eq!(with_geometry(cubes(&[Vec3d::ZERO], &[])).connected_components().len(), 1);
+        assert!(SubObject::default().connected_components().is_empty());
+    }
+
+    #[test]
+    fn splitting_keeps_everything_in_place() {
+        let mut fighter = sample_fighter(0);
+        let centers = [Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(5.0, 0.0, 0.0)];
+        fighter.sub_objects[ObjectId(1)].bsp_data = cubes(&centers, &[]);
+        let turret_offset = fighter.sub_objects[ObjectId(1)].offset;
+        let world_verts = |subobj: &SubObject| subobj.bsp_data.verts.iter().map(|&vert| vert + turret_offset).collect::<Vec<_>>();
+        let before = world_verts(&fighter.sub_objects[ObjectId(1)]);
+
+        assert_eq!(fighter.split_disconnected(ObjectId(1)), [ObjectId(9)]);
+        let (turret, part2) = (&fighter.sub_objects[ObjectId(1)], &fighter.sub_objects[ObjectId(9)]);
+        assert_eq!(part2.name, "turret01a-part2");
+        assert_eq!((part2.parent, part2.offset), (Some(ObjectId(0)), turret_offset));
+        assert!(fighter.sub_objects[ObjectId(0)].children().any(|&child| child == ObjectId(9)));
+        // the original keeps its children, and each piece just its own vertices
+        assert!(turret.children().any(|&child| child == ObjectId(2)));
+        assert_eq!((turret.bsp_data.verts.len(), part2.bsp_data.verts.len()), (8, 8));
+        assert!(world_verts(turret).iter().all(|vert| before[..8].contains(vert)));
+        assert!(world_verts(part2).iter().all(|vert| before[8..].contains(vert)));
+        assert_eq!(fighter.header.num_subobjects, 10);
+
+        assert!(fighter.split_disconnected(ObjectId(1)).is_empty());
+        assert!(fighter.split_disconnected(ObjectId(9)).is_empty());
+    }
+}
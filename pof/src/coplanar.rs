@@ -0,0 +1,426 @@
+// finding polygons which lie in the same plane and overlap, which the engine can't depth sort and so z-fights
+// this is almost always a greeble modeled flush against the hull it sits on
+//
+// polygon indices here are in leaf order, the same as BspNode::leaves and BspData::to_indexed_mesh
+use std::collections::HashMap;
+
+use crate::spatial::SpatialHash;
+use crate::{BoundingBox, Model, ObjectId, Polygon, PolygonId, Set, SubObject, Vec3d};
+
+// normals must be within about 3 degrees of each other to count as coplanar
+const COPLANAR_NORMAL_COS: f32 = 0.9986;
+// overlaps smaller than this are just polygons touching along an edge
+const MIN_OVERLAP_AREA: f32 = 1e-6;
+// how finely planes are bucketed by SubObject::find_overlapping_coplanar, and how far apart polygons can be in the same plane
+const PLANE_NORMAL_STEPS: f32 = 32.0;
+const PLANE_OFFSET_STEP: f32 = 0.01;
+// and how much of the smaller polygon has to be covered, rounding error makes neighbors far from the origin seem to overlap a little
+const MIN_OVERLAP_FRACTION: f32 = 1e-3;
+
+// a patch where a child's polygons z-fight with its parent's
+#[derive(Debug, Clone)]
+pub struct OverlapRegion {
+    pub parent: ObjectId,
+    pub child: ObjectId,
+    pub parent_polys: Vec<usize>,
+    pub child_polys: Vec<usize>,
+    pub area: f32,
+}
+
+// two directions perpendicular to the normal and each other, for flattening polygons onto their plane
+fn plane_basis(normal: Vec3d) -> (Vec3d, Vec3d) {
+    let axis = if normal.x.abs() < 0.6 {
+        Vec3d::new(1., 0., 0.)
+    } else {
+        Vec3d::new(0., 1., 0.)
+    };
+    let u = normal.cross(&axis).normalize();
+    (u, normal.cross(&u))
+}
+
+fn signed_area(poly: &[(f32, f32)]) -> f32 {
+    let mut area = 0.0;
+    for (i, a) in poly.iter().enumerate() {
+        let b = poly[(i + 1) % poly.len()];
+        area += a.0 * b.1 - b.0 * a.1;
+    }
+    area / 2.0
+}
+
+// counter-clockwise, so the clipping below knows which side of an edge is inside
+fn project(verts: &[Vec3d], (u, v): (Vec3d, Vec3d)) -> Vec<(f32, f32)> {
+    let mut out = verts.iter().map(|vert| (vert.dot(&u), vert.dot(&v))).collect::<Vec<_>>();
+    if signed_area(&out) < 0.0 {
+        out.reverse();
+    }
+    out
+}
+
+// sutherland-hodgman, both polygons must be convex and counter-clockwise, which pof polygons are
+fn clip(subject: &[(f32, f32)], clipper: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut out = subject.to_vec();
+    for (i, &a) in clipper.iter().enumerate() {
+        let b = clipper[(i + 1) % clipper.len()];
+        let side = |p: (f32, f32)| (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0);
+        let input = std::mem::take(&mut out);
+        for (j, &p) in input.iter().enumerate() {
+            let q = input[(j + 1) % input.len()];
+            let (sp, sq) = (side(p), side(q));
+            if sp >= 0.0 {
+                out.push(p);
+            }
+            if (sp >= 0.0) != (sq >= 0.0) {
+                let t = sp / (sp - sq);
+                out.push((p.0 + (q.0 - p.0) * t, p.1 + (q.1 - p.1) * t));
+            }
+        }
+        if out.is_empty() {
+            break;
+        }
+    }
+    out
+}
+
+// the area shared by two polygons when flattened onto the plane of the first
+pub(crate) fn projected_overlap_area(a: &[Vec3d], b: &[Vec3d], normal: Vec3d) -> f32 {
+    if a.len() < 3 || b.len() < 3 {
+        return 0.0;
+    }
+    let basis = plane_basis(normal);
+    let overlap = clip(&project(a, basis), &project(b, basis));
+    if overlap.len() < 3 {
+        0.0
+    } else {
+        signed_area(&overlap).abs()
+    }
+}
+
+fn polygon_area(verts: &[Vec3d], normal: Vec3d) -> f32 {
+    let mut sum = Vec3d::ZERO;
+    for (i, vert) in verts.iter().enumerate() {
+        sum += vert.cross(&verts[(i + 1) % verts.len()]);
+    }
+    sum.dot(&normal).abs() / 2.0
+}
+
+// whether b lies within max_offset of a's plane, facing the same way
+pub(crate) fn nearly_coplanar(a: &[Vec3d], a_normal: Vec3d, b: &[Vec3d], b_normal: Vec3d, max_offset: f32) -> bool {
+    if a.is_empty() || a_normal.is_null() || b_normal.is_null() || a_normal.dot(&b_normal) < COPLANAR_NORMAL_COS {
+        return false;
+    }
+    b.iter().all(|vert| (*vert - a[0]).dot(&a_normal).abs() < max_offset)
+}
+
+impl SubObject {
+    // pairs of polygons within the subobject which lie in the same plane, face the same way and overlap, so z-fight
+    // usually from geometry duplicated in place; ids are in leaf order, and each pair is ordered and listed once
+    // planes are bucketed, so a pair straddling a bucket boundary can be missed, but exact duplicates never are
+    pub fn find_overlapping_coplanar(&self) -> Vec<(PolygonId, PolygonId)> {
+        let verts = &self.bsp_data.verts;
+        let mut polys = vec![];
+        let mut planes: HashMap<[i32; 4], Vec<usize>> = HashMap::new();
+        for (i, (_, poly)) in self.bsp_data.collision_tree.leaves().enumerate() {
+            if poly.verts.len() < 3 || poly.normal.is_null() {
+                continue;
+            }
+            let normal = poly.normal.normalize();
+            let poly_verts = poly.verts.iter().map(|vert| verts[vert.vertex_id.0 as usize]).collect::<Vec<_>>();
+            let offset = poly_verts[0].dot(&normal);
+            let key = [normal.x, normal.y, normal.z].map(|val| (val * PLANE_NORMAL_STEPS).round() as i32);
+            let key = [key[0], key[1], key[2], (offset / PLANE_OFFSET_STEP).round() as i32];
+            planes.entry(key).or_default().push(polys.len());
+            polys.push((i, normal, poly_verts));
+        }
+
+        let mut pairs = vec![];
+        for group in planes.values().filter(|group| group.len() > 1) {
+            // sweep along one axis of the plane, so only polygons whose extents overlap are clipped against each other
+            let (u, v) = plane_basis(polys[group[0]].1);
+            let extent = |verts: &[Vec3d], axis: Vec3d| {
+                let dots = verts.iter().map(|vert| vert.dot(&axis));
+                (dots.clone().fold(f32::MAX, f32::min), dots.fold(f32::MIN, f32::max))
+            };
+            let mut spans = (group.iter())
+                .map(|&idx| (idx, extent(&polys[idx].2, u), extent(&polys[idx].2, v)))
+                .collect::<Vec<_>>();
+            spans.sort_by(|a, b| a.1 .0.total_cmp(&b.1 .0));
+
+            for (j, &(a, a_u, a_v)) in spans.iter().enumerate() {
+                for &(b, b_u, b_v) in &spans[j + 1..] {
+                    if b_u.0 > a_u.1 {
+                        break;
+                    }
+                    if b_v.0 > a_v.1 || a_v.0 > b_v.1 {
+                        continue;
+                    }
+                    let ((a_idx, a_normal, a_verts), (b_idx, b_normal, b_verts)) = (&polys[a], &polys[b]);
+                    if !nearly_coplanar(a_verts, *a_normal, b_verts, *b_normal, PLANE_OFFSET_STEP) {
+                        continue;
+                    }
+                    let smaller = polygon_area(a_verts, *a_normal).min(polygon_area(b_verts, *b_normal));
+                    if projected_overlap_area(a_verts, b_verts, *a_normal) > MIN_OVERLAP_AREA.max(MIN_OVERLAP_FRACTION * smaller) {
+                        pairs.push((PolygonId(*a_idx.min(b_idx) as u32), PolygonId(*a_idx.max(b_idx) as u32)));
+                    }
+                }
+            }
+        }
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+impl Model {
+    // each polygon of the subobject, in model space
+    fn model_space_polys(&self, id: ObjectId) -> Vec<(Vec<Vec3d>, &Polygon)> {
+        let subobj = &self.sub_objects[id];
+        let offset = self.get_total_subobj_offset(id);
+        (subobj.bsp_data.collision_tree.leaves())
+            .map(|(_, poly)| {
+                let verts = poly.verts.iter().map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize] + offset);
+                (verts.collect(), poly)
+            })
+            .collect()
+    }
+
+    // polygons of a child subobject lying within max_offset of, and overlapping, its parent's polygons, in the given detail level
+    pub fn coplanar_overlap_report(&self, max_offset: f32, detail_level: u32) -> Vec<OverlapRegion> {
+        let Some(&root) = self.header.detail_levels.get(detail_level as usize) else {
+            return vec![];
+        };
+
+        let mut regions = vec![];
+        for child in &self.sub_objects {
+            let Some(parent) = child.parent() else { continue };
+            if !self.is_obj_id_ancestor(child.obj_id, root) || child.obj_id == root {
+                continue;
+            }
+            // subobjects which can't touch can't z-fight
+            let parent_bbox = self.sub_objects[parent].bbox;
+            let (parent_offset, child_offset) = (self.get_total_subobj_offset(parent), self.get_total_subobj_offset(child.obj_id));
+            let child_bbox = BoundingBox {
+                min: child.bbox.min + child_offset,
+                max: child.bbox.max + child_offset,
+            }
+            .pad(max_offset);
+            let parent_bbox = BoundingBox {
+                min: parent_bbox.min + parent_offset,
+                max: parent_bbox.max + parent_offset,
+            };
+            if !child_bbox.overlaps(&parent_bbox) {
+                continue;
+            }
+
+            let parent_polys = self.model_space_polys(parent);
+            let child_polys = self.model_space_polys(child.obj_id);
+
+            // the parent's polygons fanned into triangles, remembering which polygon each came from
+            let mut tri_owner = vec![];
+            let mut triangles = vec![];
+            for (i, (verts, _)) in parent_polys.iter().enumerate() {
+                if let [first, rest @ ..] = &verts[..] {
+                    for pair in rest.windows(2) {
+                        triangles.push([*first, pair[0], pair[1]]);
+                        tri_owner.push(i);
+                    }
+                }
+            }
+            let hash = SpatialHash::from_triangles(triangles);
+
+            let mut pairs = vec![];
+            for (child_idx, (child_verts, child_poly)) in child_polys.iter().enumerate() {
+                let bbox = BoundingBox::from_vectors(child_verts.iter().copied()).pad(max_offset);
+                let mut candidates = hash.triangles_in(&bbox).into_iter().map(|tri| tri_owner[tri]).collect::<Vec<_>>();
+                candidates.dedup();
+                for parent_idx in candidates {
+                    let (parent_verts, parent_poly) = &parent_polys[parent_idx];
+                    if !nearly_coplanar(parent_verts, parent_poly.normal, child_verts, child_poly.normal, max_offset) {
+                        continue;
+                    }
+                    let area = projected_overlap_area(parent_verts, child_verts, parent_poly.normal);
+                    if area > MIN_OVERLAP_AREA {
+                        pairs.push((parent_idx, child_idx, area));
+                    }
+                }
+            }
+
+            // pairs sharing a polygon on either side are the same region
+            let mut child_regions: Vec<OverlapRegion> = vec![];
+            for (parent_idx, child_idx, area) in pairs {
+                let mut region = OverlapRegion {
+                    parent,
+                    child: child.obj_id,
+                    parent_polys: vec![parent_idx],
+                    child_polys: vec![child_idx],
+                    area,
+                };
+                let (touching, rest) = std::mem::take(&mut child_regions)
+                    .into_iter()
+                    .partition::<Vec<_>, _>(|other| other.parent_polys.contains(&parent_idx) || other.child_polys.contains(&child_idx));
+                child_regions = rest;
+                for other in touching {
+                    region.parent_polys.extend(other.parent_polys);
+                    region.child_polys.extend(other.child_polys);
+                    region.area += other.area;
+                }
+                child_regions.push(region);
+            }
+            for region in &mut child_regions {
+                region.parent_polys.sort_unstable();
+                region.parent_polys.dedup();
+                region.child_polys.sort_unstable();
+                region.child_polys.dedup();
+            }
+            regions.extend(child_regions);
+        }
+        regions
+    }
+
+    // moves the polygons' vertices along their normals, for pulling a flush greeble off the hull it z-fights with
+    // polys are leaf order indices, which this invalidates, since the bsp tree is rebuilt
+    // vertices shared with other polygons move too, stretching those polygons to follow
+    pub fn offset_polygons_along_normal(&mut self, id: ObjectId, polys: &[usize], distance: f32) {
+        let policy = self.tolerance_policy;
+        let Some(subobj) = self.sub_objects.get_mut(id.0 as usize) else {
+            return;
+        };
+        let mut directions = vec![Vec3d::ZERO; subobj.bsp_data.verts.len()];
+        for (i, (_, poly)) in subobj.bsp_data.collision_tree.leaves().enumerate() {
+            if polys.contains(&i) {
+                for vert in &poly.verts {
+                    directions[vert.vertex_id.0 as usize] += poly.normal;
+                }
+            }
+        }
+        for (vert, direction) in subobj.bsp_data.verts.iter_mut().zip(directions) {
+            if !direction.is_null() {
+                *vert += direction.normalize() * distance;
+            }
+        }
+
+        subobj.bsp_data.collision_tree = crate::BspData::recalculate_with(
+            &subobj.bsp_data.verts,
+            std::mem::take(&mut subobj.bsp_data.collision_tree).into_leaves().map(|(_, poly)| poly),
+            &policy,
+        );
+        subobj.bbox = *subobj.bsp_data.collision_tree.bbox();
+        subobj.recalc_radius_with(&policy);
+        self.recheck_warnings(Set::All);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::{BspData, NormalId, PolyVertex, TextureId, VertexId, Warning};
+
+    // the fighter with a square plate on top of detail0's hull, height above it
+    fn fighter_with_plate(height: f32) -> (Model, ObjectId) {
+        let mut fighter = sample_fighter(0);
+        let top = fighter.sub_objects[ObjectId(0)].bbox.max.y;
+        let verts = [(-0.5, -0.5), (-0.5, 0.5), (0.5, 0.5), (0.5, -0.5)]
+            .map(|(x, z)| Vec3d::new(x, 0.0, z))
+            .to_vec();
+        let poly = Polygon {
+            normal: Vec3d::new(0.0, 1.0, 0.0),
+            texture: TextureId(0),
+            verts: (0..4)
+                .map(|i| PolyVertex {
+                    vertex_id: VertexId(i),
+                    normal_id: NormalId(0),
+                    uv: (0.0, 0.0),
+                })
+                .collect(),
+        };
+        let id = ObjectId(fighter.sub_objects.len() as u32);
+        let mut plate = SubObject {
+            obj_id: id,
+            name: "plate".to_string(),
+            parent: Some(ObjectId(0)),
+            offset: Vec3d::new(0.0, top + height, -1.0),
+            bsp_data: BspData {
+                collision_tree: BspData::recalculate(&verts, std::iter::once(poly)),
+                norms: vec![Vec3d::new(0.0, 1.0, 0.0)],
+                verts,
+            },
+            ..Default::default()
+        };
+        plate.recalc_bbox();
+        fighter.sub_objects.push(plate);
+        fighter.header.num_subobjects += 1;
+        fighter.recalc_all_children_ids();
+        (fighter, id)
+    }
+
+    fn plate_regions(model: &Model, plate: ObjectId, max_offset: f32) -> Vec<OverlapRegion> {
+        let regions = model.coplanar_overlap_report(max_offset, 0);
+        regions.into_iter().filter(|region| region.child == plate).collect()
+    }
+
+    #[test]
+    fn flush_plate() {
+        let (mut fighter, plate) = fighter_with_plate(0.0);
+        let regions = plate_regions(&fighter, plate, 0.01);
+        assert_eq!(regions.len(), 1, "{:?}", regions);
+        assert_eq!((regions[0].parent, &*regions[0].child_polys), (ObjectId(0), &[0][..]));
+        assert!((regions[0].area - 1.0).abs() < 1e-3, "{}", regions[0].area);
+        // it's in detail0, so no other detail level sees it
+        assert!(fighter.coplanar_overlap_report(0.01, 1).is_empty());
+
+        fighter.offset_polygons_along_normal(plate, &[0], 0.05);
+        assert!(plate_regions(&fighter, plate, 0.01).is_empty());
+        assert!(fighter.sub_objects[plate].bsp_data.verts.iter().all(|vert| vert.y == 0.05));
+    }
+
+    #[test]
+    fn offset_plate() {
+        let (fighter, plate) = fighter_with_plate(0.05);
+        assert!(plate_regions(&fighter, plate, 0.01).is_empty());
+        // but a loose enough tolerance finds it
+        assert_eq!(plate_regions(&fighter, plate, 0.1).len(), 1);
+    }
+
+    #[test]
+    fn stacked_quads_z_fight() {
+        // a unit quad on the xz plane at (x, y), twice at the origin, then one beside it and one above it
+        let corners = [(-0.5, -0.5), (-0.5, 0.5), (0.5, 0.5), (0.5, -0.5)];
+        let quads = [(0.0, 0.0), (0.0, 0.0), (1.0, 0.0), (0.0, 1.0)];
+        let verts = (quads.iter())
+            .flat_map(|&(x, y)| corners.map(|(u, z)| Vec3d::new(x + u, y, z)))
+            .collect::<Vec<_>>();
+        let polys = (0..quads.len() as u32).map(|quad| Polygon {
+            normal: Vec3d::new(0.0, 1.0, 0.0),
+            texture: TextureId(0),
+            verts: (0..4)
+                .map(|i| PolyVertex {
+                    vertex_id: VertexId(quad * 4 + i),
+                    normal_id: NormalId(0),
+                    uv: (0.0, 0.0),
+                })
+                .collect(),
+        });
+        let mut fighter = sample_fighter(0);
+        let subobj = &mut fighter.sub_objects[ObjectId(7)];
+        subobj.bsp_data = BspData {
+            collision_tree: BspData::recalculate(&verts, polys),
+            norms: vec![Vec3d::new(0.0, 1.0, 0.0)],
+            verts,
+        };
+
+        let pairs = subobj.find_overlapping_coplanar();
+        assert_eq!(pairs.len(), 1, "{:?}", pairs);
+        // by leaf, which quad it is
+        let quads = (subobj.bsp_data.collision_tree.leaves())
+            .map(|(_, poly)| poly.verts[0].vertex_id.0 / 4)
+            .collect::<Vec<_>>();
+        let (a, b) = pairs[0];
+        assert!(a < b);
+        let mut stacked = [quads[a.0 as usize], quads[b.0 as usize]];
+        stacked.sort();
+        assert_eq!(stacked, [0, 1]);
+
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::ZFightingPolygons(ObjectId(7))));
+        assert!(!fighter.warnings.contains(&Warning::ZFightingPolygons(ObjectId(8))));
+    }
+}
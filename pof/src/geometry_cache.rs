@@ -0,0 +1,141 @@
+// the warnings which search the model's geometry (WeaponInsideHull, PathClipsGeometry and ZFightingPolygons) are too slow to
+// redo on every recheck_warnings(Set::All), which most edits end with, though the edit rarely touched the geometry
+// so the model keeps what they're built from between rechecks, along with a fingerprint of the geometry it came from, and
+// only rebuilds it once that changes; sub_objects can be edited directly, so fingerprinting is the only way to tell
+//
+// it's only used through &mut Model, by recheck_warnings, so queries on &Model stay free of interior mutability
+// and compute_warnings works from scratch
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+use crate::clearance::HullTriangles;
+use crate::{BspData, Model, ObjectId, SubObject};
+
+#[derive(Clone, Default)]
+pub(crate) struct GeometryCache {
+    hull: Option<(u64, Arc<HullTriangles>)>,
+    // by subobject, whether it had overlapping coplanar polygons
+    z_fighting: HashMap<ObjectId, (u64, bool)>,
+}
+impl std::fmt::Debug for GeometryCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeometryCache").finish_non_exhaustive()
+    }
+}
+impl GeometryCache {
+    // the collision geometry of the detail0 hierarchy, as HullTriangles::new would build it
+    pub(crate) fn hull(&mut self, model: &Model) -> Arc<HullTriangles> {
+        let fingerprint = hull_fingerprint(model);
+        match &self.hull {
+            Some((cached, hull)) if *cached == fingerprint => hull.clone(),
+            _ => {
+                let hull = Arc::new(HullTriangles::new(model));
+                self.hull = Some((fingerprint, hull.clone()));
+                hull
+            }
+        }
+    }
+
+    // whether subobj.find_overlapping_coplanar() finds anything
+    pub(crate) fn z_fighting(&mut self, subobj: &SubObject) -> bool {
+        let fingerprint = bsp_fingerprint(&subobj.bsp_data);
+        match self.z_fighting.get(&subobj.obj_id) {
+            Some(&(cached, z_fighting)) if cached == fingerprint => z_fighting,
+            _ => {
+                let z_fighting = !subobj.find_overlapping_coplanar().is_empty();
+                self.z_fighting.insert(subobj.obj_id, (fingerprint, z_fighting));
+                z_fighting
+            }
+        }
+    }
+
+    // drops the entries of subobjects which no longer exist, so the cache doesn't grow with every one ever removed
+    pub(crate) fn retain_subobjects(&mut self, num_subobjects: usize) {
+        self.z_fighting.retain(|id, _| (id.0 as usize) < num_subobjects);
+    }
+}
+
+// the vertices and polygons as they're stored, rather than by value like SubObject::geometry_fingerprint, which needs
+// valid indices and ignores the polygons' normals, which the coplanar check uses
+fn hash_bsp_data(bsp_data: &BspData, hasher: &mut impl Hasher) {
+    for vert in &bsp_data.verts {
+        [vert.x, vert.y, vert.z].map(f32::to_bits).hash(hasher);
+    }
+    for (_, poly) in bsp_data.collision_tree.leaves() {
+        [poly.normal.x, poly.normal.y, poly.normal.z].map(f32::to_bits).hash(hasher);
+        poly.verts.len().hash(hasher);
+        for vert in &poly.verts {
+            vert.vertex_id.0.hash(hasher);
+        }
+    }
+}
+
+fn bsp_fingerprint(bsp_data: &BspData) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    hash_bsp_data(bsp_data, &mut hasher);
+    hasher.finish()
+}
+
+// everything HullTriangles::new looks at: which subobjects are in the detail0 hierarchy and collide, where they are, and
+// their geometry; this takes in every subobject, which costs a rebuild after edits which didn't need one, like renames
+fn hull_fingerprint(model: &Model) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    model.detail0_id().hash(&mut hasher);
+    for subobj in &model.sub_objects {
+        subobj.parent.hash(&mut hasher);
+        [subobj.offset.x, subobj.offset.y, subobj.offset.z].map(f32::to_bits).hash(&mut hasher);
+        subobj.properties.hash(&mut hasher);
+        hash_bsp_data(&subobj.bsp_data, &mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::{Set, Vec3d, Warning};
+
+    #[test]
+    fn rechecks_reuse_the_geometry_until_it_changes() {
+        let mut fighter = sample_fighter(0);
+        fighter.recheck_warnings(Set::All);
+        let hull = fighter.geometry_cache.hull.as_ref().unwrap().1.clone();
+
+        // an edit which doesn't touch the geometry keeps it
+        fighter.primary_weps[0][0].position = Vec3d::ZERO;
+        fighter.recheck_warnings(Set::All);
+        assert!(Arc::ptr_eq(&hull, &fighter.geometry_cache.hull.as_ref().unwrap().1));
+        let buried = Warning::WeaponInsideHull { primary: true, bank: 0, point: 0 };
+        assert!(fighter.warnings.contains(&buried));
+        assert_eq!(fighter.warnings, fighter.compute_warnings());
+
+        // but moving the hull out from under the weapon rebuilds it
+        let detail0 = fighter.detail0_id().unwrap();
+        fighter.sub_objects[detail0].offset = Vec3d::new(1000.0, 0.0, 0.0);
+        fighter.recheck_warnings(Set::All);
+        assert!(!Arc::ptr_eq(&hull, &fighter.geometry_cache.hull.as_ref().unwrap().1));
+        assert!(!fighter.warnings.contains(&buried));
+        assert_eq!(fighter.warnings, fighter.compute_warnings());
+    }
+
+    #[test]
+    fn z_fighting_follows_the_geometry() {
+        let mut fighter = sample_fighter(0);
+        let detail0 = fighter.detail0_id().unwrap();
+        fighter.recheck_warnings(Set::All);
+        assert!(!fighter.warnings.contains(&Warning::ZFightingPolygons(detail0)));
+        assert!(fighter.geometry_cache.z_fighting.contains_key(&detail0));
+
+        // a copy of one of its polygons overlaps it exactly
+        let subobj = &mut fighter.sub_objects[detail0];
+        let mut polygons = subobj.bsp_data.collision_tree.leaves().map(|(_, poly)| poly.clone()).collect::<Vec<_>>();
+        polygons.push(polygons[0].clone());
+        subobj.bsp_data.collision_tree = BspData::recalculate(&subobj.bsp_data.verts, polygons.into_iter());
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::ZFightingPolygons(detail0)));
+        assert_eq!(fighter.warnings, fighter.compute_warnings());
+    }
+}
@@ -0,0 +1,219 @@
+// comparing the geometry of two models triangle by triangle, e.g. to check that a re-export didn't lose or move anything
+//
+// subobjects are matched by name, and their polygons fanned into triangles in model space, so moving a subobject's offset
+// while moving its vertices the other way cancels out; normals, uvs and textures aren't compared
+// corners of both subobjects' triangles are welded together when within epsilon, so float noise from a round trip doesn't
+// count, and two triangles are the same if they have the same welded corners with the same winding
+// a leftover triangle which shares an edge, two corners in the same winding, with a leftover one in the other model is
+// reported as moved, as when a vertex is nudged; anything else is reported as removed or added
+use std::collections::HashMap;
+
+use crate::{Model, SubObject, Vec3d};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovedTriangle {
+    pub from: [Vec3d; 3],
+    pub to: [Vec3d; 3],
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SubObjectGeometryDiff {
+    pub name: String,
+    // in model space
+    pub removed: Vec<[Vec3d; 3]>,
+    pub added: Vec<[Vec3d; 3]>,
+    pub moved: Vec<MovedTriangle>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GeometryDiff {
+    // subobjects which are only in self, or only in the other model, by name
+    pub removed_subobjects: Vec<String>,
+    pub added_subobjects: Vec<String>,
+    // only the subobjects in both which differ, in self's order
+    pub subobjects: Vec<SubObjectGeometryDiff>,
+}
+impl GeometryDiff {
+    pub fn is_empty(&self) -> bool {
+        self.removed_subobjects.is_empty() && self.added_subobjects.is_empty() && self.subobjects.is_empty()
+    }
+}
+
+// hands out the same id to positions within epsilon of one already seen, and a new one otherwise
+struct Welder {
+    epsilon: f32,
+    cells: HashMap<[i64; 3], Vec<(Vec3d, usize)>>,
+    next_id: usize,
+}
+impl Welder {
+    fn weld(&mut self, pos: Vec3d) -> usize {
+        let cell_size = self.epsilon.max(1e-5);
+        let [x, y, z] = [pos.x, pos.y, pos.z].map(|val| (val / cell_size).floor() as i64);
+        let mut neighbors = (x - 1..=x + 1).flat_map(|x| (y - 1..=y + 1).flat_map(move |y| (z - 1..=z + 1).map(move |z| [x, y, z])));
+        let existing = neighbors.find_map(|cell| (self.cells.get(&cell)?.iter()).find(|(rep, _)| (*rep - pos).magnitude() <= self.epsilon));
+        match existing {
+            Some(&(_, id)) => id,
+            None => {
+                self.cells.entry([x, y, z]).or_default().push((pos, self.next_id));
+                self.next_id += 1;
+                self.next_id - 1
+            }
+        }
+    }
+}
+
+// rotated so the lowest id comes first, which keeps the winding
+fn canonical(ids: [usize; 3]) -> [usize; 3] {
+    let first = (0..3).min_by_key(|&i| ids[i]).unwrap();
+    [ids[first], ids[(first + 1) % 3], ids[(first + 2) % 3]]
+}
+
+fn triangles(model: &Model, subobj: &SubObject) -> Vec<[Vec3d; 3]> {
+    let offset = model.get_total_subobj_offset(subobj.obj_id);
+    let mut out = vec![];
+    for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+        if let [first, rest @ ..] = &*poly.verts {
+            for pair in rest.windows(2) {
+                out.push([first, &pair[0], &pair[1]].map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize] + offset));
+            }
+        }
+    }
+    out
+}
+
+fn diff_subobject(name: &str, tris1: Vec<[Vec3d; 3]>, tris2: Vec<[Vec3d; 3]>, epsilon: f32) -> SubObjectGeometryDiff {
+    let mut welder = Welder { epsilon, cells: HashMap::new(), next_id: 0 };
+    let mut weld_all = |tris: Vec<[Vec3d; 3]>| {
+        tris.into_iter()
+            .map(|tri| (canonical(tri.map(|pos| welder.weld(pos))), tri))
+            .collect::<Vec<_>>()
+    };
+    let (tris1, tris2) = (weld_all(tris1), weld_all(tris2));
+
+    // what's left of the other model's triangles, once each of self's has claimed its match
+    let mut unclaimed: HashMap<[usize; 3], Vec<usize>> = HashMap::new();
+    for (i, (ids, _)) in tris2.iter().enumerate() {
+        unclaimed.entry(*ids).or_default().push(i);
+    }
+    let mut leftover1 = vec![];
+    for (ids, tri) in &tris1 {
+        if unclaimed.get_mut(ids).and_then(|matches| matches.pop()).is_none() {
+            leftover1.push((*ids, *tri));
+        }
+    }
+    let mut leftover2 = unclaimed.into_values().flatten().collect::<Vec<_>>();
+    leftover2.sort_unstable();
+
+    let mut by_edge: HashMap<(usize, usize), Vec<usize>> = HashMap::new();
+    for &i in &leftover2 {
+        let ids = tris2[i].0;
+        for j in 0..3 {
+            by_edge.entry((ids[j], ids[(j + 1) % 3])).or_default().push(i);
+        }
+    }
+    let mut paired = vec![false; tris2.len()];
+    let mut diff = SubObjectGeometryDiff { name: name.to_string(), ..Default::default() };
+    for (ids, tri) in leftover1 {
+        let partner = (0..3).find_map(|j| (by_edge.get(&(ids[j], ids[(j + 1) % 3]))?.iter()).copied().find(|&i| !paired[i]));
+        match partner {
+            Some(i) => {
+                paired[i] = true;
+                diff.moved.push(MovedTriangle { from: tri, to: tris2[i].1 });
+            }
+            None => diff.removed.push(tri),
+        }
+    }
+    diff.added = leftover2.into_iter().filter(|&i| !paired[i]).map(|i| tris2[i].1).collect();
+    diff
+}
+
+impl Model {
+    // see the top of geometry_diff.rs
+    pub fn geometry_diff(&self, other: &Model, epsilon: f32) -> GeometryDiff {
+        let mut diff = GeometryDiff::default();
+        for subobj in &self.sub_objects {
+            let Some(other_id) = other.get_obj_id_by_name(&subobj.name) else {
+                diff.removed_subobjects.push(subobj.name.clone());
+                continue;
+            };
+            let subobj_diff = diff_subobject(&subobj.name, triangles(self, subobj), triangles(other, &other.sub_objects[other_id]), epsilon);
+            if !(subobj_diff.removed.is_empty() && subobj_diff.added.is_empty() && subobj_diff.moved.is_empty()) {
+                diff.subobjects.push(subobj_diff);
+            }
+        }
+        diff.added_subobjects = (other.sub_objects.iter())
+            .filter(|subobj| self.get_obj_id_by_name(&subobj.name).is_none())
+            .map(|subobj| subobj.name.clone())
+            .collect();
+        diff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::ObjectId;
+
+    #[test]
+    fn nudged_vertex() {
+        let fighter = sample_fighter(0);
+        assert!(fighter.geometry_diff(&fighter, 1e-4).is_empty());
+        let round_trip = Model::from_bytes(&fighter.write_to_vec().unwrap()).unwrap();
+        assert!(fighter.geometry_diff(&round_trip, 1e-4).is_empty());
+
+        // the triangles fanned from the polygon using the vertex are moved, and nothing else; the hull's quads don't
+        // share vertices, so the other corners at the same spot stay put
+        let mut nudged = fighter.clone();
+        let bsp_data = &mut nudged.sub_objects[ObjectId(0)].bsp_data;
+        let (_, poly) = bsp_data.collision_tree.leaves().next().unwrap();
+        let id = poly.verts[1].vertex_id.0 as usize;
+        let (from, to) = (bsp_data.verts[id], bsp_data.verts[id] + Vec3d::new(0.0, 0.25, 0.0));
+        bsp_data.verts[id] = to;
+
+        let diff = fighter.geometry_diff(&nudged, 1e-4);
+        assert!(diff.removed_subobjects.is_empty() && diff.added_subobjects.is_empty());
+        assert_eq!(diff.subobjects.len(), 1);
+        let subobj_diff = &diff.subobjects[0];
+        assert_eq!(subobj_diff.name, "detail0");
+        assert!(subobj_diff.removed.is_empty() && subobj_diff.added.is_empty());
+        // a quad fans into two triangles, and its second corner is only in the first
+        assert_eq!(subobj_diff.moved.len(), 1);
+        let moved = subobj_diff.moved[0];
+        assert!(moved.from.contains(&from) && moved.to.contains(&to));
+        assert_eq!(moved.from.iter().filter(|&&corner| moved.to.contains(&corner)).count(), 2);
+
+        // within epsilon it's the same
+        assert!(fighter.geometry_diff(&nudged, 0.5).is_empty());
+    }
+
+    #[test]
+    fn model_space_and_names() {
+        let fighter = sample_fighter(0);
+
+        // moving a turret's offset, and its vertices and its gun back by as much, leaves everything where it was
+        let mut shifted = fighter.clone();
+        let shift = Vec3d::new(1.0, 2.0, 3.0);
+        let turret = &mut shifted.sub_objects[ObjectId(1)];
+        turret.offset += shift;
+        for vert in &mut turret.bsp_data.verts {
+            *vert -= shift;
+        }
+        shifted.sub_objects[ObjectId(2)].offset -= shift;
+        assert!(fighter.geometry_diff(&shifted, 1e-4).is_empty());
+
+        // but moving just the offset moves all of its polygons
+        shifted.sub_objects[ObjectId(1)].bsp_data.verts = fighter.sub_objects[ObjectId(1)].bsp_data.verts.clone();
+        let diff = fighter.geometry_diff(&shifted, 1e-4);
+        let names = diff.subobjects.iter().map(|subobj| subobj.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["turret01a"]);
+        let turret_diff = &diff.subobjects[0];
+        assert!(turret_diff.moved.is_empty() && turret_diff.removed.len() == turret_diff.added.len());
+
+        let mut renamed = fighter.clone();
+        renamed.rename_subobject(ObjectId(7), "debris99".to_string());
+        let diff = fighter.geometry_diff(&renamed, 1e-4);
+        assert_eq!((diff.removed_subobjects, diff.added_subobjects), (vec!["debris01".to_string()], vec!["debris99".to_string()]));
+        assert!(diff.subobjects.is_empty());
+    }
+}
@@ -0,0 +1,434 @@
+// a single 0-100 number for how healthy a model is, with the breakdown behind it, for tracking quality over time
+//
+// five categories are each scored 0-100, and the total is their average weighted by ScoreWeights:
+//   validation: 100, less ERROR_PENALTY per error and WARNING_PENALTY per warning, as of their last recheck
+//   geometry cleanliness: the share of polygons which are neither degenerate nor z-fighting another
+//   collision efficiency: how close each subobject's bsp tree is to balanced, by mean leaf depth, weighted by polygons
+//   limit headroom: full up to half of the closest engine or format limit, falling to nothing at the limit
+//   lod coverage: up to 60 for having TARGET_DETAIL_LEVELS detail levels, and 40 for each one being well below the one before
+// any error also caps the total at ScoreWeights::error_cap, since the model can't be used as it is, and an invariant violation
+// (see Model::check_invariants) makes everything but validation meaningless, so those all score 0
+// a model with no geometry scores 0 for geometry and collision, there being nothing there to be good
+// everything is rounded to one decimal, so the same model always scores the same
+use std::collections::BTreeSet;
+use std::fmt::{self, Display};
+
+use crate::{Error, Model, Warning, MAX_EYES, MAX_TEXTURES};
+
+pub const ERROR_PENALTY: f32 = 50.0;
+pub const WARNING_PENALTY: f32 = 10.0;
+pub const TARGET_DETAIL_LEVELS: usize = 3;
+// each detail level should have at most this many triangles relative to the one before
+pub const LOD_REDUCTION: f32 = 0.75;
+// a subobject whose tree is less efficient than this is reported as an issue
+const COLLISION_ISSUE_THRESHOLD: f32 = 0.8;
+pub const MAX_TOP_ISSUES: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreWeights {
+    pub validation: f32,
+    pub geometry: f32,
+    pub collision: f32,
+    pub headroom: f32,
+    pub lod_coverage: f32,
+    // the most a model with any errors can score
+    pub error_cap: f32,
+}
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        Self {
+            validation: 0.35,
+            geometry: 0.2,
+            collision: 0.15,
+            headroom: 0.15,
+            lod_coverage: 0.15,
+            error_cap: 50.0,
+        }
+    }
+}
+impl ScoreWeights {
+    fn of(&self, category: HealthCategory) -> f32 {
+        let sum = self.validation + self.geometry + self.collision + self.headroom + self.lod_coverage;
+        if sum <= 0.0 {
+            return 0.0;
+        }
+        let weight = match category {
+            HealthCategory::Validation => self.validation,
+            HealthCategory::Geometry => self.geometry,
+            HealthCategory::Collision => self.collision,
+            HealthCategory::Headroom => self.headroom,
+            HealthCategory::LodCoverage => self.lod_coverage,
+        };
+        weight / sum
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum HealthCategory {
+    Validation,
+    Geometry,
+    Collision,
+    Headroom,
+    LodCoverage,
+}
+impl Display for HealthCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            HealthCategory::Validation => "validation",
+            HealthCategory::Geometry => "geometry cleanliness",
+            HealthCategory::Collision => "collision efficiency",
+            HealthCategory::Headroom => "limit headroom",
+            HealthCategory::LodCoverage => "lod coverage",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthIssue {
+    pub category: HealthCategory,
+    // how many points of the total it costs
+    pub penalty: f32,
+    pub description: String,
+    // the api which fixes it, if there's one which can; otherwise it has to be fixed in the source model
+    pub fix: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthScore {
+    pub total: f32,
+    pub validation: f32,
+    pub geometry: f32,
+    pub collision: f32,
+    pub headroom: f32,
+    pub lod_coverage: f32,
+    // the MAX_TOP_ISSUES issues costing the most, the most costly first
+    pub top_issues: Vec<HealthIssue>,
+}
+impl Display for HealthScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "health {:.1}/100", self.total)?;
+        let subscores = [
+            (HealthCategory::Validation, self.validation),
+            (HealthCategory::Geometry, self.geometry),
+            (HealthCategory::Collision, self.collision),
+            (HealthCategory::Headroom, self.headroom),
+            (HealthCategory::LodCoverage, self.lod_coverage),
+        ];
+        for (category, score) in subscores {
+            writeln!(f, "  {:<22}{:>6.1}", category, score)?;
+        }
+        for issue in &self.top_issues {
+            write!(f, "  -{:.1} {}: {}", issue.penalty, issue.category, issue.description)?;
+            match issue.fix {
+                Some(fix) => writeln!(f, " (see {})", fix)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn round(val: f32) -> f32 {
+    (val * 10.0).round() / 10.0
+}
+
+fn error_fix(error: &Error) -> Option<&'static str> {
+    match error {
+        Error::BspTooDeep(_) => Some("BspData::recalculate_sah"),
+        Error::DuplicateSubobjectName(_) | Error::UnnamedSubObject(_) => Some("Model::batch_rename"),
+        _ => None,
+    }
+}
+
+fn warning_fix(warning: &Warning) -> Option<&'static str> {
+    match warning {
+        Warning::RadiusTooSmall(Some(_)) => Some("SubObject::recalc_radius"),
+        Warning::RadiusTooSmall(None) => Some("Model::recalc_radius"),
+        Warning::BBoxTooSmall(Some(_)) | Warning::InvertedBBox(Some(_)) => Some("SubObject::recalc_bbox"),
+        Warning::BBoxTooSmall(None) | Warning::InvertedBBox(None) => Some("Model::recalc_bbox"),
+        Warning::DockingBayWithoutPath(_) => Some("Model::add_path"),
+        Warning::DuplicateWeaponPoint { .. } => Some("Model::cluster_weapon_bank"),
+        Warning::ZFightingPolygons(_) => Some("Model::offset_polygons_along_normal"),
+        Warning::NormalsRepaired => Some("Model::clean_up"),
+        Warning::LegacyQuirksFixed | Warning::ChunkLengthMismatch => Some("Model::write"),
+        _ => None,
+    }
+}
+
+// the score for usage of a limit, as a fraction of it
+fn headroom_score(usage: f32) -> f32 {
+    ((1.0 - usage) * 2.0).clamp(0.0, 1.0) * 100.0
+}
+
+impl Model {
+    // see the top of health.rs for how it's scored
+    // validation goes by the warnings and errors as of their last recheck
+    pub fn health_score(&self, weights: &ScoreWeights) -> HealthScore {
+        let mut issues = vec![];
+        let mut issue = |category, lost: f32, description: String, fix| {
+            if lost > 0.0 {
+                let penalty = lost * weights.of(category);
+                issues.push(HealthIssue { category, penalty, description, fix });
+            }
+        };
+
+        let violations = self.check_invariants();
+        let mut validation = 100.0;
+        for violation in &violations {
+            issue(HealthCategory::Validation, 100.0, format!("invariant violation: {}", violation), None);
+            validation = 0.0;
+        }
+        for error in &self.errors {
+            issue(HealthCategory::Validation, ERROR_PENALTY, format!("error {:?}", error), error_fix(error));
+        }
+        for warning in &self.warnings {
+            issue(HealthCategory::Validation, WARNING_PENALTY, format!("warning {:?}", warning), warning_fix(warning));
+        }
+        validation = f32::max(validation - ERROR_PENALTY * self.errors.len() as f32 - WARNING_PENALTY * self.warnings.len() as f32, 0.0);
+
+        let (mut geometry, mut collision, mut headroom, mut lod_coverage) = (0.0, 0.0, 0.0, 0.0);
+        if violations.is_empty() {
+            let total_polys = (self.sub_objects.iter())
+                .map(|subobj| subobj.bsp_data.collision_tree.leaves().count())
+                .sum::<usize>();
+
+            // geometry cleanliness and collision efficiency
+            if total_polys > 0 {
+                let (mut bad_polys, mut efficiency_sum) = (0, 0.0);
+                for subobj in &self.sub_objects {
+                    let polys = subobj.bsp_data.collision_tree.leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+                    if polys.is_empty() {
+                        continue;
+                    }
+                    let share = polys.len() as f32 / total_polys as f32;
+
+                    let degenerate = polys.iter().filter(|poly| poly.verts.len() < 3 || poly.normal.is_null()).count();
+                    let z_fighting = (subobj.find_overlapping_coplanar().into_iter())
+                        .flat_map(|(a, b)| [a, b])
+                        .collect::<BTreeSet<_>>()
+                        .len();
+                    bad_polys += degenerate + z_fighting;
+                    let lost = |count: usize| count as f32 / total_polys as f32 * 100.0;
+                    let description = format!("{} degenerate polygons in {}", degenerate, subobj.name);
+                    issue(HealthCategory::Geometry, lost(degenerate), description, Some("ImportOptions::weld"));
+                    let description = format!("{} z-fighting polygons in {}", z_fighting, subobj.name);
+                    issue(HealthCategory::Geometry, lost(z_fighting), description, Some("Model::offset_polygons_along_normal"));
+
+                    // a balanced tree's mean leaf depth is about log2 of the leaves, plus 1 for the root
+                    let ideal_depth = (polys.len() as f32).log2() + 1.0;
+                    let efficiency = (ideal_depth / subobj.bsp_data.collision_tree.quality().mean_leaf_depth.max(1.0)).min(1.0);
+                    efficiency_sum += efficiency * share;
+                    if efficiency < COLLISION_ISSUE_THRESHOLD {
+                        let description = format!("{}'s collision tree is {:.0}% efficient", subobj.name, efficiency * 100.0);
+                        issue(HealthCategory::Collision, (1.0 - efficiency) * share * 100.0, description, Some("BspData::recalculate_sah"));
+                    }
+                }
+                geometry = f32::max(100.0 - bad_polys as f32 / total_polys as f32 * 100.0, 0.0);
+                collision = efficiency_sum * 100.0;
+            }
+
+            // limit headroom, only the closest limit counts, but each one over half is an issue
+            let mut usages = vec![];
+            let max_verts = self.max_verts_norms_per_subobj() as f32;
+            for subobj in &self.sub_objects {
+                usages.push((subobj.bsp_data.verts.len() as f32 / max_verts, format!("{}'s vertices", subobj.name), None));
+                usages.push((subobj.bsp_data.norms.len() as f32 / max_verts, format!("{}'s normals", subobj.name), None));
+                let depth = subobj.bsp_data.collision_tree.depth() as f32 / self.bsp_depth_limit() as f32;
+                usages.push((depth, format!("{}'s collision tree depth", subobj.name), Some("BspData::recalculate_sah")));
+            }
+            if let Some(tree) = self.shield_data.as_ref().and_then(|shield| shield.collision_tree.as_ref()) {
+                usages.push((tree.depth() as f32 / self.bsp_depth_limit() as f32, "the shield's collision tree depth".to_string(), None));
+            }
+            usages.push((self.textures.len() as f32 / MAX_TEXTURES as f32, "textures".to_string(), Some("Model::remove_texture")));
+            usages.push((self.eye_points.len() as f32 / MAX_EYES as f32, "eye points".to_string(), None));
+
+            let worst = usages.iter().map(|(usage, ..)| *usage).fold(0.0, f32::max);
+            headroom = headroom_score(worst);
+            for (usage, what, fix) in usages {
+                let description = format!("{} at {:.0}% of the limit", what, usage * 100.0);
+                issue(HealthCategory::Headroom, 100.0 - headroom_score(usage), description, fix);
+            }
+
+            // lod coverage
+            let lods = self.lod_stats();
+            let levels = lods.len().min(TARGET_DETAIL_LEVELS);
+            lod_coverage = levels as f32 / TARGET_DETAIL_LEVELS as f32 * 60.0;
+            if levels < TARGET_DETAIL_LEVELS {
+                let description = format!("{} of {} detail levels", lods.len(), TARGET_DETAIL_LEVELS);
+                issue(HealthCategory::LodCoverage, 60.0 - lod_coverage, description, None);
+            }
+            if lods.len() >= 2 {
+                let per_pair = 40.0 / (lods.len() - 1) as f32;
+                for (i, pair) in lods.windows(2).enumerate() {
+                    if pair[1].triangles as f32 <= pair[0].triangles as f32 * LOD_REDUCTION {
+                        lod_coverage += per_pair;
+                    } else {
+                        let description = format!(
+                            "detail level {} has {} triangles, too close to the {} of detail level {}",
+                            i + 1,
+                            pair[1].triangles,
+                            pair[0].triangles,
+                            i
+                        );
+                        issue(HealthCategory::LodCoverage, per_pair, description, None);
+                    }
+                }
+            } else {
+                issue(HealthCategory::LodCoverage, 40.0, "no lower detail levels to reduce triangles".to_string(), None);
+            }
+        }
+
+        let subscores = [
+            (HealthCategory::Validation, validation),
+            (HealthCategory::Geometry, geometry),
+            (HealthCategory::Collision, collision),
+            (HealthCategory::Headroom, headroom),
+            (HealthCategory::LodCoverage, lod_coverage),
+        ];
+        let mut total = subscores.iter().map(|&(category, score)| score * weights.of(category)).sum::<f32>();
+        if !self.errors.is_empty() {
+            total = total.min(weights.error_cap);
+        }
+
+        for issue in &mut issues {
+            issue.penalty = round(issue.penalty);
+        }
+        issues.retain(|issue| issue.penalty > 0.0);
+        // stable and fully ordered, so ties always come out the same way
+        issues.sort_by(|a, b| b.penalty.total_cmp(&a.penalty).then_with(|| a.description.cmp(&b.description)));
+        issues.truncate(MAX_TOP_ISSUES);
+
+        HealthScore {
+            total: round(total),
+            validation: round(validation),
+            geometry: round(geometry),
+            collision: round(collision),
+            headroom: round(headroom),
+            lod_coverage: round(lod_coverage),
+            top_issues: issues,
+        }
+    }
+
+    // the score with the default weights as of each save in the stat history which recorded one, oldest first,
+    // followed by the score as of right now
+    pub fn health_trend(&self) -> HealthTrend {
+        let mut points = (self.stat_history.iter())
+            .filter_map(|snapshot| Some((Some(snapshot.timestamp), snapshot.health?)))
+            .collect::<Vec<_>>();
+        points.push((None, self.health_score(&ScoreWeights::default()).total));
+        HealthTrend { points }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthTrend {
+    // (when it was saved, seconds since the unix epoch, or None for now, score)
+    pub points: Vec<(Option<u64>, f32)>,
+}
+impl HealthTrend {
+    // how much the score has changed since the oldest recorded save, positive if it's improved
+    pub fn change(&self) -> f32 {
+        match (self.points.first(), self.points.last()) {
+            (Some(&(_, first)), Some(&(_, last))) => round(last - first),
+            _ => 0.0,
+        }
+    }
+
+    // how much the score has changed since the last save
+    pub fn recent_change(&self) -> f32 {
+        match self.points.len() {
+            0 | 1 => 0.0,
+            n => round(self.points[n - 1].1 - self.points[n - 2].1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{sample_capital, sample_degenerate_cases, sample_fighter};
+    use crate::WriteOptions;
+
+    #[test]
+    fn pinned_sample_scores() {
+        let weights = ScoreWeights::default();
+        for seed in 0..3 {
+            let score = sample_fighter(seed).health_score(&weights);
+            assert_eq!(score, sample_fighter(seed).health_score(&weights));
+            assert_eq!((score.total, score.validation, score.geometry, score.collision), (99.8, 100.0, 100.0, 98.6));
+            assert_eq!((score.headroom, score.lod_coverage), (100.0, 100.0));
+            assert!(score.top_issues.is_empty());
+        }
+        // a single detail level only loses lod coverage
+        let score = sample_capital(4, 200, 0).health_score(&weights);
+        assert_eq!((score.total, score.validation, score.lod_coverage), (87.9, 100.0, 20.0));
+
+        let expected = [
+            ("empty", 32.5),
+            ("parent cycle", 0.0),
+            ("oversized subobject", 50.0),
+            ("duplicate names", 50.0),
+            ("missing normals", 0.0),
+        ];
+        let cases = sample_degenerate_cases();
+        assert_eq!(cases.len(), expected.len());
+        for ((name, model), (expected_name, expected_total)) in cases.iter().zip(expected) {
+            assert_eq!(name, expected_name);
+            let score = model.health_score(&weights);
+            assert_eq!(score.total, expected_total, "{}", name);
+            assert!(score.total <= weights.error_cap, "{}", name);
+            assert!(!score.top_issues.is_empty() && score.top_issues.len() <= MAX_TOP_ISSUES, "{}", name);
+            assert!(score.top_issues.windows(2).all(|pair| pair[0].penalty >= pair[1].penalty), "{}", name);
+        }
+    }
+
+    #[test]
+    fn weights_are_normalized() {
+        let (_, model) = sample_degenerate_cases().into_iter().find(|(name, _)| name == "duplicate names").unwrap();
+        let validation_only = ScoreWeights {
+            validation: 1.0,
+            geometry: 0.0,
+            collision: 0.0,
+            headroom: 0.0,
+            lod_coverage: 0.0,
+            error_cap: 100.0,
+        };
+        let score = model.health_score(&validation_only);
+        assert_eq!(score.total, score.validation);
+        // the same proportions give the same score
+        let doubled = ScoreWeights { validation: 2.0, error_cap: 100.0, ..validation_only };
+        assert_eq!(model.health_score(&doubled), score);
+
+        let capped = ScoreWeights { error_cap: 10.0, ..ScoreWeights::default() };
+        assert_eq!(model.health_score(&capped).total, 10.0);
+        let nothing = ScoreWeights { validation: 0.0, ..validation_only };
+        assert_eq!(model.health_score(&nothing).total, 0.0);
+    }
+
+    #[test]
+    fn trend_follows_saves() {
+        let save = |model: &Model| {
+            let mut buf = vec![];
+            let options = WriteOptions { record_stat_history: true, ..Default::default() };
+            model.write_with(&mut buf, &options).unwrap();
+            Model::from_bytes(&buf).unwrap()
+        };
+        let mut model = sample_fighter(0);
+        assert_eq!(model.health_trend().points, [(None, 99.8)]);
+        assert_eq!(model.health_trend().change(), 0.0);
+
+        model = save(&model);
+        model.header.detail_levels.truncate(1);
+        let trend = model.health_trend();
+        assert_eq!(trend.points.len(), 2);
+        assert!(trend.points[0].0.is_some() && trend.points[1].0.is_none());
+        assert!(trend.recent_change() < 0.0);
+
+        model = save(&model);
+        model = save(&model);
+        let trend = model.health_trend();
+        assert_eq!(trend.points.len(), 4);
+        assert_eq!(trend.recent_change(), 0.0);
+        assert_eq!(trend.change(), round(trend.points[3].1 - 99.8));
+    }
+}
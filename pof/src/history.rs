@@ -0,0 +1,205 @@
+// a record of a model's key statistics each time it was saved with WriteOptions::record_stat_history, so teams can see how it evolved
+//
+// it's saved in the tool metadata chunk, see crate::tool_metadata
+// at most MAX_STAT_HISTORY snapshots are kept, the oldest are evicted first; a snapshot is around 55 bytes plus 4 per detail level,
+// so it stays under a few kilobytes
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+use crate::health::ScoreWeights;
+use crate::tool_metadata::{read_string, write_string};
+use crate::Model;
+
+pub const MAX_STAT_HISTORY: usize = 32;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StatSnapshot {
+    // seconds since the unix epoch
+    pub timestamp: u64,
+    // the version of this crate which saved the file
+    pub crate_version: String,
+    pub polys_per_lod: Vec<u32>,
+    pub textures: u32,
+    pub subsystems: u32,
+    pub mass: f32,
+    // changes whenever anything in Model::summary_text does
+    pub content_hash: u64,
+    // Model::health_score with the default weights, None if it wasn't recorded
+    pub health: Option<f32>,
+}
+
+impl Model {
+    pub fn stat_history(&self) -> &[StatSnapshot] {
+        &self.stat_history
+    }
+
+    // the model's statistics as of right now
+    pub fn stat_snapshot(&self) -> StatSnapshot {
+        let mut content_hash: u64 = 0xcbf29ce484222325;
+        for byte in self.summary_text().bytes() {
+            content_hash = (content_hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+
+        StatSnapshot {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs()),
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            polys_per_lod: (self.header.detail_levels.iter())
+                .map(|&lod| {
+                    let subobjs = self.sub_objects.iter().filter(|subobj| self.is_obj_id_ancestor(subobj.obj_id, lod));
+                    subobjs.map(|subobj| subobj.bsp_data.collision_tree.leaves().count() as u32).sum()
+                })
+                .collect(),
+            textures: self.textures.len() as u32,
+            subsystems: self.subsystem_manifest().len() as u32,
+            mass: self.header.mass,
+            content_hash,
+            health: Some(self.health_score(&ScoreWeights::default()).total),
+        }
+    }
+
+    // the history as it would be written with record_stat_history, the current snapshot added and the oldest evicted
+    pub(crate) fn stat_history_with_snapshot(&self) -> Vec<StatSnapshot> {
+        let mut history = self.stat_history.clone();
+        history.push(self.stat_snapshot());
+        let excess = history.len().saturating_sub(MAX_STAT_HISTORY);
+        history.drain(..excess);
+        history
+    }
+
+    pub fn stat_history_table(&self) -> StatHistoryTable<'_> {
+        StatHistoryTable(&self.stat_history)
+    }
+}
+
+pub struct StatHistoryTable<'a>(pub &'a [StatSnapshot]);
+impl Display for StatHistoryTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{:>12} {:>8} {:>24} {:>8} {:>10} {:>12} {:>16} {:>6}",
+            "timestamp", "version", "polys per lod", "textures", "subsystems", "mass", "hash", "health"
+        )?;
+        for snapshot in self.0 {
+            let polys = snapshot.polys_per_lod.iter().map(u32::to_string).collect::<Vec<_>>().join("/");
+            let health = snapshot.health.map_or("-".to_string(), |health| format!("{:.1}", health));
+            writeln!(
+                f,
+                "{:>12} {:>8} {:>24} {:>8} {:>10} {:>12.2} {:016x} {:>6}",
+                snapshot.timestamp,
+                snapshot.crate_version,
+                polys,
+                snapshot.textures,
+                snapshot.subsystems,
+                snapshot.mass,
+                snapshot.content_hash,
+                health
+            )?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn write_stat_history(w: &mut impl Write, history: &[StatSnapshot]) -> io::Result<()> {
+    w.write_u32::<LE>(history.len() as u32)?;
+    for snapshot in history {
+        w.write_u64::<LE>(snapshot.timestamp)?;
+        write_string(w, &snapshot.crate_version)?;
+        w.write_u32::<LE>(snapshot.polys_per_lod.len() as u32)?;
+        for &polys in &snapshot.polys_per_lod {
+            w.write_u32::<LE>(polys)?;
+        }
+        w.write_u32::<LE>(snapshot.textures)?;
+        w.write_u32::<LE>(snapshot.subsystems)?;
+        w.write_f32::<LE>(snapshot.mass)?;
+        w.write_u64::<LE>(snapshot.content_hash)?;
+        w.write_u8(snapshot.health.is_some() as u8)?;
+        w.write_f32::<LE>(snapshot.health.unwrap_or(0.0))?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_stat_history(mut buf: &[u8]) -> io::Result<Vec<StatSnapshot>> {
+    let buf = &mut buf;
+    let count = buf.read_u32::<LE>()?;
+    let mut history = vec![];
+    for _ in 0..count {
+        let timestamp = buf.read_u64::<LE>()?;
+        let crate_version = read_string(buf)?;
+        let num_lods = buf.read_u32::<LE>()?;
+        let polys_per_lod = (0..num_lods).map(|_| buf.read_u32::<LE>()).collect::<io::Result<_>>()?;
+        history.push(StatSnapshot {
+            timestamp,
+            crate_version,
+            polys_per_lod,
+            textures: buf.read_u32::<LE>()?,
+            subsystems: buf.read_u32::<LE>()?,
+            mass: buf.read_f32::<LE>()?,
+            content_hash: buf.read_u64::<LE>()?,
+            health: {
+                let has_health = buf.read_u8()? != 0;
+                let health = buf.read_f32::<LE>()?;
+                has_health.then_some(health)
+            },
+        });
+    }
+    Ok(history)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::WriteOptions;
+
+    fn save(model: &Model, record_stat_history: bool) -> Model {
+        let options = WriteOptions { record_stat_history, ..Default::default() };
+        let mut buf = vec![];
+        model.write_with(&mut buf, &options).unwrap();
+        Model::from_bytes(&buf).unwrap()
+    }
+
+    #[test]
+    fn oldest_snapshots_are_evicted() {
+        let mut model = sample_fighter(0);
+        let saves = MAX_STAT_HISTORY + 3;
+        for i in 0..saves {
+            model.header.mass = i as f32;
+            model = save(&model, true);
+        }
+        let masses = model.stat_history().iter().map(|snapshot| snapshot.mass as usize).collect::<Vec<_>>();
+        assert_eq!(masses, (saves - MAX_STAT_HISTORY..saves).collect::<Vec<_>>());
+
+        let latest = model.stat_history().last().unwrap();
+        assert_eq!(latest.polys_per_lod.len(), 3);
+        assert_eq!(latest.textures, 2);
+        assert_eq!(latest.crate_version, env!("CARGO_PKG_VERSION"));
+        assert!(latest.health.is_some());
+        assert_eq!(model.stat_history_table().to_string().lines().count(), MAX_STAT_HISTORY + 1);
+
+        // without the option, the history is carried over as it was
+        let before = model.stat_history().to_vec();
+        model.header.mass = 1000.0;
+        assert_eq!(save(&model, false).stat_history(), before);
+    }
+
+    #[test]
+    fn snapshots_without_health_round_trip() {
+        let snapshot = StatSnapshot {
+            timestamp: 1_000_000,
+            crate_version: "1.5.0".to_string(),
+            polys_per_lod: vec![12, 6],
+            textures: 1,
+            subsystems: 0,
+            mass: 50.0,
+            content_hash: 0x1234,
+            health: None,
+        };
+        let mut buf = vec![];
+        write_stat_history(&mut buf, &[snapshot.clone()]).unwrap();
+        assert_eq!(read_stat_history(&buf).unwrap(), [snapshot]);
+        assert!(read_stat_history(&buf[..20]).is_err());
+    }
+}
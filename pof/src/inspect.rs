@@ -0,0 +1,186 @@
+// lightweight inspection of a pof file's chunk layout, without actually parsing the chunks
+use std::io::{self, ErrorKind, Read, Seek, SeekFrom};
+
+// the order chunks are written in by `Model::write`, which some older tools (PCS2 among them) rely on
+// chunks which share a slot are version-dependent alternatives of each other
+pub const CHUNK_ORDER: &[&[&[u8; 4]]] = &[
+    &[b"HDR2", b"OHDR"],
+    &[b"TXTR"],
+    &[b"OBJ2", b"SOBJ"],
+    &[b"PATH"],
+    &[b"SPCL"],
+    &[b"EYE "],
+    &[b"GPNT"],
+    &[b"MPNT"],
+    &[b"TGUN", b"TMIS"],
+    &[b"FUEL"],
+    &[b"GLOW"],
+    &[b"PINF"],
+    &[b"DOCK"],
+    &[b"INSG"],
+    &[b"SHLD"],
+    &[b"SLC2", b"SLDC"],
+    &[b"ACEN"],
+    // see crate::tool_metadata
+    &[b"PTMD"],
+];
+
+fn chunk_rank(id: &[u8; 4]) -> Option<usize> {
+    CHUNK_ORDER.iter().position(|ids| ids.contains(&id))
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkInfo {
+    pub id: [u8; 4],
+    // the position of the chunk's header in the file
+    pub offset: u64,
+    pub len: u32,
+    // for subobject chunks, the id of the subobject, which is their first field
+    pub subobj_id: Option<u32>,
+}
+impl ChunkInfo {
+    pub fn name(&self) -> String {
+        String::from_utf8_lossy(&self.id).into_owned()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderingIssue {
+    // the header is missing, or isn't the first chunk
+    HeaderNotFirst,
+    // a chunk appears before one which should precede it
+    ChunkOutOfOrder { chunk: [u8; 4], after: [u8; 4] },
+    // subobject chunks are not in ascending id order
+    SubObjectOutOfOrder { id: u32, after: u32 },
+}
+
+#[derive(Debug, Clone)]
+pub struct FileInspection {
+    pub version: i32,
+    pub chunks: Vec<ChunkInfo>,
+}
+impl FileInspection {
+    // checks the file against the chunk order `Model::write` produces
+    // unknown chunks are ignored
+    pub fn ordering_issues(&self) -> Vec<OrderingIssue> {
+        let mut issues = vec![];
+
+        if self.chunks.first().and_then(|chunk| chunk_rank(&chunk.id)) != Some(0) {
+            issues.push(OrderingIssue::HeaderNotFirst);
+        }
+
+        let mut latest: Option<(usize, [u8; 4])> = None;
+        let mut last_subobj_id = None;
+        for chunk in &self.chunks {
+            if let Some(rank) = chunk_rank(&chunk.id) {
+                match latest {
+                    Some((latest_rank, after)) if rank < latest_rank => issues.push(OrderingIssue::ChunkOutOfOrder { chunk: chunk.id, after }),
+                    _ => latest = Some((rank, chunk.id)),
+                }
+            }
+
+            if let Some(id) = chunk.subobj_id {
+                if let Some(after) = last_subobj_id.filter(|&after| id <= after) {
+                    issues.push(OrderingIssue::SubObjectOutOfOrder { id, after });
+                }
+                last_subobj_id = Some(id);
+            }
+        }
+
+        issues
+    }
+}
+
+pub fn inspect(mut file: impl Read + Seek) -> io::Result<FileInspection> {
+    fn read_bytes<const N: usize>(file: &mut impl Read) -> io::Result<[u8; N]> {
+        let mut buffer = [0; N];
+        file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
+
+    if &read_bytes(&mut file)? != b"PSPO" {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Not a freespace 2 pof file!"));
+    }
+    let version = i32::from_le_bytes(read_bytes(&mut file)?);
+
+    let mut chunks = vec![];
+    loop {
+        let offset = file.stream_position()?;
+        let id = match read_bytes::<4>(&mut file) {
+            Ok(id) => id,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+        let len = u32::from_le_bytes(read_bytes(&mut file)?);
+
+        let subobj_id = if &id == b"OBJ2" || &id == b"SOBJ" {
+            Some(u32::from_le_bytes(read_bytes(&mut file)?))
+        } else {
+            None
+        };
+
+        chunks.push(ChunkInfo { id, offset, len, subobj_id });
+        file.seek(SeekFrom::Start(offset + 8 + len as u64))?;
+    }
+
+    Ok(FileInspection { version, chunks })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::ObjectId;
+
+    fn inspect_model(model: &crate::Model) -> FileInspection {
+        inspect(io::Cursor::new(model.write_to_vec().unwrap())).unwrap()
+    }
+
+    #[test]
+    fn written_in_order() {
+        let mut fighter = sample_fighter(0);
+        // the order the subobjects' children are listed in doesn't change the order they're written in
+        for subobj in fighter.sub_objects.iter_mut() {
+            subobj.children.reverse();
+        }
+
+        let inspection = inspect_model(&fighter);
+        assert_eq!(inspection.ordering_issues(), []);
+        let ranks = inspection.chunks.iter().map(|chunk| chunk_rank(&chunk.id).unwrap()).collect::<Vec<_>>();
+        assert!(ranks.windows(2).all(|pair| pair[0] <= pair[1]), "{:?}", ranks);
+        let subobj_ids = inspection.chunks.iter().filter_map(|chunk| chunk.subobj_id).collect::<Vec<_>>();
+        assert_eq!(subobj_ids, (0..fighter.sub_objects.len() as u32).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parents_go_before_their_children() {
+        let mut fighter = sample_fighter(0);
+        fighter.make_parent(ObjectId(8), ObjectId(7));
+        let inspection = inspect_model(&fighter);
+        assert_eq!(inspection.ordering_issues(), [OrderingIssue::SubObjectOutOfOrder { id: 7, after: 8 }]);
+    }
+
+    #[test]
+    fn misordered_chunks() {
+        let chunk = |id: &[u8; 4], subobj_id: Option<u32>| ChunkInfo { id: *id, offset: 0, len: 0, subobj_id };
+        let inspection = FileInspection {
+            version: 2117,
+            chunks: vec![
+                chunk(b"TXTR", None),
+                chunk(b"HDR2", None),
+                chunk(b"OBJ2", Some(1)),
+                chunk(b"OBJ2", Some(0)),
+                chunk(b"????", None),
+            ],
+        };
+        assert_eq!(
+            inspection.ordering_issues(),
+            [
+                OrderingIssue::HeaderNotFirst,
+                OrderingIssue::ChunkOutOfOrder { chunk: *b"HDR2", after: *b"TXTR" },
+                OrderingIssue::SubObjectOutOfOrder { id: 0, after: 1 },
+            ]
+        );
+        assert_eq!(inspect(io::Cursor::new(b"IDSP\x01\x02\x03\x04")).unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+}
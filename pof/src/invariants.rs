@@ -0,0 +1,344 @@
+// structural consistency checks which should hold for any model, no matter what editing operations were done on it
+// unlike warnings and errors these are never the user's fault, a violation is a bug in whatever made the last change
+// meant for debugging and tests, frontends can assert check_invariants() is empty after their own edits
+use std::fmt::{self, Display};
+
+use crate::{Model, NameLink, ObjectId, TextureId, Vec3d, Warning};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvariantViolation {
+    // field describes where the reference is, e.g. "turret 2 gun"
+    IdOutOfRange { field: String, id: u32, len: usize },
+    WrongObjectId { index: usize, id: ObjectId },
+    ParentChildMismatch { parent: ObjectId, child: ObjectId },
+    ParentCycle(ObjectId),
+    NameLinkInconsistent { id: ObjectId, link: String },
+    BBoxMissesVerts(ObjectId),
+    NonFinite { field: String },
+    // untextured_idx is set, but no polygon uses that texture
+    UnusedUntexturedSlot(TextureId),
+    // untextured_idx is set to a texture whose name isn't one of untextured_names
+    UntexturedSlotMisnamed(TextureId),
+}
+
+impl Display for InvariantViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvariantViolation::IdOutOfRange { field, id, len } => write!(f, "{} refers to {}, but there are only {}", field, id, len),
+            InvariantViolation::WrongObjectId { index, id } => write!(f, "subobject at index {} has id {}", index, id.0),
+            InvariantViolation::ParentChildMismatch { parent, child } => {
+                write!(f, "subobject {} and its parent {} disagree about being parent and child", child.0, parent.0)
+            }
+            InvariantViolation::ParentCycle(id) => write!(f, "subobject {} is its own ancestor", id.0),
+            InvariantViolation::NameLinkInconsistent { id, link } => write!(f, "subobject {} has the stale name link {}", id.0, link),
+            InvariantViolation::BBoxMissesVerts(id) => write!(f, "subobject {}'s bounding box doesn't contain all its vertices", id.0),
+            InvariantViolation::NonFinite { field } => write!(f, "{} is NaN or infinite", field),
+            InvariantViolation::UnusedUntexturedSlot(id) => write!(f, "the untextured slot is texture {}, which no polygon uses", id.0),
+            InvariantViolation::UntexturedSlotMisnamed(id) => write!(f, "the untextured slot is texture {}, which isn't named as one", id.0),
+        }
+    }
+}
+
+// the field descriptions are only built when there's a violation to report
+fn check_id(out: &mut Vec<InvariantViolation>, field: impl FnOnce() -> String, id: u32, len: usize) {
+    if id as usize >= len {
+        out.push(InvariantViolation::IdOutOfRange { field: field(), id, len });
+    }
+}
+
+fn check_finite(out: &mut Vec<InvariantViolation>, field: impl FnOnce() -> String, v: Vec3d) {
+    if !(v.x.is_finite() && v.y.is_finite() && v.z.is_finite()) {
+        out.push(InvariantViolation::NonFinite { field: field() });
+    }
+}
+
+impl Model {
+    pub fn check_invariants(&self) -> Vec<InvariantViolation> {
+        let mut out = vec![];
+        let num_subobjs = self.sub_objects.len();
+
+        for (i, subobj) in self.sub_objects.iter().enumerate() {
+            let id = subobj.obj_id;
+            if id.0 as usize != i {
+                out.push(InvariantViolation::WrongObjectId { index: i, id });
+            }
+
+            if let Some(parent) = subobj.parent() {
+                check_id(&mut out, || format!("subobject {} parent", i), parent.0, num_subobjs);
+                if let Some(parent_obj) = self.sub_objects.get(parent.0 as usize) {
+                    if parent_obj.children().filter(|&&child| child == id).count() != 1 {
+                        out.push(InvariantViolation::ParentChildMismatch { parent, child: id });
+                    }
+                }
+            }
+            for &child in subobj.children() {
+                check_id(&mut out, || format!("subobject {} child", i), child.0, num_subobjs);
+                if let Some(child_obj) = self.sub_objects.get(child.0 as usize) {
+                    if child_obj.parent() != Some(id) {
+                        out.push(InvariantViolation::ParentChildMismatch { parent: id, child });
+                    }
+                }
+            }
+
+            // a chain of parents longer than the number of subobjects must loop
+            let mut ancestor = subobj.parent();
+            for _ in 0..num_subobjs {
+                ancestor = ancestor
+                    .and_then(|ancestor| self.sub_objects.get(ancestor.0 as usize))
+                    .and_then(|obj| obj.parent());
+            }
+            if ancestor.is_some() {
+                out.push(InvariantViolation::ParentCycle(id));
+            }
+
+            let bsp = &subobj.bsp_data;
+            for (_, poly) in bsp.collision_tree.leaves() {
+                check_id(&mut out, || format!("subobject {} polygon texture", i), poly.texture.0, self.textures.len());
+                for vert in &poly.verts {
+                    check_id(&mut out, || format!("subobject {} polygon vertex", i), vert.vertex_id.0, bsp.verts.len());
+                    check_id(&mut out, || format!("subobject {} polygon normal", i), vert.normal_id.0, bsp.norms.len());
+                }
+                check_finite(&mut out, || format!("subobject {} polygon normal", i), poly.normal);
+            }
+            for &vert in &bsp.verts {
+                check_finite(&mut out, || format!("subobject {} vertex", i), vert);
+            }
+            for &norm in &bsp.norms {
+                check_finite(&mut out, || format!("subobject {} normal", i), norm);
+            }
+            check_finite(&mut out, || format!("subobject {} offset", i), subobj.offset);
+            check_finite(&mut out, || format!("subobject {} bbox", i), subobj.bbox.min);
+            check_finite(&mut out, || format!("subobject {} bbox", i), subobj.bbox.max);
+            if !subobj.radius.is_finite() {
+                out.push(InvariantViolation::NonFinite { field: format!("subobject {} radius", i) });
+            }
+
+            // a bad bbox the user has already been warned about isn't a bug
+            let warned = self.warnings.contains(&Warning::InvertedBBox(Some(id))) || self.warnings.contains(&Warning::BBoxTooSmall(Some(id)));
+            let bbox = &subobj.bbox;
+            let epsilon = 0.001 * (1.0 + subobj.radius);
+            let outside = |v: Vec3d| {
+                v.x < bbox.min.x - epsilon
+                    || v.y < bbox.min.y - epsilon
+                    || v.z < bbox.min.z - epsilon
+                    || v.x > bbox.max.x + epsilon
+                    || v.y > bbox.max.y + epsilon
+                    || v.z > bbox.max.z + epsilon
+            };
+            // only vertices which are part of a polygon, unused ones don't exist as far as the engine is concerned
+            let mut used_verts = bsp.collision_tree.leaves().flat_map(|(_, poly)| &poly.verts);
+            if !warned && used_verts.any(|vert| bsp.verts.get(vert.vertex_id.0 as usize).is_some_and(|&v| outside(v))) {
+                out.push(InvariantViolation::BBoxMissesVerts(id));
+            }
+
+            for link in &subobj.name_links {
+                if !self.name_link_consistent(id, link) {
+                    out.push(InvariantViolation::NameLinkInconsistent { id, link: format!("{:?}", link) });
+                }
+            }
+        }
+
+        for (i, &id) in self.header.detail_levels.iter().enumerate() {
+            check_id(&mut out, || format!("detail level {}", i), id.0, num_subobjs);
+        }
+        check_finite(&mut out, || format!("header bbox"), self.header.bbox.min);
+        check_finite(&mut out, || format!("header bbox"), self.header.bbox.max);
+        check_finite(&mut out, || format!("header center of mass"), self.header.center_of_mass);
+        check_finite(&mut out, || format!("visual center"), self.visual_center);
+        if !self.header.max_radius.is_finite() || !self.header.mass.is_finite() {
+            out.push(InvariantViolation::NonFinite { field: format!("header radius or mass") });
+        }
+        if let Some(idx) = self.untextured_idx {
+            check_id(&mut out, || format!("untextured slot"), idx.0, self.textures.len());
+            let used = (self.sub_objects.iter()).any(|subobj| subobj.bsp_data.collision_tree.leaves().any(|(_, poly)| poly.texture == idx));
+            if !used {
+                out.push(InvariantViolation::UnusedUntexturedSlot(idx));
+            }
+            if matches!(self.textures.get(idx.0 as usize), Some(name) if !self.untextured_names.matches(name)) {
+                out.push(InvariantViolation::UntexturedSlotMisnamed(idx));
+            }
+        }
+
+        for (i, turret) in self.turrets.iter().enumerate() {
+            check_id(&mut out, || format!("turret {} base", i), turret.base_obj.0, num_subobjs);
+            check_id(&mut out, || format!("turret {} gun", i), turret.gun_obj.0, num_subobjs);
+            check_finite(&mut out, || format!("turret {} normal", i), turret.normal.0);
+            for &point in &turret.fire_points {
+                check_finite(&mut out, || format!("turret {} fire point", i), point);
+            }
+        }
+        for (i, bank) in self.glow_banks.iter().enumerate() {
+            check_id(&mut out, || format!("glow bank {} parent", i), bank.obj_parent.0, num_subobjs);
+            for point in &bank.glow_points {
+                check_finite(&mut out, || format!("glow bank {} point", i), point.position);
+                check_finite(&mut out, || format!("glow bank {} point normal", i), point.normal);
+            }
+        }
+        for (i, eye) in self.eye_points.iter().enumerate() {
+            if let Some(id) = eye.attached_subobj {
+                check_id(&mut out, || format!("eye point {} subobject", i), id.0, num_subobjs);
+            }
+            check_finite(&mut out, || format!("eye point {}", i), eye.position);
+            check_finite(&mut out, || format!("eye point {} normal", i), eye.normal.0);
+        }
+        for (i, attachment) in self.attachments.iter().enumerate() {
+            if let Some(id) = attachment.parent {
+                check_id(&mut out, || format!("attachment {} parent", i), id.0, num_subobjs);
+            }
+            check_finite(&mut out, || format!("attachment {}", i), attachment.position);
+            check_finite(&mut out, || format!("attachment {} fvec", i), attachment.fvec.0);
+            check_finite(&mut out, || format!("attachment {} uvec", i), attachment.uvec.0);
+        }
+        for (i, dock) in self.docking_bays.iter().enumerate() {
+            if let Some(path) = dock.path {
+                check_id(&mut out, || format!("docking bay {} path", i), path.0, self.paths.len());
+            }
+            check_finite(&mut out, || format!("docking bay {}", i), dock.position);
+            check_finite(&mut out, || format!("docking bay {} fvec", i), dock.fvec.0);
+            check_finite(&mut out, || format!("docking bay {} uvec", i), dock.uvec.0);
+        }
+        for (i, path) in self.paths.iter().enumerate() {
+            for point in &path.points {
+                check_finite(&mut out, || format!("path {} point", i), point.position);
+                for turret in &point.turrets {
+                    check_id(&mut out, || format!("path {} point turret", i), turret.0, num_subobjs);
+                }
+            }
+        }
+        for (primary, banks) in [(true, &self.primary_weps), (false, &self.secondary_weps)] {
+            for (i, bank) in banks.iter().enumerate() {
+                for point in bank {
+                    let kind = if primary { "primary" } else { "secondary" };
+                    check_finite(&mut out, || format!("{} bank {} point", kind, i), point.position);
+                    check_finite(&mut out, || format!("{} bank {} point normal", kind, i), point.normal.0);
+                }
+            }
+        }
+        for (i, bank) in self.thruster_banks.iter().enumerate() {
+            for glow in &bank.glows {
+                check_finite(&mut out, || format!("thruster bank {} glow", i), glow.position);
+                check_finite(&mut out, || format!("thruster bank {} glow normal", i), glow.normal.0);
+            }
+        }
+        for (i, point) in self.special_points.iter().enumerate() {
+            check_finite(&mut out, || format!("special point {}", i), point.position);
+        }
+
+        out
+    }
+
+    // whether the link agrees with the names, and the subobject it points to links back
+    fn name_link_consistent(&self, id: ObjectId, link: &NameLink) -> bool {
+        let (target, back_link_matches): (ObjectId, &dyn Fn(&NameLink) -> bool) = match *link {
+            NameLink::DestroyedVersion(target) => (target, &|link| matches!(link, NameLink::DestroyedVersionOf(back) if *back == id)),
+            NameLink::DestroyedVersionOf(target) => (target, &|link| matches!(link, NameLink::DestroyedVersion(back) if *back == id)),
+            NameLink::LiveDebris(target) => (target, &|link| matches!(link, NameLink::LiveDebrisOf(back) if *back == id)),
+            NameLink::LiveDebrisOf(target) => (target, &|link| matches!(link, NameLink::LiveDebris(back) if *back == id)),
+            NameLink::DetailLevel(target, level) => {
+                (target, &move |link| matches!(link, NameLink::DetailLevelOf(back, back_level) if *back == id && *back_level == level))
+            }
+            NameLink::DetailLevelOf(target, level) => {
+                (target, &move |link| matches!(link, NameLink::DetailLevel(back, back_level) if *back == id && *back_level == level))
+            }
+        };
+        let Some(target_obj) = self.sub_objects.get(target.0 as usize) else {
+            return false;
+        };
+        let name = &self.sub_objects[id].name;
+        let names_match = match *link {
+            NameLink::DestroyedVersion(_) => target_obj.name.strip_suffix("-destroyed") == Some(name),
+            NameLink::DestroyedVersionOf(_) => name.strip_suffix("-destroyed") == Some(&target_obj.name),
+            NameLink::LiveDebris(_) => matches!(target_obj.name.split_once("debris-"), Some((_, debris_of)) if debris_of.starts_with(name.as_str())),
+            NameLink::LiveDebrisOf(_) => {
+                matches!(name.split_once("debris-"), Some((_, debris_of)) if debris_of.starts_with(target_obj.name.as_str()))
+            }
+            NameLink::DetailLevel(..) | NameLink::DetailLevelOf(..) => {
+                name.len() == target_obj.name.len() && name.chars().zip(target_obj.name.chars()).filter(|(c1, c2)| c1 != c2).count() == 1
+            }
+        };
+        names_match && target_obj.name_links.iter().any(back_link_matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::test_util::{random_model, Rng};
+    use crate::{SubObject, UntexturedNames};
+
+    #[test]
+    fn generated_models_are_consistent() {
+        for seed in 0..50 {
+            assert_eq!(random_model(&mut Rng::new(seed)).check_invariants(), [], "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn broken_references_are_reported() {
+        let mut model = random_model(&mut Rng::new(3));
+        // parented to detail0, without detail0 listing it as a child
+        let stray = ObjectId(model.sub_objects.len() as u32);
+        model.sub_objects.push(SubObject {
+            obj_id: stray,
+            parent: Some(ObjectId(0)),
+            name: "stray".to_string(),
+            ..Default::default()
+        });
+        model.header.detail_levels.push(ObjectId(99));
+        model.sub_objects[ObjectId(0)].bsp_data.verts[0].x = f32::NAN;
+
+        let violations = model.check_invariants();
+        assert!(violations.contains(&InvariantViolation::ParentChildMismatch { parent: ObjectId(0), child: stray }));
+        assert!(violations
+            .iter()
+            .any(|violation| matches!(violation, InvariantViolation::IdOutOfRange { id: 99, .. })));
+        assert!(violations
+            .iter()
+            .any(|violation| matches!(violation, InvariantViolation::NonFinite { .. })));
+    }
+
+    #[test]
+    fn stale_untextured_slot() {
+        let mut fighter = sample_fighter(0);
+        // the fittings' polygons become untextured, and the slot takes the removed texture's place
+        fighter.remove_texture(TextureId(1));
+        assert_eq!(fighter.textures, ["fighter-hull", UntexturedNames::default().slot_name()]);
+        assert_eq!(fighter.untextured_idx, Some(TextureId(1)));
+        assert!(fighter.warnings.contains(&Warning::UntexturedPolygons));
+        assert_eq!(fighter.check_invariants(), []);
+
+        // retexturing them without a refresh leaves the index behind
+        for subobj in fighter.sub_objects.iter_mut() {
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves_mut() {
+                poly.texture = TextureId(0);
+            }
+        }
+        assert_eq!(fighter.check_invariants(), [InvariantViolation::UnusedUntexturedSlot(TextureId(1))]);
+
+        // the slot stays in the list, but it's no longer the untextured slot
+        fighter.refresh_untextured_slot();
+        assert_eq!(fighter.textures.len(), 2);
+        assert_eq!(fighter.untextured_idx, None);
+        assert!(!fighter.warnings.contains(&Warning::UntexturedPolygons));
+        assert_eq!(fighter.check_invariants(), []);
+    }
+
+    #[test]
+    fn misnamed_untextured_slot() {
+        let mut fighter = sample_fighter(0);
+        fighter.untextured_names = UntexturedNames::new("NoTexture", ["notex"]);
+        fighter.remove_texture(TextureId(1));
+        assert_eq!(fighter.textures[1], "NoTexture");
+        // any of the names will do
+        fighter.textures[1] = "notex".to_string();
+        assert_eq!(fighter.check_invariants(), []);
+
+        // renaming it without a refresh leaves the index on what is now an ordinary texture
+        fighter.textures[1] = "fighter-fittings".to_string();
+        assert_eq!(fighter.check_invariants(), [InvariantViolation::UntexturedSlotMisnamed(TextureId(1))]);
+        #[allow(deprecated)]
+        fighter.revalidate_untextured();
+        assert_eq!((fighter.untextured_idx, fighter.check_invariants()), (None, vec![]));
+    }
+}
@@ -1,11 +1,65 @@
 // #![warn(missing_docs)]
 #![allow(clippy::useless_format)]
 
+pub mod attachments;
+pub mod bootstrap;
+pub mod carve;
+pub mod clearance;
+pub mod collision_trees;
+pub mod components;
+pub mod coplanar;
+mod geometry_cache;
+pub mod geometry_diff;
+pub mod health;
+pub mod history;
+pub mod inspect;
+pub mod invariants;
+pub mod manifest;
+#[cfg(feature = "serde")]
+mod metadata;
+pub mod operations;
+pub mod orientation;
+pub mod outliers;
 mod parse;
+pub mod points_csv;
+pub mod preview;
+pub mod properties_table;
+pub mod proxy;
+pub mod quirks;
+pub mod rename;
+pub mod repair;
+#[cfg(any(test, feature = "test-util"))]
+pub mod samples;
+pub mod spatial;
+pub mod summary;
+#[cfg(any(test, feature = "test-util"))]
+pub mod test_util;
+pub mod texture_annotations;
+pub mod texture_audit;
+pub mod tolerance;
+pub mod tool_metadata;
+pub mod turret_arc;
 mod types;
+pub mod uv_coverage;
+pub mod variants;
+pub mod weld;
 mod write;
 
 pub use parse::parse_dae;
+pub use parse::parse_dae_with;
 pub use parse::parse_gltf;
+pub use parse::ImportOptions;
+pub use parse::ImportReport;
+pub use parse::ParseError;
+pub use parse::ParseOptions;
 pub use parse::Parser;
+pub use repair::repair_file;
+pub use summary::write_summary_sidecar;
+pub use texture_audit::audit_texture_names;
 pub use types::*;
+pub use variants::derive_variant;
+pub use write::export_comparison_gltf;
+pub use write::ComparisonSummary;
+pub use write::SubObjectComparison;
+pub use write::WriteOptions;
+pub use write::WriteRefusal;
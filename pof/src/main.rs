@@ -1,13 +1,10 @@
 // #![warn(missing_docs)]
 #![allow(clippy::useless_format)]
 
-mod parse;
-mod types;
-mod write;
-
-pub use parse::parse_dae;
-pub use parse::Parser;
-pub use types::*;
+// the library's modules aren't included again, so the binary shares the library's build and tests
+pub use pof::parse_dae;
+pub use pof::Parser;
+pub use pof::*;
 
 use std::fs::File;
 use std::io::{self, Write};
@@ -0,0 +1,283 @@
+// generating ships.tbl style subsystem lists from a model, and checking existing ones against it
+use std::fmt::{self, Display};
+
+use crate::{properties_get_field, Model, ObjectId, SubsysRotationAxis, SubsysRotationType, MAX_NAME_LEN};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SubsystemSource {
+    SubObject(ObjectId),
+    SpecialPoint(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TurretLink {
+    pub base: String,
+    pub gun: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubsystemEntry {
+    // the name as the engine will see it
+    pub name: String,
+    pub source: SubsystemSource,
+    // a starting point for the hitpoint percentage, going by its size relative to the hull
+    pub suggested_percentage: u32,
+    pub turret: Option<TurretLink>,
+    pub rotation: Option<(SubsysRotationType, SubsysRotationAxis)>,
+}
+
+impl Display for SubsystemEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // the turn rate is only a placeholder for rotating subsystems, the pof has no notion of it
+        let turn_rate = if self.rotation.is_some() { 1.0 } else { 0.0 };
+        write!(f, "$Subsystem: {}, {}, {:.1}", self.name, self.suggested_percentage, turn_rate)?;
+        if let Some(turret) = &self.turret {
+            write!(f, " ; turret base '{}', gun '{}'", turret.base, turret.gun)?;
+        }
+        Ok(())
+    }
+}
+
+// a $Subsystem entry as read from a table by some external tool
+#[derive(Debug, Clone)]
+pub struct ParsedTableSubsystem {
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ManifestMismatch {
+    OnlyInModel(String),
+    OnlyInTable(String),
+    // names which are only the same once cut to MAX_NAME_LEN, so either one was cut short, or they're different names
+    // sharing a long start; the model's is given in full
+    NameMismatch { model: String, table: String },
+}
+
+// names longer than this get cut off when the engine reads them
+fn truncate_name(name: &str) -> &str {
+    match name.char_indices().nth(MAX_NAME_LEN) {
+        Some((idx, _)) => &name[..idx],
+        None => name,
+    }
+}
+
+// model and table subsystem names are paired by this one rule: trimmed, cut to MAX_NAME_LEN characters, and lowercased
+// a pair whose names differ before the cut is still a pair, but is reported as a ManifestMismatch::NameMismatch
+pub fn normalize_subsystem_name(name: &str) -> String {
+    truncate_name(name.trim()).to_lowercase()
+}
+
+impl Model {
+    pub fn subsystem_manifest(&self) -> Vec<SubsystemEntry> {
+        let hull_volume = self.header.bbox.volume();
+        let percentage = |volume: f32| {
+            if hull_volume > 0.0 {
+                ((volume / hull_volume * 100.0).round() as u32).clamp(1, 100)
+            } else {
+                1
+            }
+        };
+
+        let mut entries = vec![];
+        for subobj in &self.sub_objects {
+            let turret = self.turrets.iter().find(|turret| turret.base_obj == subobj.obj_id);
+            if !subobj.is_subsystem() && turret.is_none() {
+                continue;
+            }
+
+            entries.push(SubsystemEntry {
+                name: truncate_name(&subobj.name).to_string(),
+                source: SubsystemSource::SubObject(subobj.obj_id),
+                suggested_percentage: percentage(subobj.bbox.volume()),
+                turret: turret.map(|turret| TurretLink {
+                    base: self.sub_objects[turret.base_obj].name.clone(),
+                    gun: self.sub_objects[turret.gun_obj].name.clone(),
+                }),
+                rotation: (subobj.rotation_type != SubsysRotationType::None).then_some((subobj.rotation_type, subobj.rotation_axis)),
+            });
+        }
+
+        for (i, point) in self.special_points.iter().enumerate() {
+            if properties_get_field(&point.properties, "$special") != Some("subsystem") {
+                continue;
+            }
+
+            let volume = 4.0 / 3.0 * std::f32::consts::PI * point.radius.powi(3);
+            entries.push(SubsystemEntry {
+                name: truncate_name(self.subsystem_full_name(SubsystemSource::SpecialPoint(i))).to_string(),
+                source: SubsystemSource::SpecialPoint(i),
+                suggested_percentage: percentage(volume),
+                turret: None,
+                rotation: None,
+            });
+        }
+
+        entries
+    }
+
+    // the subsystem's name before it's cut to MAX_NAME_LEN
+    fn subsystem_full_name(&self, source: SubsystemSource) -> &str {
+        match source {
+            SubsystemSource::SubObject(id) => &self.sub_objects[id].name,
+            SubsystemSource::SpecialPoint(i) => {
+                // the engine drops the leading '$' of special point names
+                let name = &self.special_points[i].name;
+                name.strip_prefix('$').unwrap_or(name)
+            }
+        }
+    }
+
+    // pairs the manifest with the table by normalize_subsystem_name
+    pub fn diff_against_manifest(&self, entries: &[ParsedTableSubsystem]) -> Vec<ManifestMismatch> {
+        let manifest = self.subsystem_manifest();
+        let mut mismatches = vec![];
+
+        for entry in &manifest {
+            let key = normalize_subsystem_name(&entry.name);
+            let full_name = self.subsystem_full_name(entry.source);
+            match entries.iter().find(|table_entry| normalize_subsystem_name(&table_entry.name) == key) {
+                None => mismatches.push(ManifestMismatch::OnlyInModel(entry.name.clone())),
+                // the same rule as the pairing, only without the cut
+                Some(table_entry) if full_name.trim().to_lowercase() != table_entry.name.trim().to_lowercase() => {
+                    mismatches.push(ManifestMismatch::NameMismatch {
+                        model: full_name.to_string(),
+                        table: table_entry.name.clone(),
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+
+        for table_entry in entries {
+            let key = normalize_subsystem_name(&table_entry.name);
+            if !manifest.iter().any(|entry| normalize_subsystem_name(&entry.name) == key) {
+                mismatches.push(ManifestMismatch::OnlyInTable(table_entry.name.clone()));
+            }
+        }
+
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoundingBox, ObjVec, SpecialPoint, SubObject, Turret, Vec3d};
+
+    const LONG_NAME: &str = "CommunicationsArray_Starboard_Long";
+
+    fn cube(half_size: f32) -> BoundingBox {
+        BoundingBox {
+            min: Vec3d::new(-half_size, -half_size, -half_size),
+            max: Vec3d::new(half_size, half_size, half_size),
+        }
+    }
+
+    // a hull, a turret, a subsystem subobject with a name too long for the engine, and a subsystem special point
+    fn mixed_model() -> Model {
+        let subobj = |id: u32, name: &str, bbox: BoundingBox, properties: &str| SubObject {
+            obj_id: ObjectId(id),
+            name: name.to_string(),
+            bbox,
+            properties: properties.to_string(),
+            ..Default::default()
+        };
+        let mut model = Model {
+            sub_objects: ObjVec(vec![
+                subobj(0, "detail0", cube(10.0), ""),
+                subobj(1, "turret01", cube(1.0), ""),
+                subobj(2, "turret01-arm", cube(0.5), ""),
+                subobj(3, LONG_NAME, cube(4.0), "$special=subsystem"),
+            ]),
+            turrets: vec![Turret {
+                base_obj: ObjectId(1),
+                gun_obj: ObjectId(2),
+                ..Default::default()
+            }],
+            special_points: vec![
+                SpecialPoint {
+                    name: "$Engine01".to_string(),
+                    properties: "$special=subsystem".to_string(),
+                    radius: 5.0,
+                    ..Default::default()
+                },
+                SpecialPoint {
+                    name: "$shield01".to_string(),
+                    properties: "$special=shieldpoint".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        model.header.bbox = cube(10.0);
+        model.sub_objects[ObjectId(3)].rotation_type = SubsysRotationType::Regular;
+        model.sub_objects[ObjectId(3)].rotation_axis = SubsysRotationAxis::Y;
+        model
+    }
+
+    fn table(names: &[&str]) -> Vec<ParsedTableSubsystem> {
+        names.iter().map(|name| ParsedTableSubsystem { name: name.to_string() }).collect()
+    }
+
+    #[test]
+    fn normalized_names() {
+        assert_eq!(normalize_subsystem_name("  Turret01 "), "turret01");
+        assert_eq!(normalize_subsystem_name(LONG_NAME), "communicationsarray_starboard_l");
+        assert_eq!(normalize_subsystem_name(LONG_NAME).len(), MAX_NAME_LEN);
+    }
+
+    #[test]
+    fn manifest_of_mixed_subsystems() {
+        let manifest = mixed_model().subsystem_manifest();
+        let names = manifest.iter().map(|entry| entry.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["turret01", "CommunicationsArray_Starboard_L", "Engine01"]);
+        let sources = manifest.iter().map(|entry| entry.source).collect::<Vec<_>>();
+        assert_eq!(
+            sources,
+            [
+                SubsystemSource::SubObject(ObjectId(1)),
+                SubsystemSource::SubObject(ObjectId(3)),
+                SubsystemSource::SpecialPoint(0)
+            ]
+        );
+
+        // by volume relative to the hull's bbox, at least 1
+        let percentages = manifest.iter().map(|entry| entry.suggested_percentage).collect::<Vec<_>>();
+        assert_eq!(percentages, [1, 6, 7]);
+
+        assert_eq!(
+            manifest[0].turret,
+            Some(TurretLink {
+                base: "turret01".to_string(),
+                gun: "turret01-arm".to_string()
+            })
+        );
+        assert_eq!(manifest[1].rotation, Some((SubsysRotationType::Regular, SubsysRotationAxis::Y)));
+        assert_eq!(manifest[2].rotation, None);
+        assert_eq!(manifest[0].to_string(), "$Subsystem: turret01, 1, 0.0 ; turret base 'turret01', gun 'turret01-arm'");
+        assert_eq!(manifest[1].to_string(), "$Subsystem: CommunicationsArray_Starboard_L, 6, 1.0");
+    }
+
+    #[test]
+    fn diff_against_table() {
+        let model = mixed_model();
+        // case and the cut don't matter when the full names agree
+        assert_eq!(model.diff_against_manifest(&table(&["TURRET01", &LONG_NAME.to_lowercase(), "engine01"])), []);
+
+        assert_eq!(
+            model.diff_against_manifest(&table(&["turret01", LONG_NAME, "radar01"])),
+            [
+                ManifestMismatch::OnlyInModel("Engine01".to_string()),
+                ManifestMismatch::OnlyInTable("radar01".to_string())
+            ]
+        );
+
+        // the table's name was cut short, or is a different one which starts the same, and only pairs up because of the cut
+        for table_name in ["CommunicationsArray_Starboard_L", "CommunicationsArray_Starboard_Lower"] {
+            assert_eq!(
+                model.diff_against_manifest(&table(&["turret01", table_name, "Engine01"])),
+                [ManifestMismatch::NameMismatch { model: LONG_NAME.to_string(), table: table_name.to_string() }]
+            );
+        }
+    }
+}
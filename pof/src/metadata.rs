@@ -0,0 +1,191 @@
+// a json description of everything in a model but its geometry, for tools which want to know about a ship without parsing pofs
+// needs the `serde` feature, and `flate2` as well for the compressed export
+use serde_json::{json, Value};
+
+use crate::tolerance::ToleranceMargin;
+use crate::{Model, ObjectId, Vec3d};
+
+fn vec(v: Vec3d) -> Value {
+    json!([v.x, v.y, v.z])
+}
+
+fn margin(margin: ToleranceMargin) -> Value {
+    match margin {
+        ToleranceMargin::Relative(fraction) => json!({ "relative": fraction }),
+        ToleranceMargin::Absolute(distance) => json!({ "absolute": distance }),
+    }
+}
+
+impl Model {
+    fn metadata_name(&self, id: ObjectId) -> Value {
+        match self.sub_objects.get(id.0 as usize) {
+            Some(subobj) => json!(subobj.name),
+            None => Value::Null,
+        }
+    }
+
+    pub fn metadata_json(&self) -> Value {
+        let banks = |banks: &Vec<Vec<crate::WeaponHardpoint>>| {
+            banks
+                .iter()
+                .map(|bank| {
+                    bank.iter()
+                        .map(|point| json!({ "position": vec(point.position), "normal": vec(point.normal.0), "offset": point.offset }))
+                        .collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let targeting = self.targeting_reference();
+        json!({
+            "version": self.version.to_string(),
+            "tolerance_policy": {
+                "min_extent": self.tolerance_policy.min_extent,
+                "tree_padding": self.tolerance_policy.tree_padding,
+                "radius_margin": margin(self.tolerance_policy.radius_margin),
+                "bbox_margin": margin(self.tolerance_policy.bbox_margin),
+            },
+            "header": {
+                "max_radius": self.header.max_radius,
+                "flags": {
+                    "bits": self.header.obj_flags,
+                    "names": self.header.flags().names(),
+                    "unknown_bits": self.header.flags().unknown_bits(),
+                },
+                "mass": self.header.mass,
+                "center_of_mass": vec(self.header.center_of_mass),
+                "bbox": [vec(self.header.bbox.min), vec(self.header.bbox.max)],
+                "detail_levels": self.header.detail_levels.iter().map(|&id| self.metadata_name(id)).collect::<Vec<_>>(),
+                "visual_center": vec(self.visual_center),
+                "cross_sections": self.header.cross_sections.iter().map(|&(depth, radius)| json!({ "depth": depth, "radius": radius })).collect::<Vec<_>>(),
+            },
+            "targeting": {
+                "center": vec(targeting.center),
+                "autocentered": targeting.autocentered,
+                "bbox_center": vec(targeting.bbox_center),
+                "radius": targeting.radius,
+                "centered_radius": targeting.centered_radius,
+                "autocenter_shift": vec(self.simulate_autocenter_shift()),
+            },
+            "sub_objects": self.sub_objects.iter().map(|subobj| json!({
+                "name": subobj.name,
+                "parent": subobj.parent().map_or(Value::Null, |id| self.metadata_name(id)),
+                "offset": vec(subobj.offset),
+                "radius": subobj.radius,
+                "bbox": [vec(subobj.bbox.min), vec(subobj.bbox.max)],
+                "properties": subobj.properties,
+                "debris": subobj.is_debris_model,
+            })).collect::<Vec<_>>(),
+            "textures": self.textures,
+            "texture_annotations": (self.texture_annotations.iter())
+                .filter_map(|(id, annotation)| Some((self.textures.get(id.0 as usize)?.clone(), annotation.to_json())))
+                .collect::<serde_json::Map<_, _>>(),
+            "primary_banks": banks(&self.primary_weps),
+            "secondary_banks": banks(&self.secondary_weps),
+            "turrets": self.turrets.iter().map(|turret| json!({
+                "base": self.metadata_name(turret.base_obj),
+                "gun": self.metadata_name(turret.gun_obj),
+                "normal": vec(turret.normal.0),
+                "fire_points": turret.fire_points.iter().map(|&point| vec(point)).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "thruster_banks": self.thruster_banks.iter().map(|bank| json!({
+                "properties": bank.properties,
+                "glows": bank.glows.iter().map(|glow| json!({
+                    "position": vec(glow.position),
+                    "normal": vec(glow.normal.0),
+                    "radius": glow.radius,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "glow_banks": self.glow_banks.iter().map(|bank| json!({
+                "parent": self.metadata_name(bank.obj_parent),
+                "lod": bank.lod,
+                "type": bank.glow_type,
+                "disp_time": bank.disp_time,
+                "on_time": bank.on_time,
+                "off_time": bank.off_time,
+                "properties": bank.properties,
+                "points": bank.glow_points.iter().map(|point| json!({
+                    "position": vec(point.position),
+                    "normal": vec(point.normal),
+                    "radius": point.radius,
+                })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "eye_points": self.eye_points.iter().map(|eye| json!({
+                "attached": eye.attached_subobj.map_or(Value::Null, |id| self.metadata_name(id)),
+                "position": vec(eye.position),
+                "normal": vec(eye.normal.0),
+            })).collect::<Vec<_>>(),
+            "attachments": self.attachments.iter().map(|attachment| json!({
+                "name": attachment.name,
+                "parent": attachment.parent.map_or(Value::Null, |id| self.metadata_name(id)),
+                "position": vec(attachment.position),
+                "fvec": vec(attachment.fvec.0),
+                "uvec": vec(attachment.uvec.0),
+            })).collect::<Vec<_>>(),
+            "special_points": self.special_points.iter().map(|point| json!({
+                "name": point.name,
+                "properties": point.properties,
+                "position": vec(point.position),
+                "radius": point.radius,
+            })).collect::<Vec<_>>(),
+            "paths": self.paths.iter().map(|path| json!({
+                "name": path.name,
+                "parent": path.parent,
+                "points": path.points.iter().map(|point| json!({ "position": vec(point.position), "radius": point.radius })).collect::<Vec<_>>(),
+            })).collect::<Vec<_>>(),
+            "docking_bays": self.docking_bays.iter().map(|dock| json!({
+                "properties": dock.properties,
+                "path": dock.path.and_then(|id| self.paths.get(id.0 as usize)).map(|path| path.name.clone()),
+                "position": vec(dock.position),
+                "fvec": vec(dock.fvec.0),
+                "uvec": vec(dock.uvec.0),
+            })).collect::<Vec<_>>(),
+        })
+    }
+
+    // metadata_json, gzip compressed
+    #[cfg(feature = "flate2")]
+    pub fn export_metadata_gz(&self, w: &mut impl std::io::Write) -> std::io::Result<()> {
+        let mut encoder = flate2::write::GzEncoder::new(w, flate2::Compression::default());
+        serde_json::to_writer(&mut encoder, &self.metadata_json())?;
+        encoder.finish()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+
+    #[test]
+    fn fighter_metadata() {
+        let mut fighter = sample_fighter(0);
+        fighter.header.set_flags(crate::HeaderFlags(0x100) | crate::HeaderFlags::AUTOCENTER);
+        let metadata = fighter.metadata_json();
+        assert_eq!(metadata["header"]["flags"], json!({ "bits": 0x102, "names": ["autocenter"], "unknown_bits": 0x100 }));
+        assert_eq!(metadata["version"], json!(fighter.version.to_string()));
+        assert_eq!(metadata["header"]["detail_levels"], json!(["detail0", "detail1", "detail2"]));
+        assert_eq!(metadata["sub_objects"][1]["name"], json!("turret01a"));
+        assert_eq!(metadata["sub_objects"][1]["parent"], json!("detail0"));
+        assert_eq!(metadata["sub_objects"][7]["debris"], json!(true));
+        assert_eq!(metadata["textures"], json!(fighter.textures));
+        assert_eq!(metadata["primary_banks"][0].as_array().unwrap().len(), fighter.primary_weps[0].len());
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn gzipped_metadata_decompresses_to_the_same_json() {
+        use std::io::Read;
+
+        let fighter = sample_fighter(0);
+        let mut gz = vec![];
+        fighter.export_metadata_gz(&mut gz).unwrap();
+        let json = serde_json::to_vec(&fighter.metadata_json()).unwrap();
+        assert!(gz.len() < json.len());
+
+        let mut decompressed = vec![];
+        flate2::read::GzDecoder::new(&gz[..]).read_to_end(&mut decompressed).unwrap();
+        assert_eq!(decompressed, json);
+    }
+}
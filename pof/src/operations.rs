@@ -0,0 +1,331 @@
+// which of the crate's high level operations can be done on a model right now, and what they take
+// frontends can build their menus from this rather than duplicating the checks
+// new operations should be added to OPERATIONS, next to a check mirroring what the operation itself requires
+use crate::{Model, ObjectId, Version, MAX_DEBRIS_OBJECTS, MAX_EYES, MAX_WEAPON_BANK_POINTS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntityId {
+    SubObject(ObjectId),
+    PrimaryBank(usize),
+    SecondaryBank(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamKind {
+    Bool,
+    Integer,
+    Float,
+    Vec3,
+    Text,
+    Axis,
+    // one of a fixed set of names
+    Choice(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamSchema {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub default: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Applicability {
+    Enabled,
+    Disabled(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct OperationDescriptor {
+    // stable, for frontends to refer to
+    pub id: &'static str,
+    pub name: &'static str,
+    pub applicability: Applicability,
+    pub params: &'static [ParamSchema],
+}
+
+pub struct Operation {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub params: &'static [ParamSchema],
+    // whether this operation is meant for this sort of target at all
+    pub applies_to: fn(Option<&EntityId>) -> bool,
+    // the target has already been checked with applies_to, and exists
+    pub check: fn(&Model, Option<&EntityId>) -> Result<(), String>,
+}
+
+fn no_target(target: Option<&EntityId>) -> bool {
+    target.is_none()
+}
+
+fn subobj_target(target: Option<&EntityId>) -> bool {
+    matches!(target, Some(EntityId::SubObject(_)))
+}
+
+fn subobj_id(target: Option<&EntityId>) -> ObjectId {
+    match target {
+        Some(EntityId::SubObject(id)) => *id,
+        _ => unreachable!(),
+    }
+}
+
+pub const OPERATIONS: &[Operation] = &[
+    Operation {
+        id: "add_eye_point",
+        name: "Add Eye Point",
+        params: &[],
+        applies_to: no_target,
+        check: |model, _| {
+            if model.sub_objects.is_empty() {
+                Err(format!("there are no subobjects to attach an eye point to"))
+            } else if model.eye_points.len() >= MAX_EYES {
+                Err(format!("already at maximum eye points ({})", MAX_EYES))
+            } else {
+                Ok(())
+            }
+        },
+    },
+    Operation {
+        id: "set_debris",
+        name: "Set Debris",
+        params: &[ParamSchema { name: "debris", kind: ParamKind::Bool, default: Some("true") }],
+        applies_to: subobj_target,
+        check: |model, target| {
+            let id = subobj_id(target);
+            if model.header.detail_levels.contains(&id) {
+                Err(format!("detail levels can't be debris"))
+            } else if !model.sub_objects[id].is_debris_model && model.num_debris_objects() >= MAX_DEBRIS_OBJECTS {
+                Err(format!("already at maximum debris objects ({})", MAX_DEBRIS_OBJECTS))
+            } else {
+                Ok(())
+            }
+        },
+    },
+    Operation {
+        id: "rename_subobject",
+        name: "Rename",
+        params: &[ParamSchema { name: "new_name", kind: ParamKind::Text, default: None }],
+        applies_to: subobj_target,
+        check: |_, _| Ok(()),
+    },
+    Operation {
+        id: "flatten_branch",
+        name: "Flatten Branch",
+        params: &[],
+        applies_to: subobj_target,
+        check: |model, target| {
+            if model.sub_objects[subobj_id(target)].children().next().is_none() {
+                Err(format!("this subobject has no children to merge"))
+            } else {
+                Ok(())
+            }
+        },
+    },
+    Operation {
+        id: "set_subobject_translation",
+        name: "Set Translation Axis",
+        params: &[ParamSchema { name: "axis", kind: ParamKind::Axis, default: None }],
+        applies_to: subobj_target,
+        check: |model, _| {
+            if model.version < Version::V23_01 {
+                Err(format!("subobject translation needs version {} or later", Version::V23_01))
+            } else {
+                Ok(())
+            }
+        },
+    },
+    Operation {
+        id: "zero_detail0_offset",
+        name: "Zero Detail0 Offset",
+        params: &[],
+        applies_to: no_target,
+        check: |model, _| match model.detail0() {
+            None => Err(format!("there are no detail levels")),
+            Some(detail0) if detail0.offset.is_null() => Err(format!("detail0's offset is already zero")),
+            Some(_) => Ok(()),
+        },
+    },
+    Operation {
+        id: "recalc_visual_center",
+        name: "Recalculate Visual Center",
+        params: &[ParamSchema {
+            name: "method",
+            kind: ParamKind::Choice(&["SurfaceAreaAverage", "BBoxCenter", "CenterOfMass"]),
+            default: Some("SurfaceAreaAverage"),
+        }],
+        applies_to: no_target,
+        check: |model, _| {
+            if model.header.detail_levels.is_empty() {
+                Err(format!("there are no detail levels"))
+            } else {
+                Ok(())
+            }
+        },
+    },
+    Operation {
+        id: "mirror_weapon_bank",
+        name: "Mirror Weapon Bank",
+        params: &[ParamSchema { name: "axis", kind: ParamKind::Axis, default: Some("X") }],
+        applies_to: |target| matches!(target, Some(EntityId::PrimaryBank(_) | EntityId::SecondaryBank(_))),
+        check: |model, target| {
+            let bank = match target {
+                Some(EntityId::PrimaryBank(bank)) => &model.primary_weps[*bank],
+                Some(EntityId::SecondaryBank(bank)) => &model.secondary_weps[*bank],
+                _ => unreachable!(),
+            };
+            if bank.is_empty() {
+                Err(format!("this bank has no points to mirror"))
+            } else if bank.len() >= MAX_WEAPON_BANK_POINTS {
+                Err(format!("already at maximum points in this bank ({})", MAX_WEAPON_BANK_POINTS))
+            } else {
+                Ok(())
+            }
+        },
+    },
+    Operation {
+        id: "import_points_csv",
+        name: "Import Points from CSV",
+        params: &[
+            ParamSchema { name: "path", kind: ParamKind::Text, default: None },
+            ParamSchema {
+                name: "mode",
+                kind: ParamKind::Choice(&["Replace", "Append"]),
+                default: Some("Replace"),
+            },
+            ParamSchema {
+                name: "normals",
+                kind: ParamKind::Choice(&["Normalize", "Reject"]),
+                default: Some("Normalize"),
+            },
+        ],
+        applies_to: no_target,
+        check: |_, _| Ok(()),
+    },
+    Operation {
+        id: "import_properties_table",
+        name: "Import Subobject Properties",
+        params: &[ParamSchema { name: "path", kind: ParamKind::Text, default: None }],
+        applies_to: no_target,
+        check: |model, _| {
+            if model.sub_objects.is_empty() {
+                Err(format!("there are no subobjects"))
+            } else {
+                Ok(())
+            }
+        },
+    },
+];
+
+impl Model {
+    fn entity_exists(&self, entity: &EntityId) -> bool {
+        match *entity {
+            EntityId::SubObject(id) => (id.0 as usize) < self.sub_objects.len(),
+            EntityId::PrimaryBank(bank) => bank < self.primary_weps.len(),
+            EntityId::SecondaryBank(bank) => bank < self.secondary_weps.len(),
+        }
+    }
+
+    // every registered operation which applies to the target (or to the model as a whole, for None), and whether it can be done right now
+    pub fn available_operations(&self, target: Option<EntityId>) -> Vec<OperationDescriptor> {
+        if let Some(entity) = &target {
+            if !self.entity_exists(entity) {
+                return vec![];
+            }
+        }
+
+        OPERATIONS
+            .iter()
+            .filter(|op| (op.applies_to)(target.as_ref()))
+            .map(|op| OperationDescriptor {
+                id: op.id,
+                name: op.name,
+                applicability: match (op.check)(self, target.as_ref()) {
+                    Ok(()) => Applicability::Enabled,
+                    Err(reason) => Applicability::Disabled(reason),
+                },
+                params: op.params,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::EyePoint;
+
+    fn applicability(model: &Model, target: Option<EntityId>, id: &str) -> Option<Applicability> {
+        let ops = model.available_operations(target);
+        ops.into_iter().find(|op| op.id == id).map(|op| op.applicability)
+    }
+
+    fn disabled(reason: &str) -> Option<Applicability> {
+        Some(Applicability::Disabled(reason.to_string()))
+    }
+
+    #[test]
+    fn eye_limit() {
+        let mut fighter = sample_fighter(0);
+        assert_eq!(applicability(&fighter, None, "add_eye_point"), Some(Applicability::Enabled));
+        fighter.eye_points.resize_with(MAX_EYES, EyePoint::default);
+        assert_eq!(applicability(&fighter, None, "add_eye_point"), disabled("already at maximum eye points (9)"));
+        // only whole model operations apply without a target
+        assert_eq!(applicability(&fighter, None, "set_debris"), None);
+    }
+
+    #[test]
+    fn debris_limit() {
+        let mut fighter = sample_fighter(0);
+        let (detail1, debris, hull) = (EntityId::SubObject(ObjectId(5)), EntityId::SubObject(ObjectId(7)), EntityId::SubObject(ObjectId(3)));
+        assert_eq!(applicability(&fighter, Some(hull), "set_debris"), Some(Applicability::Enabled));
+        assert_eq!(applicability(&fighter, Some(detail1), "set_debris"), disabled("detail levels can't be debris"));
+
+        while fighter.num_debris_objects() < MAX_DEBRIS_OBJECTS {
+            let mut copy = fighter.sub_objects[ObjectId(7)].clone();
+            copy.obj_id = ObjectId(fighter.sub_objects.len() as u32);
+            fighter.sub_objects.push(copy);
+        }
+        assert_eq!(applicability(&fighter, Some(hull), "set_debris"), disabled("already at maximum debris objects (32)"));
+        // unflagging one which already is debris is still fine
+        assert_eq!(applicability(&fighter, Some(debris), "set_debris"), Some(Applicability::Enabled));
+    }
+
+    #[test]
+    fn version_gated() {
+        let mut fighter = sample_fighter(0);
+        let turret = Some(EntityId::SubObject(ObjectId(1)));
+        fighter.version = Version::V23_01;
+        assert_eq!(applicability(&fighter, turret, "set_subobject_translation"), Some(Applicability::Enabled));
+        fighter.version = Version::V22_01;
+        assert_eq!(
+            applicability(&fighter, turret, "set_subobject_translation"),
+            disabled(&format!("subobject translation needs version {} or later", Version::V23_01))
+        );
+    }
+
+    #[test]
+    fn targets() {
+        let fighter = sample_fighter(0);
+        // nothing applies to something that isn't there
+        assert!(fighter.available_operations(Some(EntityId::SubObject(ObjectId(100)))).is_empty());
+        assert!(fighter.available_operations(Some(EntityId::PrimaryBank(10))).is_empty());
+
+        let bank_ops = fighter.available_operations(Some(EntityId::PrimaryBank(0)));
+        assert_eq!(bank_ops.iter().map(|op| op.id).collect::<Vec<_>>(), ["mirror_weapon_bank"]);
+        assert_eq!(bank_ops[0].params[0].default, Some("X"));
+        assert_eq!(
+            applicability(&fighter, Some(EntityId::SubObject(ObjectId(5))), "flatten_branch"),
+            disabled("this subobject has no children to merge")
+        );
+        assert_eq!(applicability(&fighter, None, "zero_detail0_offset"), disabled("detail0's offset is already zero"));
+
+        // every operation is offered for some target
+        let mut offered = fighter.available_operations(None);
+        offered.extend(fighter.available_operations(Some(EntityId::SubObject(ObjectId(0)))));
+        offered.extend(bank_ops);
+        for op in OPERATIONS {
+            assert!(offered.iter().any(|offered| offered.id == op.id), "{}", op.id);
+        }
+    }
+}
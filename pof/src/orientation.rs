@@ -0,0 +1,245 @@
+// a sanity check for models imported facing the wrong way, which nothing else in the data makes obvious
+// FSO's convention is +Z forward and +Y up; each signal below is a bit of data which, in a correctly oriented model,
+// points a known way relative to forward, and they're tallied to decide which axis the model actually treats as forward
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use glm::TMat4;
+use nalgebra_glm as glm;
+
+use crate::{Axis, Model, Vec3d};
+
+pub const POF_FORWARD: Vec3d = Vec3d::new(0.0, 0.0, 1.0);
+
+// the forward candidates, every signed axis
+const SIGNED_AXES: [Vec3d; 6] = [
+    Vec3d::new(1.0, 0.0, 0.0),
+    Vec3d::new(-1.0, 0.0, 0.0),
+    Vec3d::new(0.0, 1.0, 0.0),
+    Vec3d::new(0.0, -1.0, 0.0),
+    Vec3d::new(0.0, 0.0, 1.0),
+    Vec3d::new(0.0, 0.0, -1.0),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrientationSignalKind {
+    // thruster glows face out the back, so forward is opposite their normals
+    Thrusters,
+    EyePoints,
+    Weapons,
+    // docking bays face out to the sides, top or bottom, so their consensus axis is unlikely to be forward
+    DockingBays,
+    // ships are generally longest along their direction of travel, this only gives the axis, not which end is the front
+    Elongation,
+    // the axis paths mostly run along, again without a sign
+    Paths,
+}
+impl OrientationSignalKind {
+    // how much the signal is trusted relative to the others
+    fn weight(self) -> f32 {
+        match self {
+            OrientationSignalKind::Thrusters => 1.0,
+            OrientationSignalKind::Weapons => 1.0,
+            OrientationSignalKind::EyePoints => 0.75,
+            OrientationSignalKind::Elongation => 0.5,
+            OrientationSignalKind::DockingBays => 0.25,
+            OrientationSignalKind::Paths => 0.25,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OrientationSignal {
+    pub kind: OrientationSignalKind,
+    // how many points, normals or paths went into it
+    pub samples: usize,
+    // the direction this signal suggests is forward, or for docking bays, the direction they suggest isn't
+    pub direction: Vec3d,
+    // how strongly the samples agree, from 0 to 1
+    pub agreement: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrientationSummary {
+    // only the signals which had any data
+    pub signals: Vec<OrientationSignal>,
+    // the signed axis the model seems to treat as forward, +Z if there are no signals at all
+    pub forward: Vec3d,
+    // 0 when the signals are missing or evenly split, 1 when they all agree
+    pub confidence: f32,
+}
+impl OrientationSummary {
+    pub fn matches_convention(&self) -> bool {
+        self.forward == POF_FORWARD
+    }
+
+    // a rotation for Model::apply_transform which turns the detected forward into +Z, None if it already is
+    pub fn correction(&self) -> Option<TMat4<f32>> {
+        let (x_axis, y_axis) = (glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+        match self.forward {
+            Vec3d { x, .. } if x > 0.5 => Some(glm::rotation(-FRAC_PI_2, &y_axis)),
+            Vec3d { x, .. } if x < -0.5 => Some(glm::rotation(FRAC_PI_2, &y_axis)),
+            Vec3d { y, .. } if y > 0.5 => Some(glm::rotation(FRAC_PI_2, &x_axis)),
+            Vec3d { y, .. } if y < -0.5 => Some(glm::rotation(-FRAC_PI_2, &x_axis)),
+            Vec3d { z, .. } if z < -0.5 => Some(glm::rotation(PI, &y_axis)),
+            _ => None,
+        }
+    }
+}
+
+// the average direction of the vectors, and how much they agree
+fn consensus(kind: OrientationSignalKind, vectors: impl Iterator<Item = Vec3d>) -> Option<OrientationSignal> {
+    let (mut sum, mut samples) = (Vec3d::ZERO, 0);
+    for vector in vectors.filter(|vector| !vector.is_null()) {
+        sum += vector.normalize();
+        samples += 1;
+    }
+    if samples == 0 || sum.is_null() {
+        return None;
+    }
+    let agreement = sum.magnitude() / samples as f32;
+    Some(OrientationSignal { kind, samples, direction: sum.normalize(), agreement })
+}
+
+// which axis the vectors mostly run along, ignoring their sign
+fn axis_consensus(kind: OrientationSignalKind, vectors: impl Iterator<Item = Vec3d>) -> Option<OrientationSignal> {
+    let (mut sum, mut samples) = (Vec3d::ZERO, 0);
+    for vector in vectors.filter(|vector| !vector.is_null()) {
+        let Vec3d { x, y, z } = vector.normalize();
+        sum += Vec3d::new(x.abs(), y.abs(), z.abs());
+        samples += 1;
+    }
+    if samples == 0 {
+        return None;
+    }
+    let mut components = [sum.x, sum.y, sum.z];
+    let axis = if sum.x >= sum.y && sum.x >= sum.z {
+        Axis::X
+    } else if sum.y >= sum.z {
+        Axis::Y
+    } else {
+        Axis::Z
+    };
+    components.sort_by(|a, b| b.total_cmp(a));
+    Some(OrientationSignal {
+        kind,
+        samples,
+        direction: axis_vector(axis),
+        agreement: 1.0 - components[1] / components[0],
+    })
+}
+
+fn axis_vector(axis: Axis) -> Vec3d {
+    match axis {
+        Axis::X => Vec3d::new(1.0, 0.0, 0.0),
+        Axis::Y => Vec3d::new(0.0, 1.0, 0.0),
+        Axis::Z => Vec3d::new(0.0, 0.0, 1.0),
+    }
+}
+
+impl Model {
+    pub fn orientation_summary(&self) -> OrientationSummary {
+        use OrientationSignalKind::*;
+
+        let thrusters = self.thruster_banks.iter().flat_map(|bank| &bank.glows).map(|glow| -glow.normal.0);
+        let weapons = (self.primary_weps.iter().chain(&self.secondary_weps))
+            .flatten()
+            .map(|point| point.normal.0);
+        let paths = self
+            .paths
+            .iter()
+            .flat_map(|path| path.points.windows(2).map(|pair| pair[1].position - pair[0].position));
+        let elongation = (!self.header.bbox.is_inverted()).then(|| {
+            let bbox = &self.header.bbox;
+            let mut sizes = [bbox.x_width(), bbox.y_height(), bbox.z_length()];
+            sizes.sort_by(|a, b| b.total_cmp(a));
+            OrientationSignal {
+                kind: Elongation,
+                samples: 1,
+                direction: axis_vector(bbox.greatest_dimension()),
+                agreement: if sizes[0] > 0.0 { 1.0 - sizes[1] / sizes[0] } else { 0.0 },
+            }
+        });
+
+        let signals = [
+            consensus(Thrusters, thrusters),
+            consensus(EyePoints, self.eye_points.iter().map(|eye| eye.normal.0)),
+            consensus(Weapons, weapons),
+            consensus(DockingBays, self.docking_bays.iter().map(|dock| dock.fvec.0)),
+            elongation,
+            axis_consensus(Paths, paths),
+        ];
+        let signals = signals.into_iter().flatten().filter(|signal| signal.agreement > 0.0).collect::<Vec<_>>();
+
+        let mut scores = [0.0_f32; 6];
+        for signal in &signals {
+            let strength = signal.kind.weight() * signal.agreement;
+            for (score, axis) in scores.iter_mut().zip(SIGNED_AXES) {
+                let alignment = axis.dot(&signal.direction);
+                *score += match signal.kind {
+                    Thrusters | EyePoints | Weapons => strength * alignment.max(0.0),
+                    // split between both ends of the axis
+                    Elongation | Paths => strength * alignment.abs() / 2.0,
+                    DockingBays => -strength * alignment.abs(),
+                };
+            }
+        }
+
+        let total = signals.iter().map(|signal| signal.kind.weight() * signal.agreement).sum::<f32>();
+        // ties go to +Z
+        let is_forward = |i: usize| SIGNED_AXES[i] == POF_FORWARD;
+        let best = (0..6)
+            .max_by(|&a, &b| scores[a].total_cmp(&scores[b]).then(is_forward(a).cmp(&is_forward(b))))
+            .unwrap();
+        let runner_up = (0..6).filter(|&i| i != best).map(|i| scores[i]).fold(f32::MIN, f32::max);
+
+        if total <= 0.0 || scores[best] <= 0.0 {
+            return OrientationSummary { signals, forward: POF_FORWARD, confidence: 0.0 };
+        }
+        OrientationSummary {
+            forward: SIGNED_AXES[best],
+            confidence: ((scores[best] - runner_up) / total).clamp(0.0, 1.0),
+            signals,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+
+    #[test]
+    fn correct_yawed_and_pitched_fighters() {
+        let (x_axis, y_axis) = (glm::vec3(1.0, 0.0, 0.0), glm::vec3(0.0, 1.0, 0.0));
+        let cases = [
+            ("correct", glm::identity(), POF_FORWARD),
+            ("yaw 180", glm::rotation(PI, &y_axis), Vec3d::new(0.0, 0.0, -1.0)),
+            ("pitch 90", glm::rotation(FRAC_PI_2, &x_axis), Vec3d::new(0.0, -1.0, 0.0)),
+        ];
+        for (name, matrix, forward) in cases {
+            let mut fighter = sample_fighter(0);
+            fighter.apply_transform(&matrix).unwrap();
+            let summary = fighter.orientation_summary();
+            assert_eq!(summary.forward, forward, "{}: {:?}", name, summary);
+            assert!(summary.confidence > 0.5, "{}: {:?}", name, summary);
+            assert_eq!(summary.matches_convention(), forward == POF_FORWARD, "{}", name);
+            let kinds = summary.signals.iter().map(|signal| signal.kind).collect::<Vec<_>>();
+            use OrientationSignalKind::*;
+            assert_eq!(kinds, [Thrusters, EyePoints, Weapons, DockingBays, Elongation, Paths], "{}", name);
+
+            // and the suggested fix puts it right
+            match summary.correction() {
+                Some(correction) => fighter.apply_transform(&correction).unwrap(),
+                None => assert_eq!(forward, POF_FORWARD),
+            }
+            assert!(fighter.orientation_summary().matches_convention(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn no_signals() {
+        let summary = Model::default().orientation_summary();
+        assert_eq!((summary.forward, summary.confidence), (POF_FORWARD, 0.0));
+        assert!(summary.correction().is_none());
+    }
+}
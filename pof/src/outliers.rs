@@ -0,0 +1,257 @@
+// finding the few stray vertices far from the rest of a subobject, which blow up its bbox and radius and so hurt culling
+//
+// distances are measured from the median of the vertices, which the strays can't drag around the way they would the mean,
+// and a vertex is a candidate if it's more than OUTLIER_DISTANCE_FACTOR times the given percentile of those distances out;
+// the candidates are only reported if leaving them out would shrink the bbox, by its diagonal since flat subobjects have no
+// volume, or the radius, by at least the given factor, so a subobject which is just unevenly spread out is left alone
+// each outlier is then classed by the connected components (see crate::components) its polygons are in: a component made up
+// of only outliers is junk, like orphaned construction geometry, which remove_bbox_junk can delete, while an outlier in a
+// component which also reaches the rest of the subobject is a spike attached to it, which might be meant to be there
+use std::collections::BTreeSet;
+
+use crate::components::extract_polygons;
+use crate::{BoundingBox, BspData, Model, ObjectId, PolygonId, Set, SubObject, Vec3d, VertexId};
+
+pub const DEFAULT_OUTLIER_PERCENTILE: f32 = 0.95;
+pub const OUTLIER_DISTANCE_FACTOR: f32 = 3.0;
+pub const DEFAULT_MIN_SHRINK: f32 = 1.5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutlierSummary {
+    pub outliers: Vec<VertexId>,
+    // components whose polygons only use outliers, ids in leaf order
+    pub junk_components: Vec<Vec<PolygonId>>,
+    // outliers used by a component which also has vertices among the rest
+    pub attached_spikes: Vec<VertexId>,
+    // outliers no polygon uses at all
+    pub unused: Vec<VertexId>,
+    pub radius: f32,
+    // the radius once the junk components and unused vertices are gone, as remove_bbox_junk would leave it
+    pub radius_without_junk: f32,
+}
+
+fn median(mut vals: Vec<f32>) -> f32 {
+    vals.sort_by(f32::total_cmp);
+    vals[vals.len() / 2]
+}
+
+fn diagonal(verts: impl Iterator<Item = Vec3d>) -> f32 {
+    let bbox = BoundingBox::from_vectors(verts);
+    if bbox.is_inverted() {
+        0.0
+    } else {
+        (bbox.max - bbox.min).magnitude()
+    }
+}
+
+fn radius(verts: impl Iterator<Item = Vec3d>) -> f32 {
+    verts.map(|vert| vert.magnitude()).fold(0.0, f32::max)
+}
+
+impl SubObject {
+    // the outlier vertices, in ascending order, by the default minimum shrink; see the top of outliers.rs
+    pub fn bbox_outliers(&self, percentile: f32) -> Vec<VertexId> {
+        self.bbox_outliers_with(percentile, DEFAULT_MIN_SHRINK)
+    }
+
+    pub fn bbox_outliers_with(&self, percentile: f32, min_shrink: f32) -> Vec<VertexId> {
+        let verts = &self.bsp_data.verts;
+        if verts.len() < 2 {
+            return vec![];
+        }
+
+        let center = Vec3d::new(
+            median(verts.iter().map(|vert| vert.x).collect()),
+            median(verts.iter().map(|vert| vert.y).collect()),
+            median(verts.iter().map(|vert| vert.z).collect()),
+        );
+        let distances = verts.iter().map(|&vert| (vert - center).magnitude()).collect::<Vec<_>>();
+        let mut sorted = distances.clone();
+        sorted.sort_by(f32::total_cmp);
+        let cutoff = sorted[((sorted.len() - 1) as f32 * percentile.clamp(0.0, 1.0)).round() as usize] * OUTLIER_DISTANCE_FACTOR;
+
+        let (outliers, inliers): (Vec<usize>, Vec<usize>) = (0..verts.len()).partition(|&i| distances[i] > cutoff);
+        if outliers.is_empty() || inliers.is_empty() {
+            return vec![];
+        }
+
+        let inlier_verts = || inliers.iter().map(|&i| verts[i]);
+        let shrinks = |full: f32, tight: f32| full > tight * min_shrink;
+        if shrinks(diagonal(verts.iter().copied()), diagonal(inlier_verts())) || shrinks(radius(verts.iter().copied()), radius(inlier_verts())) {
+            outliers.into_iter().map(|i| VertexId(i as u32)).collect()
+        } else {
+            vec![]
+        }
+    }
+
+    fn outlier_summary(&self, percentile: f32) -> Option<OutlierSummary> {
+        let outliers = self.bbox_outliers(percentile);
+        if outliers.is_empty() {
+            return None;
+        }
+        let is_outlier = |id: VertexId| outliers.binary_search(&id).is_ok();
+
+        let polys = self.bsp_data.collision_tree.leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+        let mut used = BTreeSet::new();
+        let mut spikes = BTreeSet::new();
+        let mut junk_components = vec![];
+        for component in self.connected_components() {
+            let component_verts = (component.iter())
+                .flat_map(|poly_id| polys[poly_id.0 as usize].verts.iter().map(|vert| vert.vertex_id))
+                .collect::<BTreeSet<_>>();
+            if component_verts.iter().all(|&id| is_outlier(id)) {
+                junk_components.push(component);
+            } else {
+                spikes.extend(component_verts.iter().copied().filter(|&id| is_outlier(id)));
+            }
+            used.extend(component_verts);
+        }
+        let unused = outliers.iter().copied().filter(|id| !used.contains(id)).collect();
+
+        let junk_polys = junk_components.iter().flatten().collect::<BTreeSet<_>>();
+        let kept_verts = (polys.iter().enumerate())
+            .filter(|(i, _)| !junk_polys.contains(&PolygonId(*i as u32)))
+            .flat_map(|(_, poly)| poly.verts.iter().map(|vert| self.bsp_data.verts[vert.vertex_id.0 as usize]));
+        Some(OutlierSummary {
+            radius: radius(self.bsp_data.verts.iter().copied()),
+            radius_without_junk: radius(kept_verts),
+            outliers,
+            junk_components,
+            attached_spikes: spikes.into_iter().collect(),
+            unused,
+        })
+    }
+}
+
+impl Model {
+    // every subobject with outliers, by DEFAULT_OUTLIER_PERCENTILE and DEFAULT_MIN_SHRINK
+    // this only reports, nothing is removed unless remove_bbox_junk is called
+    pub fn report_bbox_outliers(&self) -> Vec<(ObjectId, OutlierSummary)> {
+        (self.sub_objects.iter())
+            .filter_map(|subobj| Some((subobj.obj_id, subobj.outlier_summary(DEFAULT_OUTLIER_PERCENTILE)?)))
+            .collect()
+    }
+
+    // deletes the subobject's junk components as report_bbox_outliers finds them, along with every vertex and normal no
+    // polygon uses any more, then tightens its bbox and radius; attached spikes are left alone
+    // polygon ids are in leaf order, which this invalidates, since the bsp tree is rebuilt
+    // returns how many polygons were removed, none for a subobject which doesn't exist
+    pub fn remove_bbox_junk(&mut self, id: ObjectId) -> usize {
+        let Some(subobj) = self.sub_objects.get(id.0 as usize) else {
+            return 0;
+        };
+        let Some(summary) = subobj.outlier_summary(DEFAULT_OUTLIER_PERCENTILE) else {
+            return 0;
+        };
+        if summary.junk_components.is_empty() && summary.unused.is_empty() {
+            return 0;
+        }
+
+        let junk_polys = summary.junk_components.iter().flatten().collect::<BTreeSet<_>>();
+        let kept = (subobj.bsp_data.collision_tree.leaves().enumerate())
+            .filter(|(i, _)| !junk_polys.contains(&PolygonId(*i as u32)))
+            .map(|(_, (_, poly))| poly)
+            .collect::<Vec<_>>();
+        let (verts, norms, polygons) = extract_polygons(&subobj.bsp_data, &kept);
+
+        let policy = self.tolerance_policy;
+        let subobj = &mut self.sub_objects[id];
+        subobj.bsp_data = BspData {
+            collision_tree: BspData::recalculate_with(&verts, polygons.into_iter(), &policy),
+            norms,
+            verts,
+        };
+        subobj.recalc_bbox_with(&policy);
+        subobj.recalc_radius_with(&policy);
+
+        self.refresh_untextured_slot();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        junk_polys.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_capital;
+    use crate::{NormalId, PolyVertex, Polygon, TextureId};
+
+    const FAR: f32 = 10_000.0;
+
+    fn hull() -> Model {
+        sample_capital(1, 50, 0)
+    }
+
+    // adds a triangle of new vertices around position to detail0
+    fn add_triangle(model: &mut Model, position: Vec3d) {
+        let bsp_data = &mut model.sub_objects[ObjectId(0)].bsp_data;
+        let first = bsp_data.verts.len() as u32;
+        bsp_data.verts.extend([0.0, 1.0, 2.0].map(|x| position + Vec3d::new(x, x * x, 0.0)));
+        let verts = (first..first + 3)
+            .map(|i| PolyVertex {
+                vertex_id: VertexId(i),
+                normal_id: NormalId(0),
+                uv: (0.0, 0.0),
+            })
+            .collect();
+        let tree = std::mem::take(&mut bsp_data.collision_tree);
+        let triangle = Polygon { normal: Default::default(), texture: TextureId(0), verts };
+        let polygons = tree.into_leaves().map(|(_, poly)| poly).chain([triangle]);
+        bsp_data.collision_tree = BspData::recalculate(&bsp_data.verts, polygons);
+    }
+
+    #[test]
+    fn clean_hulls_have_no_outliers() {
+        let model = hull();
+        assert_eq!(model.report_bbox_outliers(), []);
+        assert_eq!(model.sub_objects[ObjectId(0)].bbox_outliers(DEFAULT_OUTLIER_PERCENTILE), []);
+    }
+
+    #[test]
+    fn teleported_vertex_is_a_spike() {
+        let mut model = hull();
+        model.sub_objects[ObjectId(0)].bsp_data.verts[7] = Vec3d::new(FAR, 0.0, 0.0);
+        assert_eq!(model.sub_objects[ObjectId(0)].bbox_outliers(DEFAULT_OUTLIER_PERCENTILE), [VertexId(7)]);
+
+        let report = model.report_bbox_outliers();
+        assert_eq!(report.len(), 1);
+        let (id, summary) = &report[0];
+        assert_eq!(*id, ObjectId(0));
+        assert_eq!(summary.attached_spikes, [VertexId(7)]);
+        assert!(summary.junk_components.is_empty() && summary.unused.is_empty());
+        assert!(summary.radius >= FAR);
+        // it's still attached, so nothing can go
+        assert_eq!(summary.radius_without_junk, summary.radius);
+        let before = model.sub_objects[ObjectId(0)].bsp_data.clone();
+        assert_eq!(model.remove_bbox_junk(ObjectId(0)), 0);
+        assert_eq!(model.sub_objects[ObjectId(0)].bsp_data.verts, before.verts);
+    }
+
+    #[test]
+    fn junk_is_removed_and_the_radius_shrinks() {
+        let mut model = hull();
+        let clean_radius = model.sub_objects[ObjectId(0)].radius;
+        let polys = model.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves().count();
+        add_triangle(&mut model, Vec3d::new(0.0, FAR, 0.0));
+        // and a vertex no polygon uses
+        model.sub_objects[ObjectId(0)].bsp_data.verts.push(Vec3d::new(0.0, 0.0, -FAR));
+
+        let (_, summary) = &model.report_bbox_outliers()[0];
+        assert_eq!(summary.outliers.len(), 4);
+        assert_eq!(summary.junk_components.len(), 1);
+        assert_eq!(summary.junk_components[0].len(), 1);
+        assert_eq!(summary.unused, [*summary.outliers.last().unwrap()]);
+        assert!(summary.attached_spikes.is_empty());
+        assert!(summary.radius >= FAR && summary.radius_without_junk < FAR / 10.0);
+
+        // reporting changed nothing
+        assert_eq!(model.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves().count(), polys + 1);
+        assert_eq!(model.remove_bbox_junk(ObjectId(0)), 1);
+        let subobj = &model.sub_objects[ObjectId(0)];
+        assert_eq!(subobj.bsp_data.collision_tree.leaves().count(), polys);
+        assert!(subobj.bsp_data.verts.iter().all(|vert| vert.magnitude() < FAR / 10.0));
+        assert!((subobj.radius - clean_radius).abs() < 1e-3, "{} {}", subobj.radius, clean_radius);
+        assert_eq!(model.report_bbox_outliers(), []);
+    }
+}
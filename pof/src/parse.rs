@@ -1,3 +1,9 @@
+use crate::attachments::AttachmentPoint;
+use crate::quirks::{fix_texture_index_off_by_one, Quirk, QuirkSet};
+use crate::repair::{ChunkLengthTrust, ParseNotice};
+use crate::texture_annotations::resolve_texture_annotations;
+use crate::tool_metadata::{read_tool_metadata, ToolMetadata, TOOL_METADATA_CHUNK};
+use crate::weld::{weld_mesh, WeldPolicy};
 use crate::*;
 use byteorder::{ReadBytesExt, LE};
 use core::panic;
@@ -5,13 +11,59 @@ use dae_parser as dae;
 use glm::Mat4x4;
 use log::warn;
 use nalgebra_glm as glm;
+use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::File;
 use std::io::{self, BufReader, ErrorKind, Read, Seek, SeekFrom};
 use std::path::PathBuf;
 
+// why Model::from_bytes couldn't parse the data
+#[derive(Debug)]
+pub enum ParseError {
+    // it doesn't start with the pof file id
+    NotAPof,
+    UnknownVersion(i32),
+    // the data ended early or didn't add up, including errors the parser wraps in an io::Error, like BspTooDeep
+    Io(io::Error),
+}
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::NotAPof => write!(f, "Not a freespace 2 pof file!"),
+            ParseError::UnknownVersion(version) => write!(f, "Unrecognized pof version {}", version),
+            ParseError::Io(err) => write!(f, "{}", err),
+        }
+    }
+}
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
 impl Model {
+    // parses a pof held in memory, e.g. one read out of a vp archive; path_to_file is left empty
+    // unlike Parser::new, data which isn't a pof, or is one of a version this doesn't know, is an error rather than a panic
+    pub fn from_bytes(data: &[u8]) -> Result<Model, ParseError> {
+        if data.len() < 8 || &data[..4] != b"PSPO" {
+            return Err(ParseError::NotAPof);
+        }
+        let version = i32::from_le_bytes(data[4..8].try_into().unwrap());
+        if Version::try_from(version).is_err() {
+            return Err(ParseError::UnknownVersion(version));
+        }
+        Ok(Parser::new(io::Cursor::new(data))?.parse(PathBuf::new())?)
+    }
+
     fn prune_unused_textures(&mut self) {
         // remove unused textures
         // tally up used texture ids
@@ -40,9 +92,51 @@ impl Model {
     }
 }
 
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    // workarounds for files from legacy tools, see crate::quirks
+    pub quirks: QuirkSet,
+    // a bsp or shield tree deeper than this fails the parse with BspTooDeep rather than risk overflowing the stack
+    pub max_bsp_depth: u32,
+    // where to carry on from after a chunk whose length doesn't match its content, see crate::repair
+    pub chunk_lengths: ChunkLengthTrust,
+    // if false, subobjects are loaded without their bsp data, for tools which only edit hardpoints and the like
+    // such a model can't be written until Parser::load_geometry_into has filled it back in
+    pub load_geometry: bool,
+}
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            quirks: Default::default(),
+            max_bsp_depth: MAX_BSP_DEPTH,
+            chunk_lengths: Default::default(),
+            load_geometry: true,
+        }
+    }
+}
+
+// for importing from collada
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    // see crate::weld, by default nothing is welded
+    pub weld: WeldPolicy,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    // (node name, how many vertices were merged away) for each object which had any welded
+    pub welded: Vec<(String, usize)>,
+}
+
 pub struct Parser<R> {
     file: R,
     version: Version,
+    untextured_names: UntexturedNames,
+    options: ParseOptions,
+    normal_notices: Vec<NormalAudit>,
+    parse_notices: Vec<ParseNotice>,
+    // where the file ends, so that lengths read from it can be checked before anything that big is allocated
+    file_len: u64,
 }
 impl<R: Read + Seek> Parser<R> {
     pub fn new(mut file: R) -> io::Result<Parser<R>> {
@@ -52,7 +146,28 @@ impl<R: Read + Seek> Parser<R> {
 
         // println!("The verison is {:?}", version);
 
-        Ok(Parser { file, version })
+        let pos = file.stream_position()?;
+        let file_len = file.seek(SeekFrom::End(0))?;
+        file.seek(SeekFrom::Start(pos))?;
+
+        Ok(Parser {
+            file,
+            version,
+            untextured_names: Default::default(),
+            options: Default::default(),
+            normal_notices: vec![],
+            parse_notices: vec![],
+            file_len,
+        })
+    }
+
+    pub fn set_options(&mut self, options: ParseOptions) {
+        self.options = options;
+    }
+
+    // which texture names should be considered the untextured slot, in case a mod uses something other than "Untextured"
+    pub fn set_untextured_names(&mut self, untextured_names: UntexturedNames) {
+        self.untextured_names = untextured_names;
     }
 
     pub fn parse(&mut self, path: PathBuf) -> io::Result<Model> {
@@ -73,6 +188,7 @@ impl<R: Read + Seek> Parser<R> {
         let mut insignias = None;
         let mut visual_center = None;
         let mut shield_data = None;
+        let mut tool_metadata = ToolMetadata::default();
 
         let mut shield_tree_chunk = None;
         let mut debris_objs = vec![];
@@ -83,13 +199,19 @@ impl<R: Read + Seek> Parser<R> {
                 id_result => id_result?,
             };
             let len = self.read_i32()?;
+            if len < 0 {
+                return Err(invalid_data(format!("{} chunk has a negative length", String::from_utf8_lossy(id))));
+            }
+            let start = self.file.stream_position()?;
 
             // println!("found chunk {}", std::str::from_utf8(id).unwrap());
             // println!("length is {} bytes", len);
             match id {
                 b"OHDR" | b"HDR2" => {
-                    assert!(header.is_none());
-                    assert_eq!(self.version >= Version::V21_16, id == b"HDR2");
+                    if header.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
+                    self.check_chunk_version(id, Version::V21_16, b"HDR2")?;
 
                     let (max_radius, obj_flags, num_subobjects);
                     if self.version >= Version::V21_16 {
@@ -102,6 +224,10 @@ impl<R: Read + Seek> Parser<R> {
                         obj_flags = self.read_u32()?;
                     }
 
+                    // a subobject takes more than a byte, so that many can't be in the file
+                    if num_subobjects as u64 > self.file_len {
+                        return Err(invalid_data(format!("the header lists {} subobjects, more than the file could hold", num_subobjects)));
+                    }
                     sub_objects = vec![None; num_subobjects as usize];
 
                     let bounding_box = self.read_bbox()?;
@@ -148,7 +274,7 @@ impl<R: Read + Seek> Parser<R> {
                                 kind: match this.read_u32()? {
                                     1 => BspLightKind::Muzzle,
                                     2 => BspLightKind::Thruster,
-                                    _ => panic!(), // maybe dont just panic
+                                    kind => return Err(invalid_data(format!("unknown bsp light kind {}", kind))),
                                 },
                             })
                         })?
@@ -171,8 +297,10 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", header)
                 }
                 b"SOBJ" | b"OBJ2" => {
-                    assert!(header.is_some());
-                    assert_eq!(self.version >= Version::V21_16, id == b"OBJ2");
+                    if header.is_none() {
+                        return Err(invalid_data("subobject precedes the header"));
+                    }
+                    self.check_chunk_version(id, Version::V21_16, b"OBJ2")?;
 
                     let obj_id = ObjectId(self.read_u32()?); //id
 
@@ -214,13 +342,26 @@ impl<R: Read + Seek> Parser<R> {
                         (SubsysTranslationType::None, SubsysTranslationAxis::None)
                     };
 
-                    assert!(self.read_i32()? == 0, "chunked models unimplemented in FSO");
-                    let bsp_data_buffer = self.read_byte_buffer()?;
-                    let bsp_data = parse_bsp_data(&bsp_data_buffer, self.version)?;
+                    if self.read_i32()? != 0 {
+                        return Err(invalid_data("chunked models unimplemented in FSO"));
+                    }
+                    let bsp_data = if self.options.load_geometry {
+                        let bsp_data_buffer = self.read_byte_buffer()?;
+                        parse_bsp_data(&bsp_data_buffer, self.version, self.options.max_bsp_depth)?
+                    } else {
+                        let len = self.read_u32()?;
+                        self.file.seek(SeekFrom::Current(len as i64))?;
+                        BspData::default()
+                    };
                     //println!("parsed subobject {}", name);
 
-                    assert!(sub_objects[obj_id.0 as usize].is_none());
-                    sub_objects[obj_id.0 as usize] = Some(SubObject {
+                    let subobjects_len = sub_objects.len();
+                    let slot = (sub_objects.get_mut(obj_id.0 as usize))
+                        .ok_or_else(|| invalid_data(format!("subobject {} is past the {} the header lists", obj_id.0, subobjects_len)))?;
+                    if slot.is_some() {
+                        return Err(invalid_data(format!("more than one subobject {}", obj_id.0)));
+                    }
+                    *slot = Some(SubObject {
                         obj_id,
                         radius,
                         parent,
@@ -240,13 +381,17 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("parsed subobject {:#?}", sub_objects[obj_id.0 as usize]);
                 }
                 b"TXTR" => {
-                    assert!(textures.is_none());
+                    if textures.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
 
                     textures = Some(self.read_list(|this| this.read_string())?);
                     //println!("{:#?}", textures);
                 }
                 b"PATH" => {
-                    assert!(paths.is_none());
+                    if paths.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
 
                     paths = Some(self.read_list(|this| {
                         Ok(Path {
@@ -268,7 +413,9 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", paths);
                 }
                 b"SPCL" => {
-                    assert!(special_points.is_none());
+                    if special_points.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
 
                     special_points = Some(self.read_list(|this| {
                         Ok(SpecialPoint {
@@ -281,7 +428,9 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", special_points);
                 }
                 b"EYE " => {
+                    let mut eye_idx = 0;
                     eye_points = Some(self.read_list(|this| {
+                        eye_idx += 1;
                         Ok(EyePoint {
                             attached_subobj: {
                                 let id = this.read_u32()?;
@@ -292,19 +441,27 @@ impl<R: Read + Seek> Parser<R> {
                                 }
                             },
                             position: this.read_vec3d()?,
-                            normal: this.read_vec3d()?.try_into().unwrap_or_default(),
+                            normal: this.read_normal(NormalVec3::default(), NormalField::EyePoint(eye_idx - 1))?,
                         })
                     })?);
                     //println!("{:#?}", eye_points);
                 }
                 b"GPNT" | b"MPNT" => {
                     let target = if id == b"GPNT" { &mut primary_weps } else { &mut secondary_weps };
-                    assert!(target.is_none());
+                    if target.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
+                    let primary = id == b"GPNT";
+                    let mut bank = 0;
                     *target = Some(self.read_list(|this| {
+                        bank += 1;
+                        let mut point = 0;
                         this.read_list(|this| {
+                            point += 1;
+                            let field = NormalField::Weapon { primary, bank: bank - 1, point: point - 1 };
                             Ok(WeaponHardpoint {
                                 position: this.read_vec3d()?,
-                                normal: this.read_vec3d()?.try_into().unwrap_or_default(),
+                                normal: this.read_normal(NormalVec3::default(), field)?,
                                 // TODO: document this at https://wiki.hard-light.net/index.php/POF_data_structure
                                 offset: if this.version >= Version::V21_18 && this.version != Version::V22_00 {
                                     this.read_f32()?
@@ -317,23 +474,34 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", target);
                 }
                 b"TGUN" | b"TMIS" => {
+                    let mut turret_idx = turrets.len();
                     turrets.extend(self.read_list(|this| {
+                        turret_idx += 1;
                         let base_obj = ObjectId(this.read_u32()?);
                         let gun_obj = ObjectId(this.read_u32()?);
-                        assert!(sub_objects[base_obj.0 as usize].is_some(), "turret precedes base object");
-                        assert!(sub_objects[gun_obj.0 as usize].is_some(), "turret precedes gun object");
+                        if !sub_objects.get(base_obj.0 as usize).map_or(false, Option::is_some) {
+                            return Err(invalid_data("turret precedes base object"));
+                        }
+                        if !sub_objects.get(gun_obj.0 as usize).map_or(false, Option::is_some) {
+                            return Err(invalid_data("turret precedes gun object"));
+                        }
                         Ok(Turret {
                             base_obj,
                             gun_obj,
-                            normal: this.read_vec3d()?.try_into().unwrap_or_default(),
+                            normal: this.read_normal(NormalVec3::default(), NormalField::Turret(turret_idx - 1))?,
                             fire_points: this.read_list(|this| this.read_vec3d())?,
                         })
                     })?);
                     //println!("{:#?}", turrets);
                 }
                 b"FUEL" => {
-                    assert!(thruster_banks.is_none());
+                    if thruster_banks.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
+                    let mut bank = 0;
                     thruster_banks = Some(self.read_list(|this| {
+                        bank += 1;
+                        let mut glow = 0;
                         let num_glows = this.read_u32()?;
                         Ok(ThrusterBank {
                             properties: if this.version >= Version::V21_17 {
@@ -342,9 +510,12 @@ impl<R: Read + Seek> Parser<R> {
                                 String::new()
                             },
                             glows: this.read_list_n(num_glows as usize, |this| {
+                                glow += 1;
+                                let field = NormalField::ThrusterGlow { bank: bank - 1, glow: glow - 1 };
                                 Ok(ThrusterGlow {
                                     position: this.read_vec3d()?,
-                                    normal: this.read_vec3d()?.try_into().unwrap_or_default(),
+                                    // thrusters face backward
+                                    normal: this.read_normal(NormalVec3(Vec3d::new(0.0, 0.0, -1.0)), field)?,
                                     // TODO document this at https://wiki.hard-light.net/index.php/POF_data_structure
                                     radius: if this.version > Version::V20_04 { this.read_f32()? } else { 1.0 },
                                 })
@@ -354,7 +525,9 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", thruster_banks);
                 }
                 b"GLOW" => {
-                    assert!(glow_banks.is_none());
+                    if glow_banks.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
                     glow_banks = Some(self.read_list(|this| {
                         let num_glow_points;
                         Ok(GlowPointBank {
@@ -380,12 +553,18 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", glow_banks);
                 }
                 b"ACEN" => {
-                    assert!(visual_center.is_none());
+                    if visual_center.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
                     visual_center = Some(self.read_vec3d()?);
                 }
                 b"DOCK" => {
-                    assert!(dock_points.is_none());
+                    if dock_points.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
+                    let mut dock_idx = 0;
                     dock_points = Some(self.read_list(|this| {
+                        dock_idx += 1;
                         let properties = this.read_string()?;
                         let used_paths = this.read_list(|this| this.read_u32())?; // spec allows for a list of paths but only the first will be used so dont bother
                         let path = used_paths.first().map(|&x| PathId(x));
@@ -393,16 +572,25 @@ impl<R: Read + Seek> Parser<R> {
                         let mut dockpoints = this.read_list(|this| Ok(DockingPoint { position: this.read_vec3d()?, normal: this.read_vec3d()? }))?;
                         let mut iter = dockpoints.drain(..2);
                         let (p1, p2) = (iter.next().unwrap_or_default(), iter.next().unwrap_or_default());
-                        let position = (p1.position + p2.position) / 2.0;
-                        let fvec: NormalVec3 = p1.normal.try_into().unwrap_or_default();
-                        let uvec = Dock::orthonormalize(&(p2.position - p1.position).into(), &fvec.0.into());
+                        let (dock, fvec_issue, uvec_issue) = Dock::from_two_points_audited(p1.position, p2.position, p1.normal);
+                        let issues = [
+                            (NormalField::DockFvec(dock_idx - 1), fvec_issue),
+                            (NormalField::DockUvec(dock_idx - 1), uvec_issue),
+                        ];
+                        for (field, issue) in issues {
+                            if let Some(issue) = issue {
+                                this.normal_notices.push(NormalAudit { field, issue });
+                            }
+                        }
 
-                        Ok(Dock { properties, path, position, fvec, uvec: uvec.into() })
+                        Ok(Dock { properties, path, ..dock })
                     })?);
                     //println!("{:#?}", dock_points);
                 }
                 b"INSG" => {
-                    assert!(insignias.is_none());
+                    if insignias.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
                     insignias = Some(self.read_list(|this| {
                         let num_faces;
                         Ok(Insignia {
@@ -427,7 +615,9 @@ impl<R: Read + Seek> Parser<R> {
                     //println!("{:#?}", insignias);
                 }
                 b"SHLD" => {
-                    assert!(shield_data.is_none());
+                    if shield_data.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
                     shield_data = Some((
                         self.read_list(|this| this.read_vec3d())?,
                         self.read_list(|this| {
@@ -440,26 +630,46 @@ impl<R: Read + Seek> Parser<R> {
                     ))
                 }
                 b"SLDC" | b"SLC2" => {
-                    assert!(shield_tree_chunk.is_none());
-                    assert_eq!(self.version >= Version::V22_00, id == b"SLC2");
+                    if shield_tree_chunk.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
+                    self.check_chunk_version(id, Version::V22_00, b"SLC2")?;
                     // deal with this later, once we're sure to also have the shield data
                     shield_tree_chunk = Some(self.read_byte_buffer()?);
                 }
                 b"PINF" => {
-                    assert!(comments.is_none());
+                    if comments.is_some() {
+                        return Err(duplicate_chunk(id));
+                    }
                     // gotta inline some stuff because the length of this string is the length of the chunk
-                    let mut buffer = vec![0; len as usize];
-                    self.file.read_exact(&mut buffer)?;
+                    let buffer = self.read_buffer(len as usize)?;
 
                     let end = buffer.iter().position(|&char| char == 0).unwrap_or(buffer.len());
                     comments = Some(String::from_utf8_lossy(buffer[..end].into()).to_string());
                     // println!("{:#?}", comments);
                 }
+                id if id == TOOL_METADATA_CHUNK => {
+                    let buffer = self.read_buffer(len as usize)?;
+                    // it's only informational, so a bad one isn't worth failing the whole file over
+                    match read_tool_metadata(&buffer) {
+                        Ok(metadata) => tool_metadata = metadata,
+                        Err(err) => warn!("Discarding unreadable tool metadata: {}", err),
+                    }
+                }
                 _ => {
                     eprintln!("I don't know how to handle id {:x?}", id);
                     self.file.seek(SeekFrom::Current(len as i64))?;
                 }
             }
+
+            let (declared, consumed) = (len as u32, self.file.stream_position()?.wrapping_sub(start) as u32);
+            if consumed != declared {
+                warn!("{} chunk declares {} bytes, but {} were read", String::from_utf8_lossy(id), declared, consumed);
+                self.parse_notices.push(ParseNotice::ChunkLengthMismatch { tag: *id, declared, consumed });
+                if self.options.chunk_lengths == ChunkLengthTrust::Declared {
+                    self.file.seek(SeekFrom::Start(start + declared as u64))?;
+                }
+            }
         }
 
         // finally handle the shield tree, if applicable
@@ -468,16 +678,19 @@ impl<R: Read + Seek> Parser<R> {
                 verts,
                 polygons: poly_list,
                 collision_tree: match shield_tree_chunk {
-                    Some(chunk) => Some(*parse_shield_node(&chunk, self.version)?),
+                    Some(chunk) => Some(*parse_shield_node(&chunk, self.version, 1, self.options.max_bsp_depth)?),
                     None => None,
                 },
             }),
-            (None, Some(_)) => unreachable!(),
+            (None, Some(_)) => return Err(invalid_data("shield collision tree without any shield")),
             _ => None,
         };
 
-        // now that all the subobjects shouldve have been slotted in, assert that they all exist
-        let mut sub_objects = ObjVec(sub_objects.into_iter().map(|subobj_opt| subobj_opt.unwrap()).collect());
+        // now that all the subobjects shouldve have been slotted in, check that they all exist
+        let sub_objects = (sub_objects.into_iter().enumerate())
+            .map(|(i, subobj_opt)| subobj_opt.ok_or_else(|| invalid_data(format!("subobject {} is missing", i))))
+            .collect::<io::Result<_>>()?;
+        let mut sub_objects = ObjVec(sub_objects);
 
         debris_objs.retain(|id| {
             if id.0 < sub_objects.len() as u32 {
@@ -489,7 +702,7 @@ impl<R: Read + Seek> Parser<R> {
             }
         });
 
-        let mut header = header.expect("No header chunk found???");
+        let mut header = header.ok_or_else(|| invalid_data("No header chunk found"))?;
         header.detail_levels.retain(|id| {
             if id.0 < sub_objects.len() as u32 {
                 true
@@ -518,23 +731,45 @@ impl<R: Read + Seek> Parser<R> {
                 }
             }
         }
+        let ToolMetadata {
+            stat_history,
+            mut attachments,
+            tolerance_policy,
+            texture_annotations,
+        } = tool_metadata;
+        for (i, attachment) in attachments.iter_mut().enumerate() {
+            if matches!(attachment.parent, Some(id) if id.0 >= sub_objects.len() as u32) {
+                attachment.parent = None;
+                warn!("Invalid attachment {} parent reset", i);
+            }
+        }
+
+        let comments = comments.unwrap_or_default();
+        let quirks = self.options.quirks.resolve(self.version, &comments);
+        let mut quirk_notices = vec![];
 
         let mut textures = textures.unwrap_or_default();
-        let untextured_idx = post_parse_fill_untextured_slot(&mut sub_objects, &mut textures);
+        if quirks.contains(&Quirk::TextureIndexOffByOne) {
+            quirk_notices.extend(fix_texture_index_off_by_one(&mut sub_objects, textures.len()));
+        }
+        let untextured_idx = post_parse_fill_untextured_slot_with(&mut sub_objects, &mut textures, &self.untextured_names);
+        let texture_annotations = resolve_texture_annotations(&textures, texture_annotations);
 
         let mut model = Model {
             version: self.version,
+            disk_version: Some(self.version),
             header,
             sub_objects,
             textures,
             paths: paths.unwrap_or_default(),
             special_points: special_points.unwrap_or_default(),
             eye_points: eye_points.unwrap_or_default(),
+            attachments,
             primary_weps: primary_weps.unwrap_or_default(),
             secondary_weps: secondary_weps.unwrap_or_default(),
             turrets,
             thruster_banks: thruster_banks.unwrap_or_default(),
-            comments: comments.unwrap_or_default(),
+            comments,
             docking_bays: dock_points.unwrap_or_default(),
             insignias: insignias.unwrap_or_default(),
             glow_banks: glow_banks.unwrap_or_default(),
@@ -542,10 +777,22 @@ impl<R: Read + Seek> Parser<R> {
             shield_data,
             path_to_file: path.canonicalize().unwrap_or(path),
             untextured_idx,
+            untextured_names: self.untextured_names.clone(),
+            precision_budget: None,
+            max_bsp_depth: Some(self.options.max_bsp_depth),
+            normal_notices: std::mem::take(&mut self.normal_notices),
+            parse_notices: std::mem::take(&mut self.parse_notices),
+            stat_history,
+            tolerance_policy,
+            texture_annotations,
+            quirk_notices,
+            geometry_skipped: !self.options.load_geometry,
+            geometry_cache: Default::default(),
             warnings: Default::default(),
             errors: Default::default(),
         };
 
+        model.fix_quirks(&quirks);
         model.recalc_all_children_ids();
         model.recheck_warnings(Set::All);
         model.recheck_errors(Set::All);
@@ -554,6 +801,15 @@ impl<R: Read + Seek> Parser<R> {
         Ok(model)
     }
 
+    // normals in the file are meant to be unit length, but not all exporters manage it
+    fn read_normal(&mut self, default: NormalVec3, field: NormalField) -> io::Result<NormalVec3> {
+        let (normal, issue) = NormalVec3::sanitize(self.read_vec3d()?, default);
+        if let Some(issue) = issue {
+            self.normal_notices.push(NormalAudit { field, issue });
+        }
+        Ok(normal)
+    }
+
     fn read_list<T>(&mut self, f: impl FnMut(&mut Self) -> io::Result<T>) -> io::Result<Vec<T>> {
         let n = self.read_u32()? as usize;
         self.read_list_n(n, f)
@@ -581,12 +837,60 @@ impl<R: Read + Seek> Parser<R> {
         read_i32(&mut self.file)
     }
 
+    // fills in the bsp data of a model parsed without it (see ParseOptions::load_geometry), from a new parser over the same file
+    // the subobjects must still be as they were loaded, though anything else may have been edited in the meantime
+    pub fn load_geometry_into(&mut self, model: &mut Model) -> io::Result<()> {
+        if !model.geometry_skipped {
+            return Ok(());
+        }
+        self.options.load_geometry = true;
+        let full = self.parse(PathBuf::new())?;
+
+        let same_subobjects = model.sub_objects.len() == full.sub_objects.len()
+            && (model.sub_objects.iter().zip(&full.sub_objects)).all(|(subobj, full_subobj)| subobj.name == full_subobj.name);
+        if !same_subobjects {
+            return Err(io::Error::new(ErrorKind::InvalidInput, "the subobjects don't match the file's"));
+        }
+        // the untextured slot is only added once the polygons show it's needed
+        if model.textures != full.textures {
+            if !full.textures.starts_with(&model.textures) {
+                return Err(io::Error::new(ErrorKind::InvalidInput, "the textures have changed since the model was loaded"));
+            }
+            model.textures = full.textures;
+            model.untextured_idx = full.untextured_idx;
+        }
+
+        for (subobj, full_subobj) in model.sub_objects.iter_mut().zip(full.sub_objects.0) {
+            subobj.bsp_data = full_subobj.bsp_data;
+        }
+        model.geometry_skipped = false;
+        model.recheck_warnings(Set::All);
+        model.recheck_errors(Set::All);
+        Ok(())
+    }
+
     fn read_byte_buffer(&mut self) -> io::Result<Box<[u8]>> {
-        let mut buffer = vec![0; self.read_u32()? as usize];
-        //println!("buffer size is {}", buffer.len());
+        let len = self.read_u32()? as usize;
+        //println!("buffer size is {}", len);
+        Ok(self.read_buffer(len)?.into())
+    }
+
+    // len bytes, which are checked to be there before the buffer is allocated, since a bad length could be anything
+    fn read_buffer(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        if len as u64 > self.file_len.saturating_sub(self.file.stream_position()?) {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, format!("{} bytes run past the end of the file", len)));
+        }
+        let mut buffer = vec![0; len];
         self.file.read_exact(&mut buffer)?;
+        Ok(buffer)
+    }
 
-        Ok(buffer.into())
+    // chunks which changed their id in a later version
+    fn check_chunk_version(&self, id: &[u8; 4], since: Version, new_id: &[u8; 4]) -> io::Result<()> {
+        if (self.version >= since) != (id == new_id) {
+            return Err(invalid_data(format!("{} chunk in a version {:?} file", String::from_utf8_lossy(id), self.version)));
+        }
+        Ok(())
     }
 
     fn read_bytes<const N: usize>(&mut self) -> io::Result<[u8; N]> {
@@ -667,19 +971,41 @@ fn parse_chunk_header(buf: &[u8], chunk_type_is_u8: bool) -> io::Result<(u32, &[
         }
     );*/
     let chunk_size = pointer.read_u32::<LE>()? as usize;
-    Ok((chunk_type, pointer, &buf[chunk_size..]))
+    Ok((chunk_type, pointer, bsp_offset(buf, chunk_size)?))
+}
+
+fn too_deep(max_depth: u32) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, BspTooDeep { max_depth })
 }
 
-fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
-    fn parse_bsp_node(mut buf: &[u8], verts: &[Vec3d], version: Version) -> io::Result<Box<BspNode>> {
+fn invalid_data(msg: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+fn duplicate_chunk(id: &[u8; 4]) -> io::Error {
+    invalid_data(format!("more than one {} chunk", String::from_utf8_lossy(id)))
+}
+
+// the rest of a bsp buffer from an offset read out of it
+fn bsp_offset(buf: &[u8], offset: usize) -> io::Result<&[u8]> {
+    buf.get(offset..)
+        .ok_or_else(|| invalid_data(format!("bsp offset {} is past the end of the data", offset)))
+}
+
+fn parse_bsp_data(mut buf: &[u8], version: Version, max_depth: u32) -> io::Result<BspData> {
+    fn parse_bsp_node(mut buf: &[u8], verts: &[Vec3d], version: Version, depth: u32, max_depth: u32) -> io::Result<Box<BspNode>> {
+        // a malformed file can point a node back at itself, which would otherwise recurse until the stack overflows
+        if depth > max_depth {
+            return Err(too_deep(max_depth));
+        }
         // parse the first header
         let (chunk_type, mut chunk, next_chunk) = parse_chunk_header(buf, false)?;
         // the first chunk (after deffpoints) AND the chunks pointed to be SORTNORM's front and back branches should ALWAYS be either another
         // SORTNORM or a BOUNDBOX followed by some polygons
         // dbg!(chunk_type);
         Ok(Box::new(match chunk_type {
-            BspData::SORTNORM | BspData::SORTNORM2 => BspNode::Split {
-                front: {
+            BspData::SORTNORM | BspData::SORTNORM2 => {
+                let front = {
                     if chunk_type == BspData::SORTNORM {
                         let _normal = read_vec3d(&mut chunk)?;
                         let _point = read_vec3d(&mut chunk)?;
@@ -689,18 +1015,18 @@ fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
                     if offset == 0 {
                         Box::new(BspNode::Empty)
                     } else {
-                        parse_bsp_node(&buf[offset as usize..], verts, version)?
+                        parse_bsp_node(bsp_offset(buf, offset as usize)?, verts, version, depth + 1, max_depth)?
                     }
-                },
-                back: {
+                };
+                let back = {
                     let offset = chunk.read_u32::<LE>()?;
                     if offset == 0 {
                         Box::new(BspNode::Empty)
                     } else {
-                        parse_bsp_node(&buf[offset as usize..], verts, version)?
+                        parse_bsp_node(bsp_offset(buf, offset as usize)?, verts, version, depth + 1, max_depth)?
                     }
-                },
-                bbox: {
+                };
+                let bbox = {
                     if chunk_type == BspData::SORTNORM {
                         let _prelist = chunk.read_u32::<LE>()?; //
                         let _postlist = chunk.read_u32::<LE>()?; // All 3 completely unused, as far as I can tell
@@ -711,8 +1037,9 @@ fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
                     } else {
                         BoundingBox::default()
                     }
-                },
-            },
+                };
+                BspNode::split(bbox, front, back)
+            }
             BspData::BOUNDBOX => {
                 let bbox = read_bbox(&mut chunk)?;
                 let mut poly_list = vec![];
@@ -758,7 +1085,7 @@ fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
                             break;
                         }
                         _ => {
-                            unreachable!("unknown chunk type! {}", chunk_type);
+                            return Err(invalid_data(format!("unknown chunk type! {}", chunk_type)));
                         }
                     });
 
@@ -792,7 +1119,7 @@ fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
             }
             BspData::ENDOFBRANCH => BspNode::Empty,
             _ => {
-                unreachable!();
+                return Err(invalid_data(format!("unknown chunk type! {}", chunk_type)));
             }
         }))
     }
@@ -800,14 +1127,18 @@ fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
     //println!("started parsing a bsp tree");
 
     let (chunk_type, mut chunk, next_chunk) = parse_chunk_header(buf, false)?;
-    assert!(chunk_type == BspData::DEFFPOINTS);
+    if chunk_type != BspData::DEFFPOINTS {
+        return Err(invalid_data("bsp data doesn't start with its vertices"));
+    }
 
     let num_verts = chunk.read_u32::<LE>()?;
     let num_norms = chunk.read_u32::<LE>()?;
     let offset = chunk.read_u32::<LE>()?;
-    let norm_counts = &chunk[0..num_verts as usize];
+    let norm_counts = chunk
+        .get(0..num_verts as usize)
+        .ok_or_else(|| invalid_data("bsp vertices are truncated"))?;
 
-    buf = &buf[offset as usize..];
+    buf = bsp_offset(buf, offset as usize)?;
 
     let mut verts = vec![];
     let mut norms = vec![];
@@ -818,38 +1149,49 @@ fn parse_bsp_data(mut buf: &[u8], version: Version) -> io::Result<BspData> {
         }
     }
 
-    assert!(num_norms as usize == norms.len());
+    if num_norms as usize != norms.len() {
+        return Err(invalid_data(format!("bsp data lists {} normals, but has {}", num_norms, norms.len())));
+    }
 
-    let mut bsp_tree = *parse_bsp_node(next_chunk, &verts, version)?;
+    let mut bsp_tree = *parse_bsp_node(next_chunk, &verts, version, 1, max_depth)?;
 
     if version < Version::V20_00 {
-        bsp_tree.recalculate_bboxes(&verts);
+        bsp_tree
+            .recalculate_bboxes(&verts, max_depth)
+            .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
     }
 
     Ok(BspData { collision_tree: bsp_tree, norms, verts })
 }
 
-fn parse_shield_node(buf: &[u8], version: Version) -> io::Result<Box<ShieldNode>> {
+fn parse_shield_node(buf: &[u8], version: Version, depth: u32, max_depth: u32) -> io::Result<Box<ShieldNode>> {
+    if depth > max_depth {
+        return Err(too_deep(max_depth));
+    }
     let (chunk_type, mut chunk, _) = parse_chunk_header(buf, version < Version::V22_00)?;
     Ok(Box::new(match chunk_type {
         ShieldNode::SPLIT => ShieldNode::Split {
             bbox: read_bbox(&mut chunk)?,
             front: {
                 let offset = chunk.read_u32::<LE>()?;
-                assert!(offset != 0);
-                parse_shield_node(&buf[offset as usize..], version)?
+                if offset == 0 {
+                    return Err(invalid_data("shield split without a child"));
+                }
+                parse_shield_node(bsp_offset(buf, offset as usize)?, version, depth + 1, max_depth)?
             },
             back: {
                 let offset = chunk.read_u32::<LE>()?;
-                assert!(offset != 0);
-                parse_shield_node(&buf[offset as usize..], version)?
+                if offset == 0 {
+                    return Err(invalid_data("shield split without a child"));
+                }
+                parse_shield_node(bsp_offset(buf, offset as usize)?, version, depth + 1, max_depth)?
             },
         },
         ShieldNode::LEAF => ShieldNode::Leaf {
             bbox: read_bbox(&mut chunk)?,
             poly_list: read_list_n(chunk.read_u32::<LE>()? as usize, &mut chunk, |chunk| Ok(PolygonId(chunk.read_u32::<LE>()?)))?,
         },
-        _ => unreachable!(),
+        _ => return Err(invalid_data(format!("unknown shield chunk type {}", chunk_type))),
     }))
 }
 
@@ -983,6 +1325,9 @@ trait ParseCtx<'a> {
 
     fn parse_geometry(&self, node: &Self::Node, transform: &Mat4x4) -> (Vec<Vec3d>, Vec<Vec3d>, Vec<(TextureId, Vec<PolyVertex>)>);
 
+    // a chance to clean up the node's geometry before it's built into a subobject
+    fn weld(&self, _name: &str, _verts: &mut Vec<Vec3d>, _norms: &[Vec3d], _polys: &mut Vec<(TextureId, Vec<PolyVertex>)>) {}
+
     fn parse_subobject_recursive(&self, model: &mut Model, node: Self::Node, parent: ObjectId, detail_level: Option<u32>, parent_transform: &Mat4x4) {
         let name = match node.name() {
             None => return, // subobjects must have names!
@@ -995,7 +1340,8 @@ trait ParseCtx<'a> {
         let up = self.up();
         let offset = Vec3d::from(center).from_coord(up);
 
-        let (vertices_out, normals_out, polygons_out) = self.parse_geometry(&node, &transform);
+        let (mut vertices_out, normals_out, mut polygons_out) = self.parse_geometry(&node, &transform);
+        self.weld(name, &mut vertices_out, &normals_out, &mut polygons_out);
 
         // ignore subobjects with no geo
         // metadata (empties with names like #properties) are handled below directly
@@ -1095,7 +1441,8 @@ trait ParseCtx<'a> {
                 None => continue,
             };
 
-            let (vertices_out, normals_out, polygons_out) = self.parse_geometry(&node, &transform);
+            let (mut vertices_out, normals_out, mut polygons_out) = self.parse_geometry(&node, &transform);
+            self.weld(name, &mut vertices_out, &normals_out, &mut polygons_out);
             if !polygons_out.is_empty() {
                 if name.to_lowercase() == "shield" {
                     let mut polygons = vec![];
@@ -1269,6 +1616,8 @@ trait ParseCtx<'a> {
                         }
                     }
 
+                    // a skewed transform leaves the uvec off perpendicular
+                    new_bay.sanitize();
                     model.docking_bays.push(new_bay);
                 }
             } else if name == "#glows" {
@@ -1365,6 +1714,34 @@ trait ParseCtx<'a> {
 
                     model.eye_points.push(new_point);
                 }
+            } else if name == "#attachments" {
+                for (node, name) in node_children_with_keyword(node, "") {
+                    let mut new_point = AttachmentPoint::default();
+
+                    if let Some(idx) = name.find(":") {
+                        new_point.name = format!("{}", &name[(idx + 1)..]);
+                    }
+
+                    let transform = node.transform();
+                    let zero = Vec3d::ZERO.into();
+                    new_point.position = Vec3d::from(transform.transform_point(&zero) - zero).from_coord(up);
+                    new_point.fvec = transform.transform_vector(&glm::vec3(0., 1., 0.)).try_into().unwrap_or_default();
+                    new_point.fvec.0 = new_point.fvec.0.from_coord(up);
+
+                    new_point.uvec = transform.transform_vector(&glm::vec3(0., 0., 1.)).try_into().unwrap_or_default();
+                    new_point.uvec.0 = new_point.uvec.0.from_coord(up);
+
+                    for (_, name) in node_children_with_keyword(node, "parent") {
+                        if let Some(idx) = name.find(":") {
+                            if let Ok(val) = &name[(idx + 1)..].parse() {
+                                new_point.parent = Some(ObjectId(*val));
+                                break;
+                            }
+                        }
+                    }
+
+                    model.attachments.push(new_point);
+                }
             } else if name == "#visual-center" {
                 let (pos, _, _) = node.parse_point(&transform, up);
                 model.visual_center = pos;
@@ -1418,6 +1795,8 @@ struct DaeContext<'a> {
     local_maps: dae::LocalMaps<'a>,
     material_map: HashMap<String, TextureId>,
     up: UpAxis,
+    options: &'a ImportOptions,
+    report: RefCell<ImportReport>,
 }
 
 impl<'a> ParseCtx<'a> for DaeContext<'a> {
@@ -1425,6 +1804,12 @@ impl<'a> ParseCtx<'a> for DaeContext<'a> {
     fn up(&self) -> UpAxis {
         self.up
     }
+    fn weld(&self, name: &str, verts: &mut Vec<Vec3d>, norms: &[Vec3d], polys: &mut Vec<(TextureId, Vec<PolyVertex>)>) {
+        let welded = weld_mesh(self.options.weld.mode_for(name), verts, norms, polys);
+        if welded > 0 {
+            self.report.borrow_mut().welded.push((name.to_string(), welded));
+        }
+    }
     fn parse_geometry(&self, node: &&'a dae::Node, transform: &Mat4x4) -> (Vec<Vec3d>, Vec<Vec3d>, Vec<(TextureId, Vec<PolyVertex>)>) {
         let mut vertices_out: Vec<Vec3d> = vec![];
         let mut normals_out: Vec<Vec3d> = vec![];
@@ -1546,6 +1931,10 @@ impl<'a> ParseCtx<'a> for DaeContext<'a> {
 }
 
 pub fn parse_dae(path: std::path::PathBuf) -> Model {
+    parse_dae_with(path, &ImportOptions::default()).0
+}
+
+pub fn parse_dae_with(path: std::path::PathBuf, options: &ImportOptions) -> (Model, ImportReport) {
     let document = dae::Document::from_file(&path).unwrap();
     // use std::io::Write;
     // write!(std::fs::File::create("output.log").unwrap(), "{:#?}", document).unwrap();
@@ -1553,6 +1942,8 @@ pub fn parse_dae(path: std::path::PathBuf) -> Model {
         local_maps: document.local_maps(),
         material_map: HashMap::new(),
         up: document.asset.up_axis,
+        options,
+        report: RefCell::new(ImportReport::default()),
     };
 
     document.for_each(|material: &dae::Material| {
@@ -1572,7 +1963,7 @@ pub fn parse_dae(path: std::path::PathBuf) -> Model {
 
     model.prune_unused_textures();
 
-    model
+    (model, ctx.report.into_inner())
 }
 
 // =================================================================
@@ -1743,3 +2134,340 @@ fn import_buffer_data(document: &gltf::Document, base: &std::path::Path, mut blo
     }
     buffers
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    // a chain of splits with a triangle on the front of each, so the tree is depth nodes deep
+    fn deep_chain(depth: u32) -> BspNode {
+        let verts = (0..3)
+            .map(|i| PolyVertex {
+                vertex_id: VertexId(i),
+                normal_id: NormalId(0),
+                uv: (0.0, 0.0),
+            })
+            .collect::<Vec<_>>();
+        let leaf = || BspNode::Leaf {
+            bbox: Default::default(),
+            poly: Polygon {
+                normal: Default::default(),
+                texture: TextureId(0),
+                verts: verts.clone(),
+            },
+        };
+        let mut node = leaf();
+        for _ in 1..depth {
+            node = BspNode::split(Default::default(), Box::new(leaf()), Box::new(node));
+        }
+        node
+    }
+
+    fn triangle_verts() -> Vec<Vec3d> {
+        vec![Vec3d::new(0.0, 0.0, 0.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0)]
+    }
+
+    // a model with just detail0, which has the tree over triangle_verts
+    fn model_with_tree(tree: BspNode) -> Model {
+        let hull = SubObject {
+            obj_id: ObjectId(0),
+            name: "detail0".to_string(),
+            bsp_data: BspData {
+                collision_tree: tree,
+                norms: vec![Vec3d::new(0.0, 0.0, 1.0)],
+                verts: triangle_verts(),
+            },
+            ..Default::default()
+        };
+        let mut model = Model {
+            sub_objects: ObjVec(vec![hull]),
+            textures: vec!["hull".to_string()],
+            ..Default::default()
+        };
+        model.header.num_subobjects = 1;
+        model.header.detail_levels = vec![ObjectId(0)];
+        model.recheck_warnings(Set::All);
+        model.recheck_errors(Set::All);
+        model
+    }
+
+    #[test]
+    fn from_bytes() {
+        let mut model = model_with_tree(deep_chain(1));
+        model.primary_weps = vec![vec![WeaponHardpoint { position: Vec3d::new(0.0, 0.0, 1.0), ..Default::default() }]];
+        let buf = model.write_to_vec().unwrap();
+        let parsed = Model::from_bytes(&buf).unwrap();
+        assert_eq!(parsed.path_to_file, PathBuf::new());
+        assert_eq!(parsed.sub_objects.len(), 1);
+        assert_eq!(parsed.sub_objects[ObjectId(0)].bsp_data.verts, triangle_verts());
+        assert_eq!(parsed.textures, model.textures);
+        assert_eq!(parsed.primary_weps[0][0].position, Vec3d::new(0.0, 0.0, 1.0));
+
+        assert!(matches!(Model::from_bytes(b"IDSP\x01\x02\x03\x04"), Err(ParseError::NotAPof)));
+        assert!(matches!(Model::from_bytes(&buf[..6]), Err(ParseError::NotAPof)));
+        let mut unknown_version = buf.clone();
+        unknown_version[4..8].copy_from_slice(&1234i32.to_le_bytes());
+        assert!(matches!(Model::from_bytes(&unknown_version), Err(ParseError::UnknownVersion(1234))));
+    }
+
+    #[test]
+    fn malformed_files_are_errors() {
+        let buf = model_with_tree(deep_chain(1)).write_to_vec().unwrap();
+        let with_chunk = |id: &[u8; 4], len: i32, data: &[u8]| {
+            let mut buf = buf.clone();
+            buf.extend(id);
+            buf.extend(len.to_le_bytes());
+            buf.extend(data);
+            buf
+        };
+        let error = |buf: &[u8]| match Model::from_bytes(buf).map(|_| ()).unwrap_err() {
+            ParseError::Io(err) => (err.kind(), err.to_string()),
+            err => unreachable!("{}", err),
+        };
+
+        // lengths are checked against what's left before anything is allocated for them
+        assert_eq!(error(&with_chunk(b"PINF", i32::MAX, b"comment")).0, ErrorKind::UnexpectedEof);
+        assert_eq!(error(&with_chunk(TOOL_METADATA_CHUNK, i32::MAX, &[1, 0, 0, 0])).0, ErrorKind::UnexpectedEof);
+        assert_eq!(error(&with_chunk(b"SPCL", 8, &[1, 0, 0, 0, 0xff, 0xff, 0xff, 0x7f])).0, ErrorKind::UnexpectedEof);
+        assert_eq!(error(&with_chunk(b"PINF", -8, &[])), (ErrorKind::InvalidData, "PINF chunk has a negative length".to_string()));
+
+        // as are the things the rest of the parser relies on
+        let mut turret = vec![1, 0, 0, 0, 5, 0, 0, 0, 5, 0, 0, 0];
+        turret.extend([0; 16]);
+        assert_eq!(error(&with_chunk(b"TGUN", turret.len() as i32, &turret)).1, "turret precedes base object");
+
+        let chunks = crate::inspect::inspect(io::Cursor::new(&buf)).unwrap().chunks;
+        let chunk = |id: &[u8; 4]| chunks.iter().find(|chunk| &chunk.id == id).unwrap();
+        let header = chunk(b"HDR2");
+        let header = &buf[header.offset as usize..header.offset as usize + 8 + header.len as usize];
+        assert_eq!(error(&[&buf[..], header].concat()).1, "more than one HDR2 chunk");
+
+        let subobj = chunk(b"OBJ2").offset as usize + 8;
+        let subobj_field = |at: usize, value: u32| {
+            let mut buf = buf.clone();
+            buf[at..at + 4].copy_from_slice(&value.to_le_bytes());
+            buf
+        };
+        assert_eq!(error(&subobj_field(subobj, 7)).1, "subobject 7 is past the 1 the header lists");
+        // after its id, radius, parent, offset, geo center and bbox, then its name and properties, then its rotation and translation
+        let string_end = |at: usize| at + 4 + u32::from_le_bytes(buf[at..at + 4].try_into().unwrap()) as usize;
+        let chunked = string_end(string_end(subobj + 60)) + 16;
+        assert_eq!(error(&subobj_field(chunked, 1)).1, "chunked models unimplemented in FSO");
+    }
+
+    #[test]
+    fn custom_untextured_name() {
+        let mut model = model_with_tree(deep_chain(2));
+        model.textures.push("NoTexture".to_string());
+        let tree = &mut model.sub_objects[ObjectId(0)].bsp_data.collision_tree;
+        tree.leaves_mut().nth(1).unwrap().1.texture = TextureId(1);
+        let buf = model.write_to_vec().unwrap();
+
+        // by default it's just another texture
+        let parsed = Model::from_bytes(&buf).unwrap();
+        assert_eq!(parsed.untextured_idx, None);
+
+        let mut parser = Parser::new(io::Cursor::new(&buf)).unwrap();
+        parser.set_untextured_names(UntexturedNames::new("NoTexture", ["notex"]));
+        let mut parsed = parser.parse(PathBuf::new()).unwrap();
+        assert_eq!(parsed.untextured_idx, Some(TextureId(1)));
+        assert_eq!(parsed.textures, ["hull", "NoTexture"]);
+
+        // a polygon losing its texture joins the custom slot, rather than an "Untextured" being added
+        let tree = &mut parsed.sub_objects[ObjectId(0)].bsp_data.collision_tree;
+        tree.leaves_mut().next().unwrap().1.texture = TextureId(7);
+        parsed.refresh_untextured_slot();
+        assert_eq!(parsed.textures, ["hull", "NoTexture"]);
+        assert!((parsed.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves()).all(|(_, poly)| poly.texture == TextureId(1)));
+
+        parsed.remove_texture(TextureId(0));
+        assert_eq!(parsed.textures, ["NoTexture"]);
+        assert_eq!(parsed.untextured_idx, Some(TextureId(0)));
+        assert!((parsed.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves()).all(|(_, poly)| poly.texture == TextureId(0)));
+    }
+
+    #[test]
+    fn deep_bsp_tree_hits_the_limit() {
+        let verts = triangle_verts();
+        let mut tree = deep_chain(300);
+        assert_eq!(tree.depth(), 300);
+        assert_eq!(tree.recalculate_bboxes(&verts, MAX_BSP_DEPTH), Err(BspTooDeep { max_depth: MAX_BSP_DEPTH }));
+        assert_eq!(tree.recalculate_bboxes(&verts, 400), Ok(()));
+
+        let mut model = model_with_tree(tree);
+        assert!(model.errors.contains(&Error::BspTooDeep(Some(ObjectId(0)))));
+        model.max_bsp_depth = Some(400);
+        model.recheck_errors(Set::All);
+        assert!(!model.errors.contains(&Error::BspTooDeep(Some(ObjectId(0)))));
+
+        let mut buf = vec![];
+        model.write(&mut buf).unwrap();
+
+        // the default limit stops the parse with an error, rather than following the tree down
+        // (core::panic is imported above, so plain panic! is ambiguous in here)
+        let Err(ParseError::Io(err)) = Model::from_bytes(&buf) else {
+            unreachable!("parsed a tree deeper than the limit")
+        };
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert_eq!(err.get_ref().and_then(|err| err.downcast_ref::<BspTooDeep>()), Some(&BspTooDeep { max_depth: MAX_BSP_DEPTH }));
+
+        // a higher one is kept on the model, so the tree isn't then reported as too deep
+        let mut parser = Parser::new(io::Cursor::new(&buf)).unwrap();
+        parser.set_options(ParseOptions { max_bsp_depth: 400, ..Default::default() });
+        let parsed = parser.parse(PathBuf::new()).unwrap();
+        assert_eq!(parsed.bsp_depth_limit(), 400);
+        assert_eq!(parsed.sub_objects[ObjectId(0)].bsp_data.collision_tree.depth(), 300);
+        assert!(!parsed.errors.contains(&Error::BspTooDeep(Some(ObjectId(0)))));
+    }
+
+    #[test]
+    fn bad_normals_are_repaired_on_load() {
+        let mut model = model_with_tree(deep_chain(1));
+        let zero = NormalVec3(Vec3d::ZERO);
+        model.primary_weps = vec![vec![WeaponHardpoint { normal: zero, ..Default::default() }]];
+        model.thruster_banks = vec![ThrusterBank {
+            glows: vec![ThrusterGlow { normal: zero, ..Default::default() }],
+            ..Default::default()
+        }];
+        model.eye_points = vec![EyePoint {
+            normal: NormalVec3(Vec3d::new(0.0, 2.0, 0.0)),
+            ..Default::default()
+        }];
+        let parsed = Model::from_bytes(&model.write_to_vec().unwrap()).unwrap();
+
+        assert_eq!(parsed.primary_weps[0][0].normal.0, NormalVec3::default().0);
+        assert_eq!(parsed.thruster_banks[0].glows[0].normal.0, Vec3d::new(0.0, 0.0, -1.0));
+        assert_eq!(parsed.eye_points[0].normal.0, Vec3d::new(0.0, 1.0, 0.0));
+        let mut notices = parsed.normal_notices.clone();
+        notices.sort_by_key(|notice| format!("{:?}", notice));
+        assert_eq!(
+            notices,
+            [
+                NormalAudit {
+                    field: NormalField::EyePoint(0),
+                    issue: NormalIssue::NotUnitLength
+                },
+                NormalAudit {
+                    field: NormalField::ThrusterGlow { bank: 0, glow: 0 },
+                    issue: NormalIssue::Zero
+                },
+                NormalAudit {
+                    field: NormalField::Weapon { primary: true, bank: 0, point: 0 },
+                    issue: NormalIssue::Zero
+                },
+            ]
+        );
+        assert!(parsed.warnings.contains(&Warning::NormalsRepaired));
+        assert_eq!(parsed.audit_normals(), parsed.normal_notices);
+    }
+
+    #[test]
+    fn loaded_version_survives_changes() {
+        let mut model = model_with_tree(deep_chain(1));
+        model.version = Version::V22_01;
+        assert_eq!((model.disk_version, model.loaded_version()), (None, Version::V22_01));
+        let mut parsed = Model::from_bytes(&model.write_to_vec().unwrap()).unwrap();
+        assert_eq!(parsed.disk_version, Some(Version::V22_01));
+
+        // upgrading is expected
+        parsed.version = Version::LATEST;
+        parsed.recheck_warnings(Set::One(Warning::VersionChangedFromDisk));
+        assert_eq!(parsed.loaded_version(), Version::V22_01);
+        assert!(!parsed.warnings.contains(&Warning::VersionChangedFromDisk));
+
+        // downgrading is warned about
+        parsed.version = Version::V21_17;
+        parsed.recheck_warnings(Set::One(Warning::VersionChangedFromDisk));
+        assert_eq!(parsed.loaded_version(), Version::V22_01);
+        assert!(parsed.warnings.contains(&Warning::VersionChangedFromDisk));
+    }
+
+    #[test]
+    fn dock_frames_are_stable_across_round_trips() {
+        let frame = |dock: &Dock| [dock.position, dock.fvec.0, dock.uvec.0].map(|vec| [vec.x, vec.y, vec.z].map(f32::to_bits));
+        let mut rng = crate::test_util::Rng::new(3);
+        let mut model = model_with_tree(deep_chain(1));
+        // slightly off frames, further and further from the origin
+        model.docking_bays = (0..20)
+            .map(|i| {
+                let extent = 5.0 * 2f32.powi(i / 2);
+                let fvec = NormalVec3(rng.vec3d(1.0).normalize() * rng.float(0.99, 1.01));
+                let uvec = NormalVec3(rng.vec3d(1.0).normalize() * rng.float(0.99, 1.01));
+                let position = rng.vec3d(extent);
+                Dock { position, fvec, uvec, ..Default::default() }
+            })
+            .collect();
+        // and one way off
+        model.docking_bays[0].uvec = NormalVec3((model.docking_bays[0].fvec.0 + Vec3d::new(0.1, 0.1, 0.1)).normalize());
+
+        let first = Model::from_bytes(&model.write_to_vec().unwrap()).unwrap();
+        assert!(first.normal_notices.contains(&NormalAudit {
+            field: NormalField::DockUvec(0),
+            issue: NormalIssue::NotOrthogonal,
+        }));
+        let second = Model::from_bytes(&first.write_to_vec().unwrap()).unwrap();
+        let third = Model::from_bytes(&second.write_to_vec().unwrap()).unwrap();
+        assert!(second.normal_notices.is_empty());
+        for ((first, second), third) in first.docking_bays.iter().zip(&second.docking_bays).zip(&third.docking_bays) {
+            assert!(first.is_canonical());
+            assert_eq!(frame(first), frame(second));
+            assert_eq!(frame(second), frame(third));
+        }
+
+        // the parser builds docks the same way tools can
+        for (original, parsed) in model.docking_bays.iter().zip(&first.docking_bays) {
+            let (p1, p2) = original.to_two_points();
+            assert_eq!(frame(&Dock::from_two_points(p1, p2, original.fvec.0)), frame(parsed));
+        }
+    }
+
+    #[test]
+    fn skipping_geometry() {
+        let mut fighter = crate::samples::sample_fighter(0);
+        fighter.recheck_warnings(Set::All);
+        fighter.recheck_errors(Set::All);
+        let buf = fighter.write_to_vec().unwrap();
+        let full = Model::from_bytes(&buf).unwrap();
+        let parse_without_geometry = || {
+            let mut parser = Parser::new(Cursor::new(&buf)).unwrap();
+            parser.set_options(ParseOptions { load_geometry: false, ..Default::default() });
+            parser.parse(PathBuf::new()).unwrap()
+        };
+
+        let mut model = parse_without_geometry();
+        assert!(model.geometry_skipped);
+        for subobj in &model.sub_objects {
+            assert!(matches!(subobj.bsp_data.collision_tree, BspNode::Empty), "{}", subobj.name);
+            assert!(subobj.bsp_data.verts.is_empty() && subobj.bsp_data.norms.is_empty());
+        }
+        // everything but the geometry is as a full load has it
+        let mut stripped = full.clone();
+        for subobj in stripped.sub_objects.iter_mut() {
+            subobj.bsp_data = Default::default();
+        }
+        stripped.geometry_skipped = true;
+        assert_eq!(format!("{:?}", model), format!("{:?}", stripped));
+
+        // it can't be written like this, but can once the geometry is back, keeping edits made in the meantime
+        assert!(model.write_to_vec().is_err());
+        model.primary_weps[0][0].position = Vec3d::new(1.0, 2.0, 3.0);
+        Parser::new(Cursor::new(&buf)).unwrap().load_geometry_into(&mut model).unwrap();
+        assert!(!model.geometry_skipped);
+        let reloaded = Model::from_bytes(&model.write_to_vec().unwrap()).unwrap();
+        assert_eq!(reloaded.primary_weps[0][0].position, Vec3d::new(1.0, 2.0, 3.0));
+        for (subobj, full_subobj) in reloaded.sub_objects.iter().zip(&full.sub_objects) {
+            assert_eq!(format!("{:?}", subobj.bsp_data), format!("{:?}", full_subobj.bsp_data));
+        }
+
+        // geometry for different subobjects isn't taken
+        let mut renamed = parse_without_geometry();
+        renamed.sub_objects[ObjectId(1)].name = "elsewhere".to_string();
+        let mut shortened = parse_without_geometry();
+        shortened.sub_objects.0.pop();
+        for mut model in [renamed, shortened] {
+            assert!(Parser::new(Cursor::new(&buf)).unwrap().load_geometry_into(&mut model).is_err());
+            assert!(model.geometry_skipped);
+        }
+    }
+}
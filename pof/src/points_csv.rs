@@ -0,0 +1,365 @@
+// import and export of weapon, glow and thruster points as plain csv, for spreadsheet-driven workflows
+//
+// every row is `kind,bank,x,y,z,nx,ny,nz,value`, where kind is one of primary/secondary/glow/thruster,
+// and value is the offset for weapon points, or the radius for glow points and thruster glows
+// coordinates are in the pof's convention, +x right, +y up, +z forward
+// blank lines and lines starting with '#' are ignored
+use std::fmt::{self, Display};
+use std::io::{self, BufRead, BufReader, Read, Write};
+
+use crate::{GlowPoint, GlowPointBank, Model, NormalVec3, Set, ThrusterBank, ThrusterGlow, Vec3d, WeaponHardpoint};
+
+pub const POINTS_CSV_HEADER: &str = "kind,bank,x,y,z,nx,ny,nz,value";
+const COLUMNS: [&str; 9] = ["kind", "bank", "x", "y", "z", "nx", "ny", "nz", "value"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointKind {
+    Primary,
+    Secondary,
+    Glow,
+    Thruster,
+}
+impl PointKind {
+    pub const ALL: [PointKind; 4] = [PointKind::Primary, PointKind::Secondary, PointKind::Glow, PointKind::Thruster];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PointKind::Primary => "primary",
+            PointKind::Secondary => "secondary",
+            PointKind::Glow => "glow",
+            PointKind::Thruster => "thruster",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<PointKind> {
+        PointKind::ALL.into_iter().find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CsvNormals {
+    // normals which aren't unit length are normalized
+    #[default]
+    Normalize,
+    // normals which aren't unit length are an error
+    Reject,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CsvImportMode {
+    // the points of every kind present in the file are cleared before importing, the banks themselves are kept
+    #[default]
+    Replace,
+    // points are added after any existing ones in their bank
+    Append,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CsvPointOptions {
+    pub normals: CsvNormals,
+    pub mode: CsvImportMode,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportedPoints {
+    pub primary: usize,
+    pub secondary: usize,
+    pub glow: usize,
+    pub thruster: usize,
+    pub banks_created: usize,
+    pub normals_fixed: usize,
+}
+
+#[derive(Debug)]
+pub enum CsvError {
+    Io(io::Error),
+    MissingHeader,
+    ColumnCount { line: usize, found: usize },
+    DecimalComma { line: usize },
+    UnknownKind { line: usize, kind: String },
+    InvalidNumber { line: usize, column: &'static str, value: String },
+    InvalidNormal { line: usize },
+}
+impl From<io::Error> for CsvError {
+    fn from(err: io::Error) -> Self {
+        CsvError::Io(err)
+    }
+}
+impl Display for CsvError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsvError::Io(err) => write!(f, "{}", err),
+            CsvError::MissingHeader => write!(f, "expected the header row '{}'", POINTS_CSV_HEADER),
+            CsvError::ColumnCount { line, found } => write!(f, "line {}: expected {} columns, found {}", line, COLUMNS.len(), found),
+            CsvError::DecimalComma { line } => {
+                write!(f, "line {}: numbers must use '.' as the decimal separator, this looks like a decimal comma export", line)
+            }
+            CsvError::UnknownKind { line, kind } => {
+                write!(f, "line {}: unknown kind '{}', expected primary, secondary, glow or thruster", line, kind)
+            }
+            CsvError::InvalidNumber { line, column, value } => write!(f, "line {}: '{}' is not a valid {}", line, value, column),
+            CsvError::InvalidNormal { line } => write!(f, "line {}: the normal is not unit length", line),
+        }
+    }
+}
+
+impl Model {
+    // every weapon point, glow point and thruster glow, in the format described at the top of points_csv.rs
+    pub fn export_weapon_points_csv(&self, w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "# coordinates are in pof convention: +x right, +y up, +z forward")?;
+        writeln!(w, "# value is the offset for primary/secondary points, and the radius for glow/thruster points")?;
+        writeln!(w, "{}", POINTS_CSV_HEADER)?;
+
+        let mut write_row = |kind: PointKind, bank: usize, position: Vec3d, normal: Vec3d, value: f32| {
+            let (p, n) = (position, normal);
+            writeln!(w, "{},{},{},{},{},{},{},{},{}", kind.name(), bank, p.x, p.y, p.z, n.x, n.y, n.z, value)
+        };
+
+        for (kind, banks) in [(PointKind::Primary, &self.primary_weps), (PointKind::Secondary, &self.secondary_weps)] {
+            for (i, bank) in banks.iter().enumerate() {
+                for point in bank {
+                    write_row(kind, i, point.position, point.normal.0, point.offset)?;
+                }
+            }
+        }
+        for (i, bank) in self.glow_banks.iter().enumerate() {
+            for point in &bank.glow_points {
+                write_row(PointKind::Glow, i, point.position, point.normal, point.radius)?;
+            }
+        }
+        for (i, bank) in self.thruster_banks.iter().enumerate() {
+            for glow in &bank.glows {
+                write_row(PointKind::Thruster, i, glow.position, glow.normal.0, glow.radius)?;
+            }
+        }
+        Ok(())
+    }
+
+    // reads points in the format described at the top of points_csv.rs; nothing is changed if there are any errors
+    pub fn import_weapon_points_csv(&mut self, reader: &mut impl Read, options: CsvPointOptions) -> Result<ImportedPoints, CsvError> {
+        struct Row {
+            kind: PointKind,
+            bank: usize,
+            position: Vec3d,
+            normal: Vec3d,
+            value: f32,
+        }
+
+        let mut rows = vec![];
+        let mut found_header = false;
+        let mut normals_fixed = 0;
+        for (i, line) in BufReader::new(reader).lines().enumerate() {
+            let line_num = i + 1;
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if !found_header {
+                if !fields.iter().map(|field| field.to_ascii_lowercase()).eq(COLUMNS) {
+                    return Err(CsvError::MissingHeader);
+                }
+                found_header = true;
+                continue;
+            }
+
+            if fields.len() != COLUMNS.len() {
+                // a locale with decimal commas splits the numbers apart, or uses ';' between the fields instead
+                if fields.len() > COLUMNS.len() || line.contains(';') {
+                    return Err(CsvError::DecimalComma { line: line_num });
+                }
+                return Err(CsvError::ColumnCount { line: line_num, found: fields.len() });
+            }
+
+            let kind = PointKind::from_name(fields[0]).ok_or_else(|| CsvError::UnknownKind { line: line_num, kind: fields[0].to_string() })?;
+            let bank = fields[1].parse::<usize>().map_err(|_| CsvError::InvalidNumber {
+                line: line_num,
+                column: COLUMNS[1],
+                value: fields[1].to_string(),
+            })?;
+            let mut nums = [0.0_f32; 7];
+            for (num, (column, field)) in nums.iter_mut().zip(COLUMNS[2..].iter().zip(&fields[2..])) {
+                *num = field
+                    .parse()
+                    .map_err(|_| CsvError::InvalidNumber { line: line_num, column, value: field.to_string() })?;
+            }
+            let [x, y, z, nx, ny, nz, value] = nums;
+
+            let mut normal = Vec3d::new(nx, ny, nz);
+            // glow points may have no normal, which makes them omnidirectional
+            let allow_null = kind == PointKind::Glow && normal.is_null();
+            if !allow_null && (normal.magnitude() - 1.0).abs() > 0.001 {
+                if options.normals == CsvNormals::Reject || normal.is_null() {
+                    return Err(CsvError::InvalidNormal { line: line_num });
+                }
+                normal = normal.normalize();
+                normals_fixed += 1;
+            }
+
+            rows.push(Row { kind, bank, position: Vec3d::new(x, y, z), normal, value });
+        }
+
+        if !found_header {
+            return Err(CsvError::MissingHeader);
+        }
+
+        if options.mode == CsvImportMode::Replace {
+            for kind in PointKind::ALL {
+                if !rows.iter().any(|row| row.kind == kind) {
+                    continue;
+                }
+                match kind {
+                    PointKind::Primary => self.primary_weps.iter_mut().for_each(Vec::clear),
+                    PointKind::Secondary => self.secondary_weps.iter_mut().for_each(Vec::clear),
+                    PointKind::Glow => self.glow_banks.iter_mut().for_each(|bank| bank.glow_points.clear()),
+                    PointKind::Thruster => self.thruster_banks.iter_mut().for_each(|bank| bank.glows.clear()),
+                }
+            }
+        }
+
+        let mut imported = ImportedPoints { normals_fixed, ..Default::default() };
+        fn bank_mut<'a, T: Default>(banks: &'a mut Vec<T>, bank: usize, banks_created: &mut usize) -> &'a mut T {
+            if bank >= banks.len() {
+                *banks_created += bank + 1 - banks.len();
+                banks.resize_with(bank + 1, Default::default);
+            }
+            &mut banks[bank]
+        }
+
+        for Row { kind, bank, position, normal, value } in rows {
+            let created = &mut imported.banks_created;
+            match kind {
+                PointKind::Primary | PointKind::Secondary => {
+                    let banks = if kind == PointKind::Primary {
+                        &mut self.primary_weps
+                    } else {
+                        &mut self.secondary_weps
+                    };
+                    bank_mut(banks, bank, created).push(WeaponHardpoint { position, normal: NormalVec3(normal), offset: value });
+                    if kind == PointKind::Primary {
+                        imported.primary += 1;
+                    } else {
+                        imported.secondary += 1;
+                    }
+                }
+                PointKind::Glow => {
+                    let bank: &mut GlowPointBank = bank_mut(&mut self.glow_banks, bank, created);
+                    bank.glow_points.push(GlowPoint { position, normal, radius: value });
+                    imported.glow += 1;
+                }
+                PointKind::Thruster => {
+                    let bank: &mut ThrusterBank = bank_mut(&mut self.thruster_banks, bank, created);
+                    bank.glows.push(ThrusterGlow { position, normal: NormalVec3(normal), radius: value });
+                    imported.thruster += 1;
+                }
+            }
+        }
+
+        // weapon offsets and thruster properties depend on the version
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+
+    fn fighter_with_glows() -> Model {
+        let mut fighter = sample_fighter(0);
+        let glow = |x: f32, normal: Vec3d| GlowPoint { position: Vec3d::new(x, 0.5, -1.25), normal, radius: 0.3 };
+        fighter.glow_banks = vec![GlowPointBank {
+            glow_points: vec![glow(0.1, Vec3d::new(0.0, 1.0, 0.0)), glow(-0.1, Vec3d::ZERO)],
+            ..Default::default()
+        }];
+        fighter
+    }
+
+    fn import(model: &mut Model, csv: &str, options: CsvPointOptions) -> Result<ImportedPoints, CsvError> {
+        model.import_weapon_points_csv(&mut csv.as_bytes(), options)
+    }
+
+    #[test]
+    fn round_trip() {
+        let fighter = fighter_with_glows();
+        let mut csv = vec![];
+        fighter.export_weapon_points_csv(&mut csv).unwrap();
+
+        let mut model = Model::default();
+        let imported = model.import_weapon_points_csv(&mut &csv[..], Default::default()).unwrap();
+        assert_eq!((imported.primary, imported.secondary, imported.glow, imported.thruster), (4, 2, 2, 2));
+        assert_eq!(imported.banks_created, 5);
+        assert_eq!(imported.normals_fixed, 0);
+
+        let debug = |value: &dyn fmt::Debug| format!("{:?}", value);
+        assert_eq!(debug(&model.primary_weps), debug(&fighter.primary_weps));
+        assert_eq!(debug(&model.secondary_weps), debug(&fighter.secondary_weps));
+        assert_eq!(debug(&model.glow_banks[0].glow_points), debug(&fighter.glow_banks[0].glow_points));
+        assert_eq!(debug(&model.thruster_banks[0].glows), debug(&fighter.thruster_banks[0].glows));
+
+        let mut again = vec![];
+        model.export_weapon_points_csv(&mut again).unwrap();
+        assert_eq!(again, csv);
+    }
+
+    #[test]
+    fn replace_and_append() {
+        let mut fighter = fighter_with_glows();
+        let csv = "kind,bank,x,y,z,nx,ny,nz,value\n\n# a new bank\nprimary,3,1,2,3,0,0,2,0.5\n";
+
+        let append = CsvPointOptions { mode: CsvImportMode::Append, ..Default::default() };
+        let imported = import(&mut fighter, csv, append).unwrap();
+        assert_eq!((imported.primary, imported.banks_created, imported.normals_fixed), (1, 2, 1));
+        assert_eq!(fighter.primary_weps.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 0, 1]);
+        assert_eq!(fighter.primary_weps[3][0].normal.0, Vec3d::new(0.0, 0.0, 1.0));
+
+        // only the kinds in the file are replaced
+        import(&mut fighter, csv, Default::default()).unwrap();
+        assert_eq!(fighter.primary_weps.iter().map(Vec::len).collect::<Vec<_>>(), [0, 0, 0, 1]);
+        assert_eq!(fighter.secondary_weps[0].len(), 2);
+        assert_eq!(fighter.glow_banks[0].glow_points.len(), 2);
+    }
+
+    #[test]
+    fn errors_leave_the_model_alone() {
+        let mut fighter = fighter_with_glows();
+        let mut before = vec![];
+        fighter.export_weapon_points_csv(&mut before).unwrap();
+
+        let header = "kind,bank,x,y,z,nx,ny,nz,value\n";
+        let reject = CsvPointOptions { normals: CsvNormals::Reject, ..Default::default() };
+        let cases = [
+            ("primary,0,1,2,3,0,0,1,0\n", Default::default(), "MissingHeader"),
+            ("kind,bank,x,y,z\nprimary,0,1,2,3\n", Default::default(), "MissingHeader"),
+            ("primary,0,1,5,2,3,0,0,1,0\n", Default::default(), "DecimalComma { line: 2 }"),
+            ("primary;0;1,5;2;3;0;0;1;0\n", Default::default(), "DecimalComma { line: 2 }"),
+            ("primary,0,1,2,3\n", Default::default(), "ColumnCount { line: 2, found: 5 }"),
+            ("\n# ok\nturret,0,1,2,3,0,0,1,0\n", Default::default(), "UnknownKind { line: 4, kind: \"turret\" }"),
+            ("primary,-1,1,2,3,0,0,1,0\n", Default::default(), "InvalidNumber { line: 2, column: \"bank\", value: \"-1\" }"),
+            ("primary,0,1,2,3,0,0,2,0\n", reject, "InvalidNormal { line: 2 }"),
+            ("thruster,0,1,2,3,0,0,0,0\n", Default::default(), "InvalidNormal { line: 2 }"),
+        ];
+        for (rows, options, expected) in cases {
+            let csv = if expected == "MissingHeader" {
+                rows.to_string()
+            } else {
+                format!("{}{}", header, rows)
+            };
+            let err = import(&mut fighter, &csv, options).unwrap_err();
+            assert_eq!(format!("{:?}", err), expected, "{}", rows);
+            assert!(!err.to_string().is_empty());
+        }
+
+        let mut after = vec![];
+        fighter.export_weapon_points_csv(&mut after).unwrap();
+        assert_eq!(after, before);
+
+        // a glow point without a normal is fine
+        import(&mut fighter, &format!("{}glow,0,1,2,3,0,0,0,0.5\n", header), reject).unwrap();
+        assert!(fighter.glow_banks[0].glow_points[0].normal.is_null());
+    }
+}
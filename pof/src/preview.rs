@@ -0,0 +1,127 @@
+// what glow banks and thrusters look like at a given moment, so an editor can scrub a timeline and match the game
+//
+// glow banks blink on a fixed cycle, following the engine's per-frame update of each bank (model_render_set_glow_points
+// in code/model/modelrender.cpp):
+//   if off_time is 0 the bank never blinks, and stays lit
+//   otherwise the time into the cycle is (time - disp_time) % (on_time + off_time), with C's remainder, and each frame a lit
+//   bank goes dark if that's under on_time, and a dark one goes lit if it's over off_time
+//   so from the start of each cycle the bank is dark for off_time, then lit for on_time
+//   before disp_time the remainder is negative, so a positive disp_time holds the bank dark until then, while a negative
+//   one starts it that far into its cycle
+// if on_time is longer than off_time, between the two the engine flips the bank every frame, which reads as a flicker
+// rather than going dark, so that's reported as lit
+use crate::{GlowPointBank, Model, ThrusterBank};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlowBankState {
+    pub lit: bool,
+    // how far through the current lit or dark stretch the bank is, 0 at its start and approaching 1 at its end
+    // always 0 for a bank which doesn't blink
+    pub phase: f32,
+}
+
+impl GlowPointBank {
+    pub fn state_at(&self, time_ms: u32) -> GlowBankState {
+        if self.off_time == 0 {
+            return GlowBankState { lit: true, phase: 0.0 };
+        }
+
+        let elapsed = time_ms as i64 - self.disp_time as i64;
+        if elapsed < 0 {
+            // waiting for disp_time, which counts as one long dark stretch from 0
+            return GlowBankState { lit: false, phase: time_ms as f32 / self.disp_time as f32 };
+        }
+
+        let (on_time, off_time) = (self.on_time as i64, self.off_time as i64);
+        let into_cycle = elapsed % (on_time + off_time);
+        if into_cycle < off_time {
+            GlowBankState { lit: false, phase: into_cycle as f32 / off_time as f32 }
+        } else {
+            GlowBankState {
+                lit: true,
+                phase: (into_cycle - off_time) as f32 / on_time as f32,
+            }
+        }
+    }
+}
+
+impl Model {
+    // the state of every glow bank, by index
+    pub fn glow_states_at(&self, time_ms: u32) -> Vec<(usize, GlowBankState)> {
+        self.glow_banks.iter().map(|bank| bank.state_at(time_ms)).enumerate().collect()
+    }
+}
+
+// the engine takes the forward thrust (physics_info::linear_thrust.z, -1 to 1) as the thruster length and glow scale when it
+// sets up a ship's thrusters for rendering (ship_render in code/ship/ship.cpp), raising it to 1 while the afterburner is on,
+// and modelrender.cpp scales each glow's radius by it; reverse thrust shows no glow, since the engine clamps the length at 0
+impl ThrusterBank {
+    // the scale on each glow's radius at the throttle, 0 to 1; an afterburner is full throttle
+    pub fn intensity_for_throttle(&self, throttle: f32) -> f32 {
+        if self.glows.is_empty() || throttle.is_nan() {
+            return 0.0;
+        }
+        throttle.clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ThrusterGlow;
+
+    fn bank(disp_time: i32, on_time: u32, off_time: u32) -> GlowPointBank {
+        GlowPointBank { disp_time, on_time, off_time, ..Default::default() }
+    }
+
+    #[test]
+    fn glow_bank_timing() {
+        // (bank, time, lit, phase), worked out by hand from the cycle at the top of this file
+        let cases = [
+            // always on, whatever the other times are
+            (bank(0, 0, 0), 0, true, 0.0),
+            (bank(500, 100, 0), 250, true, 0.0),
+            (bank(-500, 0, 0), 12345, true, 0.0),
+            // blinking, dark for 300 and then lit for 100
+            (bank(0, 100, 300), 0, false, 0.0),
+            (bank(0, 100, 300), 150, false, 0.5),
+            (bank(0, 100, 300), 300, true, 0.0),
+            (bank(0, 100, 300), 350, true, 0.5),
+            (bank(0, 100, 300), 400, false, 0.0),
+            (bank(0, 100, 300), 750, true, 0.5),
+            // held dark until disp_time, then blinking from there
+            (bank(200, 100, 100), 0, false, 0.0),
+            (bank(200, 100, 100), 100, false, 0.5),
+            (bank(200, 100, 100), 200, false, 0.0),
+            (bank(200, 100, 100), 350, true, 0.5),
+            // a negative disp_time starts it partway through its cycle
+            (bank(-150, 100, 100), 0, true, 0.5),
+            (bank(-150, 100, 100), 50, false, 0.0),
+            (bank(-150, 100, 100), 100, false, 0.5),
+            // and a whole cycle further back is the same
+            (bank(-350, 100, 100), 0, true, 0.5),
+            (bank(-350, 100, 100), 50, false, 0.0),
+        ];
+        for (bank, time, lit, phase) in cases {
+            let state = bank.state_at(time);
+            assert_eq!(state, GlowBankState { lit, phase }, "{:?} at {}", bank, time);
+        }
+
+        let model = Model {
+            glow_banks: vec![bank(0, 0, 0), bank(0, 100, 300)],
+            ..Default::default()
+        };
+        let states = model.glow_states_at(350);
+        assert_eq!(states, [(0, GlowBankState { lit: true, phase: 0.0 }), (1, GlowBankState { lit: true, phase: 0.5 })]);
+    }
+
+    #[test]
+    fn thruster_intensity() {
+        let thrusters = ThrusterBank { glows: vec![ThrusterGlow::default()], ..Default::default() };
+        let cases = [(-1.0, 0.0), (0.0, 0.0), (0.25, 0.25), (1.0, 1.0), (2.0, 1.0), (f32::NAN, 0.0)];
+        for (throttle, intensity) in cases {
+            assert_eq!(thrusters.intensity_for_throttle(throttle), intensity, "{}", throttle);
+        }
+        assert_eq!(ThrusterBank::default().intensity_for_throttle(1.0), 0.0);
+    }
+}
@@ -0,0 +1,158 @@
+// export and import of every subobject's properties as one text file, for bulk editing $special, $fov and the like
+//
+// each subobject is a `[name]` line followed by its properties, verbatim, up to the next `[name]` line; a blank line
+// separates the blocks, so trailing blank lines of a block are dropped on import
+// lines before the first `[name]` starting with '#' are comments, anything else there is an error
+use std::fmt::{self, Display};
+
+use crate::{Model, Set};
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportedProperties {
+    // subobjects whose properties actually changed
+    pub updated: usize,
+    // names in the table with no subobject to go to, in the order they appear
+    pub unknown: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum PropertiesTableError {
+    TextBeforeFirstName { line: usize },
+    DuplicateName { line: usize, name: String },
+}
+impl Display for PropertiesTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertiesTableError::TextBeforeFirstName { line } => write!(f, "line {}: expected a [subobject name] line", line),
+            PropertiesTableError::DuplicateName { line, name } => write!(f, "line {}: '{}' appears more than once", line, name),
+        }
+    }
+}
+
+fn parse_name_line(line: &str) -> Option<&str> {
+    line.trim_end().strip_prefix('[')?.strip_suffix(']')
+}
+
+impl Model {
+    // every subobject's properties in the format described at the top of properties_table.rs, in subobject order
+    pub fn export_properties_table(&self) -> String {
+        let mut out = String::new();
+        for subobj in &self.sub_objects {
+            out.push_str(&format!("[{}]\n", subobj.name));
+            if !subobj.properties.is_empty() {
+                out.push_str(&subobj.properties);
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // replaces the properties of each subobject named in the table, leaving the ones it doesn't mention alone
+    // nothing is changed if the table is malformed
+    pub fn import_properties_table(&mut self, table: &str) -> Result<ImportedProperties, PropertiesTableError> {
+        let mut blocks: Vec<(&str, Vec<&str>)> = vec![];
+        for (i, line) in table.lines().enumerate() {
+            if let Some(name) = parse_name_line(line) {
+                if blocks.iter().any(|(other, _)| *other == name) {
+                    return Err(PropertiesTableError::DuplicateName { line: i + 1, name: name.to_string() });
+                }
+                blocks.push((name, vec![]));
+            } else if let Some((_, lines)) = blocks.last_mut() {
+                lines.push(line);
+            } else if !line.trim().is_empty() && !line.starts_with('#') {
+                return Err(PropertiesTableError::TextBeforeFirstName { line: i + 1 });
+            }
+        }
+
+        let mut imported = ImportedProperties::default();
+        for (name, mut lines) in blocks {
+            while lines.last().map_or(false, |line| line.trim().is_empty()) {
+                lines.pop();
+            }
+            let Some(id) = self.get_obj_id_by_name(name) else {
+                imported.unknown.push(name.to_string());
+                continue;
+            };
+            let properties = lines.join("\n");
+            if self.sub_objects[id].properties != properties {
+                self.sub_objects[id].properties = properties;
+                imported.updated += 1;
+            }
+        }
+
+        if imported.updated > 0 {
+            // besides the length, $special=subsystem, $nocollide_this_only and friends feed into plenty of other checks
+            self.recheck_warnings(Set::All);
+            self.recheck_errors(Set::All);
+        }
+        Ok(imported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::{ObjectId, Warning};
+
+    const TURRET: &str = "$special=subsystem\n$fov=180\n$name=Laser Turret";
+    const GUN: &str = "$rotate=5\n$triggered:\n$rotation_axis=x";
+
+    #[test]
+    fn round_trip() {
+        let mut model = sample_fighter(0);
+        model.sub_objects[ObjectId(1)].properties = TURRET.to_string();
+        model.sub_objects[ObjectId(2)].properties = GUN.to_string();
+        let table = model.export_properties_table();
+        assert!(table.starts_with("[detail0]\n"));
+        assert!(table.contains(&format!("[turret01a]\n{}\n\n[turret01b]\n{}\n\n", TURRET, GUN)));
+
+        let mut other = sample_fighter(0);
+        let imported = other.import_properties_table(&table).unwrap();
+        assert_eq!((imported.updated, imported.unknown.len()), (2, 0));
+        for (subobj, original) in other.sub_objects.iter().zip(&model.sub_objects) {
+            assert_eq!(subobj.properties, original.properties);
+        }
+        assert_eq!(other.export_properties_table(), table);
+        // importing it again changes nothing
+        assert_eq!(other.import_properties_table(&table).unwrap().updated, 0);
+    }
+
+    #[test]
+    fn partial_tables() {
+        let mut model = sample_fighter(0);
+        let turret02a = model.sub_objects[ObjectId(3)].properties.clone();
+        let table = format!("# just the turret\n\n[turret01a]\n{}\n\n\n[turret09a]\n$fov=90\n", TURRET);
+        let imported = model.import_properties_table(&table).unwrap();
+        assert_eq!((imported.updated, imported.unknown), (1, vec!["turret09a".to_string()]));
+        assert_eq!(model.sub_objects[ObjectId(1)].properties, TURRET);
+        assert_eq!(model.sub_objects[ObjectId(3)].properties, turret02a);
+
+        // an empty block clears the properties
+        model.import_properties_table("[turret01a]\n").unwrap();
+        assert!(model.sub_objects[ObjectId(1)].properties.is_empty());
+    }
+
+    #[test]
+    fn malformed_tables_change_nothing() {
+        let mut model = sample_fighter(0);
+        let table = model.export_properties_table();
+        let err = model.import_properties_table("$fov=90\n[turret01a]\n").unwrap_err();
+        assert!(matches!(err, PropertiesTableError::TextBeforeFirstName { line: 1 }));
+        let twice = "[turret01a]\n$fov=90\n\n[turret01a]\n$fov=45\n";
+        let err = model.import_properties_table(twice).unwrap_err();
+        assert!(matches!(err, PropertiesTableError::DuplicateName { line: 4, ref name } if name == "turret01a"));
+        assert_eq!(model.export_properties_table(), table);
+    }
+
+    #[test]
+    fn imports_are_rechecked() {
+        let mut model = sample_fighter(0);
+        let table = format!("[turret01a]\n$name={}\n", "x".repeat(crate::MAX_PROPERTIES_LEN));
+        model.import_properties_table(&table).unwrap();
+        assert!(model.warnings.contains(&Warning::SubObjectPropertiesTooLong(ObjectId(1))));
+        model.import_properties_table("[turret01a]\n$name=ok\n").unwrap();
+        assert!(!model.warnings.contains(&Warning::SubObjectPropertiesTooLong(ObjectId(1))));
+    }
+}
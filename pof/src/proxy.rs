@@ -0,0 +1,399 @@
+// simplified stand-ins for a subobject's geometry, for experimenting with cheaper collision than the visual mesh
+// (the engine collides against whatever geometry is in the pof)
+//
+// a proxy is a new subobject next to its source, textured "invisible" so the engine doesn't draw it; the source can be
+// flagged $nocollide_this_only so only the proxy collides
+use std::collections::HashSet;
+
+use crate::spatial::SpatialHash;
+use crate::{
+    oriented_box_around, properties_set_flag, BspData, Model, NormalId, ObjectId, PolyVertex, Polygon, Set, SubObject, TextureId, Vec3d, VertexId,
+    Warning,
+};
+
+// the engine skips rendering polygons with this texture
+pub const INVISIBLE_TEXTURE: &str = "invisible";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyStyle {
+    ConvexHull,
+    OrientedBox,
+    // a convex hull of at most this many triangles, scaled up about its center until it contains the source again
+    DecimatedMesh(usize),
+}
+
+struct HullFace {
+    verts: [usize; 3],
+    normal: Vec3d,
+    offset: f32,
+    // the points above this face which haven't been added yet
+    outside: Vec<usize>,
+}
+impl HullFace {
+    fn new(points: &[Vec3d], verts: [usize; 3]) -> HullFace {
+        let [a, b, c] = verts.map(|i| points[i]);
+        let normal = (b - a).cross(&(c - a)).normalize();
+        HullFace { verts, normal, offset: normal.dot(&a), outside: vec![] }
+    }
+
+    fn distance(&self, point: Vec3d) -> f32 {
+        self.normal.dot(&point) - self.offset
+    }
+}
+
+// puts each point above whichever face it's furthest above, dropping the ones which are inside all of them
+fn assign_outside(points: &[Vec3d], faces: &mut [Option<HullFace>], candidates: &[usize], unassigned: impl Iterator<Item = usize>, eps: f32) {
+    for point in unassigned {
+        let best = (candidates.iter())
+            .map(|&face| (face, faces[face].as_ref().unwrap().distance(points[point])))
+            .filter(|&(_, distance)| distance > eps)
+            .max_by(|(_, d1), (_, d2)| d1.total_cmp(d2));
+        if let Some((face, _)) = best {
+            faces[face].as_mut().unwrap().outside.push(point);
+        }
+    }
+}
+
+// the convex hull of the points as outward facing triangles, by quickhull, adding the point furthest outside each time
+// stops early rather than go over max_faces, which leaves the hull of the most significant points
+// None if the points are all in a plane, which has no volume to wrap
+fn convex_hull(points: &[Vec3d], max_faces: usize) -> Option<Vec<[usize; 3]>> {
+    let first = (0..points.len()).min_by(|&i, &j| points[i].x.total_cmp(&points[j].x))?;
+    let furthest = |metric: &dyn Fn(Vec3d) -> f32| {
+        (0..points.len())
+            .max_by(|&i, &j| metric(points[i]).total_cmp(&metric(points[j])))
+            .unwrap()
+    };
+
+    let extent = (points.iter()).fold(0.0_f32, |max, &point| max.max((point - points[first]).magnitude()));
+    let eps = (extent * 1e-5).max(1e-6);
+
+    // the starting tetrahedron, from the points furthest from each other
+    let second = furthest(&|point| (point - points[first]).magnitude());
+    let dir = (points[second] - points[first]).normalize();
+    let line_distance = |point: Vec3d| (point - points[first]).cross(&dir).magnitude();
+    let third = furthest(&line_distance);
+    if line_distance(points[third]) <= eps {
+        return None;
+    }
+    let plane = HullFace::new(points, [first, second, third]);
+    let fourth = furthest(&|point| plane.distance(point).abs());
+    if plane.distance(points[fourth]).abs() <= eps {
+        return None;
+    }
+
+    let inside = Vec3d::average([first, second, third, fourth].into_iter().map(|i| points[i]));
+    let mut faces = vec![];
+    for [a, b, c] in [
+        [first, second, third],
+        [first, fourth, second],
+        [second, fourth, third],
+        [third, fourth, first],
+    ] {
+        let face = HullFace::new(points, [a, b, c]);
+        faces.push(Some(if face.distance(inside) > 0.0 {
+            HullFace::new(points, [a, c, b])
+        } else {
+            face
+        }));
+    }
+    let used = [first, second, third, fourth];
+    assign_outside(points, &mut faces, &[0, 1, 2, 3], (0..points.len()).filter(|i| !used.contains(i)), eps);
+
+    loop {
+        let eye = (faces.iter().flatten())
+            .flat_map(|face| face.outside.iter().map(move |&point| (point, face.distance(points[point]))))
+            .max_by(|(_, d1), (_, d2)| d1.total_cmp(d2));
+        let Some((eye, _)) = eye else { break };
+
+        let visible = (0..faces.len())
+            .filter(|&i| matches!(&faces[i], Some(face) if face.distance(points[eye]) > eps))
+            .collect::<Vec<_>>();
+        let edges = (visible.iter())
+            .flat_map(|&i| {
+                let [a, b, c] = faces[i].as_ref().unwrap().verts;
+                [(a, b), (b, c), (c, a)]
+            })
+            .collect::<HashSet<_>>();
+        // the edges between visible and hidden faces, which keep their direction in the faces replacing the visible ones
+        let horizon = edges.iter().filter(|&&(a, b)| !edges.contains(&(b, a))).copied().collect::<Vec<_>>();
+
+        let num_faces = faces.iter().flatten().count();
+        if num_faces - visible.len() + horizon.len() > max_faces {
+            break;
+        }
+
+        let mut orphans = vec![];
+        for &i in &visible {
+            orphans.extend(faces[i].take().unwrap().outside.into_iter().filter(|&point| point != eye));
+        }
+        let first_new = faces.len();
+        faces.extend(horizon.into_iter().map(|(a, b)| Some(HullFace::new(points, [a, b, eye]))));
+        let new_faces = (first_new..faces.len()).collect::<Vec<_>>();
+        assign_outside(points, &mut faces, &new_faces, orphans.into_iter(), eps);
+    }
+
+    Some(faces.into_iter().flatten().map(|face| face.verts).collect())
+}
+
+// the hull's points, without the ones it doesn't use, and its triangles indexing into them
+fn compact(points: &[Vec3d], triangles: Vec<[usize; 3]>) -> (Vec<Vec3d>, Vec<Vec<usize>>) {
+    let mut remap = vec![None; points.len()];
+    let mut verts = vec![];
+    let polys = (triangles.into_iter())
+        .map(|tri| {
+            tri.iter()
+                .map(|&i| {
+                    *remap[i].get_or_insert_with(|| {
+                        verts.push(points[i]);
+                        verts.len() - 1
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    (verts, polys)
+}
+
+// the hull scaled up about its center just enough for every point to be inside all its faces
+fn scale_to_contain(verts: &mut [Vec3d], polys: &[Vec<usize>], points: &[Vec3d]) {
+    let center = Vec3d::average(verts.iter().copied());
+    let mut scale = 1.0_f32;
+    for poly in polys {
+        let [a, b, c] = [0, 1, 2].map(|i| verts[poly[i]]);
+        let normal = (b - a).cross(&(c - a)).normalize();
+        let height = normal.dot(&(a - center));
+        if height > 0.0 {
+            for &point in points {
+                scale = scale.max(normal.dot(&(point - center)) / height);
+            }
+        }
+    }
+    for vert in verts {
+        *vert = center + (*vert - center) * scale;
+    }
+}
+
+fn oriented_box(points: &[Vec3d]) -> (Vec<Vec3d>, Vec<Vec<usize>>) {
+    let (rotation, bbox) = oriented_box_around(points);
+    // indexed by bits, x = 1, y = 2, z = 4
+    let verts = (0..8)
+        .map(|i| {
+            let pick = |bit: usize, min: f32, max: f32| if i & bit != 0 { max } else { min };
+            rotation.rvec * pick(1, bbox.min.x, bbox.max.x)
+                + rotation.uvec * pick(2, bbox.min.y, bbox.max.y)
+                + rotation.fvec * pick(4, bbox.min.z, bbox.max.z)
+        })
+        .collect();
+    let polys = [[0, 2, 6, 4], [1, 5, 7, 3], [0, 4, 5, 1], [2, 3, 7, 6], [0, 1, 3, 2], [4, 6, 7, 5]]
+        .map(Vec::from)
+        .to_vec();
+    (verts, polys)
+}
+
+impl Model {
+    // the id of the invisible texture, adding it if there isn't one
+    pub fn invisible_texture(&mut self) -> TextureId {
+        match self.textures.iter().position(|texture| texture.eq_ignore_ascii_case(INVISIBLE_TEXTURE)) {
+            Some(i) => TextureId(i as u32),
+            None => {
+                self.textures.push(INVISIBLE_TEXTURE.to_string());
+                self.recheck_warnings(Set::One(Warning::TooManyTextures));
+                TextureId(self.textures.len() as u32 - 1)
+            }
+        }
+    }
+
+    pub fn generate_collision_proxy(&mut self, source: ObjectId, style: ProxyStyle) -> Option<ObjectId> {
+        self.generate_collision_proxy_with(source, style, false)
+    }
+
+    // adds a subobject named "<source>-coll" wrapping the source's geometry, parented alongside it, or under it for a
+    // detail level, which can't share a parent; with exclusive, the source is flagged $nocollide_this_only as well
+    // a source which is flat has no hull, and gets the oriented box instead
+    // returns the proxy, or None if there's no such source subobject
+    pub fn generate_collision_proxy_with(&mut self, source: ObjectId, style: ProxyStyle, exclusive: bool) -> Option<ObjectId> {
+        let points = &self.sub_objects.get(source.0 as usize)?.bsp_data.verts;
+        let hull = match style {
+            ProxyStyle::ConvexHull => convex_hull(points, usize::MAX).map(|triangles| compact(points, triangles)),
+            ProxyStyle::DecimatedMesh(max_triangles) => convex_hull(points, max_triangles.max(4)).map(|triangles| {
+                let (mut verts, polys) = compact(points, triangles);
+                scale_to_contain(&mut verts, &polys, points);
+                (verts, polys)
+            }),
+            ProxyStyle::OrientedBox => None,
+        };
+        let (verts, polys) = hull.unwrap_or_else(|| oriented_box(points));
+
+        let texture = self.invisible_texture();
+        let center = Vec3d::average(verts.iter().copied());
+        let mut norms = vec![];
+        let polygons = (polys.into_iter())
+            .map(|mut poly| {
+                // outward facing, which the box's faces are only written to be
+                let [a, b, c] = [0, 1, 2].map(|i| verts[poly[i]]);
+                let mut normal = (b - a).cross(&(c - a)).normalize();
+                if normal.dot(&(a - center)) < 0.0 {
+                    poly.reverse();
+                    normal = -normal;
+                }
+                norms.push(normal);
+                let normal_id = NormalId(norms.len() as u32 - 1);
+                Polygon {
+                    normal: Default::default(),
+                    texture,
+                    verts: poly
+                        .into_iter()
+                        .map(|i| PolyVertex { vertex_id: VertexId(i as u32), normal_id, uv: (0.0, 0.0) })
+                        .collect(),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let source_obj = &self.sub_objects[source];
+        let (parent, offset) = match source_obj.parent {
+            Some(parent) => (parent, source_obj.offset),
+            None => (source, Vec3d::ZERO),
+        };
+        let base_name = format!("{}-coll", source_obj.name);
+        let mut name = base_name.clone();
+        for i in 2.. {
+            if self.get_obj_id_by_name(&name).is_none() {
+                break;
+            }
+            name = format!("{}{}", base_name, i);
+        }
+
+        let new_id = ObjectId(self.sub_objects.len() as u32);
+        let mut proxy = SubObject {
+            obj_id: new_id,
+            parent: Some(parent),
+            offset,
+            geo_center: offset,
+            name,
+            bsp_data: BspData {
+                collision_tree: BspData::recalculate_with(&verts, polygons.into_iter(), &self.tolerance_policy),
+                norms,
+                verts,
+            },
+            ..Default::default()
+        };
+        proxy.recalc_bbox_with(&self.tolerance_policy);
+        proxy.recalc_radius_with(&self.tolerance_policy);
+        self.sub_objects.push(proxy);
+        self.sub_objects[parent].children.push(new_id);
+        self.header.num_subobjects = self.sub_objects.len() as u32;
+
+        if exclusive {
+            properties_set_flag(&mut self.sub_objects[source].properties, "$nocollide_this_only");
+        }
+
+        self.recalc_semantic_name_links();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        Some(new_id)
+    }
+
+    // how far outside the proxy the source's vertices reach, 0 if it contains them all (up to rounding)
+    // None if either subobject doesn't exist
+    pub fn collision_proxy_penetration(&self, source: ObjectId, proxy: ObjectId) -> Option<f32> {
+        if proxy.0 as usize >= self.sub_objects.len() {
+            return None;
+        }
+        let hull = SpatialHash::from_subobject(self, proxy);
+        let offset = self.get_total_subobj_offset(source);
+        let verts = &self.sub_objects.get(source.0 as usize)?.bsp_data.verts;
+        Some(verts.iter().filter_map(|&vert| hull.signed_distance(vert + offset)).fold(0.0, f32::max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::test_util::Rng;
+
+    fn extent(model: &Model, id: ObjectId) -> f32 {
+        (model.sub_objects[id].bsp_data.verts.iter()).fold(0.0, |max, vert| max.max(vert.magnitude()))
+    }
+
+    // the fighter with some of detail0's and turret01a's vertices pushed out to several times their distance from the
+    // center, which makes for a hull with deep concave pockets between the spikes
+    fn spiky_fighter(seed: u64) -> Model {
+        let mut model = sample_fighter(seed);
+        let mut rng = Rng::new(seed);
+        for id in [ObjectId(0), ObjectId(1)] {
+            for vert in &mut model.sub_objects[id].bsp_data.verts {
+                if rng.chance(30) {
+                    *vert = *vert * rng.float(1.5, 3.0);
+                }
+            }
+        }
+        model
+    }
+
+    #[test]
+    fn proxies_contain_a_spiky_mesh() {
+        for seed in 0..5 {
+            for style in [ProxyStyle::ConvexHull, ProxyStyle::OrientedBox, ProxyStyle::DecimatedMesh(12)] {
+                for source in [ObjectId(0), ObjectId(1)] {
+                    let mut model = spiky_fighter(seed);
+                    let proxy = model.generate_collision_proxy(source, style).unwrap();
+                    let tolerance = extent(&model, source) * 1e-4;
+                    let penetration = model.collision_proxy_penetration(source, proxy).unwrap();
+                    assert!(penetration <= tolerance, "{style:?} of {source:?} with seed {seed} is {penetration} short");
+
+                    let proxy_obj = &model.sub_objects[proxy];
+                    let polys = proxy_obj.bsp_data.collision_tree.leaves().count();
+                    match style {
+                        ProxyStyle::OrientedBox => assert_eq!((proxy_obj.bsp_data.verts.len(), polys), (8, 6)),
+                        ProxyStyle::DecimatedMesh(max_triangles) => assert!(polys <= max_triangles),
+                        ProxyStyle::ConvexHull => assert!(proxy_obj.bsp_data.verts.len() <= model.sub_objects[source].bsp_data.verts.len()),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn penetration_is_reported() {
+        let mut model = spiky_fighter(0);
+        let proxy = model.generate_collision_proxy(ObjectId(0), ProxyStyle::ConvexHull).unwrap();
+        let extent = extent(&model, ObjectId(0));
+        // shrunk to half its size, the proxy's centered near enough the origin for the spikes to stick out of it
+        let bsp_data = &mut model.sub_objects[proxy].bsp_data;
+        for vert in &mut bsp_data.verts {
+            *vert = *vert * 0.5;
+        }
+        let polys = bsp_data.collision_tree.leaves().map(|(_, poly)| poly.clone()).collect::<Vec<_>>();
+        bsp_data.collision_tree = BspData::recalculate(&bsp_data.verts, polys.into_iter());
+        let penetration = model.collision_proxy_penetration(ObjectId(0), proxy).unwrap();
+        assert!(penetration > extent * 0.1 && penetration <= extent * 0.5 + 1e-3, "{penetration} of {extent}");
+    }
+
+    #[test]
+    fn proxies_sit_next_to_their_source() {
+        let mut model = sample_fighter(0);
+        let num_textures = model.textures.len();
+
+        let turret_proxy = model.generate_collision_proxy_with(ObjectId(1), ProxyStyle::OrientedBox, true).unwrap();
+        let turret_proxy = &model.sub_objects[turret_proxy];
+        assert_eq!(turret_proxy.name, "turret01a-coll");
+        assert_eq!((turret_proxy.parent, turret_proxy.offset), (Some(ObjectId(0)), model.sub_objects[ObjectId(1)].offset));
+        assert!(model.sub_objects[ObjectId(0)].children.contains(&turret_proxy.obj_id));
+        assert!(!model.sub_objects[ObjectId(1)].collides() && turret_proxy.collides());
+
+        // a detail level goes under its source, and a second proxy gets a new name but the same texture
+        let hull_proxy = model.generate_collision_proxy(ObjectId(0), ProxyStyle::ConvexHull).unwrap();
+        let hull_proxy2 = model.generate_collision_proxy(ObjectId(0), ProxyStyle::DecimatedMesh(8)).unwrap();
+        assert_eq!(model.sub_objects[hull_proxy].parent, Some(ObjectId(0)));
+        assert!(model.sub_objects[ObjectId(0)].collides());
+        assert_eq!((model.sub_objects[hull_proxy].name.as_str(), model.sub_objects[hull_proxy2].name.as_str()), ("detail0-coll", "detail0-coll2"));
+        assert_eq!(model.textures.len(), num_textures + 1);
+        assert_eq!(model.textures[num_textures], INVISIBLE_TEXTURE);
+        for (_, poly) in model.sub_objects[hull_proxy2].bsp_data.collision_tree.leaves() {
+            assert_eq!(poly.texture, TextureId(num_textures as u32));
+        }
+        assert_eq!(model.header.num_subobjects as usize, model.sub_objects.len());
+    }
+}
@@ -0,0 +1,292 @@
+// workarounds for files written by old converters, which the engine tolerated but which otherwise load wrong
+//
+// every fixup checks for its symptom before touching anything, so enabling a quirk for a file which doesn't
+// have it changes nothing, and each one which does apply leaves a QuirkNotice on the model
+use std::collections::BTreeSet;
+
+use crate::{Model, ShieldData, ShieldNode, SubObject, TextureId, Version};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Quirk {
+    // COB2FS (FS1 era, version 20.16 files): polygon texture indices are 1-based, so the last texture is
+    // referred to by an index one past the end of TXTR, and would otherwise be treated as untextured
+    TextureIndexOffByOne,
+    // COB2FS: strings aren't null terminated, but padded out to their length with spaces or control bytes
+    UnterminatedStrings,
+    // early PCS builds (21.16 and 21.17 files): the shield collision tree has leaves with no polygons,
+    // or a single point as their bbox, so shield hits are missed; the tree is rebuilt from the shield mesh
+    EmptyShieldLeaves,
+    // early PCS builds: glow banks have an uninitialized detail level, which is reset to detail0
+    GarbageGlowLod,
+}
+pub const ALL_QUIRKS: [Quirk; 4] = [
+    Quirk::TextureIndexOffByOne,
+    Quirk::UnterminatedStrings,
+    Quirk::EmptyShieldLeaves,
+    Quirk::GarbageGlowLod,
+];
+
+// a tool whose files are known to have quirks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LegacyTool {
+    Cob2fs,
+    EarlyPcs,
+}
+impl LegacyTool {
+    // from the version, which COB2FS always wrote as 20.16, and the PINF comments, which PCS signs
+    // PCS2 also mentions PCS in its comments, but writes files without these quirks
+    pub fn detect(version: Version, comments: &str) -> Option<LegacyTool> {
+        let comments = comments.to_ascii_lowercase();
+        if version == Version::V20_16 || comments.contains("cob2fs") {
+            Some(LegacyTool::Cob2fs)
+        } else if version <= Version::V21_17 && comments.contains("pcs") && !comments.contains("pcs2") {
+            Some(LegacyTool::EarlyPcs)
+        } else {
+            None
+        }
+    }
+
+    pub fn quirks(self) -> &'static [Quirk] {
+        match self {
+            LegacyTool::Cob2fs => &[Quirk::TextureIndexOffByOne, Quirk::UnterminatedStrings],
+            LegacyTool::EarlyPcs => &[Quirk::EmptyShieldLeaves, Quirk::GarbageGlowLod],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuirkSet {
+    // whatever LegacyTool::detect finds the file was written by needs
+    Auto,
+    Enabled(BTreeSet<Quirk>),
+}
+impl Default for QuirkSet {
+    fn default() -> Self {
+        QuirkSet::Enabled(BTreeSet::new())
+    }
+}
+impl QuirkSet {
+    pub fn all() -> Self {
+        QuirkSet::Enabled(ALL_QUIRKS.into_iter().collect())
+    }
+
+    pub fn with(self, quirk: Quirk) -> Self {
+        let mut quirks = match self {
+            QuirkSet::Auto => BTreeSet::new(),
+            QuirkSet::Enabled(quirks) => quirks,
+        };
+        quirks.insert(quirk);
+        QuirkSet::Enabled(quirks)
+    }
+
+    pub(crate) fn resolve(&self, version: Version, comments: &str) -> BTreeSet<Quirk> {
+        match self {
+            QuirkSet::Auto => LegacyTool::detect(version, comments)
+                .map_or(&[][..], LegacyTool::quirks)
+                .iter()
+                .copied()
+                .collect(),
+            QuirkSet::Enabled(quirks) => quirks.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuirkNotice {
+    pub quirk: Quirk,
+    pub description: String,
+}
+
+// has to happen before the untextured slot is filled, or the last texture's polygons would be taken as untextured
+pub(crate) fn fix_texture_index_off_by_one(sub_objects: &mut [SubObject], num_textures: usize) -> Option<QuirkNotice> {
+    let past_end = TextureId(num_textures as u32);
+    let used = |id: TextureId| (sub_objects.iter()).any(|subobj| subobj.bsp_data.collision_tree.leaves().any(|(_, poly)| poly.texture == id));
+    // 1-based indices never use 0, and do use one past the end unless the last texture is unused
+    if num_textures == 0 || !used(past_end) || used(TextureId(0)) {
+        return None;
+    }
+
+    let mut count = 0;
+    for subobj in sub_objects.iter_mut() {
+        for (_, poly) in subobj.bsp_data.collision_tree.leaves_mut() {
+            if poly.texture.0 >= 1 && poly.texture <= past_end {
+                poly.texture = TextureId(poly.texture.0 - 1);
+                count += 1;
+            }
+        }
+    }
+    Some(QuirkNotice {
+        quirk: Quirk::TextureIndexOffByOne,
+        description: format!("{} polygons had 1-based texture indices", count),
+    })
+}
+
+fn trim_unterminated(string: &mut String) -> bool {
+    let trimmed_len = string.trim_end_matches(|char: char| char == ' ' || char.is_control()).len();
+    let changed = trimmed_len != string.len();
+    string.truncate(trimmed_len);
+    changed
+}
+
+fn shield_tree_has_empty_leaves(node: &ShieldNode) -> bool {
+    match node {
+        ShieldNode::Split { front, back, .. } => shield_tree_has_empty_leaves(front) || shield_tree_has_empty_leaves(back),
+        ShieldNode::Leaf { bbox, poly_list } => poly_list.is_empty() || bbox.min == bbox.max,
+    }
+}
+
+impl Model {
+    // the fixups which can wait until the model is put together
+    pub(crate) fn fix_quirks(&mut self, quirks: &BTreeSet<Quirk>) {
+        if quirks.contains(&Quirk::UnterminatedStrings) {
+            let mut count = 0;
+            let mut trim = |string: &mut String| count += trim_unterminated(string) as usize;
+            for subobj in self.sub_objects.iter_mut() {
+                trim(&mut subobj.name);
+                trim(&mut subobj.properties);
+            }
+            self.textures.iter_mut().for_each(&mut trim);
+            for path in &mut self.paths {
+                trim(&mut path.name);
+                trim(&mut path.parent);
+            }
+            for point in &mut self.special_points {
+                trim(&mut point.name);
+                trim(&mut point.properties);
+            }
+            self.thruster_banks.iter_mut().for_each(|bank| trim(&mut bank.properties));
+            self.glow_banks.iter_mut().for_each(|bank| trim(&mut bank.properties));
+            self.docking_bays.iter_mut().for_each(|dock| trim(&mut dock.properties));
+            if count > 0 {
+                self.quirk_notices.push(QuirkNotice {
+                    quirk: Quirk::UnterminatedStrings,
+                    description: format!("{} strings had trailing padding", count),
+                });
+            }
+        }
+
+        if quirks.contains(&Quirk::EmptyShieldLeaves) {
+            if let Some(shield) = &mut self.shield_data {
+                if !shield.polygons.is_empty() && matches!(&shield.collision_tree, Some(tree) if shield_tree_has_empty_leaves(tree)) {
+                    shield.collision_tree = Some(ShieldData::recalculate_tree_with(&shield.verts, &shield.polygons, &self.tolerance_policy));
+                    self.quirk_notices.push(QuirkNotice {
+                        quirk: Quirk::EmptyShieldLeaves,
+                        description: "the shield collision tree had empty leaves and was rebuilt".to_string(),
+                    });
+                }
+            }
+        }
+
+        if quirks.contains(&Quirk::GarbageGlowLod) {
+            let num_detail_levels = self.header.detail_levels.len() as u32;
+            let mut count = 0;
+            for bank in &mut self.glow_banks {
+                if bank.lod >= num_detail_levels.max(1) {
+                    bank.lod = 0;
+                    count += 1;
+                }
+            }
+            if count > 0 {
+                self.quirk_notices.push(QuirkNotice {
+                    quirk: Quirk::GarbageGlowLod,
+                    description: format!("{} glow banks had an invalid detail level", count),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::{ObjectId, ParseOptions, Parser, Warning, WriteOptions};
+
+    fn write(model: &Model) -> Vec<u8> {
+        let options = WriteOptions { allow_errors: true, ..Default::default() };
+        let mut buf = vec![];
+        model.write_with(&mut buf, &options).unwrap();
+        buf
+    }
+
+    fn parse(buf: &[u8], quirks: QuirkSet) -> Model {
+        let mut parser = Parser::new(Cursor::new(buf)).unwrap();
+        parser.set_options(ParseOptions { quirks, ..Default::default() });
+        parser.parse(PathBuf::new()).unwrap()
+    }
+
+    fn notices(model: &Model) -> Vec<Quirk> {
+        model.quirk_notices.iter().map(|notice| notice.quirk).collect()
+    }
+
+    #[test]
+    fn detecting_the_tool() {
+        assert_eq!(LegacyTool::detect(Version::V20_16, ""), Some(LegacyTool::Cob2fs));
+        assert_eq!(LegacyTool::detect(Version::V21_17, "Converted by COB2FS"), Some(LegacyTool::Cob2fs));
+        assert_eq!(LegacyTool::detect(Version::V21_17, "Made with PCS 1.3"), Some(LegacyTool::EarlyPcs));
+        assert_eq!(LegacyTool::detect(Version::V21_17, "Made with PCS2"), None);
+        assert_eq!(LegacyTool::detect(Version::V22_01, "Made with PCS 1.3"), None);
+        assert_eq!(LegacyTool::detect(Version::V21_17, ""), None);
+        assert_eq!(QuirkSet::Auto.resolve(Version::V20_16, ""), LegacyTool::Cob2fs.quirks().iter().copied().collect());
+        assert!(QuirkSet::default().resolve(Version::V20_16, "").is_empty());
+    }
+
+    // a COB2FS style file: 1-based texture indices, and strings padded with spaces
+    #[test]
+    fn cob2fs_textures_and_strings() {
+        let mut fighter = sample_fighter(0);
+        for subobj in fighter.sub_objects.iter_mut() {
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves_mut() {
+                poly.texture.0 += 1;
+            }
+        }
+        fighter.sub_objects[ObjectId(0)].name = "detail0   ".to_string();
+        fighter.textures[1] = "fighter-fittings\0\0".to_string();
+        let buf = write(&fighter);
+
+        // without the quirks, the last texture's polygons end up untextured, and the names are wrong
+        let plain = parse(&buf, QuirkSet::default());
+        assert_eq!(plain.textures.len(), 3);
+        assert_eq!(plain.sub_objects[ObjectId(0)].name, "detail0   ");
+        assert!(plain.quirk_notices.is_empty());
+
+        let fixed = parse(&buf, QuirkSet::default().with(Quirk::TextureIndexOffByOne).with(Quirk::UnterminatedStrings));
+        assert_eq!(fixed.textures, ["fighter-hull", "fighter-fittings"]);
+        assert_eq!(fixed.untextured_idx, None);
+        assert_eq!(fixed.sub_objects[ObjectId(0)].name, "detail0");
+        assert_eq!(notices(&fixed), [Quirk::TextureIndexOffByOne, Quirk::UnterminatedStrings]);
+        assert!(fixed.warnings.contains(&Warning::LegacyQuirksFixed));
+
+        // a file without the quirks is left alone even with them enabled
+        let clean = parse(&write(&sample_fighter(0)), QuirkSet::all());
+        assert!(clean.quirk_notices.is_empty());
+        assert_eq!(clean.textures, ["fighter-hull", "fighter-fittings"]);
+    }
+
+    // an early PCS style file: a shield tree with an empty leaf, and a glow bank with an uninitialized detail level
+    #[test]
+    fn early_pcs_shield_and_glows() {
+        let mut fighter = sample_fighter(0);
+        let shield = fighter.shield_data.as_mut().unwrap();
+        let bbox = crate::BoundingBox::from_vectors(shield.verts.iter().copied());
+        shield.collision_tree = Some(ShieldNode::Split {
+            bbox,
+            front: Box::new(shield.collision_tree.take().unwrap()),
+            back: Box::new(ShieldNode::Leaf { bbox, poly_list: vec![] }),
+        });
+        fighter.glow_banks = vec![crate::GlowPointBank { lod: 0xcdcd_cdcd, ..Default::default() }];
+        let buf = write(&fighter);
+
+        let plain = parse(&buf, QuirkSet::default());
+        assert_eq!(plain.glow_banks[0].lod, 0xcdcd_cdcd);
+
+        let fixed = parse(&buf, QuirkSet::default().with(Quirk::EmptyShieldLeaves).with(Quirk::GarbageGlowLod));
+        assert_eq!(notices(&fixed), [Quirk::EmptyShieldLeaves, Quirk::GarbageGlowLod]);
+        assert_eq!(fixed.glow_banks[0].lod, 0);
+        let tree = fixed.shield_data.as_ref().unwrap().collision_tree.as_ref().unwrap();
+        assert!(!shield_tree_has_empty_leaves(tree));
+    }
+}
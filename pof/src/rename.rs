@@ -0,0 +1,271 @@
+// renaming many subobjects, paths, special points or docking bays at once from a template, for bringing models
+// from elsewhere into a naming convention
+//
+// a template is literal text with these substituted:
+//   {old}  the old name, less the pattern's prefix and suffix
+//   {n}    a counter, in order of the renamed entities' current indices
+//   {n:03} the counter zero padded to 3 digits, or any other width
+// anything else in braces is kept as is
+use std::collections::HashMap;
+
+use crate::{properties_update_field, Model, ObjectId, Set, MAX_NAME_LEN};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameScope {
+    SubObjects,
+    Paths,
+    SpecialPoints,
+    // by their "$name" property
+    DockingBays,
+    Attachments,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseTransform {
+    #[default]
+    Unchanged,
+    Lower,
+    Upper,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenamePattern {
+    // only names starting and ending with these are renamed, and they're left out of {old}
+    pub prefix: String,
+    pub suffix: String,
+    pub template: String,
+    // applied to the whole new name
+    pub case: CaseTransform,
+    pub first_number: u32,
+}
+impl RenamePattern {
+    pub fn new(template: impl Into<String>) -> Self {
+        RenamePattern {
+            prefix: String::new(),
+            suffix: String::new(),
+            template: template.into(),
+            case: CaseTransform::Unchanged,
+            first_number: 1,
+        }
+    }
+
+    // the new name for this old one, None if the old name doesn't have the prefix and suffix
+    pub fn apply(&self, old: &str, n: u32) -> Option<String> {
+        let old = old.strip_prefix(&self.prefix)?.strip_suffix(&self.suffix)?;
+        let mut out = String::new();
+        let mut rest = &self.template[..];
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let Some(end) = rest.find('}') else { break };
+            match &rest[1..end] {
+                "old" => out.push_str(old),
+                "n" => out.push_str(&n.to_string()),
+                field => match field.strip_prefix("n:").and_then(|width| width.parse::<usize>().ok()) {
+                    Some(width) => out.push_str(&format!("{:0width$}", n, width = width)),
+                    None => out.push_str(&rest[..=end]),
+                },
+            }
+            rest = &rest[end + 1..];
+        }
+        out.push_str(rest);
+
+        Some(match self.case {
+            CaseTransform::Unchanged => out,
+            CaseTransform::Lower => out.to_lowercase(),
+            CaseTransform::Upper => out.to_uppercase(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenameProblem {
+    // another entity in the scope would end up with the same name
+    Collision,
+    // longer than the engine allows, which is only a warning on the model
+    TooLong,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenameResult {
+    // the index of the entity within the scope, the ObjectId for subobjects
+    pub index: usize,
+    pub old_name: String,
+    pub new_name: String,
+    pub problems: Vec<RenameProblem>,
+}
+
+impl Model {
+    fn names_in_scope(&self, scope: RenameScope) -> Vec<String> {
+        match scope {
+            RenameScope::SubObjects => self.sub_objects.iter().map(|subobj| subobj.name.clone()).collect(),
+            RenameScope::Paths => self.paths.iter().map(|path| path.name.clone()).collect(),
+            RenameScope::SpecialPoints => self.special_points.iter().map(|point| point.name.clone()).collect(),
+            RenameScope::DockingBays => self
+                .docking_bays
+                .iter()
+                .map(|dock| dock.get_name().unwrap_or_default().to_string())
+                .collect(),
+            RenameScope::Attachments => self.attachments.iter().map(|attachment| attachment.name.clone()).collect(),
+        }
+    }
+
+    // what batch_rename would do, without doing it, for previews
+    pub fn preview_batch_rename(&self, scope: RenameScope, pattern: &RenamePattern) -> Vec<RenameResult> {
+        let mut names = self.names_in_scope(scope);
+        let mut results = vec![];
+        let mut n = pattern.first_number;
+        for (index, old_name) in names.iter().enumerate() {
+            if let Some(new_name) = pattern.apply(old_name, n) {
+                n += 1;
+                let problems = if new_name.len() > MAX_NAME_LEN {
+                    vec![RenameProblem::TooLong]
+                } else {
+                    vec![]
+                };
+                results.push(RenameResult { index, old_name: old_name.clone(), new_name, problems });
+            }
+        }
+
+        for result in &results {
+            names[result.index] = result.new_name.clone();
+        }
+        // unnamed docking bays are left out, they can't collide
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        for name in names.iter().filter(|name| scope != RenameScope::DockingBays || !name.is_empty()) {
+            *counts.entry(name).or_default() += 1;
+        }
+        for result in &mut results {
+            if counts.get(&result.new_name[..]).copied().unwrap_or(0) > 1 {
+                result.problems.push(RenameProblem::Collision);
+            }
+        }
+        results
+    }
+
+    // renames everything in the scope matching the pattern's prefix and suffix, fixing up references to renamed subobjects
+    // if any of the new names would collide, nothing is renamed; the results say which
+    pub fn batch_rename(&mut self, scope: RenameScope, pattern: &RenamePattern) -> Vec<RenameResult> {
+        let results = self.preview_batch_rename(scope, pattern);
+        if results.iter().any(|result| result.problems.contains(&RenameProblem::Collision)) {
+            return results;
+        }
+
+        match scope {
+            RenameScope::SubObjects => {
+                // via placeholder names first, so references follow names which are swapped between subobjects
+                for result in &results {
+                    self.rename_subobject(ObjectId(result.index as u32), format!("\0{}", result.index));
+                }
+                for result in &results {
+                    self.rename_subobject(ObjectId(result.index as u32), result.new_name.clone());
+                }
+            }
+            RenameScope::Paths => {
+                for result in &results {
+                    self.paths[result.index].name = result.new_name.clone();
+                }
+            }
+            RenameScope::SpecialPoints => {
+                for result in &results {
+                    self.special_points[result.index].name = result.new_name.clone();
+                }
+            }
+            RenameScope::DockingBays => {
+                for result in &results {
+                    properties_update_field(&mut self.docking_bays[result.index].properties, "$name", &result.new_name);
+                }
+            }
+            RenameScope::Attachments => {
+                for result in &results {
+                    self.attachments[result.index].name = result.new_name.clone();
+                }
+            }
+        }
+
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::NameLink;
+
+    fn names(model: &Model) -> Vec<&str> {
+        model.sub_objects.iter().map(|subobj| &subobj.name[..]).collect()
+    }
+
+    #[test]
+    fn templates() {
+        let mut pattern = RenamePattern::new("{old}-{n}-{n:03}-{x}");
+        assert_eq!(pattern.apply("gun", 7).as_deref(), Some("gun-7-007-{x}"));
+        pattern.prefix = "turret".to_string();
+        pattern.case = CaseTransform::Upper;
+        assert_eq!(pattern.apply("turret01a", 12).as_deref(), Some("01A-12-012-{X}"));
+        assert_eq!(pattern.apply("gun", 1), None);
+    }
+
+    #[test]
+    fn counter_follows_the_current_order() {
+        let mut fighter = sample_fighter(0);
+        let mut pattern = RenamePattern::new("mount{n:02}");
+        pattern.prefix = "turret".to_string();
+        pattern.first_number = 5;
+        let results = fighter.batch_rename(RenameScope::SubObjects, &pattern);
+        assert_eq!(results.iter().map(|result| result.index).collect::<Vec<_>>(), [1, 2, 3, 4]);
+        assert!(results.iter().all(|result| result.problems.is_empty()));
+        assert_eq!(names(&fighter)[1..5], ["mount05", "mount06", "mount07", "mount08"]);
+
+        // references to the renamed subobjects follow
+        pattern = RenamePattern::new("lod{old}");
+        pattern.prefix = "detail".to_string();
+        fighter.batch_rename(RenameScope::SubObjects, &pattern);
+        assert_eq!(fighter.paths[0].parent, "lod0");
+    }
+
+    #[test]
+    fn collisions_and_long_names() {
+        let mut fighter = sample_fighter(0);
+        let mut pattern = RenamePattern::new("debris");
+        pattern.prefix = "debris".to_string();
+        let preview = fighter.preview_batch_rename(RenameScope::SubObjects, &pattern);
+        assert_eq!(preview.len(), 2);
+        assert!(preview.iter().all(|result| result.problems == [RenameProblem::Collision]));
+        // a collision renames nothing
+        assert_eq!(fighter.batch_rename(RenameScope::SubObjects, &pattern), preview);
+        assert_eq!(names(&fighter)[7..], ["debris01", "debris02"]);
+
+        // nor with a name not being renamed
+        pattern.template = "detail{old}".to_string();
+        pattern.suffix = "1".to_string();
+        assert_eq!(fighter.preview_batch_rename(RenameScope::SubObjects, &pattern)[0].problems, [RenameProblem::Collision]);
+
+        // but a long name is only a warning
+        let pattern = RenamePattern::new(format!("{{old}}{}", "x".repeat(MAX_NAME_LEN)));
+        let results = fighter.batch_rename(RenameScope::Paths, &pattern);
+        assert_eq!(results[0].problems, [RenameProblem::TooLong]);
+        assert_eq!(fighter.paths[0].name, results[0].new_name);
+    }
+
+    #[test]
+    fn name_links_are_recalculated() {
+        let mut fighter = sample_fighter(0);
+        let has_detail_level = |model: &Model| matches!(model.sub_objects[ObjectId(1)].name_links[..], [NameLink::DetailLevel(ObjectId(2), 1)]);
+        assert!(has_detail_level(&fighter));
+
+        let mut pattern = RenamePattern::new("{old}x");
+        pattern.suffix = "b".to_string();
+        fighter.batch_rename(RenameScope::SubObjects, &pattern);
+        assert_eq!(names(&fighter)[1..5], ["turret01a", "turret01x", "turret02a", "turret02x"]);
+        assert!(fighter.sub_objects.iter().all(|subobj| subobj.name_links.is_empty()));
+
+        let mut pattern = RenamePattern::new("{old}b");
+        pattern.suffix = "x".to_string();
+        fighter.batch_rename(RenameScope::SubObjects, &pattern);
+        assert!(has_detail_level(&fighter));
+    }
+}
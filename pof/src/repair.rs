@@ -0,0 +1,248 @@
+// files whose chunk length fields disagree with what's actually in the chunks, which some old tools wrote
+//
+// the engine seeks to the declared end of every chunk, so it copes as long as the declared lengths are right and only the
+// content is off; the parser does the same by default, noting each chunk which didn't add up, see ChunkLengthTrust
+// repair_file goes the other way, fixing the lengths to match the content without parsing it: a chunk's real end is taken
+// to be wherever the next chunk plausibly starts, the declared end if that works, otherwise the closest spot that does
+use std::io::{self, ErrorKind, Read, Write};
+
+use crate::inspect::{ChunkInfo, CHUNK_ORDER};
+
+// what the parser does when a chunk's parser reads a different amount than its declared length
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChunkLengthTrust {
+    // carry on from the declared end of the chunk, as the engine does
+    #[default]
+    Declared,
+    // carry on from wherever the chunk's parser stopped, for files whose lengths are wrong but content is fine
+    Consumed,
+}
+
+// things the parser tolerated in the file, which saving it again will fix
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseNotice {
+    ChunkLengthMismatch { tag: [u8; 4], declared: u32, consumed: u32 },
+}
+impl ParseNotice {
+    pub fn description(&self) -> String {
+        match self {
+            ParseNotice::ChunkLengthMismatch { tag, declared, consumed } => {
+                format!("{} declares {} bytes but holds {}", String::from_utf8_lossy(tag), declared, consumed)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkLengthFix {
+    pub tag: [u8; 4],
+    // the position of the chunk's header in the file
+    pub offset: u64,
+    pub declared: u32,
+    pub actual: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RepairReport {
+    // the repaired file's layout
+    pub chunks: Vec<ChunkInfo>,
+    pub fixes: Vec<ChunkLengthFix>,
+}
+impl RepairReport {
+    pub fn changed(&self) -> bool {
+        !self.fixes.is_empty()
+    }
+}
+
+fn is_known_chunk(tag: &[u8]) -> bool {
+    CHUNK_ORDER.iter().flat_map(|ids| ids.iter()).any(|id| &id[..] == tag)
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes(bytes[pos..pos + 4].try_into().unwrap())
+}
+
+// whether a chunk could start at pos: the end of the file, or a chunk we know of whose own length fits in the file
+fn chunk_could_start_at(bytes: &[u8], pos: usize) -> bool {
+    pos == bytes.len()
+        || (pos + 8 <= bytes.len() && is_known_chunk(&bytes[pos..pos + 4]) && pos + 8 + read_u32(bytes, pos + 4) as usize <= bytes.len())
+}
+
+// copies a pof file, fixing any chunk length which doesn't lead to the next chunk
+// the content of every chunk is copied as is
+pub fn repair_file(mut reader: impl Read, mut writer: impl Write) -> io::Result<RepairReport> {
+    let mut bytes = vec![];
+    reader.read_to_end(&mut bytes)?;
+    if bytes.len() < 8 || &bytes[..4] != b"PSPO" {
+        return Err(io::Error::new(ErrorKind::InvalidData, "Not a freespace 2 pof file!"));
+    }
+    writer.write_all(&bytes[..8])?;
+
+    let mut report = RepairReport::default();
+    let mut pos = 8;
+    while pos < bytes.len() {
+        if pos + 8 > bytes.len() {
+            return Err(io::Error::new(ErrorKind::UnexpectedEof, "the file ends partway through a chunk header"));
+        }
+        let tag: [u8; 4] = bytes[pos..pos + 4].try_into().unwrap();
+        let declared = read_u32(&bytes, pos + 4);
+        let start = pos + 8;
+
+        let declared_end = start.saturating_add(declared as usize);
+        let end = if declared_end <= bytes.len() && chunk_could_start_at(&bytes, declared_end) {
+            declared_end
+        } else {
+            // the end of the file always qualifies, so there's at least one
+            (start..=bytes.len())
+                .filter(|&end| chunk_could_start_at(&bytes, end))
+                .min_by_key(|&end| end.abs_diff(declared_end))
+                .unwrap()
+        };
+
+        let actual = (end - start) as u32;
+        if actual != declared {
+            report.fixes.push(ChunkLengthFix { tag, offset: pos as u64, declared, actual });
+        }
+        let subobj_id = if (&tag == b"OBJ2" || &tag == b"SOBJ") && start + 4 <= end {
+            Some(read_u32(&bytes, start))
+        } else {
+            None
+        };
+        report.chunks.push(ChunkInfo { id: tag, offset: pos as u64, len: actual, subobj_id });
+
+        writer.write_all(&tag)?;
+        writer.write_all(&actual.to_le_bytes())?;
+        writer.write_all(&bytes[start..end])?;
+        pos = end;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspect::inspect;
+    use crate::parse::{ParseOptions, Parser};
+    use crate::samples::sample_fighter;
+    use crate::{Model, Warning};
+    use std::io::Cursor;
+    use std::path::PathBuf;
+
+    const MANGLED: [&[u8; 4]; 3] = [b"TXTR", b"PATH", b"EYE "];
+
+    // the file rebuilt with the lengths of the MANGLED chunks off by len_delta, and padding zeroes after their content
+    fn mangle(bytes: &[u8], len_delta: i32, padding: usize) -> Vec<u8> {
+        let mut out = bytes[..8].to_vec();
+        let chunks = inspect(Cursor::new(bytes)).unwrap().chunks;
+        assert!(MANGLED.iter().all(|tag| chunks.iter().any(|chunk| &chunk.id == *tag)));
+        for chunk in chunks {
+            let start = chunk.offset as usize + 8;
+            let (len_delta, padding) = if MANGLED.contains(&&chunk.id) { (len_delta, padding) } else { (0, 0) };
+            out.extend(chunk.id);
+            out.extend((chunk.len as i32 + len_delta).to_le_bytes());
+            out.extend(&bytes[start..start + chunk.len as usize]);
+            out.extend(vec![0; padding]);
+        }
+        out
+    }
+
+    fn parse(bytes: &[u8], chunk_lengths: ChunkLengthTrust) -> Option<Model> {
+        let mut parser = Parser::new(Cursor::new(bytes)).ok()?;
+        parser.set_options(ParseOptions { chunk_lengths, ..Default::default() });
+        parser.parse(PathBuf::new()).ok()
+    }
+
+    fn repair(bytes: &[u8]) -> (Vec<u8>, RepairReport) {
+        let mut out = vec![];
+        let report = repair_file(bytes, &mut out).unwrap();
+        (out, report)
+    }
+
+    fn assert_notices(model: &Model, declared_delta: i32, original: &[u8]) {
+        let chunks = inspect(Cursor::new(original)).unwrap().chunks;
+        let expected = (chunks.iter().filter(|chunk| MANGLED.contains(&&chunk.id)))
+            .map(|chunk| ParseNotice::ChunkLengthMismatch {
+                tag: chunk.id,
+                declared: (chunk.len as i32 + declared_delta) as u32,
+                consumed: chunk.len,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(model.parse_notices, expected);
+        assert!(model.warnings.contains(&Warning::ChunkLengthMismatch));
+    }
+
+    #[test]
+    fn lengths_too_long() {
+        let original = sample_fighter(0).write_to_vec().unwrap();
+        let expected = Model::from_bytes(&original).unwrap().write_to_vec().unwrap();
+        let bytes = mangle(&original, 4, 0);
+
+        // the content is all there is, so only trusting what the parsers read gets it right
+        let model = parse(&bytes, ChunkLengthTrust::Consumed).unwrap();
+        assert_notices(&model, 4, &original);
+        assert_eq!(model.write_to_vec().unwrap(), expected);
+        assert!(!matches!(parse(&bytes, ChunkLengthTrust::Declared), Some(model) if model.parse_notices.is_empty()));
+
+        let (repaired, report) = repair(&bytes);
+        assert_eq!(repaired, original);
+        let fixes = report.fixes.iter().map(|fix| (fix.tag, fix.declared, fix.actual)).collect::<Vec<_>>();
+        let expected_fixes = (inspect(Cursor::new(&original)).unwrap().chunks.iter())
+            .filter(|chunk| MANGLED.contains(&&chunk.id))
+            .map(|chunk| (chunk.id, chunk.len + 4, chunk.len))
+            .collect::<Vec<_>>();
+        assert_eq!(fixes, expected_fixes);
+    }
+
+    #[test]
+    fn lengths_too_short() {
+        let original = sample_fighter(1).write_to_vec().unwrap();
+        let bytes = mangle(&original, -4, 0);
+
+        let model = parse(&bytes, ChunkLengthTrust::Consumed).unwrap();
+        assert_notices(&model, -4, &original);
+        assert_eq!(model.write_to_vec().unwrap(), Model::from_bytes(&original).unwrap().write_to_vec().unwrap());
+
+        let (repaired, report) = repair(&bytes);
+        assert_eq!((repaired, report.fixes.len()), (original, 3));
+    }
+
+    #[test]
+    fn padded_chunks() {
+        let original = sample_fighter(2).write_to_vec().unwrap();
+        let bytes = mangle(&original, 4, 4);
+
+        // the declared lengths cover the padding, so trusting them skips it, as the engine does
+        let model = parse(&bytes, ChunkLengthTrust::Declared).unwrap();
+        assert_notices(&model, 4, &original);
+        assert_eq!(model.write_to_vec().unwrap(), Model::from_bytes(&original).unwrap().write_to_vec().unwrap());
+
+        // and there's nothing to repair
+        let (repaired, report) = repair(&bytes);
+        assert!(!report.changed());
+        assert_eq!(repaired, bytes);
+        let layout = report.chunks.iter().map(|chunk| (chunk.id, chunk.offset, chunk.len)).collect::<Vec<_>>();
+        let inspected = inspect(Cursor::new(&bytes)).unwrap().chunks;
+        assert_eq!(layout, inspected.iter().map(|chunk| (chunk.id, chunk.offset, chunk.len)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn intact_files_are_left_alone() {
+        let original = sample_fighter(3).write_to_vec().unwrap();
+        for trust in [ChunkLengthTrust::Declared, ChunkLengthTrust::Consumed] {
+            let model = parse(&original, trust).unwrap();
+            assert!(model.parse_notices.is_empty() && !model.warnings.contains(&Warning::ChunkLengthMismatch));
+        }
+        let (repaired, report) = repair(&original);
+        assert!(!report.changed());
+        assert_eq!(repaired, original);
+
+        assert_eq!(repair_file(&b"IDSP\x16\x08\x00\x00"[..], vec![]).unwrap_err().kind(), ErrorKind::InvalidData);
+        // a file cut short is taken to end there, anything after the last whole chunk going to it
+        let cut_short = repair_file(&original[..original.len() - 3], vec![]).unwrap();
+        let fix = cut_short.fixes.last().unwrap();
+        assert_eq!(fix.offset as usize + 8 + fix.actual as usize, original.len() - 3);
+        // unless there's no whole chunk at all
+        assert_eq!(repair_file(&original[..12], vec![]).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,435 @@
+// generated models with the shape of real ones, for tests, benchmarks and documentation examples
+// built for this crate's own tests, and with the `test-util` feature, like crate::test_util
+//
+// everything is deterministic for a given seed; the geometry is boxes and plates rather than anything pretty, but the
+// structure (detail levels, turrets, debris, weapons, shield and so on) is what a real model would have
+use crate::test_util::Rng;
+use crate::{
+    BspData, Dock, EyePoint, Model, NormalId, NormalVec3, ObjectId, Path, PathId, PathPoint, PolyVertex, Polygon, PolygonId, Set, ShieldData,
+    ShieldPolygon, SubObject, TextureId, ThrusterBank, ThrusterGlow, Turret, Vec3d, Version, VertexId, VisualCenterMethod, WeaponHardpoint,
+};
+
+#[derive(Default)]
+struct Mesh {
+    verts: Vec<Vec3d>,
+    norms: Vec<Vec3d>,
+    polygons: Vec<Polygon>,
+}
+impl Mesh {
+    fn push_quad(&mut self, corners: [Vec3d; 4], normal: Vec3d, texture: TextureId) {
+        let first = self.verts.len() as u32;
+        let normal_id = NormalId(self.norms.len() as u32);
+        self.verts.extend(corners);
+        self.norms.push(normal);
+        let uvs = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+        let verts = (0..4)
+            .map(|i| PolyVertex { vertex_id: VertexId(first + i as u32), normal_id, uv: uvs[i] })
+            .collect();
+        self.polygons.push(Polygon { normal: Default::default(), texture, verts });
+    }
+}
+
+fn mul(a: Vec3d, b: Vec3d) -> Vec3d {
+    Vec3d::new(a.x * b.x, a.y * b.y, a.z * b.z)
+}
+
+// each face of the box as a normal and two axes along it, counter-clockwise seen from outside
+const FACES: [(Vec3d, Vec3d, Vec3d); 6] = [
+    (Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)),
+    (Vec3d::new(-1.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(0.0, 1.0, 0.0)),
+    (Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(1.0, 0.0, 0.0)),
+    (Vec3d::new(0.0, -1.0, 0.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)),
+    (Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0)),
+    (Vec3d::new(0.0, 0.0, -1.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(1.0, 0.0, 0.0)),
+];
+
+// a box centered on the origin, each face split into divisions x divisions quads
+fn cuboid(half_size: Vec3d, divisions: usize, texture: TextureId) -> Mesh {
+    let mut mesh = Mesh::default();
+    let divisions = divisions.max(1);
+    let step = 2.0 / divisions as f32;
+    for (normal, u, v) in FACES {
+        let point = |i: usize, j: usize| mul(normal + u * (-1.0 + i as f32 * step) + v * (-1.0 + j as f32 * step), half_size);
+        for i in 0..divisions {
+            for j in 0..divisions {
+                mesh.push_quad([point(i, j), point(i + 1, j), point(i + 1, j + 1), point(i, j + 1)], normal, texture);
+            }
+        }
+    }
+    mesh
+}
+
+// exactly num_quads quads in rows on the xz plane, each row at its own height, so every quad is flat but the plate isn't
+fn plate(size: f32, num_quads: usize, rng: &mut Rng, texture: TextureId) -> Mesh {
+    let cols = (num_quads as f32).sqrt().ceil().max(1.0) as usize;
+    let rows = (num_quads + cols - 1) / cols;
+    let (step, amplitude, frequency) = (size / cols as f32, rng.float(0.0, size / 10.0), rng.float(0.5, 2.0));
+    let height = |row: usize| amplitude * (row as f32 * frequency).sin();
+
+    let mut mesh = Mesh::default();
+    let up = Vec3d::new(0.0, 1.0, 0.0);
+    for quad in 0..num_quads {
+        let (col, row) = (quad % cols, quad / cols);
+        let point = |col: usize, row: usize| Vec3d::new(col as f32 * step - size / 2.0, height(row), row as f32 * step - rows as f32 * step / 2.0);
+        mesh.push_quad([point(col, row), point(col, row + 1), point(col + 1, row + 1), point(col + 1, row)], up, texture);
+    }
+    mesh
+}
+
+fn subobj(obj_id: u32, parent: Option<u32>, name: &str, offset: Vec3d, mesh: Mesh) -> SubObject {
+    let mut subobj = SubObject {
+        obj_id: ObjectId(obj_id),
+        parent: parent.map(ObjectId),
+        offset,
+        geo_center: offset,
+        name: name.to_string(),
+        bsp_data: BspData {
+            collision_tree: BspData::recalculate(&mesh.verts, mesh.polygons.into_iter()),
+            norms: mesh.norms,
+            verts: mesh.verts,
+        },
+        ..Default::default()
+    };
+    subobj.recalc_bbox();
+    subobj.recalc_radius();
+    subobj
+}
+
+// a closed box of triangles, sharing corners so every polygon has its three neighbors
+fn box_shield(half_size: Vec3d) -> ShieldData {
+    let corner = |sign: Vec3d| (sign.x > 0.0) as u32 | ((sign.y > 0.0) as u32) << 1 | ((sign.z > 0.0) as u32) << 2;
+    let verts = (0..8)
+        .map(|i| {
+            let sign = |bit: u32| if i & bit != 0 { 1.0 } else { -1.0 };
+            mul(Vec3d::new(sign(1), sign(2), sign(4)), half_size)
+        })
+        .collect::<Vec<_>>();
+
+    let mut polygons = vec![];
+    for (normal, u, v) in FACES {
+        let quad = [normal - u - v, normal + u - v, normal + u + v, normal - u + v].map(|sign| VertexId(corner(sign)));
+        for tri in [[quad[0], quad[1], quad[2]], [quad[0], quad[2], quad[3]]] {
+            polygons.push(ShieldPolygon {
+                normal,
+                verts: (tri[0], tri[1], tri[2]),
+                neighbors: Default::default(),
+            });
+        }
+    }
+
+    // each edge is shared with exactly one other triangle, going the other way
+    let edges = |poly: &ShieldPolygon| [(poly.verts.0, poly.verts.1), (poly.verts.1, poly.verts.2), (poly.verts.2, poly.verts.0)];
+    let neighbors = (polygons.iter())
+        .map(|poly| edges(poly).map(|(a, b)| PolygonId(polygons.iter().position(|other| edges(other).contains(&(b, a))).unwrap() as u32)))
+        .collect::<Vec<_>>();
+    for (poly, [n1, n2, n3]) in polygons.iter_mut().zip(neighbors) {
+        poly.neighbors = (n1, n2, n3);
+    }
+
+    ShieldData {
+        collision_tree: Some(ShieldData::recalculate_tree(&verts, &polygons)),
+        verts,
+        polygons,
+    }
+}
+
+// everything derived from the rest of the model, then the warnings and errors
+fn finish(model: &mut Model) {
+    model.header.num_subobjects = model.sub_objects.len() as u32;
+    model.recalc_all_children_ids();
+    model.recalc_semantic_name_links();
+    model.recalc_bbox();
+    model.recalc_radius();
+    model.recalc_mass();
+    model.recalc_moi();
+    model.recheck_warnings(Set::All);
+    model.recheck_errors(Set::All);
+}
+
+// a fighter with three detail levels, two turrets, primary and secondary banks, thrusters, a shield, two debris pieces,
+// an eye point and a docking bay with its path; it has no errors, warnings or invariant violations
+pub fn sample_fighter(seed: u64) -> Model {
+    let mut rng = Rng::new(seed);
+    let half = Vec3d::new(rng.float(2.5, 4.0), rng.float(0.75, 1.5), rng.float(4.0, 6.0));
+    let (hull, fittings) = (TextureId(0), TextureId(1));
+    let turret_half = Vec3d::new(0.4, 0.2, 0.4);
+
+    let mut model = Model {
+        textures: vec!["fighter-hull".to_string(), "fighter-fittings".to_string()],
+        ..Default::default()
+    };
+    let top = Vec3d::new(0.0, half.y + turret_half.y, 0.0);
+    let gun = Vec3d::new(0.0, turret_half.y * 2.0, 0.0);
+    model.sub_objects.0 = vec![
+        subobj(0, None, "detail0", Vec3d::ZERO, cuboid(half, 4, hull)),
+        subobj(1, Some(0), "turret01a", top, cuboid(turret_half, 1, fittings)),
+        subobj(2, Some(1), "turret01b", gun, cuboid(turret_half * 0.5, 1, fittings)),
+        subobj(3, Some(0), "turret02a", -top, cuboid(turret_half, 1, fittings)),
+        subobj(4, Some(3), "turret02b", -gun, cuboid(turret_half * 0.5, 1, fittings)),
+        subobj(5, None, "detail1", Vec3d::ZERO, cuboid(half, 2, hull)),
+        subobj(6, None, "detail2", Vec3d::ZERO, cuboid(half, 1, hull)),
+        subobj(7, None, "debris01", Vec3d::new(-half.x / 2.0, 0.0, 0.0), cuboid(mul(half, Vec3d::new(0.5, 1.0, 1.0)), 1, hull)),
+        subobj(8, None, "debris02", Vec3d::new(half.x / 2.0, 0.0, 0.0), cuboid(mul(half, Vec3d::new(0.5, 1.0, 1.0)), 1, hull)),
+    ];
+    model.sub_objects.0[1].properties = "$special=subsystem\n$fov=180".to_string();
+    model.sub_objects.0[3].properties = "$special=subsystem\n$fov=180".to_string();
+    model.sub_objects.0[7].is_debris_model = true;
+    model.sub_objects.0[8].is_debris_model = true;
+    model.header.detail_levels = vec![ObjectId(0), ObjectId(5), ObjectId(6)];
+
+    let fire_points = vec![Vec3d::new(-0.1, 0.0, 0.5), Vec3d::new(0.1, 0.0, 0.5)];
+    model.turrets = vec![
+        Turret {
+            base_obj: ObjectId(1),
+            gun_obj: ObjectId(2),
+            normal: NormalVec3(Vec3d::new(0.0, 1.0, 0.0)),
+            fire_points: fire_points.clone(),
+        },
+        Turret {
+            base_obj: ObjectId(3),
+            gun_obj: ObjectId(4),
+            normal: NormalVec3(Vec3d::new(0.0, -1.0, 0.0)),
+            fire_points,
+        },
+    ];
+
+    let forward = NormalVec3(Vec3d::new(0.0, 0.0, 1.0));
+    let point = |x: f32, y: f32, z: f32| WeaponHardpoint { position: Vec3d::new(x, y, z), normal: forward, offset: 0.0 };
+    model.primary_weps = vec![
+        vec![point(-half.x * 0.8, 0.0, half.z), point(half.x * 0.8, 0.0, half.z)],
+        vec![point(-half.x * 0.4, 0.0, half.z), point(half.x * 0.4, 0.0, half.z)],
+    ];
+    model.secondary_weps = vec![vec![
+        point(-half.x / 2.0, -half.y, half.z / 2.0),
+        point(half.x / 2.0, -half.y, half.z / 2.0),
+    ]];
+
+    let glow = |x: f32| ThrusterGlow {
+        position: Vec3d::new(x, 0.0, -half.z),
+        normal: NormalVec3(-forward.0),
+        radius: half.y / 2.0,
+    };
+    model.thruster_banks = vec![ThrusterBank {
+        properties: String::new(),
+        glows: vec![glow(-half.x / 2.0), glow(half.x / 2.0)],
+    }];
+
+    model.shield_data = Some(box_shield(half * 1.25));
+    model.eye_points = vec![EyePoint {
+        attached_subobj: Some(ObjectId(0)),
+        position: Vec3d::new(0.0, half.y, half.z / 2.0),
+        normal: forward,
+    }];
+
+    let below = |depth: f32| PathPoint {
+        position: Vec3d::new(0.0, -half.y - depth, 0.0),
+        radius: 1.0,
+        turrets: vec![],
+    };
+    model.paths = vec![Path {
+        name: "$path01".to_string(),
+        parent: "detail0".to_string(),
+        points: vec![below(2.0), below(10.0)],
+    }];
+    model.docking_bays = vec![Dock {
+        position: Vec3d::new(0.0, -half.y, 0.0),
+        fvec: NormalVec3(Vec3d::new(0.0, -1.0, 0.0)),
+        uvec: forward,
+        path: Some(PathId(0)),
+        properties: "$name=dock01".to_string(),
+    }];
+
+    model.recalc_visual_center(VisualCenterMethod::default());
+    finish(&mut model);
+    model
+}
+
+// the fighter with two walls along the top of the hull either side of turret01a, so it sits in a trench, and can fire
+// up and along it but not out to the sides
+pub fn sample_trench_turret(seed: u64) -> Model {
+    let mut model = sample_fighter(seed);
+    let half = model.sub_objects[ObjectId(0)].bbox.max;
+    let wall = |obj_id: u32, name: &str, x: f32| {
+        let mesh = cuboid(Vec3d::new(0.25, 1.0, half.z), 1, TextureId(0));
+        subobj(obj_id, Some(0), name, Vec3d::new(x, half.y + 1.0, 0.0), mesh)
+    };
+    model.sub_objects.0.extend([wall(9, "trench-left", -1.5), wall(10, "trench-right", 1.5)]);
+    finish(&mut model);
+    model
+}
+
+// the fighter with a fighterbay in its belly, behind the lower turret, and a $bay path out of it, which heads down and
+// then off to the side, and then either further down if clipping is false, or back up through the hull if it's true
+pub fn sample_fighterbay(seed: u64, clipping: bool) -> Model {
+    let mut model = sample_fighter(seed);
+    let half = model.sub_objects[ObjectId(0)].bbox.max;
+    let bay = cuboid(Vec3d::new(1.0, 0.5, 1.0), 1, TextureId(0));
+    let bay = subobj(9, Some(0), "fighterbay01", Vec3d::new(0.0, -half.y, -half.z / 2.0), bay);
+    model.sub_objects.0.push(bay);
+
+    let point = |x: f32, y: f32| PathPoint {
+        position: Vec3d::new(x, y, -half.z / 2.0),
+        radius: 0.5,
+        turrets: vec![],
+    };
+    let last = point(half.x / 2.0, if clipping { half.y + 6.0 } else { -half.y - 12.0 });
+    model.paths.push(Path {
+        name: "$bay01".to_string(),
+        parent: "fighterbay01".to_string(),
+        points: vec![
+            point(0.0, -half.y - 0.25),
+            point(0.0, -half.y - 6.0),
+            point(half.x / 2.0, -half.y - 6.0),
+            last,
+        ],
+    });
+    finish(&mut model);
+    model
+}
+
+// a single detail level of num_subobjects plates of polys_per_subobject quads each, scattered in a tree under the first,
+// for benchmarks; it's valid as long as a plate's vertices fit in the version's limit, 4 per quad
+pub fn sample_capital(num_subobjects: usize, polys_per_subobject: usize, seed: u64) -> Model {
+    let mut rng = Rng::new(seed);
+    let size = rng.float(200.0, 2000.0);
+    let mut model = Model {
+        textures: vec!["capital-hull".to_string()],
+        ..Default::default()
+    };
+
+    for i in 0..num_subobjects.max(1) {
+        let (parent, name, offset) = if i == 0 {
+            (None, "detail0".to_string(), Vec3d::ZERO)
+        } else {
+            (Some(rng.below(i) as u32), format!("section{:03}", i), rng.vec3d(size / 2.0))
+        };
+        let mesh = plate(size / 4.0, polys_per_subobject, &mut rng, TextureId(0));
+        model.sub_objects.push(subobj(i as u32, parent, &name, offset, mesh));
+    }
+    model.header.detail_levels = vec![ObjectId(0)];
+
+    finish(&mut model);
+    model
+}
+
+// models which are broken in one particular way each, labelled by what's wrong with them
+//   "empty": nothing at all, Error::NoDetailLevels
+//   "parent cycle": two subobjects which are each other's parent, InvariantViolation::ParentCycle
+//      the warnings and errors are left unchecked, since rechecking them would never finish; only check_invariants is safe
+//   "oversized subobject": more vertices than version 22.01 allows, Error::TooManyVerts
+//   "duplicate names": Error::DuplicateSubobjectName and Warning::DuplicatePathName
+//   "missing normals": a subobject whose polygons refer to normals it doesn't have, an invariant violation
+pub fn sample_degenerate_cases() -> Vec<(String, Model)> {
+    let mut rng = Rng::new(0);
+    let mut cases = vec![];
+    let mut push = |name: &str, model: Model| cases.push((name.to_string(), model));
+
+    let mut empty = Model::default();
+    finish(&mut empty);
+    push("empty", empty);
+
+    let mut cycle = sample_capital(1, 4, 0);
+    cycle
+        .sub_objects
+        .push(subobj(1, Some(2), "loop01", Vec3d::ZERO, cuboid(Vec3d::new(1.0, 1.0, 1.0), 1, TextureId(0))));
+    cycle
+        .sub_objects
+        .push(subobj(2, Some(1), "loop02", Vec3d::ZERO, cuboid(Vec3d::new(1.0, 1.0, 1.0), 1, TextureId(0))));
+    cycle.header.num_subobjects = 3;
+    cycle.recalc_all_children_ids();
+    cycle.warnings.clear();
+    cycle.errors.clear();
+    push("parent cycle", cycle);
+
+    let mut oversized = Model {
+        version: Version::V22_01,
+        textures: vec!["capital-hull".to_string()],
+        ..Default::default()
+    };
+    // 4 vertices per quad, one more than the limit of 65535
+    let mesh = plate(100.0, 128 * 128, &mut rng, TextureId(0));
+    oversized.sub_objects.push(subobj(0, None, "detail0", Vec3d::ZERO, mesh));
+    oversized.header.detail_levels = vec![ObjectId(0)];
+    finish(&mut oversized);
+    push("oversized subobject", oversized);
+
+    let mut duplicates = sample_capital(3, 4, 1);
+    duplicates.sub_objects.0[2].name = duplicates.sub_objects.0[1].name.clone();
+    let point = PathPoint { position: Vec3d::ZERO, radius: 1.0, turrets: vec![] };
+    let path = Path {
+        name: "$path01".to_string(),
+        parent: "detail0".to_string(),
+        points: vec![point],
+    };
+    duplicates.paths = vec![path.clone(), path];
+    finish(&mut duplicates);
+    push("duplicate names", duplicates);
+
+    let mut missing_normals = sample_capital(2, 4, 2);
+    missing_normals.sub_objects.0[1].bsp_data.norms.clear();
+    finish(&mut missing_normals);
+    push("missing normals", missing_normals);
+
+    cases
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invariants::InvariantViolation;
+    use crate::test_util::fuzz_model;
+    use crate::{Error, Warning};
+
+    #[test]
+    fn fighters_are_clean() {
+        for seed in 0..5 {
+            let fighter = sample_fighter(seed);
+            assert_eq!(fighter.warnings, Default::default(), "seed {}", seed);
+            assert_eq!(fighter.errors, Default::default(), "seed {}", seed);
+            assert_eq!(fighter.check_invariants(), [], "seed {}", seed);
+            assert_eq!(fighter.header.detail_levels.len(), 3);
+            assert_eq!(fighter.turrets.len(), 2);
+            assert!(fighter.shield_data.is_some());
+        }
+        assert_eq!(sample_fighter(3).summary_text(), sample_fighter(3).summary_text());
+        assert_ne!(sample_fighter(3).summary_text(), sample_fighter(4).summary_text());
+    }
+
+    #[test]
+    fn fighter_survives_fuzzing() {
+        if let Err(failure) = fuzz_model(11, sample_fighter(11), 40) {
+            panic!("{}", failure);
+        }
+    }
+
+    #[test]
+    fn capital_has_the_requested_size() {
+        let capital = sample_capital(12, 30, 5);
+        assert_eq!(capital.sub_objects.len(), 12);
+        for subobj in &capital.sub_objects {
+            assert_eq!(subobj.bsp_data.collision_tree.leaves().count(), 30);
+        }
+        assert_eq!(capital.errors, Default::default());
+        assert_eq!(capital.check_invariants(), []);
+    }
+
+    #[test]
+    fn degenerate_cases_are_broken_as_labelled() {
+        let cases = sample_degenerate_cases().into_iter().collect::<std::collections::HashMap<_, _>>();
+        assert!(cases["empty"].errors.contains(&Error::NoDetailLevels));
+        assert!(cases["parent cycle"]
+            .check_invariants()
+            .iter()
+            .any(|violation| matches!(violation, InvariantViolation::ParentCycle(_))));
+        assert!(cases["oversized subobject"].errors.contains(&Error::TooManyVerts(ObjectId(0))));
+        assert!(cases["duplicate names"]
+            .errors
+            .iter()
+            .any(|error| matches!(error, Error::DuplicateSubobjectName(_))));
+        assert!(cases["duplicate names"]
+            .warnings
+            .iter()
+            .any(|warning| matches!(warning, Warning::DuplicatePathName(_))));
+        assert!(!cases["missing normals"].check_invariants().is_empty());
+    }
+}
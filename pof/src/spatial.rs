@@ -0,0 +1,655 @@
+// a uniform grid over triangles, for doing many closest point queries against the same geometry
+use std::collections::HashMap;
+
+use crate::{ray_triangle_distance, BoundingBox, Model, ObjectId, SampleMode, Vec3d};
+
+pub struct SpatialHash {
+    cell_size: f32,
+    // the range of cells which have anything in them
+    min_cell: [i32; 3],
+    max_cell: [i32; 3],
+    cells: HashMap<[i32; 3], Vec<usize>>,
+    triangles: Vec<[Vec3d; 3]>,
+}
+
+impl SpatialHash {
+    pub fn from_triangles(triangles: Vec<[Vec3d; 3]>) -> SpatialHash {
+        let bbox = BoundingBox::from_vectors(triangles.iter().flatten().copied());
+        // roughly a triangle per cell, assuming they're spread over the bbox
+        let extent = if triangles.is_empty() {
+            0.0
+        } else {
+            bbox.size_on_axis(bbox.greatest_dimension())
+        };
+        let cell_size = (extent / (triangles.len() as f32).cbrt().max(1.0)).max(0.001);
+
+        let mut out = SpatialHash {
+            cell_size,
+            min_cell: [i32::MAX; 3],
+            max_cell: [i32::MIN; 3],
+            cells: HashMap::new(),
+            triangles: vec![],
+        };
+        for (i, tri) in triangles.iter().enumerate() {
+            let tri_bbox = BoundingBox::from_vectors(tri.iter().copied());
+            let (min, max) = (out.cell_of(tri_bbox.min), out.cell_of(tri_bbox.max));
+            for x in min[0]..=max[0] {
+                for y in min[1]..=max[1] {
+                    for z in min[2]..=max[2] {
+                        out.cells.entry([x, y, z]).or_default().push(i);
+                    }
+                }
+            }
+            for axis in 0..3 {
+                out.min_cell[axis] = out.min_cell[axis].min(min[axis]);
+                out.max_cell[axis] = out.max_cell[axis].max(max[axis]);
+            }
+        }
+        out.triangles = triangles;
+        out
+    }
+
+    // the subobject's triangles, in model space
+    pub fn from_subobject(model: &Model, id: ObjectId) -> SpatialHash {
+        let mut triangles = vec![];
+        push_subobj_triangles(model, id, &mut triangles);
+        SpatialHash::from_triangles(triangles)
+    }
+
+    // the triangles of the detail0 hierarchy which collide, in model space
+    pub fn from_detail0(model: &Model) -> SpatialHash {
+        SpatialHash::from_triangles(detail0_triangles(model))
+    }
+
+    fn cell_of(&self, point: Vec3d) -> [i32; 3] {
+        [point.x, point.y, point.z].map(|val| (val / self.cell_size).floor() as i32)
+    }
+
+    pub fn triangles(&self) -> &[[Vec3d; 3]] {
+        &self.triangles
+    }
+
+    // indices of the triangles in cells overlapping the bbox, which may include some just outside it
+    pub fn triangles_in(&self, bbox: &BoundingBox) -> Vec<usize> {
+        if self.triangles.is_empty() || bbox.is_inverted() {
+            return vec![];
+        }
+
+        let (min, max) = (self.cell_of(bbox.min), self.cell_of(bbox.max));
+        let lo = [0, 1, 2].map(|axis| min[axis].max(self.min_cell[axis]));
+        let hi = [0, 1, 2].map(|axis| max[axis].min(self.max_cell[axis]));
+        let mut out = vec![];
+        for x in lo[0]..=hi[0] {
+            for y in lo[1]..=hi[1] {
+                for z in lo[2]..=hi[2] {
+                    out.extend(self.cells.get(&[x, y, z]).into_iter().flatten());
+                }
+            }
+        }
+        out.sort_unstable();
+        out.dedup();
+        out
+    }
+
+    // the closest point on any of the triangles
+    pub fn nearest(&self, point: Vec3d) -> Option<Vec3d> {
+        if self.triangles.is_empty() {
+            return None;
+        }
+
+        let center = self.cell_of(point);
+        // rings closer than this don't overlap the grid at all
+        let first_ring = (0..3)
+            .map(|axis| (self.min_cell[axis] - center[axis]).max(center[axis] - self.max_cell[axis]).max(0))
+            .max()
+            .unwrap();
+        let last_ring = (0..3)
+            .map(|axis| (center[axis] - self.min_cell[axis]).max(self.max_cell[axis] - center[axis]))
+            .max()
+            .unwrap();
+
+        let mut best: Option<(f32, Vec3d)> = None;
+        for ring in first_ring..=last_ring {
+            // everything in this ring or beyond is at least this far away
+            if let Some((dist, _)) = best {
+                if dist <= (ring - 1) as f32 * self.cell_size {
+                    break;
+                }
+            }
+
+            let lo = [0, 1, 2].map(|axis| (center[axis] - ring).max(self.min_cell[axis]));
+            let hi = [0, 1, 2].map(|axis| (center[axis] + ring).min(self.max_cell[axis]));
+            for x in lo[0]..=hi[0] {
+                for y in lo[1]..=hi[1] {
+                    for z in lo[2]..=hi[2] {
+                        let on_ring = (x - center[0]).abs() == ring || (y - center[1]).abs() == ring || (z - center[2]).abs() == ring;
+                        if !on_ring {
+                            continue;
+                        }
+                        for &i in self.cells.get(&[x, y, z]).into_iter().flatten() {
+                            let closest = closest_point_on_triangle(point, self.triangles[i]);
+                            let dist = (closest - point).magnitude();
+                            if !matches!(best, Some((best_dist, _)) if best_dist <= dist) {
+                                best = Some((dist, closest));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, closest)| closest)
+    }
+
+    // whether the point is inside the mesh, only meaningful for a closed one, see inside_by_parity
+    pub fn contains(&self, point: Vec3d) -> bool {
+        inside_by_parity(|dir| self.ray_crossings(point, dir))
+    }
+
+    // the distance to the nearest triangle, negative if the point is inside the mesh
+    pub fn signed_distance(&self, point: Vec3d) -> Option<f32> {
+        let distance = (self.nearest(point)? - point).magnitude();
+        Some(if self.contains(point) { -distance } else { distance })
+    }
+
+    // whether the ray hits any of the triangles, from either side
+    pub fn ray_hits(&self, origin: Vec3d, dir: Vec3d) -> bool {
+        self.walk_ray(origin, dir, |i| ray_triangle_distance(origin, dir, self.triangles[i]).is_some())
+    }
+
+    // how many of the triangles the ray hits, from either side
+    fn ray_crossings(&self, origin: Vec3d, dir: Vec3d) -> usize {
+        let mut candidates = vec![];
+        self.walk_ray(origin, dir, |i| {
+            candidates.push(i);
+            false
+        });
+        // a triangle is in every cell it overlaps, so the ray can pass it more than once
+        candidates.sort_unstable();
+        candidates.dedup();
+        (candidates.into_iter())
+            .filter(|&i| ray_triangle_distance(origin, dir, self.triangles[i]).is_some())
+            .count()
+    }
+
+    // calls visit with each triangle in the cells along the ray, until it returns true, and returns whether it did
+    // walks the cells in order (Amanatides and Woo), so a ray which escapes only looks at what it passes near
+    fn walk_ray(&self, origin: Vec3d, dir: Vec3d, mut visit: impl FnMut(usize) -> bool) -> bool {
+        if self.triangles.is_empty() || dir.is_null() {
+            return false;
+        }
+
+        // the part of the ray inside the grid
+        let (o, d) = ([origin.x, origin.y, origin.z], [dir.x, dir.y, dir.z]);
+        let (mut t_enter, mut t_exit) = (0.0_f32, f32::INFINITY);
+        for axis in 0..3 {
+            let lo = self.min_cell[axis] as f32 * self.cell_size;
+            let hi = (self.max_cell[axis] + 1) as f32 * self.cell_size;
+            if d[axis] == 0.0 {
+                if o[axis] < lo || o[axis] > hi {
+                    return false;
+                }
+            } else {
+                let (t1, t2) = ((lo - o[axis]) / d[axis], (hi - o[axis]) / d[axis]);
+                t_enter = t_enter.max(t1.min(t2));
+                t_exit = t_exit.min(t1.max(t2));
+            }
+        }
+        if t_enter > t_exit {
+            return false;
+        }
+
+        let start = self.cell_of(origin + dir * t_enter);
+        let mut cell = [0, 1, 2].map(|axis| start[axis].clamp(self.min_cell[axis], self.max_cell[axis]));
+        let step = d.map(|val| if val > 0.0 { 1 } else { -1 });
+        // how far along the ray the next cell boundary on each axis is, and how far apart they are
+        let mut t_next = [0, 1, 2].map(|axis| {
+            let boundary = (cell[axis] + (step[axis] > 0) as i32) as f32 * self.cell_size;
+            if d[axis] == 0.0 {
+                f32::INFINITY
+            } else {
+                (boundary - o[axis]) / d[axis]
+            }
+        });
+        let t_delta = d.map(|val| self.cell_size / val.abs());
+
+        loop {
+            for &i in self.cells.get(&cell).into_iter().flatten() {
+                if visit(i) {
+                    return true;
+                }
+            }
+            let axis = (0..3).min_by(|&a, &b| t_next[a].total_cmp(&t_next[b])).unwrap();
+            if t_next[axis] > t_exit {
+                return false;
+            }
+            cell[axis] += step[axis];
+            if cell[axis] < self.min_cell[axis] || cell[axis] > self.max_cell[axis] {
+                return false;
+            }
+            t_next[axis] += t_delta[axis];
+        }
+    }
+}
+
+// none for a subobject which doesn't exist
+pub(crate) fn push_subobj_triangles(model: &Model, id: ObjectId, triangles: &mut Vec<[Vec3d; 3]>) {
+    let Some(subobj) = model.sub_objects.get(id.0 as usize) else {
+        return;
+    };
+    let offset = model.get_total_subobj_offset(id);
+    for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+        if let [first, rest @ ..] = &*poly.verts {
+            for pair in rest.windows(2) {
+                triangles.push([first, &pair[0], &pair[1]].map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize] + offset));
+            }
+        }
+    }
+}
+
+// skipping subobjects which don't collide, like Model::raycast, since nothing can be inside or behind them
+fn detail0_triangles(model: &Model) -> Vec<[Vec3d; 3]> {
+    let mut triangles = vec![];
+    if let Some(detail0) = model.detail0_id() {
+        for subobj in &model.sub_objects {
+            if model.is_obj_id_ancestor(subobj.obj_id, detail0) && model.subobj_collides(subobj.obj_id) {
+                push_subobj_triangles(model, subobj.obj_id, &mut triangles);
+            }
+        }
+    }
+    triangles
+}
+
+// skewed away from the axes and from each other, so rays along them rarely run along a face or through an edge
+const PARITY_DIRECTIONS: [Vec3d; 3] = [
+    Vec3d::new(0.231, 0.923, 0.307),
+    Vec3d::new(-0.835, 0.167, 0.524),
+    Vec3d::new(0.412, -0.377, -0.829),
+];
+
+// a ray from a point inside a closed mesh crosses it an odd number of times, and from outside an even number
+// a ray through an edge counts both of its triangles, and one through a hole misses a crossing, so this takes the majority
+// of PARITY_DIRECTIONS, given how many triangles a ray from the point crosses in a direction
+fn inside_by_parity(crossings: impl Fn(Vec3d) -> usize) -> bool {
+    PARITY_DIRECTIONS.iter().filter(|&&dir| crossings(dir) % 2 == 1).count() * 2 > PARITY_DIRECTIONS.len()
+}
+
+fn inside_triangles(point: Vec3d, triangles: &[[Vec3d; 3]]) -> bool {
+    inside_by_parity(|dir| {
+        (triangles.iter())
+            .filter(|&&tri| ray_triangle_distance(point, dir, tri).is_some())
+            .count()
+    })
+}
+
+fn nearest_on_triangles(point: Vec3d, triangles: &[[Vec3d; 3]]) -> Option<Vec3d> {
+    (triangles.iter())
+        .map(|&tri| closest_point_on_triangle(point, tri))
+        .min_by(|v1, v2| (*v1 - point).magnitude().total_cmp(&(*v2 - point).magnitude()))
+}
+
+// below this many points, testing each against every triangle is quicker than hashing them first
+const HASH_MIN_POINTS: usize = 8;
+
+// from Real-Time Collision Detection, 5.1.5
+pub fn closest_point_on_triangle(point: Vec3d, [a, b, c]: [Vec3d; 3]) -> Vec3d {
+    let (ab, ac, ap) = (b - a, c - a, point - a);
+    let (d1, d2) = (ab.dot(&ap), ac.dot(&ap));
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = point - b;
+    let (d3, d4) = (ab.dot(&bp), ac.dot(&bp));
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = point - c;
+    let (d5, d6) = (ab.dot(&cp), ac.dot(&cp));
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denom = 1.0 / (va + vb + vc);
+    // degenerate triangles have no interior
+    if !denom.is_finite() {
+        return [a, b, c]
+            .into_iter()
+            .min_by(|v1, v2| (*v1 - point).magnitude().total_cmp(&(*v2 - point).magnitude()))
+            .unwrap();
+    }
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+// directions spread evenly over the sphere, along a fibonacci spiral
+fn sphere_directions(count: usize) -> Vec<Vec3d> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    (0..count)
+        .map(|i| {
+            let y = 1.0 - (i as f32 + 0.5) / count as f32 * 2.0;
+            let r = (1.0 - y * y).sqrt();
+            let theta = golden_angle * i as f32;
+            Vec3d::new(r * theta.cos(), y, r * theta.sin())
+        })
+        .collect()
+}
+
+// how many points external_surface_area samples over the surface, and how many directions it looks in from each
+const EXTERNAL_AREA_SAMPLES: f32 = 4000.0;
+const EXTERNAL_AREA_DIRECTIONS: usize = 64;
+
+impl Model {
+    // the surface area of the detail0 hierarchy which can be seen from outside the model, unlike surface_area_average_pos,
+    // which also counts faces enclosed by others, like internal bulkheads or geometry buried in the hull
+    // it's an estimate: points are sampled uniformly over the surface, and a point counts as visible if a ray from it, in any of
+    // about EXTERNAL_AREA_DIRECTIONS / 2 directions spread over the hemisphere its normal faces, gets out without hitting anything
+    // with EXTERNAL_AREA_SAMPLES samples the standard error is under 1% of the total area, and the sampling is seeded, so the
+    // same model always gives the same answer; faces which can only be seen through gaps narrower than the spacing of the
+    // directions are counted as hidden, so the area of deep, narrow recesses is underestimated
+    pub fn external_surface_area(&self) -> f32 {
+        let (area, _) = self.surface_area_average_pos();
+        if area <= 0.0 {
+            return 0.0;
+        }
+
+        let samples = self.surface_samples_with(EXTERNAL_AREA_SAMPLES / area, 0, SampleMode::Uniform);
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let hash = SpatialHash::from_detail0(self);
+        let directions = sphere_directions(EXTERNAL_AREA_DIRECTIONS);
+        // rays start just off the surface, so they don't hit the polygon they start on
+        let bbox = BoundingBox::from_vectors(hash.triangles.iter().flatten().copied());
+        let epsilon = bbox.size_on_axis(bbox.greatest_dimension()) * 1e-5;
+
+        let visible = (samples.iter())
+            .filter(|sample| {
+                let origin = sample.position + sample.normal * epsilon;
+                (directions.iter())
+                    .filter(|dir| dir.dot(&sample.normal) > 0.05)
+                    .any(|&dir| !hash.ray_hits(origin, dir))
+            })
+            .count();
+        area * visible as f32 / samples.len() as f32
+    }
+
+    // the closest point on the detail0 hierarchy's surface
+    // for many queries, build a SpatialHash once with SpatialHash::from_detail0 and pass it in
+    pub fn nearest_surface_point(&self, point: Vec3d, accelerator: Option<&SpatialHash>) -> Option<Vec3d> {
+        if let Some(accelerator) = accelerator {
+            return accelerator.nearest(point);
+        }
+
+        nearest_on_triangles(point, &detail0_triangles(self))
+    }
+
+    // the distance to the detail0 hierarchy's surface, negative inside it
+    // only meaningful for a closed hull, points near holes can go either way
+    pub fn signed_distance_to_hull(&self, point: Vec3d, accelerator: Option<&SpatialHash>) -> Option<f32> {
+        if let Some(accelerator) = accelerator {
+            return accelerator.signed_distance(point);
+        }
+
+        let triangles = detail0_triangles(self);
+        let distance = (nearest_on_triangles(point, &triangles)? - point).magnitude();
+        Some(if inside_triangles(point, &triangles) { -distance } else { distance })
+    }
+
+    // for each point, whether it's more than margin inside the detail0 hierarchy, like signed_distance_to_hull(point) < -margin
+    // for all of them at once; only points that far inside its bbox can be, and only they are tested against the triangles
+    pub fn points_inside_hull(&self, points: &[Vec3d], margin: f32) -> Vec<bool> {
+        let triangles = detail0_triangles(self);
+        let bbox = BoundingBox::from_vectors(triangles.iter().flatten().copied()).pad(-margin);
+        let candidates = points.iter().filter(|&&point| bbox.contains(point)).count();
+        if candidates == 0 {
+            return vec![false; points.len()];
+        }
+
+        let further_than_margin = |point: Vec3d, nearest: Option<Vec3d>| nearest.map_or(false, |nearest| (nearest - point).magnitude() > margin);
+        if candidates < HASH_MIN_POINTS {
+            (points.iter())
+                .map(|&point| {
+                    bbox.contains(point) && inside_triangles(point, &triangles) && further_than_margin(point, nearest_on_triangles(point, &triangles))
+                })
+                .collect()
+        } else {
+            self.points_inside_hull_with(points, margin, &SpatialHash::from_triangles(triangles))
+        }
+    }
+
+    // points_inside_hull against a hash of the detail0 hierarchy which was already built, see SpatialHash::from_detail0
+    pub fn points_inside_hull_with(&self, points: &[Vec3d], margin: f32, hull: &SpatialHash) -> Vec<bool> {
+        let bbox = BoundingBox::from_vectors(hull.triangles().iter().flatten().copied()).pad(-margin);
+        let further_than_margin = |point: Vec3d, nearest: Option<Vec3d>| nearest.map_or(false, |nearest| (nearest - point).magnitude() > margin);
+        (points.iter())
+            .map(|&point| bbox.contains(point) && hull.contains(point) && further_than_margin(point, hull.nearest(point)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        BspData, NormalId, ObjVec, PolyVertex, Polygon, Set, SubObject, TextureId, VertexId, Warning, WeaponHardpoint, WEAPON_INSIDE_HULL_MARGIN,
+    };
+
+    // a model whose only subobject, detail0, is a closed box from -10 to 10 on each axis
+    fn box_model() -> Model {
+        let coord = |bit: bool| if bit { 10.0 } else { -10.0 };
+        let verts = (0..8)
+            .map(|i| Vec3d::new(coord(i & 1 != 0), coord(i & 2 != 0), coord(i & 4 != 0)))
+            .collect::<Vec<_>>();
+        let quads = [[0, 2, 6, 4], [1, 5, 7, 3], [0, 4, 5, 1], [2, 3, 7, 6], [0, 1, 3, 2], [4, 6, 7, 5]];
+        let polygons = quads.map(|quad| Polygon {
+            normal: Default::default(),
+            texture: TextureId(0),
+            verts: quad
+                .map(|i| PolyVertex {
+                    vertex_id: VertexId(i),
+                    normal_id: NormalId(0),
+                    uv: (0.0, 0.0),
+                })
+                .to_vec(),
+        });
+        let hull = SubObject {
+            obj_id: ObjectId(0),
+            name: "detail0".to_string(),
+            bsp_data: BspData {
+                collision_tree: BspData::recalculate(&verts, polygons.into_iter()),
+                norms: vec![Vec3d::new(0.0, 0.0, 1.0)],
+                verts,
+            },
+            ..Default::default()
+        };
+        let mut model = Model {
+            sub_objects: ObjVec(vec![hull]),
+            textures: vec!["hull".to_string()],
+            ..Default::default()
+        };
+        model.header.num_subobjects = 1;
+        model.header.detail_levels = vec![ObjectId(0)];
+        model
+    }
+
+    #[test]
+    fn points_inside_hull() {
+        let model = box_model();
+        let points = [
+            (Vec3d::new(0.0, 0.0, 0.0), true),
+            (Vec3d::new(5.0, 5.0, -5.0), true),
+            (Vec3d::new(0.0, -9.5, 3.0), true),
+            // inside, but by less than the margin
+            (Vec3d::new(9.95, 0.0, 0.0), false),
+            (Vec3d::new(0.0, 0.0, 10.0), false),
+            (Vec3d::new(20.0, 0.0, 0.0), false),
+            (Vec3d::new(0.0, 12.0, 0.0), false),
+        ];
+        let (positions, expected): (Vec<_>, Vec<_>) = points.into_iter().unzip();
+        assert_eq!(model.points_inside_hull(&positions, 0.1), expected);
+        // enough points to be worth hashing the hull, which should give the same answers
+        assert!(positions.len() * 2 >= HASH_MIN_POINTS);
+        let twice = [positions.clone(), positions].concat();
+        assert_eq!(model.points_inside_hull(&twice, 0.1), [expected.clone(), expected].concat());
+
+        let hash = SpatialHash::from_detail0(&model);
+        assert!(hash.contains(Vec3d::new(1.0, 2.0, 3.0)));
+        assert!(!hash.contains(Vec3d::new(1.0, 2.0, 13.0)));
+        assert_eq!(model.signed_distance_to_hull(Vec3d::new(0.0, 0.0, 4.0), None), Some(-6.0));
+        assert_eq!(model.signed_distance_to_hull(Vec3d::new(0.0, 0.0, 14.0), Some(&hash)), Some(4.0));
+    }
+
+    #[test]
+    fn non_colliding_subobjects_are_not_hull() {
+        // a copy of the box beside it, as a child which doesn't collide
+        let mut model = box_model();
+        let mut child = model.sub_objects[ObjectId(0)].clone();
+        child.obj_id = ObjectId(1);
+        child.parent = Some(ObjectId(0));
+        child.name = "antenna".to_string();
+        child.offset = Vec3d::new(30.0, 0.0, 0.0);
+        child.properties = "$nocollide_this_only".to_string();
+        model.sub_objects.push(child);
+        model.header.num_subobjects = 2;
+        model.recalc_all_children_ids();
+
+        let center = Vec3d::new(30.0, 0.0, 0.0);
+        let hash = SpatialHash::from_detail0(&model);
+        assert_eq!(hash.triangles().len(), SpatialHash::from_detail0(&box_model()).triangles().len());
+        assert!(!hash.contains(center));
+        assert_eq!(model.points_inside_hull(&[center], 0.1), vec![false]);
+        assert_eq!(model.signed_distance_to_hull(center, None), Some(20.0));
+        assert_eq!(model.nearest_surface_point(Vec3d::new(25.0, 0.0, 0.0), None), Some(Vec3d::new(10.0, 0.0, 0.0)));
+
+        // once it collides it counts
+        model.sub_objects[ObjectId(1)].properties.clear();
+        assert_eq!(model.points_inside_hull(&[center], 0.1), vec![true]);
+        assert_eq!(model.nearest_surface_point(Vec3d::new(25.0, 0.0, 0.0), None), Some(Vec3d::new(20.0, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn weapon_point_at_the_center_is_inside_the_hull() {
+        let mut model = box_model();
+        let point = |position| WeaponHardpoint { position, ..Default::default() };
+        model.primary_weps = vec![vec![
+            point(Vec3d::ZERO),
+            point(Vec3d::new(0.0, 0.0, 10.0 - WEAPON_INSIDE_HULL_MARGIN / 2.0)),
+        ]];
+        model.recheck_warnings(Set::All);
+        assert!(model.warnings.contains(&Warning::WeaponInsideHull { primary: true, bank: 0, point: 0 }));
+        assert!(!model.warnings.contains(&Warning::WeaponInsideHull { primary: true, bank: 0, point: 1 }));
+
+        model.primary_weps[0][0].position = Vec3d::new(0.0, 0.0, 20.0);
+        model.recheck_warnings(Set::One(Warning::WeaponInsideHull { primary: true, bank: 0, point: 0 }));
+        assert!(!model.warnings.contains(&Warning::WeaponInsideHull { primary: true, bank: 0, point: 0 }));
+    }
+
+    #[test]
+    fn nearest_matches_brute_force() {
+        let model = crate::samples::sample_capital(12, 6, 3);
+        let hash = SpatialHash::from_detail0(&model);
+        let triangles = detail0_triangles(&model);
+        assert_eq!(hash.triangles().len(), triangles.len());
+
+        let mut rng = crate::test_util::Rng::new(0);
+        let extent = model.header.max_radius * 1.5;
+        for _ in 0..500 {
+            let point = rng.vec3d(extent);
+            let (fast, slow) = (hash.nearest(point).unwrap(), nearest_on_triangles(point, &triangles).unwrap());
+            // ties can land on different points, but never at different distances
+            assert!(((fast - point).magnitude() - (slow - point).magnitude()).abs() < 1e-3, "{:?}", point);
+            assert_eq!(model.nearest_surface_point(point, Some(&hash)).map(|p| (p - point).magnitude()), Some((fast - point).magnitude()));
+        }
+        assert_eq!(SpatialHash::from_triangles(vec![]).nearest(Vec3d::ZERO), None);
+    }
+
+    // box_model facing outwards with flat vertex normals, optionally with a square internal baffle across its middle, facing +z,
+    // and optionally without its +z face
+    fn baffled_box(baffle: bool, open_top: bool) -> Model {
+        let mut model = box_model();
+        let bsp_data = &mut model.sub_objects[ObjectId(0)].bsp_data;
+        let tree = std::mem::take(&mut bsp_data.collision_tree);
+        let mut polygons = tree.into_leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+        if open_top {
+            polygons.retain(|poly| poly.verts.iter().any(|vert| bsp_data.verts[vert.vertex_id.0 as usize].z < 10.0));
+        }
+        // box_model's faces are wound inwards, which doesn't matter to anything but which way the samples face
+        for poly in &mut polygons {
+            poly.verts.reverse();
+        }
+        if baffle {
+            let first = bsp_data.verts.len() as u32;
+            for (x, y) in [(-5.0, -5.0), (5.0, -5.0), (5.0, 5.0), (-5.0, 5.0)] {
+                bsp_data.verts.push(Vec3d::new(x, y, 0.0));
+            }
+            let verts = (first..first + 4)
+                .map(|i| PolyVertex {
+                    vertex_id: VertexId(i),
+                    normal_id: NormalId(0),
+                    uv: (0.0, 0.0),
+                })
+                .collect();
+            polygons.push(Polygon { normal: Default::default(), texture: TextureId(0), verts });
+        }
+        // the samples face the way the vertex normals do
+        bsp_data.collision_tree = BspData::recalculate(&bsp_data.verts, polygons.into_iter());
+        bsp_data.norms.clear();
+        for (_, poly) in bsp_data.collision_tree.leaves_mut() {
+            for vert in &mut poly.verts {
+                vert.normal_id = NormalId(bsp_data.norms.len() as u32);
+            }
+            bsp_data.norms.push(poly.normal);
+        }
+        model
+    }
+
+    #[test]
+    fn internal_baffles_are_not_external() {
+        let close = |area: f32, expected: f32| (area - expected).abs() < expected * 0.02;
+
+        let model = baffled_box(false, false);
+        assert!(close(model.surface_area_average_pos().0, 2400.0));
+        let area = model.external_surface_area();
+        assert!(close(area, 2400.0), "{}", area);
+        // seeded, so it's always the same
+        assert_eq!(model.external_surface_area(), area);
+
+        // the baffle adds to the total, but can't be seen
+        let model = baffled_box(true, false);
+        let bsp_data = &model.sub_objects[ObjectId(0)].bsp_data;
+        let mut polys = bsp_data.collision_tree.leaves().map(|(_, poly)| poly);
+        let baffle = polys.find(|poly| poly.verts.iter().all(|vert| bsp_data.verts[vert.vertex_id.0 as usize].z == 0.0));
+        assert!(baffle.unwrap().normal.z > 0.99);
+        assert!(close(model.surface_area_average_pos().0, 2500.0));
+        let area = model.external_surface_area();
+        assert!(close(area, 2400.0), "{}", area);
+
+        // until the face in front of it is taken away
+        let model = baffled_box(true, true);
+        assert!(close(model.surface_area_average_pos().0, 2100.0));
+        let area = model.external_surface_area();
+        assert!(close(area, 2100.0), "{}", area);
+
+        let hash = SpatialHash::from_detail0(&model);
+        assert!(hash.ray_hits(Vec3d::new(0.0, 0.0, 20.0), Vec3d::new(0.0, 0.0, -1.0)));
+        assert!(!hash.ray_hits(Vec3d::new(0.0, 0.0, 5.0), Vec3d::new(0.0, 0.0, 1.0)));
+        assert!(!hash.ray_hits(Vec3d::new(0.0, 0.0, 20.0), Vec3d::new(0.0, 0.0, 1.0)));
+        assert!(hash.ray_hits(Vec3d::new(0.0, 0.0, 5.0), Vec3d::new(1.0, 0.0, 0.0)));
+    }
+}
@@ -0,0 +1,257 @@
+// a plain text summary of a model, meant to be committed next to the pof so version control has something readable to diff
+//
+// every line stands alone, and lines are in a fixed order: subobjects by name, everything else by index,
+// since indices are what the engine and tables refer to anyway
+// numbers are rounded to a fixed number of decimals so float noise doesn't show up as changes
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::{Model, NormalVec3, ObjectId, TextureId, Vec3d};
+
+pub const SUMMARY_EXTENSION: &str = "pofsum";
+
+// 3 decimals is a millimeter, which is well below anything visible
+fn num(val: f32) -> String {
+    let out = format!("{:.3}", val);
+    // -0.0, or anything small enough to round to it, shouldn't differ from 0.0
+    if out.trim_start_matches('-').trim_start_matches(['0', '.']).is_empty() {
+        "0.000".to_string()
+    } else {
+        out
+    }
+}
+
+fn vec(v: Vec3d) -> String {
+    format!("({}, {}, {})", num(v.x), num(v.y), num(v.z))
+}
+
+fn normal(n: NormalVec3) -> String {
+    vec(n.0)
+}
+
+impl Model {
+    fn summary_name(&self, id: ObjectId) -> String {
+        match self.sub_objects.get(id.0 as usize) {
+            Some(subobj) => format!("{:?}", subobj.name),
+            None => format!("<missing {}>", id.0),
+        }
+    }
+
+    pub fn summary_text(&self) -> String {
+        let mut out = String::new();
+        // writing to a String can't fail
+        macro_rules! line {
+            ($($arg:tt)*) => { writeln!(out, $($arg)*).unwrap() };
+        }
+
+        line!("version {}", self.version);
+        let header = &self.header;
+        line!("header max_radius {}", num(header.max_radius));
+        line!("header flags {:#x} {:?}", header.obj_flags, header.flags());
+        line!("header bbox {} {}", vec(header.bbox.min), vec(header.bbox.max));
+        line!("header mass {}", num(header.mass));
+        line!("header center_of_mass {}", vec(header.center_of_mass));
+        let detail_levels = header.detail_levels.iter().map(|&id| self.summary_name(id)).collect::<Vec<_>>();
+        line!("header detail_levels [{}]", detail_levels.join(", "));
+        line!("header visual_center {}", vec(self.visual_center));
+        let targeting = self.targeting_reference();
+        line!(
+            "targeting center {} autocentered {} bbox_center {} radius {} centered_radius {} autocenter_shift {}",
+            vec(targeting.center),
+            targeting.autocentered,
+            vec(targeting.bbox_center),
+            num(targeting.radius),
+            num(targeting.centered_radius),
+            vec(self.simulate_autocenter_shift()),
+        );
+        for (i, &(depth, radius)) in header.cross_sections.iter().enumerate() {
+            line!("cross_section {} depth {} radius {}", i, num(depth), num(radius));
+        }
+
+        let mut sub_objects = self.sub_objects.iter().collect::<Vec<_>>();
+        sub_objects.sort_by(|a, b| a.name.cmp(&b.name).then(a.obj_id.0.cmp(&b.obj_id.0)));
+        for subobj in sub_objects {
+            line!(
+                "subobject {:?} parent {} offset {} verts {} polys {} fingerprint {:016x}",
+                subobj.name,
+                subobj.parent().map_or("-".to_string(), |id| self.summary_name(id)),
+                vec(subobj.offset),
+                subobj.bsp_data.verts.len(),
+                subobj.bsp_data.collision_tree.leaves().count(),
+                subobj.geometry_fingerprint(),
+            );
+            if !subobj.properties.is_empty() {
+                line!("subobject {:?} properties {:?}", subobj.name, subobj.properties);
+            }
+        }
+
+        for (i, texture) in self.textures.iter().enumerate() {
+            line!("texture {} {:?}", i, texture);
+            if let Some(annotation) = self.texture_annotations.get(&TextureId(i as u32)) {
+                line!(
+                    "texture {} team_color_mask {} env_map {} glow_strength {} properties {:?}",
+                    i,
+                    annotation.team_color_mask,
+                    annotation.env_map,
+                    annotation.glow_strength.map_or("-".to_string(), num),
+                    annotation.properties
+                );
+            }
+        }
+
+        for (kind, banks) in [("primary", &self.primary_weps), ("secondary", &self.secondary_weps)] {
+            for (i, bank) in banks.iter().enumerate() {
+                for (j, point) in bank.iter().enumerate() {
+                    line!("{} {}.{} position {} normal {} offset {}", kind, i, j, vec(point.position), normal(point.normal), num(point.offset));
+                }
+            }
+        }
+
+        for (i, turret) in self.turrets.iter().enumerate() {
+            line!(
+                "turret {} base {} gun {} normal {} fire_points {}",
+                i,
+                self.summary_name(turret.base_obj),
+                self.summary_name(turret.gun_obj),
+                normal(turret.normal),
+                turret.fire_points.len()
+            );
+        }
+
+        for (i, bank) in self.thruster_banks.iter().enumerate() {
+            line!("thruster_bank {} properties {:?}", i, bank.properties);
+            for (j, glow) in bank.glows.iter().enumerate() {
+                line!("thruster {}.{} position {} normal {} radius {}", i, j, vec(glow.position), normal(glow.normal), num(glow.radius));
+            }
+        }
+
+        for (i, bank) in self.glow_banks.iter().enumerate() {
+            line!(
+                "glow_bank {} parent {} lod {} type {} timing {} {} {} properties {:?}",
+                i,
+                self.summary_name(bank.obj_parent),
+                bank.lod,
+                bank.glow_type,
+                bank.disp_time,
+                bank.on_time,
+                bank.off_time,
+                bank.properties
+            );
+            for (j, point) in bank.glow_points.iter().enumerate() {
+                line!("glow {}.{} position {} normal {} radius {}", i, j, vec(point.position), vec(point.normal), num(point.radius));
+            }
+        }
+
+        for (i, eye) in self.eye_points.iter().enumerate() {
+            let attached = eye.attached_subobj.map_or("-".to_string(), |id| self.summary_name(id));
+            line!("eye {} attached {} position {} normal {}", i, attached, vec(eye.position), normal(eye.normal));
+        }
+
+        for (i, attachment) in self.attachments.iter().enumerate() {
+            let parent = attachment.parent.map_or("-".to_string(), |id| self.summary_name(id));
+            line!(
+                "attachment {} {:?} parent {} position {} fvec {} uvec {}",
+                i,
+                attachment.name,
+                parent,
+                vec(attachment.position),
+                normal(attachment.fvec),
+                normal(attachment.uvec)
+            );
+        }
+
+        for (i, point) in self.special_points.iter().enumerate() {
+            line!("special {} {:?} position {} radius {} properties {:?}", i, point.name, vec(point.position), num(point.radius), point.properties);
+        }
+
+        for (i, path) in self.paths.iter().enumerate() {
+            line!("path {} {:?} parent {:?} points {}", i, path.name, path.parent, path.points.len());
+        }
+
+        for (i, dock) in self.docking_bays.iter().enumerate() {
+            let path = dock
+                .path
+                .map_or("-".to_string(), |id| format!("{:?}", self.paths.get(id.0 as usize).map_or("", |path| &path.name)));
+            line!(
+                "dock {} position {} fvec {} uvec {} path {} properties {:?}",
+                i,
+                vec(dock.position),
+                normal(dock.fvec),
+                normal(dock.uvec),
+                path,
+                dock.properties
+            );
+        }
+
+        for (i, insignia) in self.insignias.iter().enumerate() {
+            line!("insignia {} lod {} offset {} faces {}", i, insignia.detail_level, vec(insignia.offset), insignia.faces.len());
+        }
+
+        if let Some(shield) = &self.shield_data {
+            line!("shield verts {} polys {}", shield.verts.len(), shield.polygons.len());
+        }
+
+        out
+    }
+}
+
+// writes summary_text to `<name>.pofsum` next to the pof, returning the path written
+pub fn write_summary_sidecar(model: &Model, pof_path: &Path) -> io::Result<PathBuf> {
+    let sidecar = pof_path.with_extension(SUMMARY_EXTENSION);
+    fs::write(&sidecar, model.summary_text())?;
+    Ok(sidecar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+
+    #[test]
+    fn rounding() {
+        assert_eq!(num(1.0), "1.000");
+        assert_eq!(num(-0.0), "0.000");
+        assert_eq!(num(-0.0004), "0.000");
+        assert_eq!(num(-0.0006), "-0.001");
+        assert_eq!(num(2.0 / 3.0), "0.667");
+    }
+
+    #[test]
+    fn moving_a_weapon_point_changes_only_its_line() {
+        let mut fighter = sample_fighter(0);
+        let before = fighter.summary_text();
+        assert_eq!(before, fighter.clone().summary_text());
+
+        // float noise below the rounding doesn't show up
+        fighter.primary_weps[0][1].position.x += 0.0001;
+        assert_eq!(fighter.summary_text(), before);
+
+        fighter.primary_weps[0][1].position.y += 0.5;
+        let after = fighter.summary_text();
+        let changed = before.lines().zip(after.lines()).filter(|(a, b)| a != b).collect::<Vec<_>>();
+        assert_eq!(before.lines().count(), after.lines().count());
+        assert_eq!(changed.len(), 1, "{:?}", changed);
+        assert!(changed[0].1.starts_with("primary 0.1 position "), "{}", changed[0].1);
+    }
+
+    #[test]
+    fn header_flags_by_name() {
+        let mut fighter = sample_fighter(0);
+        fighter.header.set_flags(crate::HeaderFlags(0x100) | crate::HeaderFlags::ALLOW_TILING);
+        let summary = fighter.summary_text();
+        assert!(summary.lines().any(|line| line == "header flags 0x101 [allow tiling, 0x100]"));
+    }
+
+    #[test]
+    fn sidecar_next_to_the_pof() {
+        let fighter = sample_fighter(0);
+        let dir = std::env::temp_dir().join("pof-summary-sidecar");
+        fs::create_dir_all(&dir).unwrap();
+        let sidecar = write_summary_sidecar(&fighter, &dir.join("fighter.pof")).unwrap();
+        assert_eq!(sidecar, dir.join("fighter.pofsum"));
+        assert_eq!(fs::read_to_string(&sidecar).unwrap(), fighter.summary_text());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,376 @@
+// randomized editing of small generated models, checking Model::check_invariants after every step
+// built for this crate's own tests, and with the `test-util` feature for frontends to run against their own operations
+//
+// everything is driven by a seed, so a failure can be reproduced by running fuzz() again with the seed it reports
+use std::fmt::{self, Display};
+use std::io::Cursor;
+use std::path::PathBuf;
+
+use nalgebra_glm as glm;
+
+use crate::{
+    Axis, BspData, Dock, EyePoint, Model, NormalId, ObjectId, Path, PathId, PathPoint, PolyVertex, Polygon, Set, SubObject, TextureId, Turret, Vec3d,
+    VertexId, VisualCenterMethod, WeaponHardpoint, WriteOptions,
+};
+
+// xorshift64*, good enough for picking operations, and the same everywhere
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // splitmix64, so nearby seeds don't start out nearly the same, and the state isn't zero
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        Rng((z ^ (z >> 31)) | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // 0..n, or 0 if n is 0
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+
+    pub fn chance(&mut self, percent: u32) -> bool {
+        self.below(100) < percent as usize
+    }
+
+    pub fn float(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    pub fn vec3d(&mut self, extent: f32) -> Vec3d {
+        Vec3d::new(self.float(-extent, extent), self.float(-extent, extent), self.float(-extent, extent))
+    }
+}
+
+// names which exercise the semantic name links
+const NAMES: &[&str] = &[
+    "hull",
+    "turret01a",
+    "turret01b",
+    "turret01a-destroyed",
+    "debris-hull",
+    "engine01",
+    "radar",
+    "detail1",
+];
+
+fn random_subobj(rng: &mut Rng, obj_id: ObjectId, parent: Option<ObjectId>, name: String, num_textures: usize) -> SubObject {
+    let verts = (0..3 + rng.below(6)).map(|_| rng.vec3d(10.0)).collect::<Vec<_>>();
+    let norms = verts.iter().map(|_| Vec3d::new(0.0, 0.0, 1.0)).collect::<Vec<_>>();
+    let polygons = (0..1 + rng.below(4))
+        .map(|_| {
+            let mut ids = vec![];
+            while ids.len() < 3 {
+                let id = rng.below(verts.len());
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+            let verts = ids
+                .into_iter()
+                .map(|id| PolyVertex {
+                    vertex_id: VertexId(id as u32),
+                    normal_id: NormalId(id as u32),
+                    uv: (rng.float(0.0, 1.0), rng.float(0.0, 1.0)),
+                })
+                .collect();
+            Polygon {
+                normal: Default::default(),
+                texture: TextureId(rng.below(num_textures) as u32),
+                verts,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let offset = if parent.is_some() { rng.vec3d(20.0) } else { Vec3d::ZERO };
+    let mut subobj = SubObject {
+        obj_id,
+        parent,
+        offset,
+        geo_center: offset,
+        name,
+        bsp_data: BspData {
+            collision_tree: BspData::recalculate(&verts, polygons.into_iter()),
+            norms,
+            verts,
+        },
+        ..Default::default()
+    };
+    subobj.recalc_bbox();
+    subobj.recalc_radius();
+    subobj
+}
+
+// a small model with a handful of subobjects and points, all referring to each other validly
+pub fn random_model(rng: &mut Rng) -> Model {
+    let mut model = Model {
+        textures: vec!["hull_tex".to_string(), "detail_tex".to_string()],
+        ..Default::default()
+    };
+
+    let num_subobjs = 1 + rng.below(6);
+    for i in 0..num_subobjs {
+        let obj_id = ObjectId(i as u32);
+        // the first subobject is detail0, the rest hang off an earlier one, or sometimes nothing
+        let parent = (i > 0 && rng.chance(80)).then(|| ObjectId(rng.below(i) as u32));
+        let name = match NAMES.get(i) {
+            Some(name) if i > 0 => name.to_string(),
+            _ => format!("subobj{}", i),
+        };
+        let subobj = random_subobj(rng, obj_id, parent, name, model.textures.len());
+        model.sub_objects.push(subobj);
+    }
+    model.header.detail_levels = vec![ObjectId(0)];
+    model.header.num_subobjects = num_subobjs as u32;
+    model.recalc_all_children_ids();
+    model.recalc_semantic_name_links();
+
+    if let Some(gun) = model.sub_objects.iter().find(|subobj| subobj.parent().is_some()) {
+        let base_obj = gun.parent().unwrap();
+        let gun_obj = gun.obj_id;
+        model.turrets.push(Turret {
+            base_obj,
+            gun_obj,
+            fire_points: vec![rng.vec3d(1.0)],
+            ..Default::default()
+        });
+    }
+
+    for _ in 0..rng.below(3) {
+        let bank = (0..1 + rng.below(4))
+            .map(|_| WeaponHardpoint {
+                position: rng.vec3d(10.0),
+                normal: Default::default(),
+                offset: 0.0,
+            })
+            .collect();
+        model.primary_weps.push(bank);
+    }
+    for i in 0..rng.below(3) {
+        let points = (0..2)
+            .map(|_| PathPoint { position: rng.vec3d(30.0), radius: 1.0, turrets: vec![] })
+            .collect();
+        model.paths.push(Path {
+            name: format!("$path{:02}", i),
+            parent: model.sub_objects[ObjectId(0)].name.clone(),
+            points,
+        });
+    }
+    for _ in 0..rng.below(3) {
+        let path = (!model.paths.is_empty()).then(|| PathId(rng.below(model.paths.len()) as u32));
+        model.docking_bays.push(Dock { position: rng.vec3d(10.0), path, ..Default::default() });
+    }
+    if rng.chance(50) {
+        model.eye_points.push(EyePoint {
+            attached_subobj: Some(ObjectId(0)),
+            position: rng.vec3d(10.0),
+            normal: Default::default(),
+        });
+    }
+
+    model.recalc_bbox();
+    model.recalc_radius();
+    model.recheck_warnings(Set::All);
+    model.recheck_errors(Set::All);
+    model
+}
+
+#[derive(Debug, Clone)]
+pub enum Mutation {
+    Rename(ObjectId, String),
+    SetDebris(ObjectId, bool),
+    // orphans the subobject, then parents it to the new parent if any, same as frontends do
+    Reparent(ObjectId, Option<ObjectId>),
+    FlattenBranch(ObjectId),
+    MoveOffset(ObjectId, Vec3d),
+    Transform { scale: Vec3d, translation: Vec3d },
+    ZeroDetail0Offset,
+    RecalcVisualCenter,
+    // axis is 0, 1 or 2 for x, y or z
+    MirrorWeaponBank { bank: usize, axis: usize },
+    // its polygons become untextured, which the untextured slot has to keep up with
+    RemoveTexture(TextureId),
+}
+
+pub fn random_mutation(model: &Model, rng: &mut Rng) -> Mutation {
+    let num_subobjs = model.sub_objects.len();
+    let subobj = |rng: &mut Rng| ObjectId(rng.below(num_subobjs) as u32);
+    match rng.below(10) {
+        0 => {
+            let id = subobj(rng);
+            Mutation::Rename(id, NAMES[rng.below(NAMES.len())].to_string())
+        }
+        1 => {
+            let id = subobj(rng);
+            Mutation::SetDebris(id, rng.chance(50))
+        }
+        2 => {
+            let (id, parent) = (subobj(rng), subobj(rng));
+            Mutation::Reparent(id, rng.chance(75).then_some(parent))
+        }
+        3 => Mutation::FlattenBranch(subobj(rng)),
+        4 => {
+            let id = subobj(rng);
+            Mutation::MoveOffset(id, rng.vec3d(20.0))
+        }
+        5 => {
+            // mostly ordinary scales, but sometimes a mirroring or a collapsing one
+            let mut scale = || match rng.below(10) {
+                0 => 0.0,
+                1 => -1.0,
+                _ => rng.float(0.5, 2.0),
+            };
+            let scale = Vec3d::new(scale(), scale(), scale());
+            Mutation::Transform { scale, translation: rng.vec3d(5.0) }
+        }
+        6 => Mutation::ZeroDetail0Offset,
+        7 => Mutation::RecalcVisualCenter,
+        8 => Mutation::RemoveTexture(TextureId(rng.below(model.textures.len()) as u32)),
+        _ => Mutation::MirrorWeaponBank {
+            bank: rng.below(model.primary_weps.len()),
+            axis: rng.below(3),
+        },
+    }
+}
+
+// some mutations don't apply to every model, those do nothing
+pub fn apply_mutation(model: &mut Model, mutation: &Mutation) {
+    let valid = |id: &ObjectId| (id.0 as usize) < model.sub_objects.len();
+    match mutation {
+        Mutation::Rename(id, name) if valid(id) => {
+            model.rename_subobject(*id, name.clone());
+        }
+        Mutation::SetDebris(id, debris) if valid(id) => model.set_debris(*id, *debris),
+        Mutation::Reparent(id, parent) if valid(id) && parent.iter().all(valid) => {
+            model.make_orphan(*id);
+            if let Some(parent) = parent {
+                model.make_parent(*parent, *id);
+            }
+        }
+        Mutation::FlattenBranch(id) if valid(id) => {
+            let _ = model.flatten_branch(*id, &[]);
+        }
+        Mutation::MoveOffset(id, offset) if valid(id) => model.subobj_move_only_offset(*id, *offset),
+        Mutation::Transform { scale, translation } => {
+            let matrix = glm::translation(&(*translation).into()) * glm::scaling(&(*scale).into());
+            // a shield too deep to transform leaves the model as it was, which is just as consistent
+            let _ = model.apply_transform(&matrix);
+        }
+        Mutation::ZeroDetail0Offset => model.zero_detail0_offset(),
+        Mutation::RecalcVisualCenter => model.recalc_visual_center(VisualCenterMethod::default()),
+        Mutation::MirrorWeaponBank { bank, axis } if *bank < model.primary_weps.len() => {
+            let axis = [Axis::X, Axis::Y, Axis::Z][*axis % 3];
+            model.mirror_weapon_bank(true, *bank, axis);
+        }
+        Mutation::RemoveTexture(id) if (id.0 as usize) < model.textures.len() => model.remove_texture(*id),
+        _ => {}
+    }
+}
+
+#[derive(Debug)]
+pub struct FuzzFailure {
+    pub seed: u64,
+    // the mutations applied so far, the last one is the one which broke something
+    pub mutations: Vec<Mutation>,
+    pub problems: Vec<String>,
+}
+impl Display for FuzzFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "fuzzing failed with seed {} after {} mutations:", self.seed, self.mutations.len())?;
+        for mutation in &self.mutations {
+            writeln!(f, "  {:?}", mutation)?;
+        }
+        for problem in &self.problems {
+            writeln!(f, "  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+// writes the model out and reads it back, returning what didn't survive
+pub fn round_trip_problems(model: &Model) -> Vec<String> {
+    let mut buf = vec![];
+    // the fuzzer's mutations can leave errors behind, and it's the round trip being checked, not the model
+    if let Err(err) = model.write_with(&mut buf, &WriteOptions { allow_errors: true, ..Default::default() }) {
+        return vec![format!("writing failed: {}", err)];
+    }
+    let parsed = match crate::Parser::new(Cursor::new(buf)).and_then(|mut parser| parser.parse(PathBuf::from("fuzz.pof"))) {
+        Ok(parsed) => parsed,
+        Err(err) => return vec![format!("reading back failed: {}", err)],
+    };
+
+    let (before, after) = (model.summary_text(), parsed.summary_text());
+    before
+        .lines()
+        .zip(after.lines())
+        .filter(|(line1, line2)| line1 != line2)
+        .map(|(line1, line2)| format!("round trip changed '{}' to '{}'", line1, line2))
+        .chain((before.lines().count() != after.lines().count()).then(|| format!("round trip changed the number of summary lines")))
+        .collect()
+}
+
+// generates a model from the seed, and applies that many random mutations to it
+pub fn fuzz(seed: u64, steps: usize) -> Result<(), FuzzFailure> {
+    let mut rng = Rng::new(seed);
+    let model = random_model(&mut rng);
+    fuzz_with(seed, &mut rng, model, steps)
+}
+
+// applies that many random mutations chosen by the seed to a given model, such as one from crate::samples
+pub fn fuzz_model(seed: u64, model: Model, steps: usize) -> Result<(), FuzzFailure> {
+    fuzz_with(seed, &mut Rng::new(seed), model, steps)
+}
+
+fn fuzz_with(seed: u64, rng: &mut Rng, mut model: Model, steps: usize) -> Result<(), FuzzFailure> {
+    let mut mutations = vec![];
+
+    let problems = |model: &Model| -> Vec<String> {
+        let mut problems = model.check_invariants().iter().map(ToString::to_string).collect::<Vec<_>>();
+        problems.extend(round_trip_problems(model));
+        problems
+    };
+
+    let initial = problems(&model);
+    if !initial.is_empty() {
+        return Err(FuzzFailure { seed, mutations, problems: initial });
+    }
+
+    for _ in 0..steps {
+        let mutation = random_mutation(&model, rng);
+        apply_mutation(&mut model, &mutation);
+        mutations.push(mutation);
+
+        let problems = problems(&model);
+        if !problems.is_empty() {
+            return Err(FuzzFailure { seed, mutations, problems });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzz_editing_operations() {
+        for seed in 0..24 {
+            if let Err(failure) = fuzz(seed, 40) {
+                panic!("{}", failure);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_model() {
+        let (model1, model2) = (random_model(&mut Rng::new(7)), random_model(&mut Rng::new(7)));
+        assert_eq!(model1.summary_text(), model2.summary_text());
+    }
+}
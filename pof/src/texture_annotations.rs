@@ -0,0 +1,285 @@
+// notes on a model's textures for material pipelines, like whether one has a team color mask or how strong its glow is,
+// kept with the model rather than in side files which drift out of sync with its texture list
+//
+// they're kept by texture id, and follow the textures through the operations here, but saved by texture name so
+// reordering the texture list in another tool doesn't orphan them; they're saved in the tool metadata chunk, see
+// crate::tool_metadata
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+use crate::tool_metadata::{read_string, write_string};
+use crate::{Model, Set, TextureId, Warning};
+
+const TEAM_COLOR_MASK: u32 = 1 << 0;
+const ENV_MAP: u32 = 1 << 1;
+const HAS_GLOW_STRENGTH: u32 = 1 << 2;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextureAnnotation {
+    // the texture has a team color mask map
+    pub team_color_mask: bool,
+    // reflects the environment map
+    pub env_map: bool,
+    // a multiplier on the glow map, None for the pipeline's default
+    pub glow_strength: Option<f32>,
+    // anything else, for pipelines to agree on among themselves
+    pub properties: BTreeMap<String, String>,
+}
+impl TextureAnnotation {
+    pub fn is_empty(&self) -> bool {
+        *self == TextureAnnotation::default()
+    }
+
+    // for the json metadata and the glTF material extras
+    pub fn to_json(&self) -> gltf_json::Value {
+        use gltf_json::Value;
+        Value::from_iter([
+            ("team_color_mask", Value::from(self.team_color_mask)),
+            ("env_map", Value::from(self.env_map)),
+            ("glow_strength", self.glow_strength.map_or(Value::Null, Value::from)),
+            ("properties", Value::from_iter(self.properties.iter().map(|(key, value)| (key.clone(), Value::from(value.as_str()))))),
+        ])
+    }
+
+    // fills in whatever this one doesn't have from the other, for when textures are merged
+    fn merge_from(&mut self, other: &TextureAnnotation) {
+        self.team_color_mask |= other.team_color_mask;
+        self.env_map |= other.env_map;
+        self.glow_strength = self.glow_strength.or(other.glow_strength);
+        for (key, value) in &other.properties {
+            self.properties.entry(key.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+impl Model {
+    pub fn texture_annotations(&self) -> &BTreeMap<TextureId, TextureAnnotation> {
+        &self.texture_annotations
+    }
+
+    // an empty annotation removes it
+    pub fn set_texture_annotation(&mut self, id: TextureId, annotation: TextureAnnotation) {
+        if annotation.is_empty() {
+            self.texture_annotations.remove(&id);
+        } else {
+            self.texture_annotations.insert(id, annotation);
+        }
+    }
+
+    // the annotations after the textures are moved by the map, from old ids to new ones; ones missing from the map are dropped
+    // annotations of textures which end up in the same slot are merged, the lowest old id's taking precedence
+    pub fn remapped_texture_annotations(&self, id_map: &HashMap<TextureId, TextureId>) -> BTreeMap<TextureId, TextureAnnotation> {
+        let mut out = BTreeMap::<TextureId, TextureAnnotation>::new();
+        for (old_id, annotation) in &self.texture_annotations {
+            if let Some(&new_id) = id_map.get(old_id) {
+                out.entry(new_id).or_default().merge_from(annotation);
+            }
+        }
+        out
+    }
+
+    // swaps in a whole new set of annotations, giving back the old ones
+    pub fn replace_texture_annotations(&mut self, annotations: BTreeMap<TextureId, TextureAnnotation>) -> BTreeMap<TextureId, TextureAnnotation> {
+        std::mem::replace(&mut self.texture_annotations, annotations)
+    }
+
+    // the texture keeps its annotation, which will be saved under the new name
+    pub fn rename_texture(&mut self, id: TextureId, name: String) {
+        self.textures[id.0 as usize] = name;
+        self.refresh_untextured_slot();
+        self.recheck_warnings(Set::One(Warning::TooManyTextures));
+    }
+
+    // textures with the same name are merged into the first of them, along with their annotations
+    // returns the map of old ids to new ones
+    pub fn merge_duplicate_textures(&mut self) -> HashMap<TextureId, TextureId> {
+        let mut first_ids = HashMap::new();
+        let mut id_map = HashMap::new();
+        let mut textures = vec![];
+        for (i, texture) in self.textures.iter().enumerate() {
+            let new_id = *first_ids.entry(texture).or_insert_with(|| {
+                textures.push(texture.clone());
+                TextureId(textures.len() as u32 - 1)
+            });
+            id_map.insert(TextureId(i as u32), new_id);
+        }
+
+        self.apply_texture_map(textures, &id_map);
+        id_map
+    }
+
+    // polygons using the texture become untextured, and textures after it move down a slot
+    pub fn remove_texture(&mut self, id: TextureId) {
+        let mut textures = self.textures.clone();
+        textures.remove(id.0 as usize);
+        let id_map = (0..self.textures.len() as u32)
+            .filter(|&i| i != id.0)
+            .map(|i| (TextureId(i), TextureId(if i > id.0 { i - 1 } else { i })))
+            .collect();
+
+        self.apply_texture_map(textures, &id_map);
+    }
+
+    fn apply_texture_map(&mut self, textures: Vec<String>, id_map: &HashMap<TextureId, TextureId>) {
+        self.texture_annotations = self.remapped_texture_annotations(id_map);
+        for subobj in self.sub_objects.iter_mut() {
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves_mut() {
+                // anything unmapped is past the end of the list, which refresh_untextured_slot points at the untextured slot
+                poly.texture = id_map.get(&poly.texture).copied().unwrap_or(TextureId::UNTEXTURED);
+            }
+        }
+        self.textures = textures;
+        self.refresh_untextured_slot();
+        self.recheck_warnings(Set::One(Warning::TooManyTextures));
+    }
+}
+
+// by texture name; annotations of textures which don't exist are left out
+pub(crate) fn write_texture_annotations(w: &mut impl Write, model: &Model) -> io::Result<()> {
+    let annotations = (model.texture_annotations.iter())
+        .filter_map(|(id, annotation)| Some((model.textures.get(id.0 as usize)?, annotation)))
+        .collect::<Vec<_>>();
+
+    w.write_u32::<LE>(annotations.len() as u32)?;
+    for (name, annotation) in annotations {
+        write_string(w, name)?;
+        let mut flags = 0;
+        if annotation.team_color_mask {
+            flags |= TEAM_COLOR_MASK;
+        }
+        if annotation.env_map {
+            flags |= ENV_MAP;
+        }
+        if annotation.glow_strength.is_some() {
+            flags |= HAS_GLOW_STRENGTH;
+        }
+        w.write_u32::<LE>(flags)?;
+        w.write_f32::<LE>(annotation.glow_strength.unwrap_or_default())?;
+        w.write_u32::<LE>(annotation.properties.len() as u32)?;
+        for (key, value) in &annotation.properties {
+            write_string(w, key)?;
+            write_string(w, value)?;
+        }
+    }
+    Ok(())
+}
+
+// by texture name, to be matched up with the texture list once it's known
+pub(crate) fn read_texture_annotations(mut buf: &[u8]) -> io::Result<Vec<(String, TextureAnnotation)>> {
+    let buf = &mut buf;
+    let count = buf.read_u32::<LE>()?;
+    let mut annotations = vec![];
+    for _ in 0..count {
+        let name = read_string(buf)?;
+        let flags = buf.read_u32::<LE>()?;
+        let glow_strength = buf.read_f32::<LE>()?;
+        let mut properties = BTreeMap::new();
+        for _ in 0..buf.read_u32::<LE>()? {
+            let key = read_string(buf)?;
+            properties.insert(key, read_string(buf)?);
+        }
+        annotations.push((
+            name,
+            TextureAnnotation {
+                team_color_mask: flags & TEAM_COLOR_MASK != 0,
+                env_map: flags & ENV_MAP != 0,
+                glow_strength: (flags & HAS_GLOW_STRENGTH != 0).then_some(glow_strength),
+                properties,
+            },
+        ));
+    }
+    Ok(annotations)
+}
+
+// matches annotations read by name up with the texture list, the first texture of a name if there are several
+pub(crate) fn resolve_texture_annotations(
+    textures: &[String], annotations: Vec<(String, TextureAnnotation)>,
+) -> BTreeMap<TextureId, TextureAnnotation> {
+    let mut out = BTreeMap::new();
+    for (name, annotation) in annotations {
+        match textures.iter().position(|texture| *texture == name) {
+            Some(i) => {
+                out.insert(TextureId(i as u32), annotation);
+            }
+            None => log::warn!("Discarding annotation for missing texture {}", name),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::ObjectId;
+
+    fn round_trip(model: &Model) -> Model {
+        let parsed = Model::from_bytes(&model.write_to_vec().unwrap()).unwrap();
+        assert_eq!(parsed.textures, model.textures);
+        assert_eq!(parsed.texture_annotations(), model.texture_annotations());
+        parsed
+    }
+
+    #[test]
+    fn annotations_follow_their_textures() {
+        let mut fighter = sample_fighter(0);
+        let properties = |pairs: &[(&str, &str)]| pairs.iter().map(|&(key, value)| (key.to_string(), value.to_string())).collect();
+        fighter.textures.push("fighter-hull-copy".to_string());
+        let hull = TextureAnnotation {
+            team_color_mask: true,
+            properties: properties(&[("a", "1")]),
+            ..Default::default()
+        };
+        let copy = TextureAnnotation {
+            glow_strength: Some(2.0),
+            properties: properties(&[("a", "2"), ("b", "3")]),
+            ..Default::default()
+        };
+        let fittings = TextureAnnotation { env_map: true, ..Default::default() };
+        fighter.set_texture_annotation(TextureId(0), hull);
+        fighter.set_texture_annotation(TextureId(1), fittings.clone());
+        fighter.set_texture_annotation(TextureId(2), copy);
+        let (_, poly) = fighter.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves_mut().next().unwrap();
+        poly.texture = TextureId(2);
+        let mut fighter = round_trip(&fighter);
+
+        // saved under the new name
+        fighter.rename_texture(TextureId(1), "fighter-trim".to_string());
+        let mut fighter = round_trip(&fighter);
+
+        // the copy is folded into the hull, the hull's annotation taking precedence
+        fighter.rename_texture(TextureId(2), "fighter-hull".to_string());
+        let id_map = fighter.merge_duplicate_textures();
+        assert_eq!(id_map[&TextureId(2)], TextureId(0));
+        assert_eq!(fighter.textures, ["fighter-hull", "fighter-trim"]);
+        let merged = TextureAnnotation {
+            team_color_mask: true,
+            glow_strength: Some(2.0),
+            properties: properties(&[("a", "1"), ("b", "3")]),
+            ..Default::default()
+        };
+        assert_eq!(fighter.texture_annotations()[&TextureId(0)], merged);
+        let (_, poly) = fighter.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves().next().unwrap();
+        assert_eq!(poly.texture, TextureId(0));
+        let mut fighter = round_trip(&fighter);
+
+        // and removing one moves the rest down with their textures
+        fighter.remove_texture(TextureId(0));
+        assert_eq!(fighter.texture_annotations().iter().collect::<Vec<_>>(), [(&TextureId(0), &fittings)]);
+        round_trip(&fighter);
+
+        // an empty annotation is no annotation
+        fighter.set_texture_annotation(TextureId(0), TextureAnnotation::default());
+        assert!(fighter.texture_annotations().is_empty());
+    }
+
+    #[test]
+    fn annotations_for_missing_textures_are_dropped() {
+        let annotation = TextureAnnotation { env_map: true, ..Default::default() };
+        let read = vec![("gone".to_string(), annotation.clone()), ("here".to_string(), annotation.clone())];
+        let resolved = resolve_texture_annotations(&["here".to_string()], read);
+        assert_eq!(resolved.into_iter().collect::<Vec<_>>(), [(TextureId(0), annotation)]);
+    }
+}
@@ -0,0 +1,263 @@
+// finding texture names across a set of models which are probably meant to be the same texture, like `hull_01`, `Hull01`
+// and `hul01`, each of which the engine would load separately, or fail to find
+//
+// names are compared with case and separators ignored, and otherwise count as the same if they're one letter apart;
+// a differing digit is taken to be a different texture in a numbered series, so `hull01` and `hull02` are left alone
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{Model, TextureId};
+
+#[derive(Debug, Clone)]
+pub struct TextureVariant {
+    pub name: String,
+    // (index into the audited models, how many of its polygons use it) for every model with a texture of this name,
+    // which may be 0 if it's in the texture list but unused
+    pub usage: Vec<(usize, usize)>,
+}
+impl TextureVariant {
+    pub fn total_polygons(&self) -> usize {
+        self.usage.iter().map(|&(_, polygons)| polygons).sum()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TextureCluster {
+    // by name
+    pub variants: Vec<TextureVariant>,
+    // the variant the most models use, then the one the most polygons use
+    pub canonical: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TextureAuditReport {
+    // only clusters with more than one spelling, by canonical name
+    pub clusters: Vec<TextureCluster>,
+}
+impl TextureAuditReport {
+    // every other spelling to its cluster's canonical one, for Model::apply_texture_rename_map
+    pub fn rename_map(&self) -> BTreeMap<String, String> {
+        (self.clusters.iter())
+            .flat_map(|cluster| {
+                (cluster.variants.iter())
+                    .filter(move |variant| variant.name != cluster.canonical)
+                    .map(move |variant| (variant.name.clone(), cluster.canonical.clone()))
+            })
+            .collect()
+    }
+}
+
+fn comparison_key(name: &str) -> Vec<char> {
+    name.chars()
+        .filter(|c| !matches!(c, '_' | '-' | ' ' | '.'))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+// one substitution, insertion or deletion apart, not counting digits
+fn one_edit_apart(a: &[char], b: &[char]) -> bool {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let i = (0..short.len()).find(|&i| long[i] != short[i]).unwrap_or(short.len());
+    if i == long.len() {
+        return false;
+    }
+    match long.len() - short.len() {
+        0 => long[i + 1..] == short[i + 1..] && !long[i].is_ascii_digit() && !short[i].is_ascii_digit(),
+        1 => long[i + 1..] == short[i..] && !long[i].is_ascii_digit(),
+        _ => false,
+    }
+}
+
+fn find_root(parents: &mut [usize], mut i: usize) -> usize {
+    while parents[i] != i {
+        parents[i] = parents[parents[i]];
+        i = parents[i];
+    }
+    i
+}
+
+pub fn audit_texture_names(models: &[&Model]) -> TextureAuditReport {
+    // every distinct name, and who uses it
+    let mut variants: Vec<TextureVariant> = vec![];
+    let mut variant_ids = HashMap::new();
+    for (model_idx, model) in models.iter().enumerate() {
+        let mut polygons = vec![0; model.textures.len()];
+        for subobj in &model.sub_objects {
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+                if let Some(count) = polygons.get_mut(poly.texture.0 as usize) {
+                    *count += 1;
+                }
+            }
+        }
+
+        for (i, name) in model.textures.iter().enumerate() {
+            // the untextured slot is the tool's, not the mod's
+            if model.untextured_idx == Some(TextureId(i as u32)) {
+                continue;
+            }
+            let id = *variant_ids.entry(name.clone()).or_insert_with(|| {
+                variants.push(TextureVariant { name: name.clone(), usage: vec![] });
+                variants.len() - 1
+            });
+            match variants[id].usage.last_mut() {
+                // the same name twice in one model
+                Some((last_model, count)) if *last_model == model_idx => *count += polygons[i],
+                _ => variants[id].usage.push((model_idx, polygons[i])),
+            }
+        }
+    }
+
+    let keys = variants.iter().map(|variant| comparison_key(&variant.name)).collect::<Vec<_>>();
+    let mut parents = (0..variants.len()).collect::<Vec<_>>();
+    for i in 0..variants.len() {
+        for j in i + 1..variants.len() {
+            if keys[i] == keys[j] || one_edit_apart(&keys[i], &keys[j]) {
+                let (root_i, root_j) = (find_root(&mut parents, i), find_root(&mut parents, j));
+                parents[root_j] = root_i;
+            }
+        }
+    }
+
+    let mut groups = BTreeMap::<usize, Vec<TextureVariant>>::new();
+    for (i, variant) in variants.into_iter().enumerate() {
+        groups.entry(find_root(&mut parents, i)).or_default().push(variant);
+    }
+
+    let mut clusters = (groups.into_values())
+        .filter(|variants| variants.len() > 1)
+        .map(|mut variants| {
+            variants.sort_by(|v1, v2| v1.name.cmp(&v2.name));
+            let canonical = (variants.iter())
+                .max_by(|v1, v2| {
+                    (v1.usage.len(), v1.total_polygons())
+                        .cmp(&(v2.usage.len(), v2.total_polygons()))
+                        .then(v2.name.cmp(&v1.name))
+                })
+                .unwrap()
+                .name
+                .clone();
+            TextureCluster { variants, canonical }
+        })
+        .collect::<Vec<_>>();
+    clusters.sort_by(|c1, c2| c1.canonical.cmp(&c2.canonical));
+
+    TextureAuditReport { clusters }
+}
+
+impl Model {
+    // renames every texture named in the map, then merges any which now share a name
+    // returns how many were renamed
+    pub fn apply_texture_rename_map(&mut self, map: &BTreeMap<String, String>) -> usize {
+        let renames = (self.textures.iter().enumerate())
+            .filter_map(|(i, name)| Some((TextureId(i as u32), map.get(name)?.clone())))
+            .filter(|(id, new_name)| self.textures[id.0 as usize] != *new_name)
+            .collect::<Vec<_>>();
+        for (id, new_name) in &renames {
+            self.rename_texture(*id, new_name.clone());
+        }
+        if !renames.is_empty() {
+            self.merge_duplicate_textures();
+        }
+        renames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+
+    fn edit(a: &str, b: &str) -> bool {
+        one_edit_apart(&comparison_key(a), &comparison_key(b))
+    }
+
+    #[test]
+    fn near_identical_names() {
+        assert_eq!(comparison_key("Hull_01-a.b"), comparison_key("hull01ab"));
+        assert!(edit("hull", "hul") && edit("hul", "hull") && edit("hull01", "hell01") && edit("hull01", "hull01x"));
+        assert!(!edit("hull01", "hull02") && !edit("hull1", "hull") && !edit("hull", "hlul") && !edit("hull", "hull"));
+        assert!(!edit("hull", "hu") && !edit("", ""));
+    }
+
+    // five fighters with their hull and fittings textures spelled a few different ways, and one on a different hull
+    fn mod_directory() -> Vec<Model> {
+        let spellings = [
+            ["hull01", "fittings"],
+            ["Hull01", "fittings"],
+            ["hull_01", "fitings"],
+            ["hull01", "fittings"],
+            ["hull02", "fittings"],
+        ];
+        (spellings.into_iter().enumerate())
+            .map(|(seed, names)| {
+                let mut model = sample_fighter(seed as u64);
+                model.textures = names.map(String::from).to_vec();
+                model
+            })
+            .collect()
+    }
+
+    #[test]
+    fn audit_and_canonicalize() {
+        let mut models = mod_directory();
+        let polygons = |model: &Model, texture: u32| {
+            (model.sub_objects.iter())
+                .flat_map(|subobj| subobj.bsp_data.collision_tree.leaves())
+                .filter(|(_, poly)| poly.texture == TextureId(texture))
+                .count()
+        };
+        let (hull, fittings) = (polygons(&models[0], 0), polygons(&models[0], 1));
+
+        let report = audit_texture_names(&models.iter().collect::<Vec<_>>());
+        let summary = (report.clusters.iter())
+            .map(|cluster| {
+                let variants = (cluster.variants.iter())
+                    .map(|variant| (variant.name.as_str(), variant.usage.clone()))
+                    .collect::<Vec<_>>();
+                (cluster.canonical.as_str(), variants)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            [
+                (
+                    "fittings",
+                    vec![
+                        ("fitings", vec![(2, fittings)]),
+                        ("fittings", vec![(0, fittings), (1, fittings), (3, fittings), (4, fittings)])
+                    ]
+                ),
+                (
+                    "hull01",
+                    vec![
+                        ("Hull01", vec![(1, hull)]),
+                        ("hull01", vec![(0, hull), (3, hull)]),
+                        ("hull_01", vec![(2, hull)])
+                    ]
+                ),
+            ]
+        );
+        assert_eq!(report.clusters[1].variants[1].total_polygons(), hull * 2);
+
+        let map = report.rename_map();
+        assert_eq!(map.len(), 3);
+        let renamed = models.iter_mut().map(|model| model.apply_texture_rename_map(&map)).collect::<Vec<_>>();
+        assert_eq!(renamed, [0, 1, 2, 0, 0]);
+        assert!(models[..4].iter().all(|model| model.textures == ["hull01", "fittings"]));
+        assert!(audit_texture_names(&models.iter().collect::<Vec<_>>()).clusters.is_empty());
+    }
+
+    #[test]
+    fn renaming_onto_another_texture_merges_them() {
+        let mut model = sample_fighter(0);
+        model.textures = vec!["hull01".to_string(), "Hull01".to_string()];
+        let report = audit_texture_names(&[&model]);
+        assert_eq!(report.clusters.len(), 1);
+        assert_eq!(report.clusters[0].canonical, "hull01");
+
+        assert_eq!(model.apply_texture_rename_map(&report.rename_map()), 1);
+        assert_eq!(model.textures, ["hull01"]);
+        for subobj in &model.sub_objects {
+            assert!(subobj.bsp_data.collision_tree.leaves().all(|(_, poly)| poly.texture == TextureId(0)));
+        }
+    }
+}
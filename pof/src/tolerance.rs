@@ -0,0 +1,165 @@
+// the small margins used when recalculating and checking radii and bounding boxes
+// the defaults suit ships of ordinary size, but a 5m fighter and a 20km installation want different ones, too loose a floor
+// makes sloppy bounds on the first and too tight a margin makes spurious warnings on the second
+//
+// a model's policy is saved in the tool metadata chunk when it isn't the default, see crate::tool_metadata
+use std::io::{self, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+use crate::{BoundingBox, Model, Set};
+
+// how far past a radius or bbox a vertex can be before it's considered outside
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ToleranceMargin {
+    // a fraction of the radius, or of the bbox's largest dimension
+    Relative(f32),
+    // in model units
+    Absolute(f32),
+}
+impl ToleranceMargin {
+    // the extent with the margin added
+    pub fn apply(self, extent: f32) -> f32 {
+        match self {
+            ToleranceMargin::Relative(fraction) => (1.0 + fraction) * extent,
+            ToleranceMargin::Absolute(distance) => extent + distance,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TolerancePolicy {
+    // the smallest a recalculated radius or bbox can be, and what an empty model or subobject gets
+    pub min_extent: f32,
+    // how much the bsp and shield trees' bounding boxes are padded past their polygons
+    pub tree_padding: f32,
+    // for Warning::RadiusTooSmall
+    pub radius_margin: ToleranceMargin,
+    // for Warning::BBoxTooSmall
+    pub bbox_margin: ToleranceMargin,
+}
+impl Default for TolerancePolicy {
+    fn default() -> Self {
+        TolerancePolicy {
+            min_extent: 0.00001,
+            tree_padding: 0.01,
+            radius_margin: ToleranceMargin::Relative(f32::EPSILON),
+            bbox_margin: ToleranceMargin::Absolute(0.0),
+        }
+    }
+}
+impl TolerancePolicy {
+    // the bbox with bbox_margin added on every side
+    pub fn pad_bbox(&self, bbox: &BoundingBox) -> BoundingBox {
+        let size = bbox.x_width().max(bbox.y_height()).max(bbox.z_length());
+        bbox.pad(self.bbox_margin.apply(size) - size)
+    }
+}
+
+impl Model {
+    pub fn tolerance_policy(&self) -> TolerancePolicy {
+        self.tolerance_policy
+    }
+
+    // radii and bboxes aren't recalculated, only the warnings about them rechecked
+    pub fn set_tolerance_policy(&mut self, policy: TolerancePolicy) {
+        self.tolerance_policy = policy;
+        self.recheck_warnings(Set::All);
+    }
+}
+
+fn write_margin(w: &mut impl Write, margin: ToleranceMargin) -> io::Result<()> {
+    let (kind, value) = match margin {
+        ToleranceMargin::Relative(fraction) => (0, fraction),
+        ToleranceMargin::Absolute(distance) => (1, distance),
+    };
+    w.write_u32::<LE>(kind)?;
+    w.write_f32::<LE>(value)
+}
+
+fn read_margin(buf: &mut &[u8]) -> io::Result<ToleranceMargin> {
+    let kind = buf.read_u32::<LE>()?;
+    let value = buf.read_f32::<LE>()?;
+    match kind {
+        0 => Ok(ToleranceMargin::Relative(value)),
+        1 => Ok(ToleranceMargin::Absolute(value)),
+        _ => Err(io::Error::new(io::ErrorKind::Other, "unknown tolerance margin")),
+    }
+}
+
+pub(crate) fn write_tolerance_policy(w: &mut impl Write, policy: &TolerancePolicy) -> io::Result<()> {
+    w.write_f32::<LE>(policy.min_extent)?;
+    w.write_f32::<LE>(policy.tree_padding)?;
+    write_margin(w, policy.radius_margin)?;
+    write_margin(w, policy.bbox_margin)
+}
+
+pub(crate) fn read_tolerance_policy(mut buf: &[u8]) -> io::Result<TolerancePolicy> {
+    let buf = &mut buf;
+    Ok(TolerancePolicy {
+        min_extent: buf.read_f32::<LE>()?,
+        tree_padding: buf.read_f32::<LE>()?,
+        radius_margin: read_margin(buf)?,
+        bbox_margin: read_margin(buf)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::{ObjectId, Warning};
+    use nalgebra_glm as glm;
+
+    #[test]
+    fn relative_margin_on_a_tiny_model() {
+        let mut fighter = sample_fighter(0);
+        fighter.apply_transform(&glm::scaling(&glm::vec3(0.001, 0.001, 0.001))).unwrap();
+        let detail0 = ObjectId(0);
+        let radius = fighter.sub_objects[detail0].radius;
+        let radius_warning = Warning::RadiusTooSmall(Some(detail0));
+
+        // as another tool might have rounded it
+        fighter.sub_objects[detail0].radius = radius * (1.0 - 1e-5);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&radius_warning));
+        let policy = TolerancePolicy {
+            radius_margin: ToleranceMargin::Relative(1e-4),
+            ..Default::default()
+        };
+        fighter.set_tolerance_policy(policy);
+        assert!(!fighter.warnings.contains(&radius_warning));
+
+        // but one which really is too small still warns
+        fighter.sub_objects[detail0].radius = radius * 0.9;
+        fighter.recheck_warnings(Set::One(radius_warning.clone()));
+        assert!(fighter.warnings.contains(&radius_warning));
+        // where an absolute margin made for a ship of ordinary size wouldn't notice
+        let policy = TolerancePolicy {
+            radius_margin: ToleranceMargin::Absolute(0.01),
+            ..Default::default()
+        };
+        fighter.set_tolerance_policy(policy);
+        assert!(!fighter.warnings.contains(&radius_warning));
+    }
+
+    #[test]
+    fn policy_is_saved_with_the_model() {
+        let mut fighter = sample_fighter(0);
+        let policy = TolerancePolicy {
+            min_extent: 0.001,
+            tree_padding: 0.5,
+            radius_margin: ToleranceMargin::Relative(0.01),
+            bbox_margin: ToleranceMargin::Absolute(0.25),
+        };
+        fighter.set_tolerance_policy(policy);
+        let parsed = Model::from_bytes(&fighter.write_to_vec().unwrap()).unwrap();
+        assert_eq!(parsed.tolerance_policy(), policy);
+
+        // an unknown kind of margin is an error, not a misread policy
+        let mut section = vec![];
+        write_tolerance_policy(&mut section, &policy).unwrap();
+        section[8] = 7;
+        assert!(read_tolerance_policy(&section).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+// everything this crate saves which the engine doesn't know about, in one chunk at the end of the file: the stat history,
+// attachment points, a tolerance policy which isn't the default and texture annotations
+//
+// the engine and other tools skip over it as an unknown chunk, though tools which rewrite the file from scratch will drop it
+// it's a format version, then a section for each of those which has anything in it, as a tag and a length; a section
+// which can't be read, or whose tag this version doesn't know, is dropped without losing the others
+use std::io::{self, Read, Write};
+
+use byteorder::{ReadBytesExt, WriteBytesExt, LE};
+
+use crate::attachments::{read_attachments, write_attachments, AttachmentPoint};
+use crate::history::{read_stat_history, write_stat_history, StatSnapshot};
+use crate::texture_annotations::{read_texture_annotations, write_texture_annotations, TextureAnnotation};
+use crate::tolerance::{read_tolerance_policy, write_tolerance_policy, TolerancePolicy};
+use crate::Model;
+
+pub const TOOL_METADATA_CHUNK: &[u8; 4] = b"PTMD";
+// bumped if the layout of any of the sections ever changes
+const FORMAT_VERSION: u32 = 1;
+
+const STAT_HISTORY: &[u8; 4] = b"HIST";
+const ATTACHMENTS: &[u8; 4] = b"ATCH";
+const TOLERANCE_POLICY: &[u8; 4] = b"TOLR";
+const TEXTURE_ANNOTATIONS: &[u8; 4] = b"TXAN";
+
+#[derive(Debug, Default)]
+pub(crate) struct ToolMetadata {
+    pub stat_history: Vec<StatSnapshot>,
+    pub attachments: Vec<AttachmentPoint>,
+    pub tolerance_policy: TolerancePolicy,
+    // by texture name, to be matched up with the texture list once it's known
+    pub texture_annotations: Vec<(String, TextureAnnotation)>,
+}
+
+pub(crate) fn write_string(w: &mut impl Write, string: &str) -> io::Result<()> {
+    w.write_u32::<LE>(string.len() as u32)?;
+    w.write_all(string.as_bytes())
+}
+
+// a length and then that many bytes, which have to be there
+fn read_bytes<'a>(buf: &mut &'a [u8]) -> io::Result<&'a [u8]> {
+    let len = buf.read_u32::<LE>()? as usize;
+    if len > buf.len() {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "tool metadata is truncated"));
+    }
+    let (bytes, rest) = buf.split_at(len);
+    *buf = rest;
+    Ok(bytes)
+}
+
+pub(crate) fn read_string(buf: &mut &[u8]) -> io::Result<String> {
+    Ok(String::from_utf8_lossy(read_bytes(buf)?).into_owned())
+}
+
+fn write_section(w: &mut Vec<u8>, tag: &[u8; 4], f: impl FnOnce(&mut Vec<u8>) -> io::Result<()>) -> io::Result<()> {
+    let mut section = vec![];
+    f(&mut section)?;
+    w.write_all(tag)?;
+    w.write_u32::<LE>(section.len() as u32)?;
+    w.write_all(&section)
+}
+
+// the chunk's contents, or None if there's nothing to save; the stat history is passed in since saving can add to it
+pub(crate) fn write_tool_metadata(model: &Model, stat_history: &[StatSnapshot]) -> io::Result<Option<Vec<u8>>> {
+    let mut buf = vec![];
+    if !stat_history.is_empty() {
+        write_section(&mut buf, STAT_HISTORY, |w| write_stat_history(w, stat_history))?;
+    }
+    if !model.attachments.is_empty() {
+        write_section(&mut buf, ATTACHMENTS, |w| write_attachments(w, &model.attachments))?;
+    }
+    if model.tolerance_policy != TolerancePolicy::default() {
+        write_section(&mut buf, TOLERANCE_POLICY, |w| write_tolerance_policy(w, &model.tolerance_policy))?;
+    }
+    if !model.texture_annotations.is_empty() {
+        write_section(&mut buf, TEXTURE_ANNOTATIONS, |w| write_texture_annotations(w, model))?;
+    }
+    if buf.is_empty() {
+        return Ok(None);
+    }
+
+    let mut chunk = vec![];
+    chunk.write_u32::<LE>(FORMAT_VERSION)?;
+    chunk.extend(buf);
+    Ok(Some(chunk))
+}
+
+pub(crate) fn read_tool_metadata(mut buf: &[u8]) -> io::Result<ToolMetadata> {
+    let buf = &mut buf;
+    if buf.read_u32::<LE>()? != FORMAT_VERSION {
+        return Err(io::Error::new(io::ErrorKind::Other, "unknown tool metadata format"));
+    }
+
+    let mut metadata = ToolMetadata::default();
+    while !buf.is_empty() {
+        let mut tag = [0; 4];
+        buf.read_exact(&mut tag)?;
+        let section = read_bytes(buf)?;
+        let name = String::from_utf8_lossy(&tag);
+        // a bad section only loses what's in it
+        let read = match &tag {
+            tag if tag == STAT_HISTORY => read_stat_history(section).map(|history| metadata.stat_history = history),
+            tag if tag == ATTACHMENTS => read_attachments(section).map(|attachments| metadata.attachments = attachments),
+            tag if tag == TOLERANCE_POLICY => read_tolerance_policy(section).map(|policy| metadata.tolerance_policy = policy),
+            tag if tag == TEXTURE_ANNOTATIONS => read_texture_annotations(section).map(|annotations| metadata.texture_annotations = annotations),
+            _ => {
+                log::warn!("Skipping unknown tool metadata section {}", name);
+                Ok(())
+            }
+        };
+        if let Err(err) = read {
+            log::warn!("Discarding unreadable tool metadata section {}: {}", name, err);
+        }
+    }
+    Ok(metadata)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::tolerance::ToleranceMargin;
+    use crate::{NormalVec3, TextureId, Vec3d, WriteOptions};
+
+    fn fighter_with_metadata() -> Model {
+        let mut fighter = sample_fighter(0);
+        fighter.attachments.push(AttachmentPoint {
+            name: "$muzzle".to_string(),
+            parent: None,
+            position: Vec3d::new(0.0, 0.0, 5.0),
+            fvec: NormalVec3(Vec3d::new(0.0, 0.0, 1.0)),
+            uvec: NormalVec3(Vec3d::new(0.0, 1.0, 0.0)),
+        });
+        fighter.set_tolerance_policy(TolerancePolicy {
+            radius_margin: ToleranceMargin::Relative(0.01),
+            ..Default::default()
+        });
+        fighter.set_texture_annotation(TextureId(1), TextureAnnotation { env_map: true, ..Default::default() });
+        fighter
+    }
+
+    #[test]
+    fn everything_goes_in_one_chunk() {
+        let fighter = fighter_with_metadata();
+        let mut buf = vec![];
+        fighter
+            .write_with(&mut buf, &WriteOptions { record_stat_history: true, ..Default::default() })
+            .unwrap();
+        let chunks = crate::inspect::inspect(io::Cursor::new(&buf)).unwrap().chunks;
+        assert_eq!(chunks.iter().filter(|chunk| &chunk.id == TOOL_METADATA_CHUNK).count(), 1);
+        assert_eq!(&chunks.last().unwrap().id, TOOL_METADATA_CHUNK);
+
+        let parsed = Model::from_bytes(&buf).unwrap();
+        assert_eq!(parsed.stat_history().len(), 1);
+        assert_eq!(parsed.attachments.len(), 1);
+        assert_eq!(parsed.tolerance_policy(), fighter.tolerance_policy());
+        assert!(parsed.texture_annotations()[&TextureId(1)].env_map);
+
+        // and none at all when there's nothing to put in it
+        let chunks = crate::inspect::inspect(io::Cursor::new(sample_fighter(0).write_to_vec().unwrap()))
+            .unwrap()
+            .chunks;
+        assert!(chunks.iter().all(|chunk| &chunk.id != TOOL_METADATA_CHUNK));
+    }
+
+    #[test]
+    fn bad_sections_are_dropped_alone() {
+        let fighter = fighter_with_metadata();
+        let chunk = write_tool_metadata(&fighter, &[fighter.stat_snapshot()]).unwrap().unwrap();
+        let read = read_tool_metadata(&chunk).unwrap();
+        assert_eq!((read.stat_history.len(), read.attachments.len(), read.texture_annotations.len()), (1, 1, 1));
+
+        // an unknown section, and a known one too short for what it holds, leave the rest as they were
+        let mut extended = chunk.clone();
+        write_section(&mut extended, b"NEW!", |w| w.write_all(b"from the future")).unwrap();
+        write_section(&mut extended, ATTACHMENTS, |w| w.write_u32::<LE>(3)).unwrap();
+        let read = read_tool_metadata(&extended).unwrap();
+        assert_eq!((read.stat_history.len(), read.attachments.len(), read.texture_annotations.len()), (1, 1, 1));
+        assert_eq!(read.tolerance_policy, fighter.tolerance_policy());
+
+        // but a newer format, or a section running off the end, can't be read at all
+        let mut newer = chunk.clone();
+        newer[0] = FORMAT_VERSION as u8 + 1;
+        assert!(read_tool_metadata(&newer).is_err());
+        assert!(read_tool_metadata(&chunk[..chunk.len() - 1]).is_err());
+    }
+}
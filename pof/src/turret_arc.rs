@@ -0,0 +1,200 @@
+// a triangle mesh of a turret's field of fire, for a viewer to draw as a translucent cone
+// it's a cone from the turret's averaged fire point, capped by a spherical dome, and each vertex of the dome is tagged
+// with whether the hull is in the way in that direction, so the obstructed parts can be drawn differently
+use std::f32::consts::PI;
+
+use crate::{properties_get_field, Model, Vec3d};
+
+// what fso assumes a turret without "$fov" can cover, a hemisphere
+pub const DEFAULT_TURRET_FOV: f32 = 180.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ArcMeshOptions {
+    // the full angle of the cone in degrees, instead of the turret's "$fov"
+    pub fov: Option<f32>,
+    // samples around the normal
+    pub segments: usize,
+    // samples from the normal out to the edge of the cone
+    pub rings: usize,
+    // how far the cone reaches, the model's radius if 0
+    pub length: f32,
+}
+impl Default for ArcMeshOptions {
+    fn default() -> Self {
+        ArcMeshOptions { fov: None, segments: 32, rings: 8, length: 0.0 }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ArcMesh {
+    // in model space, the first is the apex and the second the middle of the dome
+    pub positions: Vec<Vec3d>,
+    // per position, whether a shot in that direction would hit the hull; never set for the apex
+    pub blocked: Vec<bool>,
+    // counter-clockwise seen from outside the cone
+    pub triangles: Vec<[u32; 3]>,
+}
+impl ArcMesh {
+    // the fraction of the dome's samples which are blocked
+    pub fn blocked_fraction(&self) -> f32 {
+        let dome = &self.blocked[1.min(self.blocked.len())..];
+        if dome.is_empty() {
+            0.0
+        } else {
+            dome.iter().filter(|&&blocked| blocked).count() as f32 / dome.len() as f32
+        }
+    }
+}
+
+// two unit vectors perpendicular to the normal and each other, without a special case near any axis
+// (Duff et al., "Building an Orthonormal Basis, Revisited")
+fn perpendicular_basis(normal: Vec3d) -> (Vec3d, Vec3d) {
+    let sign = 1.0_f32.copysign(normal.z);
+    let a = -1.0 / (sign + normal.z);
+    let b = normal.x * normal.y * a;
+    (Vec3d::new(1.0 + sign * normal.x * normal.x * a, sign * b, -sign * normal.x), Vec3d::new(b, sign + normal.y * normal.y * a, -normal.y))
+}
+
+impl Model {
+    // the full angle of the turret's field of fire in degrees, from "$fov" on its base subobject
+    pub fn turret_fov(&self, turret_idx: usize) -> f32 {
+        let turret = &self.turrets[turret_idx];
+        properties_get_field(&self.sub_objects[turret.base_obj].properties, "$fov")
+            .and_then(|fov| fov.trim().parse::<f32>().ok())
+            .filter(|fov| fov.is_finite() && *fov > 0.0)
+            .unwrap_or(DEFAULT_TURRET_FOV)
+    }
+
+    // the turret's fire points averaged, in model space, or the gun subobject's position if it has none
+    pub fn turret_average_fire_point(&self, turret_idx: usize) -> Vec3d {
+        let turret = &self.turrets[turret_idx];
+        let mut sum = Vec3d::ZERO;
+        for &point in &turret.fire_points {
+            sum += point;
+        }
+        let average = if turret.fire_points.is_empty() {
+            Vec3d::ZERO
+        } else {
+            sum / turret.fire_points.len() as f32
+        };
+        average + self.get_total_subobj_offset(turret.gun_obj)
+    }
+
+    // the turret's field of fire about its normal, see ArcMesh
+    // blocking is checked against the detail0 hull at rest, leaving out the turret's own base and gun
+    pub fn turret_arc_mesh(&self, turret_idx: usize, options: ArcMeshOptions) -> ArcMesh {
+        let turret = &self.turrets[turret_idx];
+        let half_angle = (options.fov.unwrap_or_else(|| self.turret_fov(turret_idx)) / 2.0)
+            .clamp(0.0, 180.0)
+            .to_radians();
+        let length = if options.length > 0.0 { options.length } else { self.header.max_radius };
+        let (segments, rings) = (options.segments.max(3), options.rings.max(1));
+
+        let apex = self.turret_average_fire_point(turret_idx);
+        let normal = turret.normal.0;
+        let (u, v) = perpendicular_basis(normal);
+        let is_blocked = |dir: Vec3d| {
+            self.raycast_filtered(apex, dir, |id| id != turret.base_obj && id != turret.gun_obj)
+                .is_some()
+        };
+
+        let mut mesh = ArcMesh::default();
+        let push = |mesh: &mut ArcMesh, dir: Vec3d| {
+            mesh.positions.push(apex + dir * length);
+            mesh.blocked.push(is_blocked(dir));
+        };
+        mesh.positions.push(apex);
+        mesh.blocked.push(false);
+        push(&mut mesh, normal);
+        for ring in 1..=rings {
+            let polar = half_angle * ring as f32 / rings as f32;
+            for segment in 0..segments {
+                let azimuth = 2.0 * PI * segment as f32 / segments as f32;
+                let dir = normal * polar.cos() + (u * azimuth.cos() + v * azimuth.sin()) * polar.sin();
+                push(&mut mesh, dir);
+            }
+        }
+
+        // the index of a position on a ring, ring 0 being the middle of the dome
+        let index = |ring: usize, segment: usize| {
+            if ring == 0 {
+                1
+            } else {
+                (2 + (ring - 1) * segments + segment % segments) as u32
+            }
+        };
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let (a, b) = (index(ring + 1, segment), index(ring + 1, segment + 1));
+                if ring == 0 {
+                    mesh.triangles.push([1, a, b]);
+                } else {
+                    let (c, d) = (index(ring, segment), index(ring, segment + 1));
+                    mesh.triangles.push([c, a, b]);
+                    mesh.triangles.push([c, b, d]);
+                }
+            }
+        }
+        // the sides of the cone, from the apex to the rim
+        for segment in 0..segments {
+            mesh.triangles.push([0, index(rings, segment + 1), index(rings, segment)]);
+        }
+
+        mesh
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{sample_fighter, sample_trench_turret};
+    use crate::{NormalVec3, ObjectId};
+
+    #[test]
+    fn cones_around_any_normal() {
+        let mut fighter = sample_fighter(0);
+        let options = ArcMeshOptions { fov: Some(90.0), segments: 12, rings: 3, length: 10.0 };
+        let normals = [(0.0, 1.0, 0.0), (0.0, 0.0, -1.0), (0.0, 0.001, -1.0), (1.0, -2.0, 3.0), (-1.0, 0.0, 0.0)];
+        for normal in normals.map(|(x, y, z)| Vec3d::new(x, y, z).normalize()) {
+            fighter.turrets[0].normal = NormalVec3(normal);
+            let mesh = fighter.turret_arc_mesh(0, options);
+            let apex = fighter.turret_average_fire_point(0);
+            assert_eq!(mesh.positions[0], apex);
+            assert_eq!(mesh.positions.len(), 2 + 3 * 12);
+            assert_eq!(mesh.triangles.len(), 12 + 2 * 12 * 2 + 12);
+
+            for (i, &position) in mesh.positions.iter().enumerate().skip(1) {
+                let dir = position - apex;
+                assert!((dir.magnitude() - 10.0).abs() < 1e-4, "{:?} {}", normal, i);
+                // ring by ring out to the edge of the cone
+                let ring = if i == 1 { 0 } else { (i - 2) / 12 + 1 };
+                let angle = dir.cross(&normal).magnitude().atan2(dir.dot(&normal)).to_degrees();
+                assert!((angle - 15.0 * ring as f32).abs() < 0.01, "{:?} {} {}", normal, i, angle);
+            }
+        }
+    }
+
+    #[test]
+    fn trench_turret_is_blocked_to_the_sides() {
+        // out in the open, nothing is in the way
+        let options = |fov: f32| ArcMeshOptions { fov: Some(fov), ..Default::default() };
+        assert_eq!(sample_fighter(0).turret_arc_mesh(0, options(180.0)).blocked_fraction(), 0.0);
+
+        let model = sample_trench_turret(0);
+        assert_eq!(model.turret_fov(0), 180.0);
+        // the walls are lower than 60 degrees from straight up
+        assert_eq!(model.turret_arc_mesh(0, options(60.0)).blocked_fraction(), 0.0);
+
+        let mesh = model.turret_arc_mesh(0, options(180.0));
+        let fraction = mesh.blocked_fraction();
+        assert!(fraction > 0.1 && fraction < 0.9, "{}", fraction);
+        // exactly the directions which reach a wall before getting over it or past its end
+        let (apex, half) = (mesh.positions[0], model.sub_objects[ObjectId(0)].bbox.max);
+        for (&position, &blocked) in mesh.positions.iter().zip(&mesh.blocked).skip(1) {
+            let dir = (position - apex).normalize();
+            let to_wall = (1.25 - apex.x * dir.x.signum()) / dir.x.abs();
+            let hit = apex + dir * to_wall;
+            assert_eq!(blocked, hit.y < half.y + 2.0 && hit.z.abs() < half.z, "{:?}", dir);
+        }
+    }
+}
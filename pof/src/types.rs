@@ -1,5 +1,5 @@
 use std::cell::Cell;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::convert::TryFrom;
 use std::fmt::{Debug, Display};
 use std::io::{self, Write};
@@ -16,6 +16,14 @@ use nalgebra::Matrix3;
 use nalgebra_glm::Mat4;
 extern crate nalgebra_glm as glm;
 
+use crate::attachments::AttachmentPoint;
+use crate::geometry_cache::GeometryCache;
+use crate::history::StatSnapshot;
+use crate::quirks::QuirkNotice;
+use crate::repair::ParseNotice;
+use crate::spatial::SpatialHash;
+use crate::texture_annotations::TextureAnnotation;
+use crate::tolerance::TolerancePolicy;
 use crate::write::Serialize;
 
 macro_rules! id_type {
@@ -59,7 +67,7 @@ pub(crate) fn get_version() -> Version {
 }
 
 // like a regular vector, but indexed with ObjectIds only, for some safety
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ObjVec<T>(pub Vec<T>);
 impl<T> Index<ObjectId> for ObjVec<T> {
     type Output = T;
@@ -123,7 +131,7 @@ macro_rules! mk_struct {
     };
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Axis {
     X,
     Y,
@@ -213,6 +221,14 @@ impl FromStr for Vec3d {
         }
     }
 }
+// components past the third are ignored, and missing ones are 0, see Vec3d::from_iter_exact to catch either
+impl FromIterator<f32> for Vec3d {
+    fn from_iter<T: IntoIterator<Item = f32>>(iter: T) -> Self {
+        let mut iter = iter.into_iter();
+        let mut next = || iter.next().unwrap_or(0.0);
+        Vec3d::new(next(), next(), next())
+    }
+}
 impl Display for Vec3d {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}, {}, {}", &self.x, &self.y, &self.z)
@@ -232,6 +248,16 @@ impl Vec3d {
     pub fn to_tuple(self) -> (f32, f32, f32) {
         (self.x, self.y, self.z)
     }
+    // x, y, then z
+    pub fn iter(&self) -> impl Iterator<Item = f32> {
+        [self.x, self.y, self.z].into_iter()
+    }
+    // None unless the iterator has exactly three components, unlike collecting, which is lenient
+    pub fn from_iter_exact(iter: impl IntoIterator<Item = f32>) -> Option<Vec3d> {
+        let mut iter = iter.into_iter();
+        let vec = Vec3d::new(iter.next()?, iter.next()?, iter.next()?);
+        iter.next().is_none().then_some(vec)
+    }
     pub fn magnitude(self) -> f32 {
         f32::sqrt(self.magnitude_squared())
     }
@@ -251,17 +277,30 @@ impl Vec3d {
     pub fn is_null(self) -> bool {
         self.x.abs() <= 0.000001 && self.y.abs() <= 0.000001 && self.z.abs() <= 0.000001
     }
+    // the zero vector for an empty iterator
     pub fn average(iter: impl Iterator<Item = Self>) -> Vec3d {
-        let mut out = Vec3d::ZERO;
+        // accumulate in f64, an f32 sum over a large model drifts noticeably
+        let mut out = [0.0_f64; 3];
         let mut n = 0;
 
         for vec in iter {
-            out += vec;
+            out[0] += vec.x as f64;
+            out[1] += vec.y as f64;
+            out[2] += vec.z as f64;
             n += 1;
         }
 
-        out /= n as f32;
-        out
+        if n == 0 {
+            return Vec3d::ZERO;
+        }
+
+        let [x, y, z] = out.map(|val| (val / n as f64) as f32);
+        Vec3d { x, y, z }
+    }
+
+    // the gap between adjacent f32 values at this vector's largest coordinate
+    pub fn quantization(self) -> f32 {
+        f32_quantization(self.x.abs().max(self.y.abs()).max(self.z.abs()))
     }
 
     /// Swizzle coordinates from POF (Right: `+x`, Up: `+y`, In: `-z`) to specified DAE convention
@@ -433,6 +472,83 @@ pub fn mat4_rotation_only(matrix: &TMat4<f32>) -> TMat4<f32> {
     TMat3::from_columns(&arr).to_homogeneous()
 }
 
+// None if the matrix has a zero scale, which leaves no meaningful rotation
+// (mat4_rotation_only gives an arbitrary result in that case)
+pub fn mat4_try_rotation_only(matrix: &TMat4<f32>) -> Option<TMat4<f32>> {
+    let matrix = mat4_rotation_and_scaling_only(matrix);
+    let mut arr = [Vec3::x(), Vec3::y(), Vec3::z()].map(|axis| matrix.transform_vector(&axis));
+    (Vec3::orthonormalize(&mut arr) == 3).then(|| TMat3::from_columns(&arr).to_homogeneous())
+}
+
+// the box around the points along their principal axes, see Model::oriented_bounding_box
+pub(crate) fn oriented_box_around(verts: &[Vec3d]) -> (Mat3d, BoundingBox) {
+    let aabb = BoundingBox::from_vectors(verts.iter().copied());
+    if verts.is_empty() {
+        return (Mat3d::IDENTITY, aabb);
+    }
+
+    let center = Vec3d::average(verts.iter().copied());
+    let mut covariance = Matrix3::<f64>::zeros();
+    for &vert in verts {
+        let vert = vert - center;
+        let v = nalgebra::Vector3::<f64>::new(vert.x.into(), vert.y.into(), vert.z.into());
+        covariance += v * v.transpose();
+    }
+
+    let eigen = covariance.symmetric_eigen();
+    let axes = [0, 1, 2].map(|i| {
+        let axis = eigen.eigenvectors.column(i);
+        Vec3d::new(axis.x as f32, axis.y as f32, axis.z as f32)
+    });
+    if axes.iter().any(|axis| axis.is_null()) {
+        return (Mat3d::IDENTITY, aabb);
+    }
+
+    // match each principal axis up with the model axis it's closest to
+    let order = (0..3)
+        .permutations(3)
+        .max_by(|p1, p2| {
+            let closeness = |p: &Vec<usize>| (0..3).map(|i| axes[p[i]][ALL_AXES[i]].abs()).sum::<f32>();
+            closeness(p1).partial_cmp(&closeness(p2)).unwrap()
+        })
+        .unwrap();
+    let [rvec, uvec, _] = [0, 1, 2].map(|i| {
+        let axis = axes[order[i]].normalize();
+        if axis[ALL_AXES[i]] < 0.0 {
+            -axis
+        } else {
+            axis
+        }
+    });
+    // keep it a proper rotation
+    let rotation = Mat3d { rvec, uvec, fvec: rvec.cross(&uvec).normalize() };
+
+    let local = verts
+        .iter()
+        .map(|vert| Vec3d::new(vert.dot(&rotation.rvec), vert.dot(&rotation.uvec), vert.dot(&rotation.fvec)));
+    let obb = BoundingBox::from_vectors(local);
+    if obb.volume() < aabb.volume() {
+        (rotation, obb)
+    } else {
+        (Mat3d::IDENTITY, aabb)
+    }
+}
+
+// splits a matrix into translation, rotation and per-axis scale, such that translation * rotation * scale gives it back
+// a mirroring matrix gets a negative z scale so the rotation stays a proper one, and any shear is lost
+pub fn decompose_mat4(matrix: &TMat4<f32>) -> (Vec3d, Mat3d, Vec3d) {
+    let zero = Vec3d::ZERO.into();
+    let translation = matrix.transform_point(&zero) - zero;
+    let matrix = mat4_rotation_and_scaling_only(matrix);
+    let mut rotation = glm::mat4_to_mat3(&mat4_rotation_only(&matrix));
+    if rotation.determinant() < 0.0 {
+        rotation.set_column(2, &-rotation.column(2));
+    }
+    let axes = [Vec3::x(), Vec3::y(), Vec3::z()].map(|axis| matrix.transform_vector(&axis));
+    let scale = Vec3::new(axes[0].dot(&rotation.column(0)), axes[1].dot(&rotation.column(1)), axes[2].dot(&rotation.column(2)));
+    (translation.into(), rotation.into(), scale.into())
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct NormalVec3(pub Vec3d);
 
@@ -441,11 +557,74 @@ impl Default for NormalVec3 {
         Self(Vec3d { x: 0.0, y: 0.0, z: 1.0 })
     }
 }
+impl NormalVec3 {
+    // the direction from one point to another, if they aren't the same point
+    pub fn toward(from: Vec3d, to: Vec3d) -> Option<NormalVec3> {
+        (to - from).try_into().ok()
+    }
+
+    // for normals read from a file, which aren't always unit length, or even non-zero
+    pub fn sanitize(vec: Vec3d, default: NormalVec3) -> (NormalVec3, Option<NormalIssue>) {
+        match NormalVec3::try_from(vec) {
+            Err(()) => (default, Some(NormalIssue::Zero)),
+            Ok(normal) if (vec.magnitude() - 1.0).abs() > 0.001 => (normal, Some(NormalIssue::NotUnitLength)),
+            Ok(normal) => (normal, None),
+        }
+    }
+
+    // rotates by the rotation part of the matrix
+    // a matrix with a zero scale has no meaningful rotation, so the previous direction is kept and this returns false
+    pub fn apply_rotation(&mut self, matrix: &TMat4<f32>) -> bool {
+        match mat4_try_rotation_only(matrix).map(|matrix| (&matrix * self.0).try_into()) {
+            Some(Ok(normal)) => {
+                *self = normal;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalField {
+    EyePoint(usize),
+    Weapon { primary: bool, bank: usize, point: usize },
+    Turret(usize),
+    ThrusterGlow { bank: usize, glow: usize },
+    DockFvec(usize),
+    DockUvec(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalIssue {
+    // normalized on load
+    NotUnitLength,
+    // replaced with a default on load
+    Zero,
+    // kept its previous direction through a transform with a zero scale
+    DegenerateTransform,
+    // thrusters should point backward
+    ThrusterFacingForward,
+    // a dock's uvec wasn't perpendicular to its fvec
+    NotOrthogonal,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NormalAudit {
+    pub field: NormalField,
+    pub issue: NormalIssue,
+}
 impl TryFrom<Vec3d> for NormalVec3 {
     type Error = ();
 
     fn try_from(value: Vec3d) -> Result<Self, Self::Error> {
-        Ok(Self(Vec3::from(value).try_normalize(1e-6).ok_or(())?.into()))
+        let normal = Vec3::from(value).try_normalize(1e-6).ok_or(())?;
+        // NaNs get through try_normalize
+        if normal.iter().all(|val| val.is_finite()) {
+            Ok(Self(normal.into()))
+        } else {
+            Err(())
+        }
     }
 }
 impl TryFrom<Vec3> for NormalVec3 {
@@ -505,6 +684,9 @@ impl BoundingBox {
             (self.max.x - self.min.x) * (self.max.y - self.min.y) * (self.max.z - self.min.z)
         }
     }
+    pub fn center(&self) -> Vec3d {
+        (self.min + self.max) / 2.0
+    }
     pub fn x_width(&self) -> f32 {
         self.max.x - self.min.x
     }
@@ -562,6 +744,12 @@ impl BoundingBox {
         self
     }
 
+    pub fn overlaps(&self, other: &BoundingBox) -> bool {
+        ALL_AXES
+            .into_iter()
+            .all(|axis| self.min[axis] <= other.max[axis] && other.min[axis] <= self.max[axis])
+    }
+
     pub fn contains(self, vec: Vec3d) -> bool {
         for axis in ALL_AXES {
             if vec[axis] < self.min[axis] || vec[axis] > self.max[axis] {
@@ -595,7 +783,7 @@ impl Serialize for BspLightKind {
 pub const MAX_EYES: usize = 9;
 
 mk_struct! {
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     // this is pretty much unused by the engine
     pub struct BspLight {
         pub location: Vec3d,
@@ -619,9 +807,7 @@ pub struct EyePoint {
 impl EyePoint {
     pub fn apply_transform(&mut self, matrix: &TMat4<f32>) {
         self.position = matrix * self.position;
-
-        let matrix = mat4_rotation_only(matrix);
-        self.normal = (&matrix * self.normal.0).try_into().unwrap();
+        self.normal.apply_rotation(matrix);
     }
 }
 impl Serialize for EyePoint {
@@ -722,7 +908,7 @@ impl Serialize for ShieldPolygon {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum ShieldNode {
     Split {
         bbox: BoundingBox,
@@ -737,6 +923,20 @@ pub enum ShieldNode {
 impl ShieldNode {
     pub(crate) const SPLIT: u32 = 0;
     pub(crate) const LEAF: u32 = 1;
+
+    // the number of nodes on the longest path from here to a leaf
+    pub fn depth(&self) -> u32 {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            deepest = deepest.max(depth);
+            if let ShieldNode::Split { front, back, .. } = node {
+                stack.push((front, depth + 1));
+                stack.push((back, depth + 1));
+            }
+        }
+        deepest
+    }
 }
 impl Serialize for ShieldNode {
     fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
@@ -797,6 +997,17 @@ pub struct WeaponHardpoint {
     pub normal: NormalVec3,
     pub offset: f32,
 }
+
+// the most firing points fso allows in a single weapon bank
+pub const MAX_WEAPON_BANK_POINTS: usize = 25;
+// how far inside detail0 a hardpoint can be before Warning::WeaponInsideHull, points sunk just below the surface are common and harmless
+pub const WEAPON_INSIDE_HULL_MARGIN: f32 = 0.1;
+// how close two weapon, docking or eye points' positions and normals have to be to count as a copy-paste duplicate
+pub const DUPLICATE_POINT_EPSILON: f32 = 0.001;
+
+fn points_coincide((position1, normal1): (Vec3d, Vec3d), (position2, normal2): (Vec3d, Vec3d)) -> bool {
+    (position1 - position2).magnitude() <= DUPLICATE_POINT_EPSILON && (normal1 - normal2).magnitude() <= DUPLICATE_POINT_EPSILON
+}
 impl Serialize for WeaponHardpoint {
     fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
         self.position.write_to(w)?;
@@ -809,10 +1020,47 @@ impl Serialize for WeaponHardpoint {
     }
 }
 impl WeaponHardpoint {
+    // the offset is only written for V21_18 and up, other than V22_00, see Model::add_weapon_point
+    pub fn with_offset(position: Vec3d, normal: NormalVec3, offset: f32) -> Self {
+        WeaponHardpoint { position, normal, offset }
+    }
+
     pub fn apply_transform(&mut self, matrix: &TMat4<f32>) {
         self.position = matrix * self.position;
-        let matrix = mat4_rotation_only(&matrix);
-        self.normal = (&matrix * self.normal.0).try_into().unwrap();
+        self.normal.apply_rotation(matrix);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum WeaponKind {
+    Primary,
+    Secondary,
+}
+impl WeaponKind {
+    pub fn from_primary(primary: bool) -> Self {
+        if primary {
+            WeaponKind::Primary
+        } else {
+            WeaponKind::Secondary
+        }
+    }
+
+    pub fn is_primary(self) -> bool {
+        self == WeaponKind::Primary
+    }
+}
+
+// a weapon point by its bank and index in the bank, which stays valid as long as points aren't added, removed or reordered
+// orders primaries before secondaries, then by bank and point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WeaponPointRef {
+    pub kind: WeaponKind,
+    pub bank: u16,
+    pub point: u16,
+}
+impl WeaponPointRef {
+    pub fn new(kind: WeaponKind, bank: usize, point: usize) -> Self {
+        WeaponPointRef { kind, bank: bank as u16, point: point as u16 }
     }
 }
 
@@ -848,8 +1096,7 @@ impl ThrusterGlow {
         let scalar = matrix.determinant().abs().powf(1. / 3.);
         self.radius *= scalar;
 
-        let matrix = mat4_rotation_only(&matrix);
-        self.normal = (&matrix * self.normal.0).try_into().unwrap();
+        self.normal.apply_rotation(matrix);
     }
 }
 
@@ -898,7 +1145,69 @@ impl GlowPoint {
     }
 }
 
-#[derive(Debug, Default)]
+// the header's obj_flags bitfield
+// only the flags the engine reads from the file are named here, any other bits are left untouched so they survive a round trip
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct HeaderFlags(pub u32);
+impl HeaderFlags {
+    pub const NONE: Self = Self(0);
+    // allows textures to tile, i.e. uvs outside of 0-1
+    pub const ALLOW_TILING: Self = Self(1 << 0);
+    // the model has autocentering info; FSO sets this itself when it finds an ACEN chunk
+    pub const AUTOCENTER: Self = Self(1 << 1);
+
+    pub const KNOWN: [(Self, &'static str); 2] = [(Self::ALLOW_TILING, "allow tiling"), (Self::AUTOCENTER, "autocenter")];
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn contains(self, flags: Self) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    pub fn set(&mut self, flags: Self, value: bool) {
+        if value {
+            self.0 |= flags.0;
+        } else {
+            self.0 &= !flags.0;
+        }
+    }
+
+    // any bits set which aren't one of the known flags
+    pub fn unknown_bits(self) -> u32 {
+        Self::KNOWN.iter().fold(self.0, |bits, (flag, _)| bits & !flag.0)
+    }
+
+    // the names of the known flags which are set, in the order of KNOWN
+    pub fn names(self) -> Vec<&'static str> {
+        (Self::KNOWN.iter())
+            .filter(|(flag, _)| self.contains(*flag))
+            .map(|&(_, name)| name)
+            .collect()
+    }
+}
+impl std::ops::BitOr for HeaderFlags {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+impl Debug for HeaderFlags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut list = f.debug_list();
+        for name in self.names() {
+            list.entry(&format_args!("{}", name));
+        }
+        if self.unknown_bits() != 0 {
+            list.entry(&format_args!("{:#x}", self.unknown_bits()));
+        }
+        list.finish()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct ObjHeader {
     pub max_radius: f32,
     pub obj_flags: u32,
@@ -912,7 +1221,18 @@ pub struct ObjHeader {
     pub bsp_lights: Vec<BspLight>,
 }
 
-#[derive(Debug)]
+impl ObjHeader {
+    pub fn flags(&self) -> HeaderFlags {
+        HeaderFlags(self.obj_flags)
+    }
+
+    // replaces the whole bitfield, to change a single flag use flags() and set() so unknown bits are kept
+    pub fn set_flags(&mut self, flags: HeaderFlags) {
+        self.obj_flags = flags.0;
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct ShieldData {
     pub verts: Vec<Vec3d>,
     pub polygons: Vec<ShieldPolygon>,
@@ -920,6 +1240,11 @@ pub struct ShieldData {
 }
 impl ShieldData {
     pub fn recalculate_tree(verts: &[Vec3d], polygons: &[ShieldPolygon]) -> ShieldNode {
+        Self::recalculate_tree_with(verts, polygons, &TolerancePolicy::default())
+    }
+
+    pub fn recalculate_tree_with(verts: &[Vec3d], polygons: &[ShieldPolygon], policy: &TolerancePolicy) -> ShieldNode {
+        let padding = policy.tree_padding;
         // these structs make up the smallest bits of data we'll need for this
         // the regular data structure isn't well-optimized for this, so its easier to make something purpose built
         struct ShieldPolyInfo {
@@ -943,26 +1268,26 @@ impl ShieldData {
 
                 ShieldPolyInfo {
                     id: PolygonId(i as u32),
-                    bbox: BoundingBox::from_vectors(vert_iter.clone()).pad(0.01),
+                    bbox: BoundingBox::from_vectors(vert_iter.clone()).pad(padding),
                     center: Vec3d::average(vert_iter),
                 }
             })
             .collect::<Vec<_>>();
 
-        fn recalc_recurse(poly_infos: &mut [&ShieldPolyInfo]) -> ShieldNode {
+        fn recalc_recurse(poly_infos: &mut [&ShieldPolyInfo], padding: f32) -> ShieldNode {
             if let [poly_info] = *poly_infos {
                 // if theres only one polygon we're at the base case
                 ShieldNode::Leaf { bbox: poly_info.bbox, poly_list: vec![poly_info.id] }
             } else {
-                let bbox = BoundingBox::from_bboxes(poly_infos.iter().map(|poly_info| &poly_info.bbox)).pad(0.01);
+                let bbox = BoundingBox::from_bboxes(poly_infos.iter().map(|poly_info| &poly_info.bbox)).pad(padding);
                 let axis = bbox.greatest_dimension();
                 poly_infos.sort_by(|a, b| a.center[axis].partial_cmp(&b.center[axis]).unwrap());
 
                 let halfpoint = poly_infos.len() / 2;
 
                 ShieldNode::Split {
-                    front: Box::new(recalc_recurse(&mut poly_infos[..halfpoint])),
-                    back: Box::new(recalc_recurse(&mut poly_infos[halfpoint..])),
+                    front: Box::new(recalc_recurse(&mut poly_infos[..halfpoint], padding)),
+                    back: Box::new(recalc_recurse(&mut poly_infos[halfpoint..], padding)),
                     bbox,
                 }
             }
@@ -971,20 +1296,27 @@ impl ShieldData {
         if poly_infos.is_empty() {
             ShieldNode::Leaf { bbox: BoundingBox::default(), poly_list: vec![] }
         } else {
-            recalc_recurse(&mut poly_infos.iter().collect::<Vec<_>>())
+            recalc_recurse(&mut poly_infos.iter().collect::<Vec<_>>(), padding)
         }
     }
 
-    pub fn recalculate_bboxes(&mut self) {
+    // max_depth is usually Model::bsp_depth_limit
+    pub fn recalculate_bboxes(&mut self, max_depth: u32) -> Result<(), BspTooDeep> {
         if let Some(tree) = &mut self.collision_tree {
-            recalculate_bboxes_recurse(&self.verts, &self.polygons, tree);
+            recalculate_bboxes_recurse(&self.verts, &self.polygons, tree, 1, max_depth)?;
         }
+        return Ok(());
 
-        fn recalculate_bboxes_recurse(verts: &[Vec3d], polygons: &[ShieldPolygon], node: &mut ShieldNode) {
+        fn recalculate_bboxes_recurse(
+            verts: &[Vec3d], polygons: &[ShieldPolygon], node: &mut ShieldNode, depth: u32, max_depth: u32,
+        ) -> Result<(), BspTooDeep> {
+            if depth > max_depth {
+                return Err(BspTooDeep { max_depth });
+            }
             match node {
                 ShieldNode::Split { bbox, front, back } => {
-                    recalculate_bboxes_recurse(verts, polygons, front);
-                    recalculate_bboxes_recurse(verts, polygons, back);
+                    recalculate_bboxes_recurse(verts, polygons, front, depth + 1, max_depth)?;
+                    recalculate_bboxes_recurse(verts, polygons, back, depth + 1, max_depth)?;
 
                     *bbox = BoundingBox::EMPTY;
                     let child_bbox = match **front {
@@ -1007,10 +1339,21 @@ impl ShieldData {
                     }
                 }
             }
+            Ok(())
         }
     }
 
-    pub fn apply_transform(&mut self, matrix: &TMat4<f32>) {
+    // a tree deeper than MAX_BSP_DEPTH can't have its bboxes recalculated, so it is an error and nothing is transformed
+    pub fn apply_transform(&mut self, matrix: &TMat4<f32>) -> Result<(), BspTooDeep> {
+        self.apply_transform_with(matrix, MAX_BSP_DEPTH)
+    }
+
+    // max_depth is usually Model::bsp_depth_limit
+    pub fn apply_transform_with(&mut self, matrix: &TMat4<f32>, max_depth: u32) -> Result<(), BspTooDeep> {
+        if self.collision_tree.as_ref().map_or(false, |tree| tree.depth() > max_depth) {
+            return Err(BspTooDeep { max_depth });
+        }
+
         for vert in &mut self.verts {
             *vert = matrix * *vert;
         }
@@ -1020,7 +1363,7 @@ impl ShieldData {
             poly.normal = &matrix * poly.normal;
         }
 
-        self.recalculate_bboxes();
+        self.recalculate_bboxes(max_depth)
     }
 }
 
@@ -1034,12 +1377,25 @@ pub struct Polygon {
     pub verts: Vec<PolyVertex>,
 }
 
+// a polygon as index lists into BspData's shared verts and norms, see BspData::to_indexed_mesh
+#[derive(Clone, Debug)]
+pub struct IndexedPoly {
+    pub normal: Vec3d,
+    pub texture: TextureId,
+    pub vertex_ids: Vec<u32>,
+    pub normal_ids: Vec<u32>,
+    pub uvs: Vec<(f32, f32)>,
+}
+
 #[derive(Debug, Clone)]
 pub enum BspNode {
     Split {
         bbox: BoundingBox,
         front: Box<BspNode>,
         back: Box<BspNode>,
+        // how many leaves are under it, so a leaf's id can be known without walking the branches before it
+        // trees are only ever rebuilt whole, through BspNode::split, which keeps it right
+        num_leaves: u32,
     },
     Leaf {
         bbox: BoundingBox,
@@ -1064,6 +1420,19 @@ impl BspNode {
         }
     }
 
+    pub fn split(bbox: BoundingBox, front: Box<BspNode>, back: Box<BspNode>) -> BspNode {
+        let num_leaves = front.num_leaves() + back.num_leaves();
+        BspNode::Split { bbox, front, back, num_leaves }
+    }
+
+    pub fn num_leaves(&self) -> u32 {
+        match self {
+            BspNode::Split { num_leaves, .. } => *num_leaves,
+            BspNode::Leaf { .. } => 1,
+            BspNode::Empty => 0,
+        }
+    }
+
     pub fn leaves(&self) -> BspNodeIter<'_> {
         BspNodeIter { stack: vec![self] }
     }
@@ -1072,6 +1441,56 @@ impl BspNode {
         BspNodeIntoIter { stack: vec![Box::new(self)] }
     }
 
+    // the nearest polygon the ray hits, from either side, and the distance along direction, which isn't normalized, to it
+    // the polygon id is its index in leaf order, as from leaves()
+    // a pof's splitting planes aren't kept once it's parsed, so instead the child whose bbox the ray reaches first is tried
+    // first, and the other is skipped if the ray only reaches it past a hit already found
+    pub fn ray_intersect(&self, origin: Vec3d, direction: Vec3d, verts: &[Vec3d]) -> Option<(f32, PolygonId)> {
+        let mut best = None;
+        ray_intersect_recurse(self, origin, direction, verts, 0, &mut best);
+        return best;
+
+        // first_id is the id of the first leaf under the node
+        fn ray_intersect_recurse(node: &BspNode, origin: Vec3d, dir: Vec3d, verts: &[Vec3d], first_id: u32, best: &mut Option<(f32, PolygonId)>) {
+            let beaten = |entry: Option<f32>, best: &Option<(f32, PolygonId)>| match (entry, best) {
+                (None, _) => true,
+                (Some(entry), Some((distance, _))) => entry > *distance,
+                (Some(_), None) => false,
+            };
+            match node {
+                BspNode::Split { front, back, .. } => {
+                    let (front_entry, back_entry) = (ray_bbox_entry(origin, dir, front.bbox()), ray_bbox_entry(origin, dir, back.bbox()));
+                    let back_first = matches!((front_entry, back_entry), (Some(front), Some(back)) if back < front) || front_entry.is_none();
+                    let mut children = [(&**front, front_entry, first_id), (&**back, back_entry, first_id + front.num_leaves())];
+                    if back_first {
+                        children.swap(0, 1);
+                    }
+                    for (child, entry, child_first_id) in children {
+                        if !beaten(entry, best) {
+                            ray_intersect_recurse(child, origin, dir, verts, child_first_id, best);
+                        }
+                    }
+                }
+                BspNode::Leaf { bbox, poly } => {
+                    if !beaten(ray_bbox_entry(origin, dir, bbox), best) {
+                        if let [first, rest @ ..] = &*poly.verts {
+                            for pair in rest.windows(2) {
+                                let tri = [first, &pair[0], &pair[1]].map(|vert| verts[vert.vertex_id.0 as usize]);
+                                // a zero area triangle, or one the ray runs parallel to, is never hit
+                                if let Some(distance) = ray_triangle_distance(origin, dir, tri) {
+                                    if !matches!(best, Some((best_distance, _)) if *best_distance <= distance) {
+                                        *best = Some((distance, PolygonId(first_id)));
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+                BspNode::Empty => {}
+            }
+        }
+    }
+
     pub fn sum_of_bboxes(&self) -> f32 {
         match self {
             BspNode::Split { bbox, front, back, .. } => bbox.volume() + front.sum_of_bboxes() + back.sum_of_bboxes(),
@@ -1092,19 +1511,45 @@ impl BspNode {
         }
     }
 
-    pub fn recalculate_bboxes(&mut self, verts: &[Vec3d]) {
-        match self {
-            BspNode::Split { bbox, front, back, .. } => {
-                front.recalculate_bboxes(verts);
-                back.recalculate_bboxes(verts);
-                *bbox = *front.bbox();
-                bbox.expand_bbox(back.bbox());
+    // max_depth is usually Model::bsp_depth_limit
+    pub fn recalculate_bboxes(&mut self, verts: &[Vec3d], max_depth: u32) -> Result<(), BspTooDeep> {
+        return recalculate_bboxes_recurse(self, verts, 1, max_depth);
+
+        fn recalculate_bboxes_recurse(node: &mut BspNode, verts: &[Vec3d], depth: u32, max_depth: u32) -> Result<(), BspTooDeep> {
+            if depth > max_depth {
+                return Err(BspTooDeep { max_depth });
+            }
+            match node {
+                BspNode::Split { bbox, front, back, .. } => {
+                    recalculate_bboxes_recurse(front, verts, depth + 1, max_depth)?;
+                    recalculate_bboxes_recurse(back, verts, depth + 1, max_depth)?;
+                    *bbox = *front.bbox();
+                    bbox.expand_bbox(back.bbox());
+                }
+                BspNode::Leaf { bbox, poly } => {
+                    *bbox = BoundingBox::from_vectors(poly.verts.iter().map(|vert| verts[vert.vertex_id.0 as usize]));
+                }
+                BspNode::Empty => {}
             }
-            BspNode::Leaf { bbox, poly } => {
-                *bbox = BoundingBox::from_vectors(poly.verts.iter().map(|vert| verts[vert.vertex_id.0 as usize]));
+            Ok(())
+        }
+    }
+
+    // the number of nodes on the longest path from here to a leaf
+    pub fn depth(&self) -> u32 {
+        let mut deepest = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            match node {
+                BspNode::Split { front, back, .. } => {
+                    stack.push((front, depth + 1));
+                    stack.push((back, depth + 1));
+                }
+                BspNode::Leaf { .. } => deepest = deepest.max(depth),
+                BspNode::Empty => {}
             }
-            BspNode::Empty => {}
         }
+        deepest
     }
 }
 
@@ -1177,6 +1622,21 @@ impl Iterator for BspNodeIntoIter {
     }
 }
 
+// legitimate trees are close to balanced, so only a few dozen deep, a tree deeper than this is malformed,
+// and walking it recursively risks overflowing the stack; this much fits comfortably in a 2MB thread stack, even in debug builds
+pub const MAX_BSP_DEPTH: u32 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BspTooDeep {
+    pub max_depth: u32,
+}
+impl Display for BspTooDeep {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "bsp tree is deeper than {} nodes", self.max_depth)
+    }
+}
+impl std::error::Error for BspTooDeep {}
+
 #[derive(Debug, Clone, Default)]
 pub struct BspData {
     pub verts: Vec<Vec3d>,
@@ -1194,9 +1654,88 @@ impl BspData {
     pub(crate) const SORTNORM2: u32 = 7;
 }
 impl BspData {
+    // the verts, the norms, and the polygons in leaf order as indices into them, for exporters and renderers
+    // which would rather have an index buffer than a tree
+    pub fn to_indexed_mesh(&self) -> (Vec<Vec3d>, Vec<Vec3d>, Vec<IndexedPoly>) {
+        let polys = (self.collision_tree.leaves())
+            .map(|(_, poly)| IndexedPoly {
+                normal: poly.normal,
+                texture: poly.texture,
+                vertex_ids: poly.verts.iter().map(|vert| vert.vertex_id.0).collect(),
+                normal_ids: poly.verts.iter().map(|vert| vert.normal_id.0).collect(),
+                uvs: poly.verts.iter().map(|vert| vert.uv).collect(),
+            })
+            .collect();
+        (self.verts.clone(), self.norms.clone(), polys)
+    }
+
+    // the edges the stored normals make hard: those where the polygons on either side use normals at a shared vertex
+    // more than angle_tolerance_deg apart; open edges, with a polygon on only one side, are never sharp
+    // each edge is listed once, lowest vertex id first, in sorted order
+    pub fn infer_sharp_edges(&self, angle_tolerance_deg: f32) -> Vec<(VertexId, VertexId)> {
+        let min_cos = angle_tolerance_deg.to_radians().cos();
+        let normal = |id: NormalId| self.norms.get(id.0 as usize).copied().unwrap_or_default();
+        let smooth = |a: NormalId, b: NormalId| a == b || normal(a).normalize().dot(&normal(b).normalize()) >= min_cos;
+
+        // for each edge, the normals each polygon using it has at its lower and higher vertex
+        let mut edges: HashMap<(VertexId, VertexId), Vec<(NormalId, NormalId)>> = HashMap::new();
+        for (_, poly) in self.collision_tree.leaves() {
+            for (i, a) in poly.verts.iter().enumerate() {
+                let b = &poly.verts[(i + 1) % poly.verts.len()];
+                let entry = if a.vertex_id <= b.vertex_id {
+                    ((a.vertex_id, b.vertex_id), (a.normal_id, b.normal_id))
+                } else {
+                    ((b.vertex_id, a.vertex_id), (b.normal_id, a.normal_id))
+                };
+                edges.entry(entry.0).or_default().push(entry.1);
+            }
+        }
+
+        let mut sharp = (edges.into_iter())
+            .filter(|(_, sides)| (sides.iter().tuple_combinations()).any(|(&(a1, b1), &(a2, b2))| !smooth(a1, a2) || !smooth(b1, b2)))
+            .map(|(edge, _)| edge)
+            .collect::<Vec<_>>();
+        sharp.sort_unstable();
+        sharp
+    }
+
     pub fn recalculate(verts: &[Vec3d], polygons: impl Iterator<Item = Polygon>) -> BspNode {
-        // first go over the polygons, filling some data, and exporting their bboxes and centers, which is important for the actual BSP generation
-        let polygons = polygons
+        Self::recalculate_with(verts, polygons, &TolerancePolicy::default())
+    }
+
+    pub fn recalculate_with(verts: &[Vec3d], polygons: impl Iterator<Item = Polygon>, policy: &TolerancePolicy) -> BspNode {
+        let padding = policy.tree_padding;
+        let polygons = Self::prepare_polygons(verts, polygons, padding);
+
+        fn recalc_recurse(polygons: &mut [&(Vec3d, BoundingBox, Polygon)], padding: f32) -> BspNode {
+            if let [&(_, bbox, ref polygon)] = *polygons {
+                // if there's only one polygon we're at the base case
+                BspNode::Leaf { bbox, poly: polygon.clone() }
+            } else {
+                let bbox = BoundingBox::from_bboxes(polygons.iter().map(|(_, bbox, _)| bbox)).pad(padding);
+                let axis = bbox.greatest_dimension();
+                polygons.sort_by(|a, b| a.0[axis].partial_cmp(&b.0[axis]).unwrap());
+
+                let halfpoint = polygons.len() / 2;
+
+                BspNode::split(
+                    bbox,
+                    Box::new(recalc_recurse(&mut polygons[..halfpoint], padding)),
+                    Box::new(recalc_recurse(&mut polygons[halfpoint..], padding)),
+                )
+            }
+        }
+
+        if polygons.is_empty() {
+            BspNode::Empty
+        } else {
+            recalc_recurse(&mut polygons.iter().collect::<Vec<_>>(), padding)
+        }
+    }
+
+    // fills in the polygons' normals, and pairs them with their centers and padded bboxes, which is what the tree builders work from
+    pub(crate) fn prepare_polygons(verts: &[Vec3d], polygons: impl Iterator<Item = Polygon>, padding: f32) -> Vec<(Vec3d, BoundingBox, Polygon)> {
+        polygons
             .map(|mut poly| {
                 let vert_iter = poly.verts.iter().map(|polyvert| verts[polyvert.vertex_id.0 as usize]);
 
@@ -1219,36 +1758,12 @@ impl BspData {
                             .map(|verts| (verts[0] - verts[1]).cross(&(verts[1] - verts[2])).into()), // ...and then get the cross from each triple, and average them
                     )
                 }
-                .normalize(); // and then normalize
+                .try_into() // and then normalize, degenerate polygons have no normal
+                .map_or(Vec3d::ZERO, |normal: NormalVec3| normal.0);
 
-                (Vec3d::average(vert_iter.clone()), BoundingBox::from_vectors(vert_iter).pad(0.01), poly)
+                (Vec3d::average(vert_iter.clone()), BoundingBox::from_vectors(vert_iter).pad(padding), poly)
             })
-            .collect::<Vec<_>>();
-
-        fn recalc_recurse(polygons: &mut [&(Vec3d, BoundingBox, Polygon)]) -> BspNode {
-            if let [&(_, bbox, ref polygon)] = *polygons {
-                // if there's only one polygon we're at the base case
-                BspNode::Leaf { bbox, poly: polygon.clone() }
-            } else {
-                let bbox = BoundingBox::from_bboxes(polygons.iter().map(|(_, bbox, _)| bbox)).pad(0.01);
-                let axis = bbox.greatest_dimension();
-                polygons.sort_by(|a, b| a.0[axis].partial_cmp(&b.0[axis]).unwrap());
-
-                let halfpoint = polygons.len() / 2;
-
-                BspNode::Split {
-                    front: Box::new(recalc_recurse(&mut polygons[..halfpoint])),
-                    back: Box::new(recalc_recurse(&mut polygons[halfpoint..])),
-                    bbox,
-                }
-            }
-        }
-
-        if polygons.is_empty() {
-            BspNode::Empty
-        } else {
-            recalc_recurse(&mut polygons.iter().collect::<Vec<_>>())
-        }
+            .collect()
     }
 }
 impl Serialize for BspData {
@@ -1445,6 +1960,11 @@ impl SubObject {
         self.children.iter()
     }
 
+    // debris is recognized by name on import, so the two should agree
+    pub fn debris_name_mismatch(&self) -> bool {
+        self.name.starts_with("debris") != self.is_debris_model
+    }
+
     pub fn is_destroyed_model(&self) -> bool {
         for link in &self.name_links {
             if matches!(link, NameLink::DestroyedVersionOf(_)) {
@@ -1455,7 +1975,11 @@ impl SubObject {
     }
 
     pub fn recalc_radius(&mut self) {
-        self.radius = 0.00001;
+        self.recalc_radius_with(&TolerancePolicy::default())
+    }
+
+    pub fn recalc_radius_with(&mut self, policy: &TolerancePolicy) {
+        self.radius = policy.min_extent;
 
         for vert in &self.bsp_data.verts {
             if vert.magnitude() > self.radius {
@@ -1465,6 +1989,16 @@ impl SubObject {
     }
 
     pub fn recalc_bbox(&mut self) {
+        self.recalc_bbox_with(&TolerancePolicy::default())
+    }
+
+    pub fn recalc_bbox_with(&mut self, policy: &TolerancePolicy) {
+        // no geometry gets the same epsilon box as an empty model
+        if self.bsp_data.verts.is_empty() {
+            self.bbox = BoundingBox::ZERO.pad(policy.min_extent);
+            return;
+        }
+
         self.bbox.min = self.bsp_data.verts[0];
         self.bbox.max = self.bsp_data.verts[0];
 
@@ -1502,22 +2036,92 @@ impl SubObject {
         properties_get_field(&self.properties, "$special") == Some("subsystem")
     }
 
+    // fso skips collision for "$no_collisions" subobjects (and their children),
+    // and for the subobject's own geometry only with "$nocollide_this_only"
+    pub fn collides(&self) -> bool {
+        properties_find_field(&self.properties, "$no_collisions").is_none()
+            && properties_find_field(&self.properties, "$nocollide_this_only").is_none()
+    }
+
+    // a hash of the subobject's geometry, for cheaply telling if two subobjects have identical geometry
+    // this hashes the polygons by value, so it doesn't depend on the offset, the BSP tree's shape, or the order of the vertex lists
+    // it's FNV-1a, so it is stable between runs
+    pub fn geometry_fingerprint(&self) -> u64 {
+        fn fnv1a(hash: &mut u64, val: u32) {
+            for byte in val.to_le_bytes() {
+                *hash = (*hash ^ byte as u64).wrapping_mul(0x100000001b3);
+            }
+        }
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+        let mut poly_hashes = vec![];
+        for (_, poly) in self.bsp_data.collision_tree.leaves() {
+            let mut hash = FNV_OFFSET;
+            fnv1a(&mut hash, poly.texture.0);
+            fnv1a(&mut hash, poly.verts.len() as u32);
+            for vert in &poly.verts {
+                let pos = self.bsp_data.verts[vert.vertex_id.0 as usize];
+                let norm = self.bsp_data.norms[vert.normal_id.0 as usize];
+                for val in [pos.x, pos.y, pos.z, norm.x, norm.y, norm.z, vert.uv.0, vert.uv.1] {
+                    fnv1a(&mut hash, val.to_bits());
+                }
+            }
+            poly_hashes.push(hash);
+        }
+        poly_hashes.sort_unstable();
+
+        let mut hash = FNV_OFFSET;
+        for poly_hash in poly_hashes {
+            fnv1a(&mut hash, poly_hash as u32);
+            fnv1a(&mut hash, (poly_hash >> 32) as u32);
+        }
+        hash
+    }
+
+    // each polygon's id (its index in BSP leaf order), centroid, normal and area
+    pub fn poly_metrics(&self) -> impl Iterator<Item = (PolygonId, Vec3d, Vec3d, f32)> + '_ {
+        self.bsp_data.collision_tree.leaves().enumerate().map(|(i, (_, poly))| {
+            let vert = |poly_vert: &PolyVertex| self.bsp_data.verts[poly_vert.vertex_id.0 as usize];
+
+            // area weighted centroid of the triangle fan
+            let mut area = 0.0;
+            let mut weighted_center = Vec3d::ZERO;
+            if let [first, rest @ ..] = &*poly.verts {
+                let v1 = vert(first);
+                for pair in rest.windows(2) {
+                    let (v2, v3) = (vert(&pair[0]), vert(&pair[1]));
+                    let tri_area = (v2 - v1).cross(&(v3 - v1)).magnitude() / 2.0;
+                    weighted_center += (v1 + v2 + v3) * (tri_area / 3.0);
+                    area += tri_area;
+                }
+            }
+
+            let center = if area > 0.0 {
+                weighted_center / area
+            } else {
+                Vec3d::average(poly.verts.iter().map(vert))
+            };
+            (PolygonId(i as u32), center, poly.normal, area)
+        })
+    }
+
     /// returns the surface area of the subobject, and the average surface area position
     pub fn surface_area_average_pos(&self) -> (f32, Vec3d) {
-        let mut surface_area = 0.0;
-        let mut weighted_sum = Vec3d::ZERO;
-        for (_, poly) in self.bsp_data.collision_tree.leaves() {
-            let v1 = self.bsp_data.verts[poly.verts[0].vertex_id.0 as usize];
-            let v2 = self.bsp_data.verts[poly.verts[1].vertex_id.0 as usize];
-            let v3 = self.bsp_data.verts[poly.verts[2].vertex_id.0 as usize];
-
-            let v12 = v2 - v1;
-            let v13 = v3 - v1;
-            let this_area = v12.cross(&v13).magnitude();
-            weighted_sum += Vec3d::average([v1, v2, v3].into_iter()) * this_area;
+        // f64 accumulators, since these sums run over every polygon
+        let mut surface_area = 0.0_f64;
+        let mut weighted_sum = [0.0_f64; 3];
+        for (_, center, _, this_area) in self.poly_metrics() {
+            let this_area = this_area as f64;
+            weighted_sum[0] += center.x as f64 * this_area;
+            weighted_sum[1] += center.y as f64 * this_area;
+            weighted_sum[2] += center.z as f64 * this_area;
             surface_area += this_area;
         }
-        (surface_area, weighted_sum / surface_area)
+        if surface_area == 0.0 {
+            return (0.0, Vec3d::ZERO);
+        }
+        let [x, y, z] = weighted_sum.map(|val| (val / surface_area) as f32);
+        (surface_area as f32, Vec3d { x, y, z })
     }
 }
 
@@ -1582,15 +2186,115 @@ impl Serialize for Dock {
             None => 0_u32.write_to(w)?,
             Some(x) => [x].write_to(w)?,
         }
+        let (p1, p2) = self.to_two_points();
         let points = vec![
-            DockingPoint { position: self.position - self.uvec.0, normal: self.fvec.0 },
-            DockingPoint { position: self.position + self.uvec.0, normal: self.fvec.0 },
+            DockingPoint { position: p1, normal: self.fvec.0 },
+            DockingPoint { position: p2, normal: self.fvec.0 },
         ];
         points.write_to(w)
     }
 }
+
+// normalizing an already normalized vector can still nudge it by a bit, so vectors that are already as close to
+// unit length as floats get are left alone
+fn settled_unit(vec: Vec3d) -> Option<NormalVec3> {
+    if (vec.magnitude() - 1.0).abs() <= 4.0 * f32::EPSILON {
+        Some(NormalVec3(vec))
+    } else {
+        vec.try_into().ok()
+    }
+}
+
 impl Dock {
-    #[must_use]
+    // the way the parser builds a dock from its two docking points: the position between them, and the uvec along them,
+    // made unit length and perpendicular to the fvec
+    pub fn from_two_points(p1: Vec3d, p2: Vec3d, normal: Vec3d) -> Dock {
+        Self::from_two_points_audited(p1, p2, normal).0
+    }
+
+    // also returns what was wrong with the fvec and uvec the points implied
+    pub(crate) fn from_two_points_audited(p1: Vec3d, p2: Vec3d, normal: Vec3d) -> (Dock, Option<NormalIssue>, Option<NormalIssue>) {
+        let fvec_issue = NormalVec3::sanitize(normal, NormalVec3::default()).1;
+        let uvec_issue = Self::uvec_issue((p2 - p1) / 2.0, settled_unit(normal).unwrap_or_default().0);
+
+        let (position, fvec, uvec) = Self::frame(p1, p2, normal);
+        let mut dock = Dock { position, fvec, uvec, ..Default::default() };
+        dock.settle();
+        (dock, fvec_issue, uvec_issue)
+    }
+
+    fn uvec_issue(uvec: Vec3d, fvec: Vec3d) -> Option<NormalIssue> {
+        match NormalVec3::try_from(uvec) {
+            Err(()) => Some(NormalIssue::Zero),
+            Ok(_) if (uvec.magnitude() - 1.0).abs() > 0.001 => Some(NormalIssue::NotUnitLength),
+            Ok(normal) if normal.0.dot(&fvec).abs() > 0.001 => Some(NormalIssue::NotOrthogonal),
+            Ok(_) => None,
+        }
+    }
+
+    // the points this is written as, see from_two_points
+    pub fn to_two_points(&self) -> (Vec3d, Vec3d) {
+        (self.position - self.uvec.0, self.position + self.uvec.0)
+    }
+
+    // position, fvec and uvec from the docking points, leaving alone what's already as exact as floats get
+    // the uvec can only be as exact as the points it's stored in, which lose precision the further they are from the origin
+    fn frame(p1: Vec3d, p2: Vec3d, normal: Vec3d) -> (Vec3d, NormalVec3, NormalVec3) {
+        let fvec = settled_unit(normal).unwrap_or_default();
+        let (position, half) = ((p1 + p2) / 2.0, (p2 - p1) / 2.0);
+        let tolerance = 8.0 * f32::EPSILON * (1.0 + position.x.abs().max(position.y.abs()).max(position.z.abs()));
+        let uvec = if (half.magnitude() - 1.0).abs() <= tolerance && half.dot(&fvec.0).abs() <= tolerance {
+            NormalVec3(half)
+        } else {
+            Self::orthonormalize(&half.into(), &fvec.0.into())
+        };
+        (position, fvec, uvec)
+    }
+
+    // writing and reading a dock back isn't exact, the midpoint and normalization can each move things by a bit,
+    // so this repeats it until it stops changing, which makes every later save and load leave the dock bitwise identical
+    fn settle(&mut self) {
+        for _ in 0..8 {
+            let (p1, p2) = self.to_two_points();
+            let (position, fvec, uvec) = Self::frame(p1, p2, self.fvec.0);
+            if position == self.position && fvec.0 == self.fvec.0 && uvec.0 == self.uvec.0 {
+                return;
+            }
+            (self.position, self.fvec, self.uvec) = (position, fvec, uvec);
+        }
+    }
+
+    // whether the dock will be saved and loaded without changing: unit length fvec and uvec, perpendicular to each other
+    pub fn is_canonical(&self) -> bool {
+        let (p1, p2) = self.to_two_points();
+        let dock = Self::from_two_points(p1, p2, self.fvec.0);
+        dock.position == self.position && dock.fvec.0 == self.fvec.0 && dock.uvec.0 == self.uvec.0
+    }
+
+    // makes the dock canonical, keeping its fvec direction and as much of its uvec as is perpendicular to that
+    pub fn canonicalize(&mut self) {
+        self.fvec = NormalVec3::sanitize(self.fvec.0, NormalVec3::default()).0;
+        self.uvec = Self::orthonormalize(&self.uvec.0.into(), &self.fvec.0.into());
+        self.settle();
+    }
+
+    // makes the fvec unit length and the uvec unit length and perpendicular to it, without moving the dock like canonicalize can
+    // a zero fvec falls back to the default dock's, the angle calculations can't do anything with it
+    // returns what was wrong with the fvec and uvec
+    pub fn sanitize(&mut self) -> (Option<NormalIssue>, Option<NormalIssue>) {
+        // only what needs fixing is touched, so a canonical dock stays bitwise identical
+        let (fvec, fvec_issue) = NormalVec3::sanitize(self.fvec.0, Dock::default().fvec);
+        if fvec_issue.is_some() {
+            self.fvec = fvec;
+        }
+        let uvec_issue = Self::uvec_issue(self.uvec.0, self.fvec.0);
+        if uvec_issue.is_some() {
+            self.uvec = Self::orthonormalize(&self.uvec.0.into(), &self.fvec.0.into());
+        }
+        (fvec_issue, uvec_issue)
+    }
+
+    #[must_use]
     pub fn orthonormalize(&(mut xvec): &Vec3, fvec: &Vec3) -> NormalVec3 {
         xvec -= *fvec * xvec.dot(fvec);
         match xvec.try_into() {
@@ -1632,12 +2336,16 @@ impl Dock {
         properties_get_field(&self.properties, "$parent_submodel")
     }
 
+    // where an approach distance units out from the dock starts, and the direction to travel from there to reach it
+    pub fn approach_vector(&self, distance: f32) -> (Vec3d, Vec3d) {
+        (self.position - self.fvec.0 * distance, self.fvec.0)
+    }
+
     pub fn apply_transform(&mut self, matrix: &TMat4<f32>) {
         self.position = matrix * self.position;
 
-        let matrix = mat4_rotation_only(&matrix);
-        self.fvec = (&matrix * self.fvec.0).try_into().unwrap();
-        self.uvec = (&matrix * self.uvec.0).try_into().unwrap();
+        self.fvec.apply_rotation(matrix);
+        self.uvec.apply_rotation(matrix);
     }
 }
 
@@ -1682,8 +2390,7 @@ impl Turret {
             *point = &matrix * *point;
         }
 
-        let matrix = mat4_rotation_only(&matrix);
-        self.normal = (&matrix * self.normal.0).try_into().unwrap();
+        self.normal.apply_rotation(&matrix);
     }
 }
 
@@ -1828,15 +2535,388 @@ mk_versions! {
     V23_01(2301, "23.01"),
 }
 
+// a small deterministic rng, so anything random is reproducible for a given seed without needing a dependency
+// this is splitmix64
+pub(crate) struct SeededRng(u64);
+impl SeededRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // uniform in [0, 1)
+    pub(crate) fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+// how the dae exporter conveys shading
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DaeShading {
+    // the stored normals, on a mesh joined at every shared vertex
+    #[default]
+    ExplicitNormals,
+    // as well as the normals, the mesh is split along the edges BspData::infer_sharp_edges finds with this tolerance in degrees,
+    // so tools which recompute normals from the topology after editing keep the hard edges
+    SplitSharpEdges(f32),
+}
+
+// how polygons are broken up into triangles by exporters which support both
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Triangulation {
+    // each polygon becomes a fan of independent triangles
+    #[default]
+    Fan,
+    // triangles are joined into strips across shared edges, which is much more compact for long thin meshes
+    Strip,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VisualCenterMethod {
+    // the average position of the detail0 hierarchy's surface area
+    #[default]
+    SurfaceAreaAverage,
+    // the center of the header's bounding box
+    BBoxCenter,
+    // the header's center of mass
+    CenterOfMass,
+}
+
+// where the engine centers things like the target box, tech room and target brackets on a model
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TargetingInfo {
+    // the visual center if the ACEN chunk is written, which it is for any non-zero visual center, otherwise the origin
+    // the engine never falls back to the bbox center
+    pub center: Vec3d,
+    pub autocentered: bool,
+    pub bbox_center: Vec3d,
+    // the header radius, which the engine measures from the origin whether or not the model is autocentered
+    pub radius: f32,
+    // the radius around center that still encloses the header bbox, which is what's actually on screen
+    pub centered_radius: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleMode {
+    // samples are distributed over the whole surface by area
+    Uniform,
+    // each polygon gets samples in proportion to its area, but always at least one, so small polygons aren't missed
+    Stratified,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SurfaceSample {
+    // model space position, i.e. with the subobject's total offset applied
+    pub position: Vec3d,
+    pub normal: Vec3d,
+    pub subobj: ObjectId,
+    pub texture: TextureId,
+}
+impl SurfaceSample {
+    pub fn write_csv(samples: &[SurfaceSample], w: &mut impl Write) -> io::Result<()> {
+        writeln!(w, "x,y,z,nx,ny,nz,subobject,texture")?;
+        for sample in samples {
+            let Vec3d { x, y, z } = sample.position;
+            let Vec3d { x: nx, y: ny, z: nz } = sample.normal;
+            writeln!(w, "{},{},{},{},{},{},{},{}", x, y, z, nx, ny, nz, sample.subobj.0, sample.texture.0)?;
+        }
+        Ok(())
+    }
+}
+
+// the largest acceptable gap between adjacent vertex positions, in meters
+pub const DEFAULT_PRECISION_BUDGET: f32 = 1.0 / 1024.0;
+
+// the gap between adjacent f32 values around this magnitude
+pub fn f32_quantization(magnitude: f32) -> f32 {
+    // keeping only the exponent bits rounds down to a power of two
+    f32::from_bits(magnitude.abs().to_bits() & 0x7f80_0000) * f32::EPSILON
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OffCenterSubObject {
+    pub id: ObjectId,
+    pub quantization: f32,
+    // if the offset were moved to the center of its geometry
+    pub recentered_quantization: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PrecisionReport {
+    // the largest coordinate of any vertex, in model space
+    pub max_coordinate: f32,
+    // the coarsest gap between representable positions at that coordinate
+    pub worst_quantization: f32,
+    pub exceeds_budget: bool,
+    // subobjects whose vertices are needlessly far from their origin
+    pub off_center_subobjects: Vec<OffCenterSubObject>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LodDuplicate {
+    // the subobject from the higher detail level first
+    pub subobjs: (ObjectId, ObjectId),
+    pub detail_levels: (u32, u32),
+    pub num_verts: (usize, usize),
+    pub num_polys: (usize, usize),
+    // identical geometry, otherwise they have the same vert count and bbox size, which is very likely a near-copy
+    pub exact: bool,
+}
+
+// slab test, the distance along dir, which isn't normalized, to where the ray enters the bbox, 0 if it starts inside
+fn ray_bbox_entry(origin: Vec3d, dir: Vec3d, bbox: &BoundingBox) -> Option<f32> {
+    if bbox.is_inverted() {
+        return None;
+    }
+    let (mut entry, mut exit) = (0.0_f32, f32::INFINITY);
+    for axis in ALL_AXES {
+        let (origin, dir, min, max) = (origin[axis], dir[axis], bbox.min[axis], bbox.max[axis]);
+        if dir == 0.0 {
+            // parallel to the slab, so it's either always inside it or never
+            if origin < min || origin > max {
+                return None;
+            }
+            continue;
+        }
+        let (t1, t2) = ((min - origin) / dir, (max - origin) / dir);
+        entry = entry.max(t1.min(t2));
+        exit = exit.min(t1.max(t2));
+    }
+    (entry <= exit).then_some(entry)
+}
+
+// moller-trumbore, hits from either side; the distance along dir, which isn't normalized, to the hit
+pub(crate) fn ray_triangle_distance(origin: Vec3d, dir: Vec3d, [v1, v2, v3]: [Vec3d; 3]) -> Option<f32> {
+    let (edge1, edge2) = (v2 - v1, v3 - v1);
+    let p = dir.cross(&edge2);
+    let det = edge1.dot(&p);
+    if det.abs() < 1e-8 {
+        return None;
+    }
+    let t_vec = origin - v1;
+    let u = t_vec.dot(&p) / det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = t_vec.cross(&edge1);
+    let v = dir.dot(&q) / det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let distance = edge2.dot(&q) / det;
+    (distance >= 0.0).then_some(distance)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct RayHit {
+    pub distance: f32,
+    pub position: Vec3d,
+    pub subobj: ObjectId,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MergeError {
+    NoBanks,
+    InvalidBank(usize),
+    // the names of the fields which differ between the banks
+    FieldsDiffer(Vec<&'static str>),
+}
+
+// something which referred to a deleted subobject, by its index from before the delete
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LostReference {
+    // the later detail levels each moved up one
+    DetailLevel(usize),
+    // turrets and glow banks can't do without their subobjects, so they're deleted too
+    Turret(usize),
+    GlowBank(usize),
+    // these are detached, left in place in model space
+    EyePoint(usize),
+    Attachment(usize),
+    PathPointTurret { path: usize, point: usize },
+    // on the deleted detail level, and left on a detail level which doesn't exist, for InsigniaInvalidDetailLevel to flag
+    Insignia(usize),
+    // these refer to subobjects by name, and are left naming one that's gone, for the warnings to flag
+    DockParent(usize),
+    PathParent(usize),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DeleteReport {
+    // the deleted subobjects, by name, the one asked for first
+    pub deleted: Vec<String>,
+    // children moved up to the deleted subobject's parent, or to the top level if it had none, by name
+    pub reparented: Vec<String>,
+    pub lost: Vec<LostReference>,
+}
+
 #[derive(Debug, Default)]
+pub struct FlattenReport {
+    // the id of the flattened subobject, which may have changed after the merged ones were removed
+    pub root: ObjectId,
+    // the names of the subobjects which were merged into root, and no longer exist
+    pub merged: Vec<String>,
+    // kept subobjects which had a merged parent, and were moved directly under root
+    pub reparented: Vec<String>,
+    pub glow_banks_reattached: Vec<usize>,
+    pub eye_points_reattached: Vec<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FlattenError {
+    // the merged subobject would exceed the verts/norms allowed by the model's version
+    TooManyVerts { count: usize, max: usize },
+    TooManyNorms { count: usize, max: usize },
+    // a turret uses one of the subobjects which would be merged, it should be kept
+    TurretSubobjectMerged { turret: usize, id: ObjectId },
+    InvalidId(ObjectId),
+}
+impl Display for FlattenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlattenError::TooManyVerts { count, max } => write!(f, "flattened subobject would have {} vertices (max {})", count, max),
+            FlattenError::TooManyNorms { count, max } => write!(f, "flattened subobject would have {} normals (max {})", count, max),
+            FlattenError::TurretSubobjectMerged { turret, id } => write!(f, "turret {} uses subobject {:?}, which would be merged", turret, id),
+            FlattenError::InvalidId(id) => write!(f, "there is no subobject {}", id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertParentError {
+    NoChildren,
+    // the children must all have the same parent, or all have none
+    DifferentParents,
+    // a detail level can't be given a parent
+    DetailLevel(ObjectId),
+    NameInUse(String),
+    InvalidId(ObjectId),
+}
+impl Display for InsertParentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsertParentError::NoChildren => write!(f, "no subobjects were given to put under the new parent"),
+            InsertParentError::DifferentParents => write!(f, "the subobjects don't all have the same parent"),
+            InsertParentError::DetailLevel(id) => write!(f, "subobject {:?} is a detail level, which can't have a parent", id),
+            InsertParentError::NameInUse(name) => write!(f, "there is already a subobject named '{}'", name),
+            InsertParentError::InvalidId(id) => write!(f, "there is no subobject {}", id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReparentError {
+    NoDetail0,
+    // a detail level can't be given a parent
+    DetailLevel(ObjectId),
+    // detail0 is the subobject itself, or under it
+    WouldCycle,
+    InvalidId(ObjectId),
+}
+impl Display for ReparentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReparentError::NoDetail0 => write!(f, "there is no detail0 to put the subobject under"),
+            ReparentError::DetailLevel(id) => write!(f, "subobject {:?} is a detail level, which can't have a parent", id),
+            ReparentError::WouldCycle => write!(f, "detail0 is this subobject, or one of its children"),
+            ReparentError::InvalidId(id) => write!(f, "there is no subobject {}", id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrossSectionError {
+    InvalidIndex(usize),
+    // another cross section is already at that depth
+    DuplicateDepth(usize),
+}
+impl Display for CrossSectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CrossSectionError::InvalidIndex(idx) => write!(f, "there is no cross section {}", idx),
+            CrossSectionError::DuplicateDepth(idx) => write!(f, "cross section {} is already at that depth", idx),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReorderPathsError {
+    InvalidId(PathId),
+    ListedTwice(PathId),
+    // the new order has to list every path
+    Missing(PathId),
+}
+impl Display for ReorderPathsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReorderPathsError::InvalidId(id) => write!(f, "there is no path {}", id.0),
+            ReorderPathsError::ListedTwice(id) => write!(f, "path {} is listed more than once", id.0),
+            ReorderPathsError::Missing(id) => write!(f, "path {} isn't listed", id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsigniaError {
+    InvalidIndex(usize),
+    // there is no such detail level, so the engine would never draw the insignia
+    InvalidDetailLevel(u32),
+}
+impl Display for InsigniaError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InsigniaError::InvalidIndex(idx) => write!(f, "there is no insignia {}", idx),
+            InsigniaError::InvalidDetailLevel(lod) => write!(f, "there is no detail level {}", lod),
+        }
+    }
+}
+
+// one insignia, for listing them, see Model::insignia_summary
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InsigniaSummary {
+    pub detail_level: u32,
+    pub faces: usize,
+    pub area: f32,
+    // the area weighted center of the faces, with the offset applied
+    pub centroid: Vec3d,
+}
+
+// depths must be strictly increasing, and radii positive
+fn cross_sections_invalid(cross_sections: &[(f32, f32)]) -> bool {
+    cross_sections.iter().any(|&(_, radius)| radius <= 0.0) || cross_sections.windows(2).any(|pair| pair[0].0 >= pair[1].0)
+}
+
+// the size of one detail level's subtree, see Model::lod_stats
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LodStats {
+    pub root: ObjectId,
+    // polygons are counted as the triangles of a fan
+    pub triangles: u32,
+    pub vertices: u32,
+    // the furthest vertex from the model's origin
+    pub radius: f32,
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct Model {
     pub version: Version,
+    // the version of the pof this was loaded from, None if it wasn't
+    pub disk_version: Option<Version>,
     pub header: ObjHeader,
     pub sub_objects: ObjVec<SubObject>,
     pub textures: Vec<String>,
     pub paths: Vec<Path>,
     pub special_points: Vec<SpecialPoint>,
     pub eye_points: Vec<EyePoint>,
+    // see crate::attachments
+    pub attachments: Vec<AttachmentPoint>,
     pub primary_weps: Vec<Vec<WeaponHardpoint>>,
     pub secondary_weps: Vec<Vec<WeaponHardpoint>>,
     pub turrets: Vec<Turret>,
@@ -1850,80 +2930,152 @@ pub struct Model {
 
     pub path_to_file: PathBuf,
     pub untextured_idx: Option<TextureId>,
+    // which texture names are the untextured slot, kept from Parser::set_untextured_names for refresh_untextured_slot
+    pub untextured_names: UntexturedNames,
+    // overrides DEFAULT_PRECISION_BUDGET for Warning::PrecisionBudgetExceeded
+    pub precision_budget: Option<f32>,
+    // overrides MAX_BSP_DEPTH for Error::BspTooDeep, set from ParseOptions::max_bsp_depth on load
+    pub max_bsp_depth: Option<u32>,
+    // normals which had to be repaired, on load or by a transform
+    pub normal_notices: Vec<NormalAudit>,
+    // see crate::history
+    pub stat_history: Vec<StatSnapshot>,
+    // see crate::tolerance, set with set_tolerance_policy so the warnings follow
+    pub(crate) tolerance_policy: TolerancePolicy,
+    // see crate::texture_annotations, kept in step with the texture list by the texture operations there
+    pub(crate) texture_annotations: BTreeMap<TextureId, TextureAnnotation>,
+    // legacy tool quirks which were fixed on load, see crate::quirks
+    pub quirk_notices: Vec<QuirkNotice>,
+    // chunks whose lengths didn't add up on load, see crate::repair
+    pub parse_notices: Vec<ParseNotice>,
+    // loaded without bsp data, see ParseOptions::load_geometry
+    pub geometry_skipped: bool,
+    // see crate::geometry_cache
+    pub(crate) geometry_cache: GeometryCache,
     pub warnings: BTreeSet<Warning>,
     pub errors: BTreeSet<Error>,
 }
+// every query takes &self and nothing in a model uses interior mutability, so frontends can run analyses on one model
+// from several threads at once; this keeps it that way
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Model>();
+};
 impl Model {
-    // rechecks just one or all of the errors on the model
+    // rechecks just one or all of the errors on the model, storing the results in self.errors
     pub fn recheck_errors(&mut self, error_to_check: Set<Error>) {
         if let Set::One(error) = error_to_check {
-            let failed_check = match &error {
-                Error::InvalidTurretGunSubobject(turret) => self.turret_gun_subobj_not_valid(*turret),
-                Error::TooManyDebrisObjects => self.num_debris_objects() > MAX_DEBRIS_OBJECTS,
-                Error::DetailAndDebrisObj(id) => self.header.detail_levels.contains(&id) && self.sub_objects[*id].is_debris_model,
-                Error::DetailObjWithParent(id) => self.header.detail_levels.contains(&id) && self.sub_objects[*id].parent().is_some(),
-                Error::TooManyVerts(id) => self.sub_objects[*id].bsp_data.verts.len() > self.max_verts_norms_per_subobj(),
-                Error::TooManyNorms(id) => self.sub_objects[*id].bsp_data.norms.len() > self.max_verts_norms_per_subobj(),
-                Error::DuplicateSubobjectName(name) => self.sub_objects.iter().filter(|subobj| subobj.name == *name).count() > 1,
-                Error::UnnamedSubObject(id) => self.sub_objects[*id].name.is_empty(),
-            };
-
-            let existing_warning = self.errors.contains(&error);
-            if existing_warning && !failed_check {
-                self.errors.remove(&error);
-            } else if !existing_warning && failed_check {
+            if self.check_error(&error) {
                 self.errors.insert(error);
+            } else {
+                self.errors.remove(&error);
             }
         } else {
-            self.errors.clear();
+            self.errors = self.compute_errors();
+        }
+    }
 
-            for i in 0..self.turrets.len() {
-                if self.turret_gun_subobj_not_valid(i) {
-                    self.errors.insert(Error::InvalidTurretGunSubobject(i));
-                }
-            }
+    // whether the model has the error, without storing it
+    pub fn check_error(&self, error: &Error) -> bool {
+        match error {
+            Error::InvalidTurretGunSubobject(turret) => self.turret_gun_subobj_not_valid(*turret),
+            Error::TooManyDebrisObjects => self.num_debris_objects() > MAX_DEBRIS_OBJECTS,
+            // a subobject which doesn't exist, say one since deleted, has none of these
+            Error::DetailAndDebrisObj(id) => self.header.detail_levels.contains(&id) && self.subobj_check(*id, |subobj| subobj.is_debris_model),
+            Error::DetailObjWithParent(id) => self.header.detail_levels.contains(&id) && self.subobj_check(*id, |subobj| subobj.parent().is_some()),
+            Error::TooManyVerts(id) => self.subobj_check(*id, |subobj| subobj.bsp_data.verts.len() > self.max_verts_norms_per_subobj()),
+            Error::TooManyNorms(id) => self.subobj_check(*id, |subobj| subobj.bsp_data.norms.len() > self.max_verts_norms_per_subobj()),
+            Error::DuplicateSubobjectName(name) => self.sub_objects.iter().filter(|subobj| subobj.name == *name).count() > 1,
+            Error::UnnamedSubObject(id) => self.subobj_check(*id, |subobj| subobj.name.is_empty()),
+            Error::NoDetailLevels => self.header.detail_levels.is_empty(),
+            Error::BspTooDeep(id) => self.bsp_too_deep(*id),
+        }
+    }
 
-            if self.num_debris_objects() > MAX_DEBRIS_OBJECTS {
-                self.errors.insert(Error::TooManyDebrisObjects);
+    // every error the model has, as recheck_errors(Set::All) would store them
+    pub fn compute_errors(&self) -> BTreeSet<Error> {
+        let mut errors = BTreeSet::new();
+
+        for i in 0..self.turrets.len() {
+            if self.turret_gun_subobj_not_valid(i) {
+                errors.insert(Error::InvalidTurretGunSubobject(i));
             }
+        }
 
-            for &id in &self.header.detail_levels {
-                let subobj = &self.sub_objects[id];
-                if subobj.parent().is_some() {
-                    self.errors.insert(Error::DetailObjWithParent(id));
-                }
-                if subobj.is_debris_model {
-                    self.errors.insert(Error::DetailAndDebrisObj(id));
-                }
+        if self.num_debris_objects() > MAX_DEBRIS_OBJECTS {
+            errors.insert(Error::TooManyDebrisObjects);
+        }
+
+        // the engine can't load a model with nothing to render
+        if self.header.detail_levels.is_empty() {
+            errors.insert(Error::NoDetailLevels);
+        }
+
+        for &id in &self.header.detail_levels {
+            let subobj = &self.sub_objects[id];
+            if subobj.parent().is_some() {
+                errors.insert(Error::DetailObjWithParent(id));
             }
+            if subobj.is_debris_model {
+                errors.insert(Error::DetailAndDebrisObj(id));
+            }
+        }
 
-            for subobj in &self.sub_objects {
-                if subobj.name.is_empty() {
-                    self.errors.insert(Error::UnnamedSubObject(subobj.obj_id));
-                }
+        for subobj in &self.sub_objects {
+            if subobj.name.is_empty() {
+                errors.insert(Error::UnnamedSubObject(subobj.obj_id));
+            }
 
-                if subobj.bsp_data.verts.len() > self.max_verts_norms_per_subobj() {
-                    self.errors.insert(Error::TooManyVerts(subobj.obj_id));
-                }
+            if subobj.bsp_data.verts.len() > self.max_verts_norms_per_subobj() {
+                errors.insert(Error::TooManyVerts(subobj.obj_id));
+            }
 
-                if subobj.bsp_data.norms.len() > self.max_verts_norms_per_subobj() {
-                    self.errors.insert(Error::TooManyNorms(subobj.obj_id));
-                }
+            if subobj.bsp_data.norms.len() > self.max_verts_norms_per_subobj() {
+                errors.insert(Error::TooManyNorms(subobj.obj_id));
             }
 
-            for duped_name in self.sub_objects.iter().map(|subobj| &subobj.name).duplicates() {
-                self.errors.insert(Error::DuplicateSubobjectName(duped_name.clone()));
+            if self.bsp_too_deep(Some(subobj.obj_id)) {
+                errors.insert(Error::BspTooDeep(Some(subobj.obj_id)));
             }
         }
+
+        if self.bsp_too_deep(None) {
+            errors.insert(Error::BspTooDeep(None));
+        }
+
+        for duped_name in self.sub_objects.iter().map(|subobj| &subobj.name).duplicates() {
+            errors.insert(Error::DuplicateSubobjectName(duped_name.clone()));
+        }
+
+        errors
+    }
+
+    // the deepest a bsp or shield tree may be before Error::BspTooDeep, see max_bsp_depth
+    pub fn bsp_depth_limit(&self) -> u32 {
+        self.max_bsp_depth.unwrap_or(MAX_BSP_DEPTH)
+    }
+
+    // writing a tree is recursive too, so one this deep can't be saved
+    fn bsp_too_deep(&self, id: Option<ObjectId>) -> bool {
+        let max_depth = self.bsp_depth_limit();
+        match id {
+            Some(id) => self.subobj_check(id, |subobj| subobj.bsp_data.collision_tree.depth() > max_depth),
+            None => matches!(&self.shield_data, Some(ShieldData { collision_tree: Some(tree), .. }) if tree.depth() > max_depth),
+        }
     }
 
     fn turret_gun_subobj_not_valid(&self, turret_num: usize) -> bool {
-        let turret = &self.turrets[turret_num];
+        let Some(turret) = self.turrets.get(turret_num) else {
+            return false;
+        };
         if turret.base_obj == turret.gun_obj {
             return false;
         }
+        let Some(base_obj) = self.sub_objects.get(turret.base_obj.0 as usize) else {
+            return true;
+        };
 
-        for &child_id in self.sub_objects[turret.base_obj].children() {
+        for &child_id in base_obj.children() {
             if child_id == turret.gun_obj {
                 return false;
             }
@@ -1932,250 +3084,418 @@ impl Model {
         true
     }
 
-    // rechecks just one or all of the warnings on the model
+    // rechecks just one or all of the warnings on the model, storing the results in self.warnings
     pub fn recheck_warnings(&mut self, warning_to_check: Set<Warning>) {
         if let Set::One(warning) = warning_to_check {
-            let failed_check = match &warning {
-                Warning::RadiusTooSmall(subobj_opt) => self.radius_test_failed(*subobj_opt),
-                Warning::BBoxTooSmall(subobj_opt) => self.bbox_test_failed(*subobj_opt),
-                Warning::DockingBayWithoutPath(bay_num) => self.docking_bays.get(*bay_num).map_or(false, |bay| bay.path.is_none()),
-                Warning::ThrusterPropertiesInvalidVersion(bank_idx) => {
-                    self.version <= Version::V21_16 && self.thruster_banks.get(*bank_idx).map_or(false, |bank| !bank.properties.is_empty())
-                }
-                Warning::WeaponOffsetInvalidVersion { primary, bank, point } => {
-                    (self.version <= Version::V21_17 || self.version == Version::V22_00) && {
-                        if *primary {
-                            *bank < self.primary_weps.len() && self.primary_weps[*bank].get(*point).map_or(false, |point| point.offset != 0.0)
-                        } else {
-                            *bank < self.secondary_weps.len() && self.secondary_weps[*bank].get(*point).map_or(false, |point| point.offset != 0.0)
-                        }
-                    }
-                }
-                Warning::SubObjectTranslationInvalidVersion(id) => {
-                    self.version < Version::V23_01 && self.sub_objects[*id].translation_axis != SubsysTranslationAxis::None
-                }
-                Warning::InvertedBBox(id_opt) => {
-                    if let Some(id) = id_opt {
-                        self.sub_objects[*id].bbox.is_inverted()
-                    } else {
-                        self.header.bbox.is_inverted()
-                    }
-                }
-                Warning::UntexturedPolygons => self.untextured_idx.is_some(),
-                Warning::TooManyEyePoints => self.eye_points.len() > MAX_EYES,
-                Warning::TooManyTextures => self.textures.len() > MAX_TEXTURES,
-                Warning::TooFewTurretFirePoints(idx) => self.turrets.get(*idx).map_or(false, |turret| turret.fire_points.is_empty()),
-                Warning::TooManyTurretFirePoints(idx) => self
-                    .turrets
-                    .get(*idx)
-                    .map_or(false, |turret| turret.fire_points.len() > MAX_TURRET_POINTS),
-                Warning::DuplicatePathName(name) => self.paths.iter().filter(|path| path.name == *name).count() > 1,
-                Warning::DuplicateDetailLevel(duped_id) => self.header.detail_levels.iter().filter(|id| duped_id == *id).count() > 1,
-
-                Warning::PathNameTooLong(idx) => self.paths.get(*idx).map_or(false, |path| path.name.len() > MAX_NAME_LEN),
-                Warning::SubObjectNameTooLong(id) => self.sub_objects[*id].name.len() > MAX_NAME_LEN,
-                Warning::SpecialPointNameTooLong(idx) => self
-                    .special_points
-                    .get(*idx)
-                    .map_or(false, |spec_point| spec_point.name.len() > MAX_NAME_LEN),
-                Warning::DockingBayNameTooLong(idx) => self
-                    .docking_bays
-                    .get(*idx)
-                    .map_or(false, |dock| properties_get_field(&dock.properties, "$name").unwrap_or_default().len() > MAX_NAME_LEN),
-
-                Warning::GlowBankPropertiesTooLong(idx) => self.glow_banks.get(*idx).map_or(false, |bank| bank.properties.len() > MAX_PROPERTIES_LEN),
-                Warning::ThrusterPropertiesTooLong(idx) => self
-                    .thruster_banks
-                    .get(*idx)
-                    .map_or(false, |bank| bank.properties.len() > MAX_PROPERTIES_LEN),
-                Warning::SubObjectPropertiesTooLong(id) => self.sub_objects[*id].properties.len() > MAX_PROPERTIES_LEN,
-                Warning::DockingBayPropertiesTooLong(idx) => self
-                    .docking_bays
-                    .get(*idx)
-                    .map_or(false, |bank| bank.properties.len() > MAX_PROPERTIES_LEN),
-                Warning::SpecialPointPropertiesTooLong(idx) => self
-                    .special_points
-                    .get(*idx)
-                    .map_or(false, |spec_point| spec_point.properties.len() > MAX_PROPERTIES_LEN),
-                Warning::InvalidDockParentSubmodel(idx) => self.docking_bays.get(*idx).map_or(false, |dock| {
-                    properties_get_field(&dock.properties, "$parent_submodel").map_or(false, |name| self.get_obj_id_by_name(name).is_none())
-                }),
-                Warning::Detail0NonZeroOffset => self
-                    .header
-                    .detail_levels
-                    .get(0)
-                    .map_or(false, |id| !self.sub_objects[*id].offset.is_null()),
-            };
-
-            let existing_warning = self.warnings.contains(&warning);
-            if existing_warning && !failed_check {
-                self.warnings.remove(&warning);
-            } else if !existing_warning && failed_check {
+            if self.check_warning(&warning) {
                 self.warnings.insert(warning);
+            } else {
+                self.warnings.remove(&warning);
             }
         } else {
-            self.warnings.clear();
+            let mut cache = std::mem::take(&mut self.geometry_cache);
+            self.warnings = self.compute_warnings_with(&mut cache);
+            cache.retain_subobjects(self.sub_objects.len());
+            self.geometry_cache = cache;
+        }
+    }
 
-            if self.radius_test_failed(None) {
-                self.warnings.insert(Warning::RadiusTooSmall(None));
+    // whether the model has the warning, without storing it
+    pub fn check_warning(&self, warning: &Warning) -> bool {
+        match warning {
+            Warning::RadiusTooSmall(subobj_opt) => self.radius_test_failed(*subobj_opt),
+            Warning::BBoxTooSmall(subobj_opt) => self.bbox_test_failed(*subobj_opt),
+            Warning::DockingBayWithoutPath(bay_num) => self.docking_bays.get(*bay_num).map_or(false, |bay| bay.path.is_none()),
+            Warning::ThrusterPropertiesInvalidVersion(bank_idx) => {
+                self.version <= Version::V21_16 && self.thruster_banks.get(*bank_idx).map_or(false, |bank| !bank.properties.is_empty())
             }
-
-            if self.bbox_test_failed(None) {
-                self.warnings.insert(Warning::BBoxTooSmall(None));
+            Warning::WeaponOffsetInvalidVersion(weapon) => {
+                (self.version <= Version::V21_17 || self.version == Version::V22_00)
+                    && matches!(self.weapon_point(*weapon), Some(point) if point.offset != 0.0)
             }
-
-            if self.header.bbox.is_inverted() && self.header.bbox != BoundingBox::EMPTY {
-                self.warnings.insert(Warning::InvertedBBox(None));
+            Warning::WeaponInsideHull { primary, bank, point } => {
+                let banks = if *primary { &self.primary_weps } else { &self.secondary_weps };
+                let position = banks.get(*bank).and_then(|bank| bank.get(*point)).map(|point| point.position);
+                position.map_or(false, |position| self.points_inside_hull(&[position], WEAPON_INSIDE_HULL_MARGIN)[0])
             }
-
-            for subobj in &self.sub_objects {
-                if self.bbox_test_failed(Some(subobj.obj_id)) {
-                    self.warnings.insert(Warning::BBoxTooSmall(Some(subobj.obj_id)));
+            Warning::DuplicateWeaponPoint { primary, bank } => self.weapon_bank_has_duplicates(*primary, *bank),
+            Warning::DuplicateDockingBay(idx) => self.docking_bay_is_duplicate(*idx),
+            Warning::DuplicateEyePoint(idx) => self.eye_point_is_duplicate(*idx),
+            Warning::SubObjectTranslationInvalidVersion(id) => {
+                self.version < Version::V23_01 && self.subobj_check(*id, |subobj| subobj.translation_axis != SubsysTranslationAxis::None)
+            }
+            Warning::InvertedBBox(id_opt) => {
+                if let Some(id) = id_opt {
+                    self.subobj_check(*id, |subobj| subobj.bbox.is_inverted())
+                } else {
+                    self.header.bbox.is_inverted()
                 }
+            }
+            Warning::UntexturedPolygons => self.untextured_idx.is_some(),
+            Warning::TooManyEyePoints => self.eye_points.len() > MAX_EYES,
+            Warning::TooManyTextures => self.textures.len() > MAX_TEXTURES,
+            Warning::TooFewTurretFirePoints(idx) => self.turrets.get(*idx).map_or(false, |turret| turret.fire_points.is_empty()),
+            Warning::TooManyTurretFirePoints(idx) => self
+                .turrets
+                .get(*idx)
+                .map_or(false, |turret| turret.fire_points.len() > MAX_TURRET_POINTS),
+            Warning::DuplicatePathName(name) => self.paths.iter().filter(|path| path.name == *name).count() > 1,
+            Warning::DuplicateAttachmentName(name) => self.attachments.iter().filter(|attachment| attachment.name == *name).count() > 1,
+            Warning::DegenerateAttachmentFrame(idx) => self.attachments.get(*idx).map_or(false, AttachmentPoint::has_degenerate_frame),
+            Warning::DuplicateDetailLevel(duped_id) => self.header.detail_levels.iter().filter(|id| duped_id == *id).count() > 1,
+
+            Warning::PathNameTooLong(idx) => self.paths.get(*idx).map_or(false, |path| path.name.len() > MAX_NAME_LEN),
+            Warning::PathClipsGeometry(idx) => {
+                self.paths.get(*idx).map_or(false, |path| self.is_bay_path(path)) && self.path_clearance_report(*idx, None).clips_geometry()
+            }
+            Warning::SubObjectNameTooLong(id) => self.subobj_check(*id, |subobj| subobj.name.len() > MAX_NAME_LEN),
+            Warning::SpecialPointNameTooLong(idx) => self
+                .special_points
+                .get(*idx)
+                .map_or(false, |spec_point| spec_point.name.len() > MAX_NAME_LEN),
+            Warning::DockingBayNameTooLong(idx) => self
+                .docking_bays
+                .get(*idx)
+                .map_or(false, |dock| properties_get_field(&dock.properties, "$name").unwrap_or_default().len() > MAX_NAME_LEN),
+
+            Warning::GlowBankPropertiesTooLong(idx) => self.glow_banks.get(*idx).map_or(false, |bank| bank.properties.len() > MAX_PROPERTIES_LEN),
+            Warning::ThrusterPropertiesTooLong(idx) => self
+                .thruster_banks
+                .get(*idx)
+                .map_or(false, |bank| bank.properties.len() > MAX_PROPERTIES_LEN),
+            Warning::SubObjectPropertiesTooLong(id) => self.subobj_check(*id, |subobj| subobj.properties.len() > MAX_PROPERTIES_LEN),
+            Warning::DockingBayPropertiesTooLong(idx) => self
+                .docking_bays
+                .get(*idx)
+                .map_or(false, |bank| bank.properties.len() > MAX_PROPERTIES_LEN),
+            Warning::SpecialPointPropertiesTooLong(idx) => self
+                .special_points
+                .get(*idx)
+                .map_or(false, |spec_point| spec_point.properties.len() > MAX_PROPERTIES_LEN),
+            Warning::InvalidDockParentSubmodel(idx) => self.docking_bays.get(*idx).map_or(false, |dock| {
+                properties_get_field(&dock.properties, "$parent_submodel").map_or(false, |name| self.get_obj_id_by_name(name).is_none())
+            }),
+            Warning::InsigniaInvalidDetailLevel(idx) => {
+                matches!(self.insignias.get(*idx), Some(insignia) if insignia.detail_level as usize >= self.header.detail_levels.len())
+            }
+            Warning::Detail0NonZeroOffset => self.detail0().map_or(false, |detail0| !detail0.offset.is_null()),
+            Warning::AutocenterFlagWithoutVisualCenter => self.header.flags().contains(HeaderFlags::AUTOCENTER) && self.visual_center.is_null(),
+            Warning::UnknownHeaderFlags => self.header.flags().unknown_bits() != 0,
+            Warning::TilingUvsWithoutTilingFlag => !self.header.flags().contains(HeaderFlags::ALLOW_TILING) && self.has_uvs_outside_texture(),
+            Warning::VisualCenterOutsideDetail0 => self.visual_center_outside_detail0(),
+            Warning::PrecisionBudgetExceeded => self.precision_report().exceeds_budget,
+            Warning::NormalsRepaired => !self.normal_notices.is_empty(),
+            Warning::LegacyQuirksFixed => !self.quirk_notices.is_empty(),
+            Warning::ChunkLengthMismatch => !self.parse_notices.is_empty(),
+            Warning::VersionChangedFromDisk => self.version_downgraded(),
+            Warning::InvalidCrossSections => cross_sections_invalid(&self.header.cross_sections),
+            Warning::DebrisFlagNameMismatch(id) => self.subobj_check(*id, SubObject::debris_name_mismatch),
+            Warning::DebrisInDetailHierarchy(id) => self.debris_in_detail_hierarchy(*id),
+            Warning::UnreachableSubobject(id) => self.subobj_unreachable(*id),
+            Warning::ZFightingPolygons(id) => self.subobj_check(*id, |subobj| !subobj.find_overlapping_coplanar().is_empty()),
+        }
+    }
 
-                if self.radius_test_failed(Some(subobj.obj_id)) {
-                    self.warnings.insert(Warning::RadiusTooSmall(Some(subobj.obj_id)));
-                }
+    // every warning the model has, as recheck_warnings(Set::All) would store them
+    pub fn compute_warnings(&self) -> BTreeSet<Warning> {
+        self.compute_warnings_with(&mut GeometryCache::default())
+    }
 
-                if subobj.bbox.is_inverted() && subobj.bbox != BoundingBox::EMPTY {
-                    self.warnings.insert(Warning::InvertedBBox(Some(subobj.obj_id)));
-                }
+    fn compute_warnings_with(&self, cache: &mut GeometryCache) -> BTreeSet<Warning> {
+        let mut warnings = BTreeSet::new();
 
-                if subobj.name.len() > MAX_NAME_LEN {
-                    self.warnings.insert(Warning::SubObjectNameTooLong(subobj.obj_id));
-                }
+        if self.radius_test_failed(None) {
+            warnings.insert(Warning::RadiusTooSmall(None));
+        }
 
-                if subobj.properties.len() > MAX_PROPERTIES_LEN {
-                    self.warnings.insert(Warning::SubObjectPropertiesTooLong(subobj.obj_id));
-                }
+        if self.bbox_test_failed(None) {
+            warnings.insert(Warning::BBoxTooSmall(None));
+        }
 
-                if self.version < Version::V23_01 && subobj.translation_axis != SubsysTranslationAxis::None {
-                    self.warnings.insert(Warning::SubObjectTranslationInvalidVersion(subobj.obj_id));
-                }
+        if self.header.bbox.is_inverted() && self.header.bbox != BoundingBox::EMPTY {
+            warnings.insert(Warning::InvertedBBox(None));
+        }
+
+        for subobj in &self.sub_objects {
+            if self.bbox_test_failed(Some(subobj.obj_id)) {
+                warnings.insert(Warning::BBoxTooSmall(Some(subobj.obj_id)));
             }
 
-            for (i, dock) in self.docking_bays.iter().enumerate() {
-                if dock.path.is_none() {
-                    self.warnings.insert(Warning::DockingBayWithoutPath(i));
-                }
+            if self.radius_test_failed(Some(subobj.obj_id)) {
+                warnings.insert(Warning::RadiusTooSmall(Some(subobj.obj_id)));
+            }
 
-                if dock.properties.len() > MAX_PROPERTIES_LEN {
-                    self.warnings.insert(Warning::DockingBayPropertiesTooLong(i));
-                }
+            if subobj.bbox.is_inverted() && subobj.bbox != BoundingBox::EMPTY {
+                warnings.insert(Warning::InvertedBBox(Some(subobj.obj_id)));
+            }
 
-                if properties_get_field(&dock.properties, "$name").unwrap_or_default().len() > MAX_NAME_LEN {
-                    self.warnings.insert(Warning::DockingBayNameTooLong(i));
-                }
+            if subobj.name.len() > MAX_NAME_LEN {
+                warnings.insert(Warning::SubObjectNameTooLong(subobj.obj_id));
+            }
 
-                if properties_get_field(&dock.properties, "$parent_submodel").map_or(false, |name| self.get_obj_id_by_name(name).is_none()) {
-                    self.warnings.insert(Warning::InvalidDockParentSubmodel(i));
-                }
+            if subobj.properties.len() > MAX_PROPERTIES_LEN {
+                warnings.insert(Warning::SubObjectPropertiesTooLong(subobj.obj_id));
             }
 
-            for (i, bank) in self.thruster_banks.iter().enumerate() {
-                if !bank.properties.is_empty() {
-                    if self.version <= Version::V21_16 {
-                        self.warnings.insert(Warning::ThrusterPropertiesInvalidVersion(i));
-                    }
+            if self.version < Version::V23_01 && subobj.translation_axis != SubsysTranslationAxis::None {
+                warnings.insert(Warning::SubObjectTranslationInvalidVersion(subobj.obj_id));
+            }
 
-                    if bank.properties.len() > MAX_PROPERTIES_LEN {
-                        self.warnings.insert(Warning::ThrusterPropertiesTooLong(i));
-                    }
-                }
+            if subobj.debris_name_mismatch() {
+                warnings.insert(Warning::DebrisFlagNameMismatch(subobj.obj_id));
             }
 
-            if self.version <= Version::V21_17 || self.version == Version::V22_00 {
-                for (i, bank) in self.primary_weps.iter().enumerate() {
-                    for (j, point) in bank.iter().enumerate() {
-                        if point.offset != 0.0 {
-                            self.warnings
-                                .insert(Warning::WeaponOffsetInvalidVersion { primary: true, bank: i, point: j });
-                        }
-                    }
-                }
-                for (i, bank) in self.secondary_weps.iter().enumerate() {
-                    for (j, point) in bank.iter().enumerate() {
-                        if point.offset != 0.0 {
-                            self.warnings
-                                .insert(Warning::WeaponOffsetInvalidVersion { primary: false, bank: i, point: j });
-                        }
-                    }
-                }
+            if self.debris_in_detail_hierarchy(subobj.obj_id) {
+                warnings.insert(Warning::DebrisInDetailHierarchy(subobj.obj_id));
             }
 
-            for (i, turret) in self.turrets.iter().enumerate() {
-                if turret.fire_points.is_empty() {
-                    self.warnings.insert(Warning::TooFewTurretFirePoints(i));
-                } else if turret.fire_points.len() > MAX_TURRET_POINTS {
-                    self.warnings.insert(Warning::TooManyTurretFirePoints(i));
-                }
+            if self.subobj_unreachable(subobj.obj_id) {
+                warnings.insert(Warning::UnreachableSubobject(subobj.obj_id));
             }
 
-            for (i, glow_bank) in self.glow_banks.iter().enumerate() {
-                if glow_bank.properties.len() > MAX_PROPERTIES_LEN {
-                    self.warnings.insert(Warning::GlowBankPropertiesTooLong(i));
-                }
+            if cache.z_fighting(subobj) {
+                warnings.insert(Warning::ZFightingPolygons(subobj.obj_id));
+            }
+        }
+
+        for (i, dock) in self.docking_bays.iter().enumerate() {
+            if dock.path.is_none() {
+                warnings.insert(Warning::DockingBayWithoutPath(i));
+            }
+
+            if dock.properties.len() > MAX_PROPERTIES_LEN {
+                warnings.insert(Warning::DockingBayPropertiesTooLong(i));
+            }
+
+            if properties_get_field(&dock.properties, "$name").unwrap_or_default().len() > MAX_NAME_LEN {
+                warnings.insert(Warning::DockingBayNameTooLong(i));
+            }
+
+            if properties_get_field(&dock.properties, "$parent_submodel").map_or(false, |name| self.get_obj_id_by_name(name).is_none()) {
+                warnings.insert(Warning::InvalidDockParentSubmodel(i));
+            }
+        }
+
+        for (i, insignia) in self.insignias.iter().enumerate() {
+            if insignia.detail_level as usize >= self.header.detail_levels.len() {
+                warnings.insert(Warning::InsigniaInvalidDetailLevel(i));
             }
+        }
 
-            for (i, special_point) in self.special_points.iter().enumerate() {
-                if special_point.name.len() > MAX_NAME_LEN {
-                    self.warnings.insert(Warning::SpecialPointNameTooLong(i));
+        for (i, bank) in self.thruster_banks.iter().enumerate() {
+            if !bank.properties.is_empty() {
+                if self.version <= Version::V21_16 {
+                    warnings.insert(Warning::ThrusterPropertiesInvalidVersion(i));
                 }
 
-                if special_point.properties.len() > MAX_PROPERTIES_LEN {
-                    self.warnings.insert(Warning::SpecialPointPropertiesTooLong(i));
+                if bank.properties.len() > MAX_PROPERTIES_LEN {
+                    warnings.insert(Warning::ThrusterPropertiesTooLong(i));
                 }
             }
+        }
+
+        if self.version <= Version::V21_17 || self.version == Version::V22_00 {
+            let offset_points = self
+                .iter_weapon_points()
+                .filter(|(_, point)| point.offset != 0.0)
+                .map(|(weapon, _)| weapon);
+            warnings.extend(offset_points.map(Warning::WeaponOffsetInvalidVersion));
+        }
 
-            for (i, path) in self.paths.iter().enumerate() {
-                if path.name.len() > MAX_NAME_LEN {
-                    self.warnings.insert(Warning::PathNameTooLong(i));
+        // shared by the weapon and path checks, and only built if one of them needs it
+        let mut hull = None;
+        if self.iter_weapon_points().next().is_some() {
+            let hull = hull.get_or_insert_with(|| cache.hull(self));
+            let positions = self.iter_weapon_points().map(|(_, point)| point.position).collect::<Vec<_>>();
+            let inside = self.points_inside_hull_with(&positions, WEAPON_INSIDE_HULL_MARGIN, &hull.hash);
+            let buried = (self.iter_weapon_points().zip(inside))
+                .filter(|(_, inside)| *inside)
+                .map(|((weapon, _), _)| Warning::WeaponInsideHull {
+                    primary: weapon.kind.is_primary(),
+                    bank: weapon.bank as usize,
+                    point: weapon.point as usize,
+                });
+            warnings.extend(buried);
+        }
+
+        for primary in [true, false] {
+            let num_banks = if primary { self.primary_weps.len() } else { self.secondary_weps.len() };
+            for bank in 0..num_banks {
+                if self.weapon_bank_has_duplicates(primary, bank) {
+                    warnings.insert(Warning::DuplicateWeaponPoint { primary, bank });
                 }
             }
+        }
 
-            for duped_name in self.paths.iter().map(|path| &path.name).duplicates() {
-                self.warnings.insert(Warning::DuplicatePathName(duped_name.clone()));
+        for i in 0..self.docking_bays.len() {
+            if self.docking_bay_is_duplicate(i) {
+                warnings.insert(Warning::DuplicateDockingBay(i));
             }
+        }
 
-            if let Some(id) = self.header.detail_levels.get(0) {
-                if !self.sub_objects[*id].offset.is_null() {
-                    self.warnings.insert(Warning::Detail0NonZeroOffset);
-                }
+        for i in 0..self.eye_points.len() {
+            if self.eye_point_is_duplicate(i) {
+                warnings.insert(Warning::DuplicateEyePoint(i));
             }
+        }
 
-            for duped_id in self.header.detail_levels.iter().duplicates() {
-                self.warnings.insert(Warning::DuplicateDetailLevel(*duped_id));
+        for (i, turret) in self.turrets.iter().enumerate() {
+            if turret.fire_points.is_empty() {
+                warnings.insert(Warning::TooFewTurretFirePoints(i));
+            } else if turret.fire_points.len() > MAX_TURRET_POINTS {
+                warnings.insert(Warning::TooManyTurretFirePoints(i));
             }
+        }
 
-            if self.untextured_idx.is_some() {
-                self.warnings.insert(Warning::UntexturedPolygons);
+        for (i, glow_bank) in self.glow_banks.iter().enumerate() {
+            if glow_bank.properties.len() > MAX_PROPERTIES_LEN {
+                warnings.insert(Warning::GlowBankPropertiesTooLong(i));
             }
+        }
 
-            if self.eye_points.len() > MAX_EYES {
-                self.warnings.insert(Warning::TooManyEyePoints);
+        for (i, special_point) in self.special_points.iter().enumerate() {
+            if special_point.name.len() > MAX_NAME_LEN {
+                warnings.insert(Warning::SpecialPointNameTooLong(i));
             }
 
-            if self.textures.len() > MAX_TEXTURES {
-                self.warnings.insert(Warning::TooManyTextures);
+            if special_point.properties.len() > MAX_PROPERTIES_LEN {
+                warnings.insert(Warning::SpecialPointPropertiesTooLong(i));
             }
         }
-    }
 
-    // tests if the radius for a subobject or the header is too small for its geometry
-    // None means the header/entire model's radius
-    fn radius_test_failed(&self, subobj_opt: Option<ObjectId>) -> bool {
-        if let Some(subobj) = subobj_opt {
-            let subobj = &self.sub_objects[subobj];
-            let radius_with_margin = (1.0 + f32::EPSILON) * subobj.radius;
-            for vert in &subobj.bsp_data.verts {
-                if vert.magnitude() > radius_with_margin {
-                    return true;
-                }
+        for (i, path) in self.paths.iter().enumerate() {
+            if path.name.len() > MAX_NAME_LEN {
+                warnings.insert(Warning::PathNameTooLong(i));
             }
-        } else {
-            let radius_with_margin = (1.0 + f32::EPSILON) * self.header.max_radius;
-            if let Some(&detail_0) = self.header.detail_levels.first() {
-                for subobj in &self.sub_objects {
+        }
+
+        if self.paths.iter().any(|path| self.is_bay_path(path)) {
+            let hull = hull.get_or_insert_with(|| cache.hull(self));
+            let clipping = (0..self.paths.len())
+                .filter(|&i| self.is_bay_path(&self.paths[i]) && self.path_clearance_report_with(i, None, hull).clips_geometry())
+                .map(Warning::PathClipsGeometry);
+            warnings.extend(clipping);
+        }
+
+        for duped_name in self.paths.iter().map(|path| &path.name).duplicates() {
+            warnings.insert(Warning::DuplicatePathName(duped_name.clone()));
+        }
+
+        for duped_name in self.attachments.iter().map(|attachment| &attachment.name).duplicates() {
+            warnings.insert(Warning::DuplicateAttachmentName(duped_name.clone()));
+        }
+
+        for (i, attachment) in self.attachments.iter().enumerate() {
+            if attachment.has_degenerate_frame() {
+                warnings.insert(Warning::DegenerateAttachmentFrame(i));
+            }
+        }
+
+        if let Some(detail0) = self.detail0() {
+            if !detail0.offset.is_null() {
+                warnings.insert(Warning::Detail0NonZeroOffset);
+            }
+        }
+
+        // the ACEN chunk is only written for a non-zero visual center, so this flag would be left pointing at nothing
+        if self.header.flags().contains(HeaderFlags::AUTOCENTER) && self.visual_center.is_null() {
+            warnings.insert(Warning::AutocenterFlagWithoutVisualCenter);
+        }
+
+        if self.header.flags().unknown_bits() != 0 {
+            warnings.insert(Warning::UnknownHeaderFlags);
+        }
+
+        if !self.header.flags().contains(HeaderFlags::ALLOW_TILING) && self.has_uvs_outside_texture() {
+            warnings.insert(Warning::TilingUvsWithoutTilingFlag);
+        }
+
+        if self.visual_center_outside_detail0() {
+            warnings.insert(Warning::VisualCenterOutsideDetail0);
+        }
+
+        if self.precision_report().exceeds_budget {
+            warnings.insert(Warning::PrecisionBudgetExceeded);
+        }
+
+        if !self.normal_notices.is_empty() {
+            warnings.insert(Warning::NormalsRepaired);
+        }
+
+        if !self.quirk_notices.is_empty() {
+            warnings.insert(Warning::LegacyQuirksFixed);
+        }
+
+        if !self.parse_notices.is_empty() {
+            warnings.insert(Warning::ChunkLengthMismatch);
+        }
+
+        if self.version_downgraded() {
+            warnings.insert(Warning::VersionChangedFromDisk);
+        }
+
+        if cross_sections_invalid(&self.header.cross_sections) {
+            warnings.insert(Warning::InvalidCrossSections);
+        }
+
+        for duped_id in self.header.detail_levels.iter().duplicates() {
+            warnings.insert(Warning::DuplicateDetailLevel(*duped_id));
+        }
+
+        if self.untextured_idx.is_some() {
+            warnings.insert(Warning::UntexturedPolygons);
+        }
+
+        if self.eye_points.len() > MAX_EYES {
+            warnings.insert(Warning::TooManyEyePoints);
+        }
+
+        if self.textures.len() > MAX_TEXTURES {
+            warnings.insert(Warning::TooManyTextures);
+        }
+
+        warnings
+    }
+
+    // debris which is the detail level object itself is already an error, this catches debris further down the hierarchy
+    fn debris_in_detail_hierarchy(&self, id: ObjectId) -> bool {
+        self.subobj_check(id, |subobj| subobj.is_debris_model) && !self.header.detail_levels.contains(&id) && self.get_sobj_detail_level(id).is_some()
+    }
+
+    // subobjects outside every detail level's hierarchy are never rendered, unless they're debris or a destroyed/live debris
+    // variant, which the engine finds by flag or by name instead
+    fn subobj_unreachable(&self, id: ObjectId) -> bool {
+        let mut id = id;
+        loop {
+            let Some(subobj) = self.sub_objects.get(id.0 as usize) else {
+                return false;
+            };
+            if subobj.is_debris_model || subobj.is_destroyed_model() || subobj.name_links.iter().any(|link| matches!(link, NameLink::LiveDebrisOf(_)))
+            {
+                return false;
+            }
+            match subobj.parent {
+                Some(parent) => id = parent,
+                None => return !self.header.detail_levels.contains(&id),
+            }
+        }
+    }
+
+    // tests if the radius for a subobject or the header is too small for its geometry
+    // None means the header/entire model's radius
+    fn radius_test_failed(&self, subobj_opt: Option<ObjectId>) -> bool {
+        if let Some(subobj) = subobj_opt {
+            let subobj = &self.sub_objects[subobj];
+            let radius_with_margin = self.tolerance_policy.radius_margin.apply(subobj.radius);
+            for vert in &subobj.bsp_data.verts {
+                if vert.magnitude() > radius_with_margin {
+                    return true;
+                }
+            }
+        } else {
+            let radius_with_margin = self.tolerance_policy.radius_margin.apply(self.header.max_radius);
+            if let Some(detail_0) = self.detail0_id() {
+                for subobj in &self.sub_objects {
                     // we dont care about subobjects which aren't part of the detail0 hierarchy
                     if !self.is_obj_id_ancestor(subobj.obj_id, detail_0) {
                         continue;
@@ -2199,12 +3519,14 @@ impl Model {
     fn bbox_test_failed(&self, subobj_opt: Option<ObjectId>) -> bool {
         if let Some(subobj) = subobj_opt {
             let subobj = &self.sub_objects[subobj];
+            let bbox = self.tolerance_policy.pad_bbox(&subobj.bbox);
             for vert in &subobj.bsp_data.verts {
-                if !subobj.bbox.contains(*vert) {
+                if !bbox.contains(*vert) {
                     return true;
                 }
             }
-        } else if let Some(&detail_0) = self.header.detail_levels.first() {
+        } else if let Some(detail_0) = self.detail0_id() {
+            let bbox = self.tolerance_policy.pad_bbox(&self.header.bbox);
             for subobj in &self.sub_objects {
                 // we dont care about subobjects which aren't part of the detail0 hierarchy
                 if !self.is_obj_id_ancestor(subobj.obj_id, detail_0) {
@@ -2213,7 +3535,7 @@ impl Model {
 
                 let offset = self.get_total_subobj_offset(subobj.obj_id);
                 for vert in &subobj.bsp_data.verts {
-                    if !self.header.bbox.contains(offset + *vert) {
+                    if !bbox.contains(offset + *vert) {
                         return true;
                     }
                 }
@@ -2223,8 +3545,16 @@ impl Model {
         false
     }
 
+    // false for a subobject which doesn't exist
+    fn subobj_check(&self, id: ObjectId, check: impl FnOnce(&SubObject) -> bool) -> bool {
+        self.sub_objects.get(id.0 as usize).map_or(false, check)
+    }
+
+    // zero for a subobject which doesn't exist
     pub fn get_total_subobj_offset(&self, id: ObjectId) -> Vec3d {
-        let mut subobj = &self.sub_objects[id];
+        let Some(mut subobj) = self.sub_objects.get(id.0 as usize) else {
+            return Vec3d::ZERO;
+        };
         let mut out = subobj.offset;
         while let Some(parent) = subobj.parent {
             subobj = &self.sub_objects[parent];
@@ -2234,12 +3564,16 @@ impl Model {
     }
 
     // see if maybe_ancestor is actually an ancestor of obj_id in the subobject hierarchy
+    // a subobject which doesn't exist has no ancestors
     pub fn is_obj_id_ancestor(&self, obj_id: ObjectId, maybe_ancestor: ObjectId) -> bool {
         if obj_id == maybe_ancestor {
             return true;
         }
 
-        let mut sub_obj_parent = self.sub_objects[obj_id].parent;
+        let Some(subobj) = self.sub_objects.get(obj_id.0 as usize) else {
+            return false;
+        };
+        let mut sub_obj_parent = subobj.parent;
         loop {
             if sub_obj_parent == Some(maybe_ancestor) {
                 return true;
@@ -2251,6 +3585,14 @@ impl Model {
         }
     }
 
+    pub fn detail0_id(&self) -> Option<ObjectId> {
+        self.header.detail_levels.first().copied()
+    }
+
+    pub fn detail0(&self) -> Option<&SubObject> {
+        self.detail0_id().map(|id| &self.sub_objects[id])
+    }
+
     pub fn get_sobj_detail_level(&self, obj_id: ObjectId) -> Option<u32> {
         for (i, id) in self.header.detail_levels.iter().enumerate() {
             if self.is_obj_id_ancestor(obj_id, *id) {
@@ -2260,6 +3602,26 @@ impl Model {
         None
     }
 
+    // one entry per detail level, in order, for comparing them; each one should be smaller than the one before
+    pub fn lod_stats(&self) -> Vec<LodStats> {
+        (self.header.detail_levels.iter())
+            .map(|&root| {
+                let mut stats = LodStats { root, triangles: 0, vertices: 0, radius: 0.0 };
+                for subobj in self.sub_objects.iter().filter(|subobj| self.is_obj_id_ancestor(subobj.obj_id, root)) {
+                    let offset = self.get_total_subobj_offset(subobj.obj_id);
+                    for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+                        stats.triangles += poly.verts.len().saturating_sub(2) as u32;
+                    }
+                    stats.vertices += subobj.bsp_data.verts.len() as u32;
+                    for &vert in &subobj.bsp_data.verts {
+                        stats.radius = stats.radius.max((vert + offset).magnitude());
+                    }
+                }
+                stats
+            })
+            .collect()
+    }
+
     pub fn get_subobj_names(&self) -> Vec<String> {
         let mut ret = vec![];
         for subobj in &self.sub_objects {
@@ -2277,6 +3639,49 @@ impl Model {
         None
     }
 
+    pub fn add_path(&mut self, path: Path) -> PathId {
+        self.paths.push(path);
+        self.recheck_warnings(Set::All);
+        PathId(self.paths.len() as u32 - 1)
+    }
+
+    // removes the path, docking bays which used it lose their path, and ones using later paths are re-indexed
+    pub fn remove_path(&mut self, idx: PathId) -> Path {
+        let path = self.paths.remove(idx.0 as usize);
+        self.path_removal_fixup(idx);
+        self.recheck_warnings(Set::All);
+        path
+    }
+
+    // new_order lists the current ids of the paths in the order they should end up in, and must include each path exactly once,
+    // otherwise the model is left as it was
+    // docking bays keep pointing at the same paths
+    pub fn reorder_paths(&mut self, new_order: &[PathId]) -> Result<(), ReorderPathsError> {
+        let mut new_ids = vec![None; self.paths.len()];
+        for (new_idx, &old_id) in new_order.iter().enumerate() {
+            let new_id = new_ids.get_mut(old_id.0 as usize).ok_or(ReorderPathsError::InvalidId(old_id))?;
+            if new_id.replace(PathId(new_idx as u32)).is_some() {
+                return Err(ReorderPathsError::ListedTwice(old_id));
+            }
+        }
+        if let Some(missing) = new_ids.iter().position(Option::is_none) {
+            return Err(ReorderPathsError::Missing(PathId(missing as u32)));
+        }
+
+        let mut old_paths = std::mem::take(&mut self.paths).into_iter().map(Some).collect::<Vec<_>>();
+        self.paths = new_order.iter().map(|old_id| old_paths[old_id.0 as usize].take().unwrap()).collect();
+        for bay in &mut self.docking_bays {
+            if let Some(path) = &mut bay.path {
+                // an already dangling reference is left alone
+                if let Some(&Some(new_id)) = new_ids.get(path.0 as usize) {
+                    *path = new_id;
+                }
+            }
+        }
+        self.recheck_warnings(Set::All);
+        Ok(())
+    }
+
     pub fn path_removal_fixup(&mut self, removed_idx: PathId) {
         for bay in &mut self.docking_bays {
             if let Some(path_num) = bay.path {
@@ -2302,7 +3707,8 @@ impl Model {
         out_vec.push(turret_obj);
 
         // then iterate through immediate base object children, which are also valid
-        for &child_id in &self.sub_objects[turret_obj].children {
+        let turret_subobj = self.sub_objects.get(turret_obj.0 as usize);
+        for &child_id in turret_subobj.map_or(&[][..], |subobj| &subobj.children) {
             if existing_obj == child_id {
                 out_idx = out_vec.len();
                 found_existing_obj = true;
@@ -2321,10 +3727,12 @@ impl Model {
     }
 
     pub fn do_for_recursive_subobj_children<'a>(&'a self, id: ObjectId, f: &mut impl FnMut(&'a SubObject)) {
-        f(&self.sub_objects[id]);
+        let Some(subobj) = self.sub_objects.get(id.0 as usize) else {
+            return;
+        };
+        f(subobj);
 
-        for &child_id in self.sub_objects[id].children() {
-            f(&self.sub_objects[child_id]);
+        for &child_id in subobj.children() {
             self.do_for_recursive_subobj_children(child_id, f);
         }
     }
@@ -2339,7 +3747,13 @@ impl Model {
         num_debris
     }
 
-    pub fn apply_transform(&mut self, matrix: &TMat4<f32>) {
+    // fails, changing nothing, if the shield's tree is too deep for its bboxes to be recalculated
+    pub fn apply_transform(&mut self, matrix: &TMat4<f32>) -> Result<(), BspTooDeep> {
+        let max_depth = self.bsp_depth_limit();
+        if matches!(&self.shield_data, Some(ShieldData { collision_tree: Some(tree), .. }) if tree.depth() > max_depth) {
+            return Err(BspTooDeep { max_depth });
+        }
+
         for i in 0..self.sub_objects.len() {
             // only apply to top-level subobjects (no parent), apply_transform() will
             // recursively apply the proper transform to its children
@@ -2349,7 +3763,6 @@ impl Model {
         }
 
         self.recalc_bbox();
-        self.recalc_radius();
 
         for path in &mut self.paths {
             path.apply_transform(&matrix);
@@ -2390,6 +3803,10 @@ impl Model {
             eye.apply_transform(&matrix)
         }
 
+        for attachment in &mut self.attachments {
+            attachment.apply_transform(&matrix);
+        }
+
         for insignia in &mut self.insignias {
             insignia.apply_transform(&matrix);
         }
@@ -2399,15 +3816,114 @@ impl Model {
         }
 
         if let Some(shield) = &mut self.shield_data {
-            shield.apply_transform(&matrix);
+            shield.apply_transform_with(&matrix, max_depth)?;
+        }
+        // after the shield, which counts toward the radius
+        self.recalc_radius();
+
+        // a zero scale collapses every direction, so the normals above kept their previous ones
+        if mat4_try_rotation_only(matrix).is_none() {
+            let notices = self
+                .normal_fields()
+                .map(|field| NormalAudit { field, issue: NormalIssue::DegenerateTransform });
+            let notices = notices.collect::<Vec<_>>();
+            self.normal_notices.extend(notices);
+            self.recheck_warnings(Set::One(Warning::NormalsRepaired));
+        }
+        Ok(())
+    }
+
+    // an estimate of the header bbox and radius apply_transform would produce, without rebuilding any bsp trees
+    // the bbox is built from the transformed corners of detail0's subobject bboxes, so it's exact for scales and
+    // translations but can be larger than the real one when rotating, and off by the bsp trees' 0.01 padding
+    // the radius is exact
+    pub fn preview_transform_bounds(&self, matrix: &TMat4<f32>) -> (BoundingBox, f32) {
+        let mut bbox = BoundingBox::ZERO.pad(self.tolerance_policy.min_extent);
+        let mut radius: f32 = self.tolerance_policy.min_extent;
+
+        if let Some(detail_0) = self.detail0_id() {
+            for subobj in &self.sub_objects {
+                if !self.is_obj_id_ancestor(subobj.obj_id, detail_0) {
+                    continue;
+                }
+
+                let offset = self.get_total_subobj_offset(subobj.obj_id);
+                let (min, max) = (subobj.bbox.min + offset, subobj.bbox.max + offset);
+                for corner in 0..8 {
+                    let x = if corner & 1 == 0 { min.x } else { max.x };
+                    let y = if corner & 2 == 0 { min.y } else { max.y };
+                    let z = if corner & 4 == 0 { min.z } else { max.z };
+                    bbox.expand_vec(matrix * Vec3d { x, y, z });
+                }
+
+                for vert in &subobj.bsp_data.verts {
+                    radius = radius.max((matrix * (*vert + offset)).magnitude());
+                }
+            }
+        }
+
+        if let Some(shield) = &self.shield_data {
+            for vert in &shield.verts {
+                radius = radius.max((matrix * *vert).magnitude());
+            }
+        }
+
+        (bbox, radius)
+    }
+
+    // every normal in the model which is expected to be unit length
+    fn normal_fields(&self) -> impl Iterator<Item = NormalField> + '_ {
+        let eyes = (0..self.eye_points.len()).map(NormalField::EyePoint);
+        let weapons = [(true, &self.primary_weps), (false, &self.secondary_weps)]
+            .into_iter()
+            .flat_map(|(primary, banks)| {
+                banks
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(bank, points)| (0..points.len()).map(move |point| NormalField::Weapon { primary, bank, point }))
+            });
+        let turrets = (0..self.turrets.len()).map(NormalField::Turret);
+        let glows = self
+            .thruster_banks
+            .iter()
+            .enumerate()
+            .flat_map(|(bank, thruster_bank)| (0..thruster_bank.glows.len()).map(move |glow| NormalField::ThrusterGlow { bank, glow }));
+        let docks = (0..self.docking_bays.len()).flat_map(|i| [NormalField::DockFvec(i), NormalField::DockUvec(i)]);
+        eyes.chain(weapons).chain(turrets).chain(glows).chain(docks)
+    }
+
+    // normals which were repaired on load or by a transform, dock uvecs edited out of true, which canonicalize fixes,
+    // and thruster glows pointing forward, which is almost always a mistake since thrusters push the ship the opposite way they face
+    pub fn audit_normals(&self) -> Vec<NormalAudit> {
+        let mut audit = self.normal_notices.clone();
+        for (i, dock) in self.docking_bays.iter().enumerate() {
+            if let Some(issue) = Dock::uvec_issue(dock.uvec.0, dock.fvec.0) {
+                audit.push(NormalAudit { field: NormalField::DockUvec(i), issue });
+            }
+        }
+        for (bank, thruster_bank) in self.thruster_banks.iter().enumerate() {
+            for (glow, thruster_glow) in thruster_bank.glows.iter().enumerate() {
+                if thruster_glow.normal.0.z > 0.5 {
+                    audit.push(NormalAudit {
+                        field: NormalField::ThrusterGlow { bank, glow },
+                        issue: NormalIssue::ThrusterFacingForward,
+                    });
+                }
+            }
         }
+        audit
     }
 
+    // like the other subobject edits, this does nothing for a subobject which doesn't exist
     pub fn apply_subobj_transform(&mut self, id: ObjectId, matrix: &TMat4<f32>, transform_offset: bool) {
+        if id.0 as usize >= self.sub_objects.len() {
+            return;
+        }
         let zero = Vec3d::ZERO.into();
         let translation = matrix.transform_point(&zero) - zero;
         let no_trans_matrix = &matrix.append_translation(&(-translation));
 
+        let policy = self.tolerance_policy;
         let subobj = &mut self.sub_objects[id];
         subobj.radius = 0.0;
         for vert in &mut subobj.bsp_data.verts {
@@ -2421,14 +3937,19 @@ impl Model {
         }
 
         // this preserves rotations, but inverts scales, which is the proper transformation for normals
-        let norm_matrix = no_trans_matrix.try_inverse().unwrap().transpose();
-
-        for norm in &mut subobj.bsp_data.norms {
-            *norm = (&norm_matrix * *norm).normalize();
+        // a matrix with a zero scale has no inverse, the normals are left alone in that case
+        if let Some(norm_matrix) = no_trans_matrix.try_inverse() {
+            let norm_matrix = norm_matrix.transpose();
+            for norm in &mut subobj.bsp_data.norms {
+                *norm = (&norm_matrix * *norm).normalize();
+            }
         }
 
-        subobj.bsp_data.collision_tree =
-            BspData::recalculate(&subobj.bsp_data.verts, std::mem::take(&mut subobj.bsp_data.collision_tree).into_leaves().map(|(_, poly)| poly));
+        subobj.bsp_data.collision_tree = BspData::recalculate_with(
+            &subobj.bsp_data.verts,
+            std::mem::take(&mut subobj.bsp_data.collision_tree).into_leaves().map(|(_, poly)| poly),
+            &policy,
+        );
 
         subobj.bbox = *subobj.bsp_data.collision_tree.bbox();
 
@@ -2444,13 +3965,17 @@ impl Model {
     }
 
     pub fn recalc_subobj_offset(&mut self, id: ObjectId) {
-        let subobj = &mut self.sub_objects[id];
+        let Some(subobj) = self.sub_objects.get(id.0 as usize) else {
+            return;
+        };
         let new_offset = Vec3d::average(subobj.bsp_data.verts.iter().map(|vert| *vert + subobj.offset));
         self.subobj_move_only_offset(id, new_offset)
     }
 
     pub fn subobj_move_only_offset(&mut self, id: ObjectId, new_offset: Vec3d) {
-        let subobj = &mut self.sub_objects[id];
+        let Some(subobj) = self.sub_objects.get(id.0 as usize) else {
+            return;
+        };
         let diff = new_offset - subobj.offset;
 
         let children = subobj.children.clone();
@@ -2463,12 +3988,71 @@ impl Model {
         subobj.bbox.min -= diff;
         subobj.offset = new_offset;
         self.apply_subobj_transform(id, &glm::translation(&(-diff).into()), false);
-        self.sub_objects[id].recalc_radius();
+        let policy = self.tolerance_policy;
+        self.sub_objects[id].recalc_radius_with(&policy);
+    }
+
+    // moves the origin of every subobject in root's subtree by delta, leaving everything where it is in model space
+    // unlike subobj_move_only_offset, which moves one origin and compensates its children, the whole subtree's origins move together,
+    // as when an import has every pivot off by the same amount, so only root's offset changes
+    pub fn rebase_subtree_offsets(&mut self, root: ObjectId, delta: Vec3d) {
+        if root.0 as usize >= self.sub_objects.len() {
+            return;
+        }
+        let subtree = (self.sub_objects.iter())
+            .filter(|subobj| self.is_obj_id_ancestor(subobj.obj_id, root))
+            .map(|subobj| subobj.obj_id)
+            .collect::<Vec<_>>();
+
+        let policy = self.tolerance_policy;
+        self.sub_objects[root].offset += delta;
+        for &id in &subtree {
+            let subobj = &mut self.sub_objects[id];
+            for vert in &mut subobj.bsp_data.verts {
+                *vert -= delta;
+            }
+            subobj.bsp_data.collision_tree = BspData::recalculate_with(
+                &subobj.bsp_data.verts,
+                std::mem::take(&mut subobj.bsp_data.collision_tree).into_leaves().map(|(_, poly)| poly),
+                &policy,
+            );
+            subobj.bbox = *subobj.bsp_data.collision_tree.bbox();
+            subobj.recalc_radius_with(&policy);
+        }
+
+        // these are relative to their subobject
+        for turret in &mut self.turrets {
+            if subtree.contains(&turret.gun_obj) {
+                for point in &mut turret.fire_points {
+                    *point -= delta;
+                }
+            }
+        }
+        for eye in &mut self.eye_points {
+            if matches!(eye.attached_subobj, Some(id) if subtree.contains(&id)) {
+                eye.position -= delta;
+            }
+        }
+        for attachment in &mut self.attachments {
+            if matches!(attachment.parent, Some(id) if subtree.contains(&id)) {
+                attachment.position -= delta;
+            }
+        }
+
+        self.recheck_warnings(Set::All);
+    }
+
+    // the fix for Warning::Detail0NonZeroOffset, moves detail0's offset into its geometry, so nothing moves in model space
+    pub fn zero_detail0_offset(&mut self) {
+        if let Some(detail0) = self.detail0_id() {
+            self.subobj_move_only_offset(detail0, Vec3d::ZERO);
+        }
+        self.recheck_warnings(Set::One(Warning::Detail0NonZeroOffset));
     }
 
     pub fn recalc_radius(&mut self) {
-        self.header.max_radius = 0.00001;
-        if let Some(&detail_0) = self.header.detail_levels.first() {
+        self.header.max_radius = self.tolerance_policy.min_extent;
+        if let Some(detail_0) = self.detail0_id() {
             for subobj in &self.sub_objects {
                 if !self.is_obj_id_ancestor(subobj.obj_id, detail_0) {
                     continue;
@@ -2495,10 +4079,11 @@ impl Model {
 
     pub fn recalc_bbox(&mut self) {
         let mut new_bbox = self.header.bbox;
-        new_bbox.min = Vec3d { x: -0.00001, y: -0.00001, z: -0.00001 };
-        new_bbox.max = Vec3d { x: 0.00001, y: 0.00001, z: 0.00001 };
+        let min_extent = self.tolerance_policy.min_extent;
+        new_bbox.min = Vec3d { x: -min_extent, y: -min_extent, z: -min_extent };
+        new_bbox.max = Vec3d { x: min_extent, y: min_extent, z: min_extent };
 
-        if let Some(&detail_0) = self.header.detail_levels.first() {
+        if let Some(detail_0) = self.detail0_id() {
             for subobj in &self.sub_objects {
                 if !self.is_obj_id_ancestor(subobj.obj_id, detail_0) {
                     continue;
@@ -2524,6 +4109,42 @@ impl Model {
         self.header.bbox = new_bbox;
     }
 
+    // which subobject's vertices reach furthest out on each side of the model's bbox, for finding the one blowing it up
+    // one entry per face: (subobject, axis, whether it's the max side), min then max for x, y and z; the first subobject wins ties
+    // like recalc_bbox this only looks at the detail0 hierarchy, but it goes by the vertices themselves in model space rather than
+    // the subobjects' bboxes, so the minimum extent isn't counted; empty if there are no vertices
+    pub fn bbox_contributors(&self) -> Vec<(ObjectId, Axis, bool)> {
+        let Some(detail0) = self.detail0_id() else {
+            return vec![];
+        };
+
+        // (value, subobject) for the min and max side of each axis
+        let mut extremes: [[Option<(f32, ObjectId)>; 2]; 3] = [[None; 2]; 3];
+        for subobj in &self.sub_objects {
+            if !self.is_obj_id_ancestor(subobj.obj_id, detail0) {
+                continue;
+            }
+            let offset = self.get_total_subobj_offset(subobj.obj_id);
+            for &vert in &subobj.bsp_data.verts {
+                let vert = vert + offset;
+                for (i, axis) in ALL_AXES.into_iter().enumerate() {
+                    let [min, max] = &mut extremes[i];
+                    if !matches!(min, Some((val, _)) if *val <= vert[axis]) {
+                        *min = Some((vert[axis], subobj.obj_id));
+                    }
+                    if !matches!(max, Some((val, _)) if *val >= vert[axis]) {
+                        *max = Some((vert[axis], subobj.obj_id));
+                    }
+                }
+            }
+        }
+
+        (ALL_AXES.into_iter().zip(extremes))
+            .flat_map(|(axis, [min, max])| [(min, false), (max, true)].map(|(extreme, is_max)| extreme.map(|(_, id)| (id, axis, is_max))))
+            .flatten()
+            .collect()
+    }
+
     pub fn recalc_mass(&mut self) {
         self.header.mass = 4.65 * (self.header.bbox.volume().powf(2.0 / 3.0));
     }
@@ -2535,8 +4156,11 @@ impl Model {
             subobjects[id].bsp_data.verts.len() + subobjects[id].children.iter().map(|id| sum_verts_recurse(subobjects, *id)).sum::<usize>()
         }
 
-        if let Some(&detail_0) = self.header.detail_levels.first() {
+        if let Some(detail_0) = self.detail0_id() {
             let num_verts = sum_verts_recurse(&self.sub_objects, detail_0);
+            if num_verts == 0 {
+                return;
+            }
 
             fn add_point_mass_moi(moi: &mut Matrix3<f64>, pos: Vec3d) {
                 moi.column_mut(0).x += (pos.y * pos.y + pos.z * pos.z) as f64;
@@ -2561,94 +4185,653 @@ impl Model {
 
             let point_mass = self.header.mass as f64 / num_verts as f64;
             new_moi *= point_mass;
-            new_moi = new_moi.try_inverse().unwrap();
-            self.header.moment_of_inertia = new_moi.cast::<f32>().into();
+            // collinear or single point geometry has no inverse, leave it at the default
+            if let Some(new_moi) = new_moi.try_inverse() {
+                self.header.moment_of_inertia = new_moi.cast::<f32>().into();
+            }
+        }
+    }
+
+    // sqrt(I / mass) about the x, y and z axes, the distance from the center of mass at which all the mass would turn the same
+    // like recalc_moi, the header holds the inverse of the inertia tensor, so this inverts it back first
+    // zero if the mass isn't positive or the moment of inertia has no inverse
+    pub fn radius_of_gyration(&self) -> Vec3d {
+        let mass = self.header.mass;
+        if mass <= 0.0 || mass.is_nan() {
+            return Vec3d::ZERO;
+        }
+        match glm::Mat3x3::from(self.header.moment_of_inertia).try_inverse() {
+            Some(inertia) => {
+                let radius = |moment: f32| (moment.max(0.0) / mass).sqrt();
+                Vec3d::new(radius(inertia[(0, 0)]), radius(inertia[(1, 1)]), radius(inertia[(2, 2)]))
+            }
+            None => Vec3d::ZERO,
         }
     }
 
     /// returns the surface area of detail0 and its children, and the average surface area position
     pub fn surface_area_average_pos(&self) -> (f32, Vec3d) {
-        let mut surface_area = 0.0;
-        let mut weighted_avg = Vec3d::ZERO;
-        let detail0 = if let Some(id) = self.header.detail_levels.first() {
+        let mut surface_area = 0.0_f64;
+        let mut weighted_avg = [0.0_f64; 3];
+        let detail0 = if let Some(id) = self.detail0_id() {
             id
         } else {
             return (0.0, Vec3d::ZERO);
         };
 
-        self.do_for_recursive_subobj_children(*detail0, &mut |subobj| {
+        self.do_for_recursive_subobj_children(detail0, &mut |subobj| {
             let (this_area, this_avg) = subobj.surface_area_average_pos();
-            weighted_avg += this_avg * this_area;
-            surface_area += this_area;
+            // each subobject's average is relative to its own origin
+            let this_avg = this_avg + self.get_total_subobj_offset(subobj.obj_id);
+            weighted_avg[0] += this_avg.x as f64 * this_area as f64;
+            weighted_avg[1] += this_avg.y as f64 * this_area as f64;
+            weighted_avg[2] += this_avg.z as f64 * this_area as f64;
+            surface_area += this_area as f64;
         });
 
-        (surface_area, weighted_avg / surface_area)
+        if surface_area == 0.0 {
+            return (0.0, Vec3d::ZERO);
+        }
+        let [x, y, z] = weighted_avg.map(|val| (val / surface_area) as f32);
+        (surface_area as f32, Vec3d { x, y, z })
+    }
+
+    pub fn recalc_visual_center(&mut self, method: VisualCenterMethod) {
+        self.visual_center = match method {
+            VisualCenterMethod::SurfaceAreaAverage => match self.surface_area_average_pos() {
+                (area, center) if area > 0.0 => center,
+                _ => Vec3d::ZERO,
+            },
+            VisualCenterMethod::BBoxCenter if self.header.bbox.is_inverted() => Vec3d::ZERO,
+            VisualCenterMethod::BBoxCenter => self.header.bbox.center(),
+            VisualCenterMethod::CenterOfMass => self.header.center_of_mass,
+        };
+        self.recheck_warnings(Set::One(Warning::AutocenterFlagWithoutVisualCenter));
+        self.recheck_warnings(Set::One(Warning::VisualCenterOutsideDetail0));
     }
 
-    pub fn recalc_all_children_ids(&mut self) {
-        for subobj in self.sub_objects.iter_mut() {
-            subobj.children.clear();
+    pub fn targeting_reference(&self) -> TargetingInfo {
+        let autocentered = !self.visual_center.is_null();
+        let center = if autocentered { self.visual_center } else { Vec3d::ZERO };
+        let bbox = &self.header.bbox;
+        let centered_radius = if bbox.is_inverted() {
+            0.0
+        } else {
+            // the furthest corner from center
+            let [x, y, z] = [
+                (bbox.min.x, bbox.max.x, center.x),
+                (bbox.min.y, bbox.max.y, center.y),
+                (bbox.min.z, bbox.max.z, center.z),
+            ]
+            .map(|(min, max, center)| (min - center).abs().max((max - center).abs()));
+            Vec3d { x, y, z }.magnitude()
+        };
+        TargetingInfo {
+            center,
+            autocentered,
+            bbox_center: if bbox.is_inverted() { Vec3d::ZERO } else { bbox.center() },
+            radius: self.header.max_radius,
+            centered_radius,
         }
+    }
 
-        for i in 0..self.sub_objects.len() {
-            if let Some(parent) = self.sub_objects.0[i].parent {
-                let id = self.sub_objects.0[i].obj_id;
-                self.sub_objects[parent].children.push(id);
+    // how far the model moves on screen in the target box and tech room with autocentering, compared to without
+    // without a visual center this is for the one recalc_visual_center would pick, for deciding whether one is worth having
+    pub fn simulate_autocenter_shift(&self) -> Vec3d {
+        let visual_center = if self.visual_center.is_null() {
+            match self.surface_area_average_pos() {
+                (area, center) if area > 0.0 => center,
+                _ => Vec3d::ZERO,
             }
-        }
+        } else {
+            self.visual_center
+        };
+        // the view is centered on the visual center instead of the origin, so the model moves the other way
+        -visual_center
     }
 
-    pub fn recalc_semantic_name_links(&mut self) {
-        // clear everything first
-        for subobj in self.sub_objects.iter_mut() {
-            subobj.name_links.clear();
-        }
+    fn weapon_bank_has_duplicates(&self, primary: bool, bank: usize) -> bool {
+        let banks = if primary { &self.primary_weps } else { &self.secondary_weps };
+        let Some(points) = banks.get(bank) else { return false };
+        (points.iter().enumerate()).any(|(i, point1)| {
+            points[i + 1..]
+                .iter()
+                .any(|point2| points_coincide((point1.position, point1.normal.0), (point2.position, point2.normal.0)))
+        })
+    }
 
-        for i in (0..self.sub_objects.len()).map(|i| ObjectId(i as u32)) {
-            let mut name1 = &self.sub_objects[i].name;
-            if let Some((_, debris_of)) = name1.split_once("debris-") {
-                if let Some(obj) = self.sub_objects.iter().find(|obj| debris_of.starts_with(&obj.name)) {
-                    let j = obj.obj_id;
-                    self.sub_objects[j].name_links.push(NameLink::LiveDebris(i));
-                    self.sub_objects[i].name_links.push(NameLink::LiveDebrisOf(j));
-                    name1 = &self.sub_objects[i].name;
-                }
-            }
-            if let Some(destroyed_of) = name1.strip_suffix("-destroyed") {
-                if let Some(obj) = self.sub_objects.iter().find(|obj| obj.name == destroyed_of) {
-                    let j = obj.obj_id;
-                    self.sub_objects[j].name_links.push(NameLink::DestroyedVersion(i));
-                    self.sub_objects[i].name_links.push(NameLink::DestroyedVersionOf(j));
-                    name1 = &self.sub_objects[i].name;
-                }
-            }
-            for j in (0..self.sub_objects.len()).map(|i| ObjectId(i as u32)) {
-                let name2 = &self.sub_objects[j].name;
-                if name1.len() == name2.len() && self.sub_objects[j].parent.is_some() && self.sub_objects[i].parent.is_some() {
-                    // zip them together and filter for equal characters, leaving only the remaining, differing characters
-                    let mut iter = name1.chars().zip(name2.chars()).filter(|(c1, c2)| c1 != c2);
-                    // grab the characters that differ and don't continue if there's more than one,
-                    // and check that they're 'a' and 'b'..='h' respectively
-                    if let (Some(('a', ch @ 'b'..='h')), None) = (iter.next(), iter.next()) {
-                        let level = ch as u8 - 'a' as u8;
-                        self.sub_objects[j].name_links.push(NameLink::DetailLevelOf(i, level));
-                        self.sub_objects[i].name_links.push(NameLink::DetailLevel(j, level));
-                        name1 = &self.sub_objects[i].name;
-                    }
-                }
-            }
-        }
+    // only the later of two coincident docking bays is flagged
+    fn docking_bay_is_duplicate(&self, idx: usize) -> bool {
+        let Some(bay) = self.docking_bays.get(idx) else { return false };
+        (self.docking_bays[..idx].iter()).any(|other| points_coincide((bay.position, bay.fvec.0), (other.position, other.fvec.0)))
     }
 
-    pub fn clean_up(&mut self) {
-        if let Some(shield) = &mut self.shield_data {
-            if shield.collision_tree.is_none() {
-                shield.collision_tree = Some(ShieldData::recalculate_tree(&shield.verts, &shield.polygons));
-            }
+    // likewise the later of two eye points, which have to be on the same subobject
+    fn eye_point_is_duplicate(&self, idx: usize) -> bool {
+        let Some(eye) = self.eye_points.get(idx) else { return false };
+        (self.eye_points[..idx].iter()).any(|other| {
+            other.attached_subobj == eye.attached_subobj && points_coincide((eye.position, eye.normal.0), (other.position, other.normal.0))
+        })
+    }
+
+    // any polygon uvs further outside of 0-1 than float noise from an exporter would put them
+    fn has_uvs_outside_texture(&self) -> bool {
+        let outside = |val: f32| !(-0.001..=1.001).contains(&val);
+        (self.sub_objects.iter())
+            .flat_map(|subobj| subobj.bsp_data.collision_tree.leaves())
+            .any(|(_, poly)| poly.verts.iter().any(|vert| outside(vert.uv.0) || outside(vert.uv.1)))
+    }
+
+    // a visual center away from the hull puts the target box and brackets off the ship
+    fn visual_center_outside_detail0(&self) -> bool {
+        match self.detail0() {
+            Some(detail0) if !self.visual_center.is_null() && !detail0.bbox.is_inverted() => !self
+                .tolerance_policy
+                .pad_bbox(&detail0.bbox)
+                .contains(self.visual_center - detail0.offset),
+            _ => false,
         }
     }
 
-    pub fn make_orphan(&mut self, would_be_orphan: ObjectId) {
+    // the radius of the piecewise linear cross section profile at this depth, held constant past either end
+    // None if there are no cross sections
+    pub fn cross_section_radius_at(&self, depth: f32) -> Option<f32> {
+        let sections = &self.header.cross_sections;
+        let (first, last) = (sections.first()?, sections.last()?);
+        if depth <= first.0 {
+            return Some(first.1);
+        } else if depth >= last.0 {
+            return Some(last.1);
+        }
+        let idx = sections.partition_point(|&(section_depth, _)| section_depth <= depth);
+        let ((depth1, radius1), (depth2, radius2)) = (sections[idx - 1], sections[idx]);
+        let t = (depth - depth1) / (depth2 - depth1);
+        Some(radius1 + (radius2 - radius1) * t)
+    }
+
+    // adds a cross section at this depth, in sorted position, with the radius the profile already has there
+    // with no other cross sections the radius is the model's radius; if one is already at this depth, that one's index is returned
+    pub fn insert_cross_section(&mut self, depth: f32) -> usize {
+        let sections = &self.header.cross_sections;
+        if let Some(idx) = sections.iter().position(|&(section_depth, _)| section_depth == depth) {
+            return idx;
+        }
+        let radius = self.cross_section_radius_at(depth).unwrap_or(self.header.max_radius);
+        let idx = sections.partition_point(|&(section_depth, _)| section_depth < depth);
+        self.header.cross_sections.insert(idx, (depth, radius));
+        self.recheck_warnings(Set::One(Warning::InvalidCrossSections));
+        idx
+    }
+
+    pub fn remove_cross_section(&mut self, idx: usize) {
+        if idx < self.header.cross_sections.len() {
+            self.header.cross_sections.remove(idx);
+            self.recheck_warnings(Set::One(Warning::InvalidCrossSections));
+        }
+    }
+
+    // moves the cross section, keeping the list sorted by depth, and returns its new index
+    pub fn set_cross_section(&mut self, idx: usize, depth: f32, radius: f32) -> Result<usize, CrossSectionError> {
+        let sections = &mut self.header.cross_sections;
+        if idx >= sections.len() {
+            return Err(CrossSectionError::InvalidIndex(idx));
+        }
+        if let Some(other) = (sections.iter().enumerate()).position(|(i, &(section_depth, _))| i != idx && section_depth == depth) {
+            return Err(CrossSectionError::DuplicateDepth(other));
+        }
+        sections.remove(idx);
+        let new_idx = sections.partition_point(|&(section_depth, _)| section_depth < depth);
+        sections.insert(new_idx, (depth, radius));
+        self.recheck_warnings(Set::One(Warning::InvalidCrossSections));
+        Ok(new_idx)
+    }
+
+    pub fn set_insignia_detail_level(&mut self, idx: usize, lod: u32) -> Result<(), InsigniaError> {
+        if lod as usize >= self.header.detail_levels.len() {
+            return Err(InsigniaError::InvalidDetailLevel(lod));
+        }
+        let insignia = self.insignias.get_mut(idx).ok_or(InsigniaError::InvalidIndex(idx))?;
+        insignia.detail_level = lod;
+        self.recheck_warnings(Set::One(Warning::InsigniaInvalidDetailLevel(idx)));
+        Ok(())
+    }
+
+    // swaps which subobjects are the two detail levels, and moves the insignias along with them
+    pub fn swap_detail_levels(&mut self, a: usize, b: usize) {
+        self.header.detail_levels.swap(a, b);
+        for insignia in &mut self.insignias {
+            if insignia.detail_level as usize == a {
+                insignia.detail_level = b as u32;
+            } else if insignia.detail_level as usize == b {
+                insignia.detail_level = a as u32;
+            }
+        }
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+    }
+
+    pub fn insignia_summary(&self) -> Vec<InsigniaSummary> {
+        (self.insignias.iter())
+            .map(|insignia| {
+                let mut area = 0.0;
+                let mut weighted = Vec3d::ZERO;
+                for (vert1, vert2, vert3) in &insignia.faces {
+                    let [v1, v2, v3] = [vert1, vert2, vert3].map(|vert| insignia.vertices[vert.vertex_id.0 as usize]);
+                    let face_area = (v2 - v1).cross(&(v3 - v1)).magnitude() / 2.0;
+                    area += face_area;
+                    weighted += (v1 + v2 + v3) * (face_area / 3.0);
+                }
+                let center = if area > 0.0 { weighted / area } else { Vec3d::ZERO };
+                InsigniaSummary {
+                    detail_level: insignia.detail_level,
+                    faces: insignia.faces.len(),
+                    area,
+                    centroid: center + insignia.offset,
+                }
+            })
+            .collect()
+    }
+
+    pub fn precision_report(&self) -> PrecisionReport {
+        let mut report = PrecisionReport::default();
+
+        for subobj in &self.sub_objects {
+            if subobj.bsp_data.verts.is_empty() {
+                continue;
+            }
+
+            // model space magnitude, going by the bbox corners
+            let offset = self.get_total_subobj_offset(subobj.obj_id);
+            for corner in [subobj.bbox.min, subobj.bbox.max] {
+                let pos = corner + offset;
+                report.max_coordinate = report.max_coordinate.max(pos.x.abs()).max(pos.y.abs()).max(pos.z.abs());
+            }
+
+            // what recalc_subobj_offset would get us
+            let local_max = subobj
+                .bsp_data
+                .verts
+                .iter()
+                .fold(0.0_f32, |max, vert| max.max(vert.x.abs()).max(vert.y.abs()).max(vert.z.abs()));
+            let center = Vec3d::average(subobj.bsp_data.verts.iter().copied());
+            let recentered_max = subobj.bsp_data.verts.iter().fold(0.0_f32, |max, vert| {
+                let vert = *vert - center;
+                max.max(vert.x.abs()).max(vert.y.abs()).max(vert.z.abs())
+            });
+            if f32_quantization(local_max) > f32_quantization(recentered_max) * 2.0 {
+                report.off_center_subobjects.push(OffCenterSubObject {
+                    id: subobj.obj_id,
+                    quantization: f32_quantization(local_max),
+                    recentered_quantization: f32_quantization(recentered_max),
+                });
+            }
+        }
+
+        report.worst_quantization = f32_quantization(report.max_coordinate);
+        report.exceeds_budget = report.worst_quantization > self.precision_budget.unwrap_or(DEFAULT_PRECISION_BUDGET);
+        report
+    }
+
+    // a guess at which way the model is meant to face, based on the fact that ships are generally longest along their direction of travel
+    // the sign is decided by the primary weapons, which tend to be toward the front, otherwise it favors +Z, which is forward for FSO
+    // meant as a sanity check for mis-oriented imports, so don't rely on it for anything else
+    pub fn guess_forward(&self) -> Vec3d {
+        const FORWARD: Vec3d = Vec3d::new(0.0, 0.0, 1.0);
+        let detail0 = match self.detail0_id() {
+            Some(id) => id,
+            None => return FORWARD,
+        };
+
+        // principal axis of the detail0 vertices about the center of mass
+        let center = self.header.center_of_mass;
+        let mut covariance = Matrix3::<f64>::zeros();
+        for subobj in &self.sub_objects {
+            if !self.is_obj_id_ancestor(subobj.obj_id, detail0) {
+                continue;
+            }
+            let offset = self.get_total_subobj_offset(subobj.obj_id) - center;
+            for &vert in &subobj.bsp_data.verts {
+                let v = nalgebra::Vector3::<f64>::new((vert.x + offset.x).into(), (vert.y + offset.y).into(), (vert.z + offset.z).into());
+                covariance += v * v.transpose();
+            }
+        }
+
+        let eigen = covariance.symmetric_eigen();
+        let (axis_idx, _) = eigen.eigenvalues.argmax();
+        let axis = eigen.eigenvectors.column(axis_idx);
+        let axis = Vec3d::new(axis.x as f32, axis.y as f32, axis.z as f32);
+        if axis.is_null() {
+            return FORWARD;
+        }
+
+        let guns = self.primary_weps.iter().flatten().map(|point| point.position - center);
+        let toward_guns = if self.primary_weps.iter().any(|bank| !bank.is_empty()) {
+            axis.dot(&Vec3d::average(guns))
+        } else {
+            0.0
+        };
+
+        let sign = if toward_guns != 0.0 { toward_guns } else { axis.dot(&FORWARD) };
+        if sign < 0.0 {
+            -axis.normalize()
+        } else {
+            axis.normalize()
+        }
+    }
+
+    // a box around detail0 and its children which needn't be axis aligned, as the rotation and the box in its frame, so that
+    // rvec * x + uvec * y + fvec * z for a point (x, y, z) in the box is a point in the model
+    // the axes come from the principal components of the vertices, so it's not the strictly smallest box, but never worse than
+    // the axis aligned one, which it falls back to along with the identity rotation
+    // the axes are ordered and signed to be as close to the model's own as possible
+    pub fn oriented_bounding_box(&self) -> (Mat3d, BoundingBox) {
+        let detail0 = match self.detail0_id() {
+            Some(id) => id,
+            None => return (Mat3d::IDENTITY, BoundingBox::EMPTY),
+        };
+
+        let mut verts = vec![];
+        for subobj in &self.sub_objects {
+            if self.is_obj_id_ancestor(subobj.obj_id, detail0) {
+                let offset = self.get_total_subobj_offset(subobj.obj_id);
+                verts.extend(subobj.bsp_data.verts.iter().map(|&vert| vert + offset));
+            }
+        }
+
+        oriented_box_around(&verts)
+    }
+
+    // how far detail0 and its children reach from the view axis along dir through the model's origin, which is where the
+    // engine centers targeting brackets; like the header radius, but for the ship seen from one side
+    // 0 if dir is zero or there is no detail0
+    pub fn silhouette_radius(&self, dir: Vec3d) -> f32 {
+        let Some(detail0) = self.detail0_id().filter(|_| !dir.is_null()) else {
+            return 0.0;
+        };
+        let dir = dir.normalize();
+
+        let mut max_sq = 0.0_f32;
+        for subobj in &self.sub_objects {
+            if self.is_obj_id_ancestor(subobj.obj_id, detail0) {
+                let offset = self.get_total_subobj_offset(subobj.obj_id);
+                for &vert in &subobj.bsp_data.verts {
+                    let vert = vert + offset;
+                    max_sq = max_sq.max(vert.magnitude_squared() - vert.dot(&dir).powi(2));
+                }
+            }
+        }
+        max_sq.max(0.0).sqrt()
+    }
+
+    // random points over the surface of detail0 and its children, with interpolated normals
+    // the result is deterministic for a given seed
+    pub fn surface_samples(&self, density_per_area: f32, seed: u64) -> Vec<SurfaceSample> {
+        self.surface_samples_with(density_per_area, seed, SampleMode::Uniform)
+    }
+
+    pub fn surface_samples_with(&self, density_per_area: f32, seed: u64, mode: SampleMode) -> Vec<SurfaceSample> {
+        struct Triangle<'a> {
+            subobj: &'a SubObject,
+            offset: Vec3d,
+            texture: TextureId,
+            verts: [&'a PolyVertex; 3],
+            area: f32,
+        }
+
+        let detail0 = match self.detail0_id() {
+            Some(id) => id,
+            None => return vec![],
+        };
+
+        // fan triangulate everything in the detail0 hierarchy
+        let mut triangles = vec![];
+        for subobj in &self.sub_objects {
+            if !self.is_obj_id_ancestor(subobj.obj_id, detail0) {
+                continue;
+            }
+            let offset = self.get_total_subobj_offset(subobj.obj_id);
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+                if let [first, rest @ ..] = &*poly.verts {
+                    for pair in rest.windows(2) {
+                        let verts = [first, &pair[0], &pair[1]];
+                        let [v1, v2, v3] = verts.map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize]);
+                        let area = (v2 - v1).cross(&(v3 - v1)).magnitude() / 2.0;
+                        triangles.push(Triangle { subobj, offset, texture: poly.texture, verts, area });
+                    }
+                }
+            }
+        }
+
+        fn sample_triangle(tri: &Triangle, rng: &mut SeededRng) -> SurfaceSample {
+            // uniform barycentric coordinates
+            let (r1, r2) = (rng.next_f32().sqrt(), rng.next_f32());
+            let weights = [1.0 - r1, r1 * (1.0 - r2), r1 * r2];
+
+            let mut position = Vec3d::ZERO;
+            let mut normal = Vec3d::ZERO;
+            for (vert, weight) in tri.verts.iter().zip(weights) {
+                position += tri.subobj.bsp_data.verts[vert.vertex_id.0 as usize] * weight;
+                normal += tri.subobj.bsp_data.norms[vert.normal_id.0 as usize] * weight;
+            }
+            if normal.is_null() {
+                let [v1, v2, v3] = tri.verts.map(|vert| tri.subobj.bsp_data.verts[vert.vertex_id.0 as usize]);
+                normal = (v2 - v1).cross(&(v3 - v1));
+            }
+
+            SurfaceSample {
+                position: position + tri.offset,
+                normal: normal.normalize(),
+                subobj: tri.subobj.obj_id,
+                texture: tri.texture,
+            }
+        }
+
+        let mut rng = SeededRng::new(seed);
+        let mut samples = vec![];
+        match mode {
+            SampleMode::Uniform => {
+                // accumulate in f64, there may be a lot of small triangles
+                let mut cumulative_areas = Vec::with_capacity(triangles.len());
+                let mut total_area = 0.0_f64;
+                for tri in &triangles {
+                    total_area += tri.area as f64;
+                    cumulative_areas.push(total_area);
+                }
+
+                let num_samples = (total_area * density_per_area as f64).round() as usize;
+                for _ in 0..num_samples {
+                    let target = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64 * total_area;
+                    let idx = cumulative_areas.partition_point(|&area| area <= target).min(triangles.len() - 1);
+                    samples.push(sample_triangle(&triangles[idx], &mut rng));
+                }
+            }
+            SampleMode::Stratified => {
+                for tri in &triangles {
+                    // stochastic rounding keeps the total in proportion to the area
+                    let expected = tri.area * density_per_area;
+                    let num_samples = (expected + rng.next_f32()).floor().max(1.0) as usize;
+                    for _ in 0..num_samples {
+                        samples.push(sample_triangle(tri, &mut rng));
+                    }
+                }
+            }
+        }
+
+        samples
+    }
+
+    // whether collision checks reach this subobject's geometry, taking "$no_collisions" on its ancestors into account
+    pub fn subobj_collides(&self, id: ObjectId) -> bool {
+        if !self.subobj_check(id, SubObject::collides) {
+            return false;
+        }
+        let mut parent = self.sub_objects[id].parent;
+        while let Some(id) = parent {
+            if properties_find_field(&self.sub_objects[id].properties, "$no_collisions").is_some() {
+                return false;
+            }
+            parent = self.sub_objects[id].parent;
+        }
+        true
+    }
+
+    // finds the nearest hit along the ray against the detail0 hierarchy, skipping subobjects which don't collide
+    // subobjects are tested at their rest positions
+    pub fn raycast(&self, origin: Vec3d, dir: Vec3d) -> Option<RayHit> {
+        self.raycast_filtered(origin, dir, |_| true)
+    }
+
+    // the same, only testing the subobjects include accepts
+    pub(crate) fn raycast_filtered(&self, origin: Vec3d, dir: Vec3d, include: impl Fn(ObjectId) -> bool) -> Option<RayHit> {
+        let detail0 = self.detail0_id()?;
+        let mut best: Option<RayHit> = None;
+
+        for subobj in &self.sub_objects {
+            if !self.is_obj_id_ancestor(subobj.obj_id, detail0) || !self.subobj_collides(subobj.obj_id) || !include(subobj.obj_id) {
+                continue;
+            }
+            let offset = self.get_total_subobj_offset(subobj.obj_id);
+            for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+                if let [first, rest @ ..] = &*poly.verts {
+                    for pair in rest.windows(2) {
+                        let tri = [first, &pair[0], &pair[1]].map(|vert| subobj.bsp_data.verts[vert.vertex_id.0 as usize] + offset);
+                        let Some(distance) = ray_triangle_distance(origin, dir, tri) else {
+                            continue;
+                        };
+                        if !matches!(best, Some(hit) if hit.distance <= distance) {
+                            best = Some(RayHit {
+                                distance,
+                                position: origin + dir * distance,
+                                subobj: subobj.obj_id,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        best
+    }
+
+    // the fraction of the hull which is protected by the shield, found by casting outward from points sampled over detail0
+    // and checking whether each ray passes through the shield mesh; 0 if there is no shield or no hull
+    pub fn shield_coverage(&self) -> f32 {
+        const NUM_SAMPLES: f32 = 2000.0;
+        let Some(shield) = &self.shield_data else { return 0.0 };
+        let (area, _) = self.surface_area_average_pos();
+        if area <= 0.0 {
+            return 0.0;
+        }
+
+        let triangles = (shield.polygons.iter())
+            .map(|poly| [poly.verts.0, poly.verts.1, poly.verts.2].map(|id| shield.verts[id.0 as usize]))
+            .collect::<Vec<_>>();
+        let samples = self.surface_samples_with(NUM_SAMPLES / area, 0, SampleMode::Uniform);
+        if samples.is_empty() {
+            return 0.0;
+        }
+        let covered = (samples.iter())
+            .filter(|sample| (triangles.iter()).any(|&tri| ray_triangle_distance(sample.position, sample.normal, tri).is_some()))
+            .count();
+        covered as f32 / samples.len() as f32
+    }
+
+    // the version the model was loaded as, or its current version if it wasn't loaded from a pof
+    pub fn loaded_version(&self) -> Version {
+        self.disk_version.unwrap_or(self.version)
+    }
+
+    fn version_downgraded(&self) -> bool {
+        matches!(self.disk_version, Some(disk_version) if self.version < disk_version)
+    }
+
+    pub fn recalc_all_children_ids(&mut self) {
+        for subobj in self.sub_objects.iter_mut() {
+            subobj.children.clear();
+        }
+
+        for i in 0..self.sub_objects.len() {
+            if let Some(parent) = self.sub_objects.0[i].parent {
+                let id = self.sub_objects.0[i].obj_id;
+                self.sub_objects[parent].children.push(id);
+            }
+        }
+    }
+
+    pub fn recalc_semantic_name_links(&mut self) {
+        // clear everything first
+        for subobj in self.sub_objects.iter_mut() {
+            subobj.name_links.clear();
+        }
+
+        for i in (0..self.sub_objects.len()).map(|i| ObjectId(i as u32)) {
+            let mut name1 = &self.sub_objects[i].name;
+            if let Some((_, debris_of)) = name1.split_once("debris-") {
+                if let Some(obj) = self.sub_objects.iter().find(|obj| debris_of.starts_with(&obj.name)) {
+                    let j = obj.obj_id;
+                    self.sub_objects[j].name_links.push(NameLink::LiveDebris(i));
+                    self.sub_objects[i].name_links.push(NameLink::LiveDebrisOf(j));
+                    name1 = &self.sub_objects[i].name;
+                }
+            }
+            if let Some(destroyed_of) = name1.strip_suffix("-destroyed") {
+                if let Some(obj) = self.sub_objects.iter().find(|obj| obj.name == destroyed_of) {
+                    let j = obj.obj_id;
+                    self.sub_objects[j].name_links.push(NameLink::DestroyedVersion(i));
+                    self.sub_objects[i].name_links.push(NameLink::DestroyedVersionOf(j));
+                    name1 = &self.sub_objects[i].name;
+                }
+            }
+            for j in (0..self.sub_objects.len()).map(|i| ObjectId(i as u32)) {
+                let name2 = &self.sub_objects[j].name;
+                if name1.len() == name2.len() && self.sub_objects[j].parent.is_some() && self.sub_objects[i].parent.is_some() {
+                    // zip them together and filter for equal characters, leaving only the remaining, differing characters
+                    let mut iter = name1.chars().zip(name2.chars()).filter(|(c1, c2)| c1 != c2);
+                    // grab the characters that differ and don't continue if there's more than one,
+                    // and check that they're 'a' and 'b'..='h' respectively
+                    if let (Some(('a', ch @ 'b'..='h')), None) = (iter.next(), iter.next()) {
+                        let level = ch as u8 - 'a' as u8;
+                        self.sub_objects[j].name_links.push(NameLink::DetailLevelOf(i, level));
+                        self.sub_objects[i].name_links.push(NameLink::DetailLevel(j, level));
+                        name1 = &self.sub_objects[i].name;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn clean_up(&mut self) {
+        // docks edited into a bad state would otherwise be saved that way
+        for (i, dock) in self.docking_bays.iter_mut().enumerate() {
+            let (fvec_issue, uvec_issue) = dock.sanitize();
+            let issues = [(NormalField::DockFvec(i), fvec_issue), (NormalField::DockUvec(i), uvec_issue)];
+            self.normal_notices
+                .extend(issues.into_iter().filter_map(|(field, issue)| Some(NormalAudit { field, issue: issue? })));
+        }
+        self.recheck_warnings(Set::One(Warning::NormalsRepaired));
+
+        if let Some(shield) = &mut self.shield_data {
+            if shield.collision_tree.is_none() {
+                shield.collision_tree = Some(ShieldData::recalculate_tree_with(&shield.verts, &shield.polygons, &self.tolerance_policy));
+            }
+        }
+    }
+
+    // whether this subobject's geometry is accounted for by recalc_moi, i.e. it is detail0 or part of its hierarchy
+    fn contributes_to_moi(&self, obj_id: ObjectId) -> bool {
+        match self.detail0_id() {
+            Some(detail0) => self.is_obj_id_ancestor(obj_id, detail0),
+            None => false,
+        }
+    }
+
+    // returns whether the moment of inertia is now stale as a result, since it depends on the detail0 hierarchy
+    // it is left to the caller to decide to recalculate, since the header physics may have been set by hand
+    pub fn make_orphan(&mut self, would_be_orphan: ObjectId) -> bool {
+        if would_be_orphan.0 as usize >= self.sub_objects.len() {
+            return false;
+        }
+        let was_contributing = self.contributes_to_moi(would_be_orphan);
         if let Some(parent_id) = self.sub_objects[would_be_orphan].parent {
             // maintain it's current relative position to the whole model
             self.sub_objects[would_be_orphan].offset = self.get_total_subobj_offset(would_be_orphan);
@@ -2657,10 +4840,19 @@ impl Model {
             parent_children.remove(parent_children.iter().position(|child_id| *child_id == would_be_orphan).unwrap());
         }
         self.sub_objects[would_be_orphan].parent = None;
+
+        was_contributing != self.contributes_to_moi(would_be_orphan)
     }
 
-    pub fn make_parent(&mut self, new_parent: ObjectId, new_child: ObjectId) -> Option<()> {
+    // returns None if this would create a cycle, or either subobject doesn't exist
+    // otherwise whether the moment of inertia is now stale, same as make_orphan
+    pub fn make_parent(&mut self, new_parent: ObjectId, new_child: ObjectId) -> Option<bool> {
+        let num_subobjs = self.sub_objects.len();
+        if new_parent.0 as usize >= num_subobjs || new_child.0 as usize >= num_subobjs {
+            return None;
+        }
         if !self.is_obj_id_ancestor(new_parent, new_child) {
+            let was_contributing = self.contributes_to_moi(new_child);
             self.sub_objects[new_parent].children.push(new_child);
             self.sub_objects[new_child].parent = Some(new_parent);
 
@@ -2668,10 +4860,553 @@ impl Model {
             let offset_from_parents = self.get_total_subobj_offset(new_child) - self.sub_objects[new_child].offset;
             self.sub_objects[new_child].offset -= offset_from_parents;
 
-            Some(())
-        } else {
-            None
+            Some(was_contributing != self.contributes_to_moi(new_child))
+        } else {
+            None
+        }
+    }
+
+    // moves the subobject under detail0, keeping it where it is in model space
+    // returns whether the moment of inertia is now stale, same as make_orphan
+    pub fn reparent_to_detail0(&mut self, id: ObjectId) -> Result<bool, ReparentError> {
+        if id.0 as usize >= self.sub_objects.len() {
+            return Err(ReparentError::InvalidId(id));
+        }
+        let detail0 = self.detail0_id().ok_or(ReparentError::NoDetail0)?;
+        if self.header.detail_levels.contains(&id) {
+            return Err(ReparentError::DetailLevel(id));
+        }
+        if self.is_obj_id_ancestor(detail0, id) {
+            return Err(ReparentError::WouldCycle);
+        }
+
+        let orphan_stale = self.make_orphan(id);
+        let parent_stale = self.make_parent(detail0, id).unwrap();
+
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        Ok(orphan_stale != parent_stale)
+    }
+
+    // puts a new empty subobject between the children and their parent, for grouping parts which should move together
+    // the new subobject sits at the average of the children's origins, and nothing moves in model space
+    pub fn insert_intermediate_parent(&mut self, new_parent_name: String, children: &[ObjectId]) -> Result<ObjectId, InsertParentError> {
+        let Some(&first) = children.first() else {
+            return Err(InsertParentError::NoChildren);
+        };
+        if let Some(&id) = children.iter().find(|id| id.0 as usize >= self.sub_objects.len()) {
+            return Err(InsertParentError::InvalidId(id));
+        }
+        let parent = self.sub_objects[first].parent;
+        if children.iter().any(|&id| self.sub_objects[id].parent != parent) {
+            return Err(InsertParentError::DifferentParents);
+        }
+        if let Some(&id) = children.iter().find(|id| self.header.detail_levels.contains(id)) {
+            return Err(InsertParentError::DetailLevel(id));
+        }
+        if self.get_obj_id_by_name(&new_parent_name).is_some() {
+            return Err(InsertParentError::NameInUse(new_parent_name));
+        }
+
+        let new_id = ObjectId(self.sub_objects.len() as u32);
+        let mut new_subobj = SubObject {
+            obj_id: new_id,
+            parent,
+            // relative to the parent already, like the children's
+            offset: Vec3d::average(children.iter().map(|&id| self.sub_objects[id].offset)),
+            name: new_parent_name,
+            ..Default::default()
+        };
+        new_subobj.recalc_bbox_with(&self.tolerance_policy);
+        new_subobj.recalc_radius_with(&self.tolerance_policy);
+        self.sub_objects.push(new_subobj);
+        self.header.num_subobjects = self.sub_objects.len() as u32;
+        if let Some(parent) = parent {
+            self.sub_objects[parent].children.push(new_id);
+        }
+
+        for &child in children {
+            self.make_orphan(child);
+            self.make_parent(new_id, child);
+        }
+
+        self.recalc_semantic_name_links();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+        Ok(new_id)
+    }
+
+    // merges all the descendants of root into its geometry, except those in keep (and their descendants)
+    // kept subobjects are moved directly under root, and anything which referred to the merged subobjects is pointed at root instead
+    pub fn flatten_branch(&mut self, root: ObjectId, keep: &[ObjectId]) -> Result<FlattenReport, FlattenError> {
+        fn collect_merged(model: &Model, id: ObjectId, keep: &[ObjectId], merged: &mut Vec<ObjectId>, kept: &mut Vec<ObjectId>) {
+            for &child_id in &model.sub_objects[id].children {
+                if keep.contains(&child_id) {
+                    kept.push(child_id);
+                } else {
+                    merged.push(child_id);
+                    collect_merged(model, child_id, keep, merged, kept);
+                }
+            }
+        }
+
+        if root.0 as usize >= self.sub_objects.len() {
+            return Err(FlattenError::InvalidId(root));
+        }
+        let mut merged = vec![];
+        let mut kept = vec![];
+        collect_merged(self, root, keep, &mut merged, &mut kept);
+
+        // check everything first, so nothing is touched on failure
+        for (i, turret) in self.turrets.iter().enumerate() {
+            if let Some(&id) = merged.iter().find(|&&id| id == turret.base_obj || id == turret.gun_obj) {
+                return Err(FlattenError::TurretSubobjectMerged { turret: i, id });
+            }
+        }
+
+        let max = self.max_verts_norms_per_subobj();
+        let count = self.sub_objects[root].bsp_data.verts.len() + merged.iter().map(|&id| self.sub_objects[id].bsp_data.verts.len()).sum::<usize>();
+        if count > max {
+            return Err(FlattenError::TooManyVerts { count, max });
+        }
+        let count = self.sub_objects[root].bsp_data.norms.len() + merged.iter().map(|&id| self.sub_objects[id].bsp_data.norms.len()).sum::<usize>();
+        if count > max {
+            return Err(FlattenError::TooManyNorms { count, max });
+        }
+
+        let mut report = FlattenReport::default();
+        let root_offset = self.get_total_subobj_offset(root);
+
+        // kept subobjects need their offsets rebased before any of the merged offsets are lost
+        for &id in &kept {
+            if self.sub_objects[id].parent != Some(root) {
+                self.sub_objects[id].offset = self.get_total_subobj_offset(id) - root_offset;
+                self.sub_objects[id].parent = Some(root);
+                report.reparented.push(self.sub_objects[id].name.clone());
+            }
+        }
+
+        let mut polygons = std::mem::take(&mut self.sub_objects[root].bsp_data.collision_tree)
+            .into_leaves()
+            .map(|(_, poly)| poly)
+            .collect::<Vec<_>>();
+
+        for &id in &merged {
+            let relative_offset = self.get_total_subobj_offset(id) - root_offset;
+
+            let subobj = &mut self.sub_objects[id];
+            let bsp_data = std::mem::take(&mut subobj.bsp_data);
+            report.merged.push(subobj.name.clone());
+
+            let root_bsp_data = &mut self.sub_objects[root].bsp_data;
+            let vert_base = root_bsp_data.verts.len() as u32;
+            let norm_base = root_bsp_data.norms.len() as u32;
+            root_bsp_data.verts.extend(bsp_data.verts.iter().map(|&vert| vert + relative_offset));
+            root_bsp_data.norms.extend(bsp_data.norms);
+
+            polygons.extend(bsp_data.collision_tree.into_leaves().map(|(_, mut poly)| {
+                for polyvert in &mut poly.verts {
+                    polyvert.vertex_id.0 += vert_base;
+                    polyvert.normal_id.0 += norm_base;
+                }
+                poly
+            }));
+
+            // glow points are in model space unless their parent moves, which merged subobjects shouldn't
+            for (i, bank) in self.glow_banks.iter_mut().enumerate() {
+                if bank.obj_parent == id {
+                    bank.obj_parent = root;
+                    report.glow_banks_reattached.push(i);
+                }
+            }
+
+            // eye points are relative to their subobject
+            for (i, eye) in self.eye_points.iter_mut().enumerate() {
+                if eye.attached_subobj == Some(id) {
+                    eye.attached_subobj = Some(root);
+                    eye.position += relative_offset;
+                    report.eye_points_reattached.push(i);
+                }
+            }
+            for attachment in &mut self.attachments {
+                if attachment.parent == Some(id) {
+                    attachment.parent = Some(root);
+                    attachment.position += relative_offset;
+                }
+            }
+
+            // anything referring to the merged subobject by name now refers to root
+            let name = &self.sub_objects[id].name;
+            let root_name = self.sub_objects[root].name.clone();
+            for dock in &mut self.docking_bays {
+                if properties_get_field(&dock.properties, "$parent_submodel") == Some(name) {
+                    properties_update_field(&mut dock.properties, "$parent_submodel", &root_name);
+                }
+            }
+            for path in &mut self.paths {
+                if path.parent == *name {
+                    path.parent = root_name.clone();
+                }
+            }
+        }
+
+        let root_subobj = &mut self.sub_objects[root];
+        root_subobj.bsp_data.collision_tree = BspData::recalculate_with(&root_subobj.bsp_data.verts, polygons.into_iter(), &self.tolerance_policy);
+        root_subobj.bbox = *root_subobj.bsp_data.collision_tree.bbox();
+        root_subobj.recalc_radius_with(&self.tolerance_policy);
+
+        let id_map = self.remove_subobjects(&merged);
+        report.root = id_map[root.0 as usize].unwrap();
+
+        Ok(report)
+    }
+
+    // removes the given subobjects, compacting the ids of the rest, and fixing up everything which refers to them by id
+    // references to a removed subobject are dropped, so anything that should survive needs to be moved off of it beforehand
+    // returns the mapping of old ids to new ids
+    fn remove_subobjects(&mut self, ids: &[ObjectId]) -> Vec<Option<ObjectId>> {
+        let mut next_id = 0;
+        let id_map = (0..self.sub_objects.len() as u32)
+            .map(|i| {
+                if ids.contains(&ObjectId(i)) {
+                    None
+                } else {
+                    next_id += 1;
+                    Some(ObjectId(next_id - 1))
+                }
+            })
+            .collect::<Vec<_>>();
+
+        self.sub_objects.retain(|subobj| !ids.contains(&subobj.obj_id));
+        self.remap_object_ids(|id| id_map.get(id.0 as usize).copied().flatten());
+        // the untextured polygons might have all been on them
+        self.refresh_untextured_slot();
+        id_map
+    }
+
+    // deletes the subobject, fixing up everything which refers to it by id
+    // its children move up to its parent, keeping where they are in model space, see delete_subobject_and_children otherwise
+    // deleting detail0 makes the next detail level detail0, and children of a detail level end up top level, with no detail level
+    pub fn delete_subobject(&mut self, id: ObjectId) -> DeleteReport {
+        self.delete_subobject_with(id, false)
+    }
+
+    // the same, but deleting its descendants along with it
+    pub fn delete_subobject_and_children(&mut self, id: ObjectId) -> DeleteReport {
+        self.delete_subobject_with(id, true)
+    }
+
+    fn delete_subobject_with(&mut self, id: ObjectId, delete_children: bool) -> DeleteReport {
+        let mut report = DeleteReport::default();
+        // nothing to delete, which the empty report says
+        if id.0 as usize >= self.sub_objects.len() {
+            return report;
+        }
+        let mut ids = vec![id];
+        let children = self.sub_objects[id].children().copied().collect::<Vec<_>>();
+        if delete_children {
+            ids.extend(
+                (self.sub_objects.iter())
+                    .map(|subobj| subobj.obj_id)
+                    .filter(|&other| other != id && self.is_obj_id_ancestor(other, id)),
+            );
+        } else {
+            let parent = self.sub_objects[id].parent;
+            for child in children {
+                self.make_orphan(child);
+                if let Some(parent) = parent {
+                    self.make_parent(parent, child);
+                }
+                report.reparented.push(self.sub_objects[child].name.clone());
+            }
+        }
+        report.deleted = ids.iter().map(|&id| self.sub_objects[id].name.clone()).collect();
+
+        let gone = |id: &ObjectId| ids.contains(id);
+        let lost = &mut report.lost;
+        for (i, level) in self.header.detail_levels.iter().enumerate() {
+            if gone(level) {
+                lost.push(LostReference::DetailLevel(i));
+                let on_level = |insignia: &&Insignia| insignia.detail_level as usize == i;
+                lost.extend(
+                    (self.insignias.iter().enumerate())
+                        .filter(|(_, insignia)| on_level(insignia))
+                        .map(|(j, _)| LostReference::Insignia(j)),
+                );
+            }
+        }
+        for (i, turret) in self.turrets.iter().enumerate() {
+            if gone(&turret.base_obj) || gone(&turret.gun_obj) {
+                lost.push(LostReference::Turret(i));
+            }
+        }
+        for (i, bank) in self.glow_banks.iter().enumerate() {
+            if gone(&bank.obj_parent) {
+                lost.push(LostReference::GlowBank(i));
+            }
+        }
+        for (i, eye) in self.eye_points.iter().enumerate() {
+            if eye.attached_subobj.as_ref().map_or(false, gone) {
+                lost.push(LostReference::EyePoint(i));
+            }
+        }
+        for (i, attachment) in self.attachments.iter().enumerate() {
+            if attachment.parent.as_ref().map_or(false, gone) {
+                lost.push(LostReference::Attachment(i));
+            }
+        }
+        for (path_idx, path) in self.paths.iter().enumerate() {
+            for (point_idx, point) in path.points.iter().enumerate() {
+                if point.turrets.iter().any(gone) {
+                    lost.push(LostReference::PathPointTurret { path: path_idx, point: point_idx });
+                }
+            }
+            if report.deleted.contains(&path.parent) {
+                lost.push(LostReference::PathParent(path_idx));
+            }
+        }
+        for (i, dock) in self.docking_bays.iter().enumerate() {
+            if properties_get_field(&dock.properties, "$parent_submodel").map_or(false, |name| report.deleted.iter().any(|deleted| deleted == name)) {
+                lost.push(LostReference::DockParent(i));
+            }
+        }
+
+        self.remove_subobjects(&ids);
+        report
+    }
+
+    // renumbers the subobjects 0..n in their current order, fixing up everything which refers to them by id
+    // ids should always match the subobjects' positions already, but a tool building a model by hand may not have kept them so
+    // references to an id no subobject has were dangling already, and are dropped, as remove_subobjects does
+    pub fn compact_object_ids(&mut self) {
+        let mut id_map = HashMap::new();
+        for (i, subobj) in self.sub_objects.iter().enumerate() {
+            // if two share an id, references to it go to the first
+            id_map.entry(subobj.obj_id).or_insert(ObjectId(i as u32));
+        }
+        self.remap_object_ids(|id| id_map.get(&id).copied());
+    }
+
+    // gives every subobject its position as its id, and changes every reference to one as map says, dropping those it maps to None,
+    // then rebuilds everything derived from them; the subobjects should already be in their new order
+    fn remap_object_ids(&mut self, map: impl Fn(ObjectId) -> Option<ObjectId>) {
+        let map = &map;
+        for (i, subobj) in self.sub_objects.iter_mut().enumerate() {
+            subobj.obj_id = ObjectId(i as u32);
+            subobj.parent = subobj.parent.and_then(map);
+        }
+
+        // insignias follow their detail level to its new index, and one whose detail level is gone is left past the end,
+        // where InsigniaInvalidDetailLevel flags it
+        let old_detail_levels = std::mem::take(&mut self.header.detail_levels);
+        self.header.detail_levels = old_detail_levels.iter().filter_map(|&id| map(id)).collect();
+        for insignia in &mut self.insignias {
+            let new_level = (old_detail_levels.get(insignia.detail_level as usize).and_then(|&id| map(id)))
+                .and_then(|id| self.header.detail_levels.iter().position(|&level| level == id));
+            insignia.detail_level = new_level.unwrap_or(self.header.detail_levels.len()) as u32;
+        }
+        self.header.num_subobjects = self.sub_objects.len() as u32;
+
+        self.turrets.retain_mut(|turret| match (map(turret.base_obj), map(turret.gun_obj)) {
+            (Some(base_obj), Some(gun_obj)) => {
+                turret.base_obj = base_obj;
+                turret.gun_obj = gun_obj;
+                true
+            }
+            _ => false,
+        });
+
+        self.glow_banks.retain_mut(|bank| match map(bank.obj_parent) {
+            Some(id) => {
+                bank.obj_parent = id;
+                true
+            }
+            None => false,
+        });
+
+        for eye in &mut self.eye_points {
+            eye.attached_subobj = eye.attached_subobj.and_then(map);
+        }
+        for attachment in &mut self.attachments {
+            attachment.parent = attachment.parent.and_then(map);
+        }
+        for point in self.paths.iter_mut().flat_map(|path| &mut path.points) {
+            point.turrets = point.turrets.iter().filter_map(|&id| map(id)).collect();
+        }
+
+        self.recalc_all_children_ids();
+        self.recalc_semantic_name_links();
+        self.recheck_warnings(Set::All);
+        self.recheck_errors(Set::All);
+    }
+
+    // renames a subobject, updating anything which refers to it by name
+    // returns the old name, or None if there's no such subobject
+    pub fn rename_subobject(&mut self, id: ObjectId, new_name: String) -> Option<String> {
+        let old_name = std::mem::replace(&mut self.sub_objects.get_mut(id.0 as usize)?.name, new_name);
+        let new_name = &self.sub_objects[id].name;
+
+        for dock in &mut self.docking_bays {
+            if properties_get_field(&dock.properties, "$parent_submodel") == Some(&old_name) {
+                properties_update_field(&mut dock.properties, "$parent_submodel", new_name);
+            }
+        }
+        for path in &mut self.paths {
+            if path.parent == old_name {
+                path.parent = new_name.clone();
+            }
+        }
+
+        self.recalc_semantic_name_links();
+        Some(old_name)
+    }
+
+    // sets or clears the debris flag, renaming the subobject to match the "debris" naming convention
+    // debris can't have a parent, so it is also detached
+    pub fn set_debris(&mut self, id: ObjectId, debris: bool) {
+        let Some(subobj) = self.sub_objects.get_mut(id.0 as usize) else {
+            return;
+        };
+        subobj.is_debris_model = debris;
+
+        let name = &self.sub_objects[id].name;
+        let new_name = if debris && !name.starts_with("debris") {
+            Some(format!("debris-{}", name))
+        } else if !debris && name.starts_with("debris") {
+            Some(name.trim_start_matches("debris").trim_start_matches(&['-', '_'][..]).to_string()).filter(|name| !name.is_empty())
+        } else {
+            None
+        };
+
+        if let Some(new_name) = new_name {
+            let old_name = self.rename_subobject(id, new_name).unwrap();
+            self.recheck_errors(Set::One(Error::DuplicateSubobjectName(old_name)));
+            self.recheck_errors(Set::One(Error::DuplicateSubobjectName(self.sub_objects[id].name.clone())));
+            self.recheck_warnings(Set::One(Warning::SubObjectNameTooLong(id)));
+        }
+
+        if debris {
+            self.make_orphan(id);
+        }
+
+        self.recheck_warnings(Set::One(Warning::DebrisFlagNameMismatch(id)));
+        self.recheck_warnings(Set::One(Warning::DebrisInDetailHierarchy(id)));
+        // this changes reachability for the whole subtree
+        for i in 0..self.sub_objects.len() {
+            self.recheck_warnings(Set::One(Warning::UnreachableSubobject(ObjectId(i as u32))));
+        }
+        self.recheck_errors(Set::One(Error::TooManyDebrisObjects));
+        self.recheck_errors(Set::One(Error::DetailAndDebrisObj(id)));
+    }
+
+    // top level subobjects which aren't a detail level or debris, and which nothing refers to
+    // these are likely debris which was never flagged as such, though they may be intentional
+    pub fn possible_unflagged_debris(&self) -> Vec<ObjectId> {
+        self.sub_objects
+            .iter()
+            .filter(|subobj| {
+                subobj.parent.is_none()
+                    && !subobj.is_debris_model
+                    && !self.header.detail_levels.contains(&subobj.obj_id)
+                    && subobj.name_links.is_empty()
+                    && !self
+                        .turrets
+                        .iter()
+                        .any(|turret| turret.base_obj == subobj.obj_id || turret.gun_obj == subobj.obj_id)
+                    && !self.glow_banks.iter().any(|bank| bank.obj_parent == subobj.obj_id)
+                    && !self.eye_points.iter().any(|eye| eye.attached_subobj == Some(subobj.obj_id))
+                    && !self.attachments.iter().any(|attachment| attachment.parent == Some(subobj.obj_id))
+                    && !self.paths.iter().any(|path| path.parent == subobj.name)
+                    && !self
+                        .docking_bays
+                        .iter()
+                        .any(|dock| properties_get_field(&dock.properties, "$parent_submodel") == Some(&subobj.name))
+            })
+            .map(|subobj| subobj.obj_id)
+            .collect()
+    }
+
+    // subobjects in a lower detail level which are copies of one in a higher detail level, rather than a decimated version
+    // these cost memory for no benefit, and should get a real LOD instead
+    pub fn duplicate_geometry_across_lods(&self) -> Vec<LodDuplicate> {
+        const BBOX_EPSILON: f32 = 0.001;
+
+        struct Candidate<'a> {
+            subobj: &'a SubObject,
+            level: u32,
+            fingerprint: u64,
+            num_polys: usize,
+            size: Vec3d,
+        }
+
+        let candidates = self
+            .sub_objects
+            .iter()
+            .filter_map(|subobj| {
+                let num_polys = subobj.bsp_data.collision_tree.leaves().count();
+                if num_polys == 0 {
+                    return None;
+                }
+                Some(Candidate {
+                    subobj,
+                    level: self.get_sobj_detail_level(subobj.obj_id)?,
+                    fingerprint: subobj.geometry_fingerprint(),
+                    num_polys,
+                    // the size rather than the position, in case a copy has its offset baked into the vertices
+                    size: subobj.bbox.max - subobj.bbox.min,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut duplicates = vec![];
+        for higher in &candidates {
+            for lower in &candidates {
+                if lower.level <= higher.level {
+                    continue;
+                }
+
+                let exact = higher.fingerprint == lower.fingerprint;
+                let size_diff = higher.size - lower.size;
+                let near = higher.subobj.bsp_data.verts.len() == lower.subobj.bsp_data.verts.len()
+                    && size_diff.x.abs() < BBOX_EPSILON
+                    && size_diff.y.abs() < BBOX_EPSILON
+                    && size_diff.z.abs() < BBOX_EPSILON;
+
+                if exact || near {
+                    duplicates.push(LodDuplicate {
+                        subobjs: (higher.subobj.obj_id, lower.subobj.obj_id),
+                        detail_levels: (higher.level, lower.level),
+                        num_verts: (higher.subobj.bsp_data.verts.len(), lower.subobj.bsp_data.verts.len()),
+                        num_polys: (higher.num_polys, lower.num_polys),
+                        exact,
+                    });
+                }
+            }
+        }
+        duplicates
+    }
+
+    pub fn lod_duplicates_display(&self) -> String {
+        let duplicates = self.duplicate_geometry_across_lods();
+        if duplicates.is_empty() {
+            return format!("No subobjects are duplicated across detail levels");
+        }
+
+        let mut out = String::new();
+        for dup in duplicates {
+            let (higher, lower) = (&self.sub_objects[dup.subobjs.0], &self.sub_objects[dup.subobjs.1]);
+            out += &format!(
+                "'{}' (detail{}) is {} of '{}' (detail{}): {} verts, {} polys\n",
+                lower.name,
+                dup.detail_levels.1,
+                if dup.exact { "an exact copy" } else { "likely a copy" },
+                higher.name,
+                dup.detail_levels.0,
+                dup.num_verts.1,
+                dup.num_polys.1
+            );
         }
+        out
     }
 
     pub fn max_verts_norms_per_subobj(&self) -> usize {
@@ -2683,6 +5418,17 @@ impl Model {
     }
 
     pub fn global_import(&mut self, mut import_model: Box<Model>) {
+        // attachments follow their parent by name, those whose parent isn't here are kept in place in model space
+        let mut attachments = std::mem::take(&mut import_model.attachments);
+        for attachment in &mut attachments {
+            if let Some(parent) = attachment.parent {
+                attachment.parent = self.get_obj_id_by_name(&import_model.sub_objects[parent].name);
+                if attachment.parent.is_none() {
+                    attachment.position += import_model.get_total_subobj_offset(parent);
+                }
+            }
+        }
+
         self.header.mass = import_model.header.mass;
         self.header.moment_of_inertia = import_model.header.moment_of_inertia;
         self.primary_weps = import_model.primary_weps;
@@ -2694,6 +5440,7 @@ impl Model {
         self.paths = import_model.paths;
         self.eye_points = import_model.eye_points;
         self.insignias = import_model.insignias;
+        self.attachments = attachments;
 
         // turrets are more complicated, exact base + arm object name matches only
         import_model.turrets.retain_mut(|turret| {
@@ -2714,6 +5461,264 @@ impl Model {
         self.recheck_errors(Set::All);
     }
 
+    // appends a mirror image of each point in the bank, flipped across the plane perpendicular to axis
+    // points lying on that plane already are their own mirror image, and are skipped
+    // stops at MAX_WEAPON_BANK_POINTS, returns how many points were added
+    pub fn mirror_weapon_bank(&mut self, primary: bool, bank: usize, axis: Axis) -> usize {
+        let bank = if primary {
+            &mut self.primary_weps[bank]
+        } else {
+            &mut self.secondary_weps[bank]
+        };
+
+        let mut mirrored = vec![];
+        for point in bank.iter() {
+            if point.position[axis].abs() < 0.00001 {
+                continue;
+            }
+            let mut point = point.clone();
+            point.position[axis] = -point.position[axis];
+            point.normal.0[axis] = -point.normal.0[axis];
+            mirrored.push(point);
+        }
+
+        mirrored.truncate(MAX_WEAPON_BANK_POINTS.saturating_sub(bank.len()));
+        let num_added = mirrored.len();
+        bank.extend(mirrored);
+        num_added
+    }
+
+    // appends the point to an existing bank, rechecking the warnings it can cause, including that the model's version can't
+    // hold its offset
+    pub fn add_weapon_point(&mut self, primary: bool, bank: usize, point: WeaponHardpoint) -> WeaponPointRef {
+        let kind = WeaponKind::from_primary(primary);
+        let points = &mut self.weapon_banks_mut(kind)[bank];
+        points.push(point);
+        let weapon = WeaponPointRef::new(kind, bank, points.len() - 1);
+
+        self.recheck_warnings(Set::One(Warning::WeaponOffsetInvalidVersion(weapon)));
+        self.recheck_warnings(Set::One(Warning::WeaponInsideHull { primary, bank, point: weapon.point as usize }));
+        self.recheck_warnings(Set::One(Warning::DuplicateWeaponPoint { primary, bank }));
+        weapon
+    }
+
+    // every operation which removes, renames or reassigns textures ends with this, so untextured_idx can't go stale:
+    // polygons whose texture no longer exists are pointed at the untextured slot, which is found again by name, or added
+    // if needed, and untextured_idx and its warning are updated to match, None if no polygon uses the slot anymore
+    pub fn refresh_untextured_slot(&mut self) {
+        let untextured_names = self.untextured_names.clone();
+        self.refresh_untextured_slot_with(&untextured_names);
+    }
+
+    pub fn refresh_untextured_slot_with(&mut self, untextured_names: &UntexturedNames) {
+        self.untextured_idx = post_parse_fill_untextured_slot_with(&mut self.sub_objects, &mut self.textures, untextured_names);
+        self.recheck_warnings(Set::One(Warning::UntexturedPolygons));
+    }
+
+    #[deprecated = "renamed to refresh_untextured_slot"]
+    pub fn revalidate_untextured(&mut self) {
+        self.refresh_untextured_slot();
+    }
+
+    #[deprecated = "renamed to refresh_untextured_slot_with"]
+    pub fn revalidate_untextured_with(&mut self, untextured_names: &UntexturedNames) {
+        self.refresh_untextured_slot_with(untextured_names);
+    }
+
+    pub fn weapon_banks(&self, kind: WeaponKind) -> &Vec<Vec<WeaponHardpoint>> {
+        match kind {
+            WeaponKind::Primary => &self.primary_weps,
+            WeaponKind::Secondary => &self.secondary_weps,
+        }
+    }
+
+    pub fn weapon_banks_mut(&mut self, kind: WeaponKind) -> &mut Vec<Vec<WeaponHardpoint>> {
+        match kind {
+            WeaponKind::Primary => &mut self.primary_weps,
+            WeaponKind::Secondary => &mut self.secondary_weps,
+        }
+    }
+
+    pub fn weapon_point(&self, weapon: WeaponPointRef) -> Option<&WeaponHardpoint> {
+        self.weapon_banks(weapon.kind).get(weapon.bank as usize)?.get(weapon.point as usize)
+    }
+
+    pub fn weapon_point_mut(&mut self, weapon: WeaponPointRef) -> Option<&mut WeaponHardpoint> {
+        self.weapon_banks_mut(weapon.kind)
+            .get_mut(weapon.bank as usize)?
+            .get_mut(weapon.point as usize)
+    }
+
+    // every weapon point, primaries first, in bank and point order
+    pub fn iter_weapon_points(&self) -> impl Iterator<Item = (WeaponPointRef, &WeaponHardpoint)> {
+        [WeaponKind::Primary, WeaponKind::Secondary].into_iter().flat_map(move |kind| {
+            (self.weapon_banks(kind).iter().enumerate()).flat_map(move |(bank, points)| {
+                (points.iter().enumerate()).map(move |(point, hardpoint)| (WeaponPointRef::new(kind, bank, point), hardpoint))
+            })
+        })
+    }
+
+    // splits the bank into clusters of points, where a point joins a cluster if it's within max_dist of any point in it
+    // points keep their positions, normals and offsets, and their order within each cluster
+    // the bank keeps the cluster with its first point, new banks are added at the end
+    // returns the indices of all the resulting banks, starting with bank
+    pub fn cluster_weapon_bank(&mut self, primary: bool, bank: usize, max_dist: f32) -> Vec<usize> {
+        let banks = if primary { &mut self.primary_weps } else { &mut self.secondary_weps };
+
+        let points = &banks[bank];
+        let mut cluster_of: Vec<Option<usize>> = vec![None; points.len()];
+        let mut num_clusters = 0;
+        for start in 0..points.len() {
+            if cluster_of[start].is_some() {
+                continue;
+            }
+            cluster_of[start] = Some(num_clusters);
+            let mut stack = vec![start];
+            while let Some(i) = stack.pop() {
+                for j in 0..points.len() {
+                    if cluster_of[j].is_none() && (points[i].position - points[j].position).magnitude() <= max_dist {
+                        cluster_of[j] = Some(num_clusters);
+                        stack.push(j);
+                    }
+                }
+            }
+            num_clusters += 1;
+        }
+
+        let mut clusters = vec![vec![]; num_clusters];
+        for (point, cluster) in std::mem::take(&mut banks[bank]).into_iter().zip(cluster_of) {
+            clusters[cluster.unwrap()].push(point);
+        }
+
+        let mut out = vec![bank];
+        let mut clusters = clusters.into_iter();
+        banks[bank] = clusters.next().unwrap_or_default();
+        for points in clusters {
+            out.push(banks.len());
+            banks.push(points);
+        }
+        self.recheck_warnings(Set::All);
+        out
+    }
+
+    // the closest common ancestor of the subobjects, if they're in the same hierarchy
+    fn common_ancestor(&self, ids: &[ObjectId]) -> Option<ObjectId> {
+        let (&first, rest) = ids.split_first()?;
+        let mut candidate = Some(first);
+        while let Some(id) = candidate {
+            if rest.iter().all(|&other| self.is_obj_id_ancestor(other, id)) {
+                return Some(id);
+            }
+            candidate = self.sub_objects[id].parent;
+        }
+        None
+    }
+
+    // splits the bank into one bank per subobject its points are closest to, among the subobjects of the bank's detail level
+    // glow point positions are in model space no matter the parent (the engine subtracts the parent's offset itself),
+    // so the points keep their positions, only obj_parent changes
+    // the bank keeps the points which belong to its current parent, or the first group if none do, new banks are added at the end
+    // returns the indices of all the resulting banks, starting with bank_idx
+    pub fn split_glow_bank_by_parent(&mut self, bank_idx: usize) -> Vec<usize> {
+        let bank = &self.glow_banks[bank_idx];
+        let Some(&lod_root) = self.header.detail_levels.get(bank.lod as usize).or(self.header.detail_levels.first()) else {
+            return vec![bank_idx];
+        };
+
+        let candidates = self
+            .sub_objects
+            .iter()
+            .filter(|subobj| self.is_obj_id_ancestor(subobj.obj_id, lod_root) && !subobj.bsp_data.verts.is_empty())
+            .map(|subobj| (subobj.obj_id, SpatialHash::from_subobject(self, subobj.obj_id)))
+            .collect::<Vec<_>>();
+
+        let mut groups: Vec<(ObjectId, Vec<GlowPoint>)> = vec![];
+        for point in &bank.glow_points {
+            let distance = |hash: &SpatialHash| {
+                hash.nearest(point.position)
+                    .map_or(f32::INFINITY, |nearest| (nearest - point.position).magnitude())
+            };
+            // ties go to the current parent
+            let parent = candidates
+                .iter()
+                .map(|(id, hash)| (*id, distance(hash)))
+                .min_by(|(id1, dist1), (id2, dist2)| dist1.total_cmp(dist2).then((*id2 == bank.obj_parent).cmp(&(*id1 == bank.obj_parent))))
+                .map_or(bank.obj_parent, |(id, _)| id);
+
+            match groups.iter_mut().find(|(id, _)| *id == parent) {
+                Some((_, points)) => points.push(point.clone()),
+                None => groups.push((parent, vec![point.clone()])),
+            }
+        }
+
+        if groups.is_empty() {
+            return vec![bank_idx];
+        }
+        let kept = groups.iter().position(|(id, _)| *id == bank.obj_parent).unwrap_or(0);
+        let (parent, points) = groups.remove(kept);
+        let template = GlowPointBank { glow_points: vec![], ..self.glow_banks[bank_idx].clone() };
+        self.glow_banks[bank_idx].obj_parent = parent;
+        self.glow_banks[bank_idx].glow_points = points;
+
+        let mut out = vec![bank_idx];
+        for (parent, points) in groups {
+            out.push(self.glow_banks.len());
+            self.glow_banks
+                .push(GlowPointBank { obj_parent: parent, glow_points: points, ..template.clone() });
+        }
+        self.recheck_warnings(Set::All);
+        out
+    }
+
+    // merges the banks into the one with the lowest index, the rest are removed
+    // they must have the same timing, type, detail level and properties, their parent becomes the closest common ancestor of their parents
+    // returns the index of the merged bank
+    pub fn merge_glow_banks(&mut self, indices: &[usize]) -> Result<usize, MergeError> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let (&first, rest) = indices.split_first().ok_or(MergeError::NoBanks)?;
+        if let Some(&invalid) = indices.iter().find(|&&idx| idx >= self.glow_banks.len()) {
+            return Err(MergeError::InvalidBank(invalid));
+        }
+
+        let mut differing = vec![];
+        let base = &self.glow_banks[first];
+        for &idx in rest {
+            let other = &self.glow_banks[idx];
+            let fields: [(&'static str, bool); 6] = [
+                ("disp_time", base.disp_time == other.disp_time),
+                ("on_time", base.on_time == other.on_time),
+                ("off_time", base.off_time == other.off_time),
+                ("lod", base.lod == other.lod),
+                ("glow_type", base.glow_type == other.glow_type),
+                ("properties", base.properties == other.properties),
+            ];
+            for (name, same) in fields {
+                if !same && !differing.contains(&name) {
+                    differing.push(name);
+                }
+            }
+        }
+        if !differing.is_empty() {
+            return Err(MergeError::FieldsDiffer(differing));
+        }
+
+        let parents = indices.iter().map(|&idx| self.glow_banks[idx].obj_parent).collect::<Vec<_>>();
+        let parent = self.common_ancestor(&parents).unwrap_or(parents[0]);
+        // in reverse so the indices stay valid
+        let mut points = vec![];
+        for &idx in rest.iter().rev() {
+            points.splice(0..0, self.glow_banks.remove(idx).glow_points);
+        }
+        let merged = &mut self.glow_banks[first];
+        merged.glow_points.extend(points);
+        merged.obj_parent = parent;
+
+        self.recheck_warnings(Set::All);
+        Ok(first)
+    }
+
     pub fn turret_matrix(&self, turret_idx: usize) -> TMat4<f32> {
         let turret = &self.turrets[turret_idx];
         let mut arr = if let Some((uvec, fvec)) = self.sub_objects[turret.base_obj].uvec_fvec() {
@@ -2732,6 +5737,12 @@ impl Model {
         let mat = TMat3::from_columns(&[rvec, uvec, fvec]);
         mat.to_homogeneous()
     }
+
+    // the direction the turret aims in at rest, in model space
+    // this is the forward axis of turret_matrix, so it's always perpendicular to the turret's normal
+    pub fn turret_rest_direction(&self, turret_idx: usize) -> Vec3d {
+        &self.turret_matrix(turret_idx) * Vec3d::new(0.0, 0.0, 1.0)
+    }
 }
 
 pub enum Set<T> {
@@ -2749,8 +5760,32 @@ pub enum Error {
     TooManyNorms(ObjectId),
     UnnamedSubObject(ObjectId),
     DuplicateSubobjectName(String),
+    NoDetailLevels,
+    // None for the shield's tree
+    BspTooDeep(Option<ObjectId>),
     // all turret base/gun objects must be disjoint!
 }
+impl Error {
+    // whether writing a file with this error is refused without WriteOptions::allow_errors
+    // this is a heuristic, not checked against the engine: errors which leave the file structurally broken, with
+    // references or counts the format can't represent, block writing, while naming mistakes, which only affect lookups
+    // by name, don't
+    pub fn blocks_write(&self) -> bool {
+        match self {
+            // refers to a subobject which doesn't exist, or which isn't a child of the base
+            Error::InvalidTurretGunSubobject(_) => true,
+            // more than the format's debris list is meant to hold
+            Error::TooManyDebrisObjects => true,
+            Error::DetailObjWithParent(_) => true,
+            Error::DetailAndDebrisObj(_) => true,
+            // more than the version's vertex and normal indices can address
+            Error::TooManyVerts(_) | Error::TooManyNorms(_) => true,
+            Error::NoDetailLevels => true,
+            Error::BspTooDeep(_) => true,
+            Error::UnnamedSubObject(_) | Error::DuplicateSubobjectName(_) => false,
+        }
+    }
+}
 
 #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
 pub enum Warning {
@@ -2760,16 +5795,47 @@ pub enum Warning {
     UntexturedPolygons,
     DockingBayWithoutPath(usize),
     ThrusterPropertiesInvalidVersion(usize),
-    WeaponOffsetInvalidVersion { primary: bool, bank: usize, point: usize },
+    WeaponOffsetInvalidVersion(WeaponPointRef),
+    // further inside detail0 than WEAPON_INSIDE_HULL_MARGIN, so it fires from inside the ship
+    WeaponInsideHull { primary: bool, bank: usize, point: usize },
+    // two points in the bank within DUPLICATE_POINT_EPSILON of each other, likely pasted twice
+    DuplicateWeaponPoint { primary: bool, bank: usize },
+    // the same, for a docking bay or eye point and an earlier one
+    DuplicateDockingBay(usize),
+    DuplicateEyePoint(usize),
     SubObjectTranslationInvalidVersion(ObjectId),
     TooFewTurretFirePoints(usize),
     TooManyTurretFirePoints(usize),
     DuplicatePathName(String),
+    DuplicateAttachmentName(String),
+    // fvec and uvec not unit length and perpendicular
+    DegenerateAttachmentFrame(usize),
     DuplicateDetailLevel(ObjectId),
     TooManyEyePoints,
     TooManyTextures,
     InvalidDockParentSubmodel(usize),
+    InsigniaInvalidDetailLevel(usize),
     Detail0NonZeroOffset,
+    AutocenterFlagWithoutVisualCenter,
+    // bits in the header's flags which aren't HeaderFlags::KNOWN; they're kept as they are, but the engine doesn't read them
+    UnknownHeaderFlags,
+    // uvs outside of 0-1 when the header doesn't have HeaderFlags::ALLOW_TILING, so the texture is clamped rather than tiled
+    TilingUvsWithoutTilingFlag,
+    VisualCenterOutsideDetail0,
+    PrecisionBudgetExceeded,
+    NormalsRepaired,
+    LegacyQuirksFixed,
+    ChunkLengthMismatch,
+    // saving would write an older version than the file was loaded as, dropping anything the older version can't hold
+    VersionChangedFromDisk,
+    InvalidCrossSections,
+    DebrisFlagNameMismatch(ObjectId),
+    DebrisInDetailHierarchy(ObjectId),
+    UnreachableSubobject(ObjectId),
+    // see SubObject::find_overlapping_coplanar
+    ZFightingPolygons(ObjectId),
+    // a bay path which a ship following it would hit the hull on, see Model::path_clearance_report
+    PathClipsGeometry(usize),
 
     PathNameTooLong(usize),
     SpecialPointNameTooLong(usize),
@@ -2787,9 +5853,47 @@ pub enum Warning {
     // turret subobject properties not set up for a turret
 }
 
+pub const DEFAULT_UNTEXTURED_NAME: &str = "Untextured";
+
+// the set of texture names which are treated as the placeholder slot for untextured polygons
+// the first name is the one used if a new slot needs to be added
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UntexturedNames(Vec<String>);
+impl Default for UntexturedNames {
+    fn default() -> Self {
+        Self(vec![DEFAULT_UNTEXTURED_NAME.to_string()])
+    }
+}
+impl UntexturedNames {
+    pub fn new(slot_name: &str, aliases: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        let mut names = vec![slot_name.to_string()];
+        names.extend(aliases.into_iter().map(Into::into));
+        Self(names)
+    }
+
+    pub fn slot_name(&self) -> &str {
+        &self.0[0]
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.0
+    }
+
+    // texture names are case insensitive as far as FSO is concerned
+    pub fn matches(&self, texture: &str) -> bool {
+        self.0.iter().any(|name| name.eq_ignore_ascii_case(texture))
+    }
+}
+
 pub fn post_parse_fill_untextured_slot(sub_objects: &mut Vec<SubObject>, textures: &mut Vec<String>) -> Option<TextureId> {
+    post_parse_fill_untextured_slot_with(sub_objects, textures, &UntexturedNames::default())
+}
+
+pub fn post_parse_fill_untextured_slot_with(
+    sub_objects: &mut Vec<SubObject>, textures: &mut Vec<String>, untextured_names: &UntexturedNames,
+) -> Option<TextureId> {
     let max_texture = TextureId(textures.len().try_into().unwrap());
-    let untextured_id = match textures.iter().position(|tex| tex == "Untextured") {
+    let untextured_id = match textures.iter().position(|tex| untextured_names.matches(tex)) {
         Some(index) => TextureId(index.try_into().unwrap()),
         None => max_texture,
     };
@@ -2797,18 +5901,19 @@ pub fn post_parse_fill_untextured_slot(sub_objects: &mut Vec<SubObject>, texture
     for subobj in sub_objects.iter_mut() {
         for (_, poly) in subobj.bsp_data.collision_tree.leaves_mut() {
             if poly.texture >= max_texture {
-                has_untextured = true;
                 poly.texture = untextured_id;
             }
+            has_untextured |= poly.texture == untextured_id;
         }
     }
-    if untextured_id < max_texture {
-        Some(untextured_id)
-    } else if has_untextured {
-        textures.push(format!("Untextured"));
-        Some(untextured_id)
-    } else {
+    // a slot no polygon uses is left in the list, but isn't the untextured slot, so there's nothing to warn about
+    if !has_untextured {
         None
+    } else {
+        if untextured_id == max_texture {
+            textures.push(untextured_names.slot_name().to_string());
+        }
+        Some(untextured_id)
     }
 }
 
@@ -2881,3 +5986,1751 @@ pub fn properties_set_flag(properties: &mut String, flag: &str) {
 pub fn properties_remove_flag(properties: &mut String, flag: &str) {
     properties_delete_field(properties, flag);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::{sample_capital, sample_fighter};
+
+    fn moi(model: &Model) -> [Vec3d; 3] {
+        let moi = model.header.moment_of_inertia;
+        [moi.rvec, moi.uvec, moi.fvec]
+    }
+
+    #[test]
+    fn reparenting_stales_the_moment_of_inertia() {
+        let mut fighter = sample_fighter(0);
+        let original = moi(&fighter);
+
+        // debris is outside detail0 wherever it goes, so nothing changes
+        assert!(!fighter.make_orphan(ObjectId(7)));
+        assert_eq!(fighter.make_parent(ObjectId(5), ObjectId(7)), Some(false));
+        assert!(!fighter.make_orphan(ObjectId(7)));
+
+        // turret01a and its gun leave detail0, and back
+        assert!(fighter.make_orphan(ObjectId(1)));
+        fighter.recalc_moi();
+        let detached = moi(&fighter);
+        assert_ne!(detached, original);
+        assert_eq!(fighter.make_parent(ObjectId(2), ObjectId(1)), None);
+        assert_eq!(fighter.make_parent(ObjectId(0), ObjectId(1)), Some(true));
+        fighter.recalc_moi();
+        assert_ne!(moi(&fighter), detached);
+
+        // into detail0 from elsewhere, and out again by moving under another detail level
+        assert_eq!(fighter.make_parent(ObjectId(0), ObjectId(8)), Some(true));
+        fighter.make_orphan(ObjectId(8));
+        assert_eq!(fighter.make_parent(ObjectId(3), ObjectId(8)), Some(true));
+        assert!(fighter.make_orphan(ObjectId(8)));
+        assert_eq!(fighter.make_parent(ObjectId(5), ObjectId(8)), Some(false));
+    }
+
+    #[test]
+    fn degenerate_geometry_leaves_the_default_moment_of_inertia() {
+        let mut model = sample_capital(1, 4, 0);
+        let detail0 = &mut model.sub_objects[ObjectId(0)];
+
+        // a plate is flat, but only collinear points have no inverse
+        for vert in &mut detail0.bsp_data.verts {
+            vert.y = 0.0;
+        }
+        model.recalc_moi();
+        assert_ne!(moi(&model), [Vec3d::ZERO; 3]);
+        assert!(moi(&model).iter().all(|row| row.x.is_finite() && row.y.is_finite() && row.z.is_finite()));
+
+        let detail0 = &mut model.sub_objects[ObjectId(0)];
+        for vert in &mut detail0.bsp_data.verts {
+            *vert = Vec3d::new(vert.x, 0.0, 0.0);
+        }
+        model.recalc_moi();
+        assert_eq!(moi(&model), [Vec3d::ZERO; 3]);
+
+        let detail0 = &mut model.sub_objects[ObjectId(0)];
+        for vert in &mut detail0.bsp_data.verts {
+            *vert = Vec3d::new(1.0, 2.0, 3.0);
+        }
+        model.recalc_moi();
+        assert_eq!(moi(&model), [Vec3d::ZERO; 3]);
+    }
+
+    #[test]
+    fn header_flags_keep_unknown_bits() {
+        let mut flags = HeaderFlags(0x8000_0100) | HeaderFlags::AUTOCENTER;
+        flags.set(HeaderFlags::ALLOW_TILING, true);
+        flags.set(HeaderFlags::AUTOCENTER, false);
+        assert_eq!(flags.bits(), 0x8000_0101);
+        assert_eq!(flags.unknown_bits(), 0x8000_0100);
+        assert!(flags.contains(HeaderFlags::ALLOW_TILING) && !flags.contains(HeaderFlags::AUTOCENTER));
+        assert_eq!(format!("{:?}", flags), "[allow tiling, 0x80000100]");
+
+        // the header's layout changed at 21.16, the flags moving with it
+        let flags = flags | HeaderFlags::AUTOCENTER;
+        Version::for_each(|version| {
+            let mut model = sample_fighter(0);
+            model.version = version;
+            model.header.set_flags(flags);
+            let parsed = Model::from_bytes(&model.write_to_vec().unwrap()).unwrap();
+            assert_eq!(parsed.version, version);
+            assert_eq!(parsed.header.flags(), flags, "{}", version);
+        });
+    }
+
+    #[test]
+    fn autocenter_flag_without_visual_center() {
+        let mut fighter = sample_fighter(0);
+        let mut flags = fighter.header.flags();
+        flags.set(HeaderFlags::AUTOCENTER, true);
+        fighter.header.set_flags(flags);
+        fighter.visual_center = Vec3d::new(0.0, 0.0, 1.0);
+        fighter.recheck_warnings(Set::One(Warning::AutocenterFlagWithoutVisualCenter));
+        assert!(!fighter.warnings.contains(&Warning::AutocenterFlagWithoutVisualCenter));
+
+        fighter.visual_center = Vec3d::ZERO;
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::AutocenterFlagWithoutVisualCenter));
+    }
+
+    #[test]
+    fn header_flag_warnings() {
+        let mut fighter = sample_fighter(0);
+        assert!(!fighter.warnings.contains(&Warning::UnknownHeaderFlags));
+        assert!(!fighter.warnings.contains(&Warning::TilingUvsWithoutTilingFlag));
+
+        fighter.header.set_flags(HeaderFlags(0x100) | HeaderFlags::AUTOCENTER);
+        fighter.recheck_warnings(Set::One(Warning::UnknownHeaderFlags));
+        assert!(fighter.warnings.contains(&Warning::UnknownHeaderFlags));
+        assert_eq!(fighter.header.flags().names(), ["autocenter"]);
+
+        // the fighter's uvs are all in 0-1 until one of its polygons is tiled twice across
+        let (_, poly) = fighter.sub_objects[ObjectId(0)].bsp_data.collision_tree.leaves_mut().next().unwrap();
+        poly.verts[2].uv = (2.0, 2.0);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::TilingUvsWithoutTilingFlag));
+        let mut flags = fighter.header.flags();
+        flags.set(HeaderFlags::ALLOW_TILING, true);
+        fighter.header.set_flags(flags);
+        fighter.recheck_warnings(Set::One(Warning::TilingUvsWithoutTilingFlag));
+        assert!(!fighter.warnings.contains(&Warning::TilingUvsWithoutTilingFlag));
+    }
+
+    #[test]
+    fn detail0_is_the_first_detail_level() {
+        let mut fighter = sample_fighter(0);
+        assert_eq!(fighter.detail0_id(), Some(fighter.header.detail_levels[0]));
+        assert_eq!(fighter.detail0().unwrap().name, "detail0");
+
+        fighter.header.detail_levels.reverse();
+        assert_eq!(fighter.detail0_id(), Some(fighter.header.detail_levels[0]));
+        assert_eq!(fighter.detail0().unwrap().name, "detail2");
+
+        fighter.header.detail_levels.clear();
+        assert_eq!(fighter.detail0_id(), None);
+        assert!(fighter.detail0().is_none());
+    }
+
+    // detail0 with a greeble under it, two more levels of greebles under that, and a radar dish on the middle one
+    fn greeble_tree() -> Model {
+        let mut model = sample_capital(1, 4, 0);
+        let children = [
+            (1, 0, "greeble", Vec3d::new(10.0, 0.0, 0.0)),
+            (2, 1, "greeble-a", Vec3d::new(0.0, 5.0, 0.0)),
+            (3, 2, "greeble-b", Vec3d::new(0.0, 0.0, 2.0)),
+            (4, 2, "radar", Vec3d::new(1.0, 1.0, 1.0)),
+        ];
+        for (id, parent, name, offset) in children {
+            let mut subobj = model.sub_objects[ObjectId(0)].clone();
+            subobj.obj_id = ObjectId(id);
+            subobj.parent = Some(ObjectId(parent));
+            subobj.name = name.to_string();
+            subobj.offset = offset;
+            model.sub_objects.push(subobj);
+        }
+        model.sub_objects[ObjectId(4)].properties = "$rotate=10".to_string();
+        model.header.num_subobjects = model.sub_objects.len() as u32;
+        model.recalc_all_children_ids();
+        model.glow_banks = vec![GlowPointBank { obj_parent: ObjectId(3), ..Default::default() }];
+        model.eye_points = vec![EyePoint { attached_subobj: Some(ObjectId(3)), ..Default::default() }];
+        model.recheck_warnings(Set::All);
+        model.recheck_errors(Set::All);
+        model
+    }
+
+    #[test]
+    fn flatten_keeping_the_radar() {
+        let mut model = greeble_tree();
+        let greeble_verts = model.sub_objects[ObjectId(1)].bsp_data.verts.clone();
+        let radar_position = model.get_total_subobj_offset(ObjectId(4));
+        let eye_position = model.get_total_subobj_offset(ObjectId(3));
+
+        let report = model.flatten_branch(ObjectId(1), &[ObjectId(4)]).unwrap();
+        assert_eq!(report.root, ObjectId(1));
+        assert_eq!(report.merged, ["greeble-a", "greeble-b"]);
+        assert_eq!(report.reparented, ["radar"]);
+        assert_eq!(report.glow_banks_reattached, [0]);
+        assert_eq!(report.eye_points_reattached, [0]);
+
+        let names = model.sub_objects.iter().map(|subobj| subobj.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["detail0", "greeble", "radar"]);
+        let (greeble, radar) = (&model.sub_objects[ObjectId(1)], &model.sub_objects[ObjectId(2)]);
+        assert_eq!(greeble.children, [ObjectId(2)]);
+        assert_eq!(radar.parent, Some(ObjectId(1)));
+        assert_eq!(model.get_total_subobj_offset(ObjectId(2)), radar_position);
+
+        // the merged vertices are where they were in model space
+        let verts = &greeble.bsp_data.verts;
+        assert_eq!(verts.len(), greeble_verts.len() * 3);
+        assert_eq!(verts[..greeble_verts.len()], greeble_verts);
+        let b_offset = Vec3d::new(0.0, 5.0, 2.0);
+        assert!((verts[greeble_verts.len() * 2..].iter().zip(&greeble_verts)).all(|(&vert, &orig)| (vert - (orig + b_offset)).magnitude() < 1e-5));
+        assert_eq!(greeble.bsp_data.collision_tree.leaves().count(), 12);
+
+        assert_eq!(model.glow_banks[0].obj_parent, ObjectId(1));
+        let eye = &model.eye_points[0];
+        assert_eq!(eye.attached_subobj, Some(ObjectId(1)));
+        assert!((model.get_total_subobj_offset(ObjectId(1)) + eye.position - eye_position).magnitude() < 1e-5);
+        assert_eq!(model.check_invariants(), []);
+    }
+
+    #[test]
+    fn flatten_refuses_to_merge_a_turret() {
+        let mut model = greeble_tree();
+        model.turrets = vec![Turret {
+            base_obj: ObjectId(2),
+            gun_obj: ObjectId(4),
+            ..Default::default()
+        }];
+        let err = model.flatten_branch(ObjectId(1), &[ObjectId(4)]).unwrap_err();
+        assert_eq!(err, FlattenError::TurretSubobjectMerged { turret: 0, id: ObjectId(2) });
+        assert_eq!(model.sub_objects.len(), 5);
+    }
+
+    #[test]
+    fn debris_flags_and_names() {
+        let mut fighter = sample_fighter(0);
+        assert_eq!(fighter.possible_unflagged_debris(), []);
+
+        fighter.rename_subobject(ObjectId(7), "chunk01".to_string());
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::DebrisFlagNameMismatch(ObjectId(7))));
+        fighter.set_debris(ObjectId(7), true);
+        assert_eq!(fighter.sub_objects[ObjectId(7)].name, "debris-chunk01");
+        assert!(!fighter.warnings.contains(&Warning::DebrisFlagNameMismatch(ObjectId(7))));
+
+        fighter.sub_objects[ObjectId(3)].is_debris_model = true;
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::DebrisFlagNameMismatch(ObjectId(3))));
+        assert!(fighter.warnings.contains(&Warning::DebrisInDetailHierarchy(ObjectId(3))));
+        fighter.set_debris(ObjectId(3), true);
+        assert_eq!(fighter.sub_objects[ObjectId(3)].name, "debris-turret02a");
+        assert_eq!(fighter.sub_objects[ObjectId(3)].parent, None);
+        assert!(!fighter.warnings.contains(&Warning::DebrisFlagNameMismatch(ObjectId(3))));
+        assert!(!fighter.warnings.contains(&Warning::DebrisInDetailHierarchy(ObjectId(3))));
+
+        fighter.set_debris(ObjectId(8), false);
+        assert_eq!(fighter.sub_objects[ObjectId(8)].name, "02");
+        assert!(!fighter.warnings.contains(&Warning::DebrisFlagNameMismatch(ObjectId(8))));
+        assert_eq!(fighter.possible_unflagged_debris(), [ObjectId(8)]);
+    }
+
+    #[test]
+    fn surface_samples_cover_the_surface() {
+        let fighter = sample_fighter(0);
+        let (area, _) = fighter.surface_area_average_pos();
+        let density = 2.0;
+
+        let uniform = fighter.surface_samples(density, 1);
+        assert!((uniform.len() as f32 - area * density).abs() <= 1.0);
+        // each triangle gets at least one, which adds up at low densities
+        let stratified = fighter.surface_samples_with(density * 10.0, 1, SampleMode::Stratified);
+        assert!((stratified.len() as f32 / (area * density * 10.0) - 1.0).abs() < 0.05);
+        let sparse = fighter.surface_samples_with(0.0001, 1, SampleMode::Stratified);
+        let num_triangles = (fighter.sub_objects.iter())
+            .filter(|subobj| fighter.is_obj_id_ancestor(subobj.obj_id, ObjectId(0)))
+            .flat_map(|subobj| subobj.bsp_data.collision_tree.leaves())
+            .map(|(_, poly)| poly.verts.len() - 2)
+            .sum::<usize>();
+        assert_eq!(sparse.len(), num_triangles);
+
+        // every sample is on the plane of one of its subobject's polygons, facing the same way
+        for sample in uniform.iter().chain(&stratified) {
+            let subobj = &fighter.sub_objects[sample.subobj];
+            let position = sample.position - fighter.get_total_subobj_offset(sample.subobj);
+            let on_a_polygon = subobj.bsp_data.collision_tree.leaves().any(|(_, poly)| {
+                let [v1, v2, v3] = [0, 1, 2].map(|i| subobj.bsp_data.verts[poly.verts[i].vertex_id.0 as usize]);
+                let normal = (v2 - v1).cross(&(v3 - v1)).normalize();
+                (position - v1).dot(&normal).abs() < 1e-4 && normal.dot(&sample.normal) > 0.999
+            });
+            assert!(on_a_polygon, "{:?}", sample);
+        }
+
+        let same_seed = fighter.surface_samples(density, 1);
+        assert!((uniform.iter().zip(&same_seed)).all(|(a, b)| a.position == b.position && a.subobj == b.subobj));
+        assert_ne!(fighter.surface_samples(density, 2)[0].position, uniform[0].position);
+
+        let mut csv = vec![];
+        SurfaceSample::write_csv(&uniform, &mut csv).unwrap();
+        assert_eq!(String::from_utf8(csv).unwrap().lines().count(), uniform.len() + 1);
+    }
+
+    #[test]
+    fn forward_follows_the_guns_along_the_longest_axis() {
+        let mut fighter = sample_fighter(0);
+        let close_to = |dir: Vec3d, expected: Vec3d| dir.dot(&expected) > 0.99;
+        assert!(close_to(fighter.guess_forward(), Vec3d::new(0.0, 0.0, 1.0)));
+
+        // the guns moved to the back turn it around
+        for point in fighter.primary_weps.iter_mut().flatten() {
+            point.position.z = -point.position.z;
+        }
+        assert!(close_to(fighter.guess_forward(), Vec3d::new(0.0, 0.0, -1.0)));
+
+        // without guns it's +Z either way
+        fighter.primary_weps.clear();
+        assert!(close_to(fighter.guess_forward(), Vec3d::new(0.0, 0.0, 1.0)));
+    }
+
+    #[test]
+    fn raycast_skips_subobjects_without_collisions() {
+        let mut fighter = sample_fighter(0);
+        let (origin, down) = (Vec3d::new(0.0, 100.0, 0.0), Vec3d::new(0.0, -1.0, 0.0));
+        let gun = fighter.raycast(origin, down).unwrap();
+        assert_eq!(gun.subobj, ObjectId(2));
+        assert!(fighter.raycast(origin, -down).is_none());
+
+        properties_set_flag(&mut fighter.sub_objects[ObjectId(2)].properties, "$nocollide_this_only");
+        assert!(!fighter.sub_objects[ObjectId(2)].collides());
+        let base = fighter.raycast(origin, down).unwrap();
+        assert_eq!(base.subobj, ObjectId(1));
+        assert!(base.distance > gun.distance);
+
+        // this one applies to the children as well
+        fighter.sub_objects[ObjectId(2)].properties.clear();
+        properties_set_flag(&mut fighter.sub_objects[ObjectId(1)].properties, "$no_collisions");
+        assert!(fighter.sub_objects[ObjectId(2)].collides() && !fighter.subobj_collides(ObjectId(2)));
+        let hull = fighter.raycast(origin, down).unwrap();
+        assert_eq!(hull.subobj, ObjectId(0));
+        assert!(hull.distance > base.distance);
+        assert!((hull.position - Vec3d::new(0.0, fighter.sub_objects[ObjectId(0)].bbox.max.y, 0.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn average_of_millions_of_verts_doesnt_drift() {
+        const NUM_VERTS: usize = 8_000_000;
+        let vert = Vec3d::new(5000.1, -1234.5, 0.3);
+        let verts = || std::iter::repeat(vert).take(NUM_VERTS);
+
+        // an f32 sum this large is off by whole units per addition, the f64 one is exact up to the final rounding
+        let f32_sum = verts().fold(Vec3d::ZERO, |sum, vert| sum + vert);
+        assert!((f32_sum / NUM_VERTS as f32 - vert).magnitude() > 1.0);
+        assert_eq!(Vec3d::average(verts()), vert);
+    }
+
+    #[test]
+    fn precision_report_of_a_far_off_subobject() {
+        assert_eq!(f32_quantization(1.0), f32::EPSILON);
+        assert_eq!(f32_quantization(5000.0), 4096.0 * f32::EPSILON);
+
+        let mut fighter = sample_fighter(0);
+        let report = fighter.precision_report();
+        assert!(!report.exceeds_budget && report.off_center_subobjects.is_empty());
+        assert!(report.max_coordinate < 10.0);
+        assert!(!fighter.warnings.contains(&Warning::PrecisionBudgetExceeded));
+
+        // detail1's geometry far from its origin, rather than its offset
+        let far = Vec3d::new(0.0, 0.0, 20000.0);
+        let detail1 = &mut fighter.sub_objects[ObjectId(5)];
+        for vert in &mut detail1.bsp_data.verts {
+            *vert += far;
+        }
+        detail1.recalc_bbox();
+        let report = fighter.precision_report();
+        assert!(report.max_coordinate > 20000.0);
+        assert_eq!(report.worst_quantization, f32_quantization(report.max_coordinate));
+        assert!(report.exceeds_budget);
+        assert_eq!(report.off_center_subobjects.len(), 1);
+        let off_center = report.off_center_subobjects[0];
+        assert_eq!(off_center.id, ObjectId(5));
+        assert!(off_center.recentered_quantization < off_center.quantization / 100.0);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::PrecisionBudgetExceeded));
+
+        fighter.precision_budget = Some(0.01);
+        fighter.recheck_warnings(Set::One(Warning::PrecisionBudgetExceeded));
+        assert!(!fighter.warnings.contains(&Warning::PrecisionBudgetExceeded));
+    }
+
+    #[test]
+    fn visual_center_methods() {
+        let mut capital = sample_capital(6, 8, 3);
+        let bbox = capital.header.bbox;
+        let methods = [
+            (VisualCenterMethod::SurfaceAreaAverage, capital.surface_area_average_pos().1),
+            (VisualCenterMethod::BBoxCenter, bbox.center()),
+            (VisualCenterMethod::CenterOfMass, capital.header.center_of_mass),
+        ];
+        for (method, expected) in methods {
+            capital.recalc_visual_center(method);
+            assert_eq!(capital.visual_center, expected, "{:?}", method);
+            assert!(bbox.contains(capital.visual_center), "{:?}", method);
+        }
+        // the sections are scattered unevenly, so the methods disagree
+        assert!((methods[0].1 - methods[1].1).magnitude() > 1.0);
+        assert!((methods[1].1 - methods[2].1).magnitude() > 1.0);
+
+        let mut empty = Model::default();
+        for (method, _) in methods {
+            empty.recalc_visual_center(method);
+            assert_eq!(empty.visual_center, Vec3d::ZERO, "{:?}", method);
+        }
+    }
+
+    #[test]
+    fn insignia_on_a_missing_detail_level() {
+        let mut fighter = sample_fighter(0);
+        let insignia = |detail_level: u32| Insignia { detail_level, ..Default::default() };
+        fighter.insignias = vec![insignia(0), insignia(3), insignia(2)];
+        fighter.recheck_warnings(Set::All);
+        let flagged = (fighter.warnings.iter())
+            .filter_map(|warning| match warning {
+                Warning::InsigniaInvalidDetailLevel(i) => Some(*i),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(flagged, [1]);
+
+        // deleting detail2 leaves the third insignia on a detail level which no longer exists
+        let report = fighter.delete_subobject(ObjectId(6));
+        assert!(report.lost.contains(&LostReference::Insignia(2)));
+        assert!(fighter.warnings.contains(&Warning::InsigniaInvalidDetailLevel(1)));
+        assert!(fighter.warnings.contains(&Warning::InsigniaInvalidDetailLevel(2)));
+        assert!(!fighter.warnings.contains(&Warning::InsigniaInvalidDetailLevel(0)));
+    }
+
+    #[test]
+    fn normal_toward_a_point() {
+        assert_eq!(NormalVec3::toward(Vec3d::ZERO, Vec3d::new(0.0, 0.0, 5.0)).unwrap().0, Vec3d::new(0.0, 0.0, 1.0));
+        assert_eq!(NormalVec3::toward(Vec3d::new(1.0, 2.0, 3.0), Vec3d::new(1.0, -2.0, 3.0)).unwrap().0, Vec3d::new(0.0, -1.0, 0.0));
+        assert!(NormalVec3::toward(Vec3d::new(1.0, 2.0, 3.0), Vec3d::new(1.0, 2.0, 3.0)).is_none());
+    }
+
+    // everything which doesn't need a valid id, read-only queries first, then edits, none of which should panic
+    fn exercise(mut model: Model) {
+        model.recheck_warnings(Set::All);
+        model.recheck_errors(Set::All);
+        let _ = model.summary_text();
+        let _ = model.lod_stats();
+        let _ = model.get_subobj_names();
+        let _ = model.bbox_contributors();
+        let _ = model.radius_of_gyration();
+        let _ = model.surface_area_average_pos();
+        let _ = model.targeting_reference();
+        let _ = model.simulate_autocenter_shift();
+        let _ = model.insignia_summary();
+        let _ = model.precision_report();
+        let _ = model.guess_forward();
+        let _ = model.oriented_bounding_box();
+        let _ = model.silhouette_radius(Vec3d::new(0.0, 0.0, 1.0));
+        let _ = model.surface_samples(1.0, 0);
+        let _ = model.surface_samples_with(1.0, 0, SampleMode::Stratified);
+        let _ = model.raycast(Vec3d::new(0.0, 0.0, 100.0), Vec3d::new(0.0, 0.0, -1.0));
+        let _ = model.shield_coverage();
+        let _ = model.audit_normals();
+        let _ = model.possible_unflagged_debris();
+        let _ = model.duplicate_geometry_across_lods();
+        let _ = model.lod_duplicates_display();
+        let _ = model.cross_section_radius_at(0.0);
+        let _ = model.preview_transform_bounds(&glm::scaling(&glm::vec3(2.0, 2.0, 2.0)));
+        let _ = model.texture_uv_coverage(16);
+        let _ = model.coplanar_overlap_report(0.01, 0);
+        let _ = model.report_bbox_outliers();
+        let _ = model.orientation_summary();
+        let _ = model.subsystem_manifest();
+        let _ = model.export_properties_table();
+        let _ = model.export_collision_trees();
+        let _ = model.external_surface_area();
+        let _ = model.health_score(&Default::default());
+        let _ = model.check_invariants();
+        let _ = model.write_to_vec();
+        let _ = model.write_gltf(vec![], true);
+        model.write_dae(&mut vec![]).unwrap();
+        let mut csv = vec![];
+        model.export_weapon_points_csv(&mut csv).unwrap();
+        let _ = model.geometry_diff(&Model::default(), 0.01);
+
+        model.recalc_radius();
+        model.recalc_bbox();
+        model.recalc_mass();
+        model.recalc_moi();
+        for method in [
+            VisualCenterMethod::SurfaceAreaAverage,
+            VisualCenterMethod::BBoxCenter,
+            VisualCenterMethod::CenterOfMass,
+        ] {
+            model.recalc_visual_center(method);
+        }
+        model.insert_cross_section(0.0);
+        model.zero_detail0_offset();
+        model.apply_transform(&glm::scaling(&glm::vec3(2.0, 2.0, 2.0))).unwrap();
+        model.merge_duplicate_textures();
+        model.refresh_untextured_slot();
+        model.recalc_all_children_ids();
+        model.recalc_semantic_name_links();
+        model.compact_object_ids();
+        model.clean_up();
+        model.bootstrap_detail_hierarchy();
+        model.bootstrap_minimum_metadata(crate::bootstrap::ShipClassHint::Fighter);
+        model.recheck_warnings(Set::All);
+        model.recheck_errors(Set::All);
+    }
+
+    #[test]
+    fn degenerate_models_dont_panic() {
+        let empty = Model::default();
+        assert!(empty.compute_errors().contains(&Error::NoDetailLevels));
+        exercise(empty);
+
+        let mut no_detail_levels = sample_fighter(0);
+        no_detail_levels.header.detail_levels.clear();
+        assert!(no_detail_levels.compute_errors().contains(&Error::NoDetailLevels));
+        exercise(no_detail_levels);
+
+        let mut empty_subobject = sample_fighter(0);
+        empty_subobject.sub_objects[ObjectId(1)].bsp_data = Default::default();
+        exercise(empty_subobject);
+    }
+
+    #[test]
+    fn unknown_subobject_ids_change_nothing() {
+        use crate::carve::SelectionVolume;
+        use crate::proxy::ProxyStyle;
+
+        for mut model in [Model::default(), sample_fighter(0)] {
+            let id = ObjectId(model.sub_objects.len() as u32 + 3);
+            // the empty model can't be written, so the debug output stands in for the file
+            let before = format!("{:?}", model);
+
+            assert_eq!(model.get_total_subobj_offset(id), Vec3d::ZERO);
+            assert!(!model.is_obj_id_ancestor(id, ObjectId(0)));
+            assert_eq!(model.get_sobj_detail_level(id), None);
+            assert_eq!(model.get_valid_gun_subobjects_for_turret(ObjectId(0), id), (vec![id, ObjectId(0)], 1));
+            model.do_for_recursive_subobj_children(id, &mut |_| panic!());
+            assert!(!model.subobj_collides(id));
+            assert_eq!(model.collision_proxy_penetration(id, ObjectId(0)), None);
+            assert_eq!(model.collision_proxy_penetration(ObjectId(0), id), None);
+            assert!(crate::spatial::SpatialHash::from_subobject(&model, id).triangles().is_empty());
+            for warning in [
+                Warning::SubObjectNameTooLong(id),
+                Warning::InvertedBBox(Some(id)),
+                Warning::UnreachableSubobject(id),
+            ] {
+                model.recheck_warnings(Set::One(warning.clone()));
+                assert!(!model.warnings.contains(&warning));
+            }
+            for error in [Error::TooManyVerts(id), Error::UnnamedSubObject(id), Error::BspTooDeep(Some(id))] {
+                model.recheck_errors(Set::One(error.clone()));
+                assert!(!model.errors.contains(&error));
+            }
+
+            model.apply_subobj_transform(id, &glm::scaling(&glm::vec3(2.0, 2.0, 2.0)), true);
+            model.recalc_subobj_offset(id);
+            model.subobj_move_only_offset(id, Vec3d::new(1.0, 0.0, 0.0));
+            model.rebase_subtree_offsets(id, Vec3d::new(1.0, 0.0, 0.0));
+            model.set_debris(id, true);
+            model.offset_polygons_along_normal(id, &[0], 1.0);
+            assert!(!model.make_orphan(id));
+            assert_eq!(model.make_parent(id, ObjectId(0)), None);
+            assert_eq!(model.make_parent(ObjectId(0), id), None);
+            assert_eq!(model.rename_subobject(id, "renamed".to_string()), None);
+            assert!(model.delete_subobject(id).deleted.is_empty());
+            assert!(model.delete_subobject_and_children(id).deleted.is_empty());
+            assert!(model.split_disconnected(id).is_empty());
+            assert_eq!(model.remove_bbox_junk(id), 0);
+            assert_eq!(model.reparent_to_detail0(id), Err(ReparentError::InvalidId(id)));
+            assert_eq!(model.insert_intermediate_parent("group".to_string(), &[id]), Err(InsertParentError::InvalidId(id)));
+            assert_eq!(model.flatten_branch(id, &[]).unwrap_err(), FlattenError::InvalidId(id));
+            let region = SelectionVolume::Sphere { center: Vec3d::ZERO, radius: 100.0 };
+            assert_eq!(model.extract_region_to_child(id, region, "carved".to_string()), None);
+            assert_eq!(model.generate_collision_proxy(id, ProxyStyle::ConvexHull), None);
+
+            assert_eq!(format!("{:?}", model), before);
+        }
+    }
+
+    #[test]
+    fn mirror_a_weapon_point_across_x() {
+        let mut model = Model::default();
+        let normal = NormalVec3(Vec3d::new(0.6, 0.0, 0.8));
+        let point = WeaponHardpoint { position: Vec3d::new(3.0, -1.0, 5.0), normal, offset: 0.5 };
+        let on_the_plane = WeaponHardpoint { position: Vec3d::new(0.0, 1.0, 5.0), normal, offset: 0.0 };
+        model.primary_weps = vec![vec![point.clone(), on_the_plane]];
+
+        assert_eq!(model.mirror_weapon_bank(true, 0, Axis::X), 1);
+        let mirrored = &model.primary_weps[0][2];
+        assert_eq!(mirrored.position, Vec3d::new(-3.0, -1.0, 5.0));
+        assert_eq!(mirrored.normal.0, Vec3d::new(-0.6, 0.0, 0.8));
+        assert_eq!(mirrored.offset, 0.5);
+
+        // the bank is capped at what the engine allows
+        model.secondary_weps = vec![vec![point; MAX_WEAPON_BANK_POINTS - 2]];
+        assert_eq!(model.mirror_weapon_bank(false, 0, Axis::X), 2);
+        assert_eq!(model.secondary_weps[0].len(), MAX_WEAPON_BANK_POINTS);
+    }
+
+    #[test]
+    fn lod_duplicates() {
+        let mut fighter = sample_fighter(0);
+        // detail1 and detail2 are properly decimated
+        assert!(fighter.duplicate_geometry_across_lods().is_empty());
+        assert!(!fighter.lod_duplicates_display().is_empty());
+
+        let pairs = |model: &Model| {
+            let mut pairs = model
+                .duplicate_geometry_across_lods()
+                .iter()
+                .map(|dup| (dup.subobjs, dup.detail_levels, dup.exact))
+                .collect::<Vec<_>>();
+            pairs.sort();
+            pairs
+        };
+
+        // an exact copy at a different offset, and one whose offset has been baked into its vertices
+        let detail0 = fighter.sub_objects[ObjectId(0)].clone();
+        let shift = Vec3d::new(5.0, 0.0, 0.0);
+        let detail1 = &mut fighter.sub_objects[ObjectId(5)];
+        detail1.bsp_data = detail0.bsp_data.clone();
+        detail1.bbox = detail0.bbox;
+        detail1.offset = shift;
+        let detail2 = &mut fighter.sub_objects[ObjectId(6)];
+        detail2.bsp_data = detail0.bsp_data.clone();
+        detail2.bsp_data.verts.iter_mut().for_each(|vert| *vert += shift);
+        detail2.recalc_bbox();
+
+        let expected = vec![
+            ((ObjectId(0), ObjectId(5)), (0, 1), true),
+            ((ObjectId(0), ObjectId(6)), (0, 2), false),
+            ((ObjectId(5), ObjectId(6)), (1, 2), false),
+        ];
+        assert_eq!(pairs(&fighter), expected);
+        let display = fighter.lod_duplicates_display();
+        assert_eq!(display.lines().count(), 3, "{}", display);
+    }
+
+    #[test]
+    fn zeroing_detail0_offset_keeps_the_geometry_in_place() {
+        let mut fighter = sample_fighter(0);
+        fighter.sub_objects[ObjectId(0)].offset = Vec3d::new(1.0, -2.0, 3.0);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::Detail0NonZeroOffset));
+
+        let world_verts = |model: &Model| {
+            (0..5)
+                .flat_map(|id| {
+                    let offset = model.get_total_subobj_offset(ObjectId(id));
+                    model.sub_objects[ObjectId(id)].bsp_data.verts.iter().map(move |&vert| vert + offset)
+                })
+                .collect::<Vec<_>>()
+        };
+        let before = world_verts(&fighter);
+
+        fighter.zero_detail0_offset();
+        assert!(fighter.sub_objects[ObjectId(0)].offset.is_null());
+        assert!(!fighter.warnings.contains(&Warning::Detail0NonZeroOffset));
+        for (after, before) in world_verts(&fighter).into_iter().zip(before) {
+            assert!((after - before).magnitude() < 1e-4, "{:?} moved to {:?}", before, after);
+        }
+    }
+
+    #[test]
+    fn poly_metrics_of_known_polygons() {
+        let verts = vec![
+            // a triangle
+            Vec3d::new(0.0, 0.0, 0.0),
+            Vec3d::new(2.0, 0.0, 0.0),
+            Vec3d::new(0.0, 2.0, 0.0),
+            // and a trapezoid, which is a 2x2 square with a triangle beside it
+            Vec3d::new(0.0, 0.0, 5.0),
+            Vec3d::new(4.0, 0.0, 5.0),
+            Vec3d::new(2.0, 2.0, 5.0),
+            Vec3d::new(0.0, 2.0, 5.0),
+        ];
+        let poly = |ids: &[u32]| Polygon {
+            normal: Vec3d::new(0.0, 0.0, 1.0),
+            texture: TextureId(0),
+            verts: ids
+                .iter()
+                .map(|&id| PolyVertex {
+                    vertex_id: VertexId(id),
+                    normal_id: NormalId(0),
+                    uv: (0.0, 0.0),
+                })
+                .collect(),
+        };
+        let subobj = SubObject {
+            bsp_data: BspData {
+                collision_tree: BspData::recalculate(&verts, [poly(&[0, 1, 2]), poly(&[3, 4, 5, 6])].into_iter()),
+                norms: vec![Vec3d::new(0.0, 0.0, 1.0)],
+                verts,
+            },
+            ..Default::default()
+        };
+
+        let mut metrics = subobj
+            .poly_metrics()
+            .map(|(_, center, normal, area)| (area, center, normal))
+            .collect::<Vec<_>>();
+        metrics.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let expected = [
+            (2.0, Vec3d::new(2.0 / 3.0, 2.0 / 3.0, 0.0)),
+            (6.0, Vec3d::new(14.0 / 9.0, 8.0 / 9.0, 5.0)),
+        ];
+        assert_eq!(metrics.len(), 2);
+        for ((area, center, normal), (expected_area, expected_center)) in metrics.into_iter().zip(expected) {
+            assert!((area - expected_area).abs() < 1e-5, "{}", area);
+            assert!((center - expected_center).magnitude() < 1e-5, "{:?}", center);
+            assert_eq!(normal, Vec3d::new(0.0, 0.0, 1.0));
+        }
+        let ids = subobj.poly_metrics().map(|(id, ..)| id.0).collect::<Vec<_>>();
+        assert_eq!(ids, [0, 1]);
+
+        let (area, average) = subobj.surface_area_average_pos();
+        assert!((area - 8.0).abs() < 1e-5);
+        assert!((average.z - 30.0 / 8.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn transform_by_a_singular_matrix() {
+        let mut fighter = sample_fighter(0);
+        fighter.thruster_banks[0].glows[0].normal = NormalVec3(Vec3d::new(0.0, 0.0, 1.0));
+        let normals = |model: &Model| {
+            let weapons = model.primary_weps.iter().flatten().map(|point| point.normal.0);
+            let glows = model.thruster_banks.iter().flat_map(|bank| &bank.glows).map(|glow| glow.normal.0);
+            weapons
+                .chain(glows)
+                .chain(model.docking_bays.iter().flat_map(|dock| [dock.fvec.0, dock.uvec.0]))
+                .collect::<Vec<_>>()
+        };
+        let before = normals(&fighter);
+
+        // flatten the ship onto the yz plane
+        fighter.apply_transform(&glm::scaling(&glm::vec3(0.0, 1.0, 1.0))).unwrap();
+        assert_eq!(normals(&fighter), before);
+        assert!(fighter.primary_weps.iter().flatten().all(|point| point.position.x == 0.0));
+        assert!(fighter
+            .sub_objects
+            .iter()
+            .flat_map(|subobj| &subobj.bsp_data.norms)
+            .all(|norm| (norm.magnitude() - 1.0).abs() < 1e-4));
+        assert!(fighter.warnings.contains(&Warning::NormalsRepaired));
+
+        let audit = fighter.audit_normals();
+        let degenerate = audit.iter().filter(|audit| audit.issue == NormalIssue::DegenerateTransform).count();
+        assert_eq!(degenerate, fighter.normal_fields().count());
+        let forward = NormalAudit {
+            field: NormalField::ThrusterGlow { bank: 0, glow: 0 },
+            issue: NormalIssue::ThrusterFacingForward,
+        };
+        assert!(audit.contains(&forward));
+    }
+
+    #[test]
+    fn removing_a_path_reindexes_the_docks() {
+        let mut fighter = sample_fighter(0);
+        let path = |name: &str| Path {
+            name: name.to_string(),
+            parent: "detail0".to_string(),
+            points: vec![],
+        };
+        assert_eq!(fighter.add_path(path("$path02")), PathId(1));
+        assert_eq!(fighter.add_path(path("$path03")), PathId(2));
+        let mut dock = fighter.docking_bays[0].clone();
+        dock.path = Some(PathId(2));
+        fighter.docking_bays.push(dock);
+        let dock_paths = |model: &Model| model.docking_bays.iter().map(|dock| dock.path).collect::<Vec<_>>();
+
+        assert_eq!(fighter.remove_path(PathId(1)).name, "$path02");
+        assert_eq!(fighter.paths.iter().map(|path| &*path.name).collect::<Vec<_>>(), ["$path01", "$path03"]);
+        assert_eq!(dock_paths(&fighter), [Some(PathId(0)), Some(PathId(1))]);
+
+        fighter.reorder_paths(&[PathId(1), PathId(0)]).unwrap();
+        assert_eq!(fighter.paths[0].name, "$path03");
+        assert_eq!(dock_paths(&fighter), [Some(PathId(1)), Some(PathId(0))]);
+
+        // a bad order leaves everything where it was
+        assert_eq!(fighter.reorder_paths(&[PathId(0), PathId(2)]), Err(ReorderPathsError::InvalidId(PathId(2))));
+        assert_eq!(fighter.reorder_paths(&[PathId(0), PathId(0)]), Err(ReorderPathsError::ListedTwice(PathId(0))));
+        assert_eq!(fighter.reorder_paths(&[PathId(1)]), Err(ReorderPathsError::Missing(PathId(0))));
+        assert_eq!(fighter.paths[0].name, "$path03");
+        assert_eq!(dock_paths(&fighter), [Some(PathId(1)), Some(PathId(0))]);
+
+        fighter.remove_path(PathId(0));
+        assert_eq!(dock_paths(&fighter), [Some(PathId(0)), None]);
+    }
+
+    #[test]
+    fn turret_rest_directions() {
+        let mut fighter = sample_fighter(0);
+        let close = |a: Vec3d, b: Vec3d| (a - b).magnitude() < 1e-5;
+        // top and bottom turrets without an orientation on their bases aim forward
+        assert!(close(fighter.turret_rest_direction(0), Vec3d::new(0.0, 0.0, 1.0)));
+        assert!(close(fighter.turret_rest_direction(1), Vec3d::new(0.0, 0.0, 1.0)));
+
+        // one facing forward can't also aim forward, it aims up instead
+        fighter.turrets[0].normal = NormalVec3(Vec3d::new(0.0, 0.0, 1.0));
+        assert!(close(fighter.turret_rest_direction(0), Vec3d::new(0.0, 1.0, 0.0)));
+
+        // a side turret whose base has its $uvec pointing right and its $fvec up, which take priority over its slightly off normal
+        fighter.turrets[0].normal = NormalVec3(Vec3d::new(1.0, 0.1, 0.0).normalize());
+        fighter.sub_objects[ObjectId(1)].properties = "$uvec=1,0,0\n$fvec=0,2,0".to_string();
+        let direction = fighter.turret_rest_direction(0);
+        assert!(close(direction, Vec3d::new(0.0, 1.0, 0.0)), "{:?}", direction);
+    }
+
+    // a hull with a nacelle out to either side, and a single glow bank on the hull with two points behind each nacelle
+    fn two_engine_ship() -> Model {
+        let mut model = sample_capital(1, 4, 0);
+        let width = model.sub_objects[ObjectId(0)].bbox.x_width();
+        for (id, name, x) in [(1, "nacelle-left", -2.0 * width), (2, "nacelle-right", 2.0 * width)] {
+            let mut nacelle = model.sub_objects[ObjectId(0)].clone();
+            nacelle.obj_id = ObjectId(id);
+            nacelle.parent = Some(ObjectId(0));
+            nacelle.name = name.to_string();
+            nacelle.offset = Vec3d::new(x, 0.0, 0.0);
+            model.sub_objects.push(nacelle);
+        }
+        model.header.num_subobjects = 3;
+        model.recalc_all_children_ids();
+
+        let center = model.sub_objects[ObjectId(0)].bbox.center();
+        let glow = |x: f32, y: f32| GlowPoint {
+            position: center + Vec3d::new(x, y, -1.0),
+            normal: Vec3d::new(0.0, 0.0, -1.0),
+            radius: 1.0,
+        };
+        let glow_points = vec![
+            glow(-2.0 * width, 1.0),
+            glow(2.0 * width, 1.0),
+            glow(-2.0 * width, -1.0),
+            glow(2.0 * width, -1.0),
+        ];
+        model.glow_banks = vec![GlowPointBank {
+            obj_parent: ObjectId(0),
+            on_time: 100,
+            glow_points,
+            ..Default::default()
+        }];
+        model.recheck_warnings(Set::All);
+        model
+    }
+
+    #[test]
+    fn split_and_merge_glow_banks() {
+        let mut ship = two_engine_ship();
+        let positions = |bank: &GlowPointBank| bank.glow_points.iter().map(|point| point.position).collect::<Vec<_>>();
+        let original = positions(&ship.glow_banks[0]);
+
+        assert_eq!(ship.split_glow_bank_by_parent(0), [0, 1]);
+        assert_eq!(ship.glow_banks.len(), 2);
+        // points stay where they were in model space, only the parent changes
+        assert_eq!(ship.glow_banks[0].obj_parent, ObjectId(1));
+        assert_eq!(positions(&ship.glow_banks[0]), [original[0], original[2]]);
+        assert_eq!(ship.glow_banks[1].obj_parent, ObjectId(2));
+        assert_eq!(positions(&ship.glow_banks[1]), [original[1], original[3]]);
+        assert_eq!(ship.glow_banks[1].on_time, 100);
+
+        // splitting a bank which is all on one parent does nothing
+        assert_eq!(ship.split_glow_bank_by_parent(1), [1]);
+        assert_eq!(ship.glow_banks.len(), 2);
+
+        ship.glow_banks[1].on_time = 200;
+        ship.glow_banks[1].properties = "$glow_texture=thrust".to_string();
+        assert!(matches!(ship.merge_glow_banks(&[0, 1]), Err(MergeError::FieldsDiffer(fields)) if fields == ["on_time", "properties"]));
+        assert!(matches!(ship.merge_glow_banks(&[0, 5]), Err(MergeError::InvalidBank(5))));
+        assert!(matches!(ship.merge_glow_banks(&[]), Err(MergeError::NoBanks)));
+
+        ship.glow_banks[1].on_time = 100;
+        ship.glow_banks[1].properties = String::new();
+        assert!(matches!(ship.merge_glow_banks(&[1, 0]), Ok(0)));
+        assert_eq!(ship.glow_banks.len(), 1);
+        assert_eq!(ship.glow_banks[0].obj_parent, ObjectId(0));
+        assert_eq!(positions(&ship.glow_banks[0]), [original[0], original[2], original[1], original[3]]);
+    }
+
+    #[test]
+    fn transform_preview_matches_the_real_thing() {
+        let fighter = sample_fighter(0);
+        let close = |a: Vec3d, b: Vec3d| (a - b).magnitude() < 0.05;
+        let quarter_turn = glm::rotation(std::f32::consts::FRAC_PI_2, &glm::vec3(0.0, 1.0, 0.0));
+        let rigid = glm::translation(&glm::vec3(3.0, -2.0, 7.0)) * quarter_turn;
+        let tilted = glm::rotation(0.3, &glm::vec3(1.0, 1.0, 0.0).normalize());
+
+        for (matrix, exact_bbox) in [(rigid, true), (tilted, false)] {
+            let (bbox, radius) = fighter.preview_transform_bounds(&matrix);
+            let mut transformed = fighter.clone();
+            transformed.apply_transform(&matrix).unwrap();
+            let actual = transformed.header.bbox;
+            assert!((radius - transformed.header.max_radius).abs() < 0.05, "{} {}", radius, transformed.header.max_radius);
+            if exact_bbox {
+                assert!(close(bbox.min, actual.min) && close(bbox.max, actual.max), "{:?} {:?}", bbox, actual);
+            } else {
+                // rotated corners make the preview larger than the real bbox, but never smaller
+                assert!(bbox.pad(0.05).contains(actual.min) && bbox.pad(0.05).contains(actual.max), "{:?} {:?}", bbox, actual);
+            }
+        }
+    }
+
+    #[test]
+    fn unreachable_subobjects() {
+        let mut fighter = sample_fighter(0);
+        let mut add = |name: &str, parent: Option<u32>| {
+            let mut subobj = fighter.sub_objects[ObjectId(6)].clone();
+            subobj.obj_id = ObjectId(fighter.sub_objects.len() as u32);
+            subobj.parent = parent.map(ObjectId);
+            subobj.name = name.to_string();
+            fighter.sub_objects.push(subobj);
+        };
+        // forgotten geometry, and something under it
+        add("leftover", None);
+        add("leftover-child", Some(9));
+        // the destroyed version of a turret, which the engine swaps in
+        add("turret01a-destroyed", None);
+        fighter.header.num_subobjects = fighter.sub_objects.len() as u32;
+        fighter.recalc_all_children_ids();
+        fighter.recalc_semantic_name_links();
+        fighter.recheck_warnings(Set::All);
+
+        let unreachable = fighter
+            .warnings
+            .iter()
+            .filter_map(|warning| match warning {
+                Warning::UnreachableSubobject(id) => Some(*id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // the debris, 7 and 8, aren't part of any detail level either, but that's expected of them
+        assert_eq!(unreachable, [ObjectId(9), ObjectId(10)]);
+
+        fighter.sub_objects[ObjectId(9)].parent = Some(ObjectId(0));
+        fighter.recalc_all_children_ids();
+        fighter.recheck_warnings(Set::One(Warning::UnreachableSubobject(ObjectId(9))));
+        fighter.recheck_warnings(Set::One(Warning::UnreachableSubobject(ObjectId(10))));
+        assert!(!fighter.warnings.iter().any(|warning| matches!(warning, Warning::UnreachableSubobject(_))));
+    }
+
+    #[test]
+    fn indexed_mesh_matches_the_leaves() {
+        let capital = sample_capital(1, 9, 2);
+        let bsp_data = &capital.sub_objects[ObjectId(0)].bsp_data;
+        let (verts, norms, polys) = bsp_data.to_indexed_mesh();
+        assert_eq!((verts.len(), norms.len()), (bsp_data.verts.len(), bsp_data.norms.len()));
+        assert_eq!(polys.len(), 9);
+
+        for ((_, leaf), poly) in bsp_data.collision_tree.leaves().zip(&polys) {
+            assert_eq!((poly.normal, poly.texture), (leaf.normal, leaf.texture));
+            let from_leaf = leaf
+                .verts
+                .iter()
+                .map(|vert| (bsp_data.verts[vert.vertex_id.0 as usize], bsp_data.norms[vert.normal_id.0 as usize], vert.uv));
+            let from_indices =
+                (0..poly.vertex_ids.len()).map(|i| (verts[poly.vertex_ids[i] as usize], norms[poly.normal_ids[i] as usize], poly.uvs[i]));
+            assert!(from_leaf.eq(from_indices));
+        }
+    }
+
+    #[test]
+    fn shield_coverage_of_full_and_half_shields() {
+        let mut fighter = sample_fighter(0);
+        let coverage = fighter.shield_coverage();
+        assert!(coverage > 0.99, "{}", coverage);
+
+        // without the shield's left, bottom and back faces, which face as much of the boxy hull as the other three
+        let shield = fighter.shield_data.as_mut().unwrap();
+        shield.polygons.retain(|poly| poly.normal.x.min(poly.normal.y).min(poly.normal.z) > -0.5);
+        let coverage = fighter.shield_coverage();
+        assert!((coverage - 0.5).abs() < 0.05, "{}", coverage);
+
+        fighter.shield_data = None;
+        assert_eq!(fighter.shield_coverage(), 0.0);
+    }
+
+    #[test]
+    fn editing_cross_sections() {
+        let mut model = sample_fighter(0);
+        model.header.cross_sections = vec![];
+        assert_eq!(model.cross_section_radius_at(0.0), None);
+
+        // the first takes the model's radius, the rest are interpolated from their neighbors
+        assert_eq!(model.insert_cross_section(0.0), 0);
+        assert_eq!(model.header.cross_sections, [(0.0, model.header.max_radius)]);
+        model.header.cross_sections = vec![(-10.0, 2.0), (10.0, 6.0)];
+        assert_eq!(model.insert_cross_section(5.0), 1);
+        assert_eq!(model.header.cross_sections[1], (5.0, 5.0));
+        // at the ends, the end radius is kept
+        assert_eq!(model.insert_cross_section(-20.0), 0);
+        assert_eq!(model.insert_cross_section(20.0), 4);
+        assert_eq!(model.header.cross_sections, [(-20.0, 2.0), (-10.0, 2.0), (5.0, 5.0), (10.0, 6.0), (20.0, 6.0)]);
+        // and inserting where one already is gives that one back
+        assert_eq!(model.insert_cross_section(5.0), 2);
+        assert_eq!(model.header.cross_sections.len(), 5);
+
+        // sampling past the ends holds the end radii
+        assert_eq!(model.cross_section_radius_at(-100.0), Some(2.0));
+        assert_eq!(model.cross_section_radius_at(100.0), Some(6.0));
+        assert_eq!(model.cross_section_radius_at(7.5), Some(5.5));
+
+        assert_eq!(model.set_cross_section(1, 15.0, 8.0), Ok(3));
+        assert_eq!(model.header.cross_sections, [(-20.0, 2.0), (5.0, 5.0), (10.0, 6.0), (15.0, 8.0), (20.0, 6.0)]);
+        assert_eq!(model.set_cross_section(0, 10.0, 1.0), Err(CrossSectionError::DuplicateDepth(2)));
+        assert_eq!(model.set_cross_section(9, 0.0, 1.0), Err(CrossSectionError::InvalidIndex(9)));
+        model.remove_cross_section(0);
+        assert_eq!(model.header.cross_sections[0], (5.0, 5.0));
+
+        assert!(!model.warnings.contains(&Warning::InvalidCrossSections));
+        model.set_cross_section(0, 5.0, 0.0).unwrap();
+        assert!(model.warnings.contains(&Warning::InvalidCrossSections));
+        model.header.cross_sections = vec![(5.0, 1.0), (1.0, 1.0)];
+        model.recheck_warnings(Set::One(Warning::InvalidCrossSections));
+        assert!(model.warnings.contains(&Warning::InvalidCrossSections));
+    }
+
+    #[test]
+    fn dock_approach_vector() {
+        let dock = Dock {
+            position: Vec3d::new(1.0, 2.0, 3.0),
+            fvec: NormalVec3(Vec3d::new(0.0, 1.0, 0.0)),
+            ..Default::default()
+        };
+        let (start, direction) = dock.approach_vector(10.0);
+        assert_eq!(start, dock.position - Vec3d::new(0.0, 10.0, 0.0));
+        assert_eq!(direction, Vec3d::new(0.0, 1.0, 0.0));
+    }
+
+    #[test]
+    fn rebasing_a_subtree_keeps_it_in_place() {
+        let world_verts = |model: &Model| {
+            (model.sub_objects.iter())
+                .map(|subobj| {
+                    let offset = model.get_total_subobj_offset(subobj.obj_id);
+                    subobj.bsp_data.verts.iter().map(|&vert| vert + offset).collect::<Vec<_>>()
+                })
+                .collect::<Vec<_>>()
+        };
+        let world_fire_points = |model: &Model| {
+            let turret = &model.turrets[0];
+            let offset = model.get_total_subobj_offset(turret.gun_obj);
+            turret.fire_points.iter().map(|&point| point + offset).collect::<Vec<_>>()
+        };
+
+        let mut fighter = sample_fighter(0);
+        let (verts, fire_points) = (world_verts(&fighter), world_fire_points(&fighter));
+        assert!(!fire_points.is_empty());
+        let offsets = fighter.sub_objects.iter().map(|subobj| subobj.offset).collect::<Vec<_>>();
+        let delta = Vec3d::new(1.0, 2.0, 3.0);
+        fighter.rebase_subtree_offsets(ObjectId(1), delta);
+
+        // only turret01a's origin is moved, its gun follows it
+        assert_eq!(fighter.sub_objects[ObjectId(1)].offset, offsets[1] + delta);
+        assert_eq!(fighter.get_total_subobj_offset(ObjectId(2)), offsets[1] + offsets[2] + delta);
+        for (i, (before, after)) in verts.iter().zip(world_verts(&fighter)).enumerate() {
+            assert_eq!(before.len(), after.len());
+            for (before, after) in before.iter().zip(after) {
+                assert!((*before - after).magnitude() < 1e-5, "{}: {:?} moved to {:?}", i, before, after);
+            }
+            if i != 1 {
+                assert_eq!(fighter.sub_objects[ObjectId(i as u32)].offset, offsets[i]);
+            }
+        }
+        for (before, after) in fire_points.iter().zip(world_fire_points(&fighter)) {
+            assert!((*before - after).magnitude() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn clustering_a_weapon_bank() {
+        let mut fighter = sample_fighter(0);
+        let point = |x: f32, z: f32| (Vec3d::new(x, 0.0, z), z);
+        let banks = |model: &Model| {
+            let points = |bank: &Vec<WeaponHardpoint>| bank.iter().map(|point| (point.position, point.offset)).collect::<Vec<_>>();
+            model.primary_weps.iter().map(points).collect::<Vec<_>>()
+        };
+        // the left and right guns, interleaved
+        let (left, right) = ([point(-5.0, 0.0), point(-5.0, 1.0), point(-5.0, 2.0)], [point(5.0, 0.5), point(5.0, 1.5)]);
+        fighter.primary_weps[0] = ([left[0], right[0], left[1], left[2], right[1]].into_iter())
+            .map(|(position, offset)| WeaponHardpoint { position, offset, ..Default::default() })
+            .collect();
+        let second_bank = banks(&fighter)[1].clone();
+
+        assert_eq!(fighter.cluster_weapon_bank(true, 0, 1.5), [0, 2]);
+        assert_eq!(banks(&fighter), [left.to_vec(), second_bank, right.to_vec()]);
+
+        // closer than any two points, every point gets its own bank
+        assert_eq!(fighter.cluster_weapon_bank(true, 2, 0.5), [2, 3]);
+        assert_eq!(banks(&fighter)[2..], [vec![right[0]], vec![right[1]]]);
+    }
+
+    #[test]
+    fn weapon_point_refs() {
+        let mut fighter = sample_fighter(0);
+        let refs = fighter.iter_weapon_points().map(|(weapon, _)| weapon).collect::<Vec<_>>();
+        let (primary, secondary) = (WeaponKind::Primary, WeaponKind::Secondary);
+        let expected = [(primary, 0, 0), (primary, 0, 1), (primary, 1, 0), (primary, 1, 1)].into_iter();
+        let expected = expected.chain((0..fighter.secondary_weps[0].len()).map(|point| (secondary, 0, point)));
+        let expected = expected.map(|(kind, bank, point)| WeaponPointRef::new(kind, bank, point));
+        assert_eq!(refs, expected.collect::<Vec<_>>());
+        assert!(refs.windows(2).all(|pair| pair[0] < pair[1]));
+        for (weapon, point) in fighter.iter_weapon_points() {
+            assert!(std::ptr::eq(fighter.weapon_point(weapon).unwrap(), point));
+        }
+        assert!(fighter.weapon_point(WeaponPointRef::new(primary, 2, 0)).is_none());
+        assert!(fighter.weapon_point(WeaponPointRef::new(secondary, 0, 99)).is_none());
+
+        // edits which don't add or remove points leave the refs pointing at the same points
+        let weapon = refs[3];
+        fighter.version = Version::V21_17;
+        fighter.weapon_point_mut(weapon).unwrap().offset = 0.5;
+        fighter.weapon_point_mut(weapon).unwrap().position.x += 1.0;
+        fighter.recheck_warnings(Set::All);
+        assert_eq!(fighter.primary_weps[1][1].offset, 0.5);
+        assert_eq!(fighter.iter_weapon_points().map(|(weapon, _)| weapon).collect::<Vec<_>>(), refs);
+        assert!(fighter.warnings.contains(&Warning::WeaponOffsetInvalidVersion(weapon)));
+        fighter.weapon_point_mut(weapon).unwrap().offset = 0.0;
+        fighter.recheck_warnings(Set::One(Warning::WeaponOffsetInvalidVersion(weapon)));
+        assert!(!fighter.warnings.contains(&Warning::WeaponOffsetInvalidVersion(weapon)));
+    }
+
+    #[test]
+    fn removing_the_untextured_slot() {
+        fn first_poly(model: &mut Model) -> &mut TextureId {
+            let tree = &mut model.sub_objects[ObjectId(0)].bsp_data.collision_tree;
+            &mut tree.leaves_mut().next().unwrap().1.texture
+        }
+        let mut fighter = sample_fighter(0);
+        *first_poly(&mut fighter) = TextureId(9);
+        fighter.refresh_untextured_slot();
+        assert_eq!(fighter.textures, ["fighter-hull", "fighter-fittings", UntexturedNames::default().slot_name()]);
+        assert_eq!((fighter.untextured_idx, *first_poly(&mut fighter)), (Some(TextureId(2)), TextureId(2)));
+        assert!(fighter.warnings.contains(&Warning::UntexturedPolygons));
+
+        // once the polygon has a real texture again, the slot can go
+        *first_poly(&mut fighter) = TextureId(0);
+        fighter.remove_texture(TextureId(2));
+        assert_eq!(fighter.textures, ["fighter-hull", "fighter-fittings"]);
+        assert_eq!((fighter.untextured_idx, *first_poly(&mut fighter)), (None, TextureId(0)));
+        assert!(!fighter.warnings.contains(&Warning::UntexturedPolygons));
+    }
+
+    #[test]
+    fn lod_stats_of_two_detail_levels() {
+        let mut fighter = sample_fighter(0);
+        fighter.header.detail_levels.truncate(2);
+        let stats = fighter.lod_stats();
+        let counts = stats.iter().map(|lod| (lod.root, lod.triangles, lod.vertices)).collect::<Vec<_>>();
+        // detail0 is a box of 4x4 quads a side with the two turrets' boxes of 6 quads under it, detail1 has 2x2 a side
+        assert_eq!(counts, [(ObjectId(0), 2 * (96 + 4 * 6), 4 * (96 + 4 * 6)), (ObjectId(5), 2 * 24, 4 * 24)]);
+        assert!(stats[1].triangles < stats[0].triangles);
+
+        // the turrets are within the hull's corners, until one is moved out, which counts where it is in model space
+        let hull_corner = fighter.sub_objects[ObjectId(5)].bbox.max.magnitude();
+        assert!(stats.iter().all(|lod| (lod.radius - hull_corner).abs() < 1e-5));
+        fighter.sub_objects[ObjectId(1)].offset.x += 2.0 * hull_corner;
+        let stats = fighter.lod_stats();
+        assert!(stats[0].radius > 2.0 * hull_corner);
+        assert!((stats[1].radius - hull_corner).abs() < 1e-5);
+    }
+
+    #[test]
+    fn inserting_an_intermediate_parent() {
+        let mut fighter = sample_fighter(0);
+        let world_offsets = |model: &Model| (0..9).map(|id| model.get_total_subobj_offset(ObjectId(id))).collect::<Vec<_>>();
+        let before = world_offsets(&fighter);
+
+        use InsertParentError::*;
+        assert_eq!(fighter.insert_intermediate_parent("turrets".to_string(), &[]), Err(NoChildren));
+        assert_eq!(fighter.insert_intermediate_parent("turrets".to_string(), &[ObjectId(1), ObjectId(2)]), Err(DifferentParents));
+        assert_eq!(fighter.insert_intermediate_parent("lods".to_string(), &[ObjectId(5), ObjectId(6)]), Err(DetailLevel(ObjectId(5))));
+        let name_in_use = fighter.insert_intermediate_parent("detail1".to_string(), &[ObjectId(1)]);
+        assert_eq!(name_in_use, Err(NameInUse("detail1".to_string())));
+        assert_eq!(fighter.sub_objects.len(), 9);
+
+        let group = fighter.insert_intermediate_parent("turrets".to_string(), &[ObjectId(1), ObjectId(3)]);
+        let group = group.unwrap();
+        assert_eq!(group, ObjectId(9));
+        let subobj = &fighter.sub_objects[group];
+        assert_eq!((subobj.name.as_str(), subobj.parent, subobj.offset), ("turrets", Some(ObjectId(0)), Vec3d::ZERO));
+        assert!(subobj.bsp_data.collision_tree.leaves().next().is_none());
+        assert_eq!(subobj.children().copied().collect::<Vec<_>>(), [ObjectId(1), ObjectId(3)]);
+        assert_eq!(fighter.sub_objects[ObjectId(0)].children().copied().collect::<Vec<_>>(), [group]);
+        assert_eq!((fighter.sub_objects[ObjectId(1)].parent, fighter.sub_objects[ObjectId(3)].parent), (Some(group), Some(group)));
+
+        // nothing moved
+        for (before, after) in before.iter().zip(world_offsets(&fighter)) {
+            assert!((*before - after).magnitude() < 1e-5, "{:?} moved to {:?}", before, after);
+        }
+        assert_eq!(fighter.header.num_subobjects, 10);
+        assert!(fighter.errors.is_empty(), "{:?}", fighter.errors);
+    }
+
+    #[test]
+    fn decomposing_a_trs_matrix() {
+        let close = |a: Vec3d, b: Vec3d| (a - b).magnitude() < 1e-5;
+        let rotation = glm::rotation(0.7, &glm::vec3(1.0, 2.0, 3.0).normalize());
+        let translation = glm::translation(&glm::vec3(1.0, -2.0, 3.0));
+        for scale in [Vec3d::new(2.0, 3.0, 4.0), Vec3d::new(0.5, 0.5, 0.5), Vec3d::new(2.0, 3.0, -4.0)] {
+            let matrix = translation * rotation * glm::scaling(&scale.into());
+            let (t, r, s) = decompose_mat4(&matrix);
+            assert!(close(t, Vec3d::new(1.0, -2.0, 3.0)), "{:?}", t);
+            assert!(close(s, scale), "{:?} {:?}", s, scale);
+            let (r, expected) = (glm::Mat3x3::from(r), glm::mat4_to_mat3(&rotation));
+            assert!((r - expected).abs().max() < 1e-5, "{} {}", r, expected);
+
+            // and they put it back together
+            let recomposed = glm::translation(&t.into()) * glm::mat3_to_mat4(&r) * glm::scaling(&s.into());
+            assert!((recomposed - matrix).abs().max() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn targeting_an_offset_hull() {
+        let close = |a: Vec3d, b: Vec3d| (a - b).magnitude() < 1e-3;
+        let mut fighter = sample_fighter(0);
+        fighter.visual_center = Vec3d::ZERO;
+        fighter.apply_transform(&glm::translation(&glm::vec3(15.0, 0.0, 0.0))).unwrap();
+        let hull = &fighter.sub_objects[ObjectId(0)];
+        assert!(close(hull.offset + hull.bbox.center(), Vec3d::new(15.0, 0.0, 0.0)), "{:?}", hull.bbox);
+        let hull_end = hull.offset.x + hull.bbox.max.x;
+        // the header bbox always reaches back to the origin
+        let bbox = fighter.header.bbox;
+        assert!(bbox.min.x <= 0.0 && bbox.max.x == hull_end);
+        let (y, z) = (bbox.max.y, bbox.max.z);
+
+        // without a visual center, everything is about the origin
+        let info = fighter.targeting_reference();
+        assert_eq!((info.center, info.autocentered, info.radius), (Vec3d::ZERO, false, fighter.header.max_radius));
+        assert_eq!(info.bbox_center, bbox.center());
+        assert!((info.centered_radius - Vec3d::new(hull_end, y, z).magnitude()).abs() < 1e-3);
+        // and the hull's center by area would move it back by its offset
+        assert!(close(fighter.simulate_autocenter_shift(), Vec3d::new(-15.0, 0.0, 0.0)), "{:?}", fighter.simulate_autocenter_shift());
+
+        fighter.visual_center = Vec3d::new(15.0, 0.0, 0.0);
+        let info = fighter.targeting_reference();
+        assert_eq!((info.center, info.autocentered), (fighter.visual_center, true));
+        // the origin is the furthest part of the bbox from the hull's center
+        assert!((info.centered_radius - Vec3d::new(15.0, y, z).magnitude()).abs() < 1e-3);
+        assert!(close(fighter.simulate_autocenter_shift(), Vec3d::new(-15.0, 0.0, 0.0)));
+
+        // but the origin is off the side of the hull itself
+        fighter.recheck_warnings(Set::One(Warning::VisualCenterOutsideDetail0));
+        assert!(!fighter.warnings.contains(&Warning::VisualCenterOutsideDetail0));
+        fighter.visual_center = Vec3d::new(1.0, 0.0, 0.0);
+        fighter.recheck_warnings(Set::One(Warning::VisualCenterOutsideDetail0));
+        assert!(fighter.warnings.contains(&Warning::VisualCenterOutsideDetail0));
+    }
+
+    #[test]
+    fn oriented_box_of_a_diagonal_ship() {
+        let sizes = |bbox: &BoundingBox| {
+            let mut sizes = [bbox.x_width(), bbox.y_height(), bbox.z_length()];
+            sizes.sort_by(f32::total_cmp);
+            sizes
+        };
+        let volume = |bbox: &BoundingBox| bbox.x_width() * bbox.y_height() * bbox.z_length();
+
+        let mut fighter = sample_fighter(0);
+        let (rotation, unrotated) = fighter.oriented_bounding_box();
+        assert_eq!([rotation.rvec, rotation.uvec, rotation.fvec], [Mat3d::IDENTITY.rvec, Mat3d::IDENTITY.uvec, Mat3d::IDENTITY.fvec]);
+
+        let turn = glm::rotation(std::f32::consts::FRAC_PI_4, &glm::vec3(0.0, 0.0, 1.0));
+        fighter.apply_transform(&turn).unwrap();
+        let (rotation, obb) = fighter.oriented_bounding_box();
+        // each axis is along one of the ship's own, turned with it
+        let ship_axes = [Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)].map(|axis| &turn * axis);
+        for axis in [rotation.rvec, rotation.uvec, rotation.fvec] {
+            assert!(ship_axes.iter().any(|ship_axis| (axis.dot(ship_axis).abs() - 1.0).abs() < 1e-4), "{:?}", axis);
+        }
+        for (a, b) in sizes(&obb).into_iter().zip(sizes(&unrotated)) {
+            assert!((a - b).abs() < 1e-3, "{:?} {:?}", obb, unrotated);
+        }
+        assert!(volume(&obb) < 0.8 * volume(&fighter.header.bbox));
+    }
+
+    #[test]
+    fn silhouette_of_an_elongated_ship() {
+        let mut fighter = sample_fighter(0);
+        let mut half = fighter.sub_objects[ObjectId(0)].bbox.max;
+        half.z *= 3.0;
+        for subobj in &mut fighter.sub_objects.0 {
+            for vert in &mut subobj.bsp_data.verts {
+                vert.z *= 3.0;
+            }
+        }
+        let front = fighter.silhouette_radius(Vec3d::new(0.0, 0.0, 1.0));
+        let side = fighter.silhouette_radius(Vec3d::new(-2.0, 0.0, 0.0));
+        assert!(side > front && side >= half.z);
+        // the turrets stick out above and below the hull, but not beyond its ends
+        assert!(front > half.x.hypot(half.y) - 1e-4 && front < half.x.hypot(half.y + 1.0));
+
+        // which way along the axis and how long dir is doesn't matter
+        assert_eq!(fighter.silhouette_radius(Vec3d::new(0.0, 0.0, -5.0)), front);
+        assert_eq!(fighter.silhouette_radius(Vec3d::ZERO), 0.0);
+        // nothing sticks out past the bounding sphere
+        let diagonal = fighter.silhouette_radius(Vec3d::new(1.0, 1.0, 1.0));
+        assert!(diagonal <= half.magnitude() + 1.0 && diagonal > 0.0);
+    }
+
+    #[test]
+    fn duplicate_points() {
+        let mut fighter = sample_fighter(0);
+        let duplicate = Warning::DuplicateWeaponPoint { primary: true, bank: 0 };
+        assert!(!fighter.warnings.contains(&duplicate));
+
+        // pasted twice, just off the original, which still counts
+        let mut point = fighter.primary_weps[0][0].clone();
+        point.position += Vec3d::new(0.0, 0.0, DUPLICATE_POINT_EPSILON / 2.0);
+        fighter.primary_weps[0].push(point);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&duplicate));
+        assert!(!fighter.warnings.contains(&Warning::DuplicateWeaponPoint { primary: true, bank: 1 }));
+
+        // facing the other way, it's a different point
+        fighter.primary_weps[0][2].normal = NormalVec3(-fighter.primary_weps[0][2].normal.0);
+        fighter.recheck_warnings(Set::One(duplicate.clone()));
+        assert!(!fighter.warnings.contains(&duplicate));
+
+        let secondary = fighter.secondary_weps[0][1].clone();
+        fighter.secondary_weps[0].insert(0, secondary);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::DuplicateWeaponPoint { primary: false, bank: 0 }));
+
+        // for docking bays and eye points, only the later copy is flagged
+        fighter.docking_bays.push(fighter.docking_bays[0].clone());
+        let mut eye = fighter.eye_points[0].clone();
+        eye.attached_subobj = Some(ObjectId(1));
+        fighter.eye_points.push(eye.clone());
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.warnings.contains(&Warning::DuplicateDockingBay(1)) && !fighter.warnings.contains(&Warning::DuplicateDockingBay(0)));
+        assert!(!fighter.warnings.contains(&Warning::DuplicateEyePoint(1)));
+        fighter.eye_points[1].attached_subobj = fighter.eye_points[0].attached_subobj;
+        fighter.recheck_warnings(Set::One(Warning::DuplicateEyePoint(1)));
+        assert!(fighter.warnings.contains(&Warning::DuplicateEyePoint(1)));
+    }
+
+    #[test]
+    fn reparenting_to_detail0() {
+        let mut fighter = sample_fighter(0);
+        let world_verts = |model: &Model, id: ObjectId| {
+            let offset = model.get_total_subobj_offset(id);
+            model.sub_objects[id].bsp_data.verts.iter().map(|&vert| vert + offset).collect::<Vec<_>>()
+        };
+
+        for id in [ObjectId(2), ObjectId(7)] {
+            let before = world_verts(&fighter, id);
+            fighter.reparent_to_detail0(id).unwrap();
+            assert_eq!(fighter.sub_objects[id].parent, Some(ObjectId(0)));
+            assert!(fighter.sub_objects[ObjectId(0)].children().any(|&child| child == id));
+            for (before, after) in before.iter().zip(world_verts(&fighter, id)) {
+                assert!((*before - after).magnitude() < 1e-5, "{:?} moved to {:?}", before, after);
+            }
+        }
+        assert!(!fighter.sub_objects[ObjectId(1)].children().any(|&child| child == ObjectId(2)));
+        // the gun is no longer on its base, which is checked straight away
+        assert_eq!(fighter.errors.iter().collect::<Vec<_>>(), [&Error::InvalidTurretGunSubobject(0)]);
+
+        assert_eq!(fighter.reparent_to_detail0(ObjectId(5)), Err(ReparentError::DetailLevel(ObjectId(5))));
+        assert_eq!(fighter.reparent_to_detail0(ObjectId(0)), Err(ReparentError::DetailLevel(ObjectId(0))));
+        fighter.header.detail_levels.clear();
+        assert_eq!(fighter.reparent_to_detail0(ObjectId(1)), Err(ReparentError::NoDetail0));
+    }
+
+    #[test]
+    fn sanitizing_degenerate_docks() {
+        let orthonormal = |dock: &Dock| {
+            let (fvec, uvec) = (dock.fvec.0, dock.uvec.0);
+            (fvec.magnitude() - 1.0).abs() < 1e-5 && (uvec.magnitude() - 1.0).abs() < 1e-5 && fvec.dot(&uvec).abs() < 1e-5
+        };
+        use NormalIssue::*;
+        let cases = [
+            (Vec3d::ZERO, Vec3d::ZERO, Some(Zero), Some(Zero)),
+            (Vec3d::ZERO, Vec3d::new(1.0, 0.0, 0.0), Some(Zero), None),
+            (Vec3d::new(0.0, 0.0, 3.0), Vec3d::new(0.0, 1.0, 1.0), Some(NotUnitLength), Some(NotUnitLength)),
+            (Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(2.0, 0.0, 0.0), None, Some(NotUnitLength)),
+            (Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), None, Some(NotOrthogonal)),
+        ];
+        for (fvec, uvec, fvec_issue, uvec_issue) in cases {
+            let mut dock = Dock {
+                fvec: NormalVec3(fvec),
+                uvec: NormalVec3(uvec),
+                ..Default::default()
+            };
+            assert_eq!(dock.sanitize(), (fvec_issue, uvec_issue), "{:?} {:?}", fvec, uvec);
+            assert!(orthonormal(&dock), "{:?} {:?} became {:?}", fvec, uvec, dock);
+            assert!(dock.get_uvec_angle().is_finite());
+            // and it's left alone after that
+            let fixed = dock.clone();
+            assert_eq!(dock.sanitize(), (None, None));
+            assert_eq!((dock.fvec.0, dock.uvec.0), (fixed.fvec.0, fixed.uvec.0));
+        }
+
+        // edited into a bad state, it's fixed before saving, and noted
+        let mut fighter = sample_fighter(0);
+        fighter.docking_bays[0].fvec = NormalVec3(Vec3d::ZERO);
+        fighter.clean_up();
+        assert!(orthonormal(&fighter.docking_bays[0]));
+        assert!(fighter.warnings.contains(&Warning::NormalsRepaired));
+    }
+
+    #[test]
+    fn compacting_object_ids() {
+        let mut fighter = sample_fighter(0);
+        fighter.glow_banks.push(GlowPointBank { obj_parent: ObjectId(7), ..Default::default() });
+        fighter.paths[0].points[0].turrets = vec![ObjectId(1), ObjectId(3)];
+        let expected = fighter.write_to_vec().unwrap();
+
+        // the same model, with ids that have nothing to do with the subobjects' positions
+        let scramble = |id: ObjectId| ObjectId(100 + id.0 * 10);
+        let mut model = fighter.clone();
+        for subobj in model.sub_objects.iter_mut() {
+            subobj.obj_id = scramble(subobj.obj_id);
+            subobj.parent = subobj.parent.map(scramble);
+            subobj.children = subobj.children.iter().map(|&id| scramble(id)).collect();
+        }
+        model.header.detail_levels = model.header.detail_levels.iter().map(|&id| scramble(id)).collect();
+        for turret in &mut model.turrets {
+            (turret.base_obj, turret.gun_obj) = (scramble(turret.base_obj), scramble(turret.gun_obj));
+        }
+        model.glow_banks[0].obj_parent = scramble(model.glow_banks[0].obj_parent);
+        model.eye_points[0].attached_subobj = model.eye_points[0].attached_subobj.map(scramble);
+        let turrets = &mut model.paths[0].points[0].turrets;
+        *turrets = turrets.iter().map(|&id| scramble(id)).collect();
+
+        model.compact_object_ids();
+        let ids = model.sub_objects.iter().map(|subobj| subobj.obj_id.0).collect::<Vec<_>>();
+        assert_eq!(ids, (0..fighter.sub_objects.len() as u32).collect::<Vec<_>>());
+        assert!(model.check_invariants().is_empty());
+        assert_eq!(model.errors, fighter.errors);
+        assert_eq!(model.write_to_vec().unwrap(), expected);
+
+        // a reference to an id no subobject has is dropped
+        model.glow_banks[0].obj_parent = ObjectId(50);
+        model.paths[0].points[0].turrets.push(ObjectId(50));
+        model.compact_object_ids();
+        assert!(model.glow_banks.is_empty());
+        assert_eq!(model.paths[0].points[0].turrets, [ObjectId(1), ObjectId(3)]);
+    }
+
+    #[test]
+    fn bbox_contributors() {
+        let faces = |id: ObjectId| {
+            (ALL_AXES.into_iter())
+                .flat_map(|axis| [(id, axis, false), (id, axis, true)])
+                .collect::<Vec<_>>()
+        };
+
+        // detail0 blown up well past everything on it
+        let mut fighter = sample_fighter(0);
+        for vert in &mut fighter.sub_objects[ObjectId(0)].bsp_data.verts {
+            *vert = *vert * 10.0;
+        }
+        assert_eq!(fighter.bbox_contributors(), faces(ObjectId(0)));
+
+        // a stray turret is found, even though it's a child of a child, and only it moves
+        fighter.sub_objects[ObjectId(4)].offset.x += 1000.0;
+        let mut expected = faces(ObjectId(0));
+        expected[1].0 = ObjectId(4);
+        assert_eq!(fighter.bbox_contributors(), expected);
+
+        // lower detail levels and debris don't count, however big they are
+        for id in [ObjectId(5), ObjectId(7)] {
+            for vert in &mut fighter.sub_objects[id].bsp_data.verts {
+                *vert = *vert * 1000.0;
+            }
+        }
+        assert_eq!(fighter.bbox_contributors(), expected);
+
+        fighter.header.detail_levels.clear();
+        assert_eq!(fighter.bbox_contributors(), []);
+    }
+
+    #[test]
+    fn fixing_insignia_detail_levels() {
+        let mut fighter = sample_fighter(0);
+        let vert = |i: u32| PolyVertex { vertex_id: VertexId(i), normal_id: (), uv: (0.0, 0.0) };
+        let square = Insignia {
+            detail_level: 2,
+            vertices: vec![
+                Vec3d::ZERO,
+                Vec3d::new(2.0, 0.0, 0.0),
+                Vec3d::new(2.0, 2.0, 0.0),
+                Vec3d::new(0.0, 2.0, 0.0),
+            ],
+            offset: Vec3d::new(0.0, 0.0, 5.0),
+            faces: vec![(vert(0), vert(1), vert(2)), (vert(0), vert(2), vert(3))],
+        };
+        fighter.insignias = vec![Insignia { detail_level: 0, ..square.clone() }, square];
+
+        let summary = fighter.insignia_summary();
+        assert_eq!((summary[1].detail_level, summary[1].faces, summary[1].area), (2, 2, 4.0));
+        assert!((summary[1].centroid - Vec3d::new(1.0, 1.0, 5.0)).magnitude() < 1e-5);
+
+        // reordering the detail levels carries the insignias along
+        fighter.swap_detail_levels(0, 2);
+        assert_eq!(fighter.insignias.iter().map(|insignia| insignia.detail_level).collect::<Vec<_>>(), [2, 0]);
+        fighter.swap_detail_levels(0, 2);
+
+        // removing the detail level an insignia was on flags it, and moving it to one which exists clears that
+        fighter.delete_subobject(ObjectId(6));
+        assert!(fighter.warnings.contains(&Warning::InsigniaInvalidDetailLevel(1)));
+        assert_eq!(fighter.set_insignia_detail_level(1, 2), Err(InsigniaError::InvalidDetailLevel(2)));
+        assert_eq!(fighter.set_insignia_detail_level(2, 1), Err(InsigniaError::InvalidIndex(2)));
+        assert!(fighter.warnings.contains(&Warning::InsigniaInvalidDetailLevel(1)));
+        assert_eq!(fighter.set_insignia_detail_level(1, 1), Ok(()));
+        assert!(!fighter.warnings.contains(&Warning::InsigniaInvalidDetailLevel(1)));
+        assert_eq!(fighter.insignias[1].detail_level, 1);
+    }
+
+    #[test]
+    fn vec3d_components() {
+        let vec = Vec3d::new(1.0, 2.0, 3.0);
+        assert_eq!(vec.iter().collect::<Vec<_>>(), [1.0, 2.0, 3.0]);
+        assert_eq!(vec.iter().collect::<Vec3d>(), vec);
+        assert_eq!(vec.iter().map(|val| val * 2.0).collect::<Vec3d>(), Vec3d::new(2.0, 4.0, 6.0));
+        assert_eq!(Vec3d::from_iter_exact(vec.iter()), Some(vec));
+
+        // collecting is lenient, from_iter_exact isn't
+        assert_eq!([1.0, 2.0].into_iter().collect::<Vec3d>(), Vec3d::new(1.0, 2.0, 0.0));
+        assert_eq!([1.0, 2.0, 3.0, 4.0].into_iter().collect::<Vec3d>(), vec);
+        assert_eq!(Vec3d::from_iter_exact([1.0, 2.0]), None);
+        assert_eq!(Vec3d::from_iter_exact([1.0, 2.0, 3.0, 4.0]), None);
+        assert_eq!(Vec3d::from_iter_exact([]), None);
+    }
+
+    #[test]
+    fn deleting_subobjects() {
+        let consistent = |model: &Model| {
+            assert!(model.check_invariants().is_empty());
+            assert_eq!(model.errors, model.compute_errors());
+            assert_eq!(model.warnings, model.compute_warnings());
+            let ids = model.sub_objects.iter().map(|subobj| subobj.obj_id.0).collect::<Vec<_>>();
+            assert_eq!(ids, (0..model.sub_objects.len() as u32).collect::<Vec<_>>());
+            assert_eq!(model.header.num_subobjects as usize, model.sub_objects.len());
+        };
+        let names = |model: &Model, ids: &[ObjectId]| ids.iter().map(|&id| model.sub_objects[id].name.clone()).collect::<Vec<_>>();
+        let mut fighter = sample_fighter(0);
+        fighter.paths[0].points[0].turrets = vec![ObjectId(1), ObjectId(3)];
+        fighter.recheck_errors(Set::All);
+        fighter.recheck_warnings(Set::All);
+
+        // a turret base: its gun moves up to detail0, and the turret which used it is dropped
+        let mut model = fighter.clone();
+        let report = model.delete_subobject(ObjectId(1));
+        assert_eq!((report.deleted, report.reparented), (vec!["turret01a".to_string()], vec!["turret01b".to_string()]));
+        assert_eq!(report.lost, [LostReference::Turret(0), LostReference::PathPointTurret { path: 0, point: 0 }]);
+        consistent(&model);
+        let gun = model.get_obj_id_by_name("turret01b").unwrap();
+        assert_eq!(model.sub_objects[gun].parent, Some(ObjectId(0)));
+        assert_eq!(model.turrets.len(), 1);
+        assert_eq!(names(&model, &[model.turrets[0].base_obj, model.turrets[0].gun_obj]), ["turret02a", "turret02b"]);
+        assert_eq!(names(&model, &model.paths[0].points[0].turrets), ["turret02a"]);
+        assert_eq!(names(&model, &model.header.detail_levels), ["detail0", "detail1", "detail2"]);
+
+        // or deleted along with it
+        let mut model = fighter.clone();
+        let report = model.delete_subobject_and_children(ObjectId(1));
+        assert_eq!(report.deleted, ["turret01a", "turret01b"]);
+        assert!(report.reparented.is_empty());
+        consistent(&model);
+        assert_eq!(model.sub_objects.len(), fighter.sub_objects.len() - 2);
+
+        // detail0: the next detail level takes its place, and what was on it is left top level
+        let mut model = fighter.clone();
+        let report = model.delete_subobject(ObjectId(0));
+        assert!(report.lost.contains(&LostReference::DetailLevel(0)) && report.lost.contains(&LostReference::EyePoint(0)));
+        consistent(&model);
+        assert_eq!(names(&model, &model.header.detail_levels), ["detail1", "detail2"]);
+        assert_eq!(model.detail0_id().map(|id| model.sub_objects[id].name.clone()).as_deref(), Some("detail1"));
+        let turret = model.get_obj_id_by_name("turret01a").unwrap();
+        assert_eq!(model.sub_objects[turret].parent, None);
+        assert_eq!(model.eye_points[0].attached_subobj, None);
+
+        // the only subobject, leaving a model with none, which is an error, but still consistent
+        let mut model = sample_capital(1, 4, 0);
+        let report = model.delete_subobject(ObjectId(0));
+        assert_eq!(report.lost, [LostReference::DetailLevel(0)]);
+        consistent(&model);
+        assert!(model.sub_objects.is_empty() && model.header.detail_levels.is_empty());
+        assert!(model.errors.contains(&Error::NoDetailLevels));
+    }
+
+    #[test]
+    fn ray_intersect_matches_brute_force() {
+        let brute_force = |tree: &BspNode, origin: Vec3d, dir: Vec3d, verts: &[Vec3d]| {
+            let mut best: Option<(f32, PolygonId)> = None;
+            for (i, (_, poly)) in tree.leaves().enumerate() {
+                for pair in poly.verts[1..].windows(2) {
+                    let tri = [&poly.verts[0], &pair[0], &pair[1]].map(|vert| verts[vert.vertex_id.0 as usize]);
+                    if let Some(distance) = ray_triangle_distance(origin, dir, tri) {
+                        if best.map_or(true, |(best_distance, _)| distance < best_distance) {
+                            best = Some((distance, PolygonId(i as u32)));
+                        }
+                    }
+                }
+            }
+            best
+        };
+
+        let mut rng = crate::test_util::Rng::new(0);
+        for model in [sample_capital(3, 200, 2), sample_fighter(0)] {
+            let extent = model.header.max_radius;
+            let mut hits = 0;
+            for subobj in &model.sub_objects {
+                let (tree, verts) = (&subobj.bsp_data.collision_tree, &subobj.bsp_data.verts);
+                assert_eq!(tree.num_leaves() as usize, tree.leaves().count(), "{}", subobj.name);
+                for _ in 0..200 {
+                    // aimed somewhere near the middle, so most of them hit
+                    let origin = rng.vec3d(extent);
+                    let dir = rng.vec3d(extent / 4.0) - origin;
+                    let (fast, slow) = (tree.ray_intersect(origin, dir, verts), brute_force(tree, origin, dir, verts));
+                    // ties can go to either polygon, but never at different distances
+                    assert_eq!(fast.map(|(distance, _)| distance), slow.map(|(distance, _)| distance), "{:?} {:?}", origin, dir);
+                    // and the id is one of the polygons hit at that distance
+                    if let Some((distance, id)) = fast {
+                        let (_, poly) = tree.leaves().nth(id.0 as usize).unwrap();
+                        let single = BspNode::Leaf { bbox: Default::default(), poly: poly.clone() };
+                        assert_eq!(brute_force(&single, origin, dir, verts), Some((distance, PolygonId(0))), "{}", subobj.name);
+                        hits += 1;
+                    }
+                }
+            }
+            assert!(hits > 100, "{}", hits);
+        }
+    }
+
+    #[test]
+    fn ray_intersect_edge_cases() {
+        let fighter = sample_fighter(0);
+        let hull = &fighter.sub_objects[ObjectId(0)];
+        let (tree, verts) = (&hull.bsp_data.collision_tree, &hull.bsp_data.verts);
+        let half = hull.bbox.max;
+        let face = |id: PolygonId| {
+            let (_, poly) = tree.leaves().nth(id.0 as usize).unwrap();
+            poly.verts.iter().map(|vert| verts[vert.vertex_id.0 as usize]).collect::<Vec<_>>()
+        };
+
+        // from inside the bbox, the face ahead, along its normal, is hit from behind
+        let (distance, id) = tree.ray_intersect(Vec3d::ZERO, Vec3d::new(0.0, 0.0, 2.0), verts).unwrap();
+        assert!((distance - half.z / 2.0).abs() < 1e-5, "{}", distance);
+        assert!(face(id).iter().all(|vert| vert.z == half.z));
+        // and from outside, the near face, not the far one
+        let below = Vec3d::new(0.0, 0.0, -half.z * 3.0);
+        let (distance, id) = tree.ray_intersect(below, Vec3d::new(0.0, 0.0, 1.0), verts).unwrap();
+        assert!((distance - half.z * 2.0).abs() < 1e-4, "{}", distance);
+        assert!(face(id).iter().all(|vert| vert.z == -half.z));
+
+        // running parallel to a face, outside the box, or away from it, hits nothing
+        assert_eq!(tree.ray_intersect(Vec3d::new(0.0, half.y * 2.0, -half.z * 3.0), Vec3d::new(0.0, 0.0, 1.0), verts), None);
+        assert_eq!(tree.ray_intersect(Vec3d::new(0.0, 0.0, half.z * 2.0), Vec3d::new(0.0, 0.0, 1.0), verts), None);
+        assert_eq!(tree.ray_intersect(Vec3d::ZERO, Vec3d::ZERO, verts), None);
+
+        // a polygon with no area is never hit, even dead on
+        let verts = [Vec3d::new(-1.0, 0.0, 0.0), Vec3d::ZERO, Vec3d::new(1.0, 0.0, 0.0)];
+        let poly_verts = (0..3)
+            .map(|i| PolyVertex {
+                vertex_id: VertexId(i),
+                normal_id: NormalId(0),
+                uv: (0.0, 0.0),
+            })
+            .collect();
+        let poly = Polygon {
+            normal: Default::default(),
+            texture: TextureId(0),
+            verts: poly_verts,
+        };
+        let sliver = BspData::recalculate(&verts, [poly].into_iter());
+        assert_eq!(sliver.ray_intersect(Vec3d::new(0.0, 0.0, -1.0), Vec3d::new(0.0, 0.0, 1.0), &verts), None);
+        assert_eq!(sliver.ray_intersect(Vec3d::new(-2.0, 0.0, 0.0), Vec3d::new(1.0, 0.0, 0.0), &verts), None);
+    }
+
+    #[test]
+    fn adding_an_offset_weapon_point() {
+        let front = |fighter: &Model| Vec3d::new(0.0, 0.0, fighter.header.bbox.max.z + 5.0);
+        let normal = NormalVec3(Vec3d::new(0.0, 0.0, 1.0));
+        let versions = [
+            (Version::V21_16, true),
+            (Version::V21_18, false),
+            (Version::V22_00, true),
+            (Version::V22_01, false),
+        ];
+        for (version, warns) in versions {
+            let mut fighter = sample_fighter(0);
+            fighter.version = version;
+            fighter.recheck_warnings(Set::All);
+            let point = WeaponHardpoint::with_offset(front(&fighter), normal, 2.5);
+            let weapon = fighter.add_weapon_point(true, 0, point);
+            assert_eq!(fighter.primary_weps[0].last().unwrap().offset, 2.5);
+            assert_eq!(weapon, WeaponPointRef::new(WeaponKind::Primary, 0, fighter.primary_weps[0].len() - 1));
+            assert_eq!(fighter.warnings.contains(&Warning::WeaponOffsetInvalidVersion(weapon)), warns, "{:?}", version);
+            // the same as rechecking everything
+            assert_eq!(fighter.warnings, fighter.compute_warnings(), "{:?}", version);
+        }
+
+        // without an offset it's fine on any version
+        let mut fighter = sample_fighter(0);
+        fighter.version = Version::V21_16;
+        fighter.recheck_warnings(Set::All);
+        let weapon = fighter.add_weapon_point(false, 0, WeaponHardpoint::with_offset(front(&fighter), normal, 0.0));
+        assert!(!fighter.warnings.contains(&Warning::WeaponOffsetInvalidVersion(weapon)));
+    }
+
+    #[test]
+    fn radius_of_gyration_from_a_diagonal_moi() {
+        // moments of 4, 9 and 16 about x, y and z; the header holds the inverse
+        let mut model = Model::default();
+        model.header.mass = 4.0;
+        model.header.moment_of_inertia = Mat3d {
+            rvec: Vec3d::new(1.0 / 4.0, 0.0, 0.0),
+            uvec: Vec3d::new(0.0, 1.0 / 9.0, 0.0),
+            fvec: Vec3d::new(0.0, 0.0, 1.0 / 16.0),
+        };
+        let radius = model.radius_of_gyration();
+        assert!((radius - Vec3d::new(1.0, 1.5, 2.0)).magnitude() < 1e-5, "{:?}", radius);
+
+        // no mass, or a moment of inertia with no inverse, gives nothing rather than infinities
+        model.header.mass = 0.0;
+        assert_eq!(model.radius_of_gyration(), Vec3d::ZERO);
+        model.header.mass = f32::NAN;
+        assert_eq!(model.radius_of_gyration(), Vec3d::ZERO);
+        model.header.mass = 4.0;
+        model.header.moment_of_inertia.fvec = Vec3d::ZERO;
+        assert_eq!(model.radius_of_gyration(), Vec3d::ZERO);
+
+        // a recalculated one is inside the ship
+        let mut fighter = sample_fighter(0);
+        fighter.recalc_mass();
+        fighter.recalc_moi();
+        let radius = fighter.radius_of_gyration();
+        assert!(radius.iter().all(|val| val > 0.0 && val < fighter.header.max_radius), "{:?}", radius);
+    }
+
+    #[test]
+    fn transforms_stop_at_a_shield_too_deep() {
+        let mut fighter = sample_fighter(0);
+        let depth = fighter.shield_data.as_ref().unwrap().collision_tree.as_ref().unwrap().depth();
+        assert!(depth > 1);
+        let scale = glm::scaling(&glm::vec3(2.0, 2.0, 2.0));
+
+        // too deep, and neither the shield nor the rest of the model is touched
+        fighter.max_bsp_depth = Some(depth - 1);
+        let before = format!("{:?}", fighter);
+        assert_eq!(fighter.apply_transform(&scale), Err(BspTooDeep { max_depth: depth - 1 }));
+        let shield = fighter.shield_data.as_mut().unwrap();
+        assert_eq!(shield.apply_transform_with(&scale, depth - 1), Err(BspTooDeep { max_depth: depth - 1 }));
+        assert_eq!(format!("{:?}", fighter), before);
+
+        // right at the limit it goes through, and the root bbox follows the verts
+        fighter.max_bsp_depth = Some(depth);
+        let radius = fighter.header.max_radius;
+        fighter.apply_transform(&scale).unwrap();
+        assert!((fighter.header.max_radius - radius * 2.0).abs() < 1e-3);
+        let shield = fighter.shield_data.as_ref().unwrap();
+        let (ShieldNode::Split { bbox, .. } | ShieldNode::Leaf { bbox, .. }) = shield.collision_tree.as_ref().unwrap();
+        assert!(shield.verts.iter().all(|&vert| bbox.pad(1e-3).contains(vert)));
+    }
+}
@@ -0,0 +1,244 @@
+// how much of each texture's uv square the model's polygons actually use, to judge whether textures could be packed into an
+// atlas or scaled down
+//
+// each texture's polygons are fanned into triangles and rasterized into a resolution x resolution grid over the 0-1 square,
+// a texel being covered by a polygon if its center is inside one of the polygon's triangles; so a polygon smaller than a
+// texel may cover none at all, and the finer the grid the closer the figures are
+// only detail0 and its children are counted, since lower detail levels and debris reuse the same uv space by design, or
+// every subobject if the model has no detail levels
+// tiled uvs outside 0-1 are wrapped back into the square, as the texture repeats there, and such polygons are counted too
+// islands are groups of covered texels joined by an edge, not wrapping around the sides of the square
+use crate::{Model, Polygon, TextureId};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CoverageStats {
+    pub polygons: usize,
+    // polygons with a uv outside 0-1, which were wrapped into the square
+    pub tiled_polygons: usize,
+    // fraction of the texels covered by at least one polygon
+    pub covered: f32,
+    // fraction of the texels covered by more than one polygon, i.e. overlapping uvs, like mirrored halves sharing a texture
+    // region, which break baking
+    pub overdraw: f32,
+    pub islands: usize,
+}
+
+struct CoverageGrid {
+    resolution: usize,
+    // how many polygons cover each texel, row by row
+    counts: Vec<u32>,
+    // the last polygon to cover each texel, so a polygon whose triangles share texels covers them once
+    last_polygon: Vec<usize>,
+}
+impl CoverageGrid {
+    fn new(resolution: usize) -> Self {
+        CoverageGrid {
+            resolution,
+            counts: vec![0; resolution * resolution],
+            last_polygon: vec![usize::MAX; resolution * resolution],
+        }
+    }
+
+    fn add_polygon(&mut self, poly_index: usize, poly: &Polygon) {
+        if let [first, rest @ ..] = &*poly.verts {
+            for pair in rest.windows(2) {
+                self.add_triangle(poly_index, [first.uv, pair[0].uv, pair[1].uv]);
+            }
+        }
+    }
+
+    // by scanline, each row of texel centers crossing the triangle in one interval, wrapped into the grid
+    fn add_triangle(&mut self, poly_index: usize, uvs: [(f32, f32); 3]) {
+        let res = self.resolution as f32;
+        if uvs.iter().any(|&(u, v)| !u.is_finite() || !v.is_finite()) {
+            return;
+        }
+        let (v_min, v_max) = (uvs.iter().map(|uv| uv.1).fold(f32::INFINITY, f32::min), uvs.iter().map(|uv| uv.1).fold(f32::NEG_INFINITY, f32::max));
+        for row in (v_min * res - 0.5).ceil() as i64..=(v_max * res - 0.5).floor() as i64 {
+            let v = (row as f32 + 0.5) / res;
+            let mut crossing = (f32::INFINITY, f32::NEG_INFINITY);
+            for i in 0..3 {
+                let (a, b) = (uvs[i], uvs[(i + 1) % 3]);
+                if (a.1.min(b.1)..=a.1.max(b.1)).contains(&v) && a.1 != b.1 {
+                    let u = a.0 + (b.0 - a.0) * (v - a.1) / (b.1 - a.1);
+                    crossing = (crossing.0.min(u), crossing.1.max(u));
+                }
+            }
+            let (first_col, last_col) = ((crossing.0 * res - 0.5).ceil() as i64, (crossing.1 * res - 0.5).floor() as i64);
+            if first_col > last_col {
+                continue;
+            }
+            // a row which crosses the whole square covers all of it, however many times it wraps
+            let last_col = last_col.min(first_col + self.resolution as i64 - 1);
+            let row = row.rem_euclid(self.resolution as i64) as usize;
+            for col in first_col..=last_col {
+                let texel = row * self.resolution + col.rem_euclid(self.resolution as i64) as usize;
+                if self.last_polygon[texel] != poly_index {
+                    self.last_polygon[texel] = poly_index;
+                    self.counts[texel] += 1;
+                }
+            }
+        }
+    }
+
+    fn islands(&self) -> usize {
+        let res = self.resolution;
+        let mut seen = vec![false; res * res];
+        let mut islands = 0;
+        let mut stack = vec![];
+        for start in 0..res * res {
+            if self.counts[start] == 0 || seen[start] {
+                continue;
+            }
+            islands += 1;
+            seen[start] = true;
+            stack.push(start);
+            while let Some(texel) = stack.pop() {
+                let (row, col) = (texel / res, texel % res);
+                let neighbors = [
+                    (row > 0).then(|| texel - res),
+                    (row + 1 < res).then(|| texel + res),
+                    (col > 0).then(|| texel - 1),
+                    (col + 1 < res).then(|| texel + 1),
+                ];
+                for neighbor in neighbors.into_iter().flatten() {
+                    if self.counts[neighbor] != 0 && !seen[neighbor] {
+                        seen[neighbor] = true;
+                        stack.push(neighbor);
+                    }
+                }
+            }
+        }
+        islands
+    }
+}
+
+impl Model {
+    // every texture in the texture list, in order, with its coverage at the given resolution (e.g. 256); textures no
+    // polygon uses are all zeros; see the top of uv_coverage.rs
+    pub fn texture_uv_coverage(&self, resolution: u32) -> Vec<(TextureId, CoverageStats)> {
+        let resolution = resolution.max(1) as usize;
+        let mut grids = (0..self.textures.len()).map(|_| None).collect::<Vec<Option<CoverageGrid>>>();
+        let mut stats = vec![CoverageStats::default(); self.textures.len()];
+
+        let detail0 = self.detail0_id();
+        let subobjs = (self.sub_objects.iter()).filter(|subobj| detail0.map_or(true, |detail0| self.is_obj_id_ancestor(subobj.obj_id, detail0)));
+        let polys = subobjs.flat_map(|subobj| subobj.bsp_data.collision_tree.leaves().map(|(_, poly)| poly));
+        for (poly_index, poly) in polys.enumerate() {
+            let texture = poly.texture.0 as usize;
+            if texture >= self.textures.len() {
+                continue;
+            }
+            stats[texture].polygons += 1;
+            if (poly.verts.iter()).any(|vert| !(0.0..=1.0).contains(&vert.uv.0) || !(0.0..=1.0).contains(&vert.uv.1)) {
+                stats[texture].tiled_polygons += 1;
+            }
+            grids[texture]
+                .get_or_insert_with(|| CoverageGrid::new(resolution))
+                .add_polygon(poly_index, poly);
+        }
+
+        let num_texels = (resolution * resolution) as f32;
+        for (stats, grid) in stats.iter_mut().zip(&grids) {
+            if let Some(grid) = grid {
+                stats.covered = grid.counts.iter().filter(|&&count| count > 0).count() as f32 / num_texels;
+                stats.overdraw = grid.counts.iter().filter(|&&count| count > 1).count() as f32 / num_texels;
+                stats.islands = grid.islands();
+            }
+        }
+        stats.into_iter().enumerate().map(|(i, stats)| (TextureId(i as u32), stats)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BspData, NormalId, ObjVec, ObjectId, PolyVertex, SubObject, Vec3d, VertexId};
+
+    const RESOLUTION: u32 = 64;
+
+    // detail0, made of a quad per entry, each with its texture and uvs for its corners, in the same winding
+    fn model(quads: &[(u32, [(f32, f32); 4])]) -> Model {
+        let (mut verts, mut polygons) = (vec![], vec![]);
+        for (i, &(texture, uvs)) in quads.iter().enumerate() {
+            let first = verts.len() as u32;
+            let z = i as f32;
+            verts.extend([(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)].map(|(x, y)| Vec3d::new(x, y, z)));
+            let poly_verts = (0..4)
+                .map(|corner| PolyVertex {
+                    vertex_id: VertexId(first + corner),
+                    normal_id: NormalId(0),
+                    uv: uvs[corner as usize],
+                })
+                .collect();
+            polygons.push(Polygon {
+                normal: Default::default(),
+                texture: TextureId(texture),
+                verts: poly_verts,
+            });
+        }
+        let hull = SubObject {
+            obj_id: ObjectId(0),
+            name: "detail0".to_string(),
+            bsp_data: BspData {
+                collision_tree: BspData::recalculate(&verts, polygons.into_iter()),
+                norms: vec![Vec3d::new(0.0, 0.0, 1.0)],
+                verts,
+            },
+            ..Default::default()
+        };
+        let mut model = Model {
+            sub_objects: ObjVec(vec![hull]),
+            textures: vec!["hull".to_string(), "unused".to_string()],
+            ..Default::default()
+        };
+        model.header.detail_levels = vec![ObjectId(0)];
+        model
+    }
+
+    fn rect((u1, v1): (f32, f32), (u2, v2): (f32, f32)) -> [(f32, f32); 4] {
+        [(u1, v1), (u2, v1), (u2, v2), (u1, v2)]
+    }
+
+    #[test]
+    fn half_empty_texture() {
+        let coverage = model(&[(0, rect((0.0, 0.0), (0.5, 1.0)))]).texture_uv_coverage(RESOLUTION);
+        let expected = CoverageStats {
+            polygons: 1,
+            tiled_polygons: 0,
+            covered: 0.5,
+            overdraw: 0.0,
+            islands: 1,
+        };
+        assert_eq!(coverage, [(TextureId(0), expected), (TextureId(1), CoverageStats::default())]);
+
+        // the same, tiled one square over, is wrapped back onto it
+        let coverage = model(&[(0, rect((1.0, 0.0), (1.5, 1.0)))]).texture_uv_coverage(RESOLUTION);
+        assert_eq!(coverage[0].1, CoverageStats { tiled_polygons: 1, ..expected });
+
+        // two corners apart are two islands
+        let quads = [(0, rect((0.0, 0.0), (0.25, 0.25))), (0, rect((0.5, 0.5), (0.75, 0.75)))];
+        let coverage = model(&quads).texture_uv_coverage(RESOLUTION);
+        assert_eq!((coverage[0].1.covered, coverage[0].1.islands), (0.125, 2));
+    }
+
+    #[test]
+    fn mirrored_uvs_overlap() {
+        // the second half of the hull mirrors the first onto the same half of the texture
+        let quads = [(0, rect((0.0, 0.0), (0.5, 1.0))), (0, rect((0.5, 0.0), (0.0, 1.0)))];
+        let coverage = model(&quads).texture_uv_coverage(RESOLUTION);
+        let expected = CoverageStats {
+            polygons: 2,
+            tiled_polygons: 0,
+            covered: 0.5,
+            overdraw: 0.5,
+            islands: 1,
+        };
+        assert_eq!(coverage[0].1, expected);
+
+        // side by side instead, there's no overdraw, and one island across the whole square
+        let quads = [(0, rect((0.0, 0.0), (0.5, 1.0))), (0, rect((0.5, 0.0), (1.0, 1.0)))];
+        let coverage = model(&quads).texture_uv_coverage(RESOLUTION);
+        assert_eq!(coverage[0].1, CoverageStats { covered: 1.0, overdraw: 0.0, ..expected });
+    }
+}
@@ -0,0 +1,790 @@
+// variants of a model which share its geometry but differ in metadata, as when a mod ships one hull as several ship classes
+// with different turrets, thrusters and properties
+//
+// a ModelVariant is what one variant changes relative to the base, which Model::apply_variant lays back over the base
+// subobjects are referred to by name, so a variant still applies to a base which has been edited since, as long as the subobjects
+// it mentions are still there; lists are compared index by index, an entry which differs is replaced whole and the list is grown
+// or cut short at the end, so a variant can't be applied to a base whose list has changed length since
+// with the `serde` feature, variants can be saved as json, see ModelVariant::sidecar_path
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+
+use crate::write::Serialize;
+use crate::{
+    Dock, EyePoint, GlowPointBank, HeaderFlags, Model, ObjectId, Path, PathId, Set, SpecialPoint, TextureId, ThrusterBank, Turret, Version,
+    WeaponHardpoint,
+};
+
+#[derive(Debug, Clone)]
+pub struct ListDelta<T> {
+    // the length of the list in the base the variant was derived from, which the base must still have
+    pub base_len: usize,
+    // (index, new entry) for each entry in both which differs
+    pub modified: Vec<(usize, T)>,
+    // appended after the base's entries
+    pub added: Vec<T>,
+    // how many of the base's entries are dropped from the end
+    pub removed: usize,
+}
+impl<T> Default for ListDelta<T> {
+    fn default() -> Self {
+        Self { base_len: 0, modified: vec![], added: vec![], removed: 0 }
+    }
+}
+impl<T> ListDelta<T> {
+    pub fn is_empty(&self) -> bool {
+        self.modified.is_empty() && self.added.is_empty() && self.removed == 0
+    }
+
+    fn entries_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.modified.iter_mut().map(|(_, entry)| entry).chain(&mut self.added)
+    }
+
+    fn check(&self, list: &'static str, base: &[T]) -> Result<(), VariantError> {
+        if base.len() == self.base_len {
+            Ok(())
+        } else {
+            Err(VariantError::ListChanged { list, expected: self.base_len, found: base.len() })
+        }
+    }
+}
+impl<T: Clone> ListDelta<T> {
+    fn between(base: &[T], modified: &[T]) -> Self
+    where
+        T: Serialize,
+    {
+        ListDelta {
+            base_len: base.len(),
+            modified: (base.iter().zip(modified).enumerate())
+                .filter(|(_, (base, modified))| serialized(*base) != serialized(*modified))
+                .map(|(i, (_, modified))| (i, modified.clone()))
+                .collect(),
+            added: modified.get(base.len()..).unwrap_or_default().to_vec(),
+            removed: base.len().saturating_sub(modified.len()),
+        }
+    }
+
+    fn apply_to(&self, list: &mut Vec<T>) {
+        for (i, entry) in &self.modified {
+            list[*i] = entry.clone();
+        }
+        list.truncate(list.len() - self.removed);
+        list.extend(self.added.iter().cloned());
+    }
+}
+
+// how the value would be written to the file, for comparing things which have no PartialEq
+fn serialized(value: &impl Serialize) -> Vec<u8> {
+    let mut buf = vec![];
+    value.write_to(&mut buf).unwrap();
+    buf
+}
+
+// the subobject ids held by each kind of entry, which are ids in the variant's subobject_names until it's applied
+trait SubObjectRefs {
+    fn subobj_refs_mut(&mut self) -> Vec<&mut ObjectId> {
+        vec![]
+    }
+}
+impl SubObjectRefs for String {}
+impl SubObjectRefs for Vec<WeaponHardpoint> {}
+impl SubObjectRefs for ThrusterBank {}
+impl SubObjectRefs for Dock {}
+impl SubObjectRefs for SpecialPoint {}
+impl SubObjectRefs for Turret {
+    fn subobj_refs_mut(&mut self) -> Vec<&mut ObjectId> {
+        vec![&mut self.base_obj, &mut self.gun_obj]
+    }
+}
+impl SubObjectRefs for GlowPointBank {
+    fn subobj_refs_mut(&mut self) -> Vec<&mut ObjectId> {
+        vec![&mut self.obj_parent]
+    }
+}
+impl SubObjectRefs for EyePoint {
+    fn subobj_refs_mut(&mut self) -> Vec<&mut ObjectId> {
+        self.attached_subobj.iter_mut().collect()
+    }
+}
+impl SubObjectRefs for Path {
+    fn subobj_refs_mut(&mut self) -> Vec<&mut ObjectId> {
+        self.points.iter_mut().flat_map(|point| &mut point.turrets).collect()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ModelVariant {
+    pub name: String,
+    // the subobjects of the model the variant was derived from, by id, which the ids in the entries below refer to
+    pub subobject_names: Vec<String>,
+    // the whole bitfield, if it differs from the base's
+    pub header_flags: Option<HeaderFlags>,
+    pub textures: ListDelta<String>,
+    // new properties for subobjects, by name
+    pub properties: BTreeMap<String, String>,
+    pub paths: ListDelta<Path>,
+    // their paths are indices into the paths as the variant leaves them
+    pub docking_bays: ListDelta<Dock>,
+    pub primary_banks: ListDelta<Vec<WeaponHardpoint>>,
+    pub secondary_banks: ListDelta<Vec<WeaponHardpoint>>,
+    pub turrets: ListDelta<Turret>,
+    pub thruster_banks: ListDelta<ThrusterBank>,
+    pub glow_banks: ListDelta<GlowPointBank>,
+    pub eye_points: ListDelta<EyePoint>,
+    pub special_points: ListDelta<SpecialPoint>,
+}
+impl ModelVariant {
+    pub fn is_empty(&self) -> bool {
+        self.header_flags.is_none()
+            && self.textures.is_empty()
+            && self.properties.is_empty()
+            && self.paths.is_empty()
+            && self.docking_bays.is_empty()
+            && self.primary_banks.is_empty()
+            && self.secondary_banks.is_empty()
+            && self.turrets.is_empty()
+            && self.thruster_banks.is_empty()
+            && self.glow_banks.is_empty()
+            && self.eye_points.is_empty()
+            && self.special_points.is_empty()
+    }
+
+    // where the variant of that name is kept next to the base pof, ship.pof's "heavy" variant is ship.heavy.pofvariant
+    pub fn sidecar_path(base_path: &std::path::Path, name: &str) -> std::path::PathBuf {
+        base_path.with_extension(format!("{}.pofvariant", name))
+    }
+
+    // every subobject id in the entries, for checking them when loading
+    #[cfg(feature = "serde")]
+    fn subobj_refs_mut(&mut self) -> Vec<&mut ObjectId> {
+        let mut refs = vec![];
+        refs.extend(self.paths.entries_mut().flat_map(SubObjectRefs::subobj_refs_mut));
+        refs.extend(self.turrets.entries_mut().flat_map(SubObjectRefs::subobj_refs_mut));
+        refs.extend(self.glow_banks.entries_mut().flat_map(SubObjectRefs::subobj_refs_mut));
+        refs.extend(self.eye_points.entries_mut().flat_map(SubObjectRefs::subobj_refs_mut));
+        refs
+    }
+
+    fn resolve<T: Clone + SubObjectRefs>(&self, delta: &ListDelta<T>, base: &Model) -> Result<ListDelta<T>, VariantError> {
+        let mut delta = delta.clone();
+        for entry in delta.entries_mut() {
+            for id in entry.subobj_refs_mut() {
+                let name = &self.subobject_names[id.0 as usize];
+                *id = base
+                    .get_obj_id_by_name(name)
+                    .ok_or_else(|| VariantError::MissingSubObject(name.clone()))?;
+            }
+        }
+        Ok(delta)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariantError {
+    // deriving: variants can only differ in metadata, so both models need the same subobjects, in the same order
+    SubObjectsDiffer,
+    GeometryDiffers(String),
+    ShieldDiffers,
+    InsigniasDiffer,
+    // applying: the base has changed since the variant was derived
+    MissingSubObject(String),
+    ListChanged { list: &'static str, expected: usize, found: usize },
+}
+impl Display for VariantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VariantError::SubObjectsDiffer => write!(f, "the models don't have the same subobjects"),
+            VariantError::GeometryDiffers(name) => write!(f, "the geometry of {} differs", name),
+            VariantError::ShieldDiffers => write!(f, "the shields differ"),
+            VariantError::InsigniasDiffer => write!(f, "the insignias differ"),
+            VariantError::MissingSubObject(name) => write!(f, "the base has no subobject named {}", name),
+            VariantError::ListChanged { list, expected, found } => {
+                write!(f, "the variant expects {} {} in the base, but it has {}", expected, list, found)
+            }
+        }
+    }
+}
+
+// what makes modified a variant of base, named after modified's file
+pub fn derive_variant(base: &Model, modified: &Model) -> Result<ModelVariant, VariantError> {
+    if base.sub_objects.len() != modified.sub_objects.len() || base.header.detail_levels != modified.header.detail_levels {
+        return Err(VariantError::SubObjectsDiffer);
+    }
+    for (subobj1, subobj2) in base.sub_objects.iter().zip(modified.sub_objects.iter()) {
+        if subobj1.name != subobj2.name || subobj1.parent != subobj2.parent {
+            return Err(VariantError::SubObjectsDiffer);
+        }
+        if subobj1.offset != subobj2.offset || subobj1.geometry_fingerprint() != subobj2.geometry_fingerprint() {
+            return Err(VariantError::GeometryDiffers(subobj1.name.clone()));
+        }
+    }
+    let shield = |model: &Model| {
+        model
+            .shield_data
+            .as_ref()
+            .map(|shield| (serialized(&shield.verts), serialized(&shield.polygons)))
+    };
+    if shield(base) != shield(modified) {
+        return Err(VariantError::ShieldDiffers);
+    }
+    if serialized(&base.insignias) != serialized(&modified.insignias) {
+        return Err(VariantError::InsigniasDiffer);
+    }
+
+    // compare everything as the latest version writes it, so no field is skipped
+    crate::VERSION.with(|f| f.set(Version::LATEST));
+    Ok(ModelVariant {
+        name: modified
+            .path_to_file
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+        subobject_names: modified.sub_objects.iter().map(|subobj| subobj.name.clone()).collect(),
+        header_flags: (base.header.obj_flags != modified.header.obj_flags).then(|| modified.header.flags()),
+        textures: ListDelta::between(&base.textures, &modified.textures),
+        properties: (base.sub_objects.iter().zip(modified.sub_objects.iter()))
+            .filter(|(subobj1, subobj2)| subobj1.properties != subobj2.properties)
+            .map(|(_, subobj)| (subobj.name.clone(), subobj.properties.clone()))
+            .collect(),
+        paths: ListDelta::between(&base.paths, &modified.paths),
+        docking_bays: ListDelta::between(&base.docking_bays, &modified.docking_bays),
+        primary_banks: ListDelta::between(&base.primary_weps, &modified.primary_weps),
+        secondary_banks: ListDelta::between(&base.secondary_weps, &modified.secondary_weps),
+        turrets: ListDelta::between(&base.turrets, &modified.turrets),
+        thruster_banks: ListDelta::between(&base.thruster_banks, &modified.thruster_banks),
+        glow_banks: ListDelta::between(&base.glow_banks, &modified.glow_banks),
+        eye_points: ListDelta::between(&base.eye_points, &modified.eye_points),
+        special_points: ListDelta::between(&base.special_points, &modified.special_points),
+    })
+}
+
+impl Model {
+    // a copy of the model with the variant laid over it, the model itself is left alone
+    pub fn apply_variant(&self, variant: &ModelVariant) -> Result<Model, VariantError> {
+        // check everything first, the lists the variant doesn't touch can have changed freely
+        fn check<T>(delta: &ListDelta<T>, list: &'static str, base: &[T]) -> Result<(), VariantError> {
+            if delta.is_empty() {
+                Ok(())
+            } else {
+                delta.check(list, base)
+            }
+        }
+        check(&variant.textures, "textures", &self.textures)?;
+        check(&variant.paths, "paths", &self.paths)?;
+        check(&variant.docking_bays, "docking bays", &self.docking_bays)?;
+        check(&variant.primary_banks, "primary banks", &self.primary_weps)?;
+        check(&variant.secondary_banks, "secondary banks", &self.secondary_weps)?;
+        check(&variant.turrets, "turrets", &self.turrets)?;
+        check(&variant.thruster_banks, "thruster banks", &self.thruster_banks)?;
+        check(&variant.glow_banks, "glow banks", &self.glow_banks)?;
+        check(&variant.eye_points, "eye points", &self.eye_points)?;
+        check(&variant.special_points, "special points", &self.special_points)?;
+        let properties = (variant.properties.iter())
+            .map(|(name, properties)| {
+                Ok((
+                    self.get_obj_id_by_name(name)
+                        .ok_or_else(|| VariantError::MissingSubObject(name.clone()))?,
+                    properties,
+                ))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let paths = variant.resolve(&variant.paths, self)?;
+        let turrets = variant.resolve(&variant.turrets, self)?;
+        let glow_banks = variant.resolve(&variant.glow_banks, self)?;
+        let eye_points = variant.resolve(&variant.eye_points, self)?;
+
+        let mut model = self.clone();
+        if let Some(flags) = variant.header_flags {
+            model.header.set_flags(flags);
+        }
+        let textures = &variant.textures;
+        for (i, name) in &textures.modified {
+            model.rename_texture(TextureId(*i as u32), name.clone());
+        }
+        for _ in 0..textures.removed {
+            model.remove_texture(TextureId(model.textures.len() as u32 - 1));
+        }
+        model.textures.extend(textures.added.iter().cloned());
+
+        for (id, properties) in properties {
+            model.sub_objects[id].properties = properties.clone();
+        }
+
+        // through add_path and remove_path, so docking bays the variant leaves alone don't dangle
+        for (i, path) in &paths.modified {
+            model.paths[*i] = path.clone();
+        }
+        for _ in 0..paths.removed {
+            model.remove_path(PathId(model.paths.len() as u32 - 1));
+        }
+        for path in &paths.added {
+            model.add_path(path.clone());
+        }
+
+        variant.docking_bays.apply_to(&mut model.docking_bays);
+        variant.primary_banks.apply_to(&mut model.primary_weps);
+        variant.secondary_banks.apply_to(&mut model.secondary_weps);
+        turrets.apply_to(&mut model.turrets);
+        variant.thruster_banks.apply_to(&mut model.thruster_banks);
+        glow_banks.apply_to(&mut model.glow_banks);
+        eye_points.apply_to(&mut model.eye_points);
+        variant.special_points.apply_to(&mut model.special_points);
+
+        model.refresh_untextured_slot();
+        model.recalc_semantic_name_links();
+        model.recheck_warnings(Set::All);
+        model.recheck_errors(Set::All);
+        Ok(model)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod json {
+    use serde_json::{json, Value};
+
+    use super::{ListDelta, ModelVariant};
+    use crate::{
+        Dock, EyePoint, GlowPoint, GlowPointBank, HeaderFlags, NormalVec3, ObjectId, Path, PathId, PathPoint, SpecialPoint, ThrusterBank,
+        ThrusterGlow, Turret, Vec3d, WeaponHardpoint,
+    };
+
+    fn vec_to_json(v: Vec3d) -> Value {
+        json!([v.x, v.y, v.z])
+    }
+
+    fn vec_from_json(value: &Value) -> Option<Vec3d> {
+        match value.as_array()?.as_slice() {
+            [x, y, z] => Some(Vec3d::new(x.as_f64()? as f32, y.as_f64()? as f32, z.as_f64()? as f32)),
+            _ => None,
+        }
+    }
+
+    fn f32_from_json(value: &Value) -> Option<f32> {
+        Some(value.as_f64()? as f32)
+    }
+
+    fn u32_from_json(value: &Value) -> Option<u32> {
+        value.as_u64()?.try_into().ok()
+    }
+
+    fn string_from_json(value: &Value) -> Option<String> {
+        Some(value.as_str()?.to_string())
+    }
+
+    fn list_from_json<T>(value: &Value, from_json: impl Fn(&Value) -> Option<T>) -> Option<Vec<T>> {
+        value.as_array()?.iter().map(from_json).collect()
+    }
+
+    // the known flags by name, so the file says what they are, and any other bits as a number
+    fn flags_to_json(flags: HeaderFlags) -> Value {
+        json!({ "names": flags.names(), "unknown_bits": flags.unknown_bits() })
+    }
+
+    fn flags_from_json(value: &Value) -> Option<HeaderFlags> {
+        let mut flags = HeaderFlags(u32_from_json(value.get("unknown_bits")?)?);
+        for name in value.get("names")?.as_array()? {
+            let &(flag, _) = HeaderFlags::KNOWN.iter().find(|(_, known)| name.as_str() == Some(*known))?;
+            flags = flags | flag;
+        }
+        Some(flags)
+    }
+
+    // subobject ids are written as is, they index the variant's subobject_names
+    trait Json: Sized {
+        fn to_json(&self) -> Value;
+        fn from_json(value: &Value) -> Option<Self>;
+    }
+
+    impl Json for String {
+        fn to_json(&self) -> Value {
+            json!(self)
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            string_from_json(value)
+        }
+    }
+
+    impl Json for Vec<WeaponHardpoint> {
+        fn to_json(&self) -> Value {
+            (self.iter())
+                .map(|point| json!({ "position": vec_to_json(point.position), "normal": vec_to_json(point.normal.0), "offset": point.offset }))
+                .collect()
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            list_from_json(value, |point| {
+                Some(WeaponHardpoint {
+                    position: vec_from_json(point.get("position")?)?,
+                    normal: NormalVec3(vec_from_json(point.get("normal")?)?),
+                    offset: f32_from_json(point.get("offset")?)?,
+                })
+            })
+        }
+    }
+
+    impl Json for Turret {
+        fn to_json(&self) -> Value {
+            json!({
+                "base": self.base_obj.0,
+                "gun": self.gun_obj.0,
+                "normal": vec_to_json(self.normal.0),
+                "fire_points": self.fire_points.iter().map(|&point| vec_to_json(point)).collect::<Vec<_>>(),
+            })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            Some(Turret {
+                base_obj: ObjectId(u32_from_json(value.get("base")?)?),
+                gun_obj: ObjectId(u32_from_json(value.get("gun")?)?),
+                normal: NormalVec3(vec_from_json(value.get("normal")?)?),
+                fire_points: list_from_json(value.get("fire_points")?, vec_from_json)?,
+            })
+        }
+    }
+
+    impl Json for ThrusterBank {
+        fn to_json(&self) -> Value {
+            json!({
+                "properties": self.properties,
+                "glows": self.glows.iter().map(|glow| json!({
+                    "position": vec_to_json(glow.position),
+                    "normal": vec_to_json(glow.normal.0),
+                    "radius": glow.radius,
+                })).collect::<Vec<_>>(),
+            })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            Some(ThrusterBank {
+                properties: string_from_json(value.get("properties")?)?,
+                glows: list_from_json(value.get("glows")?, |glow| {
+                    Some(ThrusterGlow {
+                        position: vec_from_json(glow.get("position")?)?,
+                        normal: NormalVec3(vec_from_json(glow.get("normal")?)?),
+                        radius: f32_from_json(glow.get("radius")?)?,
+                    })
+                })?,
+            })
+        }
+    }
+
+    impl Json for GlowPointBank {
+        fn to_json(&self) -> Value {
+            json!({
+                "parent": self.obj_parent.0,
+                "lod": self.lod,
+                "type": self.glow_type,
+                "disp_time": self.disp_time,
+                "on_time": self.on_time,
+                "off_time": self.off_time,
+                "properties": self.properties,
+                "points": self.glow_points.iter().map(|point| json!({
+                    "position": vec_to_json(point.position),
+                    "normal": vec_to_json(point.normal),
+                    "radius": point.radius,
+                })).collect::<Vec<_>>(),
+            })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            Some(GlowPointBank {
+                obj_parent: ObjectId(u32_from_json(value.get("parent")?)?),
+                lod: u32_from_json(value.get("lod")?)?,
+                glow_type: u32_from_json(value.get("type")?)?,
+                disp_time: value.get("disp_time")?.as_i64()?.try_into().ok()?,
+                on_time: u32_from_json(value.get("on_time")?)?,
+                off_time: u32_from_json(value.get("off_time")?)?,
+                properties: string_from_json(value.get("properties")?)?,
+                glow_points: list_from_json(value.get("points")?, |point| {
+                    Some(GlowPoint {
+                        position: vec_from_json(point.get("position")?)?,
+                        normal: vec_from_json(point.get("normal")?)?,
+                        radius: f32_from_json(point.get("radius")?)?,
+                    })
+                })?,
+            })
+        }
+    }
+
+    impl Json for EyePoint {
+        fn to_json(&self) -> Value {
+            json!({
+                "attached": self.attached_subobj.map(|id| id.0),
+                "position": vec_to_json(self.position),
+                "normal": vec_to_json(self.normal.0),
+            })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            let attached_subobj = match value.get("attached")? {
+                Value::Null => None,
+                id => Some(ObjectId(u32_from_json(id)?)),
+            };
+            Some(EyePoint {
+                attached_subobj,
+                position: vec_from_json(value.get("position")?)?,
+                normal: NormalVec3(vec_from_json(value.get("normal")?)?),
+            })
+        }
+    }
+
+    impl Json for SpecialPoint {
+        fn to_json(&self) -> Value {
+            json!({ "name": self.name, "properties": self.properties, "position": vec_to_json(self.position), "radius": self.radius })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            Some(SpecialPoint {
+                name: string_from_json(value.get("name")?)?,
+                properties: string_from_json(value.get("properties")?)?,
+                position: vec_from_json(value.get("position")?)?,
+                radius: f32_from_json(value.get("radius")?)?,
+            })
+        }
+    }
+
+    impl Json for Path {
+        fn to_json(&self) -> Value {
+            json!({
+                "name": self.name,
+                "parent": self.parent,
+                "points": self.points.iter().map(|point| json!({
+                    "position": vec_to_json(point.position),
+                    "radius": point.radius,
+                    "turrets": point.turrets.iter().map(|id| id.0).collect::<Vec<_>>(),
+                })).collect::<Vec<_>>(),
+            })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            Some(Path {
+                name: string_from_json(value.get("name")?)?,
+                parent: string_from_json(value.get("parent")?)?,
+                points: list_from_json(value.get("points")?, |point| {
+                    Some(PathPoint {
+                        position: vec_from_json(point.get("position")?)?,
+                        radius: f32_from_json(point.get("radius")?)?,
+                        turrets: list_from_json(point.get("turrets")?, |id| Some(ObjectId(u32_from_json(id)?)))?,
+                    })
+                })?,
+            })
+        }
+    }
+
+    impl Json for Dock {
+        fn to_json(&self) -> Value {
+            json!({
+                "properties": self.properties,
+                "path": self.path.map(|id| id.0),
+                "position": vec_to_json(self.position),
+                "fvec": vec_to_json(self.fvec.0),
+                "uvec": vec_to_json(self.uvec.0),
+            })
+        }
+        fn from_json(value: &Value) -> Option<Self> {
+            let path = match value.get("path")? {
+                Value::Null => None,
+                id => Some(PathId(u32_from_json(id)?)),
+            };
+            Some(Dock {
+                properties: string_from_json(value.get("properties")?)?,
+                path,
+                position: vec_from_json(value.get("position")?)?,
+                fvec: NormalVec3(vec_from_json(value.get("fvec")?)?),
+                uvec: NormalVec3(vec_from_json(value.get("uvec")?)?),
+            })
+        }
+    }
+
+    impl<T: Json> Json for ListDelta<T> {
+        fn to_json(&self) -> Value {
+            json!({
+                "base_len": self.base_len,
+                "modified": self.modified.iter().map(|(i, entry)| json!([i, entry.to_json()])).collect::<Vec<_>>(),
+                "added": self.added.iter().map(T::to_json).collect::<Vec<_>>(),
+                "removed": self.removed,
+            })
+        }
+
+        fn from_json(value: &Value) -> Option<Self> {
+            let delta = ListDelta {
+                base_len: value.get("base_len")?.as_u64()? as usize,
+                modified: list_from_json(value.get("modified")?, |pair| match pair.as_array()?.as_slice() {
+                    [i, entry] => Some((i.as_u64()? as usize, T::from_json(entry)?)),
+                    _ => None,
+                })?,
+                added: list_from_json(value.get("added")?, T::from_json)?,
+                removed: value.get("removed")?.as_u64()? as usize,
+            };
+            // so apply_variant can index with them
+            let in_range = delta.removed <= delta.base_len && delta.modified.iter().all(|&(i, _)| i < delta.base_len - delta.removed);
+            in_range.then_some(delta)
+        }
+    }
+
+    impl ModelVariant {
+        pub fn to_json(&self) -> Value {
+            json!({
+                "name": self.name,
+                "subobject_names": self.subobject_names,
+                "header_flags": self.header_flags.map_or(Value::Null, flags_to_json),
+                "textures": self.textures.to_json(),
+                "properties": self.properties,
+                "paths": self.paths.to_json(),
+                "docking_bays": self.docking_bays.to_json(),
+                "primary_banks": self.primary_banks.to_json(),
+                "secondary_banks": self.secondary_banks.to_json(),
+                "turrets": self.turrets.to_json(),
+                "thruster_banks": self.thruster_banks.to_json(),
+                "glow_banks": self.glow_banks.to_json(),
+                "eye_points": self.eye_points.to_json(),
+                "special_points": self.special_points.to_json(),
+            })
+        }
+
+        // None if it isn't a variant written by to_json
+        pub fn from_json(value: &Value) -> Option<ModelVariant> {
+            let variant = ModelVariant {
+                name: string_from_json(value.get("name")?)?,
+                subobject_names: list_from_json(value.get("subobject_names")?, string_from_json)?,
+                // missing from variants saved before the flags were
+                header_flags: match value.get("header_flags") {
+                    None | Some(Value::Null) => None,
+                    Some(flags) => Some(flags_from_json(flags)?),
+                },
+                textures: ListDelta::from_json(value.get("textures")?)?,
+                properties: (value.get("properties")?.as_object()?.iter())
+                    .map(|(name, properties)| Some((name.clone(), string_from_json(properties)?)))
+                    .collect::<Option<_>>()?,
+                paths: ListDelta::from_json(value.get("paths")?)?,
+                docking_bays: ListDelta::from_json(value.get("docking_bays")?)?,
+                primary_banks: ListDelta::from_json(value.get("primary_banks")?)?,
+                secondary_banks: ListDelta::from_json(value.get("secondary_banks")?)?,
+                turrets: ListDelta::from_json(value.get("turrets")?)?,
+                thruster_banks: ListDelta::from_json(value.get("thruster_banks")?)?,
+                glow_banks: ListDelta::from_json(value.get("glow_banks")?)?,
+                eye_points: ListDelta::from_json(value.get("eye_points")?)?,
+                special_points: ListDelta::from_json(value.get("special_points")?)?,
+            };
+
+            // every subobject id has to name one
+            let mut variant = variant;
+            let num_names = variant.subobject_names.len();
+            let valid = variant.subobj_refs_mut().iter().all(|id| (id.0 as usize) < num_names);
+            valid.then_some(variant)
+        }
+
+        pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+            std::fs::write(path, serde_json::to_string_pretty(&self.to_json())?)
+        }
+
+        // InvalidData if the file isn't a variant
+        pub fn load(path: &std::path::Path) -> std::io::Result<ModelVariant> {
+            let value: Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+            Self::from_json(&value).ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "not a model variant"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::Vec3d;
+
+    // the fighter as a heavier class: a turret fewer, an extra primary bank, a renamed texture and new properties
+    fn heavy_variant(base: &Model) -> Model {
+        let mut heavy = base.clone();
+        heavy.path_to_file = "ships/fighter_heavy.pof".into();
+        heavy.turrets.pop();
+        let mut bank = heavy.primary_weps[0].clone();
+        for point in &mut bank {
+            point.position.y += 0.5;
+        }
+        heavy.primary_weps.push(bank);
+        heavy.rename_texture(TextureId(1), "heavy-fittings".to_string());
+        heavy.sub_objects[ObjectId(1)].properties = "$special=subsystem\n$name=Heavy Turret".to_string();
+        heavy.eye_points[0].position.z += 1.0;
+        heavy.thruster_banks[0].properties = "$engine_subsystem=engine".to_string();
+        heavy.header.set_flags(HeaderFlags(0x100) | HeaderFlags::ALLOW_TILING);
+        heavy.recheck_warnings(Set::One(crate::Warning::UnknownHeaderFlags));
+        heavy
+    }
+
+    #[test]
+    fn derive_then_apply() {
+        let base = sample_fighter(0);
+        let heavy = heavy_variant(&base);
+        let variant = derive_variant(&base, &heavy).unwrap();
+        assert_eq!(variant.name, "fighter_heavy");
+        assert_eq!((variant.turrets.removed, variant.primary_banks.added.len()), (1, 1));
+        assert_eq!(variant.textures.modified, [(1, "heavy-fittings".to_string())]);
+        assert_eq!(variant.properties.keys().collect::<Vec<_>>(), ["turret01a"]);
+        assert_eq!(variant.header_flags, Some(HeaderFlags(0x101)));
+        assert!(variant.paths.is_empty() && variant.docking_bays.is_empty() && variant.special_points.is_empty());
+
+        let applied = base.apply_variant(&variant).unwrap();
+        assert_eq!(applied.write_to_vec().unwrap(), heavy.write_to_vec().unwrap());
+        assert_eq!(applied.warnings, heavy.warnings);
+        assert!(derive_variant(&base, &base).unwrap().is_empty());
+    }
+
+    #[test]
+    fn only_metadata_can_differ() {
+        let base = sample_fighter(0);
+        let mut moved = base.clone();
+        moved.sub_objects[ObjectId(0)].bsp_data.verts[0].x += 1.0;
+        assert_eq!(derive_variant(&base, &moved).unwrap_err(), VariantError::GeometryDiffers("detail0".to_string()));
+        let mut moved = base.clone();
+        moved.sub_objects[ObjectId(7)].offset = Vec3d::new(1.0, 2.0, 3.0);
+        assert_eq!(derive_variant(&base, &moved).unwrap_err(), VariantError::GeometryDiffers("debris01".to_string()));
+        let mut renamed = base.clone();
+        renamed.sub_objects[ObjectId(7)].name = "debris99".to_string();
+        assert_eq!(derive_variant(&base, &renamed).unwrap_err(), VariantError::SubObjectsDiffer);
+        let mut unshielded = base.clone();
+        unshielded.shield_data = None;
+        assert_eq!(derive_variant(&base, &unshielded).unwrap_err(), VariantError::ShieldDiffers);
+    }
+
+    #[test]
+    fn conflicts_with_an_edited_base() {
+        let base = sample_fighter(0);
+        let mut heavy = base.clone();
+        heavy.turrets[0].fire_points.push(Vec3d::new(0.0, 0.0, 1.0));
+        heavy.eye_points[0].position.z += 1.0;
+        let variant = derive_variant(&base, &heavy).unwrap();
+
+        // the turret's base subobject is gone under its old name
+        let mut edited = base.clone();
+        edited.sub_objects[ObjectId(1)].name = "turret01".to_string();
+        assert_eq!(edited.apply_variant(&variant).unwrap_err(), VariantError::MissingSubObject("turret01a".to_string()));
+
+        let mut edited = base.clone();
+        edited.eye_points.push(edited.eye_points[0].clone());
+        let expected = VariantError::ListChanged { list: "eye points", expected: 1, found: 2 };
+        assert_eq!(edited.apply_variant(&variant).unwrap_err(), expected);
+
+        // a list the variant leaves alone can change freely
+        let mut edited = base.clone();
+        edited.special_points.clear();
+        let applied = edited.apply_variant(&variant).unwrap();
+        assert!(applied.special_points.is_empty());
+        assert_eq!(applied.turrets[0].fire_points.len(), 3);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn sidecar_round_trip() {
+        let base = sample_fighter(0);
+        let heavy = heavy_variant(&base);
+        let variant = derive_variant(&base, &heavy).unwrap();
+        let sidecar = ModelVariant::sidecar_path(std::path::Path::new("ships/fighter.pof"), "heavy");
+        assert_eq!(sidecar, std::path::Path::new("ships/fighter.heavy.pofvariant"));
+
+        let json = serde_json::to_string(&variant.to_json()).unwrap();
+        let loaded = ModelVariant::from_json(&serde_json::from_str(&json).unwrap()).unwrap();
+        let applied = base.apply_variant(&loaded).unwrap();
+        assert_eq!(applied.write_to_vec().unwrap(), heavy.write_to_vec().unwrap());
+
+        assert_eq!(variant.to_json()["header_flags"], serde_json::json!({ "names": ["allow tiling"], "unknown_bits": 0x100 }));
+
+        let mut broken = variant.to_json();
+        broken["turrets"]["removed"] = serde_json::json!(5);
+        assert!(ModelVariant::from_json(&broken).is_none());
+        let mut broken = variant.to_json();
+        broken["header_flags"]["names"] = serde_json::json!(["warp drive"]);
+        assert!(ModelVariant::from_json(&broken).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+// merging coincident vertices in imported meshes, so seams the exporter split are closed up
+//
+// the pof format keeps normals and uvs on the polygon corners rather than the vertices, so welding only ever merges positions,
+// but two vertices are only merged if some corner at each agrees on the normal and the uv, so a uv seam, or a window pane
+// modeled as separate geometry sitting exactly on the hull, stays split unless the uv tolerance is loosened to allow it
+// what's merged into a vertex is compared against that vertex's own position, so welds don't chain further than the tolerance
+use std::collections::HashMap;
+
+use crate::{NormalId, PolyVertex, TextureId, Vec3d, VertexId};
+
+// how far apart normals can be and still count as the same, for the weld
+const NORMAL_TOLERANCE: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum WeldMode {
+    // every vertex is kept as the exporter wrote it
+    #[default]
+    None,
+    // vertices at most `position` apart are merged, as long as their uvs are at most `uv` apart in each coordinate
+    Tolerance {
+        position: f32,
+        uv: f32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct WeldPolicy {
+    // for objects none of the overrides match
+    pub default: WeldMode,
+    // (node name pattern, mode), the first match wins
+    // patterns ignore case, and can use * for any run of characters and ? for any one
+    pub overrides: Vec<(String, WeldMode)>,
+}
+impl WeldPolicy {
+    pub fn mode_for(&self, name: &str) -> WeldMode {
+        (self.overrides.iter())
+            .find(|(pattern, _)| glob_matches(pattern, name))
+            .map_or(self.default, |&(_, mode)| mode)
+    }
+}
+
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let name = name.to_lowercase().chars().collect::<Vec<_>>();
+    // the usual backtracking to the last star
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn cell_of(vert: Vec3d, cell_size: f32) -> [i64; 3] {
+    [vert.x, vert.y, vert.z].map(|val| (val / cell_size).floor() as i64)
+}
+
+// welds the vertices of one object as the mode says, dropping any polygon corners and polygons this collapses
+// returns how many vertices were merged away
+pub(crate) fn weld_mesh(mode: WeldMode, verts: &mut Vec<Vec3d>, norms: &[Vec3d], polys: &mut Vec<(TextureId, Vec<PolyVertex>)>) -> usize {
+    let WeldMode::Tolerance { position: tolerance, uv: uv_tolerance } = mode else {
+        return 0;
+    };
+
+    let mut corners: Vec<Vec<(NormalId, (f32, f32))>> = vec![vec![]; verts.len()];
+    for (_, poly) in polys.iter() {
+        for vert in poly {
+            corners[vert.vertex_id.0 as usize].push((vert.normal_id, vert.uv));
+        }
+    }
+    let compatible = |a: usize, b: usize| {
+        // a vertex no polygon uses has nothing to disagree about
+        corners[a].is_empty()
+            || corners[b].is_empty()
+            || corners[a].iter().any(|&(normal1, uv1)| {
+                corners[b].iter().any(|&(normal2, uv2)| {
+                    (norms[normal1.0 as usize] - norms[normal2.0 as usize]).magnitude() <= NORMAL_TOLERANCE
+                        && (uv1.0 - uv2.0).abs() <= uv_tolerance
+                        && (uv1.1 - uv2.1).abs() <= uv_tolerance
+                })
+            })
+    };
+
+    let cell_size = tolerance.max(1e-5);
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut new_verts = vec![];
+    let mut new_ids = vec![];
+    for (i, &vert) in verts.iter().enumerate() {
+        let [x, y, z] = cell_of(vert, cell_size);
+        let mut neighbors = (x - 1..=x + 1).flat_map(|x| (y - 1..=y + 1).flat_map(move |y| (z - 1..=z + 1).map(move |z| [x, y, z])));
+        let existing =
+            neighbors.find_map(|cell| (cells.get(&cell)?.iter()).find(|&&rep| (verts[rep] - vert).magnitude() <= tolerance && compatible(rep, i)));
+        match existing {
+            Some(&rep) => new_ids.push(new_ids[rep]),
+            None => {
+                cells.entry([x, y, z]).or_default().push(i);
+                new_ids.push(VertexId(new_verts.len() as u32));
+                new_verts.push(vert);
+            }
+        }
+    }
+
+    let welded = verts.len() - new_verts.len();
+    if welded > 0 {
+        *verts = new_verts;
+        for (_, poly) in polys.iter_mut() {
+            for vert in poly.iter_mut() {
+                vert.vertex_id = new_ids[vert.vertex_id.0 as usize];
+            }
+            // an edge shorter than the tolerance collapses
+            poly.dedup_by_key(|vert| vert.vertex_id);
+            while poly.len() > 1 && poly.first().map(|vert| vert.vertex_id) == poly.last().map(|vert| vert.vertex_id) {
+                poly.pop();
+            }
+        }
+        polys.retain(|(_, poly)| poly.len() >= 3);
+    }
+    welded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Polys = Vec<(TextureId, Vec<PolyVertex>)>;
+
+    fn quad(first: u32, normal: u32, uvs: [(f32, f32); 4]) -> (TextureId, Vec<PolyVertex>) {
+        let verts = (0..4)
+            .map(|i| PolyVertex {
+                vertex_id: VertexId(first + i),
+                normal_id: NormalId(normal),
+                uv: uvs[i as usize],
+            })
+            .collect();
+        (TextureId(0), verts)
+    }
+
+    // a hull of two quads side by side, split along the seam between them with matching uvs, as exporters like to, and a
+    // window pane sitting exactly on the left one with its own uvs, facing the other way if window_normal is 1
+    fn fixture(window_normal: u32) -> (Vec<Vec3d>, Vec<Vec3d>, Polys) {
+        let square = |x: f32| [(x, 0.0), (x + 1.0, 0.0), (x + 1.0, 1.0), (x, 1.0)];
+        let verts = [square(0.0), square(1.0), square(0.0)]
+            .iter()
+            .flatten()
+            .map(|&(x, y)| Vec3d::new(x, y, 0.0))
+            .collect();
+        let norms = vec![Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(0.0, 0.0, -1.0)];
+        let hull_uvs = |x: f32| square(x).map(|(x, y)| (x / 2.0, y));
+        let polys = vec![
+            quad(0, 0, hull_uvs(0.0)),
+            quad(4, 0, hull_uvs(1.0)),
+            quad(8, window_normal, [(0.75, 0.5); 4]),
+        ];
+        (verts, norms, polys)
+    }
+
+    fn weld(mode: WeldMode, window_normal: u32) -> (usize, Vec<Vec3d>, Polys) {
+        let (mut verts, norms, mut polys) = fixture(window_normal);
+        let welded = weld_mesh(mode, &mut verts, &norms, &mut polys);
+        (welded, verts, polys)
+    }
+
+    #[test]
+    fn seams_close_and_panes_stay_separate() {
+        let strict = WeldMode::Tolerance { position: 0.001, uv: 0.0 };
+        let (welded, verts, polys) = weld(strict, 0);
+        assert_eq!((welded, verts.len(), polys.len()), (2, 10, 3));
+        // the right quad now shares the seam with the left one, and the pane shares nothing
+        let ids = |poly: &Vec<PolyVertex>| poly.iter().map(|vert| vert.vertex_id.0).collect::<Vec<_>>();
+        assert_eq!(ids(&polys[1].1), [1, 4, 5, 2]);
+        assert_eq!(ids(&polys[2].1), [6, 7, 8, 9]);
+
+        // with the uvs allowed to differ, the pane is welded onto the hull too, but only if it faces the same way
+        let loose = WeldMode::Tolerance { position: 0.001, uv: 1.0 };
+        assert_eq!(weld(loose, 0).0, 6);
+        assert_eq!(weld(loose, 1).0, 2);
+
+        assert_eq!(weld(WeldMode::None, 0).0, 0);
+        assert_eq!(weld(WeldMode::None, 0).1, fixture(0).0);
+    }
+
+    #[test]
+    fn collapsed_polygons_are_dropped() {
+        let mut verts = vec![
+            Vec3d::new(0.0, 0.0, 0.0),
+            Vec3d::new(0.0005, 0.0, 0.0),
+            Vec3d::new(0.0, 1.0, 0.0),
+            Vec3d::new(1.0, 1.0, 0.0),
+        ];
+        let corner = |i: u32| PolyVertex {
+            vertex_id: VertexId(i),
+            normal_id: NormalId(0),
+            uv: (0.0, 0.0),
+        };
+        let mut polys = vec![
+            (TextureId(0), vec![corner(0), corner(1), corner(2)]),
+            (TextureId(0), vec![corner(1), corner(3), corner(2)]),
+        ];
+        let welded = weld_mesh(WeldMode::Tolerance { position: 0.001, uv: 0.0 }, &mut verts, &[Vec3d::new(0.0, 0.0, 1.0)], &mut polys);
+        assert_eq!((welded, verts.len()), (1, 3));
+        // the sliver lost a corner, and the other polygon now starts at the welded one
+        assert_eq!(polys.len(), 1);
+        assert_eq!(polys[0].1.iter().map(|vert| vert.vertex_id.0).collect::<Vec<_>>(), [0, 2, 1]);
+    }
+
+    #[test]
+    fn per_object_overrides() {
+        let policy = WeldPolicy {
+            default: WeldMode::Tolerance { position: 0.01, uv: 0.0 },
+            overrides: vec![("window*".to_string(), WeldMode::None), ("*-pane?".to_string(), WeldMode::None)],
+        };
+        assert_eq!(policy.mode_for("hull"), policy.default);
+        assert_eq!(policy.mode_for("Window01"), WeldMode::None);
+        assert_eq!(policy.mode_for("bridge-pane2"), WeldMode::None);
+        assert_eq!(policy.mode_for("bridge-pane12"), policy.default);
+
+        assert!(glob_matches("*", "") && glob_matches("a*b*c", "aXXbYYc") && glob_matches("a?c", "ABC"));
+        assert!(!glob_matches("a*b", "acb-") && !glob_matches("?", "") && !glob_matches("abc", "ab"));
+    }
+}
@@ -1,6 +1,8 @@
 use std::{
     borrow::Cow,
+    collections::{HashMap, HashSet},
     f32::consts::PI,
+    hash::Hash,
     io::{self, Write},
     mem::size_of,
 };
@@ -15,11 +17,39 @@ use json::validation::Checked::Valid;
 use json::Index;
 extern crate nalgebra_glm as glm;
 
+use crate::attachments::AttachmentPoint;
+use crate::tool_metadata::{write_tool_metadata, TOOL_METADATA_CHUNK};
 use crate::{
-    BoundingBox, BspData, BspNode, Dock, EyePoint, GlowPointBank, Insignia, Model, ObjVec, ObjectId, Path, ShieldData, ShieldNode, SpecialPoint,
-    SubObject, ThrusterBank, Turret, Vec3d, Version, WeaponHardpoint,
+    BoundingBox, BspData, BspNode, DaeShading, Dock, EyePoint, GlowPointBank, Insignia, Model, ObjVec, ObjectId, Path, ShieldData, ShieldNode,
+    SpecialPoint, SubObject, TextureId, ThrusterBank, Triangulation, Turret, Vec3d, Version, VertexId, WeaponHardpoint,
 };
 
+#[derive(Debug, Clone, Default)]
+pub struct WriteOptions {
+    // adds a snapshot of the model's statistics to its stat history, see history.rs
+    // the model itself is not changed, the snapshot only exists in what is written
+    pub record_stat_history: bool,
+    // also writes the model's attachments as special points, see attachments.rs
+    // they're written to their own chunk either way
+    pub attachments_as_special_points: bool,
+    // writes the model even if it has errors which block writing, see Error::blocks_write
+    pub allow_errors: bool,
+}
+
+// why Model::write_with refused to write a model, carried as the inner error of the io::Error it returns, so a caller can
+// get it back with `err.get_ref().and_then(|err| err.downcast_ref::<WriteRefusal>())`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteRefusal {
+    // every error the model has which blocks writing, see Error::blocks_write
+    pub blocking: Vec<crate::Error>,
+}
+impl std::fmt::Display for WriteRefusal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "the model has errors which block writing: {:?}", self.blocking)
+    }
+}
+impl std::error::Error for WriteRefusal {}
+
 pub(crate) trait Serialize {
     fn write_to(&self, w: &mut impl Write) -> io::Result<()>;
 }
@@ -184,7 +214,7 @@ pub(crate) fn write_bsp_data(buf: &mut Vec<u8>, version: Version, bsp_data: &Bsp
 
     fn write_bsp_node(buf: &mut Vec<u8>, verts: &[Vec3d], version: Version, bsp_node: &BspNode) -> io::Result<()> {
         match bsp_node {
-            BspNode::Split { bbox, front, back } => {
+            BspNode::Split { bbox, front, back, .. } => {
                 let base = buf.len();
                 if version >= Version::V23_00 {
                     buf.write_u32::<LE>(BspData::SORTNORM2)?;
@@ -375,7 +405,32 @@ fn write_subobjects(w: &mut impl Write, chunk_name: &[u8; 4], objects: &[SubObje
 }
 
 impl Model {
+    // chunks are always written in the same order, which some older tools depend on (see inspect::CHUNK_ORDER):
+    // the header, then textures, then subobjects in ascending id order, then everything else in a fixed sequence
+    // the one exception is that a subobject's parent is always written before it, so a parent with a higher id than
+    // its child is moved up to just before that child
     pub fn write(&self, w: &mut impl Write) -> io::Result<()> {
+        self.write_with(w, &WriteOptions::default())
+    }
+
+    // the pof in memory, e.g. to pack into a vp archive, see Model::from_bytes for reading one back
+    pub fn write_to_vec(&self) -> io::Result<Vec<u8>> {
+        let mut buf = vec![];
+        self.write(&mut buf)?;
+        Ok(buf)
+    }
+
+    pub fn write_with(&self, w: &mut impl Write, options: &WriteOptions) -> io::Result<()> {
+        if self.geometry_skipped {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "the model was loaded without its geometry, which would be lost"));
+        }
+        if !options.allow_errors {
+            // computed afresh rather than trusting self.errors, which might not have been rechecked since the last edit
+            let blocking = self.compute_errors().into_iter().filter(|err| err.blocks_write()).collect::<Vec<_>>();
+            if !blocking.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, WriteRefusal { blocking }));
+            }
+        }
         // set the version to be using be all the serializers
         crate::VERSION.with(|f| {
             f.set(self.version);
@@ -422,10 +477,16 @@ impl Model {
             }
             Ok(())
         })?;
-        write_subobjects(w, if self.version >= Version::V21_16 { b"OBJ2" } else { b"SOBJ" }, &self.sub_objects)?;
         write_chunk_vec(w, b"TXTR", &self.textures)?;
+        write_subobjects(w, if self.version >= Version::V21_16 { b"OBJ2" } else { b"SOBJ" }, &self.sub_objects)?;
         write_chunk_vec(w, b"PATH", &self.paths)?;
-        write_chunk_vec(w, b"SPCL", &self.special_points)?;
+        if options.attachments_as_special_points && !self.attachments.is_empty() {
+            let mut special_points = self.special_points.clone();
+            special_points.extend(self.attachments_as_special_points());
+            write_chunk_vec(w, b"SPCL", &special_points)?;
+        } else {
+            write_chunk_vec(w, b"SPCL", &self.special_points)?;
+        }
         write_chunk_vec(w, b"EYE ", &self.eye_points)?;
         if !self.primary_weps.is_empty() {
             write_chunk(w, b"GPNT", Some(&self.primary_weps))?;
@@ -461,11 +522,61 @@ impl Model {
         if self.visual_center != Vec3d::default() {
             write_chunk(w, b"ACEN", Some(&self.visual_center))?;
         }
+        // existing history is kept whether or not a new snapshot is recorded
+        let history = if options.record_stat_history {
+            self.stat_history_with_snapshot()
+        } else {
+            self.stat_history.clone()
+        };
+        if let Some(metadata) = write_tool_metadata(self, &history)? {
+            write_chunk_raw(w, TOOL_METADATA_CHUNK, |w| w.write_all(&metadata))?;
+        }
 
         Ok(())
     }
 }
 
+impl BspNode {
+    // writes the bounding boxes of the tree as OBJ line geometry, one box per node, down to max_depth (0 being just the root)
+    pub fn export_bbox_wireframe(&self, w: &mut impl Write, max_depth: u32) -> io::Result<()> {
+        // OBJ indices are 1-based, and count up across the whole file
+        fn write_node(w: &mut impl Write, node: &BspNode, depth: u32, max_depth: u32, num_verts: &mut u32) -> io::Result<()> {
+            if depth > max_depth {
+                return Ok(());
+            }
+            let bbox = match node {
+                BspNode::Split { bbox, .. } | BspNode::Leaf { bbox, .. } => bbox,
+                BspNode::Empty => return Ok(()),
+            };
+
+            writeln!(w, "g depth{}", depth)?;
+            for i in 0..8 {
+                let x = if i & 1 == 0 { bbox.min.x } else { bbox.max.x };
+                let y = if i & 2 == 0 { bbox.min.y } else { bbox.max.y };
+                let z = if i & 4 == 0 { bbox.min.z } else { bbox.max.z };
+                writeln!(w, "v {} {} {}", x, y, z)?;
+            }
+            // each edge connects two corners which differ in exactly one bit
+            for i in 0..8 {
+                for bit in [1, 2, 4] {
+                    if i & bit == 0 {
+                        writeln!(w, "l {} {}", *num_verts + i + 1, *num_verts + (i | bit) + 1)?;
+                    }
+                }
+            }
+            *num_verts += 8;
+
+            if let BspNode::Split { front, back, .. } = node {
+                write_node(w, front, depth + 1, max_depth, num_verts)?;
+                write_node(w, back, depth + 1, max_depth, num_verts)?;
+            }
+            Ok(())
+        }
+
+        write_node(w, self, 0, max_depth, &mut 0)
+    }
+}
+
 // ==============================================================================
 // DAE / glTF Writing
 // ==============================================================================
@@ -756,6 +867,32 @@ fn make_eyes_node<N: Node>(ctx: &mut N::Ctx, eye_points: &[EyePoint], up: UpAxis
     node.build(ctx)
 }
 
+fn make_attachments_node<N: Node>(ctx: &mut N::Ctx, attachments: &[AttachmentPoint], up: UpAxis) -> N {
+    let mut node = N::from_id(format!("#attachments"));
+
+    for (i, attachment) in attachments.iter().enumerate() {
+        let mut point_node = N::from_name(format!("#a{}", i), format!("#a{}:{}", i, attachment.name));
+
+        // the same frame as docking bays, fvec along local y and uvec along local z
+        let fvec: Vec3 = attachment.fvec.0.to_coord(up).into();
+        let uvec = attachment.uvec.0.to_coord(up).into();
+        let mat = nalgebra::Matrix::from_columns(&[fvec.cross(&uvec), fvec, uvec]);
+        let mut mat: Mat4x4 = glm::mat3_to_mat4(&mat);
+        mat.append_translation_mut(&attachment.position.to_coord(up).into());
+        point_node.matrix_transform(mat);
+
+        if let Some(id) = attachment.parent {
+            point_node
+                .children()
+                .push(N::from_name(format!("#a{}-parent", i), format!("#a{}-parent:{}", i, id.0)).build(ctx));
+        }
+
+        node.children().push(point_node.build(ctx));
+    }
+
+    node.build(ctx)
+}
+
 fn make_visual_center_node<N: Node>(ctx: &mut N::Ctx, visual_center: &Vec3d, up: UpAxis) -> N {
     let mut node = N::from_id(format!("#visual-center"));
 
@@ -879,8 +1016,71 @@ fn make_shield_node(shield: &ShieldData, geometries: &mut Vec<Geometry>, up: UpA
     node
 }
 
+// gives the corners of each polygon, in leaf order, their own position index wherever they're separated from
+// the other corners at the same vertex by sharp edges, returning the vertex each position index is a copy of
+fn split_at_sharp_edges(bsp_data: &BspData, angle_tolerance_deg: f32) -> (Vec<VertexId>, Vec<Vec<u32>>) {
+    let sharp = bsp_data.infer_sharp_edges(angle_tolerance_deg).into_iter().collect::<HashSet<_>>();
+    let polys = bsp_data.collision_tree.leaves().map(|(_, poly)| poly).collect::<Vec<_>>();
+    let mut corner_starts = vec![];
+    let mut num_corners = 0;
+    for poly in &polys {
+        corner_starts.push(num_corners);
+        num_corners += poly.verts.len();
+    }
+
+    // union find over the corners, joining corners at the same vertex across smooth edges
+    let mut roots = (0..num_corners).collect::<Vec<_>>();
+    fn find(roots: &mut [usize], mut corner: usize) -> usize {
+        while roots[corner] != corner {
+            roots[corner] = roots[roots[corner]];
+            corner = roots[corner];
+        }
+        corner
+    }
+    let mut edges: HashMap<(VertexId, VertexId), Vec<(usize, usize)>> = HashMap::new();
+    for (i, poly) in polys.iter().enumerate() {
+        for j in 0..poly.verts.len() {
+            let k = (j + 1) % poly.verts.len();
+            let (a, b) = (poly.verts[j].vertex_id, poly.verts[k].vertex_id);
+            let (edge, corners) = if a <= b { ((a, b), (j, k)) } else { ((b, a), (k, j)) };
+            if !sharp.contains(&edge) {
+                edges
+                    .entry(edge)
+                    .or_default()
+                    .push((corner_starts[i] + corners.0, corner_starts[i] + corners.1));
+            }
+        }
+    }
+    for sides in edges.values() {
+        for pair in sides.windows(2) {
+            for (a, b) in [(pair[0].0, pair[1].0), (pair[0].1, pair[1].1)] {
+                let (a, b) = (find(&mut roots, a), find(&mut roots, b));
+                roots[a] = b;
+            }
+        }
+    }
+
+    let mut position_of_root = HashMap::new();
+    let mut positions = vec![];
+    let indices = (polys.iter().enumerate())
+        .map(|(i, poly)| {
+            (poly.verts.iter().enumerate())
+                .map(|(j, vert)| {
+                    let root = find(&mut roots, corner_starts[i] + j);
+                    *position_of_root.entry(root).or_insert_with(|| {
+                        positions.push(vert.vertex_id);
+                        positions.len() as u32 - 1
+                    })
+                })
+                .collect()
+        })
+        .collect();
+    (positions, indices)
+}
+
 fn make_subobj_node(
     up: UpAxis, subobjs: &ObjVec<SubObject>, subobj: &SubObject, turrets: &[Turret], geometries: &mut Vec<Geometry>, materials: &[String],
+    shading: DaeShading,
 ) -> DaeNode {
     let geo_id = format!("{}-geometry", subobj.name);
     let pos_id = format!("{}-geometry-position", subobj.name);
@@ -891,9 +1091,19 @@ fn make_subobj_node(
     let uv_id = format!("{}-geometry-uv", subobj.name);
     let uv_array_id = format!("{}-array", uv_id);
 
+    // the position each polygon corner uses, by default just its vertex
+    let (position_verts, corner_positions) = match shading {
+        DaeShading::ExplicitNormals => {
+            let corners = (subobj.bsp_data.collision_tree.leaves())
+                .map(|(_, poly)| poly.verts.iter().map(|vert| vert.vertex_id.0).collect())
+                .collect::<Vec<Vec<u32>>>();
+            ((0..subobj.bsp_data.verts.len() as u32).map(VertexId).collect(), corners)
+        }
+        DaeShading::SplitSharpEdges(angle_tolerance_deg) => split_at_sharp_edges(&subobj.bsp_data, angle_tolerance_deg),
+    };
     let mut positions = vec![];
-    for vert in &subobj.bsp_data.verts {
-        positions.extend_from_slice(&<[_; 3]>::from(vert.to_coord(up)));
+    for vert in position_verts {
+        positions.extend_from_slice(&<[_; 3]>::from(subobj.bsp_data.verts[vert.0 as usize].to_coord(up)));
     }
 
     let mut normals = vec![];
@@ -905,11 +1115,11 @@ fn make_subobj_node(
     let mut uv_len = 0;
     let mut prim_elems = vec![(vec![], vec![]); materials.len() + 1];
 
-    for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
+    for ((_, poly), corner_positions) in subobj.bsp_data.collision_tree.leaves().zip(corner_positions) {
         let (vert_count, indices) = &mut prim_elems[poly.texture.0 as usize + 1];
         vert_count.push(poly.verts.len() as u32);
-        for vert in poly.verts.iter().rev() {
-            indices.push(vert.vertex_id.0 as _);
+        for (vert, position) in poly.verts.iter().zip(corner_positions).rev() {
+            indices.push(position as _);
             indices.push(vert.normal_id.0 as _);
             indices.push(uv_len);
             uv_len += 1;
@@ -1012,7 +1222,7 @@ fn make_subobj_node(
         subobj
             .children
             .iter()
-            .map(|&id| make_subobj_node(up, subobjs, &subobjs[id], turrets, geometries, materials)),
+            .map(|&id| make_subobj_node(up, subobjs, &subobjs[id], turrets, geometries, materials, shading)),
     );
 
     node
@@ -1020,6 +1230,10 @@ fn make_subobj_node(
 
 impl Model {
     pub fn write_dae(&self, w: &mut impl Write) -> Result<(), dae_parser::Error> {
+        self.write_dae_with(w, DaeShading::ExplicitNormals)
+    }
+
+    pub fn write_dae_with(&self, w: &mut impl Write, shading: DaeShading) -> Result<(), dae_parser::Error> {
         let mut geometries = vec![];
 
         let mut nodes = vec![];
@@ -1029,7 +1243,7 @@ impl Model {
 
         for subobj in &self.sub_objects {
             if subobj.parent.is_none() {
-                let mut top_level_node = make_subobj_node(up, &self.sub_objects, subobj, &self.turrets, &mut geometries, &materials);
+                let mut top_level_node = make_subobj_node(up, &self.sub_objects, subobj, &self.turrets, &mut geometries, &materials, shading);
 
                 for (i, insignia) in self.insignias.iter().enumerate() {
                     if self.get_sobj_detail_level(subobj.obj_id) == Some(insignia.detail_level) {
@@ -1077,6 +1291,10 @@ impl Model {
             nodes.push(make_eyes_node(&mut (), &self.eye_points, up));
         }
 
+        if !self.attachments.is_empty() {
+            nodes.push(make_attachments_node(&mut (), &self.attachments, up));
+        }
+
         if !self.visual_center.is_null() {
             nodes.push(make_visual_center_node(&mut (), &self.visual_center, up));
         }
@@ -1114,6 +1332,61 @@ impl Model {
 struct GltfBuilder {
     root: json::Root,
     buffer: Vec<u8>,
+    triangulation: Triangulation,
+    // if set, all geometry uses this material instead of its texture's
+    material_override: Option<usize>,
+    // prepended to subobject node names
+    name_prefix: String,
+}
+
+// greedily walks the triangles across shared edges to make strips, then stitches those together with degenerate triangles
+// winding is preserved, every triangle in the strip faces the same way as it did in the input
+fn triangle_strip<K: Copy + Eq + Hash>(tris: &[[K; 3]]) -> Vec<K> {
+    let mut edges: HashMap<(K, K), Vec<usize>> = HashMap::new();
+    for (i, &[a, b, c]) in tris.iter().enumerate() {
+        for edge in [(a, b), (b, c), (c, a)] {
+            edges.entry(edge).or_default().push(i);
+        }
+    }
+
+    let mut used = vec![false; tris.len()];
+    let mut out = vec![];
+    for start in 0..tris.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+        let mut strip = tris[start].to_vec();
+
+        // odd triangles in a strip are wound backwards
+        let mut odd = true;
+        loop {
+            let (p, q) = (strip[strip.len() - 2], strip[strip.len() - 1]);
+            let edge = if odd { (q, p) } else { (p, q) };
+            let next = edges.get(&edge).and_then(|tri_ids| tri_ids.iter().copied().find(|&i| !used[i]));
+            if let Some(i) = next {
+                used[i] = true;
+                let tri = tris[i];
+                let j = (0..3).find(|&j| tri[j] == edge.0 && tri[(j + 1) % 3] == edge.1).unwrap();
+                strip.push(tri[(j + 2) % 3]);
+                odd = !odd;
+            } else {
+                break;
+            }
+        }
+
+        if let Some(&last) = out.last() {
+            // the next strip has to start on an even triangle to keep its winding
+            let pad = out.len() % 2 == 1;
+            out.push(last);
+            out.push(strip[0]);
+            if pad {
+                out.push(strip[0]);
+            }
+        }
+        out.extend(strip);
+    }
+    out
 }
 
 fn sanitize_f32(f: f32) -> f32 {
@@ -1328,7 +1601,7 @@ impl GltfBuilder {
         let mut prim_elems = vec![vec![]; materials];
         for (_, poly) in subobj.bsp_data.collision_tree.leaves() {
             if let [vert1, rest @ ..] = &*poly.verts {
-                let tris = &mut prim_elems[poly.texture.0 as usize];
+                let tris = &mut prim_elems[self.material_override.unwrap_or(poly.texture.0 as usize)];
                 for verts in rest.windows(2) {
                     // intentional swizzle
                     tris.push([vert1, &verts[1], &verts[0]]);
@@ -1341,11 +1614,25 @@ impl GltfBuilder {
             .enumerate()
             .filter(|(_, vcount)| !vcount.is_empty())
             .map(|(material, prim_elem)| {
+                let (verts, mode) = match self.triangulation {
+                    Triangulation::Fan => (prim_elem.into_iter().flatten().collect::<Vec<_>>(), json::mesh::Mode::Triangles),
+                    Triangulation::Strip => {
+                        // only vertices which are identical in every attribute can be shared
+                        let keys = prim_elem
+                            .iter()
+                            .map(|tri| tri.map(|vert| (vert.vertex_id, vert.normal_id, vert.uv.0.to_bits(), vert.uv.1.to_bits())))
+                            .collect::<Vec<_>>();
+                        let verts = (keys.iter().flatten().copied())
+                            .zip(prim_elem.iter().flatten().copied())
+                            .collect::<HashMap<_, _>>();
+                        (triangle_strip(&keys).iter().map(|key| verts[key]).collect(), json::mesh::Mode::TriangleStrip)
+                    }
+                };
                 let start = self.buffer.len();
-                let count = 3 * prim_elem.len();
+                let count = verts.len();
                 let view = self.push_buffer_view(start, size_of::<(Vec3d, Vec3d, [f32; 2])>(), false, count, Target::ArrayBuffer);
                 let mut bbox_pos = BoundingBox::EMPTY;
-                for vert in prim_elem.into_iter().flatten() {
+                for vert in verts {
                     let position = subobj.bsp_data.verts[vert.vertex_id.0 as usize].to_coord(up);
                     let normal = subobj.bsp_data.norms[vert.normal_id.0 as usize].to_coord(up);
                     bbox_pos.expand_vec(position);
@@ -1371,7 +1658,7 @@ impl GltfBuilder {
                     extras: Default::default(),
                     indices: None,
                     material: Some(Index::new(material as _)),
-                    mode: Valid(json::mesh::Mode::Triangles),
+                    mode: Valid(mode),
                     targets: None,
                 }
             })
@@ -1379,7 +1666,7 @@ impl GltfBuilder {
 
         let geo_id = self.push_mesh(primitives);
 
-        let mut node = NodeIndex::from_id(subobj.name.clone());
+        let mut node = NodeIndex::from_id(format!("{}{}", self.name_prefix, subobj.name));
         node.translate(subobj.offset.to_coord(up).into());
 
         // kind of expensive to do per subobj?
@@ -1428,7 +1715,7 @@ impl GltfBuilder {
 
     pub fn build_gltf(&mut self, model: &Model) {
         let up = UpAxis::YUp;
-        self.root.materials.extend(model.textures.iter().map(|tex| {
+        self.root.materials.extend(model.textures.iter().enumerate().map(|(i, tex)| {
             let image = json::Image {
                 uri: Some(tex.into()),
                 buffer_view: Default::default(),
@@ -1455,6 +1742,10 @@ impl GltfBuilder {
                     }),
                     ..Default::default()
                 },
+                extras: model
+                    .texture_annotations
+                    .get(&TextureId(i as u32))
+                    .map(|annotation| json::extras::RawValue::from_string(annotation.to_json().to_string()).expect("a json value is valid json")),
                 ..Default::default()
             }
         }));
@@ -1511,10 +1802,18 @@ impl GltfBuilder {
             nodes.push(make_eyes_node(&mut self.root.nodes, &model.eye_points, up));
         }
 
+        if !model.attachments.is_empty() {
+            nodes.push(make_attachments_node(&mut self.root.nodes, &model.attachments, up));
+        }
+
         if !model.visual_center.is_null() {
             nodes.push(make_visual_center_node(&mut self.root.nodes, &model.visual_center, up));
         }
 
+        self.push_scene(nodes);
+    }
+
+    fn push_scene(&mut self, nodes: Vec<NodeIndex>) {
         self.root.scene = Some(GltfBuilder::push(
             &mut self.root.scenes,
             json::Scene {
@@ -1537,30 +1836,338 @@ impl GltfBuilder {
 
 impl Model {
     pub fn write_gltf(&self, writer: impl Write, binary: bool) -> Result<(), gltf::Error> {
-        let mut builder = GltfBuilder::default();
+        self.write_gltf_with(writer, binary, Triangulation::Fan)
+    }
+
+    pub fn write_gltf_with(&self, writer: impl Write, binary: bool, triangulation: Triangulation) -> Result<(), gltf::Error> {
+        let mut builder = GltfBuilder { triangulation, ..Default::default() };
         builder.build_gltf(self);
+        builder.write(writer, binary)
+    }
+}
+
+impl GltfBuilder {
+    fn write(mut self, writer: impl Write, binary: bool) -> Result<(), gltf::Error> {
         if binary {
-            let json_string = json::serialize::to_string(&builder.root)?;
+            let json_string = json::serialize::to_string(&self.root)?;
             let mut json_offset = json_string.len() as u32;
             json_offset += json_offset.wrapping_neg() % 4;
-            align_buf(&mut builder.buffer).unwrap();
+            align_buf(&mut self.buffer).unwrap();
             let glb = gltf::binary::Glb {
                 header: gltf::binary::Header {
                     magic: *b"glTF",
                     version: 2,
-                    length: json_offset + builder.root.buffers[0].byte_length.0 as u32,
+                    length: json_offset + self.root.buffers[0].byte_length.0 as u32,
                 },
-                bin: Some(Cow::Borrowed(&builder.buffer)),
+                bin: Some(Cow::Borrowed(&self.buffer)),
                 json: Cow::Owned(json_string.into_bytes()),
             };
             glb.to_writer(writer)
         } else {
-            builder.root.buffers[0].uri = Some(format!(
-                "data:application/octet-stream;base64,{}",
-                base64::display::Base64Display::with_config(&builder.buffer, base64::STANDARD)
-            ));
-            json::serialize::to_writer_pretty(writer, &builder.root)?;
+            self.root.buffers[0].uri =
+                Some(format!("data:application/octet-stream;base64,{}", base64::display::Base64Display::with_config(&self.buffer, base64::STANDARD)));
+            json::serialize::to_writer_pretty(writer, &self.root)?;
             Ok(())
         }
     }
+
+    // puts both models' detail0 hierarchies in one scene, under "old" and "new" nodes
+    // subobject nodes are named "old/<name>" and "new/<name>" so a viewer can pair them up
+    fn build_comparison(&mut self, old: &Model, new: &Model) {
+        let materials = [
+            ("old", [1.0, 0.3, 0.3, 0.35], json::material::AlphaMode::Blend),
+            ("new", [0.8, 0.8, 0.8, 1.0], json::material::AlphaMode::Opaque),
+        ];
+        self.root
+            .materials
+            .extend(materials.into_iter().map(|(name, color, alpha_mode)| json::Material {
+                name: Some(name.into()),
+                alpha_mode: Valid(alpha_mode),
+                pbr_metallic_roughness: json::material::PbrMetallicRoughness {
+                    base_color_factor: json::material::PbrBaseColorFactor(color),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }));
+
+        let mut nodes = vec![];
+        for (i, (name, model)) in [("old", old), ("new", new)].into_iter().enumerate() {
+            let mut node = NodeIndex::from_id(name.into());
+            if let Some(detail0) = model.detail0() {
+                self.material_override = Some(i);
+                self.name_prefix = format!("{}/", name);
+                let subobj_node = self.make_subobj_node(&model.sub_objects, detail0, &model.turrets, materials.len());
+                let subobj_node = subobj_node.build(&mut self.root.nodes);
+                node.children().push(subobj_node);
+            }
+            nodes.push(node.build(&mut self.root.nodes));
+        }
+        self.material_override = None;
+        self.name_prefix.clear();
+
+        self.push_scene(nodes);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SubObjectComparison {
+    pub name: String,
+    // (old, new) for each of these
+    pub num_verts: (usize, usize),
+    pub num_polys: (usize, usize),
+    pub bbox: (BoundingBox, BoundingBox),
+    pub same_geometry: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ComparisonSummary {
+    // subobjects in both detail0 hierarchies, matched by name
+    pub pairs: Vec<SubObjectComparison>,
+    pub only_in_old: Vec<String>,
+    pub only_in_new: Vec<String>,
+}
+
+// writes a single gltf (or glb, going by the extension) with the detail0 hierarchies of both models superimposed,
+// with the old one in a translucent material, and summarizes the differences between subobjects of the same name
+pub fn export_comparison_gltf(old: &Model, new: &Model, path: &std::path::Path) -> io::Result<ComparisonSummary> {
+    fn detail0_subobjs(model: &Model) -> Vec<&SubObject> {
+        let mut subobjs = vec![];
+        if let Some(detail0) = model.detail0_id() {
+            subobjs.extend(model.sub_objects.iter().filter(|subobj| model.is_obj_id_ancestor(subobj.obj_id, detail0)));
+        }
+        subobjs
+    }
+
+    let old_subobjs = detail0_subobjs(old);
+    let new_subobjs = detail0_subobjs(new);
+
+    let mut summary = ComparisonSummary::default();
+    for old_subobj in &old_subobjs {
+        if let Some(new_subobj) = new_subobjs.iter().find(|subobj| subobj.name == old_subobj.name) {
+            summary.pairs.push(SubObjectComparison {
+                name: old_subobj.name.clone(),
+                num_verts: (old_subobj.bsp_data.verts.len(), new_subobj.bsp_data.verts.len()),
+                num_polys: (old_subobj.bsp_data.collision_tree.leaves().count(), new_subobj.bsp_data.collision_tree.leaves().count()),
+                bbox: (old_subobj.bbox, new_subobj.bbox),
+                same_geometry: old_subobj.geometry_fingerprint() == new_subobj.geometry_fingerprint(),
+            });
+        } else {
+            summary.only_in_old.push(old_subobj.name.clone());
+        }
+    }
+    for new_subobj in &new_subobjs {
+        if !old_subobjs.iter().any(|subobj| subobj.name == new_subobj.name) {
+            summary.only_in_new.push(new_subobj.name.clone());
+        }
+    }
+
+    let mut builder = GltfBuilder::default();
+    builder.build_comparison(old, new);
+    let binary = matches!(path.extension(), Some(ext) if ext.eq_ignore_ascii_case("glb"));
+    let file = std::fs::File::create(path)?;
+    builder
+        .write(io::BufWriter::new(file), binary)
+        .map_err(|err| io::Error::new(io::ErrorKind::Other, err.to_string()))?;
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::samples::sample_fighter;
+    use crate::{Error, NormalId, PolyVertex, Polygon, Set};
+
+    #[test]
+    fn bbox_wireframe_has_a_box_per_node() {
+        fn count_boxes(node: &BspNode, depth: u32, max_depth: u32) -> usize {
+            match node {
+                _ if depth > max_depth => 0,
+                BspNode::Split { front, back, .. } => 1 + count_boxes(front, depth + 1, max_depth) + count_boxes(back, depth + 1, max_depth),
+                BspNode::Leaf { .. } => 1,
+                BspNode::Empty => 0,
+            }
+        }
+
+        let fighter = sample_fighter(0);
+        let tree = &fighter.sub_objects[ObjectId(0)].bsp_data.collision_tree;
+        for max_depth in [0, 1, 3, 100] {
+            let mut buf = vec![];
+            tree.export_bbox_wireframe(&mut buf, max_depth).unwrap();
+            let text = String::from_utf8(buf).unwrap();
+            let boxes = count_boxes(tree, 0, max_depth);
+            assert_eq!(text.lines().filter(|line| line.starts_with("l ")).count(), boxes * 12, "depth {}", max_depth);
+            assert_eq!(text.lines().filter(|line| line.starts_with("v ")).count(), boxes * 8, "depth {}", max_depth);
+        }
+
+        // just the root's box, its last edge along x at max y and z
+        let mut buf = vec![];
+        tree.export_bbox_wireframe(&mut buf, 0).unwrap();
+        assert_eq!(String::from_utf8(buf).unwrap().lines().last(), Some("l 7 8"));
+    }
+
+    // the triangles a strip draws, each rotated to start at its smallest vertex, without the degenerate ones
+    fn strip_triangles(strip: &[u32]) -> HashSet<[u32; 3]> {
+        (strip.windows(3).enumerate())
+            .map(|(i, tri)| {
+                if i % 2 == 0 {
+                    [tri[0], tri[1], tri[2]]
+                } else {
+                    [tri[1], tri[0], tri[2]]
+                }
+            })
+            .filter(|&[a, b, c]| a != b && b != c && c != a)
+            .map(|tri| {
+                let first = (0..3).min_by_key(|&i| tri[i]).unwrap();
+                [tri[first], tri[(first + 1) % 3], tri[(first + 2) % 3]]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn strips_are_smaller_than_fans_on_a_grid() {
+        let (cols, rows) = (12, 5);
+        let vert = |col: u32, row: u32| row * (cols + 1) + col;
+        let mut tris = vec![];
+        for row in 0..rows {
+            for col in 0..cols {
+                tris.push([vert(col, row), vert(col + 1, row), vert(col + 1, row + 1)]);
+                tris.push([vert(col, row), vert(col + 1, row + 1), vert(col, row + 1)]);
+            }
+        }
+
+        let strip = triangle_strip(&tris);
+        assert!(strip.len() < tris.len() * 3, "{} vertices as a strip", strip.len());
+        let expected = tris
+            .iter()
+            .map(|&tri| strip_triangles(&tri).into_iter().next().unwrap())
+            .collect::<HashSet<_>>();
+        assert_eq!(strip_triangles(&strip), expected);
+    }
+
+    #[test]
+    fn comparison_pairs_subobjects_by_name() {
+        let old = sample_fighter(0);
+        let mut new = sample_fighter(0);
+        for vert in &mut new.sub_objects[ObjectId(2)].bsp_data.verts {
+            *vert *= 2.0;
+        }
+        new.sub_objects[ObjectId(2)].recalc_bbox();
+
+        let path = std::env::temp_dir().join(format!("pof-comparison-{}.gltf", std::process::id()));
+        let summary = export_comparison_gltf(&old, &new, &path).unwrap();
+        let root = json::Root::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let detail0 = ["detail0", "turret01a", "turret01b", "turret02a", "turret02b"];
+        let pairs = summary.pairs.iter().map(|pair| pair.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(pairs, detail0);
+        assert!(summary.only_in_old.is_empty() && summary.only_in_new.is_empty());
+        for pair in &summary.pairs {
+            assert_eq!(pair.same_geometry, pair.name != "turret01b", "{}", pair.name);
+            assert_eq!(pair.num_verts.0, pair.num_verts.1);
+        }
+
+        let names = root.nodes.iter().filter_map(|node| node.name.as_deref()).collect::<Vec<_>>();
+        for side in ["old", "new"] {
+            assert!(names.contains(&side));
+            let mut subobjs = (names.iter())
+                .filter_map(|name| name.strip_prefix(side)?.strip_prefix('/'))
+                .collect::<Vec<_>>();
+            subobjs.sort_unstable();
+            assert_eq!(subobjs, detail0, "{}", side);
+        }
+    }
+
+    // an n-sided cylinder along z, with smooth sides and hard caps: the bottom ring is vertices 0..n and the top n..2n
+    fn cylinder(sides: u32) -> BspData {
+        let angle = |i: u32| (i % sides) as f32 * 2.0 * PI / sides as f32;
+        let verts = (0..2 * sides)
+            .map(|i| Vec3d::new(angle(i).cos(), angle(i).sin(), if i < sides { -1.0 } else { 1.0 }))
+            .collect::<Vec<_>>();
+        let mut norms = (0..sides).map(|i| Vec3d::new(angle(i).cos(), angle(i).sin(), 0.0)).collect::<Vec<_>>();
+        norms.extend([Vec3d::new(0.0, 0.0, -1.0), Vec3d::new(0.0, 0.0, 1.0)]);
+
+        let corner = |vertex: u32, normal: u32| PolyVertex {
+            vertex_id: VertexId(vertex),
+            normal_id: NormalId(normal),
+            uv: (0.0, 0.0),
+        };
+        let mut polys = (0..sides)
+            .map(|i| {
+                let j = (i + 1) % sides;
+                let verts = vec![corner(i, i), corner(j, j), corner(sides + j, j), corner(sides + i, i)];
+                Polygon {
+                    normal: (norms[i as usize] + norms[j as usize]).normalize(),
+                    texture: TextureId(0),
+                    verts,
+                }
+            })
+            .collect::<Vec<_>>();
+        for (cap, normal) in [(0, sides), (sides, sides + 1)] {
+            let verts = (0..sides).map(|i| corner(cap + i, normal)).collect();
+            polys.push(Polygon { normal: norms[normal as usize], texture: TextureId(0), verts });
+        }
+
+        let collision_tree = BspData::recalculate(&verts, polys.into_iter());
+        BspData { verts, norms, collision_tree }
+    }
+
+    #[test]
+    fn cylinder_with_hard_caps() {
+        let cylinder = cylinder(8);
+
+        // only the rims are sharp, the sides meet with the same normals
+        let rims = (0..8).flat_map(|i| [(i, (i + 1) % 8), (8 + i, 8 + (i + 1) % 8)]);
+        let mut expected = rims.map(|(a, b)| (VertexId(a.min(b)), VertexId(a.max(b)))).collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(cylinder.infer_sharp_edges(30.0), expected);
+        // the caps are at right angles to the sides
+        assert_eq!(cylinder.infer_sharp_edges(100.0), []);
+
+        // each rim vertex gets a copy for its cap and one for the sides
+        let (positions, indices) = split_at_sharp_edges(&cylinder, 30.0);
+        assert_eq!(positions.len(), 32);
+        assert_eq!(indices.iter().map(Vec::len).sum::<usize>(), 4 * 8 + 2 * 8);
+        for (poly, (_, original)) in indices.iter().zip(cylinder.collision_tree.leaves()) {
+            let copies = poly.iter().map(|&i| positions[i as usize]).collect::<Vec<_>>();
+            assert_eq!(copies, original.verts.iter().map(|vert| vert.vertex_id).collect::<Vec<_>>());
+        }
+        // which is just the original vertices when everything is smooth
+        assert_eq!(split_at_sharp_edges(&cylinder, 100.0).0.len(), 16);
+    }
+
+    #[test]
+    fn engine_breaking_errors_block_writing() {
+        let refusal = |model: &Model, options: &WriteOptions| {
+            let err = model.write_with(&mut vec![], options).unwrap_err();
+            err.get_ref().and_then(|err| err.downcast_ref::<WriteRefusal>()).cloned()
+        };
+
+        // detail1 on detail0, with its errors not rechecked since, which doesn't stop it being caught
+        let mut fighter = sample_fighter(0);
+        fighter.sub_objects[ObjectId(5)].parent = Some(ObjectId(0));
+        fighter.recalc_all_children_ids();
+        assert!(fighter.errors.is_empty());
+        let blocking = vec![Error::DetailObjWithParent(ObjectId(5))];
+        assert_eq!(refusal(&fighter, &WriteOptions::default()), Some(WriteRefusal { blocking }));
+
+        // unless it's asked for
+        let options = WriteOptions { allow_errors: true, ..Default::default() };
+        let mut buf = vec![];
+        fighter.write_with(&mut buf, &options).unwrap();
+        let written = Model::from_bytes(&buf).unwrap();
+        assert_eq!(written.sub_objects[ObjectId(5)].parent, Some(ObjectId(0)));
+        assert!(written.errors.contains(&Error::DetailObjWithParent(ObjectId(5))));
+
+        // naming mistakes and warnings never block
+        let mut fighter = sample_fighter(0);
+        fighter.sub_objects[ObjectId(8)].name = "debris01".to_string();
+        fighter.sub_objects[ObjectId(0)].radius = 0.0;
+        fighter.recheck_errors(Set::All);
+        fighter.recheck_warnings(Set::All);
+        assert!(fighter.errors.contains(&Error::DuplicateSubobjectName("debris01".to_string())));
+        assert!(!fighter.warnings.is_empty());
+        assert!(fighter.write_to_vec().is_ok());
+    }
 }
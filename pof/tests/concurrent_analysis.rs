@@ -0,0 +1,101 @@
+// analyses only need &Model, so a frontend can run them side by side on one model without any locking
+
+use pof::health::ScoreWeights;
+use pof::{BspData, Model, NormalId, ObjectId, PolyVertex, Polygon, Set, SubObject, TextureId, Vec3d, VertexId};
+
+// a closed box, its corners counter-clockwise seen from outside; built here rather than taken from pof::samples, which
+// needs the test-util feature, so this runs with a plain `cargo test`
+fn cuboid(obj_id: u32, parent: Option<u32>, offset: Vec3d, half: Vec3d) -> SubObject {
+    let faces = [
+        (Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)),
+        (Vec3d::new(-1.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(0.0, 1.0, 0.0)),
+        (Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(1.0, 0.0, 0.0)),
+        (Vec3d::new(0.0, -1.0, 0.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 0.0, 1.0)),
+        (Vec3d::new(0.0, 0.0, 1.0), Vec3d::new(1.0, 0.0, 0.0), Vec3d::new(0.0, 1.0, 0.0)),
+        (Vec3d::new(0.0, 0.0, -1.0), Vec3d::new(0.0, 1.0, 0.0), Vec3d::new(1.0, 0.0, 0.0)),
+    ];
+    let scale = |v: Vec3d| Vec3d::new(v.x * half.x, v.y * half.y, v.z * half.z);
+    let (mut verts, mut norms, mut polygons) = (vec![], vec![], vec![]);
+    for (normal, u, v) in faces {
+        let first = verts.len() as u32;
+        let normal_id = NormalId(norms.len() as u32);
+        verts.extend([normal - u - v, normal + u - v, normal + u + v, normal - u + v].map(scale));
+        norms.push(normal);
+        let verts = (0..4)
+            .map(|i| PolyVertex { vertex_id: VertexId(first + i), normal_id, uv: (0.0, 0.0) })
+            .collect();
+        polygons.push(Polygon { normal: Default::default(), texture: TextureId(0), verts });
+    }
+
+    // some of SubObject's fields are private, so it's filled in from a default
+    let mut subobj = SubObject::default();
+    subobj.obj_id = ObjectId(obj_id);
+    subobj.parent = parent.map(ObjectId);
+    subobj.offset = offset;
+    subobj.geo_center = offset;
+    subobj.name = if obj_id == 0 {
+        "detail0".to_string()
+    } else {
+        format!("section{:03}", obj_id)
+    };
+    subobj.bsp_data = BspData {
+        collision_tree: BspData::recalculate(&verts, polygons.into_iter()),
+        norms,
+        verts,
+    };
+    subobj.recalc_bbox();
+    subobj.recalc_radius();
+    subobj
+}
+
+// a hull with a chain of sections along it, so there's a hierarchy to walk
+fn sample_model(num_sections: u32) -> Model {
+    let mut model = Model::default();
+    model.textures = vec!["hull".to_string()];
+    model.sub_objects.push(cuboid(0, None, Vec3d::ZERO, Vec3d::new(20.0, 5.0, 40.0)));
+    for i in 1..=num_sections {
+        let offset = Vec3d::new(if i % 2 == 0 { 8.0 } else { -8.0 }, 5.0, 4.0);
+        model
+            .sub_objects
+            .push(cuboid(i, Some(i / 2), offset, Vec3d::new(2.0, 1.0 + i as f32 * 0.1, 3.0)));
+    }
+    model.header.detail_levels = vec![ObjectId(0)];
+    model.header.num_subobjects = model.sub_objects.len() as u32;
+    model.recalc_all_children_ids();
+    model.recalc_semantic_name_links();
+    model.recalc_bbox();
+    model.recalc_radius();
+    model.recalc_mass();
+    model.recalc_moi();
+    model
+}
+
+#[test]
+fn analyses_run_concurrently() {
+    let mut model = sample_model(40);
+    model.recheck_warnings(Set::All);
+    model.recheck_errors(Set::All);
+    let model: &Model = &model;
+
+    let dir = Vec3d::new(0.0, 0.0, 1.0);
+    let (warnings, errors, health, silhouette) = std::thread::scope(|scope| {
+        let warnings = scope.spawn(|| model.compute_warnings());
+        let errors = scope.spawn(|| model.compute_errors());
+        let health = scope.spawn(|| model.health_score(&ScoreWeights::default()));
+        let silhouette = scope.spawn(|| model.silhouette_radius(dir));
+        (warnings.join().unwrap(), errors.join().unwrap(), health.join().unwrap(), silhouette.join().unwrap())
+    });
+
+    // the same as running them one at a time, and the same as what was stored
+    assert_eq!(warnings, model.warnings);
+    assert_eq!(errors, model.errors);
+    assert_eq!(health, model.health_score(&ScoreWeights::default()));
+    assert_eq!(silhouette, model.silhouette_radius(dir));
+
+    // many threads on the same query agree too
+    let areas = std::thread::scope(|scope| {
+        let handles = (0..4).map(|_| scope.spawn(|| model.external_surface_area())).collect::<Vec<_>>();
+        handles.into_iter().map(|handle| handle.join().unwrap()).collect::<Vec<_>>()
+    });
+    assert!(areas.iter().all(|&area| area == areas[0] && area > 0.0));
+}
@@ -22,15 +22,15 @@ use glium::{glutin::surface::WindowSurface, texture::SrgbTexture2d, BlendingFunc
 use glm::Mat4x4;
 use native_dialog::FileDialog;
 use pof::{
-    properties_get_field, BspData, Insignia, NameLink, NormalId, NormalVec3, ObjVec, ObjectId, Parser, PolyVertex, Polygon, ShieldData, SubObject,
-    TextureId, Vec3d, VertexId,
+    properties_get_field, quirks::QuirkSet, BspData, Insignia, NameLink, NormalId, NormalVec3, ObjVec, ObjectId, ParseOptions, Parser, PolyVertex,
+    Polygon, ShieldData, SubObject, TextureId, Vec3d, VertexId, WriteRefusal,
 };
 use simplelog::*;
 use std::{
     collections::HashMap,
     f32::consts::PI,
     fs::File,
-    io::{Cursor, Read},
+    io::{Cursor, Read, Write},
     ops::{Deref, DerefMut},
     path::PathBuf,
     sync::mpsc::{Receiver, TryRecvError},
@@ -398,8 +398,9 @@ impl Model {
 type LoadingThread = Option<Receiver<Result<Option<Box<Model>>, String>>>;
 
 impl PofToolsGui {
-    fn save_model(model: &Model) -> Option<String> {
-        let mut out = None;
+    // Err if the model has errors which block writing a pof, which the cached model.errors might not have caught up with
+    fn save_model(model: &Model) -> Result<Option<String>, WriteRefusal> {
+        let mut out = Ok(None);
         // use a scoped thread here, its ok to block the main window for now i guess
         crossbeam::thread::scope(|s| {
             s.spawn(|_| {
@@ -412,15 +413,30 @@ impl PofToolsGui {
                     .add_filter("GL Transmission Format (Binary)", &["glb"])
                     .show_save_single_file();
                 if let Ok(Some(path)) = path {
-                    let mut file = File::create(path.clone()).unwrap();
                     match path.extension().map(|ext| ext.to_ascii_lowercase()) {
-                        Some(s) if s == "glb" => model.write_gltf(&mut file, true).unwrap(),
-                        Some(s) if s == "gltf" => model.write_gltf(&mut file, false).unwrap(),
-                        Some(s) if s == "dae" => model.write_dae(&mut file).unwrap(),
-                        Some(s) if s == "pof" => model.write(&mut file).unwrap(),
-                        s => panic!("unexpected extension {:?}", s),
+                        Some(s) if s == "pof" => {
+                            // written to memory first, so a refused write doesn't leave an empty file behind
+                            let mut buf = vec![];
+                            if let Err(err) = model.write(&mut buf) {
+                                match err.get_ref().and_then(|err| err.downcast_ref::<WriteRefusal>()) {
+                                    Some(refusal) => out = Err(refusal.clone()),
+                                    None => panic!("{}", err),
+                                }
+                                return;
+                            }
+                            File::create(path.clone()).unwrap().write_all(&buf).unwrap();
+                        }
+                        s => {
+                            let mut file = File::create(path.clone()).unwrap();
+                            match s {
+                                Some(s) if s == "glb" => model.write_gltf(&mut file, true).unwrap(),
+                                Some(s) if s == "gltf" => model.write_gltf(&mut file, false).unwrap(),
+                                Some(s) if s == "dae" => model.write_dae(&mut file).unwrap(),
+                                s => panic!("unexpected extension {:?}", s),
+                            }
+                        }
                     }
-                    out = Some(path.file_name().and_then(|f| f.to_str()).unwrap_or("").to_string());
+                    out = Ok(Some(path.file_name().and_then(|f| f.to_str()).unwrap_or("").to_string()));
                 }
             });
         })
@@ -452,6 +468,7 @@ impl PofToolsGui {
                         Some("pof") => {
                             let file = File::open(&path).expect("TODO invalid file or smth i dunno");
                             let mut parser = Parser::new(file).expect("TODO invalid version of file or smth i dunno");
+                            parser.set_options(ParseOptions { quirks: QuirkSet::Auto, ..Default::default() });
                             parser.parse(path).expect("TODO invalid pof file or smth i dunno")
                         }
                         _ => todo!(),
@@ -545,7 +562,7 @@ impl PofToolsGui {
         self.camera_pitch = -0.4;
         self.camera_offset = Vec3d::ZERO;
         self.camera_scale = self.model.header.max_radius * 1.5;
-        self.ui_state.last_selected_subobj = self.model.header.detail_levels.first().copied();
+        self.ui_state.last_selected_subobj = self.model.detail0_id();
         self.ui_state.tree_view_selection = TreeValue::Header;
 
         self.maybe_recalculate_3d_helpers(display);
@@ -1599,10 +1616,11 @@ fn get_list_of_display_subobjects(model: &Model, tree_selection: TreeValue, last
     }
 
     if let TreeValue::Insignia(InsigniaTreeValue::Insignia(idx)) = tree_selection {
-        // show the LOD objects according to the detail level of the currently selected insignia
-        for (i, sub_object) in model.sub_objects.iter().enumerate() {
-            out.0[i] = model.is_obj_id_ancestor(sub_object.obj_id, model.header.detail_levels[model.insignias[idx].detail_level as usize])
-                && !sub_object.is_destroyed_model();
+        // show the LOD objects according to the detail level of the currently selected insignia, if it has a valid one
+        if let Some(&detail_level) = model.header.detail_levels.get(model.insignias[idx].detail_level as usize) {
+            for (i, sub_object) in model.sub_objects.iter().enumerate() {
+                out.0[i] = model.is_obj_id_ancestor(sub_object.obj_id, detail_level) && !sub_object.is_destroyed_model();
+            }
         }
     } else if let Some(last_selected_subobj) = last_selected_subobj {
         //find the top level parent of the currently subobject
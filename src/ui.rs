@@ -9,7 +9,7 @@ use pof::{
     ThrusterBank, ThrusterGlow, Turret, Vec3d, Version, Warning, WeaponHardpoint,
 };
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     f32::consts::{FRAC_PI_2, PI},
     hash::Hash,
     sync::mpsc::Receiver,
@@ -17,6 +17,7 @@ use std::{
 use winit::window::Window;
 
 use eframe::egui::{self, Button, TextStyle, Ui};
+use pof::texture_annotations::TextureAnnotation;
 use pof::ObjectId;
 
 use crate::{
@@ -109,6 +110,9 @@ impl TreeValue {
                 Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(*id)))
             }
             Error::DuplicateSubobjectName(_) => None,
+            Error::NoDetailLevels => Some(TreeValue::Header),
+            Error::BspTooDeep(Some(id)) => Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(*id))),
+            Error::BspTooDeep(None) => Some(TreeValue::Shield),
         }
     }
 
@@ -125,16 +129,20 @@ impl TreeValue {
             Warning::UntexturedPolygons => Some(TreeValue::Textures(TextureTreeValue::tex(model.untextured_idx))),
             Warning::DockingBayWithoutPath(idx) => Some(TreeValue::DockingBays(DockingTreeValue::Bay(*idx))),
             Warning::ThrusterPropertiesInvalidVersion(idx) => Some(TreeValue::Thrusters(ThrusterTreeValue::Bank(*idx))),
-            Warning::WeaponOffsetInvalidVersion { primary, bank, point } => {
-                if *primary {
-                    Some(TreeValue::Weapons(WeaponTreeValue::PriBankPoint(*bank, *point)))
-                } else {
-                    Some(TreeValue::Weapons(WeaponTreeValue::SecBankPoint(*bank, *point)))
-                }
+            Warning::WeaponOffsetInvalidVersion(weapon) => {
+                Some(TreeValue::Weapons(WeaponTreeValue::bank_point(weapon.kind.is_primary(), weapon.bank as usize, Some(weapon.point as usize))))
             }
+            Warning::WeaponInsideHull { primary, bank, point } => {
+                Some(TreeValue::Weapons(WeaponTreeValue::bank_point(*primary, *bank, Some(*point))))
+            }
+            Warning::DuplicateWeaponPoint { primary, bank } => Some(TreeValue::Weapons(WeaponTreeValue::bank_point(*primary, *bank, None))),
+            Warning::DuplicateDockingBay(idx) => Some(TreeValue::DockingBays(DockingTreeValue::Bay(*idx))),
+            Warning::DuplicateEyePoint(idx) => Some(TreeValue::EyePoints(EyeTreeValue::EyePoint(*idx))),
             Warning::TooFewTurretFirePoints(idx) => Some(TreeValue::Turrets(TurretTreeValue::Turret(*idx))),
             Warning::TooManyTurretFirePoints(idx) => Some(TreeValue::Turrets(TurretTreeValue::Turret(*idx))),
             Warning::DuplicatePathName(_) => None,
+            Warning::DuplicateAttachmentName(_) => None,
+            Warning::DegenerateAttachmentFrame(_) => None,
             Warning::DuplicateDetailLevel(_) => Some(TreeValue::Header),
             Warning::TooManyEyePoints => Some(TreeValue::EyePoints(EyeTreeValue::Header)),
             Warning::TooManyTextures => Some(TreeValue::Textures(TextureTreeValue::Header)),
@@ -148,7 +156,23 @@ impl TreeValue {
             Warning::GlowBankPropertiesTooLong(idx) => Some(TreeValue::Glows(GlowTreeValue::Bank(*idx))),
             Warning::SpecialPointPropertiesTooLong(idx) => Some(TreeValue::SpecialPoints(SpecialPointTreeValue::Point(*idx))),
             Warning::InvalidDockParentSubmodel(idx) => Some(TreeValue::DockingBays(DockingTreeValue::Bay(*idx))),
+            Warning::InsigniaInvalidDetailLevel(idx) => Some(TreeValue::Insignia(InsigniaTreeValue::Insignia(*idx))),
             Warning::Detail0NonZeroOffset => Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(model.header.detail_levels[0]))),
+            Warning::AutocenterFlagWithoutVisualCenter => Some(TreeValue::Header),
+            Warning::UnknownHeaderFlags => Some(TreeValue::Header),
+            Warning::TilingUvsWithoutTilingFlag => Some(TreeValue::Header),
+            Warning::VisualCenterOutsideDetail0 => Some(TreeValue::VisualCenter),
+            Warning::PrecisionBudgetExceeded => Some(TreeValue::Header),
+            Warning::NormalsRepaired => Some(TreeValue::Header),
+            Warning::LegacyQuirksFixed => Some(TreeValue::Header),
+            Warning::ChunkLengthMismatch => Some(TreeValue::Header),
+            Warning::VersionChangedFromDisk => Some(TreeValue::Header),
+            Warning::InvalidCrossSections => Some(TreeValue::Header),
+            Warning::DebrisFlagNameMismatch(id) => Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(*id))),
+            Warning::DebrisInDetailHierarchy(id) => Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(*id))),
+            Warning::UnreachableSubobject(id) => Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(*id))),
+            Warning::ZFightingPolygons(id) => Some(TreeValue::SubObjects(SubObjectTreeValue::SubObject(*id))),
+            Warning::PathClipsGeometry(idx) => Some(TreeValue::Paths(PathTreeValue::Path(*idx))),
         }
     }
 
@@ -701,7 +725,7 @@ impl UiState {
             if let TreeValue::SubObjects(SubObjectTreeValue::SubObject(id)) = self.tree_view_selection {
                 self.last_selected_subobj = Some(id);
             } else if let TreeValue::SubObjects(SubObjectTreeValue::Header) | TreeValue::Header = self.tree_view_selection {
-                self.last_selected_subobj = model.header.detail_levels.first().copied();
+                self.last_selected_subobj = model.detail0_id();
             }
         }
     }
@@ -734,7 +758,7 @@ impl UiState {
                     if let TreeValue::SubObjects(SubObjectTreeValue::SubObject(id)) = self.tree_view_selection {
                         self.last_selected_subobj = Some(id);
                     } else if let TreeValue::SubObjects(SubObjectTreeValue::Header) | TreeValue::Header = self.tree_view_selection {
-                        self.last_selected_subobj = model.header.detail_levels.first().copied();
+                        self.last_selected_subobj = model.detail0_id();
                     }
                 }
             })
@@ -782,6 +806,7 @@ pub enum UndoAction {
     ChangeTextures {
         id_map: HashMap<TextureId, TextureId>,
         textures: Vec<String>,
+        annotations: BTreeMap<TextureId, TextureAnnotation>,
     },
 }
 
@@ -792,9 +817,19 @@ impl undo::Action for UndoAction {
 
     fn apply(&mut self, target: &mut Model) -> undo::Result<UndoAction> {
         match self {
-            UndoAction::ChangeTextures { id_map, textures } => {
+            UndoAction::ChangeTextures { id_map, textures, annotations } => {
+                // the untextured slot follows its polygons' original texture id to wherever the new map puts it
+                let untextured_orig = (target.untextured_idx).and_then(|idx| {
+                    (target.texture_map.iter())
+                        .filter(|(_, &current)| current == idx)
+                        .map(|(&orig, _)| orig)
+                        .min()
+                });
                 std::mem::swap(&mut target.texture_map, id_map);
                 std::mem::swap(&mut target.textures, textures);
+                *annotations = target.replace_texture_annotations(std::mem::take(annotations));
+                target.untextured_idx = untextured_orig.and_then(|orig| target.texture_map.get(&orig).copied());
+                target.recheck_warnings(pof::Set::One(Warning::UntexturedPolygons));
                 Ok(())
             }
             UndoAction::MoveLollipop { tree_val, delta_vec } => {
@@ -952,9 +987,14 @@ impl PofToolsGui {
                     {
                         self.model.clean_up();
 
-                        let new_filename = PofToolsGui::save_model(&self.model);
-                        if let Some(filename) = new_filename {
-                            window.set_title(&format!("Pof Tools v{} - {}", POF_TOOLS_VERSION, filename));
+                        match PofToolsGui::save_model(&self.model) {
+                            Ok(Some(filename)) => window.set_title(&format!("Pof Tools v{} - {}", POF_TOOLS_VERSION, filename)),
+                            Ok(None) => {}
+                            Err(refusal) => {
+                                // the cached errors were stale, so bring them up to date for the info bar to list what blocked it
+                                error!("Refused to save: {}", refusal);
+                                self.model.recheck_errors(pof::Set::All);
+                            }
                         }
                         ui.close_menu();
                     }
@@ -1143,6 +1183,13 @@ impl PofToolsGui {
                                 Error::UnnamedSubObject(id) => {
                                     format!("⊗ Subobject id {:?} requires a name", id)
                                 }
+                                Error::NoDetailLevels => {
+                                    format!("⊗ This model has no detail levels")
+                                }
+                                Error::BspTooDeep(id) => {
+                                    let tree = id.map_or("The shield".to_string(), |id| format!("Subobject '{}'", self.model.sub_objects[id].name));
+                                    format!("⊗ {}'s collision tree is deeper than {} nodes, and must be rebuilt", tree, self.model.bsp_depth_limit())
+                                }
                                 Error::DuplicateSubobjectName(name) => {
                                     format!("⊗ More than one subobject shares the name '{}'", name)
                                 }
@@ -1218,14 +1265,35 @@ impl PofToolsGui {
                                 Warning::ThrusterPropertiesInvalidVersion(idx) => {
                                     format!("⚠ Thruster bank {} has properties, which the currently selected version does not support", idx + 1)
                                 }
-                                Warning::WeaponOffsetInvalidVersion { primary, bank, point } => {
+                                Warning::WeaponInsideHull { primary, bank, point } => {
                                     format!(
-                                        "⚠ {} bank {}, point {}, has an external angle offset, which the currently selected version does not support",
+                                        "⚠ {} bank {}, point {}, is inside the hull, so it will fire from inside the ship",
                                         if *primary { "Primary" } else { "Secondary" },
                                         bank + 1,
                                         point + 1
                                     )
                                 }
+                                Warning::DuplicateWeaponPoint { primary, bank } => {
+                                    format!(
+                                        "⚠ {} bank {} has two points in the same spot, facing the same way",
+                                        if *primary { "Primary" } else { "Secondary" },
+                                        bank + 1
+                                    )
+                                }
+                                Warning::DuplicateDockingBay(idx) => {
+                                    format!("⚠ Docking bay {} is in the same spot as an earlier one, facing the same way", idx + 1)
+                                }
+                                Warning::DuplicateEyePoint(idx) => {
+                                    format!("⚠ Eye point {} is in the same spot as an earlier one, facing the same way", idx + 1)
+                                }
+                                Warning::WeaponOffsetInvalidVersion(weapon) => {
+                                    format!(
+                                        "⚠ {} bank {}, point {}, has an external angle offset, which the currently selected version does not support",
+                                        if weapon.kind.is_primary() { "Primary" } else { "Secondary" },
+                                        weapon.bank + 1,
+                                        weapon.point + 1
+                                    )
+                                }
                                 Warning::SubObjectTranslationInvalidVersion(id) => {
                                     format!(
                                         "⚠ Subobject {} has a translation axis defined, which the currently selected version does not support",
@@ -1268,6 +1336,15 @@ impl PofToolsGui {
                                 Warning::DuplicatePathName(duped_name) => {
                                     format!("⚠ More than one path shares the name '{}'", duped_name)
                                 }
+                                Warning::DuplicateAttachmentName(duped_name) => {
+                                    format!("⚠ More than one attachment shares the name '{}'", duped_name)
+                                }
+                                Warning::DegenerateAttachmentFrame(idx) => {
+                                    format!(
+                                        "⚠ Attachment '{}' has a forward and up vector which are not unit length and perpendicular",
+                                        self.model.attachments[*idx].name
+                                    )
+                                }
                                 Warning::DuplicateDetailLevel(id) => {
                                     format!("⚠ Subobject '{}' belongs to more than one detail level", self.model.sub_objects[*id].name)
                                 }
@@ -1281,10 +1358,90 @@ impl PofToolsGui {
                                         dock_name
                                     )
                                 }
+                                Warning::InsigniaInvalidDetailLevel(idx) => {
+                                    format!(
+                                        "⚠ Insignia {} is on detail level {}, but this model only has {} detail levels",
+                                        idx + 1,
+                                        self.model.insignias[*idx].detail_level,
+                                        self.model.header.detail_levels.len()
+                                    )
+                                }
                                 Warning::Detail0NonZeroOffset => {
                                     let id = self.model.header.detail_levels[0];
                                     format!("⚠ Detail0 object '{}' should have a (0, 0, 0) offset.", self.model.sub_objects[id].name)
                                 }
+                                Warning::AutocenterFlagWithoutVisualCenter => {
+                                    format!("⚠ The header has the autocenter flag set, but this model has no visual center")
+                                }
+                                Warning::UnknownHeaderFlags => {
+                                    format!(
+                                        "⚠ The header has flags {:#x} set, which the engine doesn't know about",
+                                        self.model.header.flags().unknown_bits()
+                                    )
+                                }
+                                Warning::TilingUvsWithoutTilingFlag => {
+                                    format!("⚠ Some UVs are outside of 0-1, but the header doesn't have the allow tiling flag set, so textures won't tile")
+                                }
+                                Warning::VisualCenterOutsideDetail0 => {
+                                    format!("⚠ The visual center is outside of the detail0 bounding box, so the target box will be off the ship")
+                                }
+                                Warning::PrecisionBudgetExceeded => {
+                                    let report = self.model.precision_report();
+                                    format!(
+                                        "⚠ This model is large enough that vertex positions are only accurate to {}m; recentering subobject offsets may help",
+                                        report.worst_quantization
+                                    )
+                                }
+                                Warning::NormalsRepaired => {
+                                    format!(
+                                        "⚠ {} normals were zero, not unit length, or lost to a transform, and have been repaired",
+                                        self.model.normal_notices.len()
+                                    )
+                                }
+                                Warning::LegacyQuirksFixed => {
+                                    let notices = self.model.quirk_notices.iter().map(|notice| &notice.description[..]).collect::<Vec<_>>();
+                                    format!("⚠ This file was written by a legacy tool, and was fixed up on load: {}", notices.join(", "))
+                                }
+                                Warning::ChunkLengthMismatch => {
+                                    let notices = self.model.parse_notices.iter().map(|notice| notice.description()).collect::<Vec<_>>();
+                                    format!("⚠ Some chunk lengths in this file are wrong, saving will correct them: {}", notices.join(", "))
+                                }
+                                Warning::VersionChangedFromDisk => {
+                                    format!(
+                                        "⚠ This model was loaded as version {}, but will be saved as the older {}",
+                                        self.model.loaded_version(),
+                                        self.model.version
+                                    )
+                                }
+                                Warning::InvalidCrossSections => {
+                                    format!("⚠ Cross section depths must be in increasing order with no duplicates, and radii must be positive")
+                                }
+                                Warning::DebrisFlagNameMismatch(id) => {
+                                    let subobj = &self.model.sub_objects[*id];
+                                    if subobj.is_debris_model {
+                                        format!("⚠ Subobject '{}' is debris, but its name does not start with 'debris'", subobj.name)
+                                    } else {
+                                        format!("⚠ Subobject '{}' is named like debris, but is not a debris object", subobj.name)
+                                    }
+                                }
+                                Warning::DebrisInDetailHierarchy(id) => {
+                                    format!("⚠ Debris subobject '{}' is part of a detail level's hierarchy", self.model.sub_objects[*id].name)
+                                }
+                                Warning::UnreachableSubobject(id) => {
+                                    format!(
+                                        "⚠ Subobject '{}' is not part of any detail level and is not debris, so it will never be rendered",
+                                        self.model.sub_objects[*id].name
+                                    )
+                                }
+                                Warning::ZFightingPolygons(id) => {
+                                    format!(
+                                        "⚠ Subobject '{}' has overlapping polygons in the same plane, which will flicker",
+                                        self.model.sub_objects[*id].name
+                                    )
+                                }
+                                Warning::PathClipsGeometry(idx) => {
+                                    format!("⚠ Path '{}' runs into the hull, so ships following it will crash", self.model.paths[*idx].name)
+                                }
                                 Warning::PathNameTooLong(_)
                                 | Warning::SubObjectNameTooLong(_)
                                 | Warning::SpecialPointNameTooLong(_)
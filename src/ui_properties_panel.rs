@@ -10,7 +10,7 @@ use glium::Display;
 use nalgebra_glm::TMat4;
 use pof::{
     Dock, Error, EyePoint, GlowPoint, GlowPointBank, Insignia, ObjectId, PathId, PathPoint, Set::*, SpecialPoint, SubsysRotationAxis,
-    SubsysRotationType, SubsysTranslationAxis, SubsysTranslationType, ThrusterGlow, Vec3d, Warning, WeaponHardpoint,
+    SubsysRotationType, SubsysTranslationAxis, SubsysTranslationType, ThrusterGlow, Vec3d, Warning, WeaponHardpoint, WeaponKind, WeaponPointRef,
 };
 
 use crate::Model;
@@ -1163,9 +1163,7 @@ impl PofToolsGui {
                             self.model.header.detail_levels.push(new_id);
                         } else if let Some(swapped_level) = self.model.header.detail_levels.iter().position(|&id| id == new_id) {
                             // swap with an existing level
-                            let swapped_id = self.model.header.detail_levels[level];
-                            self.model.header.detail_levels[level] = new_id;
-                            self.model.header.detail_levels[swapped_level] = swapped_id;
+                            self.model.swap_detail_levels(level, swapped_level);
                         } else {
                             self.model.header.detail_levels[level] = new_id;
                         }
@@ -1186,7 +1184,9 @@ impl PofToolsGui {
                     transform_window.open = true;
                 }
                 if let Some(matrix) = UiState::show_transform_window(ctx, transform_window) {
-                    self.model.apply_transform(&matrix);
+                    if let Err(err) = self.model.apply_transform(&matrix) {
+                        error!("Couldn't transform the mesh: {}", err);
+                    }
                     rebuild_all_buffers = true;
                     self.ui_state.viewport_3d_dirty = true;
                     self.ui_state.properties_panel_dirty = true;
@@ -1240,6 +1240,7 @@ impl PofToolsGui {
                     let old_name = self.model.sub_objects[id].name.clone();
                     if text_edit_single(ui, "subobj name", &mut self.model.sub_objects[id].name).changed() {
                         self.model.recheck_warnings(One(Warning::SubObjectNameTooLong(id)));
+                        self.model.recheck_warnings(One(Warning::DebrisFlagNameMismatch(id)));
                         self.model.recheck_errors(One(Error::UnnamedSubObject(id)));
                         self.model.recheck_errors(One(Error::DuplicateSubobjectName(old_name)));
                         self.model
@@ -1282,6 +1283,8 @@ impl PofToolsGui {
                         self.model.sub_objects[selected_id.unwrap()].is_debris_model = *is_debris_check;
                         self.model.recheck_errors(One(Error::TooManyDebrisObjects));
                         self.model.recheck_errors(One(Error::DetailAndDebrisObj(selected_id.unwrap())));
+                        self.model.recheck_warnings(One(Warning::DebrisFlagNameMismatch(selected_id.unwrap())));
+                        self.model.recheck_warnings(One(Warning::DebrisInDetailHierarchy(selected_id.unwrap())));
                     }
 
                     UiState::reset_widget_color(ui);
@@ -1337,7 +1340,8 @@ impl PofToolsGui {
 
                 let response = ui.add_enabled(selected_id.is_some(), egui::Button::new("Recalculate"));
                 if response.clicked() {
-                    self.model.sub_objects[selected_id.unwrap()].recalc_bbox();
+                    let policy = self.model.tolerance_policy();
+                    self.model.sub_objects[selected_id.unwrap()].recalc_bbox_with(&policy);
                     self.ui_state.properties_panel_dirty = true;
                     bbox_changed = true;
                 }
@@ -1451,7 +1455,8 @@ impl PofToolsGui {
 
                 let response = ui.add_enabled(selected_id.is_some(), egui::Button::new("Recalculate"));
                 if response.clicked() {
-                    self.model.sub_objects[selected_id.unwrap()].recalc_radius();
+                    let policy = self.model.tolerance_policy();
+                    self.model.sub_objects[selected_id.unwrap()].recalc_radius_with(&policy);
                     self.ui_state.properties_panel_dirty = true;
                     radius_changed = true;
                 }
@@ -1516,6 +1521,8 @@ impl PofToolsGui {
                     //Error::InvalidTurretGunSubobject(())
                     //Error::DetailObjWithParent(())
                     self.model.recheck_errors(All);
+                    // Warning::UnreachableSubobject(())
+                    self.model.recheck_warnings(All);
                 }
 
                 // Properties edit ================================================================
@@ -1744,12 +1751,8 @@ impl PofToolsGui {
                 };
 
                 ui.label("Texture Name:");
-                if UiState::model_value_edit("textures texture name", &mut self.ui_state.viewport_3d_dirty, ui, false, tex, texture_name).changed()
-                    && self.model.untextured_idx.is_some()
-                    && self.ui_state.tree_view_selection == TreeValue::Textures(TextureTreeValue::Texture(self.model.untextured_idx.unwrap()))
-                {
-                    self.model.untextured_idx = None;
-                    self.model.recheck_warnings(One(Warning::UntexturedPolygons));
+                if UiState::model_value_edit("textures texture name", &mut self.ui_state.viewport_3d_dirty, ui, false, tex, texture_name).changed() {
+                    self.model.refresh_untextured_slot();
                 }
 
                 ui.add_space(5.0);
@@ -1924,19 +1927,41 @@ impl PofToolsGui {
                     };
 
                 ui.label("Position:");
-                UiState::model_value_edit("weapons position", &mut self.ui_state.viewport_3d_dirty, ui, false, pos, position_string);
+                let inside_hull_warning = Warning::WeaponInsideHull {
+                    primary: weapon_selection.is_primary(),
+                    bank: bank_num.unwrap_or_default(),
+                    point: point_num.unwrap_or_default(),
+                };
+                let duplicate_warning = Warning::DuplicateWeaponPoint {
+                    primary: weapon_selection.is_primary(),
+                    bank: bank_num.unwrap_or_default(),
+                };
+                let duplicate = self.model.pof_model.warnings.contains(&duplicate_warning);
+                let position_changed = UiState::model_value_edit(
+                    "weapons position",
+                    &mut self.ui_state.viewport_3d_dirty,
+                    ui,
+                    self.model.pof_model.warnings.contains(&inside_hull_warning) || duplicate,
+                    pos,
+                    position_string,
+                )
+                .changed();
                 ui.label("Normal:");
-                UiState::model_value_edit("weapons normal", &mut self.ui_state.viewport_3d_dirty, ui, false, norm, normal_string);
+                let normal_changed =
+                    UiState::model_value_edit("weapons normal", &mut self.ui_state.viewport_3d_dirty, ui, duplicate, norm, normal_string).changed();
                 ui.label("Offset:");
                 let offset_changed = UiState::model_value_edit(
                     "weapons angle offset",
                     &mut self.ui_state.viewport_3d_dirty,
                     ui,
-                    self.model.pof_model.warnings.contains(&Warning::WeaponOffsetInvalidVersion {
-                        primary: weapon_selection.is_primary(),
-                        bank: bank_num.unwrap_or_default(),
-                        point: point_num.unwrap_or_default(),
-                    }),
+                    self.model
+                        .pof_model
+                        .warnings
+                        .contains(&Warning::WeaponOffsetInvalidVersion(WeaponPointRef::new(
+                            WeaponKind::from_primary(weapon_selection.is_primary()),
+                            bank_num.unwrap_or_default(),
+                            point_num.unwrap_or_default(),
+                        ))),
                     offset,
                     offset_string,
                 )
@@ -1979,11 +2004,17 @@ impl PofToolsGui {
                 }
 
                 if offset_changed {
-                    self.model.recheck_warnings(One(Warning::WeaponOffsetInvalidVersion {
-                        primary: weapon_selection.is_primary(),
-                        bank: bank_num.unwrap(),
-                        point: point_num.unwrap(),
-                    }));
+                    self.model.recheck_warnings(One(Warning::WeaponOffsetInvalidVersion(WeaponPointRef::new(
+                        WeaponKind::from_primary(weapon_selection.is_primary()),
+                        bank_num.unwrap(),
+                        point_num.unwrap(),
+                    ))));
+                }
+                if position_changed {
+                    self.model.recheck_warnings(One(inside_hull_warning));
+                }
+                if position_changed || normal_changed {
+                    self.model.recheck_warnings(One(duplicate_warning));
                 }
             }
             PropertiesPanel::DockingBay {
@@ -2111,16 +2142,27 @@ impl PofToolsGui {
                 ui.add_space(10.0);
 
                 ui.label("Position:");
+                let duplicate = bay_num.map_or(false, |num| self.model.warnings.contains(&Warning::DuplicateDockingBay(num)));
                 let pos = bay_num.map(|num| &mut self.model.docking_bays[num].position);
-                UiState::model_value_edit("docking bay position", &mut self.ui_state.viewport_3d_dirty, ui, false, pos, position_string);
+                let position_changed =
+                    UiState::model_value_edit("docking bay position", &mut self.ui_state.viewport_3d_dirty, ui, duplicate, pos, position_string)
+                        .changed();
 
                 ui.label(RichText::new("Forward Vector:").color(Color32::from_rgb(140, 150, 210)));
                 let norm = bay_num.map(|num| &mut self.model.docking_bays[num].fvec);
-                if UiState::model_value_edit("docking bay fvec", &mut self.ui_state.viewport_3d_dirty, ui, false, norm, fvec_string).changed() {
+                let fvec_changed =
+                    UiState::model_value_edit("docking bay fvec", &mut self.ui_state.viewport_3d_dirty, ui, duplicate, norm, fvec_string).changed();
+                if fvec_changed {
                     let bay = &mut self.model.docking_bays[bay_num.unwrap()];
                     bay.uvec = Dock::orthonormalize(&bay.uvec.0.into(), &bay.fvec.0.into());
                     *uvec_ang = bay.get_uvec_angle().to_degrees() % 360.0
                 }
+                // moving one bay can make any later one a duplicate, or not
+                if position_changed || fvec_changed {
+                    for i in 0..self.model.docking_bays.len() {
+                        self.model.recheck_warnings(One(Warning::DuplicateDockingBay(i)));
+                    }
+                }
 
                 ui.label(RichText::new("Up Vector:").color(Color32::from_rgb(210, 140, 140)));
                 ui.add_enabled_ui(bay_num.is_some(), |ui| {
@@ -2556,6 +2598,7 @@ impl PofToolsGui {
                             .pof_model
                             .recheck_warnings(One(Warning::DuplicatePathName(self.model.pof_model.paths[num].name.clone())));
                         self.model.recheck_warnings(One(Warning::PathNameTooLong(num)));
+                        self.model.recheck_warnings(One(Warning::PathClipsGeometry(num)));
                     };
                 } else {
                     ui.add_enabled(false, egui::TextEdit::multiline(name).desired_rows(1));
@@ -2563,8 +2606,12 @@ impl PofToolsGui {
 
                 ui.label("Parent:");
                 if let Some(num) = path_num {
-                    ui.add(egui::TextEdit::multiline(&mut self.model.paths[num].parent).desired_rows(1))
-                        .changed();
+                    if ui
+                        .add(egui::TextEdit::multiline(&mut self.model.paths[num].parent).desired_rows(1))
+                        .changed()
+                    {
+                        self.model.recheck_warnings(One(Warning::PathClipsGeometry(num)));
+                    }
                 } else {
                     ui.add_enabled(false, egui::TextEdit::multiline(parent_string).desired_rows(1));
                 }
@@ -2576,6 +2623,10 @@ impl PofToolsGui {
 
                 ui.add_space(10.0);
 
+                let clips_warning = path_num.map(Warning::PathClipsGeometry);
+                let clips = clips_warning
+                    .as_ref()
+                    .map_or(false, |warning| self.model.pof_model.warnings.contains(warning));
                 let (radius, pos) = if let TreeValue::Paths(PathTreeValue::PathPoint(path, point)) = self.ui_state.tree_view_selection {
                     let PathPoint { position, radius, .. } = &mut self.model.paths[path].points[point];
                     (Some(radius), Some(position))
@@ -2584,9 +2635,14 @@ impl PofToolsGui {
                 };
 
                 ui.label("Radius:");
-                UiState::model_value_edit("path radius", &mut self.ui_state.viewport_3d_dirty, ui, false, radius, radius_string);
+                let radius_changed =
+                    UiState::model_value_edit("path radius", &mut self.ui_state.viewport_3d_dirty, ui, clips, radius, radius_string).changed();
                 ui.label("Position:");
-                UiState::model_value_edit("path position", &mut self.ui_state.viewport_3d_dirty, ui, false, pos, position_string);
+                let position_changed =
+                    UiState::model_value_edit("path position", &mut self.ui_state.viewport_3d_dirty, ui, clips, pos, position_string).changed();
+                if let Some(warning) = clips_warning.filter(|_| radius_changed || position_changed) {
+                    self.model.recheck_warnings(One(warning));
+                }
 
                 if let Some(response) = path_idx_response {
                     if let IndexingButtonsResponse::Delete(idx) = response {
@@ -2627,7 +2683,13 @@ impl PofToolsGui {
 
                 ui.add_space(10.0);
 
-                let (lod, offset) = if let TreeValue::Insignia(InsigniaTreeValue::Insignia(idx)) = self.ui_state.tree_view_selection {
+                let insignia_idx = match self.ui_state.tree_view_selection {
+                    TreeValue::Insignia(InsigniaTreeValue::Insignia(idx)) => Some(idx),
+                    _ => None,
+                };
+                let lod_warning = insignia_idx.map_or(false, |idx| self.model.warnings.contains(&Warning::InsigniaInvalidDetailLevel(idx)));
+
+                let (lod, offset) = if let Some(idx) = insignia_idx {
                     let Insignia { detail_level, offset, .. } = &mut self.model.insignias[idx];
                     (Some(detail_level), Some(offset))
                 } else {
@@ -2635,7 +2697,10 @@ impl PofToolsGui {
                 };
 
                 ui.label("Detail Level:");
-                UiState::model_value_edit("insignia lod", &mut self.ui_state.viewport_3d_dirty, ui, false, lod, lod_string);
+                if UiState::model_value_edit("insignia lod", &mut self.ui_state.viewport_3d_dirty, ui, lod_warning, lod, lod_string).changed() {
+                    self.model
+                        .recheck_warnings(One(Warning::InsigniaInvalidDetailLevel(insignia_idx.unwrap())));
+                }
                 ui.label("Offset:");
                 UiState::model_value_edit("insignia offset", &mut self.ui_state.viewport_3d_dirty, ui, false, offset, offset_string);
             }
@@ -2682,10 +2747,18 @@ impl PofToolsGui {
                 } else {
                     (None, None)
                 };
+                let duplicate = eye_num.map_or(false, |num| self.model.warnings.contains(&Warning::DuplicateEyePoint(num)));
                 ui.label("Position:");
-                UiState::model_value_edit("eye position", &mut self.ui_state.viewport_3d_dirty, ui, false, pos, position_string);
+                let position_changed =
+                    UiState::model_value_edit("eye position", &mut self.ui_state.viewport_3d_dirty, ui, duplicate, pos, position_string).changed();
                 ui.label("Normal:");
-                UiState::model_value_edit("eye normal", &mut self.ui_state.viewport_3d_dirty, ui, false, norm, normal_string);
+                let normal_changed =
+                    UiState::model_value_edit("eye normal", &mut self.ui_state.viewport_3d_dirty, ui, duplicate, norm, normal_string).changed();
+                if position_changed || normal_changed {
+                    for i in 0..self.model.eye_points.len() {
+                        self.model.recheck_warnings(One(Warning::DuplicateEyePoint(i)));
+                    }
+                }
 
                 if let Some(response) = eye_idx_response {
                     let new_idx = response.get_new_ui_idx(&self.model.eye_points);
@@ -2705,14 +2778,20 @@ impl PofToolsGui {
 
                 ui.label("The visual center is treated as the center for things like the targeting box, or tech room.");
 
-                UiState::model_value_edit(
+                let outside_warning = self.model.warnings.contains(&Warning::VisualCenterOutsideDetail0);
+                if UiState::model_value_edit(
                     "viscenter position",
                     &mut self.ui_state.viewport_3d_dirty,
                     ui,
-                    false,
+                    outside_warning,
                     Some(&mut self.model.visual_center),
                     position,
-                );
+                )
+                .changed()
+                {
+                    self.model.recheck_warnings(One(Warning::AutocenterFlagWithoutVisualCenter));
+                    self.model.recheck_warnings(One(Warning::VisualCenterOutsideDetail0));
+                }
 
                 ui.add_space(5.0);
 
@@ -2721,8 +2800,7 @@ impl PofToolsGui {
                     .on_hover_text("Chooses the average position of its surface area")
                     .clicked()
                 {
-                    let (_, center) = self.model.surface_area_average_pos();
-                    self.model.visual_center = center;
+                    self.model.recalc_visual_center(pof::VisualCenterMethod::SurfaceAreaAverage);
                     self.ui_state.properties_panel_dirty = true;
                 }
             }
@@ -2748,13 +2826,14 @@ impl PofToolsGui {
                 }
             }
 
+            let annotations = self.model.remapped_texture_annotations(&changed_id_map);
             let mut new_map = self.model.texture_map.clone();
             for (id1, id2) in changed_id_map {
                 *(new_map.get_mut(&id1).unwrap()) = id2;
             }
 
             undo_history
-                .apply(&mut self.model, UndoAction::ChangeTextures { id_map: new_map, textures: new_textures })
+                .apply(&mut self.model, UndoAction::ChangeTextures { id_map: new_map, textures: new_textures, annotations })
                 .unwrap();
 
             self.ui_state.properties_panel_dirty = true;